@@ -0,0 +1,871 @@
+// Configurable API client
+
+use crate::compression::{RequestCompression, MIN_COMPRESSED_BYTES};
+use crate::errors::ApiError;
+use crate::relay::{to_relay_request, Relay};
+use crate::request_log::{RequestLog, RequestRecord, REQUEST_ID_HEADER};
+use plantuml_editor_core::diff::line_patch;
+use plantuml_editor_core::{
+    validate_plantuml_content, ActivityEntry, ActivityResponse, AnalyzeRequest, AnalyzeResponse,
+    BatchExportItem,
+    BatchExportRequest, ConvertRequest,
+    ConvertResponse, DiagramAnalysis, Diagnostic, DiagnosticsRequest, DiagnosticsResponse, DiagramUrlImportRequest, DiagramUrlImportResponse, DocumentListResponse, DocumentPatchRequest, DocumentRequest, DocumentResponse, DocumentRole, ErrorCode, Example, ExampleListResponse, ExportScale,
+    Folder, FontListResponse, FolderId, FolderListResponse, FolderRequest, FolderResponse, ImageFormat, JobInfo,
+    JobResponse, MoveDocumentRequest, OpenApiImportRequest, OpenApiImportResponse,
+    OpenApiOperation, PlantUMLDocument, ProcessResult, SearchHit, SearchResponse, SettingsResponse,
+    SettingsSnapshot, StatusLevel,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// API server URL used when API_BASE_URL is not set
+pub const DEFAULT_API_BASE_URL: &str = "http://localhost:8080";
+
+const NO_RESPONSE: &str = "サーバーが応答していません。時間をおいて再度接続を試すか管理者に問い合わせてください。";
+const INVALID_RESPONSE: &str = "レスポンスの解析に失敗しました。";
+
+/// Response of GET /api/v1/health
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct HealthStatus {
+    pub status: String,
+    pub service: String,
+    pub version: String,
+}
+
+/// Response of GET /api/v1/status
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ServiceStatus {
+    /// Diagram conversion is switched off by the operator
+    pub maintenance: bool,
+    /// Notice for users (e.g. planned downtime of the PlantUML server)
+    #[serde(default)]
+    pub announcement: Option<String>,
+}
+
+/// Reusable client for the PlantUML API server
+///
+/// Holds one HTTP connection pool, so embedding applications can build it once
+/// and share it between requests.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    timeout: Option<Duration>,
+    relay: Option<Arc<dyn Relay>>,
+    compression: Option<RequestCompression>,
+    /// Font family sent with conversions and exports
+    font: Option<String>,
+    /// Ask the export endpoints for metadata-free output
+    deterministic: bool,
+    /// Ask the export endpoints to embed the source in the image
+    embed_source: bool,
+    /// Export post-processors to run instead of the server's default
+    pipeline: Option<Vec<String>>,
+    request_log: Option<RequestLog>,
+}
+
+/// Builder for [`ApiClient`]
+#[derive(Debug, Clone)]
+pub struct ApiClientBuilder {
+    base_url: String,
+    timeout: Option<Duration>,
+    headers: HeaderMap,
+    relay: Option<Arc<dyn Relay>>,
+    compression: Option<RequestCompression>,
+    request_log: Option<RequestLog>,
+}
+
+/// Content type and body of a successful response
+struct RawResponse {
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+impl ApiClientBuilder {
+    /// API server URL (e.g., "http://localhost:8080")
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`
+    ///
+    /// Elapsed requests fail with ErrorCode::TimeoutError.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Header sent with every request (e.g., authentication for a proxy)
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>` with every request
+    ///
+    /// For servers validating OIDC access tokens. Tokens expire, so build a
+    /// new client after refreshing one. Relayed requests carry no headers;
+    /// the relay authenticates them itself.
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        match HeaderValue::from_str(&format!("Bearer {}", token)) {
+            Ok(mut value) => {
+                value.set_sensitive(true);
+                self.headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            // Sent without it, the server answers AuthenticationRequired
+            Err(_) => {
+                self.headers.remove(reqwest::header::AUTHORIZATION);
+            }
+        }
+        self
+    }
+
+    /// Hand requests to `relay` instead of sending them over HTTP
+    ///
+    /// URLs still start with the base URL, for the relay to forward or
+    /// rewrite. On native targets the relay must enforce timeouts itself.
+    pub fn relay(mut self, relay: Arc<dyn Relay>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Compress JSON bodies of at least MIN_COMPRESSED_BYTES bytes
+    ///
+    /// Saves upload time on slow connections; the server must accept the
+    /// Content-Encoding (the API server decompresses gzip and Brotli).
+    pub fn compress_requests(mut self, compression: RequestCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Record every request into `log` (see `RequestLog`)
+    ///
+    /// Relayed requests are recorded without a request ID.
+    pub fn request_log(mut self, log: RequestLog) -> Self {
+        self.request_log = Some(log);
+        self
+    }
+
+    pub fn build(self) -> Result<ApiClient, ApiError> {
+        let http = reqwest::Client::builder()
+            .default_headers(self.headers)
+            .build()
+            .map_err(|_| ApiError::NetworkError("HTTPクライアントの初期化に失敗しました。".to_string()))?;
+
+        Ok(ApiClient {
+            http,
+            base_url: self.base_url,
+            timeout: self.timeout,
+            relay: self.relay,
+            compression: self.compression,
+            font: None,
+            deterministic: false,
+            embed_source: false,
+            pipeline: None,
+            request_log: self.request_log,
+        })
+    }
+}
+
+impl Default for ApiClient {
+    /// Client for the server in API_BASE_URL (or localhost:8080)
+    fn default() -> Self {
+        Self::new(
+            env::var("API_BASE_URL").unwrap_or_else(|_| DEFAULT_API_BASE_URL.to_string()),
+        )
+    }
+}
+
+impl ApiClient {
+    /// Client for `base_url` without timeout or extra headers
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            timeout: None,
+            relay: None,
+            compression: None,
+            font: None,
+            deterministic: false,
+            embed_source: false,
+            pipeline: None,
+            request_log: None,
+        }
+    }
+
+    pub fn builder() -> ApiClientBuilder {
+        ApiClientBuilder {
+            base_url: DEFAULT_API_BASE_URL.to_string(),
+            timeout: None,
+            headers: HeaderMap::new(),
+            relay: None,
+            compression: None,
+            request_log: None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Copy of this client with a different timeout, for a single call
+    ///
+    /// The copy shares the connection pool and default headers.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this client that renders with `font` (None for the server default)
+    ///
+    /// The server only accepts fonts listed by [`ApiClient::fonts`].
+    pub fn with_font(&self, font: Option<String>) -> Self {
+        Self {
+            font,
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this client whose exports leave out run-dependent metadata, so
+    /// re-exporting an unchanged diagram gives identical bytes
+    pub fn with_deterministic(&self, deterministic: bool) -> Self {
+        Self {
+            deterministic,
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this client whose PNG and SVG exports carry their source, to be
+    /// recovered with `plantuml_editor_core::embed::extract_source`
+    pub fn with_embed_source(&self, embed_source: bool) -> Self {
+        Self {
+            embed_source,
+            ..self.clone()
+        }
+    }
+
+    /// Copy of this client whose exports run the post-processors `steps` in
+    /// order (None for the server's default, see GET /api/v1/export/pipeline)
+    pub fn with_pipeline(&self, steps: Option<Vec<String>>) -> Self {
+        Self {
+            pipeline: steps,
+            ..self.clone()
+        }
+    }
+
+    /// Convert PlantUML text to image
+    ///
+    /// # Returns
+    /// Binary image data and processing result on success
+    pub async fn convert(
+        &self,
+        plantuml_text: String,
+        format: ImageFormat,
+    ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
+        self.render("/api/v1/convert", plantuml_text, format, ExportScale::X1).await
+    }
+
+    /// Export PlantUML diagram
+    ///
+    /// # Returns
+    /// Binary image data and processing result on success
+    pub async fn export(
+        &self,
+        plantuml_text: String,
+        format: ImageFormat,
+    ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
+        self.export_scaled(plantuml_text, format, ExportScale::X1).await
+    }
+
+    /// Export PlantUML diagram at a resolution preset (e.g. 4x PNG for print)
+    ///
+    /// # Returns
+    /// Binary image data and processing result on success
+    pub async fn export_scaled(
+        &self,
+        plantuml_text: String,
+        format: ImageFormat,
+        scale: ExportScale,
+    ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
+        self.render("/api/v1/export", plantuml_text, format, scale).await
+    }
+
+    /// Check PlantUML text with the same rules as the server, without a round trip
+    pub fn validate(&self, plantuml_text: &str) -> Result<(), ApiError> {
+        validate_plantuml_content(plantuml_text)
+            .map_err(|e| ApiError::ProcessError(e.to_error_code()))
+    }
+
+    /// Check that the API server is up
+    pub async fn health(&self) -> Result<HealthStatus, ApiError> {
+        let request = self.http.get(self.url("/api/v1/health"));
+        self.send(request).await
+    }
+
+    /// Get the maintenance state and the operator's announcement
+    pub async fn status(&self) -> Result<ServiceStatus, ApiError> {
+        let request = self.http.get(self.url("/api/v1/status"));
+        self.send(request).await
+    }
+
+    /// Font families the server allows for rendering (empty when fonts cannot be chosen)
+    pub async fn fonts(&self) -> Result<Vec<String>, ApiError> {
+        let request = self.http.get(self.url("/api/v1/fonts"));
+        let response: FontListResponse = self.send(request).await?;
+        Ok(response.fonts)
+    }
+
+    /// Example diagrams bundled with the server
+    ///
+    /// # Returns
+    /// Categories in display order and the examples
+    pub async fn examples(&self) -> Result<(Vec<String>, Vec<Example>), ApiError> {
+        let request = self.http.get(self.url("/api/v1/examples"));
+        let response: ExampleListResponse = self.send(request).await?;
+        Ok((response.categories, response.examples))
+    }
+
+    /// SVG thumbnail of an example (its `thumbnail_url`)
+    pub async fn example_thumbnail(&self, id: &str) -> Result<Vec<u8>, ApiError> {
+        let request = self
+            .http
+            .get(self.url(&format!("/api/v1/examples/{}/thumbnail", id)));
+        let response = self.send_raw(request).await?;
+
+        // Errors come back as the JSON envelope instead of an SVG
+        let is_json = response
+            .content_type
+            .is_some_and(|value| value.starts_with("application/json"));
+        if is_json {
+            let response: ConvertResponse = decode(&response.body)?;
+            return Err(ApiError::from_process_result(response.result));
+        }
+        Ok(response.body)
+    }
+
+    /// Generate a sequence diagram from an OpenAPI document
+    ///
+    /// # Returns
+    /// Generated PlantUML source, all operations in the document and processing result on success
+    pub async fn import_openapi(
+        &self,
+        spec: String,
+        operations: Vec<String>,
+    ) -> Result<(String, Vec<OpenApiOperation>, ProcessResult), ApiError> {
+        let request = OpenApiImportRequest {
+            spec,
+            operations,
+            list_only: false,
+        };
+        let response: OpenApiImportResponse =
+            self.post("/api/v1/import/openapi", &request).await?;
+
+        match response.plantuml_text {
+            Some(plantuml_text) => Ok((plantuml_text, response.operations, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Operations of an OpenAPI document, without generating a diagram
+    ///
+    /// Lets the caller offer a subset when the diagram for all of them cannot
+    /// be generated (e.g. it would be too large).
+    pub async fn list_openapi_operations(&self, spec: String) -> Result<Vec<OpenApiOperation>, ApiError> {
+        let request = OpenApiImportRequest {
+            spec,
+            operations: Vec::new(),
+            list_only: true,
+        };
+        let response: OpenApiImportResponse =
+            self.post("/api/v1/import/openapi", &request).await?;
+
+        match response.result.code {
+            ErrorCode::ImportOk => Ok(response.operations),
+            _ => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Source of a diagram from its PlantUML server image URL
+    pub async fn import_diagram_url(&self, url: String) -> Result<(String, ProcessResult), ApiError> {
+        let request = DiagramUrlImportRequest { url };
+        let response: DiagramUrlImportResponse = self.post("/api/v1/import/url", &request).await?;
+
+        match response.plantuml_text {
+            Some(plantuml_text) => Ok((plantuml_text, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Problems in PlantUML text, with LSP-style positions
+    ///
+    /// With `check_syntax` the server also asks PlantUML for syntax errors.
+    pub async fn diagnostics(
+        &self,
+        plantuml_text: String,
+        check_syntax: bool,
+    ) -> Result<Vec<Diagnostic>, ApiError> {
+        let request = DiagnosticsRequest {
+            plantuml_text,
+            check_syntax,
+        };
+        let response: DiagnosticsResponse = self.post("/api/v1/diagnostics", &request).await?;
+        Ok(response.diagnostics)
+    }
+
+    /// Structure statistics of PlantUML text (diagram type, counts, complexity)
+    pub async fn analyze(&self, plantuml_text: String) -> Result<DiagramAnalysis, ApiError> {
+        let request = AnalyzeRequest { plantuml_text };
+        let response: AnalyzeResponse = self.post("/api/v1/analyze", &request).await?;
+        response
+            .analysis
+            .ok_or_else(|| ApiError::from_process_result(response.result))
+    }
+
+    /// Queue a background export job
+    ///
+    /// # Returns
+    /// Job status and processing result on success
+    pub async fn start_export_job(
+        &self,
+        items: Vec<BatchExportItem>,
+        format: ImageFormat,
+    ) -> Result<(JobInfo, ProcessResult), ApiError> {
+        let request = BatchExportRequest { format, items };
+        let response: JobResponse = self.post("/api/v1/jobs/export", &request).await?;
+        job_result(response)
+    }
+
+    /// Get the status of a background job
+    pub async fn get_job(&self, job_id: &str) -> Result<(JobInfo, ProcessResult), ApiError> {
+        let request = self.http.get(self.url(&format!("/api/v1/jobs/{}", job_id)));
+        let response: JobResponse = self.send(request).await?;
+        job_result(response)
+    }
+
+    /// Get a server-side document and the caller's role on it
+    ///
+    /// The role decides whether the document may be edited; the server
+    /// rejects changes from viewers either way.
+    pub async fn get_document(
+        &self,
+        id: &str,
+    ) -> Result<(PlantUMLDocument, DocumentRole), ApiError> {
+        let request = self.http.get(self.url(&format!("/api/v1/documents/{}", id)));
+        let response: DocumentResponse = self.send(request).await?;
+
+        match response.document {
+            Some(document) => Ok((document, response.role.unwrap_or(DocumentRole::Viewer))),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Replace a server-side document edited from `base_version`
+    ///
+    /// Fails with DocumentConflict when someone else saved the document after
+    /// that version was loaded.
+    pub async fn update_document(
+        &self,
+        id: &str,
+        content: String,
+        title: Option<String>,
+        base_version: u64,
+    ) -> Result<(PlantUMLDocument, ProcessResult), ApiError> {
+        let request = self.json(
+            self.http.put(self.url(&format!("/api/v1/documents/{}", id))),
+            &DocumentRequest {
+                content,
+                title,
+                base_version: Some(base_version),
+            },
+        )?;
+        let response: DocumentResponse = self.send(request).await?;
+
+        match response.document {
+            Some(document) => Ok((document, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Save an edit of a server-side document as the lines changed since `base`
+    ///
+    /// Sends a `diff::line_patch` instead of the whole text; the server
+    /// answers DocumentConflict when `base` is no longer its latest version.
+    pub async fn patch_document(
+        &self,
+        base: &PlantUMLDocument,
+        content: &str,
+        title: Option<String>,
+    ) -> Result<(PlantUMLDocument, ProcessResult), ApiError> {
+        let request = self.json(
+            self.http
+                .patch(self.url(&format!("/api/v1/documents/{}", base.id.0))),
+            &DocumentPatchRequest {
+                patch: line_patch(&base.content, content),
+                title,
+                base_version: base.version,
+            },
+        )?;
+        let response: DocumentResponse = self.send(request).await?;
+
+        match response.document {
+            Some(document) => Ok((document, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// ZIP of a server-side document with its history, for importing elsewhere
+    pub async fn export_document_archive(&self, id: &str) -> Result<Vec<u8>, ApiError> {
+        let request = self
+            .http
+            .get(self.url(&format!("/api/v1/documents/{}/archive", id)));
+        let response = self.send_raw(request).await?;
+
+        // Errors come back as the JSON envelope instead of a ZIP
+        let is_json = response
+            .content_type
+            .is_some_and(|value| value.starts_with("application/json"));
+        if is_json {
+            let response: DocumentResponse = decode(&response.body)?;
+            return Err(ApiError::from_process_result(response.result));
+        }
+        Ok(response.body)
+    }
+
+    /// Store a document from an archive made by `export_document_archive`
+    pub async fn import_document_archive(
+        &self,
+        archive: Vec<u8>,
+    ) -> Result<(PlantUMLDocument, ProcessResult), ApiError> {
+        let request = self
+            .http
+            .post(self.url("/api/v1/documents/import"))
+            .header(reqwest::header::CONTENT_TYPE, "application/zip")
+            .body(archive);
+        let response: DocumentResponse = self.send(request).await?;
+
+        match response.document {
+            Some(document) => Ok((document, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Server-side documents visible to the caller, most recently updated first
+    pub async fn list_documents(&self) -> Result<Vec<PlantUMLDocument>, ApiError> {
+        let request = self.http.get(self.url("/api/v1/documents"));
+        let response: DocumentListResponse = self.send(request).await?;
+        Ok(response.documents)
+    }
+
+    /// Recent changes to the server-side documents visible to the caller, newest first
+    pub async fn activity(&self, limit: usize) -> Result<Vec<ActivityEntry>, ApiError> {
+        let request = self
+            .http
+            .get(self.url("/api/v1/activity"))
+            .query(&[("limit", limit)]);
+        let response: ActivityResponse = self.send(request).await?;
+        Ok(response.entries)
+    }
+
+    /// Full-text search over the server-side documents visible to the caller
+    pub async fn search_documents(&self, query: &str) -> Result<Vec<SearchHit>, ApiError> {
+        let request = self
+            .http
+            .get(self.url("/api/v1/documents/search"))
+            .query(&[("q", query)]);
+        let response: SearchResponse = self.send(request).await?;
+        Ok(response.hits)
+    }
+
+    /// Move a server-side document into a folder (or to the top level with None)
+    pub async fn move_document(
+        &self,
+        id: &str,
+        folder: Option<FolderId>,
+    ) -> Result<(PlantUMLDocument, ProcessResult), ApiError> {
+        let request = self.json(
+            self.http.put(self.url(&format!("/api/v1/documents/{}/folder", id))),
+            &MoveDocumentRequest { folder },
+        )?;
+        let response: DocumentResponse = self.send(request).await?;
+
+        match response.document {
+            Some(document) => Ok((document, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// All server-side folders, sorted by name
+    pub async fn list_folders(&self) -> Result<Vec<Folder>, ApiError> {
+        let request = self.http.get(self.url("/api/v1/folders"));
+        let response: FolderListResponse = self.send(request).await?;
+        Ok(response.folders)
+    }
+
+    /// Create a server-side folder inside `parent` (or at the top level)
+    pub async fn create_folder(
+        &self,
+        name: String,
+        parent: Option<FolderId>,
+    ) -> Result<(Folder, ProcessResult), ApiError> {
+        let request = FolderRequest { name, parent };
+        let response: FolderResponse = self.post("/api/v1/folders", &request).await?;
+
+        match response.folder {
+            Some(folder) => Ok((folder, response.result)),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// Editor settings saved for the caller (None before they saved any)
+    pub async fn settings(&self) -> Result<Option<SettingsSnapshot>, ApiError> {
+        let request = self.http.get(self.url("/api/v1/settings"));
+        let response: SettingsResponse = self.send(request).await?;
+        if response.result.level != StatusLevel::Info {
+            return Err(ApiError::from_process_result(response.result));
+        }
+        Ok(response.settings)
+    }
+
+    /// Save the caller's editor settings
+    ///
+    /// Returns the settings in effect, which are the server's when they were
+    /// changed after `snapshot` (last write wins).
+    pub async fn save_settings(&self, snapshot: &SettingsSnapshot) -> Result<SettingsSnapshot, ApiError> {
+        let request = self.json(self.http.put(self.url("/api/v1/settings")), snapshot)?;
+        let response: SettingsResponse = self.send(request).await?;
+        match response.settings {
+            Some(settings) => Ok(settings),
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    /// URL of the file download endpoint (GET with query or POST with form fields)
+    pub fn export_download_url(&self) -> String {
+        self.url("/api/v1/export/download")
+    }
+
+    /// URL of the server-sent progress events for a job
+    pub fn job_events_url(&self, job_id: &str) -> String {
+        self.url(&format!("/api/v1/jobs/{}/events", job_id))
+    }
+
+    /// URL of the ZIP archive produced by a completed job
+    pub fn job_result_url(&self, job_id: &str) -> String {
+        self.url(&format!("/api/v1/jobs/{}/result", job_id))
+    }
+
+    async fn render(
+        &self,
+        path: &str,
+        plantuml_text: String,
+        format: ImageFormat,
+        scale: ExportScale,
+    ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
+        let request = ConvertRequest {
+            plantuml_text,
+            format,
+            scale,
+            font: self.font.clone(),
+            deterministic: self.deterministic,
+            embed_source: self.embed_source,
+            pipeline: self.pipeline.clone(),
+        };
+        let response: ConvertResponse = self.post(path, &request).await?;
+
+        match response.image_data {
+            Some(image_data) => Ok((image_data, response.result)),
+            // Server returned an error result
+            None => Err(ApiError::from_process_result(response.result)),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, ApiError> {
+        let request = self.json(self.http.post(self.url(path)), body)?;
+        self.send(request).await
+    }
+
+    /// `request` with `body` as JSON, compressed when the client is set up to
+    fn json<B: Serialize>(
+        &self,
+        request: reqwest::RequestBuilder,
+        body: &B,
+    ) -> Result<reqwest::RequestBuilder, ApiError> {
+        let invalid = |_| ApiError::NetworkError("リクエストの作成に失敗しました。".to_string());
+        let json = serde_json::to_vec(body).map_err(invalid)?;
+        let request = request.header(reqwest::header::CONTENT_TYPE, "application/json");
+        match self.compression {
+            Some(compression) if json.len() >= MIN_COMPRESSED_BYTES => {
+                let compressed = compression.compress(&json).map_err(|_| {
+                    ApiError::NetworkError("リクエストの圧縮に失敗しました。".to_string())
+                })?;
+                Ok(request
+                    .header(reqwest::header::CONTENT_ENCODING, compression.content_encoding())
+                    .body(compressed))
+            }
+            _ => Ok(request.body(json)),
+        }
+    }
+
+    /// Send a request and decode the JSON envelope
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, ApiError> {
+        decode(&self.send_raw(request).await?.body)
+    }
+
+    /// Send a request, failing on network errors and HTTP error statuses
+    async fn send_raw(&self, request: reqwest::RequestBuilder) -> Result<RawResponse, ApiError> {
+        if let Some(relay) = &self.relay {
+            return self.send_relayed(relay.as_ref(), request).await;
+        }
+
+        let response = match self.timeout {
+            Some(timeout) => send_with_timeout(request, timeout).await?,
+            None => request
+                .send()
+                .await
+                .map_err(|_| ApiError::NetworkError(NO_RESPONSE.to_string()))?,
+        };
+        if let Some(log) = &self.request_log {
+            log.record(RequestRecord {
+                path: response.url().path().to_string(),
+                status: response.status().as_u16(),
+                request_id: response
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+            });
+        }
+
+        if !response.status().is_success() {
+            // HTTP error (should not happen with new API design, but keep for safety)
+            return Err(ApiError::ServerError(format!("HTTPエラー: {}", response.status())));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response
+            .bytes()
+            .await
+            .map_err(|_| ApiError::NetworkError(INVALID_RESPONSE.to_string()))?;
+        Ok(RawResponse {
+            content_type,
+            body: body.to_vec(),
+        })
+    }
+
+    async fn send_relayed(
+        &self,
+        relay: &dyn Relay,
+        request: reqwest::RequestBuilder,
+    ) -> Result<RawResponse, ApiError> {
+        let request = request
+            .build()
+            .map_err(|_| ApiError::NetworkError(NO_RESPONSE.to_string()))?;
+        let path = request.url().path().to_string();
+        let sent = relay.send(to_relay_request(request));
+        let response = match self.timeout {
+            #[cfg(target_arch = "wasm32")]
+            Some(timeout) => relay_with_timeout(sent, timeout).await?,
+            _ => sent.await?,
+        };
+        if let Some(log) = &self.request_log {
+            log.record(RequestRecord {
+                path,
+                status: response.status,
+                request_id: None,
+            });
+        }
+
+        if !(200..300).contains(&response.status) {
+            return Err(ApiError::ServerError(format!("HTTPエラー: {}", response.status)));
+        }
+        Ok(RawResponse {
+            content_type: response.content_type,
+            body: response.body,
+        })
+    }
+}
+
+fn decode<T: DeserializeOwned>(body: &[u8]) -> Result<T, ApiError> {
+    serde_json::from_slice(body).map_err(|_| ApiError::NetworkError(INVALID_RESPONSE.to_string()))
+}
+
+fn job_result(response: JobResponse) -> Result<(JobInfo, ProcessResult), ApiError> {
+    match response.job {
+        Some(job) => Ok((job, response.result)),
+        None => Err(ApiError::from_process_result(response.result)),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn send_with_timeout(
+    request: reqwest::RequestBuilder,
+    timeout: Duration,
+) -> Result<reqwest::Response, ApiError> {
+    request.timeout(timeout).send().await.map_err(|e| {
+        if e.is_timeout() {
+            timeout_error(timeout)
+        } else {
+            ApiError::NetworkError(NO_RESPONSE.to_string())
+        }
+    })
+}
+
+/// The browser fetch backend has no timeout option, so race it against a timer
+#[cfg(target_arch = "wasm32")]
+async fn send_with_timeout(
+    request: reqwest::RequestBuilder,
+    timeout: Duration,
+) -> Result<reqwest::Response, ApiError> {
+    use futures::future::{select, Either};
+
+    let send = Box::pin(request.send());
+    let timer = Box::pin(gloo_timers::future::sleep(timeout));
+    match select(send, timer).await {
+        Either::Left((result, _)) => {
+            result.map_err(|_| ApiError::NetworkError(NO_RESPONSE.to_string()))
+        }
+        Either::Right(_) => Err(timeout_error(timeout)),
+    }
+}
+
+/// Relayed requests are raced against a timer like browser fetches
+#[cfg(target_arch = "wasm32")]
+async fn relay_with_timeout(
+    sent: crate::relay::RelayFuture,
+    timeout: Duration,
+) -> Result<crate::relay::RelayResponse, ApiError> {
+    use futures::future::{select, Either};
+
+    let timer = Box::pin(gloo_timers::future::sleep(timeout));
+    match select(sent, timer).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(timeout_error(timeout)),
+    }
+}
+
+fn timeout_error(timeout: Duration) -> ApiError {
+    ApiError::ProcessError(ErrorCode::TimeoutError {
+        duration_ms: timeout.as_millis() as u64,
+    })
+}
@@ -18,6 +18,16 @@ impl ApiError {
     pub fn from_process_result(result: ProcessResult) -> Self {
         ApiError::ProcessError(result.code)
     }
+
+    /// Whether sending the request again later may succeed: no response from
+    /// the server, or a transient error code
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::NetworkError(_) => true,
+            ApiError::ServerError(_) => false,
+            ApiError::ProcessError(code) => code.is_transient(),
+        }
+    }
 }
 
 impl std::fmt::Display for ApiError {
@@ -74,4 +84,12 @@ mod tests {
             "処理エラー: PlantUMLソースを入力してください"
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_api_error_is_transient() {
+        assert!(ApiError::NetworkError("応答がありません".to_string()).is_transient());
+        assert!(ApiError::ProcessError(ErrorCode::Maintenance).is_transient());
+        assert!(!ApiError::ProcessError(ErrorCode::ValidationEmpty).is_transient());
+        assert!(!ApiError::ServerError("HTTPエラー: 500".to_string()).is_transient());
+    }
+}
@@ -1,14 +1,14 @@
 // HTTP client for PlantUML API
+//
+// Convenience wrappers around a default ApiClient (API_BASE_URL). Embedding
+// applications that need a custom base URL, timeout or headers should build
+// their own ApiClient instead.
 
+use crate::client::ApiClient;
 use crate::errors::ApiError;
-use plantuml_editor_core::{ConvertRequest, ConvertResponse, ImageFormat, ProcessResult};
-use std::env;
-
-fn get_api_base_url() -> String {
-    env::var("API_BASE_URL")
-        .unwrap_or_else(|_| "http://localhost:8080".to_string())
-}
-
+use plantuml_editor_core::{
+    BatchExportItem, ImageFormat, JobInfo, OpenApiOperation, ProcessResult,
+};
 
 /// Convert PlantUML text to image via API server
 ///
@@ -22,39 +22,7 @@ pub async fn convert_plantuml(
     plantuml_text: String,
     format: ImageFormat,
 ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
-    let request = ConvertRequest {
-        plantuml_text,
-        format,
-    };
-    
-    let client = reqwest::Client::new();
-    let api_base_url = get_api_base_url();
-    let response = client
-        .post(format!("{}/api/v1/convert", api_base_url))
-        .json(&request)
-        .send()
-        .await
-        .map_err(|_| ApiError::NetworkError("サーバーが応答していません。時間をおいて再度接続を試すか管理者に問い合わせてください。".to_string()))?;
-    
-    if response.status().is_success() {
-        let convert_response: ConvertResponse = response
-            .json()
-            .await
-            .map_err(|_| ApiError::NetworkError("レスポンスの解析に失敗しました。".to_string()))?;
-        
-        // Check if conversion succeeded
-        if let Some(image_data) = convert_response.image_data {
-            Ok((image_data, convert_response.result))
-        } else {
-            // Server returned an error result
-            Err(ApiError::from_process_result(convert_response.result))
-        }
-    } else {
-        // HTTP error (should not happen with new API design, but keep for safety)
-        Err(ApiError::ServerError(
-            format!("HTTPエラー: {}", response.status())
-        ))
-    }
+    ApiClient::default().convert(plantuml_text, format).await
 }
 
 /// Export PlantUML diagram via API server
@@ -69,34 +37,73 @@ pub async fn export_plantuml(
     plantuml_text: String,
     format: ImageFormat,
 ) -> Result<(Vec<u8>, ProcessResult), ApiError> {
-    let request = ConvertRequest {
-        plantuml_text,
-        format,
-    };
-    
-    let client = reqwest::Client::new();
-    let api_base_url = get_api_base_url();
-    let response = client
-        .post(format!("{}/api/v1/export", api_base_url))
-        .json(&request)
-        .send()
-        .await
-        .map_err(|_| ApiError::NetworkError("サーバーが応答していません。時間をおいて再度接続を試すか管理者に問い合わせてください。".to_string()))?;
-    
-    if response.status().is_success() {
-        let convert_response: ConvertResponse = response
-            .json()
-            .await
-            .map_err(|_| ApiError::NetworkError("レスポンスの解析に失敗しました。".to_string()))?;
-        
-        if let Some(image_data) = convert_response.image_data {
-            Ok((image_data, convert_response.result))
-        } else {
-            Err(ApiError::from_process_result(convert_response.result))
-        }
-    } else {
-        Err(ApiError::ServerError(
-            format!("HTTPエラー: {}", response.status())
-        ))
-    }
+    ApiClient::default().export(plantuml_text, format).await
+}
+
+/// Generate a sequence diagram from an OpenAPI document via API server
+///
+/// # Arguments
+/// * `spec` - OpenAPI document (JSON or YAML)
+/// * `operations` - Operation IDs to include (empty means all)
+///
+/// # Returns
+/// Generated PlantUML source, all operations in the document and processing result on success
+pub async fn import_openapi(
+    spec: String,
+    operations: Vec<String>,
+) -> Result<(String, Vec<OpenApiOperation>, ProcessResult), ApiError> {
+    ApiClient::default().import_openapi(spec, operations).await
+}
+
+/// List the operations of an OpenAPI document via API server
+///
+/// # Arguments
+/// * `spec` - OpenAPI document (JSON or YAML)
+///
+/// # Returns
+/// All operations in the document, also when no diagram could be generated for them
+pub async fn list_openapi_operations(spec: String) -> Result<Vec<OpenApiOperation>, ApiError> {
+    ApiClient::default().list_openapi_operations(spec).await
+}
+
+/// Queue a background export job via API server
+///
+/// # Arguments
+/// * `items` - Sources to render (file name and PlantUML text)
+/// * `format` - Output image format (PNG or SVG)
+///
+/// # Returns
+/// Job status and processing result on success. Progress is streamed from
+/// [`job_events_url`] and the ZIP is downloaded from [`job_result_url`].
+pub async fn start_export_job(
+    items: Vec<BatchExportItem>,
+    format: ImageFormat,
+) -> Result<(JobInfo, ProcessResult), ApiError> {
+    ApiClient::default().start_export_job(items, format).await
+}
+
+/// Get the status of a background job via API server
+///
+/// # Arguments
+/// * `job_id` - Job ID returned by [`start_export_job`]
+///
+/// # Returns
+/// Job status and processing result on success
+pub async fn get_job(job_id: &str) -> Result<(JobInfo, ProcessResult), ApiError> {
+    ApiClient::default().get_job(job_id).await
+}
+
+/// URL of the file download endpoint (GET with query or POST with form fields)
+pub fn export_download_url() -> String {
+    ApiClient::default().export_download_url()
+}
+
+/// URL of the server-sent progress events for a job
+pub fn job_events_url(job_id: &str) -> String {
+    ApiClient::default().job_events_url(job_id)
+}
+
+/// URL of the ZIP archive produced by a completed job
+pub fn job_result_url(job_id: &str) -> String {
+    ApiClient::default().job_result_url(job_id)
 }
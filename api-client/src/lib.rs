@@ -3,9 +3,20 @@
 // This crate provides HTTP client functionality for communicating
 // with the PlantUML API server from the browser-based frontend.
 
+pub mod client;
+pub mod compression;
 pub mod errors;
 pub mod http_client;
+pub mod relay;
+pub mod request_log;
 
 // Re-export commonly used items
+pub use client::{ApiClient, ApiClientBuilder, HealthStatus, ServiceStatus};
+pub use compression::RequestCompression;
 pub use errors::ApiError;
-pub use http_client::{convert_plantuml, export_plantuml};
+pub use relay::{Relay, RelayFuture, RelayRequest, RelayResponse};
+pub use request_log::{RequestLog, RequestRecord};
+pub use http_client::{
+    convert_plantuml, export_download_url, export_plantuml, get_job, import_openapi,
+    job_events_url, job_result_url, list_openapi_operations, start_export_job,
+};
@@ -0,0 +1,134 @@
+// Requests relayed through the embedding application
+//
+// Some hosts do not let the frontend reach the API server itself; a VS Code
+// webview, for example, has to ask its extension to make the request. An
+// ApiClient built with a relay hands each request over as plain data and
+// decodes the answer exactly as it would an HTTP response.
+
+use crate::errors::ApiError;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+
+/// Request for the relay to send to the API server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayRequest {
+    /// HTTP method (e.g., "GET", "POST")
+    pub method: String,
+    /// Absolute URL, including the query string
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Compression of the body (e.g., "gzip"), to forward as Content-Encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub body: Vec<u8>,
+}
+
+/// The API server's answer to a relayed request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub body: Vec<u8>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type RelayFuture = Pin<Box<dyn Future<Output = Result<RelayResponse, ApiError>> + Send>>;
+
+/// The browser is single-threaded, so relays there may hold JavaScript values
+#[cfg(target_arch = "wasm32")]
+pub type RelayFuture = Pin<Box<dyn Future<Output = Result<RelayResponse, ApiError>>>>;
+
+/// Sends requests on the client's behalf
+///
+/// Failing to reach the server is reported as `ApiError::NetworkError`; HTTP
+/// error statuses are returned as responses like any other.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait Relay: Send + Sync {
+    fn send(&self, request: RelayRequest) -> RelayFuture;
+}
+
+/// Sends requests on the client's behalf
+///
+/// Failing to reach the server is reported as `ApiError::NetworkError`; HTTP
+/// error statuses are returned as responses like any other.
+#[cfg(target_arch = "wasm32")]
+pub trait Relay {
+    fn send(&self, request: RelayRequest) -> RelayFuture;
+}
+
+impl std::fmt::Debug for dyn Relay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Relay")
+    }
+}
+
+/// Relay request for a built reqwest request
+pub(crate) fn to_relay_request(request: reqwest::Request) -> RelayRequest {
+    let header = |name| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header(reqwest::header::CONTENT_TYPE);
+    let content_encoding = header(reqwest::header::CONTENT_ENCODING);
+    let body = request
+        .body()
+        .and_then(|body| body.as_bytes())
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default();
+
+    RelayRequest {
+        method: request.method().to_string(),
+        url: request.url().to_string(),
+        content_type,
+        content_encoding,
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_request_from_reqwest() {
+        let request = reqwest::Client::new()
+            .post("http://localhost:8080/api/v1/activity")
+            .query(&[("limit", 5)])
+            .json(&serde_json::json!({ "a": 1 }))
+            .build()
+            .unwrap();
+
+        let relayed = to_relay_request(request);
+        assert_eq!(relayed.method, "POST");
+        assert_eq!(relayed.url, "http://localhost:8080/api/v1/activity?limit=5");
+        assert_eq!(relayed.content_type.as_deref(), Some("application/json"));
+        assert_eq!(relayed.body, br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn test_relay_messages_omit_empty_fields() {
+        let request = RelayRequest {
+            method: "GET".to_string(),
+            url: "http://localhost:8080/api/v1/health".to_string(),
+            content_type: None,
+            content_encoding: None,
+            body: Vec::new(),
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({ "method": "GET", "url": "http://localhost:8080/api/v1/health" })
+        );
+
+        let response: RelayResponse = serde_json::from_str(r#"{"status":204}"#).unwrap();
+        assert_eq!(response.content_type, None);
+        assert!(response.body.is_empty());
+    }
+}
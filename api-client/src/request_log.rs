@@ -0,0 +1,79 @@
+// Log of the most recent API requests
+//
+// Clients built with the same log record their requests into it, so an
+// application that builds a client per request still has one history. Bug
+// reports include it: the request IDs the server returned (X-Request-Id) find
+// the matching entries in the server logs.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Header carrying the ID the server gave a request
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Requests kept in a log; older ones are dropped
+pub const REQUEST_LOG_LIMIT: usize = 20;
+
+/// One request sent by a client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RequestRecord {
+    /// Path of the request URL (e.g. "/api/v1/convert")
+    pub path: String,
+    /// HTTP status of the response
+    pub status: u16,
+    /// X-Request-Id of the response, when the server sent one
+    pub request_id: Option<String>,
+}
+
+/// Shared log of recent requests, newest last
+#[derive(Debug, Clone, Default)]
+pub struct RequestLog {
+    records: Arc<Mutex<VecDeque<RequestRecord>>>,
+}
+
+impl RequestLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, record: RequestRecord) {
+        let Ok(mut records) = self.records.lock() else {
+            return;
+        };
+        if records.len() == REQUEST_LOG_LIMIT {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Recorded requests, oldest first
+    pub fn records(&self) -> Vec<RequestRecord> {
+        self.records
+            .lock()
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_log_keeps_the_latest() {
+        let log = RequestLog::new();
+        let shared = log.clone();
+        for status in 0..(REQUEST_LOG_LIMIT as u16 + 5) {
+            shared.record(RequestRecord {
+                path: "/api/v1/convert".to_string(),
+                status,
+                request_id: None,
+            });
+        }
+        let records = log.records();
+        assert_eq!(records.len(), REQUEST_LOG_LIMIT);
+        assert_eq!(records[0].status, 5);
+        assert_eq!(records.last().map(|record| record.status), Some(REQUEST_LOG_LIMIT as u16 + 4));
+    }
+}
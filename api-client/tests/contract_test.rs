@@ -0,0 +1,43 @@
+// API contract tests (deserialization side)
+//
+// Every fixture shared with the api-server suite must be read back by the
+// client as the same result.
+
+use plantuml_editor_api_client::{ApiClient, ApiError};
+use plantuml_editor_contract_tests::{convert_response_cases, SAMPLE_IMAGE};
+use plantuml_editor_core::ImageFormat;
+use serial_test::serial;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+#[serial]
+async fn test_convert_responses_match_fixtures() {
+    // localhostへのアクセスにプロキシを使わない
+    std::env::set_var("NO_PROXY", "localhost,127.0.0.1");
+    
+    let mock_server = MockServer::start().await;
+    let client = ApiClient::new(mock_server.uri());
+    
+    for case in convert_response_cases() {
+        mock_server.reset().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1/convert"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&case.json))
+            .mount(&mock_server)
+            .await;
+        
+        let result = client.convert("@startuml\n@enduml".to_string(), ImageFormat::Svg).await;
+        
+        match (result, case.response.image_data) {
+            (Ok((image, result)), Some(_)) => {
+                assert_eq!(image, SAMPLE_IMAGE.to_vec(), "{}", case.name);
+                assert_eq!(result, case.response.result, "{}", case.name);
+            }
+            (Err(ApiError::ProcessError(code)), None) => {
+                assert_eq!(code, case.response.result.code, "{}", case.name);
+            }
+            (other, _) => panic!("{} was read as {:?}", case.name, other),
+        }
+    }
+}
@@ -1,9 +1,19 @@
-use plantuml_editor_api_client::{convert_plantuml, export_plantuml};
-use plantuml_editor_core::{ErrorCode, ImageFormat, StatusLevel};
+use plantuml_editor_api_client::{
+    convert_plantuml, export_plantuml, get_job, import_openapi, job_events_url,
+    list_openapi_operations, start_export_job,
+    ApiClient, ApiError, Relay, RelayFuture, RelayRequest, RelayResponse, RequestCompression,
+    RequestLog,
+};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use plantuml_editor_core::{
+    BatchExportItem, ErrorCode, ImageFormat, JobState, SettingsSnapshot, StatusLevel, UserSettings,
+};
 use serde_json::json;
 use serial_test::serial;
 use wiremock::{MockServer, Mock, ResponseTemplate};
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{body_partial_json, header, method, path};
 
 // ========================================
 // テスト用ヘルパー関数
@@ -315,3 +325,803 @@ async fn test_export_plantuml_parse_error() {
         panic!("Expected ProcessError");
     }
 }
+
+// ========================================
+// import_openapi のテスト
+// ========================================
+
+#[tokio::test]
+#[serial]
+async fn test_import_openapi_success() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "INFO",
+            "code": {
+                "type": "ImportOk"
+            }
+        },
+        "operations": [
+            { "id": "getUser", "method": "GET", "path": "/users/{id}" }
+        ],
+        "plantuml_text": "@startuml\nClient -> Server\n@enduml"
+    });
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/import/openapi"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let result = import_openapi("openapi: 3.0.0".to_string(), vec![]).await;
+    
+    assert!(result.is_ok(), "Expected Ok but got: {:?}", result);
+    let (plantuml_text, operations, process_result) = result.unwrap();
+    
+    assert!(plantuml_text.starts_with("@startuml"));
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].id, "getUser");
+    assert!(operations[0].summary.is_none());
+    assert!(matches!(process_result.code, ErrorCode::ImportOk));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_import_openapi_invalid_document() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "WARNING",
+            "code": {
+                "type": "ImportError",
+                "reason": "OpenAPIとして解析できませんでした"
+            }
+        },
+        "operations": []
+    });
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/import/openapi"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let result = import_openapi("not a spec".to_string(), vec![]).await;
+    
+    if let Err(plantuml_editor_api_client::ApiError::ProcessError(error_code)) = result {
+        assert_eq!(error_code.status_level(), StatusLevel::Warning);
+        assert!(matches!(error_code, ErrorCode::ImportError { .. }));
+    } else {
+        panic!("Expected ProcessError");
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_list_openapi_operations() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "INFO",
+            "code": {
+                "type": "ImportOk"
+            }
+        },
+        "operations": [
+            { "id": "getUser", "method": "GET", "path": "/users/{id}" },
+            { "id": "POST /users", "method": "POST", "path": "/users", "summary": "登録" }
+        ]
+    });
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/import/openapi"))
+        .and(body_partial_json(json!({ "list_only": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let operations = list_openapi_operations("openapi: 3.0.0".to_string()).await.unwrap();
+    
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[1].summary.as_deref(), Some("登録"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_import_diagram_url() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/v1/import/url"))
+        .and(body_partial_json(json!({ "url": "https://www.plantuml.com/plantuml/png/SyfFKj2rKt3CoKnELR1Io4ZDoSa70000" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "ImportOk" } },
+            "plantuml_text": "@startuml\nBob -> Alice : hello\n@enduml"
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ApiClient::new(mock_server.uri());
+    let (plantuml_text, result) = client
+        .import_diagram_url("https://www.plantuml.com/plantuml/png/SyfFKj2rKt3CoKnELR1Io4ZDoSa70000".to_string())
+        .await
+        .unwrap();
+    assert_eq!(plantuml_text, "@startuml\nBob -> Alice : hello\n@enduml");
+    assert!(matches!(result.code, ErrorCode::ImportOk));
+}
+
+// ========================================
+// ジョブAPIのテスト
+// ========================================
+
+#[tokio::test]
+#[serial]
+async fn test_start_export_job_success() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "INFO",
+            "code": {
+                "type": "JobAccepted",
+                "total": 1
+            }
+        },
+        "job": {
+            "id": "job-1",
+            "state": "queued",
+            "total": 1,
+            "succeeded": 0,
+            "failed": 0,
+            "created_at": 1700000000
+        }
+    });
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/jobs/export"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let items = vec![BatchExportItem {
+        name: "a.puml".to_string(),
+        plantuml_text: "@startuml\nA -> B\n@enduml".to_string(),
+    }];
+    let result = start_export_job(items, ImageFormat::Png).await;
+    
+    let (job, process_result) = result.expect("Expected Ok");
+    assert_eq!(job.id, "job-1");
+    assert_eq!(job.state, JobState::Queued);
+    assert!(matches!(process_result.code, ErrorCode::JobAccepted { total: 1 }));
+    assert_eq!(
+        job_events_url(&job.id),
+        format!("{}/api/v1/jobs/job-1/events", mock_server.uri())
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_start_export_job_queue_full() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "WARNING",
+            "code": {
+                "type": "JobQueueFull",
+                "capacity": 32
+            }
+        }
+    });
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/jobs/export"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let result = start_export_job(Vec::new(), ImageFormat::Svg).await;
+    
+    if let Err(plantuml_editor_api_client::ApiError::ProcessError(error_code)) = result {
+        assert_eq!(error_code, ErrorCode::JobQueueFull { capacity: 32 });
+        assert_eq!(error_code.status_level(), StatusLevel::Warning);
+    } else {
+        panic!("Expected ProcessError");
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_get_job_not_found() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    std::env::set_var("API_BASE_URL", mock_server.uri());
+    
+    let mock_response = json!({
+        "result": {
+            "level": "WARNING",
+            "code": {
+                "type": "JobNotFound",
+                "id": "missing"
+            }
+        }
+    });
+    
+    Mock::given(method("GET"))
+        .and(path("/api/v1/jobs/missing"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&mock_response))
+        .mount(&mock_server)
+        .await;
+    
+    let result = get_job("missing").await;
+    
+    assert!(matches!(
+        result,
+        Err(plantuml_editor_api_client::ApiError::ProcessError(ErrorCode::JobNotFound { .. }))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_export_document_archive() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("GET"))
+        .and(path("/api/v1/documents/abc/archive"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/zip")
+                .set_body_bytes(b"PK\x03\x04".to_vec()),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/documents/missing/archive"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": {
+                "level": "WARNING",
+                "code": { "type": "DocumentNotFound", "id": "missing" }
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::new(mock_server.uri());
+    let archive = client.export_document_archive("abc").await.unwrap();
+    assert_eq!(archive, b"PK\x03\x04");
+    
+    // エラーはZIPではなくJSONで返る
+    let result = client.export_document_archive("missing").await;
+    assert!(matches!(
+        result,
+        Err(ApiError::ProcessError(ErrorCode::DocumentNotFound { .. }))
+    ));
+}
+
+// ========================================
+// ApiClient のテスト
+// ========================================
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_sends_default_headers() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("GET"))
+        .and(path("/api/v1/health"))
+        .and(header("x-api-key", "secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "healthy",
+            "service": "plantuml-editor-api",
+            "version": "0.1.0"
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    // API_BASE_URL は使わず、ビルダーで指定したURLに接続する
+    std::env::remove_var("API_BASE_URL");
+    let client = ApiClient::builder()
+        .base_url(format!("{}/", mock_server.uri()))
+        .default_header(
+            HeaderName::from_static("x-api-key"),
+            HeaderValue::from_static("secret"),
+        )
+        .build()
+        .unwrap();
+    
+    let health = client.health().await.unwrap();
+    assert_eq!(health.status, "healthy");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_sends_bearer_token() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/folders"))
+        .and(header("authorization", "Bearer access-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "DocumentLoaded" } },
+            "folders": []
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ApiClient::builder()
+        .base_url(mock_server.uri())
+        .bearer_token("access-1")
+        .build()
+        .unwrap();
+    assert!(client.list_folders().await.unwrap().is_empty());
+
+    // 改行を含むトークンは送らない（サーバーが AuthenticationRequired を返す）
+    let client = ApiClient::builder()
+        .base_url(mock_server.uri())
+        .bearer_token("broken\ntoken")
+        .build()
+        .unwrap();
+    assert!(client.list_folders().await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_settings() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/settings"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "WARNING", "code": { "type": "AuthenticationRequired" } }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    // サーバー側の設定の方が新しければそちらが返る
+    Mock::given(method("PUT"))
+        .and(path("/api/v1/settings"))
+        .and(body_partial_json(json!({ "updated_at": 5 })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "SettingsSaved" } },
+            "settings": { "settings": { "spellcheck": true }, "updated_at": 9 }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ApiClient::new(mock_server.uri());
+    let error = client.settings().await.unwrap_err();
+    assert!(matches!(error, ApiError::ProcessError(ErrorCode::AuthenticationRequired)));
+
+    let snapshot = SettingsSnapshot { settings: UserSettings::default(), updated_at: 5 };
+    let saved = client.save_settings(&snapshot).await.unwrap();
+    assert_eq!(saved.updated_at, 9);
+    assert!(saved.settings.spellcheck);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_request_log() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/fonts"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req-1")
+                .set_body_json(json!({ "fonts": [] })),
+        )
+        .mount(&mock_server)
+        .await;
+
+    // クライアントごとではなく、同じログを共有するクライアント全体で記録する
+    let log = RequestLog::new();
+    let build = || {
+        ApiClient::builder()
+            .base_url(mock_server.uri())
+            .request_log(log.clone())
+            .build()
+            .unwrap()
+    };
+    build().fonts().await.unwrap();
+    let _ = build().status().await;
+
+    let records = log.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].path, "/api/v1/fonts");
+    assert_eq!(records[0].request_id.as_deref(), Some("req-1"));
+    assert_eq!(records[1].path, "/api/v1/status");
+    assert_eq!(records[1].status, 404);
+    assert_eq!(records[1].request_id, None);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_status() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("GET"))
+        .and(path("/api/v1/status"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "maintenance": true,
+            "announcement": "10:00〜11:00 はPlantUMLサーバーのメンテナンスのため変換できません"
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::new(mock_server.uri());
+    let status = client.status().await.unwrap();
+    assert!(status.maintenance);
+    assert_eq!(
+        status.announcement.as_deref(),
+        Some("10:00〜11:00 はPlantUMLサーバーのメンテナンスのため変換できません")
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_analyze() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/analyze"))
+        .and(body_partial_json(json!({ "plantuml_text": "@startuml\nA -> B\n@enduml" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "DiagramAnalyzed" } },
+            "analysis": {
+                "diagram_type": "sequence",
+                "pages": 1,
+                "lines": 3,
+                "participants": 2,
+                "messages": 1,
+                "classes": 0,
+                "notes": 0,
+                "complexity": 3,
+                "complexity_level": "low"
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/analyze"))
+        .and(body_partial_json(json!({ "plantuml_text": "" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "WARNING", "code": { "type": "ValidationEmpty" } }
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::new(mock_server.uri());
+    let analysis = client
+        .analyze("@startuml\nA -> B\n@enduml".to_string())
+        .await
+        .unwrap();
+    assert_eq!(analysis.diagram_type, "sequence");
+    assert_eq!(analysis.complexity, 3);
+    
+    let result = client.analyze(String::new()).await;
+    assert!(matches!(result, Err(ApiError::ProcessError(ErrorCode::ValidationEmpty))));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_font() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("GET"))
+        .and(path("/api/v1/fonts"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "fonts": ["Noto Sans CJK JP", "IPAexGothic"]
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v1/convert"))
+        .and(body_partial_json(json!({ "font": "Noto Sans CJK JP" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "ConversionOk" } },
+            "image_data": [0x3C, 0x73, 0x76, 0x67]
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::new(mock_server.uri());
+    let fonts = client.fonts().await.unwrap();
+    assert_eq!(fonts, vec!["Noto Sans CJK JP", "IPAexGothic"]);
+    
+    let (image_data, _) = client
+        .with_font(Some(fonts[0].clone()))
+        .convert("@startuml\nAlice -> ボブ\n@enduml".to_string(), ImageFormat::Svg)
+        .await
+        .unwrap();
+    assert_eq!(image_data, vec![0x3C, 0x73, 0x76, 0x67]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_examples() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/api/v1/examples"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "DocumentLoaded" } },
+            "categories": ["シーケンス図"],
+            "examples": [{
+                "id": "sequence-login",
+                "title": "ログイン処理",
+                "category": "シーケンス図",
+                "description": "",
+                "plantuml_text": "@startuml\nA -> B\n@enduml",
+                "thumbnail_url": "/api/v1/examples/sequence-login/thumbnail"
+            }]
+        })))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/examples/sequence-login/thumbnail"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "image/svg+xml")
+                .set_body_bytes(b"<svg/>".to_vec()),
+        )
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/examples/missing/thumbnail"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": {
+                "level": "WARNING",
+                "code": { "type": "DocumentNotFound", "id": "missing" }
+            }
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ApiClient::new(mock_server.uri());
+    let (categories, examples) = client.examples().await.unwrap();
+    assert_eq!(categories, vec!["シーケンス図"]);
+    assert_eq!(examples[0].id, "sequence-login");
+
+    let thumbnail = client.example_thumbnail(&examples[0].id).await.unwrap();
+    assert_eq!(thumbnail, b"<svg/>");
+    let result = client.example_thumbnail("missing").await;
+    assert!(matches!(
+        result,
+        Err(ApiError::ProcessError(ErrorCode::DocumentNotFound { .. }))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_deterministic_export() {
+    disable_proxy_for_test();
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/api/v1/export"))
+        .and(body_partial_json(json!({ "deterministic": true })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "ExportOk" } },
+            "image_data": [0x3C, 0x73, 0x76, 0x67]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let client = ApiClient::new(mock_server.uri()).with_deterministic(true);
+    let (image_data, _) = client
+        .export("@startuml\nAlice -> Bob\n@enduml".to_string(), ImageFormat::Svg)
+        .await
+        .unwrap();
+    assert_eq!(image_data, vec![0x3C, 0x73, 0x76, 0x67]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_timeout() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/convert"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::builder()
+        .base_url(mock_server.uri())
+        .timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+    
+    let result = client.convert("@startuml\n@enduml".to_string(), ImageFormat::Svg).await;
+    assert!(matches!(
+        result,
+        Err(ApiError::ProcessError(ErrorCode::TimeoutError { duration_ms: 100 }))
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_per_request_timeout() {
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/export"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .mount(&mock_server)
+        .await;
+    
+    // クライアント全体にはタイムアウトを設定せず、呼び出し単位で指定する
+    let client = ApiClient::new(mock_server.uri());
+    let result = client
+        .with_timeout(Duration::from_millis(50))
+        .export("@startuml\n@enduml".to_string(), ImageFormat::Png)
+        .await;
+    
+    let Err(ApiError::ProcessError(code)) = result else {
+        panic!("Expected TimeoutError");
+    };
+    assert_eq!(code, ErrorCode::TimeoutError { duration_ms: 50 });
+    assert_eq!(code.status_level(), StatusLevel::Error);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_api_client_compresses_large_bodies() {
+    use std::io::Read;
+
+    disable_proxy_for_test();
+    
+    let mock_server = MockServer::start().await;
+    
+    Mock::given(method("POST"))
+        .and(path("/api/v1/convert"))
+        .and(header("content-encoding", "gzip"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "result": { "level": "INFO", "code": { "type": "ConversionOk" } },
+            "image_data": [1]
+        })))
+        .mount(&mock_server)
+        .await;
+    
+    let client = ApiClient::builder()
+        .base_url(mock_server.uri())
+        .compress_requests(RequestCompression::Gzip)
+        .build()
+        .unwrap();
+    let text = format!("@startuml\n{}@enduml", "Alice -> Bob : hello\n".repeat(100));
+    client.convert(text.clone(), ImageFormat::Svg).await.unwrap();
+    
+    // 送信されたボディは圧縮されており、展開すると元のJSONになる
+    let requests = mock_server.received_requests().await.unwrap();
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(requests[0].body.as_slice())
+        .read_to_string(&mut json)
+        .unwrap();
+    let sent: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(sent["plantuml_text"], text);
+    assert!(requests[0].body.len() < json.len() / 4);
+    
+    // 小さなボディはそのまま送る（ヘッダーがないためモックに一致しない）
+    let result = client.convert("@startuml\n@enduml".to_string(), ImageFormat::Svg).await;
+    assert!(matches!(result, Err(ApiError::ServerError(_))));
+}
+
+#[test]
+fn test_api_client_validate() {
+    let client = ApiClient::new("http://localhost:8080");
+    
+    assert!(client.validate("@startuml\n@enduml").is_ok());
+    assert!(matches!(
+        client.validate("   "),
+        Err(ApiError::ProcessError(ErrorCode::ValidationEmpty))
+    ));
+}
+
+/// Relay that records requests and answers with a fixed response
+struct FakeRelay {
+    requests: Mutex<Vec<RelayRequest>>,
+    response: RelayResponse,
+}
+
+impl Relay for FakeRelay {
+    fn send(&self, request: RelayRequest) -> RelayFuture {
+        self.requests.lock().unwrap().push(request);
+        let response = self.response.clone();
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[tokio::test]
+async fn test_api_client_relay() {
+    let body = json!({
+        "result": { "level": "INFO", "code": { "type": "ConversionOk" } },
+        "image_data": [1, 2, 3]
+    });
+    let relay = Arc::new(FakeRelay {
+        requests: Mutex::new(Vec::new()),
+        response: RelayResponse {
+            status: 200,
+            content_type: Some("application/json".to_string()),
+            body: serde_json::to_vec(&body).unwrap(),
+        },
+    });
+    
+    // 接続できないURLでも、リレー経由なら応答が得られる
+    let client = ApiClient::builder()
+        .base_url("http://127.0.0.1:9")
+        .relay(relay.clone())
+        .build()
+        .unwrap();
+    let (image_data, _) = client
+        .convert("@startuml\n@enduml".to_string(), ImageFormat::Svg)
+        .await
+        .unwrap();
+    assert_eq!(image_data, vec![1, 2, 3]);
+    
+    let requests = relay.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].url, "http://127.0.0.1:9/api/v1/convert");
+    let sent: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(sent["format"], "svg");
+}
+
+#[tokio::test]
+async fn test_api_client_relay_http_error() {
+    let relay = Arc::new(FakeRelay {
+        requests: Mutex::new(Vec::new()),
+        response: RelayResponse {
+            status: 502,
+            content_type: None,
+            body: Vec::new(),
+        },
+    });
+    let client = ApiClient::builder().relay(relay).build().unwrap();
+    
+    let result = client.health().await;
+    assert!(matches!(result, Err(ApiError::ServerError(_))));
+}
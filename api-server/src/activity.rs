@@ -0,0 +1,131 @@
+// Recent changes to server-side documents
+//
+// A bounded in-memory log of create/update/delete events, newest last. Each
+// entry remembers who could see the document at the time, so the feed never
+// shows private documents (including deleted ones) to non-members.
+
+use plantuml_editor_core::clock::{SharedClock, SystemClock};
+use plantuml_editor_core::{ActivityEntry, ActivityKind, PlantUMLDocument};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::RwLock;
+
+use crate::documents::Roles;
+
+/// Entries kept before the oldest is dropped
+pub const ACTIVITY_CAPACITY: usize = 200;
+
+/// Entries returned when the request does not ask for a number
+pub const DEFAULT_ACTIVITY_LIMIT: usize = 50;
+
+struct Recorded {
+    entry: ActivityEntry,
+    /// Members of the document at the time (None for documents open to everyone)
+    visible_to: Option<BTreeSet<String>>,
+}
+
+pub struct ActivityLog {
+    entries: RwLock<VecDeque<Recorded>>,
+    clock: SharedClock,
+}
+
+impl Default for ActivityLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityLog {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::shared())
+    }
+
+    /// Log taking its timestamps from `clock`
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            entries: Default::default(),
+            clock,
+        }
+    }
+
+    /// Record a change made by `user` to a document with the given members
+    pub fn record(
+        &self,
+        kind: ActivityKind,
+        document: &PlantUMLDocument,
+        user: Option<&str>,
+        roles: &Roles,
+    ) {
+        let recorded = Recorded {
+            entry: ActivityEntry {
+                kind,
+                document_id: document.id,
+                title: document.title.clone(),
+                user: user.map(str::to_string),
+                at: self.clock.now(),
+            },
+            visible_to: (!roles.is_empty()).then(|| roles.keys().cloned().collect()),
+        };
+
+        let mut entries = self.entries.write().unwrap();
+        entries.push_back(recorded);
+        while entries.len() > ACTIVITY_CAPACITY {
+            entries.pop_front();
+        }
+    }
+
+    /// Up to `limit` entries the user may see, newest first
+    pub fn recent(&self, user: Option<&str>, limit: usize) -> Vec<ActivityEntry> {
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|recorded| match (&recorded.visible_to, user) {
+                (None, _) => true,
+                (Some(members), Some(user)) => members.contains(user),
+                (Some(_), None) => false,
+            })
+            .take(limit)
+            .map(|recorded| recorded.entry.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::DocumentRole;
+
+    fn document(title: &str) -> PlantUMLDocument {
+        let mut document = PlantUMLDocument::new("@startuml\n@enduml".to_string());
+        document.title = Some(title.to_string());
+        document
+    }
+
+    #[test]
+    fn test_recent_is_newest_first_and_filtered() {
+        let log = ActivityLog::new();
+        let private = Roles::from([("alice".to_string(), DocumentRole::Owner)]);
+        log.record(ActivityKind::Created, &document("open"), None, &Roles::new());
+        log.record(ActivityKind::Updated, &document("secret"), Some("alice"), &private);
+
+        let titles = |entries: Vec<ActivityEntry>| -> Vec<String> {
+            entries.into_iter().filter_map(|entry| entry.title).collect()
+        };
+        assert_eq!(titles(log.recent(Some("alice"), 10)), vec!["secret", "open"]);
+        assert_eq!(titles(log.recent(Some("bob"), 10)), vec!["open"]);
+        assert_eq!(titles(log.recent(None, 10)), vec!["open"]);
+        assert_eq!(log.recent(Some("alice"), 1).len(), 1);
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let log = ActivityLog::new();
+        for index in 0..ACTIVITY_CAPACITY + 5 {
+            log.record(ActivityKind::Updated, &document(&index.to_string()), None, &Roles::new());
+        }
+        let entries = log.recent(None, usize::MAX);
+        assert_eq!(entries.len(), ACTIVITY_CAPACITY);
+        assert_eq!(entries.last().unwrap().title.as_deref(), Some("5"));
+    }
+}
@@ -0,0 +1,287 @@
+// ZIP archive builder for bulk export, and the portable archive of a single
+// server document with its history
+
+use plantuml_editor_core::{
+    ArchiveManifest, ArchiveManifestEntry, DocumentArchive, DocumentRevision, ErrorCode,
+    ImageFormat, PlantUMLDocument, ProcessResult,
+};
+use std::collections::HashSet;
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{result::ZipError, CompressionMethod, ZipArchive, ZipWriter};
+
+/// Maximum number of files rendered per request
+pub const MAX_ARCHIVE_FILES: usize = 100;
+
+/// Name of the manifest entry inside the archive
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// Writes rendered images and a manifest into an in-memory ZIP archive
+pub struct ArchiveWriter {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    format: ImageFormat,
+    entries: Vec<ArchiveManifestEntry>,
+    used_paths: HashSet<String>,
+}
+
+impl ArchiveWriter {
+    pub fn new(format: ImageFormat) -> Self {
+        Self {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            format,
+            entries: Vec::new(),
+            used_paths: HashSet::from([MANIFEST_NAME.to_string()]),
+        }
+    }
+
+    /// Add a rendered image for the given source file
+    pub fn add_image(&mut self, source: &str, data: &[u8]) -> Result<(), ZipError> {
+        let path = self.unique_path(source);
+        self.zip.start_file(path.as_str(), file_options())?;
+        self.zip.write_all(data)?;
+
+        self.entries.push(ArchiveManifestEntry {
+            source: source.to_string(),
+            image: Some(path),
+            result: ProcessResult::new(ErrorCode::ExportOk),
+        });
+        Ok(())
+    }
+
+    /// Record a file that could not be rendered
+    pub fn add_error(&mut self, source: &str, code: ErrorCode) {
+        self.entries.push(ArchiveManifestEntry {
+            source: source.to_string(),
+            image: None,
+            result: ProcessResult::new(code),
+        });
+    }
+
+    /// Write the manifest and return the archive bytes
+    pub fn finish(mut self) -> Result<Vec<u8>, ZipError> {
+        let manifest = ArchiveManifest {
+            format: self.format,
+            files: self.entries,
+        };
+        let json = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| ZipError::Io(std::io::Error::other(e)))?;
+
+        self.zip.start_file(MANIFEST_NAME, file_options())?;
+        self.zip.write_all(&json)?;
+        Ok(self.zip.finish()?.into_inner())
+    }
+
+    /// Image path for a source file, made unique within the archive
+    fn unique_path(&mut self, source: &str) -> String {
+        let base = image_path(source, self.format);
+        let (stem, extension) = base.rsplit_once('.').unwrap_or((base.as_str(), ""));
+
+        let mut path = base.clone();
+        let mut counter = 2;
+        while self.used_paths.contains(&path) {
+            path = format!("{}-{}.{}", stem, counter, extension);
+            counter += 1;
+        }
+        self.used_paths.insert(path.clone());
+        path
+    }
+}
+
+/// Name of the document entry inside a document archive
+pub const DOCUMENT_ENTRY_NAME: &str = "document.json";
+
+/// Layout version written to document archives
+pub const DOCUMENT_ARCHIVE_VERSION: u32 = 1;
+
+/// Largest document.json read from an uploaded archive (bytes, uncompressed)
+const MAX_DOCUMENT_ENTRY_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Why an uploaded document archive could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentArchiveError {
+    NotZip,
+    MissingDocument,
+    InvalidDocument,
+    UnsupportedVersion(u32),
+}
+
+impl DocumentArchiveError {
+    pub fn to_error_code(&self) -> ErrorCode {
+        let reason = match self {
+            DocumentArchiveError::NotZip => "ZIPファイルではありません".to_string(),
+            DocumentArchiveError::MissingDocument => {
+                format!("{} が含まれていません", DOCUMENT_ENTRY_NAME)
+            }
+            DocumentArchiveError::InvalidDocument => {
+                format!("{} の形式が正しくありません", DOCUMENT_ENTRY_NAME)
+            }
+            DocumentArchiveError::UnsupportedVersion(version) => {
+                format!("対応していないアーカイブのバージョンです（{}）", version)
+            }
+        };
+        ErrorCode::ImportError { reason }
+    }
+}
+
+/// ZIP with document.json (the document, its earlier versions and metadata)
+/// and the given rendered images of the current version
+pub fn write_document_archive(
+    document: PlantUMLDocument,
+    revisions: Vec<DocumentRevision>,
+    images: Vec<(String, Vec<u8>)>,
+) -> Result<Vec<u8>, ZipError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in &images {
+        zip.start_file(name.as_str(), file_options())?;
+        zip.write_all(data)?;
+    }
+
+    let archive = DocumentArchive {
+        archive_version: DOCUMENT_ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        document,
+        revisions,
+        images: images.into_iter().map(|(name, _)| name).collect(),
+    };
+    let json = serde_json::to_vec_pretty(&archive)
+        .map_err(|e| ZipError::Io(std::io::Error::other(e)))?;
+    zip.start_file(DOCUMENT_ENTRY_NAME, file_options())?;
+    zip.write_all(&json)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Document and history from an archive written by `write_document_archive`
+///
+/// Images are not read; they are rendered again from the source when needed.
+pub fn read_document_archive(bytes: &[u8]) -> Result<DocumentArchive, DocumentArchiveError> {
+    let mut zip =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|_| DocumentArchiveError::NotZip)?;
+    let entry = zip
+        .by_name(DOCUMENT_ENTRY_NAME)
+        .map_err(|_| DocumentArchiveError::MissingDocument)?;
+
+    let mut json = Vec::new();
+    entry
+        .take(MAX_DOCUMENT_ENTRY_BYTES)
+        .read_to_end(&mut json)
+        .map_err(|_| DocumentArchiveError::InvalidDocument)?;
+    let archive: DocumentArchive =
+        serde_json::from_slice(&json).map_err(|_| DocumentArchiveError::InvalidDocument)?;
+    if archive.archive_version != DOCUMENT_ARCHIVE_VERSION {
+        return Err(DocumentArchiveError::UnsupportedVersion(archive.archive_version));
+    }
+    Ok(archive)
+}
+
+fn file_options() -> FileOptions {
+    FileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// Image path for an uploaded file name
+///
+/// Keeps relative directories so a directory upload keeps its layout,
+/// but drops absolute prefixes and `..` components.
+pub fn image_path(source: &str, format: ImageFormat) -> String {
+    let components: Vec<&str> = source
+        .split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+        .collect();
+
+    let path = match components.split_last() {
+        Some((file, dirs)) => {
+            let stem = match file.rsplit_once('.') {
+                Some((stem, _)) if !stem.is_empty() => stem,
+                _ => file,
+            };
+            dirs.iter().copied().chain([stem]).collect::<Vec<_>>().join("/")
+        }
+        None => "diagram".to_string(),
+    };
+
+    let extension = match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Svg => "svg",
+        ImageFormat::Txt => "txt",
+        ImageFormat::Pdf => "pdf",
+    };
+    format!("{}.{}", path, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_path() {
+        assert_eq!(image_path("sequence.puml", ImageFormat::Png), "sequence.png");
+        assert_eq!(image_path("docs/er.plantuml", ImageFormat::Svg), "docs/er.svg");
+        assert_eq!(image_path("/abs/../x.puml", ImageFormat::Png), "abs/x.png");
+        assert_eq!(image_path(".hidden", ImageFormat::Png), ".hidden.png");
+        assert_eq!(image_path("", ImageFormat::Png), "diagram.png");
+    }
+
+    #[test]
+    fn test_archive_contains_images_and_manifest() {
+        let mut writer = ArchiveWriter::new(ImageFormat::Png);
+        writer.add_image("a.puml", b"first").unwrap();
+        writer.add_image("sub/a.puml", b"second").unwrap();
+        writer.add_image("a.pu", b"third").unwrap();
+        writer.add_error("broken.puml", ErrorCode::ValidationEmpty);
+        let bytes = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["a-2.png", "a.png", "manifest.json", "sub/a.png"]);
+
+        let mut manifest = String::new();
+        archive
+            .by_name(MANIFEST_NAME)
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: ArchiveManifest = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest.format, ImageFormat::Png);
+        assert_eq!(manifest.files.len(), 4);
+        assert_eq!(manifest.files[2].image.as_deref(), Some("a-2.png"));
+        assert_eq!(manifest.files[3].source, "broken.puml");
+        assert!(manifest.files[3].image.is_none());
+        assert_eq!(manifest.files[3].result.code, ErrorCode::ValidationEmpty);
+    }
+
+    #[test]
+    fn test_document_archive_round_trip() {
+        let mut document = PlantUMLDocument::new("@startuml\nB -> A\n@enduml".to_string());
+        document.version = 2;
+        let revisions = vec![DocumentRevision {
+            version: 1,
+            content: "@startuml\nA -> B\n@enduml".to_string(),
+            title: None,
+            updated_at: 1700000000,
+        }];
+        let images = vec![("diagram.svg".to_string(), b"<svg/>".to_vec())];
+        let bytes = write_document_archive(document.clone(), revisions.clone(), images).unwrap();
+
+        let names: Vec<String> = ZipArchive::new(Cursor::new(bytes.as_slice()))
+            .unwrap()
+            .file_names()
+            .map(str::to_string)
+            .collect();
+        assert!(names.contains(&"diagram.svg".to_string()));
+
+        let archive = read_document_archive(&bytes).unwrap();
+        assert_eq!(archive.document, document);
+        assert_eq!(archive.revisions, revisions);
+        assert_eq!(archive.images, vec!["diagram.svg"]);
+    }
+
+    #[test]
+    fn test_read_document_archive_errors() {
+        assert_eq!(read_document_archive(b"not a zip"), Err(DocumentArchiveError::NotZip));
+
+        let mut writer = ArchiveWriter::new(ImageFormat::Png);
+        writer.add_image("a.puml", b"image").unwrap();
+        let bulk = writer.finish().unwrap();
+        assert_eq!(read_document_archive(&bulk), Err(DocumentArchiveError::MissingDocument));
+    }
+}
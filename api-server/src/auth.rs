@@ -0,0 +1,508 @@
+// OIDC access tokens for server storage
+//
+// With OIDC_ISSUER set, server storage (documents, folders, includes and the
+// activity feed) requires `Authorization: Bearer <access token>` issued by
+// that provider; the web UI gets one with the authorization code flow (PKCE).
+// Tokens are JWTs signed with RS256 or ES256 by a key of the provider's JWKS.
+// The keys are downloaded on first use and again when a token names a key
+// not seen yet (at most once a minute, so bogus tokens cannot flood the
+// provider). The user in the token replaces USER_HEADER, so document roles
+// apply to logged-in users as they do behind an authenticating proxy.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::config::OidcConfig;
+
+/// Clock skew tolerated when checking `exp` and `nbf` (seconds)
+const LEEWAY_SECS: i64 = 60;
+
+/// Shortest time between two downloads of the signing keys (seconds)
+const JWKS_REFRESH_SECS: i64 = 60;
+
+/// Why a request was not authenticated (logged, never sent to the client)
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AuthError {
+    #[error("no bearer token")]
+    MissingToken,
+    #[error("malformed token")]
+    Malformed,
+    #[error("unsupported algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("unknown signing key")]
+    UnknownKey,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("token expired")]
+    Expired,
+    #[error("token not valid yet")]
+    NotYetValid,
+    #[error("token from another issuer")]
+    WrongIssuer,
+    #[error("token for another audience")]
+    WrongAudience,
+    #[error("token has no {0} claim")]
+    MissingUser(String),
+    #[error("signing keys unavailable: {0}")]
+    KeysUnavailable(String),
+}
+
+/// Public key of the provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PublicKey {
+    /// RS256: modulus and exponent, big-endian
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// ES256: uncompressed P-256 point
+    P256 { point: Vec<u8> },
+}
+
+impl PublicKey {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+        let verified = match self {
+            Self::Rsa { n, e } => RsaPublicKeyComponents { n, e }.verify(
+                &signature::RSA_PKCS1_2048_8192_SHA256,
+                message,
+                signature,
+            ),
+            Self::P256 { point } => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, signature)
+            }
+        };
+        verified.map_err(|_| AuthError::InvalidSignature)
+    }
+}
+
+/// Key as listed in a JWKS document
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    #[serde(default)]
+    kid: Option<String>,
+    #[serde(default, rename = "use")]
+    usage: Option<String>,
+    #[serde(default)]
+    crv: Option<String>,
+    #[serde(default)]
+    n: Option<String>,
+    #[serde(default)]
+    e: Option<String>,
+    #[serde(default)]
+    x: Option<String>,
+    #[serde(default)]
+    y: Option<String>,
+}
+
+impl Jwk {
+    /// The key, when it is a signing key of a supported type
+    fn public_key(&self) -> Option<PublicKey> {
+        if self.usage.as_deref().is_some_and(|usage| usage != "sig") {
+            return None;
+        }
+        let decode = |value: &Option<String>| URL_SAFE_NO_PAD.decode(value.as_deref()?).ok();
+        match (self.kty.as_str(), self.crv.as_deref()) {
+            ("RSA", _) => Some(PublicKey::Rsa {
+                n: decode(&self.n)?,
+                e: decode(&self.e)?,
+            }),
+            ("EC", Some("P-256")) => {
+                let mut point = vec![0x04];
+                point.extend(decode(&self.x)?);
+                point.extend(decode(&self.y)?);
+                Some(PublicKey::P256 { point })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Signing keys of the provider, by key ID
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeySet {
+    keys: Vec<(Option<String>, PublicKey)>,
+}
+
+impl KeySet {
+    /// Parse a JWKS document, skipping encryption keys and unsupported types
+    pub fn from_jwks(json: &str) -> Result<Self, AuthError> {
+        #[derive(Deserialize)]
+        struct Jwks {
+            keys: Vec<Jwk>,
+        }
+        let jwks: Jwks = serde_json::from_str(json)
+            .map_err(|e| AuthError::KeysUnavailable(format!("invalid JWKS: {}", e)))?;
+        Ok(Self {
+            keys: jwks
+                .keys
+                .iter()
+                .filter_map(|jwk| Some((jwk.kid.clone(), jwk.public_key()?)))
+                .collect(),
+        })
+    }
+
+    /// Key for `alg`, matching `kid` when the token names one
+    fn find(&self, kid: Option<&str>, alg: &str) -> Result<&PublicKey, AuthError> {
+        let fits = |key: &PublicKey| match alg {
+            "RS256" => matches!(key, PublicKey::Rsa { .. }),
+            "ES256" => matches!(key, PublicKey::P256 { .. }),
+            _ => false,
+        };
+        if !matches!(alg, "RS256" | "ES256") {
+            return Err(AuthError::UnsupportedAlgorithm(alg.to_string()));
+        }
+        self.keys
+            .iter()
+            .find(|(id, key)| fits(key) && (kid.is_none() || id.as_deref() == kid))
+            .map(|(_, key)| key)
+            .ok_or(AuthError::UnknownKey)
+    }
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+    #[serde(default)]
+    kid: Option<String>,
+}
+
+fn decode_part<T: DeserializeOwned>(part: &str) -> Result<T, AuthError> {
+    let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| AuthError::Malformed)?;
+    serde_json::from_slice(&bytes).map_err(|_| AuthError::Malformed)
+}
+
+/// Check `token` against `keys` and `config` at `now`, returning the user
+pub fn verify(
+    token: &str,
+    keys: &KeySet,
+    config: &OidcConfig,
+    now: i64,
+) -> Result<String, AuthError> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AuthError::Malformed);
+    };
+    let header_fields: JwtHeader = decode_part(header)?;
+    let key = keys.find(header_fields.kid.as_deref(), &header_fields.alg)?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| AuthError::Malformed)?;
+    // The signature covers the encoded header and payload as sent
+    let signed = &token[..header.len() + 1 + payload.len()];
+    key.verify(signed.as_bytes(), &signature)?;
+
+    let claims: Map<String, Value> = decode_part(payload)?;
+    if config.issuer.as_deref() != claims.get("iss").and_then(Value::as_str) {
+        return Err(AuthError::WrongIssuer);
+    }
+    if let Some(audience) = &config.audience {
+        let listed = match claims.get("aud") {
+            Some(Value::String(aud)) => aud == audience,
+            Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+            _ => false,
+        };
+        if !listed {
+            return Err(AuthError::WrongAudience);
+        }
+    }
+    let exp = claims
+        .get("exp")
+        .and_then(Value::as_i64)
+        .ok_or(AuthError::Malformed)?;
+    if exp + LEEWAY_SECS < now {
+        return Err(AuthError::Expired);
+    }
+    if claims
+        .get("nbf")
+        .and_then(Value::as_i64)
+        .is_some_and(|nbf| nbf - LEEWAY_SECS > now)
+    {
+        return Err(AuthError::NotYetValid);
+    }
+    claims
+        .get(&config.user_claim)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| AuthError::MissingUser(config.user_claim.clone()))
+}
+
+/// Verifies access tokens with the provider's keys, downloaded when needed
+pub struct TokenVerifier {
+    keys: RwLock<KeySet>,
+    /// When the keys were last downloaded
+    fetched_at: Mutex<Option<i64>>,
+    http: reqwest::Client,
+}
+
+impl Default for TokenVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenVerifier {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Self {
+            keys: RwLock::default(),
+            fetched_at: Mutex::default(),
+            http,
+        }
+    }
+
+    /// Replace the known keys (also used by tests instead of downloading)
+    pub fn set_keys(&self, keys: KeySet) {
+        *self.keys.write().unwrap() = keys;
+    }
+
+    /// Check `token`, downloading the keys when it names an unknown one
+    pub async fn authenticate(
+        &self,
+        token: &str,
+        config: &OidcConfig,
+        now: i64,
+    ) -> Result<String, AuthError> {
+        let result = verify(token, &self.keys.read().unwrap(), config, now);
+        match result {
+            Err(AuthError::UnknownKey) if self.may_fetch(now) => {
+                let keys = fetch_keys(&self.http, config).await?;
+                tracing::info!("Downloaded {} OIDC signing keys", keys.keys.len());
+                self.set_keys(keys);
+                verify(token, &self.keys.read().unwrap(), config, now)
+            }
+            result => result,
+        }
+    }
+
+    fn may_fetch(&self, now: i64) -> bool {
+        let mut fetched_at = self.fetched_at.lock().unwrap();
+        if fetched_at.is_some_and(|at| now - at < JWKS_REFRESH_SECS) {
+            return false;
+        }
+        *fetched_at = Some(now);
+        true
+    }
+}
+
+/// Download the JWKS, finding its URL in the discovery document when needed
+async fn fetch_keys(http: &reqwest::Client, config: &OidcConfig) -> Result<KeySet, AuthError> {
+    let unavailable = |e: reqwest::Error| AuthError::KeysUnavailable(e.to_string());
+    let jwks_url = match (&config.jwks_url, &config.issuer) {
+        (Some(url), _) => url.clone(),
+        (None, Some(issuer)) => {
+            let discovery: Value = http
+                .get(format!("{}/.well-known/openid-configuration", issuer))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(unavailable)?
+                .json()
+                .await
+                .map_err(unavailable)?;
+            discovery
+                .get("jwks_uri")
+                .and_then(Value::as_str)
+                .ok_or_else(|| AuthError::KeysUnavailable("no jwks_uri in discovery".to_string()))?
+                .to_string()
+        }
+        (None, None) => return Err(AuthError::KeysUnavailable("no issuer".to_string())),
+    };
+    let jwks = http
+        .get(jwks_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(unavailable)?
+        .text()
+        .await
+        .map_err(unavailable)?;
+    KeySet::from_jwks(&jwks)
+}
+
+/// ES256 tokens signed with a key generated for the test
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+    pub const ISSUER: &str = "https://login.example.com";
+    pub const AUDIENCE: &str = "plantuml-editor";
+
+    pub struct Signer {
+        kid: String,
+        key_pair: EcdsaKeyPair,
+    }
+
+    impl Signer {
+        pub fn new(kid: &str) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let key_pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            Self {
+                kid: kid.to_string(),
+                key_pair,
+            }
+        }
+
+        pub fn jwks(&self) -> String {
+            let point = self.key_pair.public_key().as_ref();
+            serde_json::json!({
+                "keys": [{
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "use": "sig",
+                    "kid": self.kid,
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                }]
+            })
+            .to_string()
+        }
+
+        pub fn key_set(&self) -> KeySet {
+            KeySet::from_jwks(&self.jwks()).unwrap()
+        }
+
+        pub fn sign(&self, claims: Value) -> String {
+            let header = serde_json::json!({ "alg": "ES256", "kid": self.kid, "typ": "JWT" });
+            let signed = format!(
+                "{}.{}",
+                URL_SAFE_NO_PAD.encode(header.to_string()),
+                URL_SAFE_NO_PAD.encode(claims.to_string())
+            );
+            let signature = self
+                .key_pair
+                .sign(&SystemRandom::new(), signed.as_bytes())
+                .unwrap();
+            format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+        }
+
+        /// Token for `user` valid until `exp`
+        pub fn token(&self, user: &str, exp: i64) -> String {
+            self.sign(serde_json::json!({
+                "iss": ISSUER,
+                "aud": [AUDIENCE, "account"],
+                "sub": user,
+                "exp": exp,
+            }))
+        }
+    }
+
+    pub fn config() -> OidcConfig {
+        OidcConfig {
+            issuer: Some(ISSUER.to_string()),
+            audience: Some(AUDIENCE.to_string()),
+            jwks_url: None,
+            user_claim: "sub".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::*;
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_verify_token() {
+        let signer = Signer::new("key-1");
+        let keys = signer.key_set();
+        let config = config();
+
+        let token = signer.token("alice", NOW + 300);
+        assert_eq!(verify(&token, &keys, &config, NOW), Ok("alice".to_string()));
+        // Expiry tolerates a little clock skew
+        assert!(verify(&token, &keys, &config, NOW + 330).is_ok());
+        assert_eq!(verify(&token, &keys, &config, NOW + 400), Err(AuthError::Expired));
+
+        let claims = |iss: &str, aud: &str| {
+            serde_json::json!({ "iss": iss, "aud": aud, "sub": "alice", "exp": NOW + 300 })
+        };
+        let token = signer.sign(claims("https://other.example.com", AUDIENCE));
+        assert_eq!(verify(&token, &keys, &config, NOW), Err(AuthError::WrongIssuer));
+        let token = signer.sign(claims(ISSUER, "other-app"));
+        assert_eq!(verify(&token, &keys, &config, NOW), Err(AuthError::WrongAudience));
+
+        let token = signer.sign(serde_json::json!({ "iss": ISSUER, "aud": AUDIENCE, "exp": NOW + 300 }));
+        assert_eq!(
+            verify(&token, &keys, &config, NOW),
+            Err(AuthError::MissingUser("sub".to_string()))
+        );
+        let token = signer.sign(
+            serde_json::json!({ "iss": ISSUER, "aud": AUDIENCE, "sub": "alice", "exp": NOW + 300, "nbf": NOW + 600 }),
+        );
+        assert_eq!(verify(&token, &keys, &config, NOW), Err(AuthError::NotYetValid));
+    }
+
+    #[test]
+    fn test_verify_rejects_forged_tokens() {
+        let signer = Signer::new("key-1");
+        let config = config();
+        let token = signer.token("alice", NOW + 300);
+
+        // Signed by a key the provider does not list
+        let other = Signer::new("key-1");
+        assert_eq!(
+            verify(&token, &other.key_set(), &config, NOW),
+            Err(AuthError::InvalidSignature)
+        );
+        assert_eq!(
+            verify(&token, &Signer::new("key-2").key_set(), &config, NOW),
+            Err(AuthError::UnknownKey)
+        );
+
+        // Claims changed after signing
+        let parts: Vec<&str> = token.split('.').collect();
+        let forged_claims = serde_json::json!({ "iss": ISSUER, "aud": AUDIENCE, "sub": "admin", "exp": NOW + 300 });
+        let forged = format!(
+            "{}.{}.{}",
+            parts[0],
+            URL_SAFE_NO_PAD.encode(forged_claims.to_string()),
+            parts[2]
+        );
+        assert_eq!(
+            verify(&forged, &signer.key_set(), &config, NOW),
+            Err(AuthError::InvalidSignature)
+        );
+
+        // Unsigned tokens are never accepted
+        let none = format!("{}.{}.", URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#), parts[1]);
+        assert_eq!(
+            verify(&none, &signer.key_set(), &config, NOW),
+            Err(AuthError::UnsupportedAlgorithm("none".to_string()))
+        );
+        assert_eq!(verify("not-a-jwt", &signer.key_set(), &config, NOW), Err(AuthError::Malformed));
+    }
+
+    #[test]
+    fn test_key_set_skips_unusable_keys() {
+        let jwks = r#"{"keys":[
+            {"kty":"RSA","use":"enc","kid":"enc","n":"AQAB","e":"AQAB"},
+            {"kty":"EC","crv":"P-384","kid":"p384","x":"AA","y":"AA"},
+            {"kty":"RSA","kid":"rsa","n":"AQAB","e":"AQAB"}
+        ]}"#;
+        let keys = KeySet::from_jwks(jwks).unwrap();
+        assert_eq!(keys.keys.len(), 1);
+        assert!(keys.find(Some("rsa"), "RS256").is_ok());
+        assert_eq!(keys.find(Some("enc"), "RS256"), Err(AuthError::UnknownKey));
+        assert!(KeySet::from_jwks("not json").is_err());
+    }
+}
@@ -0,0 +1,524 @@
+// In-memory server-side document store
+//
+// Documents created by an identified user are private to their members: the
+// creator becomes the owner and grants other users a role. Documents created
+// without a user have no members and stay open to everyone.
+
+use plantuml_editor_core::clock::{SharedClock, SystemClock};
+use plantuml_editor_core::{
+    DocumentId, DocumentRevision, DocumentRole, ErrorCode, FolderId, IdStrategy, PlantUMLDocument,
+    SearchHit, ValidationError,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+use crate::search::{self, SearchIndex};
+
+/// Members of a document and their roles
+pub type Roles = BTreeMap<String, DocumentRole>;
+
+/// Why a document could not be updated
+#[derive(Debug)]
+pub enum UpdateError {
+    Invalid(ValidationError),
+    /// The document changed after the edit started from `base_version`
+    Conflict { current_version: u64 },
+}
+
+impl UpdateError {
+    pub fn to_error_code(&self) -> ErrorCode {
+        match self {
+            UpdateError::Invalid(e) => e.to_error_code(),
+            UpdateError::Conflict { current_version } => ErrorCode::DocumentConflict {
+                current_version: *current_version,
+            },
+        }
+    }
+}
+
+/// Documents stored on the server (kept in memory for the lifetime of the process)
+pub struct DocumentStore {
+    documents: RwLock<HashMap<DocumentId, PlantUMLDocument>>,
+    roles: RwLock<HashMap<DocumentId, Roles>>,
+    /// Versions replaced by updates, oldest first
+    revisions: RwLock<HashMap<DocumentId, Vec<DocumentRevision>>>,
+    index: RwLock<SearchIndex>,
+    clock: SharedClock,
+    id_strategy: IdStrategy,
+}
+
+impl Default for DocumentStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DocumentStore {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::shared())
+    }
+
+    /// Store taking its timestamps from `clock`
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            documents: Default::default(),
+            roles: Default::default(),
+            revisions: Default::default(),
+            index: Default::default(),
+            clock,
+            id_strategy: IdStrategy::default(),
+        }
+    }
+
+    /// Store choosing the IDs of new documents with `strategy`
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Self {
+        self.id_strategy = strategy;
+        self
+    }
+
+    /// All documents, most recently updated first
+    pub fn list(&self) -> Vec<PlantUMLDocument> {
+        let mut documents: Vec<PlantUMLDocument> =
+            self.documents.read().unwrap().values().cloned().collect();
+        documents.sort_by_key(|document| std::cmp::Reverse(document.updated_at));
+        documents
+    }
+
+    /// Documents the user can view, most recently updated first
+    pub fn list_visible(&self, user: Option<&str>) -> Vec<PlantUMLDocument> {
+        self.list()
+            .into_iter()
+            .filter(|document| self.role_of(&document.id, user).is_some())
+            .collect()
+    }
+
+    pub fn get(&self, id: &DocumentId) -> Option<PlantUMLDocument> {
+        self.documents.read().unwrap().get(id).cloned()
+    }
+
+    /// Documents the user can view matching a full-text query, best match first
+    pub fn search(&self, query: &str, user: Option<&str>) -> Vec<SearchHit> {
+        let ranked = self.index.read().unwrap().search(query);
+        let documents = self.documents.read().unwrap();
+        ranked
+            .into_iter()
+            .filter(|(id, _)| self.role_of(id, user).is_some())
+            .filter_map(|(id, score)| Some(search::hit(documents.get(&id)?, query, score)))
+            .collect()
+    }
+
+    /// Validate and store a new document, owned by `owner` when given
+    pub fn create(
+        &self,
+        content: String,
+        title: Option<String>,
+        owner: Option<&str>,
+    ) -> Result<PlantUMLDocument, ValidationError> {
+        let mut document = PlantUMLDocument::new_at(content, self.clock.now());
+        document.validate()?;
+        document.title = title;
+        document.fill_title_from_source();
+
+        {
+            let mut documents = self.documents.write().unwrap();
+            // A second document with the same content cannot share its ID
+            let id = self.id_strategy.id_for(&document.content);
+            if !documents.contains_key(&id) {
+                document.id = id;
+            }
+            documents.insert(document.id, document.clone());
+        }
+        self.index.write().unwrap().upsert(&document);
+        self.set_owner(document.id, owner);
+        Ok(document)
+    }
+
+    /// Validate and replace the content of an existing document
+    ///
+    /// With a `base_version`, the update is rejected when the stored document
+    /// is at a different version. Returns `Ok(None)` when the document does
+    /// not exist.
+    pub fn update(
+        &self,
+        id: &DocumentId,
+        content: String,
+        title: Option<String>,
+        base_version: Option<u64>,
+    ) -> Result<Option<PlantUMLDocument>, UpdateError> {
+        plantuml_editor_core::validate_plantuml_content(&content).map_err(UpdateError::Invalid)?;
+
+        let mut documents = self.documents.write().unwrap();
+        let Some(document) = documents.get_mut(id) else {
+            return Ok(None);
+        };
+        if let Some(base_version) = base_version {
+            if base_version != document.version {
+                return Err(UpdateError::Conflict {
+                    current_version: document.version,
+                });
+            }
+        }
+        self.revisions
+            .write()
+            .unwrap()
+            .entry(*id)
+            .or_default()
+            .push(DocumentRevision {
+                version: document.version,
+                content: std::mem::take(&mut document.content),
+                title: document.title.take(),
+                updated_at: document.updated_at,
+            });
+        document.content = content;
+        document.title = title;
+        document.fill_title_from_source();
+        document.updated_at = self.clock.now();
+        document.version += 1;
+        self.index.write().unwrap().upsert(document);
+        Ok(Some(document.clone()))
+    }
+
+    /// Versions of a document replaced by updates, oldest first
+    pub fn revisions(&self, id: &DocumentId) -> Vec<DocumentRevision> {
+        self.revisions.read().unwrap().get(id).cloned().unwrap_or_default()
+    }
+
+    /// Store a document exported from another deployment with its history,
+    /// owned by `owner` when given
+    ///
+    /// The document keeps its ID unless one is already in use here. Folders
+    /// do not carry over, so it is placed at the top level.
+    pub fn import(
+        &self,
+        mut document: PlantUMLDocument,
+        revisions: Vec<DocumentRevision>,
+        owner: Option<&str>,
+    ) -> Result<PlantUMLDocument, ValidationError> {
+        document.validate()?;
+        document.folder = None;
+
+        {
+            let mut documents = self.documents.write().unwrap();
+            if documents.contains_key(&document.id) {
+                document.id = DocumentId::new();
+            }
+            documents.insert(document.id, document.clone());
+        }
+        self.revisions.write().unwrap().insert(document.id, revisions);
+        self.index.write().unwrap().upsert(&document);
+        self.set_owner(document.id, owner);
+        Ok(document)
+    }
+
+    /// Move a document into a folder (or to the top level with None)
+    ///
+    /// Returns None when the document does not exist.
+    pub fn move_to(&self, id: &DocumentId, folder: Option<FolderId>) -> Option<PlantUMLDocument> {
+        let mut documents = self.documents.write().unwrap();
+        let document = documents.get_mut(id)?;
+        document.folder = folder;
+        Some(document.clone())
+    }
+
+    /// Store a copy of a document under a new ID, owned by `owner` when given
+    ///
+    /// Returns None when the document does not exist.
+    pub fn duplicate(&self, id: &DocumentId, owner: Option<&str>) -> Option<PlantUMLDocument> {
+        let copy = {
+            let mut documents = self.documents.write().unwrap();
+            let copy = documents.get(id)?.duplicate_at(self.clock.now());
+            documents.insert(copy.id, copy.clone());
+            copy
+        };
+        self.index.write().unwrap().upsert(&copy);
+        self.set_owner(copy.id, owner);
+        Some(copy)
+    }
+
+    /// Returns false when the document does not exist
+    pub fn delete(&self, id: &DocumentId) -> bool {
+        self.roles.write().unwrap().remove(id);
+        self.revisions.write().unwrap().remove(id);
+        self.index.write().unwrap().remove(id);
+        self.documents.write().unwrap().remove(id).is_some()
+    }
+
+    /// The user's role on a document (None when they are not a member)
+    ///
+    /// Everyone is an owner of a document without members, including
+    /// documents that do not exist, so handlers can report those as not found.
+    pub fn role_of(&self, id: &DocumentId, user: Option<&str>) -> Option<DocumentRole> {
+        let roles = self.roles.read().unwrap();
+        match roles.get(id) {
+            Some(members) if !members.is_empty() => members.get(user?).copied(),
+            _ => Some(DocumentRole::Owner),
+        }
+    }
+
+    /// Members of a document
+    pub fn roles(&self, id: &DocumentId) -> Roles {
+        self.roles.read().unwrap().get(id).cloned().unwrap_or_default()
+    }
+
+    /// Grant `user` a role, or remove them with None
+    ///
+    /// On a document without members, `caller` becomes its owner first so
+    /// they keep access. Returns None, leaving the roles unchanged, when the
+    /// document would be left without an owner.
+    pub fn set_role(
+        &self,
+        id: &DocumentId,
+        caller: Option<&str>,
+        user: String,
+        role: Option<DocumentRole>,
+    ) -> Option<Roles> {
+        let mut roles = self.roles.write().unwrap();
+        let mut members = roles.get(id).cloned().unwrap_or_default();
+        if members.is_empty() {
+            if let Some(caller) = caller {
+                members.insert(caller.to_string(), DocumentRole::Owner);
+            }
+        }
+        match role {
+            Some(role) => members.insert(user, role),
+            None => members.remove(&user),
+        };
+
+        if !members.values().any(|role| *role == DocumentRole::Owner) {
+            return None;
+        }
+        roles.insert(*id, members.clone());
+        Some(members)
+    }
+
+    fn set_owner(&self, id: DocumentId, owner: Option<&str>) {
+        if let Some(owner) = owner {
+            let members = Roles::from([(owner.to_string(), DocumentRole::Owner)]);
+            self.roles.write().unwrap().insert(id, members);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::clock::MockClock;
+
+    const SOURCE: &str = "@startuml\nA -> B\n@enduml";
+
+    #[test]
+    fn test_create_get_update_delete() {
+        let store = DocumentStore::new();
+        let created = store
+            .create(SOURCE.to_string(), Some("Flow".to_string()), None)
+            .unwrap();
+        assert_eq!(store.get(&created.id).unwrap().title.as_deref(), Some("Flow"));
+
+        let updated = store
+            .update(&created.id, "@startuml\nB -> A\n@enduml".to_string(), None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.id, created.id);
+        assert!(updated.content.contains("B -> A"));
+        assert_eq!(updated.version, created.version + 1);
+        assert_eq!(store.list().len(), 1);
+
+        assert!(store.delete(&created.id));
+        assert!(!store.delete(&created.id));
+        assert!(store.get(&created.id).is_none());
+    }
+
+    #[test]
+    fn test_update_from_stale_version_conflicts() {
+        let store = DocumentStore::new();
+        let created = store.create(SOURCE.to_string(), None, None).unwrap();
+        let base = created.version;
+
+        // Teammate saves first
+        store
+            .update(&created.id, SOURCE.to_string(), None, Some(base))
+            .unwrap()
+            .unwrap();
+
+        let stale = store.update(&created.id, "@startuml\nB -> A\n@enduml".to_string(), None, Some(base));
+        assert!(matches!(
+            stale,
+            Err(UpdateError::Conflict { current_version }) if current_version == base + 1
+        ));
+        assert_eq!(store.get(&created.id).unwrap().content, SOURCE);
+    }
+
+    #[test]
+    fn test_revisions_and_import() {
+        let store = DocumentStore::new();
+        let created = store
+            .create(SOURCE.to_string(), Some("Flow".to_string()), None)
+            .unwrap();
+        let updated = store
+            .update(&created.id, "@startuml\nB -> A\n@enduml".to_string(), None, None)
+            .unwrap()
+            .unwrap();
+
+        let revisions = store.revisions(&created.id);
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].version, created.version);
+        assert_eq!(revisions[0].content, SOURCE);
+        assert_eq!(revisions[0].title.as_deref(), Some("Flow"));
+
+        // The ID is taken here, so the import gets a new one
+        let imported = store.import(updated.clone(), revisions.clone(), Some("alice")).unwrap();
+        assert_ne!(imported.id, updated.id);
+        assert_eq!(imported.version, updated.version);
+        assert_eq!(store.revisions(&imported.id), revisions);
+        assert_eq!(store.role_of(&imported.id, Some("bob")), None);
+
+        let other = DocumentStore::new();
+        assert_eq!(other.import(updated.clone(), Vec::new(), None).unwrap().id, updated.id);
+
+        assert!(store.delete(&created.id));
+        assert!(store.revisions(&created.id).is_empty());
+    }
+
+    #[test]
+    fn test_timestamps_follow_clock() {
+        let clock = MockClock::new(1_700_000_000);
+        let store = DocumentStore::with_clock(clock.shared());
+        let created = store.create(SOURCE.to_string(), None, None).unwrap();
+        assert_eq!(created.created_at, 1_700_000_000);
+        assert_eq!(created.updated_at, 1_700_000_000);
+
+        clock.advance(60);
+        let updated = store
+            .update(&created.id, "@startuml\nB -> A\n@enduml".to_string(), None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.created_at, 1_700_000_000);
+        assert_eq!(updated.updated_at, 1_700_000_060);
+        // The revision keeps the time the old version was saved
+        assert_eq!(store.revisions(&created.id)[0].updated_at, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_content_ids() {
+        let store = DocumentStore::new().with_id_strategy(IdStrategy::Content);
+        let first = store.create(SOURCE.to_string(), None, None).unwrap();
+        assert_eq!(first.id, DocumentId::from_content(SOURCE));
+
+        // Same content again gets a random ID instead of replacing the first
+        let second = store.create(SOURCE.to_string(), None, None).unwrap();
+        assert_ne!(second.id, first.id);
+        assert_eq!(store.list().len(), 2);
+    }
+
+    #[test]
+    fn test_title_from_source() {
+        let store = DocumentStore::new();
+        let source = "@startuml\ntitle Checkout\nA -> B\n@enduml".to_string();
+        let created = store.create(source.clone(), None, None).unwrap();
+        assert_eq!(created.title.as_deref(), Some("Checkout"));
+
+        let explicit = store.create(source, Some("Mine".to_string()), None).unwrap();
+        assert_eq!(explicit.title.as_deref(), Some("Mine"));
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let store = DocumentStore::new();
+        let original = store
+            .create(SOURCE.to_string(), Some("Flow".to_string()), None)
+            .unwrap();
+
+        let copy = store.duplicate(&original.id, None).unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.title.as_deref(), Some("Flow のコピー"));
+        assert_eq!(store.list().len(), 2);
+        assert!(store.duplicate(&DocumentId::new(), None).is_none());
+    }
+
+    #[test]
+    fn test_validation_and_missing_document() {
+        let store = DocumentStore::new();
+        assert!(matches!(
+            store.create("   ".to_string(), None, None),
+            Err(ValidationError::EmptyContent)
+        ));
+        assert!(store
+            .update(&DocumentId::new(), SOURCE.to_string(), None, None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_roles() {
+        let store = DocumentStore::new();
+        let open = store.create(SOURCE.to_string(), None, None).unwrap();
+        assert_eq!(store.role_of(&open.id, None), Some(DocumentRole::Owner));
+
+        let private = store.create(SOURCE.to_string(), None, Some("alice")).unwrap();
+        assert_eq!(store.role_of(&private.id, Some("alice")), Some(DocumentRole::Owner));
+        assert_eq!(store.role_of(&private.id, Some("bob")), None);
+        assert_eq!(store.role_of(&private.id, None), None);
+        assert_eq!(store.list_visible(Some("bob")).len(), 1);
+
+        store
+            .set_role(&private.id, Some("alice"), "bob".to_string(), Some(DocumentRole::Viewer))
+            .unwrap();
+        assert_eq!(store.role_of(&private.id, Some("bob")), Some(DocumentRole::Viewer));
+        assert_eq!(store.list_visible(Some("bob")).len(), 2);
+
+        // The last owner cannot leave
+        assert!(store
+            .set_role(&private.id, Some("alice"), "alice".to_string(), None)
+            .is_none());
+        assert_eq!(store.role_of(&private.id, Some("alice")), Some(DocumentRole::Owner));
+    }
+
+    #[test]
+    fn test_first_role_makes_caller_owner() {
+        let store = DocumentStore::new();
+        let document = store.create(SOURCE.to_string(), None, None).unwrap();
+        let roles = store
+            .set_role(&document.id, Some("alice"), "bob".to_string(), Some(DocumentRole::Editor))
+            .unwrap();
+        assert_eq!(roles.get("alice"), Some(&DocumentRole::Owner));
+        assert_eq!(roles.get("bob"), Some(&DocumentRole::Editor));
+
+        let anonymous = store.create(SOURCE.to_string(), None, None).unwrap();
+        assert!(store
+            .set_role(&anonymous.id, None, "bob".to_string(), Some(DocumentRole::Viewer))
+            .is_none());
+    }
+
+    #[test]
+    fn test_move_to_folder() {
+        let store = DocumentStore::new();
+        let document = store.create(SOURCE.to_string(), None, None).unwrap();
+        let folder = FolderId::new();
+
+        let moved = store.move_to(&document.id, Some(folder)).unwrap();
+        assert_eq!(moved.folder, Some(folder));
+        assert_eq!(store.duplicate(&document.id, None).unwrap().folder, Some(folder));
+        assert!(store.move_to(&DocumentId::new(), None).is_none());
+    }
+
+    #[test]
+    fn test_search_follows_changes_and_roles() {
+        let store = DocumentStore::new();
+        let open = store.create(SOURCE.to_string(), Some("Login".to_string()), None).unwrap();
+        let private = store
+            .create(SOURCE.to_string(), Some("Login admin".to_string()), Some("alice"))
+            .unwrap();
+
+        assert_eq!(store.search("login", Some("alice")).len(), 2);
+        let hits = store.search("login", Some("bob"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, open.id);
+
+        store
+            .update(&open.id, SOURCE.to_string(), Some("Checkout".to_string()), None)
+            .unwrap();
+        assert!(store.search("login", None).is_empty());
+        assert!(store.delete(&private.id));
+        assert!(store.search("admin", Some("alice")).is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+// Example diagrams bundled with the server
+//
+// A small curated set for GET /api/v1/examples, one or two per diagram kind,
+// so users can start from something that renders. Thumbnails are rendered on
+// request like any other diagram (and cached by the renderer).
+
+use plantuml_editor_core::Example;
+
+/// Example as compiled into the server
+pub struct BundledExample {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub category: &'static str,
+    pub description: &'static str,
+    pub source: &'static str,
+}
+
+impl BundledExample {
+    pub fn to_example(&self) -> Example {
+        Example {
+            id: self.id.to_string(),
+            title: self.title.to_string(),
+            category: self.category.to_string(),
+            description: self.description.to_string(),
+            plantuml_text: self.source.to_string(),
+            thumbnail_url: format!("/api/v1/examples/{}/thumbnail", self.id),
+        }
+    }
+}
+
+/// Examples in display order, grouped by category
+pub const EXAMPLES: &[BundledExample] = &[
+    BundledExample {
+        id: "sequence-login",
+        title: "ログイン処理",
+        category: "シーケンス図",
+        description: "ブラウザ・サーバー・DB間のやり取りと、認証失敗時の分岐",
+        source: "@startuml
+title ログイン処理
+actor ユーザー as User
+participant ブラウザ as Browser
+participant APIサーバー as API
+database DB
+
+User -> Browser : ID・パスワードを入力
+Browser -> API : POST /login
+API -> DB : ユーザーを検索
+DB --> API : ユーザー情報
+alt 認証成功
+    API --> Browser : 200 OK (セッション)
+    Browser --> User : ホーム画面
+else 認証失敗
+    API --> Browser : 401 Unauthorized
+    Browser --> User : エラーメッセージ
+end
+@enduml",
+    },
+    BundledExample {
+        id: "sequence-order",
+        title: "注文処理",
+        category: "シーケンス図",
+        description: "ループと非同期メッセージを含む注文から出荷までの流れ",
+        source: "@startuml
+title 注文処理
+participant 注文サービス as Order
+participant 在庫サービス as Stock
+queue メッセージキュー as Queue
+participant 出荷サービス as Shipping
+
+Order -> Stock : 在庫を引き当て
+loop 明細ごと
+    Stock -> Stock : 数量を確認
+end
+Stock --> Order : 引き当て完了
+Order ->> Queue : 注文確定イベント
+Queue ->> Shipping : 出荷を依頼
+note right of Shipping : 非同期で処理
+@enduml",
+    },
+    BundledExample {
+        id: "class-shop",
+        title: "ECサイトのドメインモデル",
+        category: "クラス図",
+        description: "継承・集約・多重度を使ったクラスの関係",
+        source: "@startuml
+title ECサイトのドメインモデル
+class 顧客 {
+    +ID : int
+    +名前 : String
+    +注文する()
+}
+class 注文 {
+    +注文番号 : String
+    +合計金額() : int
+}
+class 注文明細 {
+    +数量 : int
+}
+abstract class 商品 {
+    +価格 : int
+}
+class 書籍
+class 家電
+
+顧客 \"1\" -- \"*\" 注文
+注文 \"1\" *-- \"1..*\" 注文明細
+注文明細 \"*\" --> \"1\" 商品
+商品 <|-- 書籍
+商品 <|-- 家電
+@enduml",
+    },
+    BundledExample {
+        id: "activity-approval",
+        title: "経費精算の承認フロー",
+        category: "アクティビティ図",
+        description: "スイムレーンと分岐で表した申請から支払いまでの流れ",
+        source: "@startuml
+title 経費精算の承認フロー
+|申請者|
+start
+:経費を申請;
+|上長|
+:内容を確認;
+if (承認する?) then (はい)
+    |経理|
+    :支払いを処理;
+else (いいえ)
+    |申請者|
+    :差し戻しを確認;
+endif
+stop
+@enduml",
+    },
+    BundledExample {
+        id: "usecase-library",
+        title: "図書館システム",
+        category: "ユースケース図",
+        description: "利用者と司書のユースケースと、include・extend の関係",
+        source: "@startuml
+title 図書館システム
+left to right direction
+actor 利用者
+actor 司書
+rectangle 図書館システム {
+    usecase \"本を検索する\" as Search
+    usecase \"本を借りる\" as Borrow
+    usecase \"本を予約する\" as Reserve
+    usecase \"貸出を記録する\" as Record
+}
+利用者 --> Search
+利用者 --> Borrow
+Borrow ..> Record : <<include>>
+Reserve ..> Borrow : <<extend>>
+司書 --> Record
+@enduml",
+    },
+    BundledExample {
+        id: "state-order",
+        title: "注文のステータス",
+        category: "状態遷移図",
+        description: "注文が受付から完了・キャンセルに至るまでの状態",
+        source: "@startuml
+title 注文のステータス
+[*] --> 受付済み
+受付済み --> 支払い待ち : 注文確定
+支払い待ち --> 発送準備中 : 入金確認
+支払い待ち --> キャンセル : 期限切れ
+発送準備中 --> 発送済み : 出荷
+発送済み --> [*]
+キャンセル --> [*]
+@enduml",
+    },
+    BundledExample {
+        id: "component-web",
+        title: "Webアプリケーションの構成",
+        category: "コンポーネント図",
+        description: "フロントエンド・API・データストアの依存関係",
+        source: "@startuml
+title Webアプリケーションの構成
+package フロントエンド {
+    [Webアプリ] as Web
+}
+package バックエンド {
+    [APIサーバー] as API
+    [ジョブワーカー] as Worker
+}
+database PostgreSQL as DB
+queue Redis as Cache
+
+Web --> API : HTTPS
+API --> DB
+API --> Cache
+Worker --> Cache
+Worker --> DB
+@enduml",
+    },
+    BundledExample {
+        id: "mindmap-project",
+        title: "プロジェクト計画",
+        category: "マインドマップ",
+        description: "目的・体制・スケジュールを整理したマインドマップ",
+        source: "@startmindmap
+* 新システム導入
+** 目的
+*** 業務の効率化
+*** 情報の一元管理
+** 体制
+*** 開発チーム
+*** 運用チーム
+left side
+** スケジュール
+*** 要件定義
+*** 開発
+*** 移行
+@endmindmap",
+    },
+    BundledExample {
+        id: "gantt-release",
+        title: "リリース計画",
+        category: "ガントチャート",
+        description: "タスクの期間と依存関係、マイルストーン",
+        source: "@startgantt
+Project starts 2025-04-01
+[設計] lasts 10 days
+[実装] lasts 20 days
+[実装] starts at [設計]'s end
+[テスト] lasts 10 days
+[テスト] starts at [実装]'s end
+[リリース] happens at [テスト]'s end
+@endgantt",
+    },
+];
+
+/// Categories in the order their first example appears
+pub fn categories() -> Vec<&'static str> {
+    let mut categories = Vec::new();
+    for example in EXAMPLES {
+        if !categories.contains(&example.category) {
+            categories.push(example.category);
+        }
+    }
+    categories
+}
+
+pub fn find(id: &str) -> Option<&'static BundledExample> {
+    EXAMPLES.iter().find(|example| example.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::diagnostics::lint;
+    use plantuml_editor_core::{validate_plantuml_content, DiagnosticSeverity};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_examples_are_valid() {
+        let ids: HashSet<_> = EXAMPLES.iter().map(|example| example.id).collect();
+        assert_eq!(ids.len(), EXAMPLES.len(), "IDs are unique");
+
+        for example in EXAMPLES {
+            assert!(validate_plantuml_content(example.source).is_ok(), "{}", example.id);
+            let errors: Vec<_> = lint(example.source)
+                .into_iter()
+                .filter(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", example.id, errors);
+        }
+    }
+
+    #[test]
+    fn test_categories_keep_example_order() {
+        let categories = categories();
+        assert_eq!(categories.first(), Some(&"シーケンス図"));
+        assert_eq!(categories.len(), 8);
+        // Examples of a category are listed together
+        let order: Vec<_> = EXAMPLES
+            .iter()
+            .map(|example| categories.iter().position(|c| *c == example.category))
+            .collect();
+        assert!(order.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(
+            find("class-shop").map(|example| example.to_example().thumbnail_url),
+            Some("/api/v1/examples/class-shop/thumbnail".to_string())
+        );
+    }
+}
@@ -0,0 +1,154 @@
+// In-memory folder tree for server-side documents
+
+use plantuml_editor_core::clock::{SharedClock, SystemClock};
+use plantuml_editor_core::{ErrorCode, Folder, FolderId};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Why a folder could not be created or moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FolderError {
+    NotFound(FolderId),
+    EmptyName,
+    /// The folder would end up inside itself
+    Cycle,
+}
+
+impl FolderError {
+    pub fn to_error_code(self) -> ErrorCode {
+        match self {
+            FolderError::NotFound(id) => ErrorCode::FolderNotFound { id: id.0.to_string() },
+            FolderError::EmptyName => ErrorCode::FolderNameEmpty,
+            FolderError::Cycle => ErrorCode::FolderCycle,
+        }
+    }
+}
+
+/// Folders stored on the server (kept in memory for the lifetime of the process)
+pub struct FolderStore {
+    folders: RwLock<HashMap<FolderId, Folder>>,
+    clock: SharedClock,
+}
+
+impl Default for FolderStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FolderStore {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::shared())
+    }
+
+    /// Store taking its timestamps from `clock`
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            folders: Default::default(),
+            clock,
+        }
+    }
+
+    /// All folders, sorted by name
+    pub fn list(&self) -> Vec<Folder> {
+        let mut folders: Vec<Folder> = self.folders.read().unwrap().values().cloned().collect();
+        folders.sort_by(|a, b| a.name.cmp(&b.name));
+        folders
+    }
+
+    pub fn contains(&self, id: &FolderId) -> bool {
+        self.folders.read().unwrap().contains_key(id)
+    }
+
+    /// Create a folder inside `parent` (or at the top level)
+    pub fn create(&self, name: &str, parent: Option<FolderId>) -> Result<Folder, FolderError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(FolderError::EmptyName);
+        }
+
+        let mut folders = self.folders.write().unwrap();
+        if let Some(parent) = parent {
+            if !folders.contains_key(&parent) {
+                return Err(FolderError::NotFound(parent));
+            }
+        }
+        let folder = Folder {
+            id: FolderId::new(),
+            name: name.to_string(),
+            parent,
+            created_at: self.clock.now(),
+        };
+        folders.insert(folder.id, folder.clone());
+        Ok(folder)
+    }
+
+    /// Rename a folder and move it under `parent`
+    pub fn update(
+        &self,
+        id: &FolderId,
+        name: &str,
+        parent: Option<FolderId>,
+    ) -> Result<Folder, FolderError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(FolderError::EmptyName);
+        }
+
+        let mut folders = self.folders.write().unwrap();
+        if !folders.contains_key(id) {
+            return Err(FolderError::NotFound(*id));
+        }
+        // Walk up from the new parent; meeting the folder itself means a cycle
+        let mut ancestor = parent;
+        while let Some(current) = ancestor {
+            if current == *id {
+                return Err(FolderError::Cycle);
+            }
+            ancestor = folders
+                .get(&current)
+                .ok_or(FolderError::NotFound(current))?
+                .parent;
+        }
+
+        let folder = folders.get_mut(id).expect("checked above");
+        folder.name = name.to_string();
+        folder.parent = parent;
+        Ok(folder.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_list() {
+        let store = FolderStore::new();
+        let specs = store.create("仕様", None).unwrap();
+        let api = store.create(" API ", Some(specs.id)).unwrap();
+        assert_eq!(api.name, "API");
+        assert_eq!(api.parent, Some(specs.id));
+
+        let names: Vec<String> = store.list().into_iter().map(|folder| folder.name).collect();
+        assert_eq!(names, vec!["API", "仕様"]);
+
+        assert_eq!(store.create("  ", None), Err(FolderError::EmptyName));
+        let missing = FolderId::new();
+        assert_eq!(store.create("x", Some(missing)), Err(FolderError::NotFound(missing)));
+    }
+
+    #[test]
+    fn test_move_rejects_cycles() {
+        let store = FolderStore::new();
+        let outer = store.create("outer", None).unwrap();
+        let inner = store.create("inner", Some(outer.id)).unwrap();
+
+        assert_eq!(store.update(&outer.id, "outer", Some(inner.id)), Err(FolderError::Cycle));
+        assert_eq!(store.update(&outer.id, "outer", Some(outer.id)), Err(FolderError::Cycle));
+
+        let moved = store.update(&inner.id, "renamed", None).unwrap();
+        assert_eq!(moved.parent, None);
+        assert_eq!(moved.name, "renamed");
+    }
+}
@@ -1,17 +1,51 @@
 // API handlers
 
 use axum::{
-    extract::Json,
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    body::Bytes,
+    extract::{multipart::MultipartError, Extension, Form, Json, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
 };
+use futures::StreamExt;
+use plantuml_client::source_url::source_from_url;
+use plantuml_editor_core::diagnostics::{self, line_diagnostic};
+use plantuml_editor_core::diff::apply_patch;
+use plantuml_editor_core::metadata::{extract_title, file_stem_from_title};
+use plantuml_editor_core::preprocess::{apply_font, apply_scale};
+use plantuml_editor_core::sarif::{sarif_log, SarifArtifact, SARIF_CONTENT_TYPE};
+use plantuml_editor_core::stats;
 use plantuml_editor_core::{
-    ConvertRequest, ConvertResponse,
-    ErrorCode,
+    content_size_warning, validate_plantuml_content, ActivityKind, ActivityResponse, AnalyzeRequest, AnalyzeResponse, BatchExportRequest, ConvertRequest, ConvertResponse,
+    Diagnostic, DiagnosticSeverity, DiagnosticsRequest, DiagnosticsResponse, DiagramUrlImportRequest, DiagramUrlImportResponse, DocumentId, DocumentListResponse,
+    DocumentPatchRequest, DocumentRequest, DocumentResponse, DocumentRole, ErrorCode, ExampleListResponse, ExportScale, Folder, FolderId,
+    FolderListResponse, FolderRequest, FolderResponse, FontListResponse, ImageFormat, IncludeFile,
+    IncludeListResponse, IncludeRequest, IncludeResponse, JobInfo, JobResponse,
+    MoveDocumentRequest, OpenApiImportRequest, OpenApiImportResponse, PlantUMLDocument,
+    ProcessResult, RoleRequest, RolesResponse, SearchResponse, SettingsResponse, SettingsSnapshot,
+    ShareLink, ShareRequest, ShareResponse,
 };
-use plantuml_client::PlantUmlClient;
+use serde::Deserialize;
 use serde_json::json;
 
+use crate::activity::DEFAULT_ACTIVITY_LIMIT;
+use crate::archive::{
+    image_path, read_document_archive, write_document_archive, ArchiveWriter, MAX_ARCHIVE_FILES,
+};
+use crate::config::{LargeSvgAction, LargeSvgConfig, RELOADABLE_SETTINGS};
+use crate::documents::{Roles, UpdateError};
+use crate::examples;
+use crate::middleware::caller;
+use crate::openapi::OpenApiDocument;
+use crate::pipeline::{self, ExportPipeline, PostProcessor};
+use crate::readiness;
+use crate::render::{content_type, format_name, RenderError};
+use crate::share::ShareError;
+use crate::state::AppState;
+use crate::webhooks::WebhookEvent;
+
 /// GET /api/v1/health - Health check endpoint
 pub async fn health() -> Response {
     let health_status = json!({
@@ -23,8 +57,185 @@ pub async fn health() -> Response {
     (StatusCode::OK, Json(health_status)).into_response()
 }
 
+/// GET /api/v1/ready - Readiness check
+///
+/// Returns 503 until the configuration is valid and the PlantUML server answers.
+pub async fn ready(State(state): State<AppState>) -> Response {
+    let checks = readiness::run_checks(&state).await;
+    let ready = checks.iter().all(|check| check.ok);
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": checks,
+    });
+    
+    (status, Json(body)).into_response()
+}
+
+/// GET /api/v1/config - Configuration in effect, with secrets redacted
+///
+/// Also lists the settings a reload applies and how the last reload went.
+pub async fn get_config(State(state): State<AppState>) -> Response {
+    let body = json!({
+        "config": state.config.current().redacted(),
+        "reloadable": RELOADABLE_SETTINGS,
+        "last_reload": state.config.last_reload(),
+    });
+
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// GET /api/v1/metrics - Request rates, response codes and PlantUML server
+/// latency over the last hour
+pub async fn metrics(State(state): State<AppState>) -> Response {
+    let snapshot = state.metrics.snapshot(state.clock.now());
+    (StatusCode::OK, Json(snapshot)).into_response()
+}
+
+/// GET /dashboard - Operator page showing /api/v1/metrics, refreshed every few seconds
+pub async fn dashboard() -> Response {
+    Html(include_str!("dashboard.html")).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MaintenanceRequest {
+    pub enabled: bool,
+}
+
+/// GET /api/v1/admin/maintenance - Whether conversions are switched off
+pub async fn get_maintenance(State(state): State<AppState>) -> Response {
+    let body = json!({ "maintenance": state.renderer.is_maintenance() });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// PUT /api/v1/admin/maintenance - Switch conversions off (Maintenance) or on
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(payload): Json<MaintenanceRequest>,
+) -> Response {
+    state.renderer.set_maintenance(payload.enabled);
+    tracing::warn!(
+        "Maintenance mode {}",
+        if payload.enabled { "enabled" } else { "disabled" }
+    );
+    let body = json!({ "maintenance": payload.enabled });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnnouncementRequest {
+    /// Notice for users; null or blank removes it
+    pub message: Option<String>,
+}
+
+/// PUT /api/v1/admin/announcement - Set or clear the notice shown in the web UI
+pub async fn set_announcement(
+    State(state): State<AppState>,
+    Json(payload): Json<AnnouncementRequest>,
+) -> Response {
+    let message = payload
+        .message
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty());
+    tracing::info!("Announcement {}", if message.is_some() { "set" } else { "cleared" });
+    *state.announcement.write().unwrap() = message;
+    status(State(state)).await
+}
+
+/// GET /api/v1/status - Maintenance state and announcement for the web UI banner
+pub async fn status(State(state): State<AppState>) -> Response {
+    let body = json!({
+        "maintenance": state.renderer.is_maintenance(),
+        "announcement": *state.announcement.read().unwrap(),
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// GET /api/v1/fonts - Font families clients may pick for rendering (ALLOWED_FONTS)
+pub async fn fonts(State(state): State<AppState>) -> Response {
+    let response = FontListResponse {
+        fonts: state.config.current().allowed_fonts.clone(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/export/pipeline - Export post-processors (EXPORT_PIPELINE)
+///
+/// `default` runs when a request names no `pipeline`; `available` lists the
+/// steps a request may name, without `watermark` when no text is configured.
+pub async fn export_pipeline(State(state): State<AppState>) -> Response {
+    let config = state.config.current();
+    let available: Vec<&str> = PostProcessor::ALL
+        .iter()
+        .filter(|step| **step != PostProcessor::Watermark || config.export_pipeline.watermark.is_some())
+        .map(|step| step.name())
+        .collect();
+    let body = json!({
+        "default": config.export_pipeline.steps.iter().map(|step| step.name()).collect::<Vec<_>>(),
+        "available": available,
+    });
+    (StatusCode::OK, Json(body)).into_response()
+}
+
+/// GET /api/v1/examples - Example diagrams bundled with the server
+pub async fn list_examples() -> Response {
+    let response = ExampleListResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        categories: examples::categories().into_iter().map(String::from).collect(),
+        examples: examples::EXAMPLES.iter().map(|example| example.to_example()).collect(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/examples/:id/thumbnail - An example rendered as SVG
+pub async fn example_thumbnail(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(example) = examples::find(&id) else {
+        let response = ConvertResponse::error(ErrorCode::DocumentNotFound { id });
+        return (StatusCode::OK, Json(response)).into_response();
+    };
+
+    let format = ImageFormat::Svg;
+    let result = state
+        .renderer
+        .render(DocumentId::from_content(example.source), example.source, format, None)
+        .await;
+
+    match result {
+        Ok(image) => {
+            (StatusCode::OK, [(header::CONTENT_TYPE, content_type(format))], image.data)
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Example render failed: {}", e);
+            let error_code = e.to_error_code(|_| ErrorCode::ParseError { line: None });
+            let response = ConvertResponse::error(error_code);
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// The requested font when ALLOWED_FONTS lists it (a blank font means the default)
+fn allowed_font<'a>(state: &AppState, font: Option<&'a str>) -> Result<Option<&'a str>, ErrorCode> {
+    let Some(font) = font.map(str::trim).filter(|font| !font.is_empty()) else {
+        return Ok(None);
+    };
+    if state.config.current().allowed_fonts.iter().any(|allowed| allowed == font) {
+        Ok(Some(font))
+    } else {
+        Err(ErrorCode::FontNotAllowed { font: font.to_string() })
+    }
+}
+
 /// POST /api/v1/convert - Convert PlantUML text to image
-pub async fn convert(Json(payload): Json<ConvertRequest>) -> Response {
+pub async fn convert(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ConvertRequest>,
+) -> Response {
     // Validate request
     if let Err(e) = payload.validate() {
         tracing::warn!("Validation failed: {}", e);
@@ -32,48 +243,48 @@ pub async fn convert(Json(payload): Json<ConvertRequest>) -> Response {
         let response = ConvertResponse::error(error_code);
         return (StatusCode::OK, Json(response)).into_response();
     }
-    
-    // Create PlantUML client
-    let client = match PlantUmlClient::new("http://localhost:8081".to_string()) {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!("Failed to create PlantUML client: {}", e);
-            let error_code = ErrorCode::ServerError {
-                message: e.to_string(),
-            };
-            let response = ConvertResponse::error(error_code);
-            return (StatusCode::OK, Json(response)).into_response();
-        }
+    let font = match allowed_font(&state, payload.font.as_deref()) {
+        Ok(font) => font,
+        Err(code) => return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response(),
     };
     
-    // Convert PlantUML text to image
-    let document_id = plantuml_editor_core::DocumentId::new();
-    let result = match payload.format {
-        plantuml_editor_core::ImageFormat::Png => {
-            client.convert_to_png(document_id, &payload.plantuml_text).await
-        }
-        plantuml_editor_core::ImageFormat::Svg => {
-            client.convert_to_svg(document_id, &payload.plantuml_text).await
-        }
-    };
+    // Convert PlantUML text to image, with the caller's own include files
+    let document_id = state.config.current().id_strategy.id_for(&payload.plantuml_text);
+    let plantuml_text = apply_font(&payload.plantuml_text, font);
+    let user = caller(&headers);
+    let result = state
+        .renderer
+        .render(document_id, &plantuml_text, payload.format, user)
+        .await;
     
     match result {
         Ok(image) => {
             tracing::info!("PlantUML conversion successful: {} bytes", image.data.len());
-            let response = ConvertResponse::success(image.data, ErrorCode::ConversionOk);
+            let large_svg = state.config.current().large_svg;
+            let response = if payload.format == ImageFormat::Svg
+                && large_svg.threshold_bytes > 0
+                && image.data.len() > large_svg.threshold_bytes
+            {
+                large_svg_response(&state, large_svg, &plantuml_text, user, image.data).await
+            } else {
+                let code = content_size_warning(&payload.plantuml_text);
+                ConvertResponse::success(image.data, code.unwrap_or(ErrorCode::ConversionOk))
+            };
             (StatusCode::OK, Json(response)).into_response()
         }
         Err(e) => {
             tracing::error!("PlantUML conversion failed: {}", e);
             
             // Determine error code based on error type
-            let error_code = if e.to_string().contains("エンコードエラー") {
-                ErrorCode::EncodingError {
-                    encoding: "UTF-8".to_string(),
+            let error_code = e.to_error_code(|e| {
+                if e.to_string().contains("エンコードエラー") {
+                    ErrorCode::EncodingError {
+                        encoding: "UTF-8".to_string(),
+                    }
+                } else {
+                    ErrorCode::ParseError { line: None }
                 }
-            } else {
-                ErrorCode::ParseError { line: None }
-            };
+            });
             
             let response = ConvertResponse::error(error_code);
             (StatusCode::OK, Json(response)).into_response()
@@ -81,8 +292,44 @@ pub async fn convert(Json(payload): Json<ConvertRequest>) -> Response {
     }
 }
 
+/// Preview response for an SVG over LARGE_SVG_BYTES
+///
+/// With LARGE_SVG_ACTION=png the diagram is rendered again as PNG; when that
+/// fails the SVG is returned with the warning instead.
+async fn large_svg_response(
+    state: &AppState,
+    large_svg: LargeSvgConfig,
+    plantuml_text: &str,
+    user: Option<&str>,
+    svg: Vec<u8>,
+) -> ConvertResponse {
+    let svg_bytes = svg.len();
+    if large_svg.action == LargeSvgAction::Png {
+        let png = state
+            .renderer
+            .render(DocumentId::new(), plantuml_text, ImageFormat::Png, user)
+            .await;
+        match png {
+            Ok(png) => {
+                tracing::info!("Large SVG ({} bytes) replaced by PNG", svg_bytes);
+                return ConvertResponse::success(png.data, ErrorCode::SvgDowngraded { svg_bytes });
+            }
+            Err(e) => tracing::warn!("PNG fallback for a large SVG failed: {}", e),
+        }
+    }
+    let code = ErrorCode::LargeSvg {
+        svg_bytes,
+        threshold_bytes: large_svg.threshold_bytes,
+    };
+    ConvertResponse::success(svg, code)
+}
+
 /// POST /api/v1/export - Export PlantUML diagram
-pub async fn export(Json(payload): Json<ConvertRequest>) -> Response {
+pub async fn export(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ConvertRequest>,
+) -> Response {
     // Validate request
     if let Err(e) = payload.validate() {
         tracing::warn!("Export validation failed: {}", e);
@@ -90,59 +337,1278 @@ pub async fn export(Json(payload): Json<ConvertRequest>) -> Response {
         let response = ConvertResponse::error(error_code);
         return (StatusCode::OK, Json(response)).into_response();
     }
-    
-    // Create PlantUML client
-    let client = match PlantUmlClient::new("http://localhost:8081".to_string()) {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!("Failed to create PlantUML client for export: {}", e);
-            let error_code = ErrorCode::ServerError {
-                message: e.to_string(),
-            };
-            let response = ConvertResponse::error(error_code);
-            return (StatusCode::OK, Json(response)).into_response();
-        }
+    let font = match allowed_font(&state, payload.font.as_deref()) {
+        Ok(font) => font,
+        Err(code) => return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response(),
+    };
+    let pipeline = match ExportPipeline::for_request(&state.config.current().export_pipeline, &payload) {
+        Ok(pipeline) => pipeline,
+        Err(code) => return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response(),
     };
     
-    // Convert PlantUML text to image
-    let document_id = plantuml_editor_core::DocumentId::new();
-    let result = match payload.format {
-        plantuml_editor_core::ImageFormat::Png => {
-            client.convert_to_png(document_id, &payload.plantuml_text).await
-        }
-        plantuml_editor_core::ImageFormat::Svg => {
-            client.convert_to_svg(document_id, &payload.plantuml_text).await
+    // Convert PlantUML text to image at the requested resolution, with the
+    // caller's own include files
+    let document_id = state.config.current().id_strategy.id_for(&payload.plantuml_text);
+    let plantuml_text = apply_font(&payload.plantuml_text, font);
+    let user = caller(&headers);
+    let (plantuml_text, warning) = match state.renderer.enforce_policy(&plantuml_text, user) {
+        Ok(enforced) => enforced,
+        Err(code) => {
+            tracing::warn!("Export rejected by the style policy: {:?}", code);
+            return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response();
         }
     };
+    let plantuml_text = apply_scale(&plantuml_text, payload.scale);
+    let result = state
+        .renderer
+        .render(document_id, &plantuml_text, payload.format, user)
+        .await;
     
     match result {
         Ok(image) => {
             tracing::info!("PlantUML export successful: {} bytes", image.data.len());
+            let output = pipeline.run(image.data, &payload, state.clock.now());
             // Return ExportOk instead of ConversionOk
-            let response = ConvertResponse::success(image.data, ErrorCode::ExportOk);
+            let code = warning
+                .or(output.warning)
+                .or_else(|| content_size_warning(&payload.plantuml_text))
+                .unwrap_or(ErrorCode::ExportOk);
+            let mut response = ConvertResponse::success(output.data, code);
+            response.file_name = output
+                .file_stem
+                .map(|stem| download_file_name(Some(&stem), &payload.plantuml_text, payload.format));
             (StatusCode::OK, Json(response)).into_response()
         }
         Err(e) => {
             tracing::error!("PlantUML export failed: {}", e);
             
             // Determine error code based on error type
-            let error_code = if e.to_string().contains("エンコードエラー") {
-                ErrorCode::EncodingError {
-                    encoding: "UTF-8".to_string(),
+            let error_code = e.to_error_code(|e| {
+                if e.to_string().contains("エンコードエラー") {
+                    ErrorCode::EncodingError {
+                        encoding: "UTF-8".to_string(),
+                    }
+                } else {
+                    ErrorCode::ExportError {
+                        format: format_name(payload.format).to_string(),
+                    }
                 }
+            });
+            
+            let response = ConvertResponse::error(error_code);
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+
+/// POST /api/v1/import/openapi - Generate a sequence diagram from an OpenAPI document
+///
+/// The operations are listed even when the diagram cannot be generated, so
+/// the client can offer a subset; with `list_only` nothing is generated.
+pub async fn import_openapi(Json(payload): Json<OpenApiImportRequest>) -> Response {
+    let document = match OpenApiDocument::parse(&payload.spec) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::warn!("OpenAPI parse failed: {}", e);
+            let response = OpenApiImportResponse {
+                result: ProcessResult::new(e.to_error_code()),
+                operations: Vec::new(),
+                plantuml_text: None,
+            };
+            return (StatusCode::OK, Json(response)).into_response();
+        }
+    };
+    
+    let operations = document.operations();
+    if payload.list_only {
+        let response = OpenApiImportResponse {
+            result: ProcessResult::new(ErrorCode::ImportOk),
+            operations,
+            plantuml_text: None,
+        };
+        return (StatusCode::OK, Json(response)).into_response();
+    }
+    let response = match document.to_sequence_diagram(&payload.operations) {
+        Ok(plantuml_text) => {
+            tracing::info!("OpenAPI import successful: {} operations", operations.len());
+            OpenApiImportResponse {
+                result: ProcessResult::new(ErrorCode::ImportOk),
+                operations,
+                plantuml_text: Some(plantuml_text),
+            }
+        }
+        Err(e) => {
+            tracing::warn!("OpenAPI diagram generation failed: {}", e);
+            OpenApiImportResponse {
+                result: ProcessResult::new(e.to_error_code()),
+                operations,
+                plantuml_text: None,
+            }
+        }
+    };
+    
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /api/v1/import/url - Recover the source of a PlantUML server image URL
+pub async fn import_diagram_url(Json(payload): Json<DiagramUrlImportRequest>) -> Response {
+    let response = match source_from_url(&payload.url) {
+        Ok(plantuml_text) => DiagramUrlImportResponse {
+            result: ProcessResult::new(ErrorCode::ImportOk),
+            plantuml_text: Some(plantuml_text),
+        },
+        Err(code) => {
+            tracing::warn!("Diagram URL import failed: {:?}", code);
+            DiagramUrlImportResponse {
+                result: ProcessResult::new(code),
+                plantuml_text: None,
+            }
+        }
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Response format of /api/v1/diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticsFormat {
+    /// DiagnosticsResponse with LSP-style diagnostics
+    #[default]
+    Json,
+    /// SARIF 2.1.0 log for code scanning UIs
+    Sarif,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiagnosticsQuery {
+    #[serde(default)]
+    pub format: DiagnosticsFormat,
+    /// Artifact URI in the SARIF log (e.g. the file's path in the repository)
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// Artifact URI for SARIF when the request does not name the file
+const DEFAULT_SARIF_PATH: &str = "diagram.puml";
+
+/// POST /api/v1/diagnostics - Problems in PlantUML text for external editors
+///
+/// Positions follow the Language Server Protocol (zero-based lines, UTF-16
+/// offsets) so an editor extension can pass them straight to its diagnostics
+/// API. The PlantUML server is only asked when the static checks found no
+/// errors; when it cannot be reached the static results are still returned.
+/// With `?format=sarif` the same findings are returned as a SARIF log.
+pub async fn diagnostics(
+    State(state): State<AppState>,
+    Query(query): Query<DiagnosticsQuery>,
+    Json(payload): Json<DiagnosticsRequest>,
+) -> Response {
+    let text = &payload.plantuml_text;
+    let mut found = diagnostics::lint(text);
+    
+    let has_errors = found.iter().any(|d| d.severity == DiagnosticSeverity::Error);
+    if payload.check_syntax && !has_errors {
+        match state.renderer.check_syntax(text).await {
+            Ok(Some(error)) => found.push(diagnostics::syntax_error(text, error.line, &error.message)),
+            Ok(None) => {}
+            Err(RenderError::Maintenance) => {
+                found.push(skipped_check(text, "メンテナンス中のため、構文チェックを省略しました"));
+            }
+            Err(e) => {
+                tracing::warn!("Syntax check skipped: {}", e);
+                found.push(skipped_check(
+                    text,
+                    "PlantUMLサーバーに接続できないため、構文チェックを省略しました",
+                ));
+            }
+        }
+    }
+    
+    if query.format == DiagnosticsFormat::Sarif {
+        let artifact = SarifArtifact {
+            uri: query.path.as_deref().unwrap_or(DEFAULT_SARIF_PATH),
+            diagnostics: &found,
+        };
+        return (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, SARIF_CONTENT_TYPE)],
+            Json(sarif_log(&[artifact])),
+        )
+            .into_response();
+    }
+    let response = DiagnosticsResponse {
+        result: ProcessResult::new(diagnostics::summary(&found)),
+        diagnostics: found,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /api/v1/analyze - Structure statistics of a source, without rendering
+pub async fn analyze(Json(payload): Json<AnalyzeRequest>) -> Response {
+    let response = match validate_plantuml_content(&payload.plantuml_text) {
+        Ok(()) => AnalyzeResponse {
+            result: ProcessResult::new(ErrorCode::DiagramAnalyzed),
+            analysis: Some(stats::analyze(&payload.plantuml_text)),
+        },
+        Err(e) => AnalyzeResponse {
+            result: ProcessResult::new(e.to_error_code()),
+            analysis: None,
+        },
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Note that the PlantUML syntax check could not run
+fn skipped_check(text: &str, message: &str) -> Diagnostic {
+    line_diagnostic(
+        text,
+        0,
+        DiagnosticSeverity::Information,
+        "syntax-check-skipped",
+        message.to_string(),
+    )
+}
+
+/// Query (GET) or form (POST) parameters for /api/v1/export/download
+#[derive(Debug, Deserialize)]
+pub struct ExportDownloadParams {
+    pub plantuml_text: String,
+    #[serde(default)]
+    pub format: Option<ImageFormat>,
+    /// File name without extension (defaults to the diagram title, then "diagram")
+    #[serde(default)]
+    pub filename: Option<String>,
+    /// Resolution preset ("1x", "2x" or "4x")
+    #[serde(default)]
+    pub scale: Option<ExportScale>,
+    /// Font family, one of GET /api/v1/fonts
+    #[serde(default)]
+    pub font: Option<String>,
+    /// Strip run-dependent metadata ("true" or "false")
+    #[serde(default)]
+    pub deterministic: bool,
+    /// Embed the source in the image ("true" or "false")
+    #[serde(default)]
+    pub embed_source: bool,
+    /// Post-processors to run, comma-separated (see GET /api/v1/export/pipeline)
+    #[serde(default)]
+    pub pipeline: Option<String>,
+}
+
+/// GET/POST /api/v1/export/download - Export as a file attachment
+///
+/// Returns the image bytes with Content-Disposition: attachment so browsers can
+/// download straight from a link or form submission.
+pub async fn export_download(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(params): Form<ExportDownloadParams>,
+) -> Response {
+    let format = params.format.unwrap_or(ImageFormat::Png);
+    let request = ConvertRequest {
+        plantuml_text: params.plantuml_text,
+        format,
+        scale: params.scale.unwrap_or_default(),
+        font: params.font,
+        deterministic: params.deterministic,
+        embed_source: params.embed_source,
+        pipeline: params.pipeline.as_deref().map(pipeline::split_names),
+    };
+    if let Err(e) = request.validate() {
+        tracing::warn!("Download validation failed: {}", e);
+        let response = ConvertResponse::error(e.to_error_code());
+        return (StatusCode::OK, Json(response)).into_response();
+    }
+    let font = match allowed_font(&state, request.font.as_deref()) {
+        Ok(font) => font,
+        Err(code) => return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response(),
+    };
+    let pipeline = match ExportPipeline::for_request(&state.config.current().export_pipeline, &request) {
+        Ok(pipeline) => pipeline,
+        Err(code) => return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response(),
+    };
+    
+    let document_id = state.config.current().id_strategy.id_for(&request.plantuml_text);
+    let plantuml_text = apply_font(&request.plantuml_text, font);
+    // An attachment cannot carry a warning, so only rejections are reported
+    let user = caller(&headers);
+    let plantuml_text = match state.renderer.enforce_policy(&plantuml_text, user) {
+        Ok((plantuml_text, _)) => plantuml_text,
+        Err(code) => {
+            tracing::warn!("Download rejected by the style policy: {:?}", code);
+            return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response();
+        }
+    };
+    let plantuml_text = apply_scale(&plantuml_text, request.scale);
+    let result = state
+        .renderer
+        .render(document_id, &plantuml_text, format, user)
+        .await;
+    
+    match result {
+        Ok(image) => {
+            // Skipped steps are not reported either
+            let output = pipeline.run(image.data, &request, state.clock.now());
+            let requested = params.filename.as_deref().or(output.file_stem.as_deref());
+            let disposition = format!(
+                "attachment; filename=\"{}\"",
+                download_file_name(requested, &request.plantuml_text, format)
+            );
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, content_type(format).to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                output.data,
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("PlantUML download failed: {}", e);
+            let error_code = e.to_error_code(|_| ErrorCode::ExportError {
+                format: format_name(format).to_string(),
+            });
+            let response = ConvertResponse::error(error_code);
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// ASCII-only attachment file name with the format's extension
+///
+/// Without a requested name, the diagram's title directive is used when it has
+/// ASCII characters, then "diagram".
+fn download_file_name(requested: Option<&str>, plantuml_text: &str, format: ImageFormat) -> String {
+    let title_stem = extract_title(plantuml_text).and_then(|title| file_stem_from_title(&title));
+    let stem = requested.or(title_stem.as_deref()).unwrap_or("diagram");
+    let path = image_path(stem, format);
+    let file_name = path.rsplit('/').next().unwrap_or(&path);
+    let sanitized: String = file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
             } else {
-                let format_str = match payload.format {
-                    plantuml_editor_core::ImageFormat::Png => "PNG",
-                    plantuml_editor_core::ImageFormat::Svg => "SVG",
-                };
-                ErrorCode::ExportError {
-                    format: format_str.to_string(),
+                '_'
+            }
+        })
+        .collect();
+    if sanitized.starts_with('.') {
+        image_path("diagram", format)
+    } else {
+        sanitized
+    }
+}
+
+/// POST /api/v1/export/archive - Render uploaded PlantUML files into a ZIP archive
+///
+/// Multipart fields: `format` ("png", "svg" or "pdf", default "png") and one file part
+/// per PlantUML source. The archive contains one image per successfully rendered
+/// file and a manifest.json with the result of every file.
+pub async fn export_archive(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    let mut format = ImageFormat::Png;
+    let mut sources: Vec<(String, Vec<u8>)> = Vec::new();
+    
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("Archive upload could not be read: {}", e);
+                let error_code = multipart_error_code(&state, e);
+                let response = ConvertResponse::error(error_code);
+                return (StatusCode::OK, Json(response)).into_response();
+            }
+        };
+        
+        if field.name() == Some("format") {
+            let value = field.text().await.unwrap_or_default();
+            format = match value.trim().to_ascii_lowercase().as_str() {
+                "png" => ImageFormat::Png,
+                "svg" => ImageFormat::Svg,
+                "pdf" => ImageFormat::Pdf,
+                _ => {
+                    let response = ConvertResponse::error(ErrorCode::ExportError { format: value });
+                    return (StatusCode::OK, Json(response)).into_response();
                 }
             };
-            
+            continue;
+        }
+        
+        // Only file parts are treated as PlantUML sources
+        let Some(file_name) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+        if sources.len() >= MAX_ARCHIVE_FILES {
+            let error_code = ErrorCode::ArchiveFileLimit {
+                max_files: MAX_ARCHIVE_FILES,
+            };
+            let response = ConvertResponse::error(error_code);
+            return (StatusCode::OK, Json(response)).into_response();
+        }
+        match field.bytes().await {
+            Ok(data) => sources.push((file_name, data.to_vec())),
+            Err(e) => {
+                tracing::warn!("Archive upload could not be read: {}", e);
+                let error_code = multipart_error_code(&state, e);
+                let response = ConvertResponse::error(error_code);
+                return (StatusCode::OK, Json(response)).into_response();
+            }
+        }
+    }
+    
+    if sources.is_empty() {
+        let response = ConvertResponse::error(ErrorCode::ValidationEmpty);
+        return (StatusCode::OK, Json(response)).into_response();
+    }
+    
+    let mut writer = ArchiveWriter::new(format);
+    
+    for (file_name, data) in sources {
+        let rendered = match String::from_utf8(data) {
+            Ok(plantuml_text) => {
+                let user = caller(&headers);
+                state.renderer.render_source(&plantuml_text, format, user).await
+            }
+            Err(_) => Err(ErrorCode::EncodingError {
+                encoding: "UTF-8".to_string(),
+            }),
+        };
+        let added = match rendered {
+            Ok(image) => writer.add_image(&file_name, &image),
+            Err(code) => {
+                writer.add_error(&file_name, code);
+                Ok(())
+            }
+        };
+        if let Err(e) = added {
+            tracing::error!("Failed to write archive entry: {}", e);
+            let error_code = ErrorCode::ExportError {
+                format: "ZIP".to_string(),
+            };
+            let response = ConvertResponse::error(error_code);
+            return (StatusCode::OK, Json(response)).into_response();
+        }
+    }
+    
+    match writer.finish() {
+        Ok(archive) => {
+            tracing::info!("Archive export successful: {} bytes", archive.len());
+            (
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "application/zip"),
+                    (header::CONTENT_DISPOSITION, "attachment; filename=\"diagrams.zip\""),
+                ],
+                archive,
+            )
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Failed to finish archive: {}", e);
+            let error_code = ErrorCode::ExportError {
+                format: "ZIP".to_string(),
+            };
             let response = ConvertResponse::error(error_code);
             (StatusCode::OK, Json(response)).into_response()
         }
     }
 }
 
+
+/// Parse a document ID path segment (invalid IDs are reported as not found)
+fn multipart_error_code(state: &AppState, error: MultipartError) -> ErrorCode {
+    if error.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        ErrorCode::RequestTooLarge {
+            max_bytes: state.config.current().body_limits.bulk,
+        }
+    } else {
+        ErrorCode::ServerError {
+            message: error.body_text(),
+        }
+    }
+}
+
+fn parse_document_id(id: &str) -> Result<DocumentId, ErrorCode> {
+    uuid::Uuid::parse_str(id)
+        .map(DocumentId)
+        .map_err(|_| ErrorCode::DocumentNotFound { id: id.to_string() })
+}
+
+fn document_response(code: ErrorCode, document: Option<PlantUMLDocument>) -> Response {
+    let response = DocumentResponse {
+        result: ProcessResult::new(code),
+        document,
+        role: None,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/documents - List the server-side documents the caller can view
+pub async fn list_documents(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let response = DocumentListResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        documents: state.documents.list_visible(caller(&headers)),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Query parameters for GET /api/v1/documents/search
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+/// GET /api/v1/documents/search?q= - Full-text search over titles and content
+pub async fn search_documents(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    let response = SearchResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        hits: state.documents.search(&query.q, caller(&headers)),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /api/v1/documents - Store a new server-side document
+///
+/// An identified caller becomes the owner; anonymous documents are open to everyone.
+pub async fn create_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DocumentRequest>,
+) -> Response {
+    match state
+        .documents
+        .create(payload.content, payload.title, caller(&headers))
+    {
+        Ok(document) => {
+            tracing::info!("Document created: {}", document.id.0);
+            record_activity(&state, ActivityKind::Created, &document, &headers);
+            state.webhooks.notify(WebhookEvent::DocumentCreated, document.clone());
+            document_response(ErrorCode::DocumentSaved, Some(document))
+        }
+        Err(e) => {
+            tracing::warn!("Document validation failed: {}", e);
+            document_response(e.to_error_code(), None)
+        }
+    }
+}
+
+/// GET /api/v1/documents/:id - Get a server-side document and the caller's role
+pub async fn get_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Extension(role): Extension<DocumentRole>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    match state.documents.get(&id) {
+        Some(document) => {
+            let response = DocumentResponse {
+                result: ProcessResult::new(ErrorCode::DocumentLoaded),
+                document: Some(document),
+                role: Some(role),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        None => document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None),
+    }
+}
+
+/// PUT /api/v1/documents/:id - Replace a server-side document
+///
+/// When the request carries `base_version`, a document saved by someone else
+/// in the meantime is left untouched and DocumentConflict is returned.
+pub async fn update_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<DocumentRequest>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    let updated = state
+        .documents
+        .update(&id, payload.content, payload.title, payload.base_version);
+    updated_response(&state, &headers, id, updated)
+}
+
+/// PATCH /api/v1/documents/:id - Save an edit sent as a line patch
+///
+/// The patch is applied to the stored text of `base_version`; a document
+/// saved by someone else in the meantime is left untouched and
+/// DocumentConflict is returned, as for PUT.
+pub async fn patch_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<DocumentPatchRequest>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    let Some(document) = state.documents.get(&id) else {
+        return document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None);
+    };
+    let content = match apply_patch(&document.content, &payload.patch) {
+        Some(content) if document.version == payload.base_version => content,
+        _ => {
+            let conflict = UpdateError::Conflict {
+                current_version: document.version,
+            };
+            return updated_response(&state, &headers, id, Err(conflict));
+        }
+    };
+    let updated = state
+        .documents
+        .update(&id, content, payload.title, Some(payload.base_version));
+    updated_response(&state, &headers, id, updated)
+}
+
+/// Response to PUT or PATCH of a document
+fn updated_response(
+    state: &AppState,
+    headers: &HeaderMap,
+    id: DocumentId,
+    updated: Result<Option<PlantUMLDocument>, UpdateError>,
+) -> Response {
+    match updated {
+        Ok(Some(document)) => {
+            tracing::info!("Document updated: {}", document.id.0);
+            record_activity(state, ActivityKind::Updated, &document, headers);
+            state.webhooks.notify(WebhookEvent::DocumentUpdated, document.clone());
+            document_response(ErrorCode::DocumentSaved, Some(document))
+        }
+        Ok(None) => document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None),
+        Err(UpdateError::Invalid(e)) => {
+            tracing::warn!("Document validation failed: {}", e);
+            document_response(e.to_error_code(), None)
+        }
+        Err(conflict) => {
+            tracing::info!("Document {} changed since it was loaded", id.0);
+            document_response(conflict.to_error_code(), None)
+        }
+    }
+}
+
+/// POST /api/v1/documents/:id/duplicate - Copy a server-side document under a new ID
+///
+/// The copy is owned by the caller, so viewers can branch off a shared diagram.
+pub async fn duplicate_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    match state.documents.duplicate(&id, caller(&headers)) {
+        Some(document) => {
+            tracing::info!("Document {} duplicated as {}", id.0, document.id.0);
+            record_activity(&state, ActivityKind::Created, &document, &headers);
+            state.webhooks.notify(WebhookEvent::DocumentCreated, document.clone());
+            document_response(ErrorCode::DocumentSaved, Some(document))
+        }
+        None => document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None),
+    }
+}
+
+/// DELETE /api/v1/documents/:id - Delete a server-side document
+pub async fn delete_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    // Recorded first, while the document's members are still known
+    let Some(document) = state.documents.get(&id) else {
+        return document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None);
+    };
+    record_activity(&state, ActivityKind::Deleted, &document, &headers);
+    state.documents.delete(&id);
+    tracing::info!("Document deleted: {}", id.0);
+    document_response(ErrorCode::DocumentDeleted, None)
+}
+
+fn record_activity(
+    state: &AppState,
+    kind: ActivityKind,
+    document: &PlantUMLDocument,
+    headers: &HeaderMap,
+) {
+    let roles = state.documents.roles(&document.id);
+    state.activity.record(kind, document, caller(headers), &roles);
+}
+
+/// Query parameters for GET /api/v1/activity
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// GET /api/v1/activity - Recent changes to the documents the caller can see
+pub async fn activity(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ActivityQuery>,
+) -> Response {
+    let limit = query.limit.unwrap_or(DEFAULT_ACTIVITY_LIMIT);
+    let response = ActivityResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        entries: state.activity.recent(caller(&headers), limit),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// PUT /api/v1/documents/:id/folder - Move a server-side document into a folder
+pub async fn move_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<MoveDocumentRequest>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) => id,
+        Err(code) => return document_response(code, None),
+    };
+    if let Some(folder) = payload.folder.filter(|folder| !state.folders.contains(folder)) {
+        return document_response(ErrorCode::FolderNotFound { id: folder.0.to_string() }, None);
+    }
+    match state.documents.move_to(&id, payload.folder) {
+        Some(document) => document_response(ErrorCode::DocumentSaved, Some(document)),
+        None => document_response(ErrorCode::DocumentNotFound { id: id.0.to_string() }, None),
+    }
+}
+
+fn folder_response(code: ErrorCode, folder: Option<Folder>) -> Response {
+    let response = FolderResponse {
+        result: ProcessResult::new(code),
+        folder,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+fn include_response(code: ErrorCode, include: Option<IncludeFile>) -> Response {
+    let response = IncludeResponse {
+        result: ProcessResult::new(code),
+        include,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/includes - List shared include files and the caller's own
+pub async fn list_includes(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let response = IncludeListResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        includes: state.includes.list(caller(&headers)),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// PUT /api/v1/includes/:name - Upload an include file private to the caller
+///
+/// Shared files are written through the admin API; `shared` is rejected with
+/// AdminAccessDenied and anonymous uploads with AuthenticationRequired.
+pub async fn save_include(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(payload): Json<IncludeRequest>,
+) -> Response {
+    let owner = match include_owner(&headers, payload.shared) {
+        Ok(owner) => owner,
+        Err(code) => return include_response(code, None),
+    };
+    store_include(&state, &name, payload.content, Some(owner))
+}
+
+/// PUT /api/v1/admin/includes/:name - Upload a file shared by every user
+pub async fn save_shared_include(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(payload): Json<IncludeRequest>,
+) -> Response {
+    store_include(&state, &name, payload.content, None)
+}
+
+fn store_include(state: &AppState, name: &str, content: String, owner: Option<&str>) -> Response {
+    match state.includes.save(name, content, owner) {
+        Ok(include) => {
+            tracing::info!("Include file saved: {} ({} bytes)", include.name, include.size);
+            include_response(ErrorCode::IncludeSaved, Some(include))
+        }
+        Err(e) => include_response(e.to_error_code(), None),
+    }
+}
+
+/// The caller, who may only write their own files through the user endpoints
+fn include_owner(headers: &HeaderMap, shared: bool) -> Result<&str, ErrorCode> {
+    if shared {
+        tracing::warn!("Shared include write outside the admin API denied");
+        return Err(ErrorCode::AdminAccessDenied);
+    }
+    caller(headers).ok_or(ErrorCode::AuthenticationRequired)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncludeScope {
+    /// Remove the shared file instead of the caller's own (admin API only)
+    #[serde(default)]
+    pub shared: bool,
+}
+
+/// DELETE /api/v1/includes/:name - Remove one of the caller's include files
+pub async fn delete_include(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Query(scope): Query<IncludeScope>,
+) -> Response {
+    match include_owner(&headers, scope.shared) {
+        Ok(owner) => remove_include(&state, &name, Some(owner)),
+        Err(code) => include_response(code, None),
+    }
+}
+
+/// DELETE /api/v1/admin/includes/:name - Remove a shared include file
+pub async fn delete_shared_include(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    remove_include(&state, &name, None)
+}
+
+fn remove_include(state: &AppState, name: &str, owner: Option<&str>) -> Response {
+    match state.includes.delete(name, owner) {
+        Ok(()) => include_response(ErrorCode::IncludeDeleted, None),
+        Err(e) => include_response(e.to_error_code(), None),
+    }
+}
+
+fn settings_response(code: ErrorCode, settings: Option<SettingsSnapshot>) -> Response {
+    let response = SettingsResponse {
+        result: ProcessResult::new(code),
+        settings,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/settings - The caller's editor settings
+///
+/// Settings belong to a user, so anonymous callers get AuthenticationRequired.
+pub async fn get_settings(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    match caller(&headers) {
+        Some(user) => settings_response(ErrorCode::DocumentLoaded, state.settings.get(user)),
+        None => settings_response(ErrorCode::AuthenticationRequired, None),
+    }
+}
+
+/// PUT /api/v1/settings - Save the caller's editor settings
+///
+/// Settings older than the stored ones (by `updated_at`) are not saved; the
+/// response carries the settings in effect either way.
+pub async fn put_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SettingsSnapshot>,
+) -> Response {
+    let Some(user) = caller(&headers) else {
+        return settings_response(ErrorCode::AuthenticationRequired, None);
+    };
+    let settings = state.settings.put(user, payload);
+    settings_response(ErrorCode::SettingsSaved, Some(settings))
+}
+
+/// GET /api/v1/folders - List all folders
+pub async fn list_folders(State(state): State<AppState>) -> Response {
+    let response = FolderListResponse {
+        result: ProcessResult::new(ErrorCode::DocumentLoaded),
+        folders: state.folders.list(),
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /api/v1/folders - Create a folder
+pub async fn create_folder(
+    State(state): State<AppState>,
+    Json(payload): Json<FolderRequest>,
+) -> Response {
+    match state.folders.create(&payload.name, payload.parent) {
+        Ok(folder) => {
+            tracing::info!("Folder created: {}", folder.id.0);
+            folder_response(ErrorCode::FolderSaved, Some(folder))
+        }
+        Err(e) => folder_response(e.to_error_code(), None),
+    }
+}
+
+/// PUT /api/v1/folders/:id - Rename a folder or move it into another folder
+pub async fn update_folder(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(payload): Json<FolderRequest>,
+) -> Response {
+    let Ok(uuid) = uuid::Uuid::parse_str(&id) else {
+        return folder_response(ErrorCode::FolderNotFound { id }, None);
+    };
+    match state
+        .folders
+        .update(&FolderId(uuid), &payload.name, payload.parent)
+    {
+        Ok(folder) => folder_response(ErrorCode::FolderSaved, Some(folder)),
+        Err(e) => folder_response(e.to_error_code(), None),
+    }
+}
+
+fn roles_response(code: ErrorCode, roles: Roles) -> Response {
+    let response = RolesResponse {
+        result: ProcessResult::new(code),
+        roles,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// GET /api/v1/documents/:id/roles - Members of a server-side document
+pub async fn get_roles(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match parse_document_id(&id) {
+        Ok(id) if state.documents.get(&id).is_some() => {
+            roles_response(ErrorCode::DocumentLoaded, state.documents.roles(&id))
+        }
+        _ => roles_response(ErrorCode::DocumentNotFound { id }, Default::default()),
+    }
+}
+
+/// PUT /api/v1/documents/:id/roles - Grant or remove a user's role
+pub async fn set_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Json(payload): Json<RoleRequest>,
+) -> Response {
+    let id = match parse_document_id(&id) {
+        Ok(id) if state.documents.get(&id).is_some() => id,
+        _ => return roles_response(ErrorCode::DocumentNotFound { id }, Default::default()),
+    };
+    let user = payload.user.trim().to_string();
+    match state
+        .documents
+        .set_role(&id, caller(&headers), user, payload.role)
+    {
+        Some(roles) => {
+            tracing::info!("Roles of document {} updated", id.0);
+            roles_response(ErrorCode::DocumentSaved, roles)
+        }
+        None => roles_response(ErrorCode::DocumentOwnerRequired, state.documents.roles(&id)),
+    }
+}
+
+/// Query parameters for GET /api/v1/documents/:id/image
+#[derive(Debug, Deserialize)]
+pub struct DocumentImageQuery {
+    #[serde(default)]
+    pub format: Option<ImageFormat>,
+}
+
+/// GET /api/v1/documents/:id/image - Render a server-side document
+///
+/// Returns the image itself so wikis and chat tools can embed the URL directly.
+pub async fn document_image(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DocumentImageQuery>,
+) -> Response {
+    let document = match parse_document_id(&id).map(|id| state.documents.get(&id)) {
+        Ok(Some(document)) => document,
+        Ok(None) | Err(_) => {
+            let response = ConvertResponse::error(ErrorCode::DocumentNotFound { id });
+            return (StatusCode::OK, Json(response)).into_response();
+        }
+    };
+    
+    let format = query.format.unwrap_or(ImageFormat::Png);
+    let result = state
+        .renderer
+        .render(document.id, &document.content, format, None)
+        .await;
+    
+    match result {
+        Ok(image) => {
+            (StatusCode::OK, [(header::CONTENT_TYPE, content_type(format))], image.data)
+                .into_response()
+        }
+        Err(e) => {
+            tracing::error!("Document render failed: {}", e);
+            let error_code = e.to_error_code(|_| ErrorCode::ParseError { line: None });
+            let response = ConvertResponse::error(error_code);
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// GET /api/v1/documents/:id/archive - Export a document for another deployment
+///
+/// The ZIP holds document.json (the document with metadata and every earlier
+/// version) and the current version rendered as PNG and SVG. Formats that fail
+/// to render are left out; the source is enough to import the document.
+pub async fn export_document_archive(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let document = match parse_document_id(&id).map(|id| state.documents.get(&id)) {
+        Ok(Some(document)) => document,
+        Ok(None) | Err(_) => return document_response(ErrorCode::DocumentNotFound { id }, None),
+    };
+
+    let mut images = Vec::new();
+    for format in [ImageFormat::Png, ImageFormat::Svg] {
+        match state.renderer.render(document.id, &document.content, format, None).await {
+            Ok(image) => images.push((image_path("diagram", format), image.data)),
+            Err(e) => tracing::warn!("Archive image could not be rendered: {}", e),
+        }
+    }
+
+    let stem = document
+        .title
+        .as_deref()
+        .and_then(file_stem_from_title)
+        .unwrap_or_else(|| "document".to_string());
+    let revisions = state.documents.revisions(&document.id);
+    match write_document_archive(document, revisions, images) {
+        Ok(archive) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}.zip\"", stem),
+                ),
+            ],
+            archive,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to write document archive: {}", e);
+            let code = ErrorCode::ExportError {
+                format: "ZIP".to_string(),
+            };
+            document_response(code, None)
+        }
+    }
+}
+
+/// POST /api/v1/documents/import - Store a document from an exported archive
+///
+/// The body is the ZIP from GET /api/v1/documents/:id/archive. The caller
+/// becomes the owner of the imported document.
+pub async fn import_document_archive(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let archive = match read_document_archive(&body) {
+        Ok(archive) => archive,
+        Err(e) => {
+            tracing::warn!("Document archive could not be read: {:?}", e);
+            return document_response(e.to_error_code(), None);
+        }
+    };
+    match state
+        .documents
+        .import(archive.document, archive.revisions, caller(&headers))
+    {
+        Ok(document) => {
+            tracing::info!("Document imported: {}", document.id.0);
+            record_activity(&state, ActivityKind::Created, &document, &headers);
+            state.webhooks.notify(WebhookEvent::DocumentCreated, document.clone());
+            document_response(ErrorCode::DocumentSaved, Some(document))
+        }
+        Err(e) => {
+            tracing::warn!("Document validation failed: {}", e);
+            document_response(e.to_error_code(), None)
+        }
+    }
+}
+
+/// POST /api/v1/documents/:id/share - Create a public read-only link
+///
+/// The link renders the document as stored when it is opened, so later edits
+/// show up. The body may be empty for a link that never expires.
+pub async fn create_share_link(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: Option<Json<ShareRequest>>,
+) -> Response {
+    let share_response = |code: ErrorCode, link: Option<ShareLink>| {
+        let response = ShareResponse {
+            result: ProcessResult::new(code),
+            link,
+        };
+        (StatusCode::OK, Json(response)).into_response()
+    };
+
+    let id = match parse_document_id(&id) {
+        Ok(id) if state.documents.get(&id).is_some() => id,
+        Ok(_) | Err(_) => return share_response(ErrorCode::DocumentNotFound { id }, None),
+    };
+
+    let request = payload.map(|Json(request)| request).unwrap_or_default();
+    let expires_at = request.expires_in_secs.map(|secs| {
+        let secs = i64::try_from(secs).unwrap_or(i64::MAX);
+        state.clock.now().saturating_add(secs)
+    });
+    let token = state.share.create(id, expires_at);
+    let url = format!(
+        "{}/share/{}",
+        state.config.current().public_base_url.as_deref().unwrap_or_default(),
+        token
+    );
+    tracing::info!("Share link created for document {}", id.0);
+    share_response(
+        ErrorCode::ShareLinkCreated,
+        Some(ShareLink {
+            token,
+            url,
+            expires_at,
+        }),
+    )
+}
+
+/// GET /share/:token - Render a shared document
+///
+/// Read-only counterpart of GET /api/v1/documents/:id/image for holders of a
+/// share link.
+pub async fn shared_image(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<DocumentImageQuery>,
+) -> Response {
+    let id = match state.share.verify(&token, state.clock.now()) {
+        Ok((id, _)) => id,
+        Err(e) => {
+            let code = match e {
+                ShareError::Invalid => ErrorCode::ShareLinkInvalid,
+                ShareError::Expired(expired_at) => ErrorCode::ShareLinkExpired { expired_at },
+            };
+            tracing::warn!("Rejected share link: {:?}", e);
+            return (StatusCode::OK, Json(ConvertResponse::error(code))).into_response();
+        }
+    };
+    document_image(State(state), Path(id.0.to_string()), Query(query)).await
+}
+
+fn job_response(code: ErrorCode, job: Option<JobInfo>) -> Response {
+    let response = JobResponse {
+        result: ProcessResult::new(code),
+        job,
+    };
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// POST /api/v1/jobs/export - Queue an export of several sources
+///
+/// Rendering happens on the job worker pool. Poll GET /api/v1/jobs/:id or
+/// subscribe to GET /api/v1/jobs/:id/events, then download the ZIP from
+/// GET /api/v1/jobs/:id/result.
+pub async fn create_export_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BatchExportRequest>,
+) -> Response {
+    if payload.items.is_empty() {
+        return job_response(ErrorCode::ValidationEmpty, None);
+    }
+    if payload.items.len() > MAX_ARCHIVE_FILES {
+        let error_code = ErrorCode::ArchiveFileLimit {
+            max_files: MAX_ARCHIVE_FILES,
+        };
+        return job_response(error_code, None);
+    }
+    
+    let total = payload.items.len();
+    match state.jobs.submit_export(payload.items, payload.format, caller(&headers)) {
+        Ok(job) => {
+            tracing::info!("Export job {} queued: {} items", job.id, total);
+            job_response(ErrorCode::JobAccepted { total }, Some(job))
+        }
+        Err(error_code) => {
+            tracing::warn!("Export job rejected: {}", error_code.to_message());
+            job_response(error_code, None)
+        }
+    }
+}
+
+/// GET /api/v1/jobs/:id - Job status
+pub async fn get_job(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.jobs.status(&id) {
+        Some(job) => job_response(job.status_code(), Some(job)),
+        None => job_response(ErrorCode::JobNotFound { id }, None),
+    }
+}
+
+/// GET /api/v1/jobs/:id/events - Server-sent progress events
+pub async fn job_events(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Some(subscription) = state.progress.subscribe(&id) else {
+        return job_response(ErrorCode::JobNotFound { id }, None);
+    };
+    
+    let stream = subscription
+        .into_stream()
+        .map(|event| Event::default().json_data(event));
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// GET /api/v1/jobs/:id/result - Download the ZIP of a completed job
+pub async fn job_result(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    if let Some(archive) = state.jobs.archive(&id) {
+        return (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"diagrams.zip\""),
+            ],
+            archive,
+        )
+            .into_response();
+    }
+    match state.jobs.status(&id) {
+        Some(job) => job_response(job.status_code(), Some(job)),
+        None => job_response(ErrorCode::JobNotFound { id }, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_file_name() {
+        assert_eq!(download_file_name(None, "", ImageFormat::Png), "diagram.png");
+        assert_eq!(download_file_name(Some("sequence"), "", ImageFormat::Svg), "sequence.svg");
+        assert_eq!(download_file_name(Some("../docs/er.puml"), "", ImageFormat::Png), "er.png");
+        assert_eq!(download_file_name(Some("設計 図\""), "", ImageFormat::Png), "_____.png");
+        assert_eq!(download_file_name(Some(".."), "", ImageFormat::Png), "diagram.png");
+    }
+
+    #[test]
+    fn test_download_file_name_from_title() {
+        let source = "@startuml\ntitle Order flow v1.2\nA -> B\n@enduml";
+        assert_eq!(download_file_name(None, source, ImageFormat::Png), "Order_flow_v1_2.png");
+        assert_eq!(download_file_name(Some("mine"), source, ImageFormat::Png), "mine.png");
+
+        let source = "@startuml\ntitle 注文フロー\n@enduml";
+        assert_eq!(download_file_name(None, source, ImageFormat::Svg), "diagram.svg");
+    }
+}
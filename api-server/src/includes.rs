@@ -0,0 +1,386 @@
+// Include files uploaded to the server
+//
+// Teams keep shared style libraries (skinparams, procedures, sprites) as
+// `.iuml` / `.puml` files. A file is either shared by the whole deployment or
+// private to the user who uploaded it (`X-PlantUML-User`). Before a diagram is
+// sent to PlantUML, `!include <name>` lines naming a stored file are replaced
+// by its content; other `!include <...>` lines are left to PlantUML's standard
+// library. A user's own file takes precedence over a shared one of the same name.
+// Expansion stops with ValidationTextLimit once the lines it has gone through,
+// include lines among them, pass MAX_CONTENT_CHARS, so files including each
+// other many times cannot blow a request up.
+//
+// Only operators write shared files (through the admin API). A file may not
+// be longer than MAX_CONTENT_CHARS, each owner keeps at most
+// MAX_FILES_PER_OWNER files and the whole store at most MAX_STORE_BYTES.
+
+use plantuml_editor_core::clock::{SharedClock, SystemClock};
+use plantuml_editor_core::text::scalar_count;
+use plantuml_editor_core::{ErrorCode, IncludeFile, MAX_CONTENT_CHARS};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Extensions an include file name must end with
+pub const INCLUDE_EXTENSIONS: &[&str] = &[".iuml", ".puml"];
+
+/// Includes nested deeper than this are left to PlantUML
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Most files one user (or the shared scope) may keep
+pub const MAX_FILES_PER_OWNER: usize = 100;
+
+/// Most bytes of content the store keeps across every owner
+pub const MAX_STORE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Why an include file could not be stored or removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IncludeError {
+    InvalidName(String),
+    NotFound(String),
+    TooLarge { actual: usize },
+    FileLimit,
+    StorageFull,
+}
+
+impl IncludeError {
+    pub fn to_error_code(&self) -> ErrorCode {
+        match self {
+            IncludeError::InvalidName(name) => ErrorCode::IncludeNameInvalid { name: name.clone() },
+            IncludeError::NotFound(name) => ErrorCode::IncludeNotFound { name: name.clone() },
+            IncludeError::TooLarge { actual } => ErrorCode::ValidationTextLimit {
+                actual: *actual,
+                max: MAX_CONTENT_CHARS,
+            },
+            IncludeError::FileLimit => ErrorCode::IncludeFileLimit {
+                max_files: MAX_FILES_PER_OWNER,
+            },
+            IncludeError::StorageFull => ErrorCode::IncludeStorageFull {
+                max_bytes: MAX_STORE_BYTES,
+            },
+        }
+    }
+}
+
+struct StoredInclude {
+    content: String,
+    updated_at: i64,
+}
+
+/// Include files keyed by owner (None for shared files) and name, kept in
+/// memory for the lifetime of the process
+pub struct IncludeStore {
+    files: RwLock<HashMap<(Option<String>, String), StoredInclude>>,
+    clock: SharedClock,
+}
+
+impl Default for IncludeStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IncludeStore {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::shared())
+    }
+
+    /// Store taking its timestamps from `clock`
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            files: Default::default(),
+            clock,
+        }
+    }
+
+    /// Shared files and the files of `user`, sorted by name
+    pub fn list(&self, user: Option<&str>) -> Vec<IncludeFile> {
+        let files = self.files.read().unwrap();
+        let mut list: Vec<IncludeFile> = files
+            .iter()
+            .filter(|((owner, _), _)| owner.is_none() || owner.as_deref() == user)
+            .map(|((owner, name), file)| include_file(owner, name, file))
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name).then(a.owner.is_none().cmp(&b.owner.is_none())));
+        list
+    }
+
+    /// Store `content` as `name`, private to `owner` or shared when None
+    ///
+    /// Replacing an existing file is allowed at the file limit; the store's
+    /// size is counted without the content being replaced.
+    pub fn save(
+        &self,
+        name: &str,
+        content: String,
+        owner: Option<&str>,
+    ) -> Result<IncludeFile, IncludeError> {
+        if !is_valid_name(name) {
+            return Err(IncludeError::InvalidName(name.to_string()));
+        }
+        let actual = scalar_count(&content);
+        if actual > MAX_CONTENT_CHARS {
+            return Err(IncludeError::TooLarge { actual });
+        }
+        let key = (owner.map(str::to_string), name.to_string());
+        let mut files = self.files.write().unwrap();
+        let replaced = files.get(&key).map(|file| file.content.len());
+        if replaced.is_none() && files.keys().filter(|(o, _)| *o == key.0).count() >= MAX_FILES_PER_OWNER {
+            return Err(IncludeError::FileLimit);
+        }
+        let stored_bytes: usize = files.values().map(|file| file.content.len()).sum();
+        if stored_bytes - replaced.unwrap_or(0) + content.len() > MAX_STORE_BYTES {
+            return Err(IncludeError::StorageFull);
+        }
+        let file = StoredInclude {
+            content,
+            updated_at: self.clock.now(),
+        };
+        let stored = include_file(&key.0, &key.1, &file);
+        files.insert(key, file);
+        Ok(stored)
+    }
+
+    pub fn delete(&self, name: &str, owner: Option<&str>) -> Result<(), IncludeError> {
+        let key = (owner.map(str::to_string), name.to_string());
+        match self.files.write().unwrap().remove(&key) {
+            Some(_) => Ok(()),
+            None => Err(IncludeError::NotFound(name.to_string())),
+        }
+    }
+
+    /// `plantuml_text` with the stored files it includes expanded, as seen by `user`
+    ///
+    /// Fails with ValidationTextLimit when the expansion gets longer than
+    /// MAX_CONTENT_CHARS.
+    pub fn expand<'a>(&self, plantuml_text: &'a str, user: Option<&str>) -> Result<Cow<'a, str>, ErrorCode> {
+        if !plantuml_text.contains("!include") || self.files.read().unwrap().is_empty() {
+            return Ok(Cow::Borrowed(plantuml_text));
+        }
+        let mut out = String::with_capacity(plantuml_text.len());
+        let mut stack = Vec::new();
+        let mut chars = 0;
+        if !self.expand_into(plantuml_text, false, user, &mut stack, &mut chars, &mut out)? {
+            return Ok(Cow::Borrowed(plantuml_text));
+        }
+        // Keep the trailing newline (or its absence) of the source
+        if !plantuml_text.ends_with('\n') {
+            out.pop();
+        }
+        Ok(Cow::Owned(out))
+    }
+
+    /// Append `text` with its includes expanded to `out`; false when nothing was expanded
+    ///
+    /// `included` drops the `@start...`/`@end...` lines of an included file.
+    /// `chars` counts the characters of every line gone through so far.
+    fn expand_into(
+        &self,
+        text: &str,
+        included: bool,
+        user: Option<&str>,
+        stack: &mut Vec<String>,
+        chars: &mut usize,
+        out: &mut String,
+    ) -> Result<bool, ErrorCode> {
+        let mut expanded = false;
+        for line in text.lines() {
+            if included {
+                let trimmed = line.trim_start();
+                if trimmed.starts_with("@start") || trimmed.starts_with("@end") {
+                    continue;
+                }
+            }
+            *chars += scalar_count(line) + 1;
+            if *chars > MAX_CONTENT_CHARS {
+                return Err(ErrorCode::ValidationTextLimit {
+                    actual: *chars,
+                    max: MAX_CONTENT_CHARS,
+                });
+            }
+            let found = include_target(line)
+                .filter(|_| stack.len() < MAX_INCLUDE_DEPTH)
+                .and_then(|target| self.lookup(target, user));
+            match found {
+                // A file including itself (directly or not) is left to PlantUML
+                Some((name, content)) if !stack.contains(&name) => {
+                    stack.push(name);
+                    self.expand_into(&content, true, user, stack, chars, out)?;
+                    stack.pop();
+                    expanded = true;
+                }
+                _ => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// Name and content of the file `target` refers to, with or without extension
+    fn lookup(&self, target: &str, user: Option<&str>) -> Option<(String, String)> {
+        let files = self.files.read().unwrap();
+        let mut names = vec![target.to_string()];
+        names.extend(INCLUDE_EXTENSIONS.iter().map(|ext| format!("{}{}", target, ext)));
+        let mut owners = vec![None];
+        if let Some(user) = user {
+            owners.insert(0, Some(user.to_string()));
+        }
+        owners
+            .into_iter()
+            .flat_map(|owner| names.iter().map(move |name| (owner.clone(), name.clone())))
+            .find_map(|key| {
+                let file = files.get(&key)?;
+                Some((key.1, file.content.clone()))
+            })
+    }
+}
+
+fn include_file(owner: &Option<String>, name: &str, file: &StoredInclude) -> IncludeFile {
+    IncludeFile {
+        name: name.to_string(),
+        owner: owner.clone(),
+        size: file.content.len(),
+        updated_at: file.updated_at,
+    }
+}
+
+/// ASCII letters, digits, `-`, `_` and `.`, ending in an include extension
+pub fn is_valid_name(name: &str) -> bool {
+    !name.starts_with('.')
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && INCLUDE_EXTENSIONS
+            .iter()
+            .any(|ext| name.len() > ext.len() && name.ends_with(ext))
+}
+
+/// `name` of a `!include <name>` line
+fn include_target(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("!include")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    rest.trim().strip_prefix('<')?.strip_suffix('>')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_names() {
+        assert!(is_valid_name("company-style.iuml"));
+        assert!(is_valid_name("C4_Custom.v2.puml"));
+        assert!(!is_valid_name("style"));
+        assert!(!is_valid_name(".iuml"));
+        assert!(!is_valid_name("../style.iuml"));
+        assert!(!is_valid_name("スタイル.iuml"));
+    }
+
+    #[test]
+    fn test_expand_shared_and_private_files() {
+        let store = IncludeStore::new();
+        store
+            .save("style.iuml", "@startuml\nskinparam shadowing false\n@enduml".to_string(), None)
+            .unwrap();
+        store
+            .save("style.iuml", "skinparam monochrome true".to_string(), Some("alice"))
+            .unwrap();
+        store
+            .save("actors.puml", "!include <style>\nactor User".to_string(), None)
+            .unwrap();
+
+        let source = "@startuml\n!include <actors.puml>\n!include <tupadr3/devicons/git>\nUser -> A\n@enduml\n";
+        assert_eq!(
+            store.expand(source, None).unwrap(),
+            "@startuml\nskinparam shadowing false\nactor User\n!include <tupadr3/devicons/git>\nUser -> A\n@enduml\n"
+        );
+        assert_eq!(
+            store.expand("@startuml\n  !include <style>\n@enduml", Some("alice")).unwrap(),
+            "@startuml\nskinparam monochrome true\n@enduml"
+        );
+        assert!(matches!(store.expand("@startuml\n@enduml", None), Ok(Cow::Borrowed(_))));
+
+        let names: Vec<_> = store
+            .list(Some("alice"))
+            .into_iter()
+            .map(|file| (file.name, file.owner))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("actors.puml".to_string(), None),
+                ("style.iuml".to_string(), Some("alice".to_string())),
+                ("style.iuml".to_string(), None),
+            ]
+        );
+        assert_eq!(store.list(None).len(), 2);
+    }
+
+    #[test]
+    fn test_save_limits() {
+        let store = IncludeStore::new();
+        let too_long = "a".repeat(MAX_CONTENT_CHARS + 1);
+        assert_eq!(
+            store.save("long.iuml", too_long, None),
+            Err(IncludeError::TooLarge { actual: MAX_CONTENT_CHARS + 1 })
+        );
+
+        for i in 0..MAX_FILES_PER_OWNER {
+            store.save(&format!("f{}.iuml", i), "A".to_string(), Some("alice")).unwrap();
+        }
+        assert_eq!(
+            store.save("one-more.iuml", "A".to_string(), Some("alice")),
+            Err(IncludeError::FileLimit)
+        );
+        // Replacing a file and other owners are not held back
+        store.save("f0.iuml", "B".to_string(), Some("alice")).unwrap();
+        store.save("one-more.iuml", "A".to_string(), Some("bob")).unwrap();
+
+        let full = IncludeStore::new();
+        let content = "a".repeat(MAX_CONTENT_CHARS);
+        let fits = MAX_STORE_BYTES / MAX_CONTENT_CHARS;
+        for i in 0..fits {
+            full.save(&format!("f{}.iuml", i % 100), content.clone(), Some(&format!("user{}", i / 100)))
+                .unwrap();
+        }
+        assert_eq!(full.save("last.iuml", content, Some("carol")), Err(IncludeError::StorageFull));
+    }
+
+    #[test]
+    fn test_recursive_include_is_left_in_place() {
+        let store = IncludeStore::new();
+        store.save("a.iuml", "!include <b>\nA".to_string(), None).unwrap();
+        store.save("b.iuml", "!include <a>\nB".to_string(), None).unwrap();
+        assert_eq!(store.expand("!include <a>", None).unwrap(), "!include <a>\nB\nA");
+
+        assert_eq!(
+            store.delete("a.iuml", Some("alice")),
+            Err(IncludeError::NotFound("a.iuml".to_string()))
+        );
+        store.delete("a.iuml", None).unwrap();
+    }
+
+    #[test]
+    fn test_expansion_is_bounded() {
+        let store = IncludeStore::new();
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        for pair in names.windows(2) {
+            let content = format!("!include <{}>\n", pair[1]).repeat(1000);
+            store.save(&format!("{}.iuml", pair[0]), content, None).unwrap();
+        }
+        // The innermost file adds nothing, so only the include lines count
+        store.save("h.iuml", String::new(), None).unwrap();
+        assert!(matches!(
+            store.expand("!include <a>", None),
+            Err(ErrorCode::ValidationTextLimit { max: MAX_CONTENT_CHARS, .. })
+        ));
+
+        store.save("a.iuml", "!include <h>\nA".to_string(), None).unwrap();
+        assert_eq!(store.expand("!include <a>", None).unwrap(), "A");
+    }
+}
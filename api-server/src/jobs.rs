@@ -0,0 +1,316 @@
+// Background job queue for heavy exports
+//
+// Jobs are accepted into a bounded queue and rendered by a fixed pool of worker
+// tasks, so long exports never tie up request handlers. Progress is published
+// through the ProgressHub and finished archives are kept for JOB_RETENTION.
+
+use plantuml_editor_core::clock::SharedClock;
+use plantuml_editor_core::{
+    BatchExportItem, ErrorCode, ImageFormat, JobInfo, JobState, ProcessResult, ProgressEvent,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::archive::ArchiveWriter;
+use crate::progress::{ProgressHub, ProgressReporter};
+use crate::render::Renderer;
+
+/// How long finished jobs (status, archive and progress history) are kept
+pub const JOB_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+struct QueuedJob {
+    id: String,
+    format: ImageFormat,
+    items: Vec<BatchExportItem>,
+    /// User whose own include files are used
+    user: Option<String>,
+    reporter: ProgressReporter,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    archive: Option<Vec<u8>>,
+}
+
+/// Bounded job queue served by a pool of worker tasks
+#[derive(Clone)]
+pub struct JobQueue {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    jobs: Mutex<HashMap<String, JobEntry>>,
+    sender: mpsc::Sender<QueuedJob>,
+    receiver: tokio::sync::Mutex<mpsc::Receiver<QueuedJob>>,
+    capacity: usize,
+    renderer: Renderer,
+    progress: Arc<ProgressHub>,
+    clock: SharedClock,
+}
+
+impl JobQueue {
+    /// Create the queue and spawn `workers` worker tasks; job times are read from `clock`
+    pub fn start(
+        workers: usize,
+        capacity: usize,
+        renderer: Renderer,
+        progress: Arc<ProgressHub>,
+        clock: SharedClock,
+    ) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, receiver) = mpsc::channel(capacity);
+        let inner = Arc::new(Inner {
+            jobs: Mutex::new(HashMap::new()),
+            sender,
+            receiver: tokio::sync::Mutex::new(receiver),
+            capacity,
+            renderer,
+            progress,
+            clock,
+        });
+
+        for _ in 0..workers {
+            let inner = inner.clone();
+            tokio::spawn(async move {
+                loop {
+                    let job = inner.receiver.lock().await.recv().await;
+                    match job {
+                        Some(job) => inner.run(job).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+
+        Self { inner }
+    }
+
+    /// Enqueue an export job, rendered with `user`'s own include files
+    pub fn submit_export(
+        &self,
+        items: Vec<BatchExportItem>,
+        format: ImageFormat,
+        user: Option<&str>,
+    ) -> Result<JobInfo, ErrorCode> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let info = JobInfo {
+            id: id.clone(),
+            state: JobState::Queued,
+            total: items.len(),
+            succeeded: 0,
+            failed: 0,
+            created_at: self.inner.clock.now(),
+            finished_at: None,
+            error: None,
+        };
+
+        let reporter = self.inner.progress.create(&id);
+        for (index, item) in items.iter().enumerate() {
+            reporter.send(ProgressEvent::Queued {
+                index,
+                name: item.name.clone(),
+            });
+        }
+        self.inner.jobs.lock().unwrap().insert(
+            id.clone(),
+            JobEntry {
+                info: info.clone(),
+                archive: None,
+            },
+        );
+
+        let job = QueuedJob {
+            id: id.clone(),
+            format,
+            items,
+            user: user.map(str::to_string),
+            reporter,
+        };
+        if self.inner.sender.try_send(job).is_err() {
+            self.inner.jobs.lock().unwrap().remove(&id);
+            self.inner.progress.remove(&id);
+            return Err(ErrorCode::JobQueueFull {
+                capacity: self.inner.capacity,
+            });
+        }
+        Ok(info)
+    }
+
+    pub fn status(&self, id: &str) -> Option<JobInfo> {
+        let jobs = self.inner.jobs.lock().unwrap();
+        jobs.get(id).map(|entry| entry.info.clone())
+    }
+
+    /// Archive of a completed job
+    pub fn archive(&self, id: &str) -> Option<Vec<u8>> {
+        let jobs = self.inner.jobs.lock().unwrap();
+        jobs.get(id).and_then(|entry| entry.archive.clone())
+    }
+}
+
+impl Inner {
+    fn update(&self, id: &str, f: impl FnOnce(&mut JobEntry)) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(id) {
+            f(entry);
+        }
+    }
+
+    async fn run(self: &Arc<Self>, job: QueuedJob) {
+        self.update(&job.id, |entry| entry.info.state = JobState::Running);
+
+        match self.render_all(&job).await {
+            Ok(archive) => {
+                self.update(&job.id, |entry| {
+                    entry.info.state = JobState::Completed;
+                    entry.info.finished_at = Some(self.clock.now());
+                    entry.archive = Some(archive);
+                });
+            }
+            Err(code) => {
+                tracing::error!("Job {} failed: {}", job.id, code.to_message());
+                // Report every item that did not finish so the stream stays consistent
+                let finished = self
+                    .jobs
+                    .lock()
+                    .unwrap()
+                    .get(&job.id)
+                    .map(|entry| entry.info.succeeded + entry.info.failed)
+                    .unwrap_or(0);
+                for (index, item) in job.items.iter().enumerate().skip(finished) {
+                    job.reporter.send(ProgressEvent::Failed {
+                        index,
+                        name: item.name.clone(),
+                        result: ProcessResult::new(code.clone()),
+                    });
+                }
+                self.update(&job.id, |entry| {
+                    entry.info.state = JobState::Failed;
+                    entry.info.failed = entry.info.total - entry.info.succeeded;
+                    entry.info.finished_at = Some(self.clock.now());
+                    entry.info.error = Some(code.to_message());
+                });
+            }
+        }
+
+        if let Some(info) = self.jobs.lock().unwrap().get(&job.id).map(|e| e.info.clone()) {
+            tracing::info!(
+                "Job {} finished: {} ok, {} failed",
+                job.id,
+                info.succeeded,
+                info.failed
+            );
+            job.reporter.send(ProgressEvent::Completed {
+                succeeded: info.succeeded,
+                failed: info.failed,
+            });
+        }
+
+        let inner = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(JOB_RETENTION).await;
+            inner.jobs.lock().unwrap().remove(&job.id);
+            inner.progress.remove(&job.id);
+        });
+    }
+
+    /// Render every item into an archive, reporting progress per item
+    async fn render_all(&self, job: &QueuedJob) -> Result<Vec<u8>, ErrorCode> {
+        let zip_error = |_| ErrorCode::ExportError {
+            format: "ZIP".to_string(),
+        };
+
+        let mut writer = ArchiveWriter::new(job.format);
+        for (index, item) in job.items.iter().enumerate() {
+            let name = item.name.clone();
+            job.reporter.send(ProgressEvent::Rendering {
+                index,
+                name: name.clone(),
+            });
+
+            let user = job.user.as_deref();
+            match self.renderer.render_source(&item.plantuml_text, job.format, user).await {
+                Ok(image) => {
+                    writer.add_image(&item.name, &image).map_err(zip_error)?;
+                    self.update(&job.id, |entry| entry.info.succeeded += 1);
+                    job.reporter.send(ProgressEvent::Done { index, name });
+                }
+                Err(code) => {
+                    writer.add_error(&item.name, code.clone());
+                    self.update(&job.id, |entry| entry.info.failed += 1);
+                    job.reporter.send(ProgressEvent::Failed {
+                        index,
+                        name,
+                        result: ProcessResult::new(code),
+                    });
+                }
+            }
+        }
+
+        writer.finish().map_err(zip_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use plantuml_editor_core::clock::SystemClock;
+
+    fn renderer() -> Renderer {
+        // Nothing listens on the discard port, so rendering fails fast
+        Renderer::new("http://127.0.0.1:9".to_string(), 1, 0, Default::default(), Default::default())
+            .unwrap()
+    }
+
+    fn item(name: &str, plantuml_text: &str) -> BatchExportItem {
+        BatchExportItem {
+            name: name.to_string(),
+            plantuml_text: plantuml_text.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_to_completion_with_per_item_failures() {
+        let progress = Arc::new(ProgressHub::new());
+        let queue = JobQueue::start(1, 4, renderer(), progress.clone(), SystemClock::shared());
+
+        let items = vec![item("empty.puml", " "), item("a.puml", "@startuml\n@enduml")];
+        let info = queue.submit_export(items, ImageFormat::Png, None).unwrap();
+        assert_eq!(info.state, JobState::Queued);
+
+        let events: Vec<ProgressEvent> = progress
+            .subscribe(&info.id)
+            .unwrap()
+            .into_stream()
+            .collect()
+            .await;
+        assert!(matches!(
+            events.last(),
+            Some(ProgressEvent::Completed { succeeded: 0, failed: 2 })
+        ));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            ProgressEvent::Failed { index: 0, result, .. } if result.code == ErrorCode::ValidationEmpty
+        )));
+
+        let status = queue.status(&info.id).unwrap();
+        assert_eq!(status.state, JobState::Completed);
+        assert_eq!(status.failed, 2);
+        assert!(queue.archive(&info.id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let progress = Arc::new(ProgressHub::new());
+        // No workers: submitted jobs stay in the queue
+        let queue = JobQueue::start(0, 1, renderer(), progress, SystemClock::shared());
+
+        let first = queue.submit_export(vec![item("a.puml", "x")], ImageFormat::Svg, None);
+        assert!(first.is_ok());
+        let second = queue.submit_export(vec![item("b.puml", "x")], ImageFormat::Svg, None);
+        assert_eq!(second.unwrap_err(), ErrorCode::JobQueueFull { capacity: 1 });
+        assert!(queue.status("missing").is_none());
+    }
+}
@@ -1,15 +1,35 @@
-use axum::{routing::{get, post}, Router};
+use config::Config;
+use state::AppState;
 use std::net::SocketAddr;
-use tower_http::{
-    cors::{Any, CorsLayer},
-    limit::RequestBodyLimitLayer,
-    trace::TraceLayer,
-};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod activity;
+mod archive;
+mod auth;
+mod config;
+mod documents;
+mod examples;
+mod folders;
 mod handlers;
+mod includes;
+mod jobs;
+mod metrics;
 mod middleware;
 mod models;
+mod openapi;
+mod pipeline;
+mod policy;
+mod progress;
+mod quota;
+mod readiness;
+mod reload;
+mod render;
+mod routes;
+mod search;
+mod settings;
+mod share;
+mod state;
+mod webhooks;
 
 #[tokio::main]
 async fn main() {
@@ -22,25 +42,25 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    // Configure CORS (allow localhost development)
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let state = AppState::new(Config::from_env()).expect("Failed to create PlantUML client");
+    if state.webhooks.is_enabled() {
+        tracing::info!("Webhooks enabled for {} URL(s)", state.config.current().webhook.urls.len());
+    }
+    reload::spawn_watchers(&state);
+    tokio::spawn({
+        let state = state.clone();
+        async move { readiness::self_test(&state).await }
+    });
 
-    // Build application router
-    let app = Router::new()
-        .route("/api/v1/health", get(handlers::health))
-        .route("/api/v1/convert", post(handlers::convert))
-        .route("/api/v1/export", post(handlers::export))
-        .layer(RequestBodyLimitLayer::new(1024 * 1024)) // 1MB limit
-        .layer(cors)
-        .layer(TraceLayer::new_for_http());
+    let app = routes::build_router(state);
 
     // Bind to localhost:8080
     let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
     tracing::info!("Starting API server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Peer addresses identify clients for quotas
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
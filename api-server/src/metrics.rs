@@ -0,0 +1,223 @@
+// Live request metrics for the /dashboard page
+//
+// The last hour is kept in memory in one-minute buckets: how many requests
+// arrived, the ErrorCode each JSON response carried, and how long the
+// PlantUML server took to answer. Everything starts over when the process
+// restarts; long-term storage is left to a real monitoring stack.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Minutes of history shown by the dashboard
+pub const WINDOW_MINUTES: usize = 60;
+
+/// Counters of one minute
+#[derive(Debug, Default)]
+struct Minute {
+    /// Minutes since the Unix epoch
+    minute: i64,
+    requests: u64,
+    /// Responses by ErrorCode variant name
+    codes: HashMap<String, u64>,
+    upstream_calls: u64,
+    upstream_ms_total: u64,
+    upstream_ms_max: u64,
+}
+
+pub struct Metrics {
+    /// Oldest first, at most WINDOW_MINUTES minutes
+    minutes: Mutex<VecDeque<Minute>>,
+    /// Unix timestamp (seconds) of the process start
+    started_at: i64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(chrono::Utc::now().timestamp())
+    }
+}
+
+/// PlantUML server response times over the window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamLatency {
+    pub calls: u64,
+    pub average_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+/// Metrics as served by GET /api/v1/metrics
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    pub uptime_seconds: i64,
+    pub requests_last_minute: u64,
+    pub requests_last_hour: u64,
+    /// Requests in each of the last WINDOW_MINUTES minutes, oldest first
+    pub requests_per_minute: Vec<u64>,
+    /// Responses in the last hour by ErrorCode variant
+    pub error_codes: BTreeMap<String, u64>,
+    pub upstream: UpstreamLatency,
+}
+
+impl Metrics {
+    pub fn new(started_at: i64) -> Self {
+        Self {
+            minutes: Mutex::new(VecDeque::with_capacity(WINDOW_MINUTES)),
+            started_at,
+        }
+    }
+
+    /// Count a request at `now` (Unix seconds) answered with `code`, if any
+    pub fn record_request(&self, now: i64, code: Option<&str>) {
+        self.update(now, |minute| {
+            minute.requests += 1;
+            if let Some(code) = code {
+                *minute.codes.entry(code.to_string()).or_default() += 1;
+            }
+        });
+    }
+
+    /// Record a PlantUML server call that took `elapsed`
+    pub fn record_upstream(&self, now: i64, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        self.update(now, |minute| {
+            minute.upstream_calls += 1;
+            minute.upstream_ms_total += ms;
+            minute.upstream_ms_max = minute.upstream_ms_max.max(ms);
+        });
+    }
+
+    /// The last WINDOW_MINUTES minutes as of `now`
+    pub fn snapshot(&self, now: i64) -> MetricsSnapshot {
+        let current = now.div_euclid(60);
+        let minutes = self.minutes.lock().unwrap();
+        let recent: Vec<&Minute> = minutes
+            .iter()
+            .filter(|minute| current - minute.minute < WINDOW_MINUTES as i64)
+            .collect();
+
+        let mut requests_per_minute = vec![0; WINDOW_MINUTES];
+        let mut error_codes = BTreeMap::new();
+        for minute in &recent {
+            requests_per_minute[WINDOW_MINUTES - 1 - (current - minute.minute) as usize] =
+                minute.requests;
+            for (code, count) in &minute.codes {
+                *error_codes.entry(code.clone()).or_default() += count;
+            }
+        }
+        let calls: u64 = recent.iter().map(|minute| minute.upstream_calls).sum();
+        let total_ms: u64 = recent.iter().map(|minute| minute.upstream_ms_total).sum();
+
+        MetricsSnapshot {
+            uptime_seconds: now - self.started_at,
+            requests_last_minute: requests_per_minute[WINDOW_MINUTES - 1],
+            requests_last_hour: requests_per_minute.iter().sum(),
+            requests_per_minute,
+            error_codes,
+            upstream: UpstreamLatency {
+                calls,
+                average_ms: (calls > 0).then(|| total_ms / calls),
+                max_ms: recent
+                    .iter()
+                    .map(|minute| minute.upstream_ms_max)
+                    .max()
+                    .filter(|_| calls > 0),
+            },
+        }
+    }
+
+    fn update(&self, now: i64, apply: impl FnOnce(&mut Minute)) {
+        let current = now.div_euclid(60);
+        let mut minutes = self.minutes.lock().unwrap();
+        // Requests racing at a minute boundary may arrive slightly out of order
+        match minutes.iter().rposition(|minute| minute.minute <= current) {
+            Some(index) if minutes[index].minute == current => apply(&mut minutes[index]),
+            earlier => {
+                let mut minute = Minute {
+                    minute: current,
+                    ..Minute::default()
+                };
+                apply(&mut minute);
+                minutes.insert(earlier.map_or(0, |index| index + 1), minute);
+            }
+        }
+        let newest = minutes.back().map_or(current, |minute| minute.minute);
+        while minutes
+            .front()
+            .is_some_and(|minute| newest - minute.minute >= WINDOW_MINUTES as i64)
+        {
+            minutes.pop_front();
+        }
+    }
+}
+
+/// ErrorCode variant of a JSON response body (`result.code.type`), if any
+pub fn response_code(body: &[u8]) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Envelope {
+        result: EnvelopeResult,
+    }
+    #[derive(Deserialize)]
+    struct EnvelopeResult {
+        code: EnvelopeCode,
+    }
+    #[derive(Deserialize)]
+    struct EnvelopeCode {
+        #[serde(rename = "type")]
+        kind: String,
+    }
+
+    serde_json::from_slice::<Envelope>(body)
+        .ok()
+        .map(|envelope| envelope.result.code.kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-11-14 22:13:20 UTC
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_snapshot_covers_the_last_hour() {
+        let metrics = Metrics::new(NOW - 7200);
+        // Two hours ago: dropped from the window
+        metrics.record_request(NOW - 7200, Some("ConversionOk"));
+        metrics.record_request(NOW - 120, Some("ConversionOk"));
+        metrics.record_request(NOW - 1, Some("ParseError"));
+        metrics.record_upstream(NOW - 120, Duration::from_millis(300));
+        metrics.record_request(NOW, None);
+        metrics.record_upstream(NOW, Duration::from_millis(100));
+        // Late arrival for a minute that is already closed
+        metrics.record_upstream(NOW - 119, Duration::from_millis(200));
+
+        let snapshot = metrics.snapshot(NOW);
+        assert_eq!(snapshot.uptime_seconds, 7200);
+        assert_eq!(snapshot.requests_last_hour, 3);
+        assert_eq!(snapshot.requests_last_minute, 2);
+        assert_eq!(snapshot.requests_per_minute.len(), WINDOW_MINUTES);
+        assert_eq!(snapshot.requests_per_minute[WINDOW_MINUTES - 3], 1);
+        assert_eq!(
+            snapshot.error_codes,
+            BTreeMap::from([("ConversionOk".to_string(), 1), ("ParseError".to_string(), 1)])
+        );
+        assert_eq!(
+            snapshot.upstream,
+            UpstreamLatency {
+                calls: 3,
+                average_ms: Some(200),
+                max_ms: Some(300),
+            }
+        );
+        assert_eq!(metrics.minutes.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_response_code() {
+        let body = br#"{"result":{"level":"INFO","code":{"type":"ConversionOk"}},"image_data":[137,80]}"#;
+        assert_eq!(response_code(body).as_deref(), Some("ConversionOk"));
+        assert_eq!(response_code(br#"{"status":"healthy"}"#), None);
+    }
+}
@@ -1,10 +1,344 @@
 // Middleware modules
 
-// Request body size limit middleware is configured in main.rs using tower-http
-// CORS middleware is configured in main.rs using tower-http
+// CORS middleware is configured in routes.rs using tower-http
 
-// Future middleware implementations:
-// - Request ID generation
-// - Rate limiting
-// - Authentication (system layer responsibility)
+// Authentication: an authenticating proxy passes the user name in
+// USER_HEADER, or, with OIDC_ISSUER set, server storage requires an access
+// token from the OIDC provider (see `with_auth`); the open routes then take
+// the user only from such a token (see `with_identity`)
 
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    middleware::{from_fn, from_fn_with_state, map_response, Next},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use plantuml_editor_core::{ConvertResponse, DocumentRole, ErrorCode};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use tower_http::decompression::RequestDecompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
+use tracing::Instrument;
+
+use crate::auth::AuthError;
+use crate::config::Config;
+use crate::metrics::response_code;
+use crate::quota::{self, Usage};
+use crate::state::AppState;
+
+/// Limit request bodies of every route in `router` to `max_bytes`
+///
+/// Oversized requests get the standard error envelope (RequestTooLarge)
+/// instead of a bare 413, like every other API error. Bodies sent with
+/// `Content-Encoding: gzip` or `br` are decompressed first, so the limit
+/// applies to the decompressed size.
+pub fn with_body_limit(router: Router<AppState>, max_bytes: usize) -> Router<AppState> {
+    router
+        .layer(DefaultBodyLimit::max(max_bytes))
+        .layer(RequestBodyLimitLayer::new(max_bytes))
+        .layer(RequestDecompressionLayer::new())
+        .layer(map_response(move |response: Response| async move {
+            too_large_envelope(response, max_bytes)
+        }))
+}
+
+/// Header carrying the authenticated user name, set by the proxy in front of the server
+pub const USER_HEADER: &str = "X-PlantUML-User";
+
+/// User making the request (None for anonymous requests)
+pub fn caller(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(USER_HEADER)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+}
+
+/// Token in `Authorization: Bearer <token>`
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Require an OIDC access token for every route in `router` (see `auth`)
+///
+/// Without OIDC_ISSUER requests pass unchanged and USER_HEADER identifies the
+/// caller as before. With it the user in the token replaces USER_HEADER.
+/// Rejected requests get the standard error envelope (AuthenticationRequired).
+pub fn with_auth(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router.route_layer(from_fn_with_state(state.clone(), check_auth))
+}
+
+async fn check_auth(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let config = state.config.current();
+    if config.oidc.issuer.is_none() {
+        return next.run(request).await;
+    }
+    match verified_user(&state, &config, request.headers()).await {
+        Ok(user) => {
+            request.headers_mut().insert(user_header(), user);
+            next.run(request).await
+        }
+        Err(e) => {
+            tracing::warn!("Request to {} not authenticated: {}", request.uri().path(), e);
+            let response = ConvertResponse::error(ErrorCode::AuthenticationRequired);
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// Take the caller of every route in `router` only from a verified identity
+///
+/// Without OIDC_ISSUER requests pass unchanged. With it the routes stay open
+/// to anonymous callers, but USER_HEADER is dropped and replaced by the user
+/// in the access token, if one is sent and valid, so private include files
+/// cannot be reached by naming another user.
+pub fn with_identity(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router.route_layer(from_fn_with_state(state.clone(), check_identity))
+}
+
+async fn check_identity(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let config = state.config.current();
+    if config.oidc.issuer.is_none() {
+        return next.run(request).await;
+    }
+    let user = verified_user(&state, &config, request.headers()).await;
+    let headers = request.headers_mut();
+    headers.remove(USER_HEADER);
+    if let Ok(user) = user {
+        headers.insert(user_header(), user);
+    }
+    next.run(request).await
+}
+
+/// User in the request's OIDC access token, as a USER_HEADER value
+async fn verified_user(
+    state: &AppState,
+    config: &Config,
+    headers: &HeaderMap,
+) -> Result<HeaderValue, AuthError> {
+    let user = match bearer_token(headers).map(str::trim) {
+        Some(token) => {
+            let token = token.to_string();
+            state
+                .auth
+                .authenticate(&token, &config.oidc, state.clock.now())
+                .await?
+        }
+        None => return Err(AuthError::MissingToken),
+    };
+    HeaderValue::from_str(&user).map_err(|_| AuthError::MissingUser(config.oidc.user_claim.clone()))
+}
+
+fn user_header() -> HeaderName {
+    HeaderName::from_bytes(USER_HEADER.as_bytes()).expect("valid header name")
+}
+
+/// Require at least `required` on the document in the `:id` path segment
+///
+/// The caller's role is passed to the handler as a request extension.
+/// Rejected requests get the standard error envelope (DocumentAccessDenied).
+pub fn with_role(
+    router: Router<AppState>,
+    state: &AppState,
+    required: DocumentRole,
+) -> Router<AppState> {
+    router.route_layer(from_fn_with_state(
+        state.clone(),
+        move |state: State<AppState>, path: Path<String>, request: Request, next: Next| {
+            check_role(state, path, request, next, required)
+        },
+    ))
+}
+
+async fn check_role(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    mut request: Request,
+    next: Next,
+    required: DocumentRole,
+) -> Response {
+    // Malformed IDs are reported as not found by the handler
+    let role = match uuid::Uuid::parse_str(&id) {
+        Ok(id) => state
+            .documents
+            .role_of(&plantuml_editor_core::DocumentId(id), caller(request.headers())),
+        Err(_) => Some(DocumentRole::Owner),
+    };
+
+    match role {
+        Some(role) if role >= required => {
+            request.extensions_mut().insert(role);
+            next.run(request).await
+        }
+        _ => {
+            tracing::warn!("Access to document {} denied (requires {:?})", id, required);
+            let response = ConvertResponse::error(ErrorCode::DocumentAccessDenied { required });
+            (StatusCode::OK, Json(response)).into_response()
+        }
+    }
+}
+
+/// Count every request to `router` against the caller's daily quota
+///
+/// Responses carry the remaining quota in headers. Requests over the quota
+/// get the standard error envelope (QuotaExceeded) and a Retry-After header.
+pub fn with_quota(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router.route_layer(from_fn_with_state(state.clone(), check_quota))
+}
+
+async fn check_quota(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let config = state.config.current();
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| *peer);
+    let Some((consumer, limit)) = quota::identify(&config.quota, request.headers(), peer) else {
+        return next.run(request).await;
+    };
+
+    let now = state.clock.now();
+    let usage = state.quota.consume(&consumer, limit, now);
+    let mut response = if usage.exceeded {
+        tracing::warn!("Daily quota of {} requests exceeded by {}", limit, consumer);
+        let response = ConvertResponse::error(ErrorCode::QuotaExceeded {
+            limit,
+            reset_at: usage.reset_at,
+        });
+        let mut response = (StatusCode::OK, Json(response)).into_response();
+        response.headers_mut().insert(
+            axum::http::header::RETRY_AFTER,
+            HeaderValue::from(usage.reset_at - now),
+        );
+        response
+    } else {
+        next.run(request).await
+    };
+    insert_quota_headers(response.headers_mut(), usage);
+    response
+}
+
+/// Require `Authorization: Bearer <ADMIN_TOKEN>` for every route in `router`
+///
+/// Without ADMIN_TOKEN the admin endpoints are disabled. Rejected requests get
+/// the standard error envelope (AdminAccessDenied).
+pub fn with_admin(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router.route_layer(from_fn_with_state(state.clone(), check_admin))
+}
+
+async fn check_admin(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let config = state.config.current();
+    let presented = bearer_token(request.headers());
+    // Comparing digests keeps the time taken independent of the token
+    let authorized = match (config.admin_token.as_deref(), presented) {
+        (Some(token), Some(presented)) => {
+            Sha256::digest(token.trim().as_bytes()) == Sha256::digest(presented.trim().as_bytes())
+        }
+        _ => false,
+    };
+    if authorized {
+        return next.run(request).await;
+    }
+    tracing::warn!("Admin request to {} denied", request.uri().path());
+    let response = ConvertResponse::error(ErrorCode::AdminAccessDenied);
+    (StatusCode::OK, Json(response)).into_response()
+}
+
+/// Header carrying the ID of a request, to match bug reports with the server logs
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID accepted from the client
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+/// Give every request to `router` an ID, returned in X-Request-Id
+///
+/// An ID the client (or a proxy in front of the server) already sent is kept
+/// when it is short and made of letters, digits, `-`, `_` and `.`; otherwise
+/// a new one is generated. Everything logged while handling the request
+/// carries the ID.
+pub fn with_request_id<S: Clone + Send + Sync + 'static>(router: Router<S>) -> Router<S> {
+    router.layer(from_fn(assign_request_id))
+}
+
+/// The ID sent in `headers`, when it is acceptable
+pub fn client_request_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            (1..=MAX_REQUEST_ID_LEN).contains(&id.len())
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
+
+async fn assign_request_id(request: Request, next: Next) -> Response {
+    let id = client_request_id(request.headers())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let span = tracing::info_span!("request", id = %id);
+    let mut response = next.run(request).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Count every request to `router` in the dashboard metrics, together with
+/// the ErrorCode of its JSON response
+pub fn with_metrics(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router.layer(from_fn_with_state(state.clone(), record_metrics))
+}
+
+async fn record_metrics(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let now = state.clock.now();
+    if !is_json {
+        state.metrics.record_request(now, None);
+        return response;
+    }
+
+    // JSON responses are already complete in memory; streams are never JSON
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => {
+            state.metrics.record_request(now, response_code(&bytes).as_deref());
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(e) => {
+            tracing::warn!("Cannot read response body for metrics: {}", e);
+            state.metrics.record_request(now, None);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn insert_quota_headers(headers: &mut HeaderMap, usage: Usage) {
+    for (name, value) in [
+        (quota::LIMIT_HEADER, HeaderValue::from(usage.limit)),
+        (quota::REMAINING_HEADER, HeaderValue::from(usage.remaining)),
+        (quota::RESET_HEADER, HeaderValue::from(usage.reset_at)),
+    ] {
+        headers.insert(name, value);
+    }
+}
+
+fn too_large_envelope(response: Response, max_bytes: usize) -> Response {
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE {
+        return response;
+    }
+    tracing::warn!("Request body exceeded {} bytes", max_bytes);
+    let response = ConvertResponse::error(ErrorCode::RequestTooLarge { max_bytes });
+    (StatusCode::OK, Json(response)).into_response()
+}
@@ -0,0 +1,352 @@
+// OpenAPI document to PlantUML sequence diagram generator
+
+use plantuml_editor_core::converters::{check_output, ConvertError};
+use plantuml_editor_core::OpenApiOperation;
+use serde_json::Value;
+
+/// Maximum accepted OpenAPI document size (bytes)
+///
+/// Specs are usually much larger than diagram sources, so the editor's
+/// 24,000 character limit only applies to the generated diagram.
+pub const MAX_SPEC_BYTES: usize = 512 * 1024;
+
+const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// Parsed OpenAPI document
+pub struct OpenApiDocument {
+    title: String,
+    operations: Vec<(OpenApiOperation, Value)>,
+}
+
+impl OpenApiDocument {
+    /// Parse an OpenAPI 3.x / Swagger 2.0 document from JSON or YAML
+    pub fn parse(spec: &str) -> Result<Self, ConvertError> {
+        if spec.trim().is_empty() {
+            return Err(ConvertError::EmptyInput);
+        }
+        if spec.len() > MAX_SPEC_BYTES {
+            return Err(ConvertError::InputTooLarge(spec.len(), MAX_SPEC_BYTES));
+        }
+
+        let root: Value = match serde_json::from_str(spec) {
+            Ok(value) => value,
+            Err(_) => serde_yaml::from_str(spec).map_err(|e| invalid(e.to_string()))?,
+        };
+        if root.get("openapi").is_none() && root.get("swagger").is_none() {
+            return Err(invalid("openapi/swaggerフィールドがありません".to_string()));
+        }
+
+        let title = root
+            .pointer("/info/title")
+            .and_then(Value::as_str)
+            .map(single_line)
+            .unwrap_or_else(|| "API Server".to_string());
+
+        let mut operations = Vec::new();
+        if let Some(paths) = root.get("paths").and_then(Value::as_object) {
+            for (path, item) in paths {
+                for method in METHODS {
+                    let Some(operation) = item.get(*method) else {
+                        continue;
+                    };
+                    let method = method.to_ascii_uppercase();
+                    let id = operation
+                        .get("operationId")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("{} {}", method, path));
+                    let summary = operation
+                        .get("summary")
+                        .and_then(Value::as_str)
+                        .map(single_line);
+                    // Path-level parameters apply to every operation
+                    let mut operation = operation.clone();
+                    if let Some(shared) = item.get("parameters").and_then(Value::as_array) {
+                        let own = operation
+                            .get("parameters")
+                            .and_then(Value::as_array)
+                            .cloned()
+                            .unwrap_or_default();
+                        operation["parameters"] = Value::Array([shared.clone(), own].concat());
+                    }
+                    operations.push((
+                        OpenApiOperation {
+                            id,
+                            method,
+                            path: path.clone(),
+                            summary,
+                        },
+                        operation,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { title, operations })
+    }
+
+    /// All operations in document order
+    pub fn operations(&self) -> Vec<OpenApiOperation> {
+        self.operations.iter().map(|(op, _)| op.clone()).collect()
+    }
+
+    /// Generate a sequence diagram for the selected operations (all if empty)
+    pub fn to_sequence_diagram(&self, selected: &[String]) -> Result<String, ConvertError> {
+        let operations: Vec<&(OpenApiOperation, Value)> = self
+            .operations
+            .iter()
+            .filter(|(op, _)| selected.is_empty() || selected.contains(&op.id))
+            .collect();
+        if operations.is_empty() {
+            return Err(invalid("対象のオペレーションがありません".to_string()));
+        }
+
+        let mut lines = vec![
+            "@startuml".to_string(),
+            format!("title {}", self.title),
+            "actor Client".to_string(),
+            format!("participant \"{}\" as Server", self.title.replace('"', "'")),
+        ];
+
+        for (op, detail) in operations {
+            lines.push(String::new());
+            let header = match &op.summary {
+                Some(summary) => format!("== {} {} : {} ==", op.method, op.path, summary),
+                None => format!("== {} {} ==", op.method, op.path),
+            };
+            lines.push(header);
+            lines.extend(render_operation(op, detail));
+        }
+
+        lines.push("@enduml".to_string());
+        // Paths, names and statuses come from the spec too
+        let lines: Vec<String> = lines.iter().map(|line| single_line(line)).collect();
+        check_output(lines.join("\n"))
+    }
+}
+
+/// `text` with line breaks turned into spaces, so text from the spec cannot
+/// add lines (e.g. `@enduml` or `!include`) to the diagram
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+fn invalid(reason: String) -> ConvertError {
+    ConvertError::InvalidInput {
+        format: "OpenAPI".to_string(),
+        reason,
+    }
+}
+
+fn render_operation(op: &OpenApiOperation, detail: &Value) -> Vec<String> {
+    let mut request = format!("{} {}", op.method, op.path);
+
+    let parameters: Vec<String> = detail
+        .get("parameters")
+        .and_then(Value::as_array)
+        .map(|params| {
+            params
+                .iter()
+                .filter_map(|p| {
+                    let name = p.get("name").and_then(Value::as_str)?;
+                    let location = p.get("in").and_then(Value::as_str).unwrap_or("query");
+                    (location != "body").then(|| format!("{}: {}", location, name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if !parameters.is_empty() {
+        request.push_str(&format!("\\n({})", parameters.join(", ")));
+    }
+    if let Some(body) = request_body_name(detail) {
+        request.push_str(&format!("\\nbody: {}", body));
+    }
+
+    let mut lines = vec![
+        format!("Client -> Server : {}", request),
+        "activate Server".to_string(),
+    ];
+
+    let responses: Vec<(String, String)> = detail
+        .get("responses")
+        .and_then(Value::as_object)
+        .map(|responses| {
+            responses
+                .iter()
+                .map(|(status, response)| (status.clone(), response_label(status, response)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match responses.as_slice() {
+        [] => lines.push("Server --> Client".to_string()),
+        [(_, label)] => lines.push(format!("Server --> Client : {}", label)),
+        [(first_status, first_label), rest @ ..] => {
+            lines.push(format!("alt {}", first_status));
+            lines.push(format!("  Server --> Client : {}", first_label));
+            for (status, label) in rest {
+                lines.push(format!("else {}", status));
+                lines.push(format!("  Server --> Client : {}", label));
+            }
+            lines.push("end".to_string());
+        }
+    }
+    lines.push("deactivate Server".to_string());
+
+    lines
+}
+
+fn request_body_name(detail: &Value) -> Option<String> {
+    // OpenAPI 3.x requestBody
+    if let Some(content) = detail.pointer("/requestBody/content").and_then(Value::as_object) {
+        return content
+            .values()
+            .next()
+            .and_then(|media| media.get("schema"))
+            .map(schema_name);
+    }
+    // Swagger 2.0 body parameter
+    detail
+        .get("parameters")
+        .and_then(Value::as_array)?
+        .iter()
+        .find(|p| p.get("in").and_then(Value::as_str) == Some("body"))
+        .and_then(|p| p.get("schema"))
+        .map(schema_name)
+}
+
+fn response_label(status: &str, response: &Value) -> String {
+    let description = response
+        .get("description")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .lines()
+        .next()
+        .unwrap_or("");
+    let schema = response
+        .get("content")
+        .and_then(Value::as_object)
+        .and_then(|content| content.values().next())
+        .and_then(|media| media.get("schema"))
+        .or_else(|| response.get("schema"))
+        .map(schema_name);
+
+    let mut label = status.to_string();
+    if !description.is_empty() {
+        label.push(' ');
+        label.push_str(description);
+    }
+    if let Some(schema) = schema {
+        label.push_str(&format!(" ({})", schema));
+    }
+    label
+}
+
+/// Short display name for a schema ($ref name, array of X, or type)
+fn schema_name(schema: &Value) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_string();
+    }
+    if let Some(items) = schema.get("items") {
+        return format!("{}[]", schema_name(items));
+    }
+    schema
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("object")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r##"
+openapi: 3.0.0
+info:
+  title: User API
+paths:
+  /users/{id}:
+    parameters:
+      - name: id
+        in: path
+    get:
+      operationId: getUser
+      summary: Get a user
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/User"
+        "404":
+          description: Not found
+  /users:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/NewUser"
+      responses:
+        "201":
+          description: Created
+"##;
+
+    #[test]
+    fn test_list_operations() {
+        let document = OpenApiDocument::parse(SPEC).unwrap();
+        let operations = document.operations();
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations[0].id, "getUser");
+        assert_eq!(operations[1].id, "POST /users");
+    }
+
+    #[test]
+    fn test_sequence_diagram_for_all_operations() {
+        let document = OpenApiDocument::parse(SPEC).unwrap();
+        let diagram = document.to_sequence_diagram(&[]).unwrap();
+        assert!(diagram.starts_with("@startuml\ntitle User API"));
+        assert!(diagram.contains("== GET /users/{id} : Get a user =="));
+        assert!(diagram.contains("Client -> Server : GET /users/{id}\\n(path: id)"));
+        assert!(diagram.contains("alt 200\n  Server --> Client : 200 OK (User)"));
+        assert!(diagram.contains("else 404\n  Server --> Client : 404 Not found"));
+        assert!(diagram.contains("Client -> Server : POST /users\\nbody: NewUser"));
+        assert!(diagram.contains("Server --> Client : 201 Created"));
+        assert!(diagram.ends_with("@enduml"));
+    }
+
+    #[test]
+    fn test_sequence_diagram_for_selected_operation() {
+        let document = OpenApiDocument::parse(SPEC).unwrap();
+        let diagram = document
+            .to_sequence_diagram(&["POST /users".to_string()])
+            .unwrap();
+        assert!(!diagram.contains("GET /users/{id}"));
+
+        let result = document.to_sequence_diagram(&["unknown".to_string()]);
+        assert!(matches!(result, Err(ConvertError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn test_multi_line_text_stays_on_one_line() {
+        let spec = r#"{
+            "openapi": "3.0.0",
+            "info": {"title": "API\n@enduml\n!include <evil>"},
+            "paths": {"/a\r\nb": {"get": {"summary": "Get\n!include <evil>", "responses": {"200\nX": {"description": "OK"}}}}}
+        }"#;
+        let document = OpenApiDocument::parse(spec).unwrap();
+        assert_eq!(document.operations()[0].summary.as_deref(), Some("Get !include <evil>"));
+        let diagram = document.to_sequence_diagram(&[]).unwrap();
+        assert!(diagram.starts_with("@startuml\ntitle API @enduml !include <evil>\n"));
+        assert!(!diagram.lines().any(|line| line.starts_with("!include") || line.starts_with("X")));
+        assert_eq!(diagram.lines().filter(|line| line.starts_with("@enduml")).count(), 1);
+    }
+
+    #[test]
+    fn test_not_an_openapi_document() {
+        let result = OpenApiDocument::parse(r#"{"name": "value"}"#);
+        assert!(matches!(result, Err(ConvertError::InvalidInput { .. })));
+    }
+}
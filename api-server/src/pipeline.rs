@@ -0,0 +1,302 @@
+// Post-processing pipeline for exports
+//
+// An exported image goes through an ordered list of post-processors before it
+// is returned. Deployments set the default list (EXPORT_PIPELINE) and a
+// request can name its own (`pipeline`), so features such as optimization,
+// watermarks or embedded sources combine without a flag per combination.
+// Order matters: `optimize` drops PNG text chunks, so `embed-source` has to
+// come after it. The older `deterministic` and `embed_source` request flags
+// still work and add their step when the list lacks it.
+
+use plantuml_editor_core::embed::embed_source;
+use plantuml_editor_core::image_size::image_dimensions;
+use plantuml_editor_core::metadata::{extract_title, file_stem_from_title};
+use plantuml_editor_core::{deterministic, optimize, ConvertRequest, ErrorCode, ImageFormat};
+
+use crate::config::ExportPipelineConfig;
+use crate::render::format_name;
+
+/// Step of the export pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostProcessor {
+    /// Strip run-dependent metadata (see `deterministic`)
+    Deterministic,
+    /// Drop data viewers do not need (see `optimize`)
+    Optimize,
+    /// Add EXPORT_WATERMARK in the lower right corner (SVG only)
+    Watermark,
+    /// Embed the source so the image can be opened again (see `embed`)
+    EmbedSource,
+    /// Name the file after EXPORT_FILE_NAME
+    Rename,
+}
+
+impl PostProcessor {
+    pub const ALL: [PostProcessor; 5] = [
+        Self::Deterministic,
+        Self::Optimize,
+        Self::Watermark,
+        Self::EmbedSource,
+        Self::Rename,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|step| step.name() == name.trim())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Deterministic => "deterministic",
+            Self::Optimize => "optimize",
+            Self::Watermark => "watermark",
+            Self::EmbedSource => "embed-source",
+            Self::Rename => "rename",
+        }
+    }
+}
+
+/// Names in a comma-separated list (the `pipeline` download parameter)
+pub fn split_names(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Exported image after the pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineOutput {
+    pub data: Vec<u8>,
+    /// File name without extension, set by `rename`
+    pub file_stem: Option<String>,
+    /// PostProcessorSkipped for a step the format does not support
+    pub warning: Option<ErrorCode>,
+}
+
+/// Post-processors for one export, in order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportPipeline {
+    steps: Vec<PostProcessor>,
+    config: ExportPipelineConfig,
+}
+
+impl ExportPipeline {
+    /// Steps `request` names, else the configured default
+    ///
+    /// Fails with PostProcessorUnknown for a name that is not a step, or for
+    /// `watermark` when no watermark text is configured.
+    pub fn for_request(config: &ExportPipelineConfig, request: &ConvertRequest) -> Result<Self, ErrorCode> {
+        let mut steps = match &request.pipeline {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    PostProcessor::parse(name).ok_or_else(|| ErrorCode::PostProcessorUnknown { name: name.clone() })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => config.steps.clone(),
+        };
+        if request.deterministic && !steps.contains(&PostProcessor::Deterministic) {
+            steps.insert(0, PostProcessor::Deterministic);
+        }
+        if request.embed_source && !steps.contains(&PostProcessor::EmbedSource) {
+            steps.push(PostProcessor::EmbedSource);
+        }
+        if steps.contains(&PostProcessor::Watermark) && config.watermark.is_none() {
+            return Err(ErrorCode::PostProcessorUnknown {
+                name: PostProcessor::Watermark.name().to_string(),
+            });
+        }
+        Ok(Self {
+            steps,
+            config: config.clone(),
+        })
+    }
+
+    /// Run the steps on the image rendered for `request`; `now` is the Unix
+    /// timestamp used by `rename`
+    pub fn run(&self, data: Vec<u8>, request: &ConvertRequest, now: i64) -> PipelineOutput {
+        let format = request.format;
+        let mut output = PipelineOutput {
+            data,
+            file_stem: None,
+            warning: None,
+        };
+        for step in &self.steps {
+            match step {
+                PostProcessor::Deterministic => {
+                    output.data = deterministic::normalize(format, &output.data);
+                }
+                PostProcessor::Optimize => {
+                    output.data = optimize::optimize(format, &output.data);
+                }
+                PostProcessor::Watermark => {
+                    let text = self.config.watermark.as_deref().unwrap_or_default();
+                    match watermark_svg(format, &output.data, text) {
+                        Some(data) => output.data = data,
+                        None => {
+                            output.warning = Some(ErrorCode::PostProcessorSkipped {
+                                name: step.name().to_string(),
+                                format: format_name(format).to_string(),
+                            });
+                        }
+                    }
+                }
+                PostProcessor::EmbedSource => {
+                    output.data = embed_source(format, &output.data, &request.plantuml_text);
+                }
+                PostProcessor::Rename => {
+                    output.file_stem = Some(file_stem(&self.config.file_name, &request.plantuml_text, now));
+                }
+            }
+        }
+        output
+    }
+}
+
+/// SVG with `text` in its lower right corner, None for other formats and
+/// SVG without a size
+fn watermark_svg(format: ImageFormat, data: &[u8], text: &str) -> Option<Vec<u8>> {
+    if format != ImageFormat::Svg {
+        return None;
+    }
+    let (width, height) = image_dimensions(format, data)?;
+    let svg = std::str::from_utf8(data).ok()?;
+    let close = svg.rfind("</svg>")?;
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let mark = format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" font-family=\"sans-serif\" font-size=\"12\" fill=\"#888888\" fill-opacity=\"0.6\">{}</text>",
+        width.saturating_sub(8),
+        height.saturating_sub(8),
+        escaped
+    );
+    Some(format!("{}{}{}", &svg[..close], mark, &svg[close..]).into_bytes())
+}
+
+/// `pattern` with `{title}` (the ASCII title, else "diagram") and `{date}`
+/// (YYYYMMDD in UTC) filled in
+fn file_stem(pattern: &str, plantuml_text: &str, now: i64) -> String {
+    let title = extract_title(plantuml_text)
+        .and_then(|title| file_stem_from_title(&title))
+        .unwrap_or_else(|| "diagram".to_string());
+    let date = chrono::DateTime::from_timestamp(now, 0)
+        .map(|time| time.format("%Y%m%d").to_string())
+        .unwrap_or_default();
+    pattern.replace("{title}", &title).replace("{date}", &date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::ExportScale;
+
+    fn request(format: ImageFormat, pipeline: Option<&[&str]>) -> ConvertRequest {
+        ConvertRequest {
+            plantuml_text: "@startuml\ntitle Login flow\nA -> B\n@enduml".to_string(),
+            format,
+            scale: ExportScale::X1,
+            font: None,
+            deterministic: false,
+            embed_source: false,
+            pipeline: pipeline.map(|names| names.iter().map(|name| name.to_string()).collect()),
+        }
+    }
+
+    fn config() -> ExportPipelineConfig {
+        ExportPipelineConfig {
+            steps: vec![PostProcessor::Optimize],
+            watermark: Some("社外秘".to_string()),
+            ..ExportPipelineConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_steps_for_request() {
+        let steps = |request: &ConvertRequest| {
+            ExportPipeline::for_request(&config(), request).map(|pipeline| pipeline.steps)
+        };
+        assert_eq!(steps(&request(ImageFormat::Svg, None)), Ok(vec![PostProcessor::Optimize]));
+        assert_eq!(
+            steps(&request(ImageFormat::Svg, Some(&["rename", "watermark"]))),
+            Ok(vec![PostProcessor::Rename, PostProcessor::Watermark])
+        );
+
+        // The older flags add their steps
+        let mut flags = request(ImageFormat::Svg, Some(&[]));
+        flags.deterministic = true;
+        flags.embed_source = true;
+        assert_eq!(
+            steps(&flags),
+            Ok(vec![PostProcessor::Deterministic, PostProcessor::EmbedSource])
+        );
+
+        assert_eq!(
+            steps(&request(ImageFormat::Svg, Some(&["optimize", "sharpen"]))),
+            Err(ErrorCode::PostProcessorUnknown { name: "sharpen".to_string() })
+        );
+        let without_watermark = ExportPipelineConfig::default();
+        assert_eq!(
+            ExportPipeline::for_request(&without_watermark, &request(ImageFormat::Svg, Some(&["watermark"])))
+                .map(|pipeline| pipeline.steps),
+            Err(ErrorCode::PostProcessorUnknown { name: "watermark".to_string() })
+        );
+    }
+
+    #[test]
+    fn test_run_in_order() {
+        let svg = b"<svg width=\"100\" height=\"50\">\n  <!--SRC=[abc]-->\n  <rect/>\n</svg>".to_vec();
+        let request = request(ImageFormat::Svg, Some(&["optimize", "watermark", "embed-source", "rename"]));
+        let pipeline = ExportPipeline::for_request(&config(), &request).unwrap();
+        // 2024-05-01 09:00 UTC
+        let output = pipeline.run(svg, &request, 1_714_554_000);
+        assert_eq!(
+            String::from_utf8(output.data).unwrap(),
+            "<svg width=\"100\" height=\"50\"><desc id=\"plantuml-source\">@startuml\ntitle Login flow\nA -&gt; B\n@enduml</desc><rect/><text x=\"92\" y=\"42\" text-anchor=\"end\" font-family=\"sans-serif\" font-size=\"12\" fill=\"#888888\" fill-opacity=\"0.6\">社外秘</text></svg>"
+        );
+        assert_eq!(output.file_stem.as_deref(), Some("Login_flow_20240501"));
+        assert_eq!(output.warning, None);
+    }
+
+    #[test]
+    fn test_request_flags() {
+        let svg = b"<svg><!--SRC=[abc]--><rect/></svg>".to_vec();
+        let mut request = request(ImageFormat::Svg, Some(&[]));
+        request.plantuml_text = "A -> B".to_string();
+        let run = |request: &ConvertRequest| {
+            let pipeline = ExportPipeline::for_request(&config(), request).unwrap();
+            pipeline.run(svg.clone(), request, 0).data
+        };
+        assert_eq!(run(&request), svg);
+        request.deterministic = true;
+        assert_eq!(run(&request), b"<svg><rect/></svg>".to_vec());
+
+        request.embed_source = true;
+        let embedded = run(&request);
+        assert_eq!(
+            embedded,
+            b"<svg><desc id=\"plantuml-source\">A -&gt; B</desc><rect/></svg>".to_vec()
+        );
+        assert_eq!(
+            plantuml_editor_core::embed::extract_source(&embedded).as_deref(),
+            Some("A -> B")
+        );
+    }
+
+    #[test]
+    fn test_watermark_is_skipped_for_png() {
+        let request = request(ImageFormat::Png, Some(&["watermark"]));
+        let pipeline = ExportPipeline::for_request(&config(), &request).unwrap();
+        let output = pipeline.run(b"png".to_vec(), &request, 0);
+        assert_eq!(output.data, b"png".to_vec());
+        assert_eq!(
+            output.warning,
+            Some(ErrorCode::PostProcessorSkipped {
+                name: "watermark".to_string(),
+                format: "PNG".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,251 @@
+// Company style policy for exported diagrams
+//
+// Organizations with diagram standards can require skinparams (e.g. a font or
+// `shadowing false`) and a header (e.g. a confidentiality notice) on every
+// export. Previews are never checked. Depending on STYLE_POLICY_ACTION, an
+// export that misses a rule gets the directives added, is exported with a
+// StylePolicyWarning, or is refused with StylePolicyViolation.
+
+use plantuml_editor_core::ErrorCode;
+use std::borrow::Cow;
+
+/// What an export that does not follow the policy gets (STYLE_POLICY_ACTION)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PolicyAction {
+    /// Add the missing directives before rendering
+    #[default]
+    Inject,
+    /// Export as written, with a StylePolicyWarning
+    Warn,
+    /// Refuse the export with StylePolicyViolation
+    Reject,
+}
+
+impl PolicyAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "inject" => Some(Self::Inject),
+            "warn" => Some(Self::Warn),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Inject => "inject",
+            Self::Warn => "warn",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+/// Style rules for exports (disabled when nothing is required)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StylePolicy {
+    /// Required skinparams as (name, value) (STYLE_POLICY_SKINPARAMS,
+    /// comma-separated `name=value`)
+    pub skinparams: Vec<(String, String)>,
+
+    /// Text every diagram's header must contain (STYLE_POLICY_HEADER)
+    pub header: Option<String>,
+
+    pub action: PolicyAction,
+}
+
+impl StylePolicy {
+    pub fn is_empty(&self) -> bool {
+        self.skinparams.is_empty() && self.header.is_none()
+    }
+
+    /// Directives required by the policy that `plantuml_text` lacks
+    pub fn missing(&self, plantuml_text: &str) -> Vec<String> {
+        let skinparams = skinparams(plantuml_text);
+        let mut missing: Vec<String> = self
+            .skinparams
+            .iter()
+            .filter(|(name, value)| {
+                let set = skinparams
+                    .iter()
+                    .rev()
+                    .find(|(set_name, _)| set_name.eq_ignore_ascii_case(name));
+                !set.is_some_and(|(_, set_value)| set_value.eq_ignore_ascii_case(value))
+            })
+            .map(|(name, value)| format!("skinparam {} {}", name, value))
+            .collect();
+        if let Some(header) = &self.header {
+            if !headers(plantuml_text).iter().any(|text| text.contains(header.as_str())) {
+                missing.push(format!("header {}", header));
+            }
+        }
+        missing
+    }
+
+    /// Source to export and the warning to return with the image, or the
+    /// rejection when the policy refuses it
+    ///
+    /// The rules are checked against `expanded`, the source with its include
+    /// files expanded; missing directives are added to `plantuml_text`.
+    pub fn enforce<'a>(
+        &self,
+        plantuml_text: &'a str,
+        expanded: &str,
+    ) -> Result<(Cow<'a, str>, Option<ErrorCode>), ErrorCode> {
+        if self.is_empty() {
+            return Ok((Cow::Borrowed(plantuml_text), None));
+        }
+        let missing = self.missing(expanded);
+        if missing.is_empty() {
+            return Ok((Cow::Borrowed(plantuml_text), None));
+        }
+        match self.action {
+            PolicyAction::Inject => Ok((Cow::Owned(append_directives(plantuml_text, &missing)), None)),
+            PolicyAction::Warn => Ok((
+                Cow::Borrowed(plantuml_text),
+                Some(ErrorCode::StylePolicyWarning { missing }),
+            )),
+            PolicyAction::Reject => Err(ErrorCode::StylePolicyViolation { missing }),
+        }
+    }
+}
+
+/// `skinparam` settings in source order, including `skinparam [prefix] { ... }` blocks
+fn skinparams(plantuml_text: &str) -> Vec<(String, String)> {
+    let mut settings = Vec::new();
+    // Prefix of the open block (e.g. "sequence"), empty for `skinparam {`
+    let mut block: Option<String> = None;
+    for line in plantuml_text.lines().map(str::trim) {
+        if let Some(prefix) = &block {
+            if line.starts_with('}') {
+                block = None;
+            } else if let Some((name, value)) = line.split_once(char::is_whitespace) {
+                settings.push((format!("{}{}", prefix, name), value.trim().to_string()));
+            }
+            continue;
+        }
+        let Some(rest) = strip_keyword(line, "skinparam") else {
+            continue;
+        };
+        if let Some(prefix) = rest.strip_suffix('{') {
+            block = Some(prefix.trim().to_string());
+        } else if let Some((name, value)) = rest.split_once(char::is_whitespace) {
+            settings.push((name.to_string(), value.trim().to_string()));
+        }
+    }
+    settings
+}
+
+/// Text of every `header` (single line or `header` ... `endheader`), with
+/// optional `left`/`center`/`right`
+fn headers(plantuml_text: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut block: Option<Vec<&str>> = None;
+    for line in plantuml_text.lines().map(str::trim) {
+        if let Some(lines) = &mut block {
+            if line.eq_ignore_ascii_case("endheader") || line.eq_ignore_ascii_case("end header") {
+                found.push(lines.join("\n"));
+                block = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        let line = ["left", "center", "right"]
+            .into_iter()
+            .find_map(|align| strip_keyword(line, align))
+            .unwrap_or(line);
+        if line.eq_ignore_ascii_case("header") {
+            block = Some(Vec::new());
+        } else if let Some(text) = strip_keyword(line, "header") {
+            found.push(text.to_string());
+        }
+    }
+    found
+}
+
+/// Rest of `line` after `keyword` and whitespace
+fn strip_keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = line.get(..keyword.len())?;
+    let rest = &line[keyword.len()..];
+    (head.eq_ignore_ascii_case(keyword) && rest.starts_with(char::is_whitespace))
+        .then(|| rest.trim_start())
+}
+
+/// Insert `directives` before the last `@end` line, so they override earlier settings
+fn append_directives(plantuml_text: &str, directives: &[String]) -> String {
+    let mut lines: Vec<&str> = plantuml_text.lines().collect();
+    let at = lines
+        .iter()
+        .rposition(|line| line.trim_start().starts_with("@end"))
+        .unwrap_or(lines.len());
+    lines.splice(at..at, directives.iter().map(String::as_str));
+    let mut out = lines.join("\n");
+    if plantuml_text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(action: PolicyAction) -> StylePolicy {
+        StylePolicy {
+            skinparams: vec![
+                ("shadowing".to_string(), "false".to_string()),
+                ("SequenceArrowColor".to_string(), "#333333".to_string()),
+            ],
+            header: Some("社外秘".to_string()),
+            action,
+        }
+    }
+
+    #[test]
+    fn test_missing_directives() {
+        let policy = policy(PolicyAction::Warn);
+        assert_eq!(
+            policy.missing("@startuml\nA -> B\n@enduml"),
+            vec!["skinparam shadowing false", "skinparam SequenceArrowColor #333333", "header 社外秘"]
+        );
+
+        let compliant = "@startuml\nskinparam Shadowing true\nskinparam sequence {\n  ArrowColor #333333\n}\nskinparam shadowing false\nright header\n社外秘 / 設計部\nendheader\nA -> B\n@enduml";
+        assert!(policy.missing(compliant).is_empty());
+        // The last setting wins
+        let overridden = "@startuml\nskinparam shadowing false\nskinparam sequenceArrowColor #333333\nskinparam shadowing true\nheader 社内\n@enduml";
+        assert_eq!(
+            policy.missing(overridden),
+            vec!["skinparam shadowing false", "header 社外秘"]
+        );
+    }
+
+    #[test]
+    fn test_enforce_actions() {
+        let source = "@startuml\nskinparam shadowing false\nskinparam SequenceArrowColor #333333\nA -> B\n@enduml\n";
+
+        let (text, warning) = policy(PolicyAction::Inject).enforce(source, source).unwrap();
+        assert_eq!(
+            text,
+            "@startuml\nskinparam shadowing false\nskinparam SequenceArrowColor #333333\nA -> B\nheader 社外秘\n@enduml\n"
+        );
+        assert_eq!(warning, None);
+
+        let (text, warning) = policy(PolicyAction::Warn).enforce(source, source).unwrap();
+        assert_eq!(text, source);
+        assert_eq!(
+            warning,
+            Some(ErrorCode::StylePolicyWarning { missing: vec!["header 社外秘".to_string()] })
+        );
+
+        assert_eq!(
+            policy(PolicyAction::Reject).enforce(source, source).unwrap_err(),
+            ErrorCode::StylePolicyViolation { missing: vec!["header 社外秘".to_string()] }
+        );
+        assert!(StylePolicy::default().enforce(source, source).is_ok());
+
+        // A header set in an include file counts
+        let expanded = source.replace("A -> B", "header 社外秘\nA -> B");
+        let (text, warning) = policy(PolicyAction::Reject).enforce(source, &expanded).unwrap();
+        assert_eq!((text.as_ref(), warning), (source, None));
+    }
+}
@@ -0,0 +1,157 @@
+// Progress reporting for long-running operations, streamed to clients over SSE
+
+use futures::stream::{self, Stream};
+use plantuml_editor_core::ProgressEvent;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// Enough for every event of a maximum-size batch
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Registry of progress channels keyed by operation ID
+#[derive(Default)]
+pub struct ProgressHub {
+    channels: Mutex<HashMap<String, Arc<ProgressChannel>>>,
+}
+
+struct ProgressChannel {
+    /// Every event sent so far, replayed to late subscribers
+    history: Mutex<Vec<ProgressEvent>>,
+    sender: broadcast::Sender<ProgressEvent>,
+}
+
+/// Handle used by an operation to publish its progress
+#[derive(Clone)]
+pub struct ProgressReporter {
+    channel: Arc<ProgressChannel>,
+}
+
+/// Events already sent plus a receiver for the following ones
+pub struct ProgressSubscription {
+    pub history: Vec<ProgressEvent>,
+    pub receiver: broadcast::Receiver<ProgressEvent>,
+}
+
+impl ProgressHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new operation
+    pub fn create(&self, id: &str) -> ProgressReporter {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let channel = Arc::new(ProgressChannel {
+            history: Mutex::new(Vec::new()),
+            sender,
+        });
+        self.channels
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), channel.clone());
+        ProgressReporter { channel }
+    }
+
+    /// Subscribe to an operation (None if unknown or already removed)
+    pub fn subscribe(&self, id: &str) -> Option<ProgressSubscription> {
+        let channel = self.channels.lock().unwrap().get(id).cloned()?;
+        // Holding the history lock while subscribing guarantees no event is
+        // missed or delivered twice
+        let history = channel.history.lock().unwrap();
+        Some(ProgressSubscription {
+            receiver: channel.sender.subscribe(),
+            history: history.clone(),
+        })
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.channels.lock().unwrap().remove(id);
+    }
+}
+
+impl ProgressSubscription {
+    /// Stream of all events, ending after the final event
+    pub fn into_stream(self) -> impl Stream<Item = ProgressEvent> {
+        let state = (self.history.into_iter(), self.receiver, false);
+        stream::unfold(state, |(mut history, mut receiver, finished)| async move {
+            if finished {
+                return None;
+            }
+            let event = match history.next() {
+                Some(event) => event,
+                None => loop {
+                    match receiver.recv().await {
+                        Ok(event) => break event,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                },
+            };
+            let finished = event.is_final();
+            Some((event, (history, receiver, finished)))
+        })
+    }
+}
+
+impl ProgressReporter {
+    pub fn send(&self, event: ProgressEvent) {
+        let mut history = self.channel.history.lock().unwrap();
+        history.push(event.clone());
+        // No receivers is fine: late subscribers get the history
+        let _ = self.channel.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn done(index: usize) -> ProgressEvent {
+        ProgressEvent::Done {
+            index,
+            name: format!("{}.puml", index),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_receives_history_then_live_events() {
+        let hub = ProgressHub::new();
+        let reporter = hub.create("op");
+        reporter.send(done(0));
+
+        let mut subscription = hub.subscribe("op").unwrap();
+        assert_eq!(subscription.history, vec![done(0)]);
+
+        reporter.send(done(1));
+        assert_eq!(subscription.receiver.recv().await.unwrap(), done(1));
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_after_final_event() {
+        use futures::StreamExt;
+
+        let hub = ProgressHub::new();
+        let reporter = hub.create("op");
+        reporter.send(done(0));
+        let subscription = hub.subscribe("op").unwrap();
+        reporter.send(ProgressEvent::Completed {
+            succeeded: 1,
+            failed: 0,
+        });
+        reporter.send(done(1));
+
+        let events: Vec<ProgressEvent> = subscription.into_stream().collect().await;
+        assert_eq!(events.len(), 2);
+        assert!(events[1].is_final());
+    }
+
+    #[test]
+    fn test_unknown_or_removed_operation() {
+        let hub = ProgressHub::new();
+        assert!(hub.subscribe("missing").is_none());
+
+        hub.create("op");
+        hub.remove("op");
+        assert!(hub.subscribe("op").is_none());
+    }
+}
@@ -0,0 +1,282 @@
+// Daily request quotas per consumer
+//
+// A consumer is a known API key (sent in API_KEY_HEADER) or else the client
+// IP address; unknown keys count against the IP so rotating made-up keys
+// does not escape the limit. Counters reset at midnight UTC and, with
+// QUOTA_STATE_FILE set, are written to disk periodically so a restart does
+// not hand everyone a fresh quota. API keys are stored hashed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::http::HeaderMap;
+
+use crate::config::QuotaConfig;
+
+/// Header carrying the caller's API key
+pub const API_KEY_HEADER: &str = "X-PlantUML-Api-Key";
+
+/// Response headers describing the caller's quota
+pub const LIMIT_HEADER: &str = "X-Quota-Limit";
+pub const REMAINING_HEADER: &str = "X-Quota-Remaining";
+/// Unix timestamp (seconds) of the next reset
+pub const RESET_HEADER: &str = "X-Quota-Reset";
+
+/// How often changed counters are written to QUOTA_STATE_FILE
+pub const QUOTA_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Quota state of one consumer after a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix timestamp (seconds) when the counter resets
+    pub reset_at: i64,
+    /// The request was over the limit and was not counted
+    pub exceeded: bool,
+}
+
+/// Counters for one UTC day, as stored in QUOTA_STATE_FILE
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Counters {
+    /// Days since the Unix epoch
+    day: i64,
+    counts: HashMap<String, u64>,
+}
+
+pub struct QuotaTracker {
+    counters: Mutex<Counters>,
+    state_file: Option<PathBuf>,
+    dirty: AtomicBool,
+}
+
+impl QuotaTracker {
+    /// Tracker resuming today's counters from `state_file`, if any
+    pub fn new(state_file: Option<PathBuf>) -> Self {
+        let counters = state_file
+            .as_deref()
+            .and_then(load)
+            .unwrap_or_default();
+        Self {
+            counters: Mutex::new(counters),
+            state_file,
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Count a request by `consumer` at `now` (Unix seconds) against `limit`
+    ///
+    /// Requests over the limit are refused and not counted.
+    pub fn consume(&self, consumer: &str, limit: u64, now: i64) -> Usage {
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        let reset_at = (day + 1) * SECONDS_PER_DAY;
+
+        let mut counters = self.counters.lock().unwrap();
+        if counters.day != day {
+            *counters = Counters {
+                day,
+                counts: HashMap::new(),
+            };
+        }
+        let used = counters.counts.entry(consumer.to_string()).or_default();
+        let exceeded = *used >= limit;
+        if !exceeded {
+            *used += 1;
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        Usage {
+            limit,
+            // A reload can lower the limit below today's usage
+            remaining: limit.saturating_sub(*used),
+            reset_at,
+            exceeded,
+        }
+    }
+
+    /// Write the counters to QUOTA_STATE_FILE if they changed
+    pub fn flush(&self) -> std::io::Result<()> {
+        let Some(path) = &self.state_file else {
+            return Ok(());
+        };
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let json = {
+            let counters = self.counters.lock().unwrap();
+            serde_json::to_vec(&*counters)?
+        };
+        // Replace the file atomically so a crash never leaves it half written
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, json)
+            .and_then(|()| std::fs::rename(&temp, path))
+            .inspect_err(|_| self.dirty.store(true, Ordering::Relaxed))
+    }
+
+    /// Flush the counters every QUOTA_FLUSH_INTERVAL
+    pub fn spawn_flusher(tracker: std::sync::Arc<Self>) {
+        if tracker.state_file.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QUOTA_FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = tracker.flush() {
+                    tracing::warn!("Cannot write QUOTA_STATE_FILE: {}", e);
+                }
+            }
+        });
+    }
+}
+
+fn load(path: &Path) -> Option<Counters> {
+    let bytes = std::fs::read(path).ok()?;
+    match serde_json::from_slice(&bytes) {
+        Ok(counters) => Some(counters),
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable QUOTA_STATE_FILE {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Counter key and daily limit for a request, None when it is unlimited
+pub fn identify(
+    config: &QuotaConfig,
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+) -> Option<(String, u64)> {
+    let api_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    if let Some((key, limit)) = api_key.and_then(|key| Some((key, config.api_key_limit(key)?))) {
+        let hash = hex::encode(Sha256::digest(key.as_bytes()));
+        return (limit > 0).then(|| (format!("key:{}", &hash[..16]), limit));
+    }
+
+    if config.daily_limit == 0 {
+        return None;
+    }
+    let ip = config
+        .trust_forwarded_for
+        .then(|| forwarded_for(headers))
+        .flatten()
+        .or(peer.map(|peer| peer.ip()));
+    let ip = ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+    Some((format!("ip:{}", ip), config.daily_limit))
+}
+
+/// Address the proxy in front of the server added to X-Forwarded-For
+///
+/// Earlier entries come from the client and cannot be trusted.
+fn forwarded_for(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .next_back()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-11-14 22:13:20 UTC
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_consume_until_exceeded() {
+        let tracker = QuotaTracker::new(None);
+        let first = tracker.consume("ip:127.0.0.1", 2, NOW);
+        assert_eq!(first.remaining, 1);
+        assert_eq!(first.reset_at, 1_700_006_400);
+        assert!(!tracker.consume("ip:127.0.0.1", 2, NOW).exceeded);
+
+        let third = tracker.consume("ip:127.0.0.1", 2, NOW);
+        assert!(third.exceeded);
+        assert_eq!(third.remaining, 0);
+        // Other consumers have their own counter
+        assert!(!tracker.consume("ip:127.0.0.2", 2, NOW).exceeded);
+        // The next day starts over
+        assert!(!tracker.consume("ip:127.0.0.1", 2, third.reset_at).exceeded);
+    }
+
+    #[test]
+    fn test_limit_lowered_below_usage() {
+        let tracker = QuotaTracker::new(None);
+        for _ in 0..5 {
+            tracker.consume("key:abc", 10, NOW);
+        }
+        let lowered = tracker.consume("key:abc", 3, NOW);
+        assert!(lowered.exceeded);
+        assert_eq!(lowered.remaining, 0);
+    }
+
+    #[test]
+    fn test_counters_survive_restart() {
+        let path = std::env::temp_dir().join(format!("quota-{}.json", uuid::Uuid::new_v4()));
+        let tracker = QuotaTracker::new(Some(path.clone()));
+        tracker.consume("key:abc", 5, NOW);
+        tracker.consume("key:abc", 5, NOW);
+        tracker.flush().unwrap();
+
+        let restarted = QuotaTracker::new(Some(path.clone()));
+        assert_eq!(restarted.consume("key:abc", 5, NOW).remaining, 2);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_identify() {
+        let config = QuotaConfig {
+            daily_limit: 10,
+            api_keys: vec![("team".to_string(), Some(500)), ("batch".to_string(), Some(0))],
+            state_file: None,
+            trust_forwarded_for: false,
+        };
+        let peer: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.9, 198.51.100.7".parse().unwrap());
+
+        assert_eq!(
+            identify(&config, &headers, Some(peer)),
+            Some(("ip:10.0.0.1".to_string(), 10))
+        );
+        let trusted = QuotaConfig {
+            trust_forwarded_for: true,
+            ..config.clone()
+        };
+        assert_eq!(
+            identify(&trusted, &headers, Some(peer)),
+            Some(("ip:198.51.100.7".to_string(), 10))
+        );
+
+        headers.insert(API_KEY_HEADER, "team".parse().unwrap());
+        let (key, limit) = identify(&config, &headers, Some(peer)).unwrap();
+        assert!(key.starts_with("key:") && !key.contains("team"));
+        assert_eq!(limit, 500);
+
+        headers.insert(API_KEY_HEADER, "batch".parse().unwrap());
+        assert_eq!(identify(&config, &headers, Some(peer)), None);
+
+        // Unknown keys count against the IP
+        headers.insert(API_KEY_HEADER, "made-up".parse().unwrap());
+        assert_eq!(
+            identify(&config, &headers, Some(peer)),
+            Some(("ip:10.0.0.1".to_string(), 10))
+        );
+    }
+}
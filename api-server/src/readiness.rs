@@ -0,0 +1,82 @@
+// Readiness checks (GET /api/v1/ready and the startup self-test)
+//
+// Unlike /health, which only says the process is up, readiness verifies that
+// the configuration is usable and the PlantUML server answers, so load
+// balancers hold traffic back until requests can actually be served.
+
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::state::AppState;
+
+/// How long the upstream probe may take before the server counts as not ready
+pub const UPSTREAM_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Result of one readiness check
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadinessCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ReadinessCheck {
+    fn from_result(name: &'static str, result: Result<(), String>) -> Self {
+        match result {
+            Ok(()) => Self {
+                name,
+                ok: true,
+                detail: None,
+            },
+            Err(detail) => Self {
+                name,
+                ok: false,
+                detail: Some(detail),
+            },
+        }
+    }
+}
+
+/// Run all readiness checks
+pub async fn run_checks(state: &AppState) -> Vec<ReadinessCheck> {
+    let config = state.config.current().validate().map_err(|problems| problems.join("; "));
+    let upstream = state.renderer.probe(UPSTREAM_PROBE_TIMEOUT).await;
+
+    vec![
+        ReadinessCheck::from_result("config", config),
+        ReadinessCheck::from_result("plantuml", upstream),
+    ]
+}
+
+/// Log the readiness checks once at startup
+pub async fn self_test(state: &AppState) {
+    for check in run_checks(state).await {
+        match &check.detail {
+            None => tracing::info!("Self-test {}: ok", check.name),
+            Some(detail) => tracing::warn!("Self-test {} failed: {}", check.name, detail),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[tokio::test]
+    async fn test_not_ready_without_upstream() {
+        let config = Config {
+            // Nothing listens on the discard port
+            plantuml_url: "http://127.0.0.1:9".to_string(),
+            ..Config::default()
+        };
+        let state = AppState::new(config).unwrap();
+
+        let checks = run_checks(&state).await;
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].ok);
+        assert!(!checks[1].ok);
+        assert!(checks[1].detail.is_some());
+    }
+}
@@ -0,0 +1,199 @@
+// Applying configuration changes to a running server
+//
+// SIGHUP, or a change to CONFIG_FILE, loads the configuration again. Only the
+// settings in RELOADABLE_SETTINGS take effect; the rest (body limits, job
+// workers, webhooks, secrets) are wired into the router and background tasks
+// at startup, so changes to them are logged and wait for a restart. A config
+// that fails validation is rejected as a whole and the old one stays.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use crate::config::Config;
+use crate::state::AppState;
+
+/// How often CONFIG_FILE is checked for changes
+pub const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Result of the most recent reload, shown by GET /api/v1/config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReloadRecord {
+    /// Unix timestamp (seconds)
+    pub at: i64,
+    pub applied: Vec<&'static str>,
+    pub restart_required: Vec<&'static str>,
+    /// Why the new configuration was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Configuration in effect, replaced on reload
+pub struct LiveConfig {
+    current: RwLock<Arc<Config>>,
+    last_reload: RwLock<Option<ReloadRecord>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Config) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(config)),
+            last_reload: RwLock::new(None),
+        }
+    }
+
+    /// Snapshot of the configuration; later reloads do not change it
+    pub fn current(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn last_reload(&self) -> Option<ReloadRecord> {
+        self.last_reload.read().unwrap().clone()
+    }
+}
+
+/// Apply the reloadable settings of `new` and record the outcome
+pub fn apply(state: &AppState, new: Config) -> ReloadRecord {
+    let live = &state.config;
+    let mut record = ReloadRecord {
+        at: chrono::Utc::now().timestamp(),
+        applied: Vec::new(),
+        restart_required: Vec::new(),
+        error: None,
+    };
+
+    match new.validate() {
+        Err(problems) => record.error = Some(problems.join("; ")),
+        Ok(()) => {
+            let mut config = Config::clone(&live.current());
+            let outcome = config.apply_reload(new);
+            let switched = if outcome.applied.contains(&"PLANTUML_SERVER_URL") {
+                state.renderer.set_plantuml_url(config.plantuml_url.clone())
+            } else {
+                Ok(())
+            };
+            match switched {
+                Err(e) => record.error = Some(e.to_string()),
+                Ok(()) => {
+                    state.renderer.set_max_waiting(config.plantuml_max_waiting);
+                    state.renderer.set_image_limits(config.image_limits);
+                    state.renderer.set_style_policy(config.style_policy.clone());
+                    *live.current.write().unwrap() = Arc::new(config);
+                    record.applied = outcome.applied;
+                    record.restart_required = outcome.restart_required;
+                }
+            }
+        }
+    }
+
+    match &record.error {
+        Some(error) => tracing::warn!("Config reload rejected: {}", error),
+        None => tracing::info!(
+            "Config reloaded (applied: [{}], restart required: [{}])",
+            record.applied.join(", "),
+            record.restart_required.join(", ")
+        ),
+    }
+    *live.last_reload.write().unwrap() = Some(record.clone());
+    record
+}
+
+/// Load CONFIG_FILE and the environment again and apply the result
+pub fn reload(state: &AppState) -> ReloadRecord {
+    apply(state, Config::from_env())
+}
+
+/// Reload on SIGHUP and whenever CONFIG_FILE changes
+pub fn spawn_watchers(state: &AppState) {
+    #[cfg(unix)]
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(e) => {
+                    tracing::warn!("Cannot listen for SIGHUP: {}", e);
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                tracing::info!("SIGHUP received, reloading config");
+                reload(&state);
+            }
+        });
+    }
+
+    if let Some(path) = state.config.current().config_file.clone() {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut last_modified = modified(&path);
+            let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let modified = modified(&path);
+                if modified != last_modified {
+                    last_modified = modified;
+                    tracing::info!("{} changed, reloading config", path.display());
+                    reload(&state);
+                }
+            }
+        });
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> AppState {
+        AppState::new(Config::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_apply_reloadable_settings() {
+        let state = state();
+        let record = apply(
+            &state,
+            Config {
+                plantuml_url: "http://127.0.0.1:9".to_string(),
+                cors_origins: vec!["https://editor.example".to_string()],
+                job_workers: 7,
+                ..Config::default()
+            },
+        );
+
+        assert_eq!(record.error, None);
+        assert_eq!(record.applied, vec!["PLANTUML_SERVER_URL", "CORS_ALLOWED_ORIGINS"]);
+        assert_eq!(record.restart_required, vec!["JOB_WORKERS"]);
+        let config = state.config.current();
+        assert_eq!(config.plantuml_url, "http://127.0.0.1:9");
+        assert!(!config.allows_origin("https://other.example"));
+        assert_eq!(state.config.last_reload(), Some(record));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_is_rejected() {
+        let state = state();
+        let record = apply(
+            &state,
+            Config {
+                plantuml_url: "plantuml:8080".to_string(),
+                plantuml_max_waiting: 1,
+                ..Config::default()
+            },
+        );
+
+        assert!(record.error.unwrap().contains("PLANTUML_SERVER_URL"));
+        assert!(record.applied.is_empty());
+        assert_eq!(
+            state.config.current().plantuml_max_waiting,
+            Config::default().plantuml_max_waiting
+        );
+    }
+}
@@ -0,0 +1,516 @@
+// Shared renderer with a concurrency limit toward the PlantUML server
+//
+// Every Picoweb call goes through a semaphore so a burst of live-preview
+// requests cannot overwhelm the Java server. Interactive requests give up with
+// RenderQueueFull once too many are already waiting; background work (jobs,
+// archives, webhooks) waits for its turn instead.
+//
+// Rendered images over the configured dimension or byte limits are rejected
+// with SizeLimit, on every path, before they reach a response. In maintenance
+// mode (switched through the admin API) nothing is sent to the PlantUML
+// server and every render fails with Maintenance. Include files uploaded to
+// the server are expanded, once, before the source is sent: the shared files
+// and those of the user passed in. Stored documents, share links and webhooks
+// render with the shared files only, since a document may have several owners.
+//
+// The server URL, the waiting limit and the image limits can change on a
+// config reload; the concurrency limit is fixed for the life of the process.
+
+use plantuml_client::{ClientError, PlantUmlClient, SyntaxError};
+use plantuml_editor_core::image_size::ImageLimits;
+use plantuml_editor_core::preprocess::prepare_for_render;
+use plantuml_editor_core::{
+    validate_plantuml_content, DiagramImage, DocumentId, ErrorCode, ImageFormat,
+};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::includes::IncludeStore;
+use crate::metrics::Metrics;
+use crate::policy::StylePolicy;
+
+/// Minimal diagram rendered by the readiness probe
+const PROBE_SOURCE: &str = "@startuml\nBob -> Alice : ready\n@enduml";
+
+/// Render failures
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    /// Too many requests are already waiting for the PlantUML server
+    #[error("render queue is full (max concurrency: {max_concurrency})")]
+    QueueFull { max_concurrency: usize },
+
+    /// Conversions are switched off by an operator
+    #[error("maintenance mode")]
+    Maintenance,
+
+    /// The rendered image is over the configured limits (ErrorCode::SizeLimit)
+    #[error("rendered image is too large: {0:?}")]
+    TooLarge(ErrorCode),
+
+    /// The includes expand past the text limit (ErrorCode::ValidationTextLimit)
+    #[error("include expansion failed: {0:?}")]
+    Include(ErrorCode),
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
+impl RenderError {
+    /// ErrorCode for a rejected request, or `fallback` for client errors
+    pub fn to_error_code(&self, fallback: impl FnOnce(&ClientError) -> ErrorCode) -> ErrorCode {
+        match self {
+            RenderError::QueueFull { max_concurrency } => ErrorCode::RenderQueueFull {
+                max_concurrency: *max_concurrency,
+            },
+            RenderError::Maintenance => ErrorCode::Maintenance,
+            RenderError::TooLarge(code) | RenderError::Include(code) => code.clone(),
+            RenderError::Client(e) => fallback(e),
+        }
+    }
+}
+
+/// PlantUML client shared by all handlers and background tasks
+#[derive(Clone)]
+pub struct Renderer {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: RwLock<Arc<PlantUmlClient>>,
+    permits: Semaphore,
+    waiting: AtomicUsize,
+    max_concurrency: usize,
+    max_waiting: AtomicUsize,
+    image_limits: RwLock<ImageLimits>,
+    style_policy: RwLock<StylePolicy>,
+    maintenance: AtomicBool,
+    metrics: Arc<Metrics>,
+    includes: Arc<IncludeStore>,
+}
+
+impl Renderer {
+    /// Create a renderer allowing `max_concurrency` simultaneous Picoweb calls
+    /// and at most `max_waiting` interactive requests waiting for one
+    ///
+    /// The time each call takes is recorded in `metrics`; `!include <name>`
+    /// lines are resolved against the files in `includes`.
+    pub fn new(
+        plantuml_url: String,
+        max_concurrency: usize,
+        max_waiting: usize,
+        metrics: Arc<Metrics>,
+        includes: Arc<IncludeStore>,
+    ) -> Result<Self, ClientError> {
+        let max_concurrency = max_concurrency.max(1);
+        Ok(Self {
+            inner: Arc::new(Inner {
+                client: RwLock::new(Arc::new(PlantUmlClient::new(plantuml_url)?)),
+                permits: Semaphore::new(max_concurrency),
+                waiting: AtomicUsize::new(0),
+                max_concurrency,
+                max_waiting: AtomicUsize::new(max_waiting),
+                image_limits: RwLock::new(ImageLimits::default()),
+                style_policy: RwLock::new(StylePolicy::default()),
+                maintenance: AtomicBool::new(false),
+                metrics,
+                includes,
+            }),
+        })
+    }
+
+    /// Send later requests to another PlantUML server; requests in flight
+    /// finish against the old one
+    pub fn set_plantuml_url(&self, plantuml_url: String) -> Result<(), ClientError> {
+        let client = Arc::new(PlantUmlClient::new(plantuml_url)?);
+        *self.inner.client.write().expect("client lock poisoned") = client;
+        Ok(())
+    }
+
+    /// Change how many interactive requests may wait for a slot
+    pub fn set_max_waiting(&self, max_waiting: usize) {
+        self.inner.max_waiting.store(max_waiting, Ordering::SeqCst);
+    }
+
+    /// Change the largest image later renders may return
+    pub fn set_image_limits(&self, image_limits: ImageLimits) {
+        *self.inner.image_limits.write().expect("limits lock poisoned") = image_limits;
+    }
+
+    /// Change the style rules later exports must follow
+    pub fn set_style_policy(&self, style_policy: StylePolicy) {
+        *self.inner.style_policy.write().expect("policy lock poisoned") = style_policy;
+    }
+
+    /// Source to export under the style policy, with the warning to return
+    /// alongside the image (see `policy`)
+    ///
+    /// The rules are checked against the source with the includes of `user`
+    /// expanded, so rules set in a style library count; the returned source
+    /// keeps its include lines, which the render call expands.
+    pub fn enforce_policy(
+        &self,
+        plantuml_text: &str,
+        user: Option<&str>,
+    ) -> Result<(String, Option<ErrorCode>), ErrorCode> {
+        let expanded = self.inner.includes.expand(plantuml_text, user)?;
+        let policy = self.inner.style_policy.read().expect("policy lock poisoned");
+        policy
+            .enforce(plantuml_text, &expanded)
+            .map(|(text, warning)| (text.into_owned(), warning))
+    }
+
+    /// Switch maintenance mode on or off
+    pub fn set_maintenance(&self, enabled: bool) {
+        self.inner.maintenance.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_maintenance(&self) -> bool {
+        self.inner.maintenance.load(Ordering::SeqCst)
+    }
+
+    fn client(&self) -> Arc<PlantUmlClient> {
+        self.inner.client.read().expect("client lock poisoned").clone()
+    }
+
+    /// Render an interactive request, rejecting it when the queue is full
+    ///
+    /// `user`'s own include files are used besides the shared ones.
+    pub async fn render(
+        &self,
+        document_id: DocumentId,
+        plantuml_text: &str,
+        format: ImageFormat,
+        user: Option<&str>,
+    ) -> Result<DiagramImage, RenderError> {
+        self.check_maintenance()?;
+        let plantuml_text = self.expand(plantuml_text, user)?;
+        let _permit = self.acquire().await?;
+        let image = self.convert(document_id, &plantuml_text, format).await?;
+        self.check_size(image)
+    }
+
+    /// Render background work, waiting as long as needed for a free slot
+    pub async fn render_queued(
+        &self,
+        document_id: DocumentId,
+        plantuml_text: &str,
+        format: ImageFormat,
+        user: Option<&str>,
+    ) -> Result<DiagramImage, RenderError> {
+        self.check_maintenance()?;
+        let plantuml_text = self.expand(plantuml_text, user)?;
+        let _permit = self
+            .inner
+            .permits
+            .acquire()
+            .await
+            .expect("render semaphore is never closed");
+        let image = self.convert(document_id, &plantuml_text, format).await?;
+        self.check_size(image)
+    }
+
+    /// `plantuml_text` with the shared include files and those of `user` expanded
+    fn expand<'a>(&self, plantuml_text: &'a str, user: Option<&str>) -> Result<Cow<'a, str>, RenderError> {
+        self.inner
+            .includes
+            .expand(plantuml_text, user)
+            .map_err(RenderError::Include)
+    }
+
+    /// Ask the PlantUML server for a syntax error, rejecting the request in
+    /// maintenance mode or when the queue is full
+    ///
+    /// The source is sent without editor pragmas expanded so the reported line
+    /// matches the editor.
+    pub async fn check_syntax(&self, plantuml_text: &str) -> Result<Option<SyntaxError>, RenderError> {
+        self.check_maintenance()?;
+        let _permit = self.acquire().await?;
+        Ok(self.client().check_syntax(plantuml_text).await?)
+    }
+
+    /// Validate and export one of several sources, mapping failures to ErrorCode
+    ///
+    /// Style policy warnings cannot be returned per file and are only logged.
+    pub async fn render_source(
+        &self,
+        plantuml_text: &str,
+        format: ImageFormat,
+        user: Option<&str>,
+    ) -> Result<Vec<u8>, ErrorCode> {
+        validate_plantuml_content(plantuml_text).map_err(|e| e.to_error_code())?;
+        let (plantuml_text, warning) = self.enforce_policy(plantuml_text, user)?;
+        if let Some(warning) = warning {
+            tracing::warn!("Exported despite the style policy: {:?}", warning);
+        }
+
+        let result = self
+            .render_queued(DocumentId::new(), &plantuml_text, format, user)
+            .await;
+
+        result.map(|image| image.data).map_err(|e| {
+            tracing::warn!("PlantUML render failed: {}", e);
+            e.to_error_code(|e| {
+                if e.to_string().contains("エンコードエラー") {
+                    ErrorCode::EncodingError {
+                        encoding: "UTF-8".to_string(),
+                    }
+                } else {
+                    ErrorCode::ExportError {
+                        format: format_name(format).to_string(),
+                    }
+                }
+            })
+        })
+    }
+
+    /// Check that the PlantUML server answers within `timeout`
+    ///
+    /// Bypasses the concurrency limit so a busy server still reports as reachable.
+    pub async fn probe(&self, timeout: Duration) -> Result<(), String> {
+        let probe = self.convert(DocumentId::new(), PROBE_SOURCE, ImageFormat::Svg);
+        match tokio::time::timeout(timeout, probe).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("no response within {}ms", timeout.as_millis())),
+        }
+    }
+
+    async fn acquire(&self) -> Result<SemaphorePermit<'_>, RenderError> {
+        if let Ok(permit) = self.inner.permits.try_acquire() {
+            return Ok(permit);
+        }
+
+        // The guard also releases the slot when the client disconnects mid-wait
+        let waiting = WaitingGuard::new(&self.inner.waiting);
+        if waiting.position >= self.inner.max_waiting.load(Ordering::SeqCst) {
+            tracing::warn!(
+                "Render queue full: {} requests waiting for {} slots",
+                waiting.position,
+                self.inner.max_concurrency
+            );
+            return Err(RenderError::QueueFull {
+                max_concurrency: self.inner.max_concurrency,
+            });
+        }
+
+        let permit = self
+            .inner
+            .permits
+            .acquire()
+            .await
+            .expect("render semaphore is never closed");
+        drop(waiting);
+        Ok(permit)
+    }
+
+    fn check_maintenance(&self) -> Result<(), RenderError> {
+        if self.is_maintenance() {
+            return Err(RenderError::Maintenance);
+        }
+        Ok(())
+    }
+
+    /// The image, or SizeLimit when it is over the configured limits
+    fn check_size(&self, image: DiagramImage) -> Result<DiagramImage, RenderError> {
+        let limits = *self.inner.image_limits.read().expect("limits lock poisoned");
+        if let Err(code) = limits.check(image.format, &image.data) {
+            tracing::warn!("Rendered image rejected: {:?}", code);
+            return Err(RenderError::TooLarge(code));
+        }
+        Ok(image)
+    }
+
+    async fn convert(
+        &self,
+        document_id: DocumentId,
+        plantuml_text: &str,
+        format: ImageFormat,
+    ) -> Result<DiagramImage, ClientError> {
+        let client = self.client();
+        // Expand editor pragmas (e.g. the color-blind-safe palette) on every path
+        let plantuml_text = prepare_for_render(plantuml_text);
+        let started = Instant::now();
+        let result = match format {
+            ImageFormat::Png => client.convert_to_png(document_id, &plantuml_text).await,
+            ImageFormat::Svg => client.convert_to_svg(document_id, &plantuml_text).await,
+            ImageFormat::Txt => client.convert_to_txt(document_id, &plantuml_text).await,
+            ImageFormat::Pdf => client.convert_to_pdf(document_id, &plantuml_text).await,
+        };
+        self.inner
+            .metrics
+            .record_upstream(chrono::Utc::now().timestamp(), started.elapsed());
+        result
+    }
+}
+
+/// Counts a request as waiting for as long as the guard lives
+struct WaitingGuard<'a> {
+    counter: &'a AtomicUsize,
+    /// Number of requests that were already waiting
+    position: usize,
+}
+
+impl<'a> WaitingGuard<'a> {
+    fn new(counter: &'a AtomicUsize) -> Self {
+        let position = counter.fetch_add(1, Ordering::SeqCst);
+        Self { counter, position }
+    }
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Upper-case format name used in error messages
+pub fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "PNG",
+        ImageFormat::Svg => "SVG",
+        ImageFormat::Txt => "TXT",
+        ImageFormat::Pdf => "PDF",
+    }
+}
+
+/// MIME type of rendered images
+pub fn content_type(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Svg => "image/svg+xml",
+        ImageFormat::Txt => "text/plain; charset=utf-8",
+        ImageFormat::Pdf => "application/pdf",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rejects_when_queue_is_full() {
+        let renderer = Renderer::new("http://127.0.0.1:9".to_string(), 1, 0, Arc::default(), Arc::default())
+            .unwrap();
+        let _busy = renderer.inner.permits.acquire().await.unwrap();
+
+        let result = renderer
+            .render(DocumentId::new(), "@startuml\n@enduml", ImageFormat::Png, None)
+            .await;
+        assert!(matches!(result, Err(RenderError::QueueFull { max_concurrency: 1 })));
+        assert_eq!(renderer.inner.waiting.load(Ordering::SeqCst), 0);
+
+        // A reload can let requests wait again
+        renderer.set_max_waiting(1);
+        assert_eq!(renderer.inner.max_waiting.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_a_free_slot() {
+        let renderer = Renderer::new("http://127.0.0.1:9".to_string(), 1, 1, Arc::default(), Arc::default())
+            .unwrap();
+        let busy = renderer.inner.permits.acquire().await.unwrap();
+
+        let waiting = renderer.render(DocumentId::new(), "@startuml\n@enduml", ImageFormat::Png, None);
+        tokio::pin!(waiting);
+        tokio::select! {
+            _ = &mut waiting => panic!("render should wait for the busy slot"),
+            _ = tokio::time::sleep(std::time::Duration::from_millis(50)) => {}
+        }
+        assert_eq!(renderer.inner.waiting.load(Ordering::SeqCst), 1);
+
+        drop(busy);
+        // Nothing listens on the discard port, so the call itself fails
+        let result = waiting.await;
+        assert!(matches!(result, Err(RenderError::Client(_))));
+        assert_eq!(renderer.inner.waiting.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() {
+        let renderer = Renderer::new("http://127.0.0.1:9".to_string(), 1, 1, Arc::default(), Arc::default())
+            .unwrap();
+        renderer.set_maintenance(true);
+        let result = renderer
+            .render_queued(DocumentId::new(), "@startuml\n@enduml", ImageFormat::Png, None)
+            .await;
+        assert!(matches!(result, Err(RenderError::Maintenance)));
+        assert_eq!(
+            renderer.render_source("@startuml\nA -> B\n@enduml", ImageFormat::Svg, None).await,
+            Err(ErrorCode::Maintenance)
+        );
+
+        renderer.set_maintenance(false);
+        let result = renderer
+            .render(DocumentId::new(), "@startuml\n@enduml", ImageFormat::Png, None)
+            .await;
+        assert!(matches!(result, Err(RenderError::Client(_))));
+    }
+
+    #[tokio::test]
+    async fn test_expands_the_users_includes() {
+        let includes = Arc::new(IncludeStore::new());
+        let renderer = Renderer::new("http://127.0.0.1:9".to_string(), 1, 1, Arc::default(), includes.clone())
+            .unwrap();
+        let long = "a".repeat(plantuml_editor_core::MAX_CONTENT_CHARS);
+        includes.save("long.iuml", long, Some("alice")).unwrap();
+        includes.save("header.iuml", "header 社外秘".to_string(), Some("alice")).unwrap();
+
+        let source = "@startuml\n!include <long>\n@enduml";
+        let result = renderer.render(DocumentId::new(), source, ImageFormat::Png, Some("alice")).await;
+        assert!(matches!(
+            result,
+            Err(RenderError::Include(ErrorCode::ValidationTextLimit { .. }))
+        ));
+        // Other users do not see the file, so it is left to PlantUML
+        let result = renderer.render_queued(DocumentId::new(), source, ImageFormat::Png, Some("bob")).await;
+        assert!(matches!(result, Err(RenderError::Client(_))));
+        assert!(matches!(
+            renderer.render_source(source, ImageFormat::Png, Some("alice")).await,
+            Err(ErrorCode::ValidationTextLimit { .. })
+        ));
+
+        renderer.set_style_policy(StylePolicy {
+            header: Some("社外秘".to_string()),
+            action: crate::policy::PolicyAction::Reject,
+            ..StylePolicy::default()
+        });
+        let source = "@startuml\n!include <header>\n@enduml";
+        let (text, warning) = renderer.enforce_policy(source, Some("alice")).unwrap();
+        assert_eq!((text.as_str(), warning), (source, None));
+        assert!(renderer.enforce_policy(source, Some("bob")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_images_over_the_limits() {
+        let renderer = Renderer::new("http://127.0.0.1:9".to_string(), 1, 1, Arc::default(), Arc::default())
+            .unwrap();
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1200px" height="80px"></svg>"#;
+        let image = DiagramImage {
+            document_id: DocumentId::new(),
+            format: ImageFormat::Svg,
+            data: svg.as_bytes().to_vec(),
+            dimensions: (1200, 80),
+            generated_at: 0,
+        };
+        assert!(renderer.check_size(image.clone()).is_ok());
+
+        renderer.set_image_limits(ImageLimits {
+            max_dimension: 1000,
+            max_bytes: 1024,
+        });
+        let error = renderer.check_size(image).unwrap_err();
+        assert_eq!(
+            error.to_error_code(|_| unreachable!()),
+            ErrorCode::SizeLimit {
+                actual_bytes: svg.len(),
+                max_bytes: 1024,
+                width: Some(1200),
+                height: Some(80),
+                max_dimension: Some(1000),
+            }
+        );
+    }
+}
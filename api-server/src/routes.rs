@@ -0,0 +1,1063 @@
+// Router construction
+
+use axum::{
+    routing::{delete, get, post, put},
+    Router,
+};
+use plantuml_editor_core::DocumentRole;
+use axum::http::{request::Parts, HeaderName, HeaderValue};
+use tower_http::{
+    cors::{AllowHeaders, AllowOrigin, Any, CorsLayer},
+    trace::TraceLayer,
+};
+
+use crate::handlers;
+use crate::middleware::{
+    with_admin, with_auth, with_body_limit, with_identity, with_metrics, with_quota,
+    with_request_id, with_role, REQUEST_ID_HEADER,
+};
+use crate::quota;
+use crate::state::AppState;
+
+/// Build the application router with per-route body limits from the config
+pub fn build_router(state: AppState) -> Router {
+    let limits = state.config.current().body_limits;
+
+    // Configure CORS; CORS_ALLOWED_ORIGINS is read per request so a reload
+    // takes effect immediately (any origin when unset, for local development)
+    let config = state.config.clone();
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(
+            move |origin: &HeaderValue, _: &Parts| {
+                origin
+                    .to_str()
+                    .is_ok_and(|origin| config.current().allows_origin(origin))
+            },
+        ))
+        .allow_methods(Any)
+        // A wildcard would not cover Authorization, needed for OIDC tokens
+        .allow_headers(AllowHeaders::mirror_request())
+        .expose_headers(
+            [quota::LIMIT_HEADER, quota::REMAINING_HEADER, quota::RESET_HEADER, REQUEST_ID_HEADER]
+                .map(|name| HeaderName::from_bytes(name.as_bytes()).expect("valid header name")),
+        );
+
+    // Monitoring does not count against quotas or in the metrics
+    let monitoring_routes = Router::new()
+        .route("/api/v1/health", get(handlers::health))
+        .route("/api/v1/ready", get(handlers::ready))
+        .route("/api/v1/config", get(handlers::get_config))
+        .route("/api/v1/status", get(handlers::status))
+        .route("/api/v1/metrics", get(handlers::metrics))
+        .route("/dashboard", get(handlers::dashboard));
+
+    // Operator controls, behind ADMIN_TOKEN
+    let admin_routes = Router::new()
+        .route(
+            "/api/v1/admin/maintenance",
+            get(handlers::get_maintenance).put(handlers::set_maintenance),
+        )
+        .route("/api/v1/admin/announcement", put(handlers::set_announcement))
+        .route(
+            "/api/v1/admin/includes/:name",
+            put(handlers::save_shared_include).delete(handlers::delete_shared_include),
+        );
+
+    let api_routes = Router::new()
+        .route("/api/v1/convert", post(handlers::convert))
+        .route("/api/v1/import/openapi", post(handlers::import_openapi))
+        .route("/api/v1/import/url", post(handlers::import_diagram_url))
+        .route("/api/v1/diagnostics", post(handlers::diagnostics))
+        .route("/api/v1/analyze", post(handlers::analyze))
+        .route("/api/v1/fonts", get(handlers::fonts))
+        .route("/api/v1/export/pipeline", get(handlers::export_pipeline))
+        .route("/api/v1/examples", get(handlers::list_examples))
+        .route("/api/v1/examples/:id/thumbnail", get(handlers::example_thumbnail))
+        .route("/share/:token", get(handlers::shared_image))
+        .route("/api/v1/jobs/:id", get(handlers::get_job))
+        .route("/api/v1/jobs/:id/events", get(handlers::job_events))
+        .route("/api/v1/jobs/:id/result", get(handlers::job_result));
+
+    // Server storage, behind OIDC login when OIDC_ISSUER is set
+    let storage_routes = Router::new()
+        .route(
+            "/api/v1/documents",
+            get(handlers::list_documents).post(handlers::create_document),
+        )
+        .route("/api/v1/documents/search", get(handlers::search_documents))
+        .route("/api/v1/activity", get(handlers::activity))
+        .route(
+            "/api/v1/folders",
+            get(handlers::list_folders).post(handlers::create_folder),
+        )
+        .route("/api/v1/folders/:id", put(handlers::update_folder))
+        .route("/api/v1/includes", get(handlers::list_includes))
+        .route(
+            "/api/v1/includes/:name",
+            put(handlers::save_include).delete(handlers::delete_include),
+        )
+        .route(
+            "/api/v1/settings",
+            get(handlers::get_settings).put(handlers::put_settings),
+        );
+
+    // Single-document routes, grouped by the role they require
+    let viewer_routes = Router::new()
+        .route("/api/v1/documents/:id", get(handlers::get_document))
+        .route(
+            "/api/v1/documents/:id/duplicate",
+            post(handlers::duplicate_document),
+        )
+        .route("/api/v1/documents/:id/image", get(handlers::document_image))
+        .route(
+            "/api/v1/documents/:id/archive",
+            get(handlers::export_document_archive),
+        )
+        .route("/api/v1/documents/:id/roles", get(handlers::get_roles));
+    let editor_routes = Router::new()
+        .route(
+            "/api/v1/documents/:id",
+            put(handlers::update_document).patch(handlers::patch_document),
+        )
+        .route("/api/v1/documents/:id/folder", put(handlers::move_document));
+    let owner_routes = Router::new()
+        .route("/api/v1/documents/:id", delete(handlers::delete_document))
+        .route("/api/v1/documents/:id/share", post(handlers::create_share_link))
+        .route("/api/v1/documents/:id/roles", put(handlers::set_role));
+    let storage_routes = storage_routes
+        .merge(with_role(viewer_routes, &state, DocumentRole::Viewer))
+        .merge(with_role(editor_routes, &state, DocumentRole::Editor))
+        .merge(with_role(owner_routes, &state, DocumentRole::Owner));
+    // Open routes take the caller only from a verified token under OIDC
+    let api_routes = with_identity(api_routes, &state).merge(with_auth(storage_routes, &state));
+
+    // Export may carry embedded options and themes
+    let export_routes = Router::new()
+        .route("/api/v1/export", post(handlers::export))
+        .route(
+            "/api/v1/export/download",
+            get(handlers::export_download).post(handlers::export_download),
+        );
+
+    // Bulk export accepts larger uploads than the JSON endpoints
+    let bulk_storage_routes = Router::new().route(
+        "/api/v1/documents/import",
+        post(handlers::import_document_archive),
+    );
+    let bulk_routes = Router::new()
+        .route("/api/v1/export/archive", post(handlers::export_archive))
+        .route("/api/v1/jobs/export", post(handlers::create_export_job));
+    let bulk_routes = with_identity(bulk_routes, &state).merge(with_auth(bulk_storage_routes, &state));
+
+    let metered_routes = Router::new()
+        .merge(with_body_limit(api_routes, limits.default))
+        .merge(with_body_limit(with_identity(export_routes, &state), limits.export))
+        .merge(with_body_limit(bulk_routes, limits.bulk));
+
+    let routes = Router::new()
+        .merge(with_body_limit(monitoring_routes, limits.default))
+        .merge(with_body_limit(with_admin(admin_routes, &state), limits.default))
+        .merge(with_metrics(with_quota(metered_routes, &state), &state));
+
+    with_request_id(routes)
+        .layer(cors)
+        .layer(TraceLayer::new_for_http())
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BodyLimits, Config, ExportPipelineConfig, QuotaConfig};
+    use crate::middleware::USER_HEADER;
+    use crate::pipeline::PostProcessor;
+    use crate::policy::{PolicyAction, StylePolicy};
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header, Request, StatusCode};
+    use plantuml_editor_core::{ConvertResponse, DocumentId, ErrorCode};
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        let config = Config {
+            body_limits: BodyLimits {
+                default: 1024,
+                export: 4096,
+                bulk: 8192,
+            },
+            ..Config::default()
+        };
+        build_router(AppState::new(config).unwrap())
+    }
+
+    fn convert_body(text_len: usize) -> String {
+        format!(
+            r#"{{"plantuml_text":"{}","format":"svg"}}"#,
+            "a".repeat(text_len)
+        )
+    }
+
+    async fn post_json(uri: &str, body: String) -> (StatusCode, ConvertResponse) {
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_returns_error_envelope() {
+        let (status, response) = post_json("/api/v1/convert", convert_body(2000)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.result.code, ErrorCode::RequestTooLarge { max_bytes: 1024 });
+        assert!(response.image_data.is_none());
+    }
+
+    fn gzip(body: &str) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compressed_bodies_are_decompressed() {
+        let body = r#"{"plantuml_text":"@startuml\nloop\nA -> B","check_syntax":false}"#;
+        let request = Request::post("/api/v1/diagnostics")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from(gzip(body)))
+            .unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "DiagnosticsReported");
+
+        // The limit applies to the decompressed size
+        let compressed = gzip(&convert_body(2000));
+        assert!(compressed.len() < 1024);
+        let request = Request::post("/api/v1/convert")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .header(header::CONTENT_LENGTH, compressed.len())
+            .body(Body::from(compressed))
+            .unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "RequestTooLarge");
+    }
+
+    #[tokio::test]
+    async fn test_export_has_its_own_limit() {
+        // Over the default limit but within the export limit: reaches the handler
+        let (_, response) = post_json("/api/v1/export", format!("{:<3000}", convert_body(0))).await;
+        assert_eq!(response.result.code, ErrorCode::ValidationEmpty);
+
+        let (status, response) = post_json("/api/v1/export", convert_body(5000)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.result.code, ErrorCode::RequestTooLarge { max_bytes: 4096 });
+    }
+
+    #[tokio::test]
+    async fn test_download_reads_query_parameters() {
+        let request = Request::get("/api/v1/export/download?plantuml_text=%20&format=svg")
+            .body(Body::empty())
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_DISPOSITION).is_none());
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: ConvertResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(response.result.code, ErrorCode::ValidationEmpty);
+    }
+
+    #[tokio::test]
+    async fn test_share_rejects_invalid_token() {
+        let request = Request::get("/share/not-a-token").body(Body::empty()).unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: ConvertResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(response.result.code, ErrorCode::ShareLinkInvalid);
+    }
+
+    async fn call(app: &Router, request: Request<Body>) -> serde_json::Value {
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    fn as_user(method: &str, uri: &str, user: &str, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(USER_HEADER, user)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_document_roles_are_enforced() {
+        let app = router();
+        let created = call(
+            &app,
+            as_user("POST", "/api/v1/documents", "alice", r#"{"content":"@startuml\nA -> B\n@enduml"}"#),
+        )
+        .await;
+        let uri = format!("/api/v1/documents/{}", created["document"]["id"].as_str().unwrap());
+
+        let denied = call(&app, as_user("GET", &uri, "bob", "")).await;
+        assert_eq!(denied["result"]["code"]["type"], "DocumentAccessDenied");
+        assert_eq!(denied["result"]["code"]["required"], "viewer");
+
+        let roles_uri = format!("{}/roles", uri);
+        let body = r#"{"user":"bob","role":"viewer"}"#;
+        let granted = call(&app, as_user("PUT", &roles_uri, "alice", body)).await;
+        assert_eq!(granted["roles"]["bob"], "viewer");
+
+        let loaded = call(&app, as_user("GET", &uri, "bob", "")).await;
+        assert_eq!(loaded["role"], "viewer");
+
+        let body = r#"{"content":"@startuml\nB -> A\n@enduml"}"#;
+        let denied = call(&app, as_user("PUT", &uri, "bob", body)).await;
+        assert_eq!(denied["result"]["code"]["required"], "editor");
+        let denied = call(&app, as_user("DELETE", &uri, "bob", "")).await;
+        assert_eq!(denied["result"]["code"]["required"], "owner");
+    }
+
+    #[tokio::test]
+    async fn test_search_route_is_not_a_document_id() {
+        let app = router();
+        let body = r#"{"content":"@startuml\nUser -> Server : login\n@enduml"}"#;
+        call(&app, as_user("POST", "/api/v1/documents", "alice", body)).await;
+
+        let found = call(&app, as_user("GET", "/api/v1/documents/search?q=login", "alice", "")).await;
+        assert_eq!(found["hits"].as_array().unwrap().len(), 1);
+        assert_eq!(found["hits"][0]["snippet"], "User -> Server : login");
+
+        let hidden = call(&app, as_user("GET", "/api/v1/documents/search?q=login", "bob", "")).await;
+        assert!(hidden["hits"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_activity_records_changes() {
+        let app = router();
+        let body = r#"{"content":"@startuml\nA -> B\n@enduml","title":"Flow"}"#;
+        let created = call(&app, as_user("POST", "/api/v1/documents", "alice", body)).await;
+        let uri = format!("/api/v1/documents/{}", created["document"]["id"].as_str().unwrap());
+        call(&app, as_user("DELETE", &uri, "alice", "")).await;
+
+        let feed = call(&app, as_user("GET", "/api/v1/activity", "alice", "")).await;
+        let entries = feed["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["kind"], "deleted");
+        assert_eq!(entries[0]["user"], "alice");
+        assert_eq!(entries[1]["kind"], "created");
+        assert_eq!(entries[1]["title"], "Flow");
+
+        // The document was private to alice
+        let feed = call(&app, as_user("GET", "/api/v1/activity", "bob", "")).await;
+        assert!(feed["entries"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stale_update_conflicts() {
+        let app = router();
+        let body = r#"{"content":"@startuml\nA -> B\n@enduml"}"#;
+        let created = call(&app, as_user("POST", "/api/v1/documents", "alice", body)).await;
+        let uri = format!("/api/v1/documents/{}", created["document"]["id"].as_str().unwrap());
+        assert_eq!(created["document"]["version"], 1);
+
+        let update = r#"{"content":"@startuml\nB -> A\n@enduml","base_version":1}"#;
+        let saved = call(&app, as_user("PUT", &uri, "alice", update)).await;
+        assert_eq!(saved["document"]["version"], 2);
+
+        let conflict = call(&app, as_user("PUT", &uri, "alice", update)).await;
+        assert_eq!(conflict["result"]["code"]["type"], "DocumentConflict");
+        assert_eq!(conflict["result"]["code"]["current_version"], 2);
+        assert!(conflict.get("document").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_patch_saves_changed_lines() {
+        use plantuml_editor_core::diff::line_patch;
+
+        let app = router();
+        let old = "@startuml\nA -> B\n@enduml";
+        let body = serde_json::json!({ "content": old }).to_string();
+        let created = call(&app, as_user("POST", "/api/v1/documents", "alice", &body)).await;
+        let uri = format!("/api/v1/documents/{}", created["document"]["id"].as_str().unwrap());
+
+        let new = "@startuml\nA -> B\nB -> C\n@enduml";
+        let patch = |base_version: u64| {
+            serde_json::json!({ "patch": line_patch(old, new), "base_version": base_version }).to_string()
+        };
+        let saved = call(&app, as_user("PATCH", &uri, "alice", &patch(1))).await;
+        assert_eq!(saved["document"]["content"], new);
+        assert_eq!(saved["document"]["version"], 2);
+
+        // A patch made from an older version is not applied to the newer text
+        let conflict = call(&app, as_user("PATCH", &uri, "alice", &patch(1))).await;
+        assert_eq!(conflict["result"]["code"]["type"], "DocumentConflict");
+        assert_eq!(conflict["result"]["code"]["current_version"], 2);
+        let misfit = r#"{"patch":{"start":9,"removed":1,"lines":[]},"base_version":2}"#;
+        let conflict = call(&app, as_user("PATCH", &uri, "alice", misfit)).await;
+        assert_eq!(conflict["result"]["code"]["type"], "DocumentConflict");
+
+        let denied = call(&app, as_user("PATCH", &uri, "bob", &patch(2))).await;
+        assert_eq!(denied["result"]["code"]["required"], "editor");
+    }
+
+    #[tokio::test]
+    async fn test_import_document_archive() {
+        let app = router();
+        let source = "@startuml\nA -> B\n@enduml".to_string();
+        let mut document = plantuml_editor_core::PlantUMLDocument::new(source);
+        document.version = 3;
+        let archive =
+            crate::archive::write_document_archive(document.clone(), Vec::new(), Vec::new())
+                .unwrap();
+
+        let request = Request::post("/api/v1/documents/import")
+            .header(USER_HEADER, "alice")
+            .header(header::CONTENT_TYPE, "application/zip")
+            .body(Body::from(archive))
+            .unwrap();
+        let imported = call(&app, request).await;
+        assert_eq!(imported["result"]["code"]["type"], "DocumentSaved");
+        assert_eq!(imported["document"]["id"], document.id.0.to_string());
+        assert_eq!(imported["document"]["version"], 3);
+
+        // Imported documents are private to the importer
+        let uri = format!("/api/v1/documents/{}", document.id.0);
+        let denied = call(&app, as_user("GET", &uri, "bob", "")).await;
+        assert_eq!(denied["result"]["code"]["type"], "DocumentAccessDenied");
+
+        let invalid = as_user("POST", "/api/v1/documents/import", "alice", "not a zip");
+        let invalid = call(&app, invalid).await;
+        assert_eq!(invalid["result"]["code"]["type"], "ImportError");
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_without_syntax_check() {
+        let body = r#"{"plantuml_text":"@startuml\nloop\nA -> B","check_syntax":false}"#;
+        let request = Request::post("/api/v1/diagnostics")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "DiagnosticsReported");
+        assert_eq!(response["result"]["code"]["errors"], 1);
+        assert_eq!(response["result"]["code"]["warnings"], 1);
+
+        let diagnostics = response["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics[0]["code"], "missing-end");
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+        assert_eq!(diagnostics[1]["code"], "unclosed-group");
+    }
+
+    #[tokio::test]
+    async fn test_openapi_operations_survive_failed_generation() {
+        let spec = "openapi: 3.0.0\ninfo:\n  title: Users\npaths:\n  /users:\n    get:\n      operationId: listUsers\n";
+        let import = |request: serde_json::Value| {
+            Request::post("/api/v1/import/openapi")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(request.to_string()))
+                .unwrap()
+        };
+
+        let failed = call(&router(), import(serde_json::json!({"spec": spec, "operations": ["missing"]}))).await;
+        assert_eq!(failed["result"]["code"]["type"], "ImportError");
+        assert_eq!(failed["operations"][0]["id"], "listUsers");
+
+        let listed = call(&router(), import(serde_json::json!({"spec": spec, "list_only": true}))).await;
+        assert_eq!(listed["result"]["code"]["type"], "ImportOk");
+        assert_eq!(listed["operations"][0]["id"], "listUsers");
+        assert!(listed.get("plantuml_text").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_diagram_url() {
+        let import = |url: &str| {
+            let body = serde_json::json!({ "url": url }).to_string();
+            let request = Request::post("/api/v1/import/url")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap();
+            async move { call(&router(), request).await }
+        };
+        // Hex form of the PlantUML URL encoding
+        let response = import("https://www.plantuml.com/plantuml/svg/~h407374617274756d6c0a41202d3e20420a40656e64756d6c").await;
+        assert_eq!(response["result"]["code"]["type"], "ImportOk");
+        assert_eq!(response["plantuml_text"], "@startuml\nA -> B\n@enduml");
+
+        let response = import("https://example.com/diagram.png").await;
+        assert_eq!(response["result"]["code"]["type"], "ImportError");
+        assert!(response.get("plantuml_text").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_as_sarif() {
+        let body = r#"{"plantuml_text":"@startuml\nloop\nA -> B","check_syntax":false}"#;
+        let request = Request::post("/api/v1/diagnostics?format=sarif&path=docs/login.puml")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/sarif+json");
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let sarif: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "missing-end");
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "docs/login.puml"
+        );
+    }
+    #[tokio::test]
+    async fn test_config_is_redacted_and_reloadable() {
+        let state = AppState::new(Config {
+            share_secret: Some("s3cret".to_string()),
+            ..Config::default()
+        })
+        .unwrap();
+        let app = build_router(state.clone());
+
+        let request = Request::get("/api/v1/config").body(Body::empty()).unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["config"]["share_secret"], "***");
+        assert!(!response.to_string().contains("s3cret"));
+        assert_eq!(response["reloadable"][0], "PLANTUML_SERVER_URL");
+        assert!(response["last_reload"].is_null());
+
+        // CORS follows the reloaded origin list
+        let preflight = || {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/v1/health")
+                .header(header::ORIGIN, "https://other.example")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let allowed = |response: axum::response::Response| {
+            response
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        };
+        assert!(allowed(app.clone().oneshot(preflight()).await.unwrap()));
+        crate::reload::apply(
+            &state,
+            Config {
+                cors_origins: vec!["https://editor.example".to_string()],
+                share_secret: Some("s3cret".to_string()),
+                ..Config::default()
+            },
+        );
+        assert!(!allowed(app.clone().oneshot(preflight()).await.unwrap()));
+
+        let request = Request::get("/api/v1/config").body(Body::empty()).unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["last_reload"]["applied"][0], "CORS_ALLOWED_ORIGINS");
+    }
+    #[tokio::test]
+    async fn test_metrics_count_api_requests() {
+        let app = router();
+        let body = r#"{"plantuml_text":"@startuml\nloop\nA -> B","check_syntax":false}"#;
+        let request = Request::post("/api/v1/diagnostics")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        let response = call(&app, request).await;
+        // The body still reaches the client after being inspected
+        assert_eq!(response["result"]["code"]["type"], "DiagnosticsReported");
+
+        let request = Request::get("/api/v1/metrics").body(Body::empty()).unwrap();
+        let metrics = call(&app, request).await;
+        assert_eq!(metrics["requests_last_hour"], 1);
+        assert_eq!(metrics["error_codes"]["DiagnosticsReported"], 1);
+
+        let request = Request::get("/dashboard").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let html = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&html).contains("/api/v1/metrics"));
+    }
+    #[tokio::test]
+    async fn test_admin_maintenance_mode() {
+        let app = build_router(
+            AppState::new(Config {
+                admin_token: Some("admin-key".to_string()),
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let maintenance = |token: &str, enabled: bool| {
+            Request::put("/api/v1/admin/maintenance")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::from(format!(r#"{{"enabled":{}}}"#, enabled)))
+                .unwrap()
+        };
+
+        let response = call(&app, maintenance("wrong", true)).await;
+        assert_eq!(response["result"]["code"]["type"], "AdminAccessDenied");
+        let response = call(&app, maintenance("admin-key", true)).await;
+        assert_eq!(response["maintenance"], true);
+
+        let request = Request::post("/api/v1/convert")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"plantuml_text":"@startuml\nA -> B\n@enduml","format":"svg"}"#))
+            .unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["result"]["code"]["type"], "Maintenance");
+
+        // The syntax check is skipped without calling PlantUML
+        let request = Request::post("/api/v1/diagnostics")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"plantuml_text":"@startuml\nA -> B\n@enduml","check_syntax":true}"#))
+            .unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["diagnostics"][0]["code"], "syntax-check-skipped");
+        assert!(response["diagnostics"][0]["message"].as_str().unwrap().contains("メンテナンス"));
+
+        let response = call(&app, maintenance("admin-key", false)).await;
+        assert_eq!(response["maintenance"], false);
+    }
+
+    #[tokio::test]
+    async fn test_status_announcement() {
+        let app = build_router(
+            AppState::new(Config {
+                admin_token: Some("admin-key".to_string()),
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let announce = |message: &str| {
+            Request::put("/api/v1/admin/announcement")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, "Bearer admin-key")
+                .body(Body::from(format!(r#"{{"message":{}}}"#, message)))
+                .unwrap()
+        };
+        let status = || Request::get("/api/v1/status").body(Body::empty()).unwrap();
+
+        let response = call(&app, status()).await;
+        assert_eq!(
+            response,
+            serde_json::json!({ "maintenance": false, "announcement": null })
+        );
+
+        call(&app, announce(r#"" 18時から停止します ""#)).await;
+        let response = call(&app, status()).await;
+        assert_eq!(response["announcement"], "18時から停止します");
+
+        call(&app, announce("null")).await;
+        let response = call(&app, status()).await;
+        assert_eq!(response["announcement"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_include_files() {
+        let app = build_router(
+            AppState::new(Config {
+                admin_token: Some("admin-key".to_string()),
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let content = |shared: bool| {
+            format!(r#"{{"content":"skinparam shadowing false","shared":{}}}"#, shared)
+        };
+        let as_admin = |method: &str, token: &str| {
+            Request::builder()
+                .method(method)
+                .uri("/api/v1/admin/includes/style.iuml")
+                .header(header::CONTENT_TYPE, "application/json")
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::from(content(false)))
+                .unwrap()
+        };
+
+        // Only operators write shared files
+        let response = call(&app, as_user("PUT", "/api/v1/includes/style.iuml", "alice", &content(true))).await;
+        assert_eq!(response["result"]["code"]["type"], "AdminAccessDenied");
+        let anonymous = Request::put("/api/v1/includes/style.iuml")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(content(false)))
+            .unwrap();
+        let response = call(&app, anonymous).await;
+        assert_eq!(response["result"]["code"]["type"], "AuthenticationRequired");
+        let response = call(&app, as_admin("PUT", "wrong")).await;
+        assert_eq!(response["result"]["code"]["type"], "AdminAccessDenied");
+        let response = call(&app, as_admin("PUT", "admin-key")).await;
+        assert_eq!(response["result"]["code"]["type"], "IncludeSaved");
+        assert!(response["include"].get("owner").is_none());
+
+        let response = call(&app, as_user("PUT", "/api/v1/includes/mine.iuml", "alice", &content(false))).await;
+        assert_eq!(response["include"]["owner"], "alice");
+        let response = call(&app, as_user("PUT", "/api/v1/includes/style", "alice", &content(false))).await;
+        assert_eq!(response["result"]["code"]["type"], "IncludeNameInvalid");
+        let long = format!(r#"{{"content":"{}"}}"#, "a".repeat(plantuml_editor_core::MAX_CONTENT_CHARS + 1));
+        let response = call(&app, as_user("PUT", "/api/v1/includes/long.iuml", "alice", &long)).await;
+        assert_eq!(response["result"]["code"]["type"], "ValidationTextLimit");
+
+        let count = |list: serde_json::Value| list["includes"].as_array().unwrap().len();
+        assert_eq!(count(call(&app, as_user("GET", "/api/v1/includes", "alice", "")).await), 2);
+        assert_eq!(count(call(&app, as_user("GET", "/api/v1/includes", "bob", "")).await), 1);
+
+        let response = call(&app, as_user("DELETE", "/api/v1/includes/mine.iuml", "bob", "")).await;
+        assert_eq!(response["result"]["code"]["type"], "IncludeNotFound");
+        let response = call(&app, as_user("DELETE", "/api/v1/includes/mine.iuml", "alice", "")).await;
+        assert_eq!(response["result"]["code"]["type"], "IncludeDeleted");
+        let response = call(&app, as_user("DELETE", "/api/v1/includes/style.iuml?shared=true", "bob", "")).await;
+        assert_eq!(response["result"]["code"]["type"], "AdminAccessDenied");
+        let response = call(&app, as_admin("DELETE", "admin-key")).await;
+        assert_eq!(response["result"]["code"]["type"], "IncludeDeleted");
+    }
+
+    #[tokio::test]
+    async fn test_user_settings() {
+        let app = router();
+        let settings = |indent_width: usize, updated_at: i64| {
+            format!(r#"{{"settings":{{"indent_width":{}}},"updated_at":{}}}"#, indent_width, updated_at)
+        };
+
+        let response = call(&app, as_user("GET", "/api/v1/settings", "alice", "")).await;
+        assert_eq!(response["result"]["code"]["type"], "DocumentLoaded");
+        assert!(response.get("settings").is_none());
+
+        let response = call(&app, as_user("PUT", "/api/v1/settings", "alice", &settings(4, 10))).await;
+        assert_eq!(response["result"]["code"]["type"], "SettingsSaved");
+        assert_eq!(response["settings"]["settings"]["indent_width"], 4);
+        assert_eq!(response["settings"]["settings"]["render_pause_ms"], 300);
+
+        // An older write gets the newer settings back
+        let response = call(&app, as_user("PUT", "/api/v1/settings", "alice", &settings(0, 5))).await;
+        assert_eq!(response["settings"]["updated_at"], 10);
+        let response = call(&app, as_user("GET", "/api/v1/settings", "alice", "")).await;
+        assert_eq!(response["settings"]["settings"]["indent_width"], 4);
+
+        let response = call(&app, as_user("GET", "/api/v1/settings", "bob", "")).await;
+        assert!(response.get("settings").is_none());
+        let anonymous = Request::builder().uri("/api/v1/settings").body(Body::empty()).unwrap();
+        let response = call(&app, anonymous).await;
+        assert_eq!(response["result"]["code"]["type"], "AuthenticationRequired");
+    }
+
+    #[tokio::test]
+    async fn test_export_rejected_by_style_policy() {
+        let app = build_router(
+            AppState::new(Config {
+                style_policy: StylePolicy {
+                    header: Some("社外秘".to_string()),
+                    action: PolicyAction::Reject,
+                    ..StylePolicy::default()
+                },
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let request = Request::post("/api/v1/export")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"plantuml_text":"@startuml\nA -> B\n@enduml","format":"png"}"#))
+            .unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["result"]["code"]["type"], "StylePolicyViolation");
+        assert_eq!(response["result"]["code"]["missing"][0], "header 社外秘");
+    }
+
+    #[tokio::test]
+    async fn test_export_pipeline() {
+        let app = build_router(
+            AppState::new(Config {
+                export_pipeline: ExportPipelineConfig {
+                    steps: vec![PostProcessor::Optimize, PostProcessor::Rename],
+                    ..ExportPipelineConfig::default()
+                },
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let request = Request::get("/api/v1/export/pipeline").body(Body::empty()).unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(
+            response,
+            serde_json::json!({
+                "default": ["optimize", "rename"],
+                "available": ["deterministic", "optimize", "embed-source", "rename"],
+            })
+        );
+
+        // Watermark needs EXPORT_WATERMARK; rejected before rendering
+        let request = Request::post("/api/v1/export")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"plantuml_text":"@startuml\nA -> B\n@enduml","format":"svg","pipeline":["optimize","watermark"]}"#,
+            ))
+            .unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["result"]["code"]["type"], "PostProcessorUnknown");
+        assert_eq!(response["result"]["code"]["name"], "watermark");
+    }
+
+    #[tokio::test]
+    async fn test_analyze() {
+        let analyze = |text: &str| {
+            Request::post("/api/v1/analyze")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::json!({ "plantuml_text": text }).to_string()))
+                .unwrap()
+        };
+        let response = call(&router(), analyze("@startuml\nclass A\nclass B\nA --> B\n@enduml")).await;
+        assert_eq!(response["result"]["code"]["type"], "DiagramAnalyzed");
+        assert_eq!(response["analysis"]["diagram_type"], "class");
+        assert_eq!(response["analysis"]["classes"], 2);
+        assert_eq!(response["analysis"]["complexity"], 3);
+
+        let response = call(&router(), analyze("  ")).await;
+        assert_eq!(response["result"]["code"]["type"], "ValidationEmpty");
+        assert!(response.get("analysis").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_font_must_be_allowed() {
+        let app = build_router(
+            AppState::new(Config {
+                allowed_fonts: vec!["Noto Sans CJK JP".to_string()],
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let fonts = Request::get("/api/v1/fonts").body(Body::empty()).unwrap();
+        let response = call(&app, fonts).await;
+        assert_eq!(response, serde_json::json!({ "fonts": ["Noto Sans CJK JP"] }));
+
+        let request = Request::post("/api/v1/convert")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"plantuml_text":"@startuml\nA -> B\n@enduml","format":"svg","font":"Comic Sans MS"}"#,
+            ))
+            .unwrap();
+        let response = call(&app, request).await;
+        assert_eq!(response["result"]["code"]["type"], "FontNotAllowed");
+        assert_eq!(response["result"]["code"]["font"], "Comic Sans MS");
+    }
+
+    #[tokio::test]
+    async fn test_examples() {
+        let request = Request::get("/api/v1/examples").body(Body::empty()).unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "DocumentLoaded");
+        assert_eq!(response["categories"][0], "シーケンス図");
+        assert_eq!(response["examples"][0]["id"], "sequence-login");
+        assert_eq!(
+            response["examples"][0]["thumbnail_url"],
+            "/api/v1/examples/sequence-login/thumbnail"
+        );
+
+        let request = Request::get("/api/v1/examples/missing/thumbnail")
+            .body(Body::empty())
+            .unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "DocumentNotFound");
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_are_disabled_without_token() {
+        let request = Request::get("/api/v1/admin/maintenance")
+            .header(header::AUTHORIZATION, "Bearer ")
+            .body(Body::empty())
+            .unwrap();
+        let response = call(&router(), request).await;
+        assert_eq!(response["result"]["code"]["type"], "AdminAccessDenied");
+    }
+
+    #[tokio::test]
+    async fn test_daily_quota() {
+        let app = build_router(
+            AppState::new(Config {
+                quota: QuotaConfig {
+                    daily_limit: 2,
+                    ..QuotaConfig::default()
+                },
+                ..Config::default()
+            })
+            .unwrap(),
+        );
+        let activity = || Request::get("/api/v1/activity").body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(activity()).await.unwrap();
+        assert_eq!(response.headers()[quota::LIMIT_HEADER], "2");
+        assert_eq!(response.headers()[quota::REMAINING_HEADER], "1");
+        app.clone().oneshot(activity()).await.unwrap();
+
+        let response = app.clone().oneshot(activity()).await.unwrap();
+        assert_eq!(response.headers()[quota::REMAINING_HEADER], "0");
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let response: ConvertResponse = serde_json::from_slice(&bytes).unwrap();
+        assert!(matches!(response.result.code, ErrorCode::QuotaExceeded { limit: 2, .. }));
+
+        // Monitoring is not metered
+        let health = Request::get("/api/v1/health").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(health).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(quota::LIMIT_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let app = router();
+        let health = |id: Option<&str>| {
+            let mut request = Request::get("/api/v1/health");
+            if let Some(id) = id {
+                request = request.header(REQUEST_ID_HEADER, id);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        // Generated when missing, and different for every request
+        let first = app.clone().oneshot(health(None)).await.unwrap();
+        let second = app.clone().oneshot(health(None)).await.unwrap();
+        let first = first.headers()[REQUEST_ID_HEADER].to_str().unwrap().to_string();
+        assert_eq!(first.len(), 36);
+        assert_ne!(second.headers()[REQUEST_ID_HEADER], first.as_str());
+
+        // Kept when sent by the client, replaced when malformed
+        let response = app.clone().oneshot(health(Some("proxy-42.a_b"))).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "proxy-42.a_b");
+        let response = app.clone().oneshot(health(Some("<script>"))).await.unwrap();
+        assert_ne!(response.headers()[REQUEST_ID_HEADER], "<script>");
+    }
+
+    #[tokio::test]
+    async fn test_server_storage_requires_oidc_login() {
+        use crate::auth::testing::{self, Signer};
+
+        let state = AppState::new(Config {
+            oidc: testing::config(),
+            ..Config::default()
+        })
+        .unwrap();
+        let signer = Signer::new("key-1");
+        state.auth.set_keys(signer.key_set());
+        let app = build_router(state);
+        let with_token = |method: &str, uri: &str, token: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // USER_HEADER alone no longer identifies the caller
+        let denied = call(&app, as_user("GET", "/api/v1/documents", "alice", "")).await;
+        assert_eq!(denied["result"]["code"]["type"], "AuthenticationRequired");
+        let expired = signer.token("alice", chrono::Utc::now().timestamp() - 3600);
+        let denied = call(&app, with_token("GET", "/api/v1/folders", &expired, "")).await;
+        assert_eq!(denied["result"]["code"]["type"], "AuthenticationRequired");
+
+        // The token's subject owns what it creates
+        let exp = chrono::Utc::now().timestamp() + 300;
+        let body = r#"{"content":"@startuml\nA -> B\n@enduml"}"#;
+        let created = call(&app, with_token("POST", "/api/v1/documents", &signer.token("alice", exp), body)).await;
+        let uri = format!("/api/v1/documents/{}", created["document"]["id"].as_str().unwrap());
+        let loaded = call(&app, with_token("GET", &uri, &signer.token("alice", exp), "")).await;
+        assert_eq!(loaded["role"], "owner");
+        let mut spoofed = with_token("GET", &uri, &signer.token("bob", exp), "");
+        spoofed.headers_mut().insert(USER_HEADER, "alice".parse().unwrap());
+        let denied = call(&app, spoofed).await;
+        assert_eq!(denied["result"]["code"]["type"], "DocumentAccessDenied");
+
+        // Conversions and monitoring stay open
+        let fonts = call(&app, Request::get("/api/v1/fonts").body(Body::empty()).unwrap()).await;
+        assert_ne!(fonts["result"]["code"]["type"], "AuthenticationRequired");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_links_open_without_login() {
+        use crate::auth::testing::{self, Signer};
+
+        let state = AppState::new(Config {
+            oidc: testing::config(),
+            ..Config::default()
+        })
+        .unwrap();
+        let signer = Signer::new("key-1");
+        state.auth.set_keys(signer.key_set());
+        let app = build_router(state.clone());
+
+        let token = signer.token("alice", chrono::Utc::now().timestamp() + 300);
+        let created = Request::post("/api/v1/documents")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"content":"@startuml\nA -> B\n@enduml"}"#))
+            .unwrap();
+        let created = call(&app, created).await;
+        let id = created["document"]["id"].as_str().unwrap();
+
+        let image = format!("/api/v1/documents/{}/image", id);
+        let denied = call(&app, Request::get(&image).body(Body::empty()).unwrap()).await;
+        assert_eq!(denied["result"]["code"]["type"], "AuthenticationRequired");
+
+        // The link sent to receivers reaches the renderer without credentials
+        let id = DocumentId(uuid::Uuid::parse_str(id).unwrap());
+        let link = crate::webhooks::image_link(&state.share, "", id, state.clock.now());
+        let response = app.clone().oneshot(Request::get(&link).body(Body::empty()).unwrap()).await.unwrap();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        if let Ok(opened) = serde_json::from_slice::<serde_json::Value>(&bytes) {
+            // No PlantUML server in tests: anything but an auth or link error
+            let code = &opened["result"]["code"]["type"];
+            for rejected in ["AuthenticationRequired", "DocumentAccessDenied", "ShareLinkInvalid", "ShareLinkExpired", "DocumentNotFound"] {
+                assert_ne!(code, rejected);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_includes_need_a_verified_user() {
+        use crate::auth::testing::{self, Signer};
+
+        let state = AppState::new(Config {
+            oidc: testing::config(),
+            style_policy: StylePolicy {
+                header: Some("社外秘".to_string()),
+                action: PolicyAction::Reject,
+                ..StylePolicy::default()
+            },
+            ..Config::default()
+        })
+        .unwrap();
+        let signer = Signer::new("key-1");
+        state.auth.set_keys(signer.key_set());
+        state
+            .includes
+            .save("confidential.iuml", "header 社外秘".to_string(), Some("alice"))
+            .unwrap();
+        let app = build_router(state);
+        let body = r#"{"plantuml_text":"@startuml\n!include <confidential>\nA -> B\n@enduml","format":"png"}"#;
+
+        // Naming alice in USER_HEADER does not reach her private file
+        let spoofed = as_user("POST", "/api/v1/export", "alice", body);
+        let response = call(&app, spoofed).await;
+        assert_eq!(response["result"]["code"]["type"], "StylePolicyViolation");
+
+        let token = signer.token("alice", chrono::Utc::now().timestamp() + 300);
+        let mut verified = as_user("POST", "/api/v1/export", "bob", body);
+        verified
+            .headers_mut()
+            .insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        let response = call(&app, verified).await;
+        assert_ne!(response["result"]["code"]["type"], "StylePolicyViolation");
+        assert_ne!(response["result"]["code"]["type"], "AuthenticationRequired");
+    }
+}
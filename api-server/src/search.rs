@@ -0,0 +1,270 @@
+// Full-text index over server-side document titles and content
+//
+// The store is in memory, so the index is too: an inverted index from terms
+// to per-document counts, updated whenever a document changes. ASCII words
+// are indexed as lowercase words; runs of other letters (Japanese has no
+// spaces between words) as overlapping two-character terms, so any substring
+// of two or more characters can be found.
+
+use plantuml_editor_core::{DocumentId, PlantUMLDocument, SearchHit};
+use std::collections::HashMap;
+
+/// Maximum number of hits returned for a query
+pub const MAX_HITS: usize = 50;
+
+/// Maximum snippet length in characters
+const SNIPPET_CHARS: usize = 80;
+
+/// A title match counts this many content matches
+const TITLE_WEIGHT: f32 = 3.0;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct TermCount {
+    title: u32,
+    content: u32,
+}
+
+/// Inverted index of the stored documents
+#[derive(Default)]
+pub struct SearchIndex {
+    postings: HashMap<String, HashMap<DocumentId, TermCount>>,
+    /// Terms of each document, for removing it again
+    terms: HashMap<DocumentId, Vec<String>>,
+}
+
+impl SearchIndex {
+    /// Index a document, replacing its previous version
+    pub fn upsert(&mut self, document: &PlantUMLDocument) {
+        self.remove(&document.id);
+
+        let mut counts: HashMap<String, TermCount> = HashMap::new();
+        for term in tokenize(document.title.as_deref().unwrap_or_default()) {
+            counts.entry(term).or_default().title += 1;
+        }
+        for term in tokenize(&document.content) {
+            counts.entry(term).or_default().content += 1;
+        }
+
+        self.terms
+            .insert(document.id, counts.keys().cloned().collect());
+        for (term, count) in counts {
+            self.postings.entry(term).or_default().insert(document.id, count);
+        }
+    }
+
+    pub fn remove(&mut self, id: &DocumentId) {
+        for term in self.terms.remove(id).unwrap_or_default() {
+            if let Some(documents) = self.postings.get_mut(&term) {
+                documents.remove(id);
+                if documents.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Documents containing every term of the query, best match first
+    ///
+    /// Scores add up the TF-IDF weight of each query term, with title
+    /// matches counting more than content matches.
+    pub fn search(&self, query: &str) -> Vec<(DocumentId, f32)> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total = self.terms.len() as f32;
+        let mut scores: Option<HashMap<DocumentId, f32>> = None;
+        for term in &terms {
+            let Some(documents) = self.postings.get(term) else {
+                return Vec::new();
+            };
+            let idf = (1.0 + total / documents.len() as f32).ln();
+            let term_scores = documents.iter().map(|(id, count)| {
+                let tf = count.content as f32 + TITLE_WEIGHT * count.title as f32;
+                (*id, tf * idf)
+            });
+
+            scores = Some(match scores {
+                None => term_scores.collect(),
+                // Keep only documents that matched every earlier term
+                Some(previous) => term_scores
+                    .filter_map(|(id, score)| previous.get(&id).map(|total| (id, total + score)))
+                    .collect(),
+            });
+        }
+
+        let mut ranked: Vec<(DocumentId, f32)> = scores.unwrap_or_default().into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0 .0.cmp(&b.0 .0)));
+        ranked.truncate(MAX_HITS);
+        ranked
+    }
+}
+
+/// Search hit for a document: the first matching line as the snippet
+pub fn hit(document: &PlantUMLDocument, query: &str, score: f32) -> SearchHit {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let line = document
+        .content
+        .lines()
+        .map(str::trim)
+        .find(|line| {
+            let line = line.to_lowercase();
+            words.iter().any(|word| line.contains(word.as_str()))
+        })
+        .or_else(|| document.content.lines().map(str::trim).find(|line| !line.is_empty()))
+        .unwrap_or_default();
+    let snippet: String = line.chars().take(SNIPPET_CHARS).collect();
+
+    SearchHit {
+        id: document.id,
+        title: document.title.clone(),
+        folder: document.folder,
+        score,
+        highlights: highlights(&snippet, &words),
+        snippet,
+    }
+}
+
+/// Character ranges of the query words in a snippet, sorted and non-overlapping
+fn highlights(snippet: &str, words: &[String]) -> Vec<(usize, usize)> {
+    let lower: Vec<char> = snippet.to_lowercase().chars().collect();
+    // Lowercasing can change the length of some characters; skip highlighting then
+    if lower.len() != snippet.chars().count() {
+        return Vec::new();
+    }
+
+    let mut ranges = Vec::new();
+    for word in words {
+        let word: Vec<char> = word.chars().collect();
+        if word.is_empty() {
+            continue;
+        }
+        for start in 0..lower.len().saturating_sub(word.len() - 1) {
+            if lower[start..start + word.len()] == word[..] {
+                ranges.push((start, start + word.len()));
+            }
+        }
+    }
+    ranges.sort();
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Index terms of a text
+fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    let mut run: Vec<char> = Vec::new();
+
+    let flush_run = |run: &mut Vec<char>, terms: &mut Vec<String>| {
+        match run.len() {
+            0 => {}
+            1 => terms.push(run[0].to_string()),
+            _ => terms.extend(run.windows(2).map(|pair| pair.iter().collect())),
+        }
+        run.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            flush_run(&mut run, &mut terms);
+            word.push(c.to_ascii_lowercase());
+        } else {
+            if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+            if c.is_alphanumeric() {
+                run.extend(c.to_lowercase());
+            } else {
+                flush_run(&mut run, &mut terms);
+            }
+        }
+    }
+    if !word.is_empty() {
+        terms.push(word);
+    }
+    flush_run(&mut run, &mut terms);
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(title: &str, content: &str) -> PlantUMLDocument {
+        let mut document = PlantUMLDocument::new(content.to_string());
+        document.title = Some(title.to_string());
+        document
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Alice -> Bob_2"), vec!["alice", "bob_2"]);
+        assert_eq!(tokenize("ログイン処理"), vec!["ログ", "グイ", "イン", "ン処", "処理"]);
+        assert_eq!(tokenize("A: 認"), vec!["a", "認"]);
+    }
+
+    #[test]
+    fn test_search_ranks_title_matches_first() {
+        let mut index = SearchIndex::default();
+        let in_title = document("Login flow", "@startuml\nA -> B\n@enduml");
+        let in_content = document("Checkout", "@startuml\nUser -> Shop : login\n@enduml");
+        let unrelated = document("Other", "@startuml\nX -> Y\n@enduml");
+        for document in [&in_title, &in_content, &unrelated] {
+            index.upsert(document);
+        }
+
+        let ids: Vec<DocumentId> = index.search("LOGIN").into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec![in_title.id, in_content.id]);
+
+        // Every term must match
+        assert_eq!(index.search("login shop").len(), 1);
+        assert!(index.search("missing").is_empty());
+        assert!(index.search("  ").is_empty());
+    }
+
+    #[test]
+    fn test_search_japanese_substrings() {
+        let mut index = SearchIndex::default();
+        let document = document("認証", "@startuml\nユーザー -> サーバー : ログイン要求\n@enduml");
+        index.upsert(&document);
+
+        assert_eq!(index.search("ログイン").len(), 1);
+        assert_eq!(index.search("認証").len(), 1);
+        assert!(index.search("ログアウト").is_empty());
+    }
+
+    #[test]
+    fn test_upsert_and_remove_replace_terms() {
+        let mut index = SearchIndex::default();
+        let mut document = document("Flow", "@startuml\nAlice -> Bob\n@enduml");
+        index.upsert(&document);
+
+        document.content = "@startuml\nCarol -> Dave\n@enduml".to_string();
+        index.upsert(&document);
+        assert!(index.search("alice").is_empty());
+        assert_eq!(index.search("carol").len(), 1);
+
+        index.remove(&document.id);
+        assert!(index.search("carol").is_empty());
+        assert!(index.postings.is_empty());
+    }
+
+    #[test]
+    fn test_hit_snippet_and_highlights() {
+        let document = document("Flow", "@startuml\n  User -> Login : login()\n@enduml");
+        let hit = hit(&document, "login", 1.0);
+        assert_eq!(hit.snippet, "User -> Login : login()");
+        assert_eq!(hit.highlights, vec![(8, 13), (16, 21)]);
+    }
+}
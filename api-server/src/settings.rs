@@ -0,0 +1,64 @@
+// Editor settings of each user
+//
+// The web UI saves its preferences here when the user is logged in, so they
+// follow them to other browsers. Conflicting writes from two browsers are
+// resolved by `updated_at` (last write wins): a snapshot older than the stored
+// one is ignored and the stored one is returned instead.
+
+use plantuml_editor_core::SettingsSnapshot;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Settings keyed by user, kept in memory for the lifetime of the process
+#[derive(Default)]
+pub struct SettingsStore {
+    settings: RwLock<HashMap<String, SettingsSnapshot>>,
+}
+
+impl SettingsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, user: &str) -> Option<SettingsSnapshot> {
+        self.settings.read().unwrap().get(user).cloned()
+    }
+
+    /// Store `snapshot` unless the stored settings are newer; returns the
+    /// settings now in effect
+    pub fn put(&self, user: &str, snapshot: SettingsSnapshot) -> SettingsSnapshot {
+        let mut settings = self.settings.write().unwrap();
+        let current = match settings.remove(user) {
+            Some(stored) => stored.newer(snapshot),
+            None => snapshot,
+        };
+        settings.insert(user.to_string(), current.clone());
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::UserSettings;
+
+    fn snapshot(indent_width: usize, updated_at: i64) -> SettingsSnapshot {
+        SettingsSnapshot {
+            settings: UserSettings { indent_width, ..UserSettings::default() },
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn test_last_write_wins() {
+        let store = SettingsStore::new();
+        assert_eq!(store.get("alice"), None);
+
+        assert_eq!(store.put("alice", snapshot(4, 10)), snapshot(4, 10));
+        // A browser that changed its settings earlier gets the stored ones back
+        assert_eq!(store.put("alice", snapshot(0, 5)), snapshot(4, 10));
+        assert_eq!(store.put("alice", snapshot(0, 20)), snapshot(0, 20));
+        assert_eq!(store.get("alice"), Some(snapshot(0, 20)));
+        assert_eq!(store.get("bob"), None);
+    }
+}
@@ -0,0 +1,137 @@
+// Signed public links to server documents
+//
+// A share token carries the document ID and an optional expiry, signed with
+// HMAC-SHA256, so links can be checked without storing them. Links stay valid
+// across restarts only when SHARE_SECRET is set; otherwise a random secret is
+// generated for each process.
+//
+// The signature covers the (document ID, expiry) pair, and GET /share/:token
+// checks it before rendering, so a link cannot be turned into one for another
+// document by guessing its ID. Renders are not cached or addressed by content
+// hash; a cache added later must be reached through a verified token too.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use plantuml_editor_core::DocumentId;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// Why a share token was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareError {
+    /// Malformed token or wrong signature
+    Invalid,
+    /// Valid token past its expiry (Unix timestamp)
+    Expired(i64),
+}
+
+/// Creates and verifies share tokens
+#[derive(Clone)]
+pub struct ShareSigner {
+    secret: Arc<Vec<u8>>,
+}
+
+impl ShareSigner {
+    /// Signer for the configured secret, or a random one for this process
+    pub fn new(secret: Option<&str>) -> Self {
+        let secret = match secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                let mut random = uuid::Uuid::new_v4().as_bytes().to_vec();
+                random.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+                random
+            }
+        };
+        Self {
+            secret: Arc::new(secret),
+        }
+    }
+
+    /// Token for a document, expiring at the given Unix timestamp
+    pub fn create(&self, id: DocumentId, expires_at: Option<i64>) -> String {
+        let payload = format!("{}:{}", id.0, expires_at.map(|at| at.to_string()).unwrap_or_default());
+        let signature = self.mac(payload.as_bytes()).finalize().into_bytes();
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(payload),
+            URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+
+    /// Document ID and expiry of a token, checked against the current time
+    pub fn verify(&self, token: &str, now: i64) -> Result<(DocumentId, Option<i64>), ShareError> {
+        let (payload, signature) = token.split_once('.').ok_or(ShareError::Invalid)?;
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| ShareError::Invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| ShareError::Invalid)?;
+        self.mac(&payload)
+            .verify_slice(&signature)
+            .map_err(|_| ShareError::Invalid)?;
+
+        let payload = String::from_utf8(payload).map_err(|_| ShareError::Invalid)?;
+        let (id, expires_at) = payload.split_once(':').ok_or(ShareError::Invalid)?;
+        let id = uuid::Uuid::parse_str(id).map_err(|_| ShareError::Invalid)?;
+        let expires_at = match expires_at {
+            "" => None,
+            at => Some(at.parse::<i64>().map_err(|_| ShareError::Invalid)?),
+        };
+
+        match expires_at {
+            Some(at) if at <= now => Err(ShareError::Expired(at)),
+            _ => Ok((DocumentId(id), expires_at)),
+        }
+    }
+
+    fn mac(&self, payload: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_expiry() {
+        let signer = ShareSigner::new(Some("secret"));
+        let id = DocumentId::new();
+
+        let token = signer.create(id, None);
+        assert_eq!(signer.verify(&token, i64::MAX), Ok((id, None)));
+
+        let token = signer.create(id, Some(1_000));
+        assert_eq!(signer.verify(&token, 999), Ok((id, Some(1_000))));
+        assert_eq!(signer.verify(&token, 1_000), Err(ShareError::Expired(1_000)));
+    }
+
+    #[test]
+    fn test_rejects_tampered_tokens() {
+        let signer = ShareSigner::new(Some("secret"));
+        let token = signer.create(DocumentId::new(), Some(1_000));
+
+        // Pushing the expiry back invalidates the signature
+        let (_, signature) = token.split_once('.').unwrap();
+        let payload = URL_SAFE_NO_PAD.encode(format!("{}:{}", uuid::Uuid::new_v4(), i64::MAX));
+        let forged = format!("{}.{}", payload, signature);
+        assert_eq!(signer.verify(&forged, 0), Err(ShareError::Invalid));
+
+        assert_eq!(ShareSigner::new(Some("other")).verify(&token, 0), Err(ShareError::Invalid));
+        assert_eq!(ShareSigner::new(None).verify(&token, 0), Err(ShareError::Invalid));
+        assert_eq!(signer.verify("not-a-token", 0), Err(ShareError::Invalid));
+    }
+
+    #[test]
+    fn test_token_only_opens_its_document() {
+        let signer = ShareSigner::new(Some("secret"));
+        let token = signer.create(DocumentId::new(), Some(1_000));
+
+        // Same expiry and signature with a guessed document ID
+        let (_, signature) = token.split_once('.').unwrap();
+        let guessed = URL_SAFE_NO_PAD.encode(format!("{}:1000", uuid::Uuid::new_v4()));
+        let forged = format!("{}.{}", guessed, signature);
+        assert_eq!(signer.verify(&forged, 0), Err(ShareError::Invalid));
+    }
+}
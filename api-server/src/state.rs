@@ -0,0 +1,104 @@
+// Shared application state
+
+use plantuml_client::ClientError;
+use plantuml_editor_core::clock::{SharedClock, SystemClock};
+use std::sync::{Arc, RwLock};
+
+use crate::activity::ActivityLog;
+use crate::auth::TokenVerifier;
+use crate::config::Config;
+use crate::documents::DocumentStore;
+use crate::folders::FolderStore;
+use crate::includes::IncludeStore;
+use crate::jobs::JobQueue;
+use crate::metrics::Metrics;
+use crate::progress::ProgressHub;
+use crate::quota::QuotaTracker;
+use crate::reload::LiveConfig;
+use crate::render::Renderer;
+use crate::settings::SettingsStore;
+use crate::share::ShareSigner;
+use crate::webhooks::WebhookDispatcher;
+
+/// State shared by all handlers
+#[derive(Clone)]
+pub struct AppState {
+    /// Configuration in effect (see `reload`)
+    pub config: Arc<LiveConfig>,
+    pub renderer: Renderer,
+    pub documents: Arc<DocumentStore>,
+    pub folders: Arc<FolderStore>,
+    /// Uploaded `!include` files
+    pub includes: Arc<IncludeStore>,
+    /// Editor settings of logged-in users
+    pub settings: Arc<SettingsStore>,
+    pub activity: Arc<ActivityLog>,
+    pub webhooks: WebhookDispatcher,
+    pub progress: Arc<ProgressHub>,
+    pub jobs: JobQueue,
+    pub share: ShareSigner,
+    pub quota: Arc<QuotaTracker>,
+    /// Checks OIDC access tokens for server storage (see `auth`)
+    pub auth: Arc<TokenVerifier>,
+    /// Request and render metrics for the dashboard
+    pub metrics: Arc<Metrics>,
+    /// Operator notice shown as a banner in the web UI (GET /api/v1/status)
+    pub announcement: Arc<RwLock<Option<String>>>,
+    /// Source of the current time for the stores, share links and quotas
+    pub clock: SharedClock,
+}
+
+impl AppState {
+    /// Build the state and start background workers (requires a Tokio runtime)
+    pub fn new(config: Config) -> Result<Self, ClientError> {
+        Self::with_clock(config, SystemClock::shared())
+    }
+
+    /// `new` with the time read from `clock` (a `MockClock` in tests)
+    pub fn with_clock(config: Config, clock: SharedClock) -> Result<Self, ClientError> {
+        let metrics = Arc::new(Metrics::default());
+        let includes = Arc::new(IncludeStore::with_clock(clock.clone()));
+        let renderer = Renderer::new(
+            config.plantuml_url.clone(),
+            config.plantuml_max_concurrency,
+            config.plantuml_max_waiting,
+            metrics.clone(),
+            includes.clone(),
+        )?;
+        renderer.set_image_limits(config.image_limits);
+        renderer.set_style_policy(config.style_policy.clone());
+        let share = ShareSigner::new(config.share_secret.as_deref());
+        let webhooks = WebhookDispatcher::new(&config, renderer.clone(), share.clone(), clock.clone());
+        let progress = Arc::new(ProgressHub::new());
+        let jobs = JobQueue::start(
+            config.job_workers,
+            config.job_queue_capacity,
+            renderer.clone(),
+            progress.clone(),
+            clock.clone(),
+        );
+        let quota = Arc::new(QuotaTracker::new(config.quota.state_file.clone()));
+        QuotaTracker::spawn_flusher(quota.clone());
+        let documents = Arc::new(
+            DocumentStore::with_clock(clock.clone()).with_id_strategy(config.id_strategy),
+        );
+        Ok(Self {
+            config: Arc::new(LiveConfig::new(config)),
+            renderer,
+            documents,
+            folders: Arc::new(FolderStore::with_clock(clock.clone())),
+            includes,
+            settings: Arc::new(SettingsStore::new()),
+            activity: Arc::new(ActivityLog::with_clock(clock.clone())),
+            webhooks,
+            progress,
+            jobs,
+            share,
+            quota,
+            auth: Arc::new(TokenVerifier::new()),
+            metrics,
+            announcement: Arc::default(),
+            clock,
+        })
+    }
+}
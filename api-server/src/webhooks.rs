@@ -0,0 +1,240 @@
+// Webhook notifications for server-side document changes
+//
+// When a stored document is created or updated, the rendered diagram (or a link
+// to it) is POSTed to every configured URL. Payloads are signed with
+// HMAC-SHA256 so receivers can verify they came from this server. Links are
+// share links (GET /share/:token) expiring after LINK_EXPIRY_SECS, so
+// receivers can fetch the image without an account or document role.
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use plantuml_editor_core::clock::SharedClock;
+use plantuml_editor_core::{DocumentId, ImageFormat, PlantUMLDocument};
+use serde::Serialize;
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{Config, WebhookPayloadMode};
+use crate::render::Renderer;
+use crate::share::ShareSigner;
+
+/// Header carrying the payload signature ("sha256=<hex>")
+pub const SIGNATURE_HEADER: &str = "X-PlantUML-Signature";
+
+/// Header carrying the event name
+pub const EVENT_HEADER: &str = "X-PlantUML-Event";
+
+/// Lifetime of the image links sent in link mode (7 days)
+pub const LINK_EXPIRY_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Document change events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    DocumentCreated,
+    DocumentUpdated,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::DocumentCreated => "document.created",
+            WebhookEvent::DocumentUpdated => "document.updated",
+        }
+    }
+}
+
+/// JSON body sent to webhook receivers
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload {
+    pub event: &'static str,
+    pub document_id: DocumentId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub updated_at: i64,
+    pub format: ImageFormat,
+
+    /// Base64-encoded image (image payload mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_base64: Option<String>,
+
+    /// Share link to the image (link payload mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_url: Option<String>,
+}
+
+/// Sends webhook notifications in background tasks
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    inner: Option<Arc<Dispatcher>>,
+}
+
+struct Dispatcher {
+    urls: Vec<String>,
+    secret: Option<String>,
+    payload: WebhookPayloadMode,
+    renderer: Renderer,
+    public_base_url: Option<String>,
+    share: ShareSigner,
+    clock: SharedClock,
+    http: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    /// Create a dispatcher (disabled when no webhook URL is configured)
+    pub fn new(config: &Config, renderer: Renderer, share: ShareSigner, clock: SharedClock) -> Self {
+        if config.webhook.urls.is_empty() {
+            return Self { inner: None };
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            inner: Some(Arc::new(Dispatcher {
+                urls: config.webhook.urls.clone(),
+                secret: config.webhook.secret.clone(),
+                payload: config.webhook.payload,
+                renderer,
+                public_base_url: config.public_base_url.clone(),
+                share,
+                clock,
+                http,
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Notify receivers about a document change without blocking the request
+    pub fn notify(&self, event: WebhookEvent, document: PlantUMLDocument) {
+        let Some(dispatcher) = self.inner.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            dispatcher.deliver(event, document).await;
+        });
+    }
+}
+
+impl Dispatcher {
+    async fn deliver(&self, event: WebhookEvent, document: PlantUMLDocument) {
+        let mut payload = WebhookPayload {
+            event: event.as_str(),
+            document_id: document.id,
+            title: document.title.clone(),
+            updated_at: document.updated_at,
+            format: ImageFormat::Png,
+            image_base64: None,
+            image_url: None,
+        };
+
+        match (self.payload, &self.public_base_url) {
+            (WebhookPayloadMode::Link, Some(base_url)) => {
+                payload.image_url =
+                    Some(image_link(&self.share, base_url, document.id, self.clock.now()));
+            }
+            _ => {
+                let image = self
+                    .renderer
+                    .render_queued(document.id, &document.content, ImageFormat::Png, None)
+                    .await;
+                match image {
+                    Ok(image) => {
+                        payload.image_base64 =
+                            Some(base64::engine::general_purpose::STANDARD.encode(&image.data));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Webhook render failed for {}: {}", document.id.0, e);
+                        return;
+                    }
+                }
+            }
+        }
+
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("Failed to serialize webhook payload: {}", e);
+                return;
+            }
+        };
+
+        for url in &self.urls {
+            let mut request = self
+                .http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, event.as_str());
+            if let Some(secret) = &self.secret {
+                request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+            }
+
+            match request.body(body.clone()).send().await {
+                Ok(response) if response.status().is_success() => {
+                    tracing::info!("Webhook delivered to {} ({})", url, event.as_str());
+                }
+                Ok(response) => {
+                    tracing::warn!("Webhook to {} returned {}", url, response.status());
+                }
+                Err(e) => {
+                    tracing::warn!("Webhook to {} failed: {}", url, e);
+                }
+            }
+        }
+    }
+}
+
+/// Share link to a document's image, valid for LINK_EXPIRY_SECS from `now`
+pub fn image_link(share: &ShareSigner, base_url: &str, id: DocumentId, now: i64) -> String {
+    let token = share.create(id, Some(now.saturating_add(LINK_EXPIRY_SECS)));
+    format!("{}/share/{}", base_url, token)
+}
+
+/// Signature header value for a payload: "sha256=<hex HMAC-SHA256>"
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::clock::SystemClock;
+
+    #[test]
+    fn test_sign_known_vector() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_dispatcher_disabled_without_urls() {
+        let config = Config::default();
+        let renderer = Renderer::new(config.plantuml_url.clone(), 1, 0, Default::default(), Default::default())
+            .unwrap();
+        let dispatcher =
+            WebhookDispatcher::new(&config, renderer, ShareSigner::new(None), SystemClock::shared());
+        assert!(!dispatcher.is_enabled());
+    }
+
+    #[test]
+    fn test_image_link_is_an_expiring_share_link() {
+        let share = ShareSigner::new(Some("secret"));
+        let id = DocumentId::new();
+        let link = image_link(&share, "https://uml.example.com", id, 1000);
+
+        let token = link.strip_prefix("https://uml.example.com/share/").unwrap();
+        assert_eq!(share.verify(token, 1000), Ok((id, Some(1000 + LINK_EXPIRY_SECS))));
+        assert!(share.verify(token, 1000 + LINK_EXPIRY_SECS).is_err());
+    }
+}
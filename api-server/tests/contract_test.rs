@@ -0,0 +1,20 @@
+// API contract tests (serialization side)
+//
+// Every ConvertResponse the server can send must serialize exactly to the
+// shared fixtures that the api-client suite deserializes.
+
+use axum::body::to_bytes;
+use axum::response::IntoResponse;
+use axum::Json;
+use plantuml_editor_contract_tests::convert_response_cases;
+
+#[tokio::test]
+async fn test_convert_responses_match_fixtures() {
+    for case in convert_response_cases() {
+        let response = Json(case.response).into_response();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(json, case.json, "serialized {} differs from the fixture", case.name);
+    }
+}
@@ -0,0 +1,7 @@
+// Web worker sanitizing preview images (see plantuml_editor_web_ui::svg_worker)
+//
+// trunk builds it next to the app as `svg_worker.js`.
+
+fn main() {
+    plantuml_editor_web_ui::svg_worker::register();
+}
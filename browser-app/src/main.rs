@@ -1,19 +1,13 @@
 // PlantUML Editor Browser Application
+//
+// The same bundle runs as a standalone page and inside a VS Code webview;
+// `mount` picks the host from the configuration the page injects.
 
-use yew::prelude::*;
 use wasm_bindgen::prelude::*;
-use plantuml_editor_web_ui::App;
-
-#[function_component(Main)]
-fn main_component() -> Html {
-    html! {
-        <App />
-    }
-}
 
 #[wasm_bindgen(start)]
 pub fn run_app() {
-    yew::Renderer::<Main>::new().render();
+    plantuml_editor_web_ui::mount();
 }
 
 fn main() {
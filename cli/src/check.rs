@@ -0,0 +1,335 @@
+// `check` subcommand: validation and lint for CI
+//
+// Runs `diagnostics::lint` (which includes the content validation) on every
+// file and prints the problems as text, JSON or SARIF 2.1.0 for code scanning
+// tools. The exit code tells CI whether the check passed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use plantuml_editor_core::diagnostics::{self, line_diagnostic};
+use plantuml_editor_core::sarif::{sarif_log, SarifArtifact};
+use plantuml_editor_core::{Diagnostic, DiagnosticSeverity, ErrorCode};
+use serde_json::{json, Value};
+
+use crate::{EXIT_FAILED, EXIT_USAGE};
+
+/// Extensions of the files checked when a directory is given
+pub const SOURCE_EXTENSIONS: &[&str] = &["puml", "plantuml", "pu", "wsd"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub paths: Vec<PathBuf>,
+    pub format: OutputFormat,
+    /// Least severe diagnostic that fails the check
+    pub fail_on: DiagnosticSeverity,
+}
+
+impl Options {
+    /// Options from the arguments after `check`; None when help was asked for
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let mut options = Options {
+            paths: Vec::new(),
+            format: OutputFormat::Text,
+            fail_on: DiagnosticSeverity::Error,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--strict" => options.fail_on = DiagnosticSeverity::Warning,
+                "--format" => {
+                    options.format = match args.next().map(String::as_str) {
+                        Some("text") => OutputFormat::Text,
+                        Some("json") => OutputFormat::Json,
+                        Some("sarif") => OutputFormat::Sarif,
+                        Some(other) => return Err(format!("不明な出力形式です: {}", other)),
+                        None => return Err("--format に出力形式を指定してください".to_string()),
+                    }
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("不明なオプションです: {}", flag))
+                }
+                path => options.paths.push(PathBuf::from(path)),
+            }
+        }
+        if options.paths.is_empty() {
+            return Err("検査するファイルまたはディレクトリを指定してください".to_string());
+        }
+        Ok(Some(options))
+    }
+}
+
+/// Diagnostics of one checked file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    /// Path as given or found, with `/` separators
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub fn run(options: &Options) -> ExitCode {
+    let files = match collect_files(&options.paths) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let mut reports = Vec::with_capacity(files.len());
+    for file in &files {
+        match check_file(file) {
+            Ok(report) => reports.push(report),
+            Err(e) => {
+                eprintln!("{}: 読み込めません: {}", file.display(), e);
+                return ExitCode::from(EXIT_USAGE);
+            }
+        }
+    }
+
+    let output = match options.format {
+        OutputFormat::Text => text_report(&reports),
+        OutputFormat::Json => serde_json::to_string_pretty(&json_report(&reports)).unwrap_or_default(),
+        OutputFormat::Sarif => serde_json::to_string_pretty(&sarif_report(&reports)).unwrap_or_default(),
+    };
+    println!("{}", output);
+
+    if has_violations(&reports, options.fail_on) {
+        ExitCode::from(EXIT_FAILED)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Files named in `paths`, with directories searched for PlantUML sources
+/// (hidden directories are skipped)
+pub fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &mut files).map_err(|e| format!("{}: 読み込めません: {}", path.display(), e))?;
+        } else if path.is_file() {
+            files.push(path.clone());
+        } else {
+            return Err(format!("{}: ファイルまたはディレクトリが見つかりません", path.display()));
+        }
+    }
+    Ok(files)
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else if is_source(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_source(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SOURCE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// Lint one file; a file that is not UTF-8 gets a single encoding error
+pub fn check_file(path: &Path) -> std::io::Result<FileReport> {
+    let bytes = fs::read(path)?;
+    let diagnostics = match String::from_utf8(bytes) {
+        Ok(content) => diagnostics::lint(&content),
+        Err(_) => {
+            let code = ErrorCode::EncodingError {
+                encoding: "UTF-8".to_string(),
+            };
+            vec![line_diagnostic("", 0, DiagnosticSeverity::Error, "encoding-error", code.to_message())]
+        }
+    };
+    Ok(FileReport {
+        path: path.to_string_lossy().replace('\\', "/"),
+        diagnostics,
+    })
+}
+
+/// Any diagnostic at least as severe as `fail_on`
+pub fn has_violations(reports: &[FileReport], fail_on: DiagnosticSeverity) -> bool {
+    reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .any(|diagnostic| diagnostic.severity <= fail_on)
+}
+
+fn count(reports: &[FileReport], severity: DiagnosticSeverity) -> usize {
+    reports
+        .iter()
+        .flat_map(|report| &report.diagnostics)
+        .filter(|diagnostic| diagnostic.severity == severity)
+        .count()
+}
+
+/// `path:line:column: severity[code]: message` lines (one-based positions) and a summary
+pub fn text_report(reports: &[FileReport]) -> String {
+    let mut lines: Vec<String> = reports
+        .iter()
+        .flat_map(|report| {
+            report.diagnostics.iter().map(move |diagnostic| {
+                let start = diagnostic.range.start;
+                format!(
+                    "{}:{}:{}: {}[{}]: {}",
+                    report.path,
+                    start.line + 1,
+                    start.character + 1,
+                    severity_name(diagnostic.severity),
+                    diagnostic.code,
+                    diagnostic.message
+                )
+            })
+        })
+        .collect();
+    lines.push(format!(
+        "{}ファイルを検査しました（エラー {}件、警告 {}件）",
+        reports.len(),
+        count(reports, DiagnosticSeverity::Error),
+        count(reports, DiagnosticSeverity::Warning)
+    ));
+    lines.join("\n")
+}
+
+/// Diagnostics per file in the shape of POST /api/v1/diagnostics
+pub fn json_report(reports: &[FileReport]) -> Value {
+    json!({
+        "files": reports
+            .iter()
+            .map(|report| json!({ "path": report.path, "diagnostics": report.diagnostics }))
+            .collect::<Vec<_>>(),
+        "errors": count(reports, DiagnosticSeverity::Error),
+        "warnings": count(reports, DiagnosticSeverity::Warning),
+    })
+}
+
+/// SARIF 2.1.0 log of all files (see `plantuml_editor_core::sarif`)
+pub fn sarif_report(reports: &[FileReport]) -> Value {
+    let artifacts: Vec<SarifArtifact> = reports
+        .iter()
+        .map(|report| SarifArtifact {
+            uri: &report.path,
+            diagnostics: &report.diagnostics,
+        })
+        .collect();
+    sarif_log(&artifacts)
+}
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "error",
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Information => "info",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn report(path: &str, content: &str) -> FileReport {
+        FileReport {
+            path: path.to_string(),
+            diagnostics: diagnostics::lint(content),
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(&args(&["--format", "sarif", "--strict", "docs", "a.puml"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.format, OutputFormat::Sarif);
+        assert_eq!(options.fail_on, DiagnosticSeverity::Warning);
+        assert_eq!(options.paths, vec![PathBuf::from("docs"), PathBuf::from("a.puml")]);
+
+        assert_eq!(Options::parse(&args(&["--help"])), Ok(None));
+        assert!(Options::parse(&args(&[])).is_err());
+        assert!(Options::parse(&args(&["--format", "xml", "a.puml"])).is_err());
+        assert!(Options::parse(&args(&["--fix", "a.puml"])).is_err());
+    }
+
+    #[test]
+    fn test_violations_by_severity() {
+        let warning_only = vec![report("a.puml", "@startuml\nloop\nA -> B\nend\n}\n@enduml")];
+        assert!(!has_violations(&warning_only, DiagnosticSeverity::Error));
+        assert!(has_violations(&warning_only, DiagnosticSeverity::Warning));
+
+        let broken = vec![report("b.puml", "@startuml\nA -> B")];
+        assert!(has_violations(&broken, DiagnosticSeverity::Error));
+        assert!(!has_violations(&[report("c.puml", "@startuml\nA -> B\n@enduml")], DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn test_text_and_json_reports() {
+        let reports = vec![report("docs/b.puml", "@startuml\nA -> B")];
+        let text = text_report(&reports);
+        assert!(text.starts_with("docs/b.puml:1:1: error[missing-end]: "));
+        assert!(text.ends_with("1ファイルを検査しました（エラー 1件、警告 0件）"));
+
+        let json = json_report(&reports);
+        assert_eq!(json["errors"], 1);
+        assert_eq!(json["files"][0]["diagnostics"][0]["code"], "missing-end");
+    }
+
+    #[test]
+    fn test_sarif_report() {
+        let reports = vec![report("a.puml", "@startuml\nA -> B"), report("b.puml", "@startuml\n@enduml")];
+        let sarif = sarif_report(&reports);
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "a.puml");
+    }
+
+    #[test]
+    fn test_collect_files_in_directories() {
+        let dir = std::env::temp_dir().join(format!("plantuml-check-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        for name in ["b.puml", "nested/a.PlantUML", "style.iuml", "notes.md", ".git/c.puml"] {
+            fs::write(dir.join(name), "@startuml\n@enduml").unwrap();
+        }
+        fs::write(dir.join("latin1.pu"), [0x40, 0xE9, 0xFF]).unwrap();
+
+        let files = collect_files(std::slice::from_ref(&dir)).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| file.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(names, vec!["b.puml", "latin1.pu", "nested/a.PlantUML"]);
+
+        let latin1 = check_file(&files[1]).unwrap();
+        assert_eq!(latin1.diagnostics[0].code, "encoding-error");
+        assert!(collect_files(&[dir.join("missing.puml")]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+// `hook install` subcommand: git pre-commit hook
+//
+// Writes a pre-commit hook that runs `check` on the staged PlantUML sources
+// and, with `--render`, exports them through the API server and stages the
+// images, so committed images always match their sources. A hook that was not
+// written by this command is only replaced with `--force`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use plantuml_editor_core::ImageFormat;
+
+use crate::check::SOURCE_EXTENSIONS;
+use crate::render::{format_name, parse_format};
+use crate::{EXIT_FAILED, EXIT_USAGE};
+
+/// First comment of a generated hook, used to recognize it on reinstall
+pub const HOOK_MARKER: &str = "# Generated by plantuml-editor hook install";
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Options {
+    /// Export staged sources in this format and stage the images
+    pub render: Option<ImageFormat>,
+    /// API server for `--render` (API_BASE_URL or localhost:8080 when None)
+    pub server: Option<String>,
+    /// Fail the commit on warnings too
+    pub strict: bool,
+    /// Replace a pre-commit hook not written by this command
+    pub force: bool,
+}
+
+impl Options {
+    /// Options from the arguments after `hook`; None when help was asked for
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let mut args = args.iter();
+        match args.next().map(String::as_str) {
+            Some("install") => {}
+            Some("-h" | "--help") => return Ok(None),
+            Some(other) => return Err(format!("不明な hook の操作です: {}", other)),
+            None => return Err("hook の操作 (install) を指定してください".to_string()),
+        }
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--render" => match parse_format(args.next())? {
+                    // PDF files carry their creation time, so every commit would change them
+                    ImageFormat::Pdf => return Err("--render に pdf は指定できません".to_string()),
+                    format => options.render = Some(format),
+                },
+                "--server" => match args.next() {
+                    Some(url) => options.server = Some(url.clone()),
+                    None => return Err("--server にAPIサーバーのURLを指定してください".to_string()),
+                },
+                "--strict" => options.strict = true,
+                "--force" => options.force = true,
+                other => return Err(format!("不明なオプションです: {}", other)),
+            }
+        }
+        if options.server.is_some() && options.render.is_none() {
+            return Err("--server は --render と組み合わせて指定してください".to_string());
+        }
+        Ok(Some(options))
+    }
+}
+
+pub fn run(options: &Options) -> ExitCode {
+    let hooks_dir = match hooks_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    // The hook calls this binary by its absolute path, so it works without PATH
+    let program = std::env::current_exe()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "plantuml-editor".to_string());
+    match install_into(&hooks_dir, &hook_script(&program, options), options.force) {
+        Ok(path) => {
+            println!("pre-commit フックをインストールしました: {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(EXIT_FAILED)
+        }
+    }
+}
+
+/// Hooks directory of the current repository (honors core.hooksPath and worktrees)
+fn hooks_dir() -> Result<PathBuf, String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .map_err(|e| format!("git を実行できません: {}", e))?;
+    if !output.status.success() {
+        return Err("git リポジトリの中で実行してください".to_string());
+    }
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Write `script` as `hooks_dir/pre-commit`, returning its path
+pub fn install_into(hooks_dir: &Path, script: &str, force: bool) -> Result<PathBuf, String> {
+    let path = hooks_dir.join("pre-commit");
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !existing.contains(HOOK_MARKER) && !force {
+            return Err(format!(
+                "{} には別の pre-commit フックがあります。置き換える場合は --force を指定してください",
+                path.display()
+            ));
+        }
+    }
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(hooks_dir)?;
+        fs::write(&path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        Ok(())
+    };
+    write().map_err(|e| format!("{}: 書き込めません: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// POSIX shell pre-commit hook calling `program`
+pub fn hook_script(program: &str, options: &Options) -> String {
+    let patterns = SOURCE_EXTENSIONS
+        .iter()
+        .map(|ext| format!("'*.{}'", ext))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let check_flags = if options.strict { " --strict" } else { "" };
+
+    let mut script = format!(
+        "#!/bin/sh
+{HOOK_MARKER}
+#
+# Checks the staged PlantUML sources before each commit.
+# Skip once with `git commit --no-verify`.
+
+PLANTUML_EDITOR={program}
+
+files=$(git diff --cached --name-only --diff-filter=ACMR -- {patterns})
+[ -n \"$files\" ] || exit 0
+
+# One file per line, without glob expansion (names may contain spaces)
+IFS='
+'
+set -f
+
+\"$PLANTUML_EDITOR\" check{check_flags} $files || exit 1
+",
+        program = sh_quote(program),
+    );
+
+    if let Some(format) = options.render {
+        let ext = format_name(format);
+        let server = options
+            .server
+            .as_deref()
+            .map(|url| format!(" --server {}", sh_quote(url)))
+            .unwrap_or_default();
+        script.push_str(&format!(
+            "
+# Re-render the sources (as in the working tree) and stage the images
+for file in $files; do
+    \"$PLANTUML_EDITOR\" render --deterministic --format {ext}{server} \"$file\" || exit 1
+    git add -- \"${{file%.*}}.{ext}\" || exit 1
+done
+"
+        ));
+    }
+    script
+}
+
+/// `value` in single quotes for sh
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = Options::parse(&args(&["install", "--render", "png", "--server", "http://ci:8080", "--strict"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.render, Some(ImageFormat::Png));
+        assert_eq!(options.server.as_deref(), Some("http://ci:8080"));
+        assert!(options.strict && !options.force);
+
+        assert!(Options::parse(&args(&[])).is_err());
+        assert!(Options::parse(&args(&["uninstall"])).is_err());
+        assert!(Options::parse(&args(&["install", "--server", "http://ci:8080"])).is_err());
+        assert!(Options::parse(&args(&["install", "--render", "pdf"])).is_err());
+        assert_eq!(Options::parse(&args(&["--help"])), Ok(None));
+    }
+
+    #[test]
+    fn test_hook_script() {
+        let check_only = hook_script("/opt/bin/plantuml-editor", &Options::default());
+        assert!(check_only.starts_with("#!/bin/sh\n# Generated by plantuml-editor hook install\n"));
+        assert!(check_only.contains("PLANTUML_EDITOR='/opt/bin/plantuml-editor'"));
+        assert!(check_only.contains("-- '*.puml' '*.plantuml' '*.pu' '*.wsd')"));
+        assert!(check_only.contains("\"$PLANTUML_EDITOR\" check $files || exit 1"));
+        assert!(!check_only.contains("render"));
+
+        let options = Options {
+            render: Some(ImageFormat::Svg),
+            server: Some("http://localhost:8080".to_string()),
+            strict: true,
+            ..Options::default()
+        };
+        let rendering = hook_script("/home/o'brien/plantuml-editor", &options);
+        assert!(rendering.contains("PLANTUML_EDITOR='/home/o'\\''brien/plantuml-editor'"));
+        assert!(rendering.contains("check --strict $files"));
+        assert!(rendering.contains(
+            "render --deterministic --format svg --server 'http://localhost:8080' \"$file\" || exit 1\n    git add -- \"${file%.*}.svg\""
+        ));
+    }
+
+    #[test]
+    fn test_install_keeps_foreign_hooks() {
+        let dir = std::env::temp_dir().join(format!("plantuml-hook-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let script = hook_script("plantuml-editor", &Options::default());
+
+        let path = install_into(&dir, &script, false).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), script);
+        // Reinstalling over our own hook needs no --force
+        install_into(&dir, &script, false).unwrap();
+
+        fs::write(&path, "#!/bin/sh\nnpm test\n").unwrap();
+        assert!(install_into(&dir, &script, false).is_err());
+        install_into(&dir, &script, true).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), script);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+// PlantUML Editor command-line tools
+//
+// `plantuml-editor check` runs the editor's validation and lint rules on
+// PlantUML files without a PlantUML server, so repositories can fail CI on
+// broken diagrams. `render` exports images through the API server, and
+// `hook install` sets both up as a git pre-commit hook.
+
+use std::process::ExitCode;
+
+mod check;
+mod hook;
+mod manifest;
+mod render;
+
+/// Exit code for violations found by `check` and failed exports
+pub const EXIT_FAILED: u8 = 1;
+
+/// Exit code for invalid arguments and unreadable paths
+pub const EXIT_USAGE: u8 = 2;
+
+const USAGE: &str = "\
+使い方:
+  plantuml-editor check [オプション] <ファイルまたはディレクトリ>...
+  plantuml-editor render [オプション] <ファイルまたはディレクトリ>...
+  plantuml-editor hook install [オプション]
+
+check: 検証とリントを実行します。ディレクトリは .puml / .plantuml / .pu / .wsd
+ファイルを再帰的に検査します。
+  --format <text|json|sarif>  出力形式 (既定: text)
+  --strict                    警告も違反として扱う
+
+render: APIサーバーで画像に変換し、ソースと同じ場所に <名前>.svg / .png / .pdf を書き出します。
+  --format <svg|png|pdf>      画像形式 (既定: svg)
+  --server <URL>              APIサーバー (既定: API_BASE_URL または http://localhost:8080)
+  --deterministic             メタデータを除き、同じソースから同じバイト列を出力する
+  --manifest <ファイル>       ソースのハッシュを記録し、前回から変わった図だけを変換する
+  -j, --jobs <数>             同時に変換するファイル数 (既定: 4)
+
+hook install: ステージされた図を検査する git の pre-commit フックをインストールします。
+  --render <svg|png>          画像も (--deterministic で) 再生成してステージする
+  --server <URL>              --render で使うAPIサーバー
+  --strict                    警告でもコミットを止める
+  --force                     既存の別の pre-commit フックを置き換える
+
+  -h, --help                  この説明を表示
+
+終了コード: 0 = 成功, 1 = 違反あり・変換失敗, 2 = 引数または読み込みのエラー";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("check") => match check::Options::parse(&args[1..]) {
+            Ok(Some(options)) => check::run(&options),
+            Ok(None) => help(),
+            Err(e) => usage_error(&e),
+        },
+        Some("render") => match render::Options::parse(&args[1..]) {
+            Ok(Some(options)) => render::run(&options),
+            Ok(None) => help(),
+            Err(e) => usage_error(&e),
+        },
+        Some("hook") => match hook::Options::parse(&args[1..]) {
+            Ok(Some(options)) => hook::run(&options),
+            Ok(None) => help(),
+            Err(e) => usage_error(&e),
+        },
+        Some("-h" | "--help") => help(),
+        Some(command) => usage_error(&format!("不明なコマンドです: {}", command)),
+        None => usage_error("コマンドを指定してください"),
+    }
+}
+
+fn help() -> ExitCode {
+    println!("{}", USAGE);
+    ExitCode::SUCCESS
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", message, USAGE);
+    ExitCode::from(EXIT_USAGE)
+}
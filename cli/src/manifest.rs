@@ -0,0 +1,144 @@
+// Render manifest: skip diagrams that did not change
+//
+// `render --manifest <file>` records, for every image written, a SHA-256 of
+// the source together with the options that affect the output. On the next
+// run a source whose hash is unchanged and whose image still exists is not
+// sent to the server, so documentation builds with hundreds of diagrams only
+// re-render what changed. Changes the hash cannot see (shared include files,
+// the PlantUML server version) need the manifest to be deleted.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use plantuml_editor_core::ImageFormat;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::render::format_name;
+
+/// Written into the manifest; other versions are ignored and rebuilt
+const MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// Entries by image path (`/` separators)
+    pub images: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub source: String,
+    /// See [`content_hash`]
+    pub hash: String,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            images: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Manifest at `path`; empty when the file does not exist yet, is
+    /// unreadable or was written by another version
+    pub fn load(path: &Path) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Manifest>(&bytes).ok())
+            .filter(|manifest| manifest.version == MANIFEST_VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+
+    /// Whether `image` was last rendered from a source with `hash` and still exists
+    pub fn is_current(&self, image: &Path, hash: &str) -> bool {
+        self.images
+            .get(&key(image))
+            .is_some_and(|entry| entry.hash == hash)
+            && image.is_file()
+    }
+
+    pub fn record(&mut self, image: &Path, source: &Path, hash: String) {
+        self.images.insert(
+            key(image),
+            ManifestEntry {
+                source: key(source),
+                hash,
+            },
+        );
+    }
+
+    /// Drop entries whose source no longer exists
+    pub fn prune(&mut self) {
+        self.images.retain(|_, entry| Path::new(&entry.source).is_file());
+    }
+}
+
+/// Hex SHA-256 of the source and the options that change the image
+pub fn content_hash(source: &[u8], format: ImageFormat, deterministic: bool) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format_name(format));
+    hasher.update([0, u8::from(deterministic), 0]);
+    hasher.update(source);
+    hex::encode(hasher.finalize())
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_covers_options() {
+        let source = b"@startuml\nA -> B\n@enduml";
+        let hash = content_hash(source, ImageFormat::Svg, true);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(source, ImageFormat::Svg, true));
+        assert_ne!(hash, content_hash(source, ImageFormat::Png, true));
+        assert_ne!(hash, content_hash(source, ImageFormat::Svg, false));
+        assert_ne!(hash, content_hash(b"@startuml\nA -> C\n@enduml", ImageFormat::Svg, true));
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = std::env::temp_dir().join(format!("plantuml-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("a.puml");
+        let image = dir.join("a.svg");
+        let manifest_path = dir.join("manifest.json");
+        fs::write(&source, "@startuml\n@enduml").unwrap();
+
+        // Missing or foreign files start empty
+        assert_eq!(Manifest::load(&manifest_path), Manifest::default());
+        fs::write(&manifest_path, "{\"version\": 0, \"images\": {}}").unwrap();
+        assert_eq!(Manifest::load(&manifest_path), Manifest::default());
+
+        let mut manifest = Manifest::default();
+        manifest.record(&image, &source, "abc".to_string());
+        // Not current while the image is missing
+        assert!(!manifest.is_current(&image, "abc"));
+        fs::write(&image, "<svg/>").unwrap();
+        assert!(manifest.is_current(&image, "abc"));
+        assert!(!manifest.is_current(&image, "def"));
+
+        manifest.record(&dir.join("gone.svg"), &dir.join("gone.puml"), "x".to_string());
+        manifest.prune();
+        manifest.save(&manifest_path).unwrap();
+        let loaded = Manifest::load(&manifest_path);
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.images.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+// `render` subcommand: export images next to their sources
+//
+// Sends each file to the API server's export endpoint (so include files and
+// the style policy apply as in the editor) and writes `<name>.svg` or
+// `<name>.png` beside it. The pre-commit hook uses it, with
+// `--deterministic`, to keep committed images in sync with their sources.
+// Directories are searched like `check` does; with `--manifest` only sources
+// that changed since the last run are sent (see `manifest`).
+//
+// Up to `--jobs` files are rendered at a time with a progress bar, and a
+// table of the rendered and failed files follows. Requests use the server's
+// render timeout and retry transient failures (busy, slow or paused server)
+// with the shared `RetryPolicy`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use futures::stream::{self, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use plantuml_editor_api_client::{ApiClient, ApiError};
+use plantuml_editor_core::retry::{RetryPolicy, RENDER_TIMEOUT};
+use plantuml_editor_core::ImageFormat;
+
+use crate::check::collect_files;
+use crate::manifest::{content_hash, Manifest};
+use crate::{EXIT_FAILED, EXIT_USAGE};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Files and directories to render
+    pub paths: Vec<PathBuf>,
+    pub format: ImageFormat,
+    /// API server URL (API_BASE_URL or localhost:8080 when None)
+    pub server: Option<String>,
+    /// Leave out run-dependent metadata so unchanged sources give identical files
+    pub deterministic: bool,
+    /// Manifest of source hashes; unchanged sources are skipped when set
+    pub manifest: Option<PathBuf>,
+    /// Files rendered at the same time
+    pub jobs: usize,
+}
+
+/// Default for `--jobs`, the API server's default PLANTUML_MAX_CONCURRENCY
+pub const DEFAULT_JOBS: usize = 4;
+
+impl Options {
+    /// Options from the arguments after `render`; None when help was asked for
+    pub fn parse(args: &[String]) -> Result<Option<Self>, String> {
+        let mut options = Options {
+            paths: Vec::new(),
+            format: ImageFormat::Svg,
+            server: None,
+            deterministic: false,
+            manifest: None,
+            jobs: DEFAULT_JOBS,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--format" => options.format = parse_format(args.next())?,
+                "--server" => match args.next() {
+                    Some(url) => options.server = Some(url.clone()),
+                    None => return Err("--server にAPIサーバーのURLを指定してください".to_string()),
+                },
+                "--deterministic" => options.deterministic = true,
+                "-j" | "--jobs" => match args.next().and_then(|jobs| jobs.parse().ok()) {
+                    Some(jobs) if jobs > 0 => options.jobs = jobs,
+                    _ => return Err("--jobs に1以上の並列数を指定してください".to_string()),
+                },
+                "--manifest" => match args.next() {
+                    Some(path) => options.manifest = Some(PathBuf::from(path)),
+                    None => return Err("--manifest にマニフェストのファイルを指定してください".to_string()),
+                },
+                flag if flag.starts_with("--") => {
+                    return Err(format!("不明なオプションです: {}", flag))
+                }
+                path => options.paths.push(PathBuf::from(path)),
+            }
+        }
+        if options.paths.is_empty() {
+            return Err("変換するファイルまたはディレクトリを指定してください".to_string());
+        }
+        Ok(Some(options))
+    }
+}
+
+/// `svg`, `png` or `pdf` after `--format`
+pub fn parse_format(value: Option<&String>) -> Result<ImageFormat, String> {
+    match value.map(String::as_str) {
+        Some("svg") => Ok(ImageFormat::Svg),
+        Some("png") => Ok(ImageFormat::Png),
+        Some("pdf") => Ok(ImageFormat::Pdf),
+        Some(other) => Err(format!("不明な画像形式です: {}", other)),
+        None => Err("--format に画像形式 (svg・png・pdf) を指定してください".to_string()),
+    }
+}
+
+pub fn format_name(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Svg => "svg",
+        ImageFormat::Png => "png",
+        ImageFormat::Txt => "txt",
+        ImageFormat::Pdf => "pdf",
+    }
+}
+
+/// Image written for `source` (same directory and stem)
+pub fn output_path(source: &Path, format: ImageFormat) -> PathBuf {
+    source.with_extension(format_name(format))
+}
+
+/// Result of one source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Image written to this path
+    Rendered(PathBuf),
+    /// Skipped by the manifest
+    Unchanged,
+    /// Message of the read, export or write error
+    Failed(String),
+}
+
+pub fn run(options: &Options) -> ExitCode {
+    let files = match collect_files(&options.paths) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let client = match &options.server {
+        Some(url) => ApiClient::new(url.as_str()),
+        None => ApiClient::default(),
+    }
+    .with_timeout(RENDER_TIMEOUT)
+    .with_deterministic(options.deterministic);
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("非同期ランタイムを起動できません: {}", e);
+            return ExitCode::from(EXIT_FAILED);
+        }
+    };
+    let mut manifest = options.manifest.as_deref().map(Manifest::load);
+
+    // Hidden when stderr is not a terminal (CI logs get the table only)
+    let progress = ProgressBar::new(files.len() as u64);
+    progress.set_style(
+        ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}")
+            .expect("progress template is valid"),
+    );
+    let results: Vec<(Outcome, Option<String>)> = runtime.block_on(
+        stream::iter(&files)
+            .map(|file| {
+                let (client, manifest, progress) = (&client, manifest.as_ref(), &progress);
+                async move {
+                    let result = render_file(client, manifest, file, options).await;
+                    progress.set_message(file.display().to_string());
+                    progress.inc(1);
+                    result
+                }
+            })
+            .buffered(options.jobs)
+            .collect(),
+    );
+    progress.finish_and_clear();
+
+    let outcomes: Vec<(&Path, Outcome)> = files
+        .iter()
+        .zip(results)
+        .map(|(file, (outcome, hash))| {
+            if let (Outcome::Rendered(output), Some(manifest), Some(hash)) = (&outcome, manifest.as_mut(), hash) {
+                manifest.record(output, file, hash);
+            }
+            (file.as_path(), outcome)
+        })
+        .collect();
+    println!("{}", summary_table(&outcomes));
+
+    // Saved after failures too, so the images already written are not redone
+    if let (Some(manifest), Some(path)) = (manifest.as_mut(), options.manifest.as_deref()) {
+        manifest.prune();
+        if let Err(e) = manifest.save(path) {
+            eprintln!("{}: 書き込めません: {}", path.display(), e);
+            return ExitCode::from(EXIT_FAILED);
+        }
+    }
+    if outcomes.iter().any(|(_, outcome)| matches!(outcome, Outcome::Failed(_))) {
+        ExitCode::from(EXIT_FAILED)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Render one source, with the hash to record for rendered images
+async fn render_file(
+    client: &ApiClient,
+    manifest: Option<&Manifest>,
+    file: &Path,
+    options: &Options,
+) -> (Outcome, Option<String>) {
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) => return (Outcome::Failed(format!("読み込めません: {}", e)), None),
+    };
+    let output = output_path(file, options.format);
+    let hash = content_hash(text.as_bytes(), options.format, options.deterministic);
+    if manifest.is_some_and(|manifest| manifest.is_current(&output, &hash)) {
+        return (Outcome::Unchanged, None);
+    }
+    let image = match export_with_retry(client, text, options.format, RetryPolicy::default()).await {
+        Ok(image) => image,
+        Err(e) => return (Outcome::Failed(e.to_string()), None),
+    };
+    match fs::write(&output, image) {
+        Ok(()) => (Outcome::Rendered(output), Some(hash)),
+        Err(e) => (Outcome::Failed(format!("{}: 書き込めません: {}", output.display(), e)), None),
+    }
+}
+
+async fn export_with_retry(
+    client: &ApiClient,
+    text: String,
+    format: ImageFormat,
+    policy: RetryPolicy,
+) -> Result<Vec<u8>, ApiError> {
+    let mut attempt = 1;
+    loop {
+        match client.export(text.clone(), format).await {
+            Ok((image, _)) => return Ok(image),
+            Err(e) if e.is_transient() => match policy.delay(attempt) {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(e),
+            },
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Rendered and failed files, one per line in input order, and the counts
+pub fn summary_table(outcomes: &[(&Path, Outcome)]) -> String {
+    let mut lines = Vec::new();
+    let (mut rendered, mut unchanged, mut failed) = (0, 0, 0);
+    for (file, outcome) in outcomes {
+        match outcome {
+            Outcome::Rendered(output) => {
+                rendered += 1;
+                lines.push(format!("成功  {} -> {}", file.display(), output.display()));
+            }
+            Outcome::Unchanged => unchanged += 1,
+            Outcome::Failed(message) => {
+                failed += 1;
+                lines.push(format!("失敗  {}: {}", file.display(), message));
+            }
+        }
+    }
+    lines.push(format!("成功 {}件、変更なし {}件、失敗 {}件", rendered, unchanged, failed));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        let args: Vec<String> = ["--format", "png", "--server", "http://localhost:9000", "--deterministic", "a.puml"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let options = Options::parse(&args).unwrap().unwrap();
+        assert_eq!(options.format, ImageFormat::Png);
+        assert_eq!(options.server.as_deref(), Some("http://localhost:9000"));
+        assert_eq!(options.paths, vec![PathBuf::from("a.puml")]);
+        assert!(options.deterministic);
+        assert_eq!(options.jobs, DEFAULT_JOBS);
+
+        let jobs: Vec<String> = ["-j", "8", "docs"].iter().map(|arg| arg.to_string()).collect();
+        assert_eq!(Options::parse(&jobs).unwrap().unwrap().jobs, 8);
+        assert!(Options::parse(&["--jobs".to_string(), "0".to_string(), "a.puml".to_string()]).is_err());
+
+        assert!(Options::parse(&[]).is_err());
+        assert!(Options::parse(&["--format".to_string(), "gif".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            output_path(Path::new("docs/login flow.puml"), ImageFormat::Svg),
+            PathBuf::from("docs/login flow.svg")
+        );
+        assert_eq!(output_path(Path::new("seq.pu"), ImageFormat::Png), PathBuf::from("seq.png"));
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let outcomes = [
+            (Path::new("a.puml"), Outcome::Rendered(PathBuf::from("a.svg"))),
+            (Path::new("b.puml"), Outcome::Unchanged),
+            (Path::new("c.puml"), Outcome::Failed("処理エラー: 図の変換に失敗しました".to_string())),
+        ];
+        assert_eq!(
+            summary_table(&outcomes),
+            "成功  a.puml -> a.svg\n失敗  c.puml: 処理エラー: 図の変換に失敗しました\n成功 1件、変更なし 1件、失敗 1件"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+// API contract fixtures shared by the api-server and api-client test suites
+//
+// fixtures/convert_responses.json holds the canonical wire format of every
+// ConvertResponse shape. The server suite checks that it serializes exactly to
+// these documents and the client suite checks that it reads them back, so a
+// schema change has to update the fixtures and pass on both sides.
+
+use plantuml_editor_core::{ConvertResponse, DocumentRole, ErrorCode};
+use serde_json::Value;
+
+const CONVERT_RESPONSES: &str = include_str!("../fixtures/convert_responses.json");
+
+/// Image bytes used by the success fixtures (PNG signature)
+pub const SAMPLE_IMAGE: [u8; 4] = [137, 80, 78, 71];
+
+/// One response and its canonical JSON
+#[derive(Debug)]
+pub struct ContractCase {
+    pub name: &'static str,
+    pub response: ConvertResponse,
+    pub json: Value,
+}
+
+/// One ErrorCode of every variant
+pub fn error_codes() -> Vec<ErrorCode> {
+    vec![
+        ErrorCode::ConversionOk,
+        ErrorCode::ExportOk,
+        ErrorCode::DiagnosticsReported { errors: 1, warnings: 2 },
+        ErrorCode::DiagramAnalyzed,
+        ErrorCode::SaveSuccess { slot_number: 1 },
+        ErrorCode::LoadSuccess { slot_number: 2 },
+        ErrorCode::DeleteSuccess { slot_number: 3 },
+        ErrorCode::DuplicateSuccess { from_slot: 1, to_slot: 4 },
+        ErrorCode::RestoreSuccess { slot_number: 5 },
+        ErrorCode::TrashEmptied,
+        ErrorCode::MoveSuccess { from_slot: 2, to_slot: 1 },
+        ErrorCode::BulkDeleteSuccess { count: 3 },
+        ErrorCode::ValidationEmpty,
+        ErrorCode::ValidationTextLimit { actual: 24001, max: 24000 },
+        ErrorCode::ValidationTextNearLimit { actual: 20001, max: 24000 },
+        ErrorCode::RequestTooLarge { max_bytes: 1048576 },
+        ErrorCode::RasterizedExport,
+        ErrorCode::DeletedWithoutTrash { slot_number: 3 },
+        ErrorCode::LargeSvg { svg_bytes: 3000000, threshold_bytes: 2097152 },
+        ErrorCode::SvgDowngraded { svg_bytes: 3000000 },
+        ErrorCode::StylePolicyViolation { missing: vec!["skinparam shadowing false".to_string()] },
+        ErrorCode::StylePolicyWarning { missing: vec!["header 社外秘".to_string()] },
+        ErrorCode::FontNotAllowed { font: "Comic Sans MS".to_string() },
+        ErrorCode::PostProcessorUnknown { name: "sharpen".to_string() },
+        ErrorCode::PostProcessorSkipped { name: "watermark".to_string(), format: "PNG".to_string() },
+        ErrorCode::ImportOk,
+        ErrorCode::ImportError { reason: "JSONの解析に失敗しました".to_string() },
+        ErrorCode::RenameError { reason: "「Db」が見つかりません".to_string() },
+        ErrorCode::ExtractError { reason: "抜き出す行が選択されていません".to_string() },
+        ErrorCode::DocumentSaved,
+        ErrorCode::DocumentLoaded,
+        ErrorCode::DocumentDeleted,
+        ErrorCode::DocumentNotFound { id: "missing".to_string() },
+        ErrorCode::ShareLinkCreated,
+        ErrorCode::ShareLinkInvalid,
+        ErrorCode::ShareLinkExpired { expired_at: 1700000000 },
+        ErrorCode::DocumentAccessDenied { required: DocumentRole::Editor },
+        ErrorCode::DocumentOwnerRequired,
+        ErrorCode::FolderSaved,
+        ErrorCode::FolderNotFound { id: "abc".to_string() },
+        ErrorCode::FolderNameEmpty,
+        ErrorCode::FolderCycle,
+        ErrorCode::DocumentConflict { current_version: 3 },
+        ErrorCode::IncludeSaved,
+        ErrorCode::IncludeDeleted,
+        ErrorCode::IncludeNotFound { name: "style.iuml".to_string() },
+        ErrorCode::IncludeNameInvalid { name: "../style".to_string() },
+        ErrorCode::IncludeFileLimit { max_files: 100 },
+        ErrorCode::IncludeStorageFull { max_bytes: 16777216 },
+        ErrorCode::SettingsSaved,
+        ErrorCode::StorageInputLimit { actual: 24001, max: 24000 },
+        ErrorCode::StorageSlotLimit { max_slots: 10 },
+        ErrorCode::StorageWriteError { reason: "QuotaExceededError".to_string() },
+        ErrorCode::StorageReadError { reason: "SecurityError".to_string() },
+        ErrorCode::StorageDeleteError { reason: "SecurityError".to_string() },
+        ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000, width: None, height: None, max_dimension: None },
+        ErrorCode::EncodingError { encoding: "UTF-8".to_string() },
+        ErrorCode::ParseError { line: Some(3) },
+        ErrorCode::ExportError { format: "PNG".to_string() },
+        ErrorCode::ArchiveFileLimit { max_files: 100 },
+        ErrorCode::JobAccepted { total: 3 },
+        ErrorCode::JobRunning { finished: 1, total: 3 },
+        ErrorCode::JobCompleted { succeeded: 2, failed: 1 },
+        ErrorCode::JobFailed { reason: "ZIPの作成に失敗しました".to_string() },
+        ErrorCode::JobNotFound { id: "missing".to_string() },
+        ErrorCode::JobQueueFull { capacity: 32 },
+        ErrorCode::RenderQueueFull { max_concurrency: 4 },
+        ErrorCode::QuotaExceeded { limit: 1000, reset_at: 1700006400 },
+        ErrorCode::Maintenance,
+        ErrorCode::AdminAccessDenied,
+        ErrorCode::AuthenticationRequired,
+        ErrorCode::ServerError { message: "internal".to_string() },
+        ErrorCode::TimeoutError { duration_ms: 20000 },
+        ErrorCode::NetworkError { endpoint: "/api/v1/convert".to_string() },
+    ]
+}
+
+/// Fixture name of a variant
+///
+/// Deliberately exhaustive: adding an ErrorCode variant fails to compile here
+/// until it is added to [`error_codes`] and the fixture file.
+pub fn variant_name(code: &ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::ConversionOk => "ConversionOk",
+        ErrorCode::ExportOk => "ExportOk",
+        ErrorCode::DiagnosticsReported { .. } => "DiagnosticsReported",
+        ErrorCode::DiagramAnalyzed => "DiagramAnalyzed",
+        ErrorCode::SaveSuccess { .. } => "SaveSuccess",
+        ErrorCode::LoadSuccess { .. } => "LoadSuccess",
+        ErrorCode::DeleteSuccess { .. } => "DeleteSuccess",
+        ErrorCode::DuplicateSuccess { .. } => "DuplicateSuccess",
+        ErrorCode::RestoreSuccess { .. } => "RestoreSuccess",
+        ErrorCode::TrashEmptied => "TrashEmptied",
+        ErrorCode::MoveSuccess { .. } => "MoveSuccess",
+        ErrorCode::BulkDeleteSuccess { .. } => "BulkDeleteSuccess",
+        ErrorCode::ValidationEmpty => "ValidationEmpty",
+        ErrorCode::ValidationTextLimit { .. } => "ValidationTextLimit",
+        ErrorCode::ValidationTextNearLimit { .. } => "ValidationTextNearLimit",
+        ErrorCode::RequestTooLarge { .. } => "RequestTooLarge",
+        ErrorCode::RasterizedExport => "RasterizedExport",
+        ErrorCode::DeletedWithoutTrash { .. } => "DeletedWithoutTrash",
+        ErrorCode::LargeSvg { .. } => "LargeSvg",
+        ErrorCode::SvgDowngraded { .. } => "SvgDowngraded",
+        ErrorCode::StylePolicyViolation { .. } => "StylePolicyViolation",
+        ErrorCode::StylePolicyWarning { .. } => "StylePolicyWarning",
+        ErrorCode::FontNotAllowed { .. } => "FontNotAllowed",
+        ErrorCode::PostProcessorUnknown { .. } => "PostProcessorUnknown",
+        ErrorCode::PostProcessorSkipped { .. } => "PostProcessorSkipped",
+        ErrorCode::ImportOk => "ImportOk",
+        ErrorCode::ImportError { .. } => "ImportError",
+        ErrorCode::RenameError { .. } => "RenameError",
+        ErrorCode::ExtractError { .. } => "ExtractError",
+        ErrorCode::DocumentSaved => "DocumentSaved",
+        ErrorCode::DocumentLoaded => "DocumentLoaded",
+        ErrorCode::DocumentDeleted => "DocumentDeleted",
+        ErrorCode::DocumentNotFound { .. } => "DocumentNotFound",
+        ErrorCode::ShareLinkCreated => "ShareLinkCreated",
+        ErrorCode::ShareLinkInvalid => "ShareLinkInvalid",
+        ErrorCode::ShareLinkExpired { .. } => "ShareLinkExpired",
+        ErrorCode::DocumentAccessDenied { .. } => "DocumentAccessDenied",
+        ErrorCode::DocumentOwnerRequired => "DocumentOwnerRequired",
+        ErrorCode::FolderSaved => "FolderSaved",
+        ErrorCode::FolderNotFound { .. } => "FolderNotFound",
+        ErrorCode::FolderNameEmpty => "FolderNameEmpty",
+        ErrorCode::FolderCycle => "FolderCycle",
+        ErrorCode::DocumentConflict { .. } => "DocumentConflict",
+        ErrorCode::IncludeSaved => "IncludeSaved",
+        ErrorCode::IncludeDeleted => "IncludeDeleted",
+        ErrorCode::IncludeNotFound { .. } => "IncludeNotFound",
+        ErrorCode::IncludeNameInvalid { .. } => "IncludeNameInvalid",
+        ErrorCode::IncludeFileLimit { .. } => "IncludeFileLimit",
+        ErrorCode::IncludeStorageFull { .. } => "IncludeStorageFull",
+        ErrorCode::SettingsSaved => "SettingsSaved",
+        ErrorCode::StorageInputLimit { .. } => "StorageInputLimit",
+        ErrorCode::StorageSlotLimit { .. } => "StorageSlotLimit",
+        ErrorCode::StorageWriteError { .. } => "StorageWriteError",
+        ErrorCode::StorageReadError { .. } => "StorageReadError",
+        ErrorCode::StorageDeleteError { .. } => "StorageDeleteError",
+        ErrorCode::SizeLimit { .. } => "SizeLimit",
+        ErrorCode::EncodingError { .. } => "EncodingError",
+        ErrorCode::ParseError { .. } => "ParseError",
+        ErrorCode::ExportError { .. } => "ExportError",
+        ErrorCode::ArchiveFileLimit { .. } => "ArchiveFileLimit",
+        ErrorCode::JobAccepted { .. } => "JobAccepted",
+        ErrorCode::JobRunning { .. } => "JobRunning",
+        ErrorCode::JobCompleted { .. } => "JobCompleted",
+        ErrorCode::JobFailed { .. } => "JobFailed",
+        ErrorCode::JobNotFound { .. } => "JobNotFound",
+        ErrorCode::JobQueueFull { .. } => "JobQueueFull",
+        ErrorCode::RenderQueueFull { .. } => "RenderQueueFull",
+        ErrorCode::QuotaExceeded { .. } => "QuotaExceeded",
+        ErrorCode::Maintenance => "Maintenance",
+        ErrorCode::AdminAccessDenied => "AdminAccessDenied",
+        ErrorCode::AuthenticationRequired => "AuthenticationRequired",
+        ErrorCode::ServerError { .. } => "ServerError",
+        ErrorCode::TimeoutError { .. } => "TimeoutError",
+        ErrorCode::NetworkError { .. } => "NetworkError",
+    }
+}
+
+/// ConvertResponse cases: conversion and export successes and the large SVG
+/// warnings carry an image, every other code is returned without one
+pub fn convert_response_cases() -> Vec<ContractCase> {
+    let fixtures: Value =
+        serde_json::from_str(CONVERT_RESPONSES).expect("convert_responses.json is valid JSON");
+
+    error_codes()
+        .into_iter()
+        .map(|code| {
+            let name = variant_name(&code);
+            let json = fixtures
+                .get(name)
+                .unwrap_or_else(|| panic!("no fixture for {} in convert_responses.json", name))
+                .clone();
+            let response = match code {
+                ErrorCode::ConversionOk
+                | ErrorCode::ExportOk
+                | ErrorCode::LargeSvg { .. }
+                | ErrorCode::SvgDowngraded { .. }
+                | ErrorCode::StylePolicyWarning { .. } => {
+                    ConvertResponse::success(SAMPLE_IMAGE.to_vec(), code)
+                }
+                code => ConvertResponse::error(code),
+            };
+            ContractCase {
+                name,
+                response,
+                json,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_fixture_has_a_case() {
+        let fixtures: Value = serde_json::from_str(CONVERT_RESPONSES).unwrap();
+        let names: Vec<&str> = convert_response_cases().iter().map(|c| c.name).collect();
+        for key in fixtures.as_object().unwrap().keys() {
+            assert!(names.contains(&key.as_str()), "stale fixture: {}", key);
+        }
+    }
+
+    #[test]
+    fn test_fixture_levels_match_status_level() {
+        for case in convert_response_cases() {
+            let level = serde_json::to_value(case.response.result.code.status_level()).unwrap();
+            assert_eq!(case.json["result"]["level"], level, "{}", case.name);
+        }
+    }
+}
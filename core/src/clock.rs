@@ -0,0 +1,84 @@
+// Time source for timestamps
+//
+// Creation and update times, trash expiry, share links and quotas read the
+// current time through `Clock` instead of calling `chrono::Utc::now()`, so
+// tests can fix and advance it with `MockClock`. Timestamps are Unix seconds
+// like the fields they fill.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current Unix timestamp (seconds)
+    fn now(&self) -> i64;
+}
+
+/// Clock shared by a store and the handlers using it
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's wall clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        chrono::Utc::now().timestamp()
+    }
+}
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+/// Clock that only moves when told to; clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<AtomicI64>,
+}
+
+impl MockClock {
+    pub fn new(now: i64) -> Self {
+        Self {
+            now: Arc::new(AtomicI64::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: i64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+
+    /// Shared handle to this clock, for constructors taking a `SharedClock`
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let clock = MockClock::new(1_700_000_000);
+        let shared = clock.shared();
+        assert_eq!(shared.now(), 1_700_000_000);
+
+        clock.advance(90);
+        assert_eq!(shared.now(), 1_700_000_090);
+        clock.set(5);
+        assert_eq!(shared.now(), 5);
+    }
+}
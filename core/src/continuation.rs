@@ -0,0 +1,284 @@
+// Smart Enter for the editor
+//
+// Pressing Enter at the end of a line keeps its indentation and continues
+// the construct it belongs to:
+// - inside a note or legend the new line stays in the block; a line that
+//   opens one gets an indented body and, when nothing closes it yet, its
+//   `end` line
+// - `alt`, `loop`, `group` and the like get an indented body and an `end`
+//   line when they are not closed yet
+// - after a message the new line can start with the same participants
+//
+// Whether a line is inside a block comes from the prose scan in tokens, so
+// notes, legends and block comments are recognized the same way as for
+// spellchecking.
+
+use crate::icons::byte_offset;
+use crate::indent::IndentUnit;
+use crate::outline::split_label;
+use crate::stats::is_arrow;
+use crate::text::{utf16_len, TextEdit};
+use crate::tokens::{prose_spans, SpanKind};
+
+/// Sequence diagram groups closed by a plain `end`
+const GROUP_KEYWORDS: &[&str] = &["alt", "opt", "loop", "par", "break", "critical", "group"];
+
+/// Keywords whose multi-line form is closed by `end <keyword>`
+const BLOCK_KEYWORDS: &[&str] = &[
+    "note", "hnote", "rnote", "legend", "title", "header", "footer",
+];
+
+/// What Enter does besides keeping the indentation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContinuationOptions {
+    pub unit: IndentUnit,
+    /// Start the line after a message with the same participants and arrow
+    pub prefill_messages: bool,
+}
+
+/// Text and caret after pressing Enter at `caret` (UTF-16 offset)
+///
+/// Returns None when the caret is not at the end of a line, so the editor
+/// inserts a plain line break.
+pub fn continue_line(text: &str, caret: usize, options: ContinuationOptions) -> Option<TextEdit> {
+    let at = byte_offset(text, caret);
+    let line_start = text[..at].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[at..].find('\n').map_or(text.len(), |index| at + index);
+    if !text[at..line_end].trim().is_empty() {
+        return None;
+    }
+    let line = &text[line_start..at];
+    let trimmed = line.trim();
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let nested = format!("{}{}", indent, options.unit.text());
+
+    let (body, closing) = if in_block(text, at) {
+        if is_prose(text, line_start + indent.len()) || trimmed.is_empty() {
+            // Already in the block's body
+            (indent.to_string(), None)
+        } else {
+            let closing = block_keyword(trimmed)
+                .filter(|_| !block_closed(text, at))
+                .map(|keyword| format!("{}end {}", indent, keyword));
+            (nested, closing)
+        }
+    } else if group_keyword(trimmed).is_some() {
+        let closing = (!group_closed(text)).then(|| format!("{}end", indent));
+        (nested, closing)
+    } else if options.prefill_messages {
+        let message = message_start(trimmed).unwrap_or_default();
+        (format!("{}{}", indent, message), None)
+    } else {
+        (indent.to_string(), None)
+    };
+
+    let mut inserted = format!("\n{}", body);
+    let caret = utf16_len(&text[..at]) + utf16_len(&inserted);
+    if let Some(closing) = closing {
+        inserted.push('\n');
+        inserted.push_str(&closing);
+    }
+    Some(TextEdit {
+        text: format!("{}{}{}", &text[..at], inserted, &text[at..]),
+        start: caret,
+        end: caret,
+    })
+}
+
+fn is_prose(text: &str, at: usize) -> bool {
+    prose_spans(text)
+        .iter()
+        .any(|span| span.kind == SpanKind::Prose && span.start <= at && at < span.end)
+}
+
+/// Whether a line inserted at `at` would be inside a note, legend or comment
+fn in_block(text: &str, at: usize) -> bool {
+    let probe = format!("{}\nx{}", &text[..at], &text[at..]);
+    is_prose(&probe, at + 1)
+}
+
+/// Whether the block opened by the line ending at `at` is closed later
+///
+/// An unclosed block runs to the end of the source, turning the last line
+/// into prose.
+fn block_closed(text: &str, at: usize) -> bool {
+    let probe = format!("{}\nx{}", &text[..at], &text[at..]);
+    let last = probe.trim_end();
+    let last_line = last.rfind('\n').map_or(0, |index| index + 1);
+    let last_line = last_line + (last.len() - last_line - last[last_line..].trim_start().len());
+    !is_prose(&probe, last_line)
+}
+
+fn block_keyword(line: &str) -> Option<&'static str> {
+    let first = line.split_whitespace().next()?.to_lowercase();
+    BLOCK_KEYWORDS
+        .iter()
+        .find(|keyword| **keyword == first)
+        .copied()
+}
+
+fn group_keyword(line: &str) -> Option<&'static str> {
+    let first = line.split_whitespace().next()?.to_lowercase();
+    GROUP_KEYWORDS
+        .iter()
+        .find(|keyword| **keyword == first)
+        .copied()
+}
+
+/// Whether every group in the source has its `end`, counting the groups
+/// outside notes and comments
+fn group_closed(text: &str) -> bool {
+    let spans = prose_spans(text);
+    let mut depth: isize = 0;
+    let mut offset = 0;
+    for raw in text.split_inclusive('\n') {
+        let line = raw.trim();
+        let start = offset + (raw.len() - raw.trim_start().len());
+        offset += raw.len();
+        let prose = spans
+            .iter()
+            .any(|span| span.kind == SpanKind::Prose && span.start <= start && start < span.end);
+        if line.is_empty() || prose {
+            continue;
+        }
+        if group_keyword(line).is_some() {
+            depth += 1;
+        } else if line.eq_ignore_ascii_case("end") {
+            depth -= 1;
+        }
+    }
+    depth <= 0
+}
+
+/// `A -> B : ` for a message line, keeping the arrow as written
+fn message_start(line: &str) -> Option<String> {
+    let (head, _) = split_label(line);
+    let tokens = words(head);
+    let arrow = tokens.iter().position(|token| is_arrow(token))?;
+    let from = arrow.checked_sub(1).and_then(|index| tokens.get(index));
+    let parts: Vec<&str> = [from, tokens.get(arrow), tokens.get(arrow + 1)]
+        .into_iter()
+        .flatten()
+        .copied()
+        .collect();
+    Some(format!("{} : ", parts.join(" ")))
+}
+
+/// Words separated by whitespace, keeping quoted names together
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                start.get_or_insert(index);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    words.push(&text[start..index]);
+                }
+            }
+            _ => {
+                start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(start) = start {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Press Enter after `after` and return the text with `|` at the caret
+    fn enter(text: &str, after: &str, options: ContinuationOptions) -> Option<String> {
+        let caret = utf16_len(&text[..text.find(after).unwrap() + after.len()]);
+        let edit = continue_line(text, caret, options)?;
+        let at = byte_offset(&edit.text, edit.start);
+        Some(format!("{}|{}", &edit.text[..at], &edit.text[at..]))
+    }
+
+    #[test]
+    fn test_keeps_indentation() {
+        let text = "@startuml\n  A -> B : hi\n@enduml";
+        assert_eq!(
+            enter(text, "hi", ContinuationOptions::default()).unwrap(),
+            "@startuml\n  A -> B : hi\n  |\n@enduml"
+        );
+        // Not at the end of the line
+        assert_eq!(enter(text, "A -", ContinuationOptions::default()), None);
+    }
+
+    #[test]
+    fn test_group_gets_end() {
+        let text = "@startuml\nalt success\n@enduml";
+        assert_eq!(
+            enter(text, "success", ContinuationOptions::default()).unwrap(),
+            "@startuml\nalt success\n  |\nend\n@enduml"
+        );
+        // Already closed
+        let text = "@startuml\nloop 3 times\nend\n@enduml";
+        assert_eq!(
+            enter(text, "times", ContinuationOptions::default()).unwrap(),
+            "@startuml\nloop 3 times\n  |\nend\n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_note_block() {
+        let options = ContinuationOptions {
+            unit: IndentUnit::Spaces(4),
+            ..ContinuationOptions::default()
+        };
+        let text = "@startuml\nnote left of A\n@enduml";
+        assert_eq!(
+            enter(text, "of A", options).unwrap(),
+            "@startuml\nnote left of A\n    |\nend note\n@enduml"
+        );
+
+        // Inside the body the block continues without another end
+        let text = "@startuml\nnote left of A\n    first\nend note\n@enduml";
+        assert_eq!(
+            enter(text, "first", options).unwrap(),
+            "@startuml\nnote left of A\n    first\n    |\nend note\n@enduml"
+        );
+        let text = "@startuml\nnote left of A\nend note\n@enduml";
+        assert_eq!(
+            enter(text, "of A", options).unwrap(),
+            "@startuml\nnote left of A\n    |\nend note\n@enduml"
+        );
+
+        // A one-line note is not a block
+        let text = "note left : done\nA -> B";
+        assert_eq!(
+            enter(text, "done", options).unwrap(),
+            "note left : done\n|\nA -> B"
+        );
+    }
+
+    #[test]
+    fn test_prefill_messages() {
+        let options = ContinuationOptions {
+            prefill_messages: true,
+            ..ContinuationOptions::default()
+        };
+        let text = "User -[#red]> \"Web Server\" : login";
+        assert_eq!(
+            enter(text, "login", options).unwrap(),
+            "User -[#red]> \"Web Server\" : login\nUser -[#red]> \"Web Server\" : |"
+        );
+        assert_eq!(
+            enter("[-> B : in", "in", options).unwrap(),
+            "[-> B : in\n[-> B : |"
+        );
+        assert_eq!(
+            enter("title Flow", "Flow", options).unwrap(),
+            "title Flow\n|"
+        );
+    }
+}
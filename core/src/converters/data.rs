@@ -0,0 +1,128 @@
+// JSON/YAML to PlantUML (@startjson / @startyaml) converter
+
+use super::{check_input, check_output, ConvertError};
+
+/// Structured data format of pasted input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+}
+
+impl DataFormat {
+    /// Display name used in error messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "JSON",
+            DataFormat::Yaml => "YAML",
+        }
+    }
+}
+
+/// Detect whether pasted text is JSON or YAML
+///
+/// JSON is tried first because every JSON document is also valid YAML.
+pub fn detect_data_format(input: &str) -> Option<DataFormat> {
+    if serde_json::from_str::<serde_json::Value>(input).is_ok() {
+        Some(DataFormat::Json)
+    } else if serde_yaml::from_str::<serde_yaml::Value>(input).is_ok() {
+        Some(DataFormat::Yaml)
+    } else {
+        None
+    }
+}
+
+/// Convert pasted JSON/YAML into a PlantUML document
+///
+/// The data is pretty-printed (key order is preserved) and wrapped in
+/// @startjson/@endjson or @startyaml/@endyaml.
+pub fn convert_data_to_plantuml(input: &str, format: DataFormat) -> Result<String, ConvertError> {
+    check_input(input)?;
+
+    let invalid = |reason: String| ConvertError::InvalidInput {
+        format: format.label().to_string(),
+        reason,
+    };
+
+    let (body, start, end) = match format {
+        DataFormat::Json => {
+            let value: serde_json::Value =
+                serde_json::from_str(input).map_err(|e| invalid(e.to_string()))?;
+            let body = serde_json::to_string_pretty(&value).map_err(|e| invalid(e.to_string()))?;
+            (body, "@startjson", "@endjson")
+        }
+        DataFormat::Yaml => {
+            let value: serde_yaml::Value =
+                serde_yaml::from_str(input).map_err(|e| invalid(e.to_string()))?;
+            let body = serde_yaml::to_string(&value).map_err(|e| invalid(e.to_string()))?;
+            (body, "@startyaml", "@endyaml")
+        }
+    };
+
+    check_output(format!("{}\n{}\n{}", start, body.trim_end(), end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ErrorCode;
+
+    #[test]
+    fn test_detect_json_and_yaml() {
+        assert_eq!(detect_data_format(r#"{"a": 1}"#), Some(DataFormat::Json));
+        assert_eq!(detect_data_format("a: 1\nb: [1, 2]"), Some(DataFormat::Yaml));
+        assert_eq!(detect_data_format("{ unclosed"), None);
+    }
+
+    #[test]
+    fn test_json_is_wrapped_and_pretty_printed() {
+        let result = convert_data_to_plantuml(r#"{"name":"Alice","age":30}"#, DataFormat::Json).unwrap();
+        assert_eq!(
+            result,
+            "@startjson\n{\n  \"name\": \"Alice\",\n  \"age\": 30\n}\n@endjson"
+        );
+    }
+
+    #[test]
+    fn test_yaml_is_wrapped() {
+        let result = convert_data_to_plantuml("name: Alice\nroles:\n- admin\n", DataFormat::Yaml).unwrap();
+        assert!(result.starts_with("@startyaml\nname: Alice\n"));
+        assert!(result.ends_with("\n@endyaml"));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let result = convert_data_to_plantuml("{ invalid", DataFormat::Json);
+        assert!(matches!(result, Err(ConvertError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn test_empty_and_too_large_input() {
+        assert!(matches!(
+            convert_data_to_plantuml("  ", DataFormat::Json),
+            Err(ConvertError::EmptyInput)
+        ));
+
+        let large = format!("[{}]", vec!["1"; 13_000].join(","));
+        assert!(matches!(
+            convert_data_to_plantuml(&large, DataFormat::Json),
+            Err(ConvertError::InputTooLarge(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_pretty_printed_output_too_large() {
+        // Compact input fits, but pretty-printing adds a line per element
+        let compact = format!("[{}]", vec!["1"; 7_000].join(","));
+        assert!(matches!(
+            convert_data_to_plantuml(&compact, DataFormat::Json),
+            Err(ConvertError::OutputTooLarge(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_error_code_conversion() {
+        let error = ConvertError::EmptyInput;
+        assert!(matches!(error.to_error_code(), ErrorCode::ImportError { .. }));
+    }
+}
@@ -0,0 +1,67 @@
+// Converters from external formats to PlantUML source
+//
+// Each converter takes pasted text and produces a complete PlantUML document
+// (including @start/@end tags) ready to be loaded into the editor.
+
+pub mod data;
+pub mod rust_types;
+pub mod sql;
+
+pub use data::{convert_data_to_plantuml, detect_data_format, DataFormat};
+pub use rust_types::convert_rust_to_plantuml;
+pub use sql::convert_sql_to_plantuml;
+
+use crate::models::ErrorCode;
+use crate::text::scalar_count;
+use crate::validation::{validate_plantuml_content, ValidationError, MAX_CONTENT_CHARS};
+
+/// Maximum size of pasted input accepted by converters (same as the editor limit)
+pub const MAX_IMPORT_CHARS: usize = MAX_CONTENT_CHARS;
+
+/// Converter errors
+#[derive(Debug, thiserror::Error)]
+pub enum ConvertError {
+    #[error("取り込む内容が空です")]
+    EmptyInput,
+
+    #[error("取り込む内容が大きすぎます: {0}文字 (上限: {1}文字)")]
+    InputTooLarge(usize, usize),
+
+    #[error("{format}として解析できませんでした: {reason}")]
+    InvalidInput { format: String, reason: String },
+
+    #[error("変換結果が大きすぎます: {0}文字 (上限: {1}文字)")]
+    OutputTooLarge(usize, usize),
+}
+
+impl ConvertError {
+    /// Convert to ErrorCode with embedded data
+    pub fn to_error_code(&self) -> ErrorCode {
+        ErrorCode::ImportError {
+            reason: self.to_string(),
+        }
+    }
+}
+
+/// Check pasted input before conversion
+pub fn check_input(input: &str) -> Result<(), ConvertError> {
+    if input.trim().is_empty() {
+        return Err(ConvertError::EmptyInput);
+    }
+    let chars = scalar_count(input);
+    if chars > MAX_IMPORT_CHARS {
+        return Err(ConvertError::InputTooLarge(chars, MAX_IMPORT_CHARS));
+    }
+    Ok(())
+}
+
+/// Check generated PlantUML against the editor validation rules
+pub fn check_output(output: String) -> Result<String, ConvertError> {
+    match validate_plantuml_content(&output) {
+        Ok(()) => Ok(output),
+        Err(ValidationError::ContentTooLarge(actual, max)) => {
+            Err(ConvertError::OutputTooLarge(actual, max))
+        }
+        Err(ValidationError::EmptyContent) => Err(ConvertError::EmptyInput),
+    }
+}
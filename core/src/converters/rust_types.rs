@@ -0,0 +1,628 @@
+// Rust struct/enum definitions to PlantUML class diagram converter
+//
+// Native builds parse with `syn`. WASM builds use a simplified hand-written
+// parser (see `parse_rust_types_simple`) to keep the frontend bundle small.
+
+use super::{check_input, check_output, ConvertError};
+
+/// Field visibility
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Crate,
+    Private,
+}
+
+impl Visibility {
+    fn symbol(&self) -> char {
+        match self {
+            Visibility::Public => '+',
+            Visibility::Crate => '~',
+            Visibility::Private => '-',
+        }
+    }
+}
+
+/// Struct field (tuple fields are named by index)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub ty: String,
+    pub visibility: Visibility,
+}
+
+/// Enum variant with its payload types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+/// Kind of type definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeDefKind {
+    Struct { fields: Vec<Field> },
+    Enum { variants: Vec<Variant> },
+}
+
+/// Parsed struct or enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDef {
+    pub name: String,
+    pub generics: Vec<String>,
+    pub kind: TypeDefKind,
+}
+
+/// Convert pasted Rust type definitions into a PlantUML class diagram
+pub fn convert_rust_to_plantuml(input: &str) -> Result<String, ConvertError> {
+    check_input(input)?;
+
+    let types = parse_rust_types(input)?;
+    if types.is_empty() {
+        return Err(invalid("struct/enum定義が見つかりませんでした".to_string()));
+    }
+
+    check_output(render_class_diagram(&types))
+}
+
+fn invalid(reason: String) -> ConvertError {
+    ConvertError::InvalidInput {
+        format: "Rust".to_string(),
+        reason,
+    }
+}
+
+/// Parse struct/enum definitions with `syn`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_rust_types(input: &str) -> Result<Vec<TypeDef>, ConvertError> {
+    let file = syn::parse_file(input).map_err(|e| invalid(e.to_string()))?;
+    let mut types = Vec::new();
+    collect_syn_items(&file.items, &mut types);
+    Ok(types)
+}
+
+/// Parse struct/enum definitions with the simplified parser
+#[cfg(target_arch = "wasm32")]
+pub fn parse_rust_types(input: &str) -> Result<Vec<TypeDef>, ConvertError> {
+    parse_rust_types_simple(input)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn collect_syn_items(items: &[syn::Item], types: &mut Vec<TypeDef>) {
+    for item in items {
+        match item {
+            syn::Item::Struct(item) => types.push(TypeDef {
+                name: item.ident.to_string(),
+                generics: syn_generics(&item.generics),
+                kind: TypeDefKind::Struct {
+                    fields: syn_fields(&item.fields),
+                },
+            }),
+            syn::Item::Enum(item) => types.push(TypeDef {
+                name: item.ident.to_string(),
+                generics: syn_generics(&item.generics),
+                kind: TypeDefKind::Enum {
+                    variants: item
+                        .variants
+                        .iter()
+                        .map(|v| Variant {
+                            name: v.ident.to_string(),
+                            fields: syn_fields(&v.fields),
+                        })
+                        .collect(),
+                },
+            }),
+            syn::Item::Mod(item) => {
+                if let Some((_, items)) = &item.content {
+                    collect_syn_items(items, types);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn syn_generics(generics: &syn::Generics) -> Vec<String> {
+    generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn syn_fields(fields: &syn::Fields) -> Vec<Field> {
+    use quote::ToTokens;
+
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| Field {
+            name: field
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string())
+                .unwrap_or_else(|| index.to_string()),
+            ty: tidy_type(&field.ty.to_token_stream().to_string()),
+            visibility: match &field.vis {
+                syn::Visibility::Public(_) => Visibility::Public,
+                syn::Visibility::Restricted(_) => Visibility::Crate,
+                syn::Visibility::Inherited => Visibility::Private,
+            },
+        })
+        .collect()
+}
+
+/// Normalize token-stream spacing (`Vec < Foo >` -> `Vec<Foo>`)
+fn tidy_type(ty: &str) -> String {
+    let mut result = String::with_capacity(ty.len());
+    for part in ty.split_whitespace() {
+        let glue = matches!(part, "<" | ">" | "," | "::" | ")" | "]" | ";")
+            || result.ends_with(['<', '&', '(', '['])
+            || result.ends_with("::");
+        if !result.is_empty() && !glue {
+            result.push(' ');
+        }
+        result.push_str(part);
+        if part == "," || part == ";" {
+            result.push(' ');
+        }
+    }
+    result.replace(" >", ">").replace("  ", " ")
+}
+
+/// Parse struct/enum definitions without `syn`
+///
+/// Handles named, tuple and unit structs and enums with unit/tuple/struct
+/// variants. Attributes, comments and other items are skipped.
+pub fn parse_rust_types_simple(input: &str) -> Result<Vec<TypeDef>, ConvertError> {
+    let source = strip_comments_and_attributes(input);
+    let mut types = Vec::new();
+    let mut rest = source.as_str();
+
+    while let Some((keyword, position)) = find_next_definition(rest) {
+        rest = &rest[position + keyword.len()..];
+
+        let name_end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c.is_whitespace()))
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].trim().to_string();
+        if name.is_empty() {
+            return Err(invalid(format!("{}の名前が見つかりません", keyword)));
+        }
+        rest = &rest[name_end..];
+
+        let mut generics = Vec::new();
+        if rest.starts_with('<') {
+            let end = matching_close(rest, '<', '>')
+                .ok_or_else(|| invalid(format!("{}のジェネリクスが閉じられていません", name)))?;
+            generics = split_top_level(&rest[1..end])
+                .iter()
+                .map(|param| param.split(':').next().unwrap_or("").trim().to_string())
+                .filter(|param| !param.is_empty() && !param.starts_with('\''))
+                .collect();
+            rest = &rest[end + 1..];
+        }
+        rest = rest.trim_start();
+
+        // Skip where clauses
+        if rest.starts_with("where") {
+            let body = rest.find(['{', ';']).unwrap_or(rest.len());
+            rest = &rest[body..];
+        }
+
+        let kind = match keyword {
+            "struct" => {
+                let (fields, consumed) = parse_body_fields(rest, &name)?;
+                rest = &rest[consumed..];
+                TypeDefKind::Struct { fields }
+            }
+            _ => {
+                if !rest.starts_with('{') {
+                    return Err(invalid(format!("{}の本体が見つかりません", name)));
+                }
+                let end = matching_close(rest, '{', '}')
+                    .ok_or_else(|| invalid(format!("{}の本体が閉じられていません", name)))?;
+                let variants = split_top_level(&rest[1..end])
+                    .iter()
+                    .map(|variant| parse_variant(variant, &name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rest = &rest[end + 1..];
+                TypeDefKind::Enum { variants }
+            }
+        };
+
+        types.push(TypeDef {
+            name,
+            generics,
+            kind,
+        });
+    }
+
+    Ok(types)
+}
+
+/// Find the next `struct`/`enum` keyword at a word boundary
+fn find_next_definition(source: &str) -> Option<(&'static str, usize)> {
+    let mut best: Option<(&'static str, usize)> = None;
+    for keyword in ["struct", "enum"] {
+        let mut offset = 0;
+        while let Some(found) = source[offset..].find(keyword) {
+            let start = offset + found;
+            let end = start + keyword.len();
+            let before_ok = !source[..start]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let after_ok = source[end..].starts_with(char::is_whitespace);
+            if before_ok && after_ok {
+                if best.is_none_or(|(_, position)| start < position) {
+                    best = Some((keyword, start));
+                }
+                break;
+            }
+            offset = end;
+        }
+    }
+    best
+}
+
+/// Parse `{ ... }`, `( ... );` or `;` and return fields with consumed length
+fn parse_body_fields(rest: &str, name: &str) -> Result<(Vec<Field>, usize), ConvertError> {
+    if rest.starts_with(';') {
+        return Ok((Vec::new(), 1));
+    }
+    let (open, close) = match rest.chars().next() {
+        Some('{') => ('{', '}'),
+        Some('(') => ('(', ')'),
+        _ => return Err(invalid(format!("{}の本体が見つかりません", name))),
+    };
+    let end = matching_close(rest, open, close)
+        .ok_or_else(|| invalid(format!("{}の本体が閉じられていません", name)))?;
+    let fields = parse_field_list(&rest[1..end], open == '(');
+    Ok((fields, end + 1))
+}
+
+fn parse_variant(variant: &str, enum_name: &str) -> Result<Variant, ConvertError> {
+    let variant = variant.trim();
+    let name_end = variant
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(variant.len());
+    let name = variant[..name_end].to_string();
+    let rest = variant[name_end..].trim_start();
+
+    let fields = match rest.chars().next() {
+        Some('{') | Some('(') => parse_body_fields(rest, enum_name)?.0,
+        // Unit variant, possibly with an explicit discriminant
+        _ => Vec::new(),
+    };
+    Ok(Variant { name, fields })
+}
+
+fn parse_field_list(body: &str, tuple: bool) -> Vec<Field> {
+    split_top_level(body)
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let (visibility, field) = strip_visibility(field.trim());
+            if tuple {
+                Field {
+                    name: index.to_string(),
+                    ty: tidy_type(field),
+                    visibility,
+                }
+            } else {
+                let (name, ty) = field.split_once(':').unwrap_or((field, ""));
+                Field {
+                    name: name.trim().to_string(),
+                    ty: tidy_type(ty.trim()),
+                    visibility,
+                }
+            }
+        })
+        .collect()
+}
+
+fn strip_visibility(field: &str) -> (Visibility, &str) {
+    match field.strip_prefix("pub") {
+        // pub(crate), pub(super), pub(in path)
+        Some(rest) if rest.starts_with('(') => {
+            let end = rest.find(')').map_or(rest.len(), |end| end + 1);
+            (Visibility::Crate, rest[end..].trim_start())
+        }
+        Some(rest) if rest.starts_with(char::is_whitespace) => (Visibility::Public, rest.trim_start()),
+        _ => (Visibility::Private, field),
+    }
+}
+
+/// Position of the bracket closing the one at index 0
+fn matching_close(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (index, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth = depth.checked_sub(1)?;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+/// Split on commas outside of <>, (), [] and {}
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0i32;
+    let mut previous = ' ';
+
+    for c in text.chars() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            // `->` in fn pointer types is not a closing bracket
+            '>' if previous != '-' => depth -= 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                previous = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        previous = c;
+    }
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+fn strip_comments_and_attributes(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        result.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                result.push(' ');
+            }
+            '#' if matches!(chars.peek(), Some('[') | Some('!')) => {
+                let mut depth = 0;
+                for c in chars.by_ref() {
+                    match c {
+                        '[' => depth += 1,
+                        ']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                result.push(' ');
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Render parsed types as a PlantUML class diagram
+pub fn render_class_diagram(types: &[TypeDef]) -> String {
+    let mut lines = vec!["@startuml".to_string()];
+    let names: Vec<&str> = types.iter().map(|t| t.name.as_str()).collect();
+    let mut relations = Vec::new();
+
+    for def in types {
+        let generics = if def.generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", def.generics.join(", "))
+        };
+        lines.push(String::new());
+
+        match &def.kind {
+            TypeDefKind::Struct { fields } => {
+                lines.push(format!("class {}{} {{", def.name, generics));
+                for field in fields {
+                    lines.push(format!(
+                        "  {}{} : {}",
+                        field.visibility.symbol(),
+                        field.name,
+                        field.ty
+                    ));
+                    relations.extend(relation_lines(&def.name, "*--", field, &names));
+                }
+            }
+            TypeDefKind::Enum { variants } => {
+                lines.push(format!("enum {}{} {{", def.name, generics));
+                for variant in variants {
+                    lines.push(format!("  {}", render_variant(variant)));
+                    for field in &variant.fields {
+                        relations.extend(relation_lines(&def.name, "-->", field, &names));
+                    }
+                }
+            }
+        }
+        lines.push("}".to_string());
+    }
+
+    relations.dedup();
+    if !relations.is_empty() {
+        lines.push(String::new());
+        lines.extend(relations);
+    }
+
+    lines.push("@enduml".to_string());
+    lines.join("\n")
+}
+
+fn render_variant(variant: &Variant) -> String {
+    if variant.fields.is_empty() {
+        return variant.name.clone();
+    }
+    let tuple = variant.fields.iter().all(|f| f.name.parse::<usize>().is_ok());
+    if tuple {
+        let types: Vec<&str> = variant.fields.iter().map(|f| f.ty.as_str()).collect();
+        format!("{}({})", variant.name, types.join(", "))
+    } else {
+        let fields: Vec<String> = variant
+            .fields
+            .iter()
+            .map(|f| format!("{}: {}", f.name, f.ty))
+            .collect();
+        format!("{} {{ {} }}", variant.name, fields.join(", "))
+    }
+}
+
+/// Relations from a field to other parsed types
+fn relation_lines(owner: &str, arrow: &str, field: &Field, names: &[&str]) -> Vec<String> {
+    const MANY: &[&str] = &[
+        "Vec", "VecDeque", "HashSet", "BTreeSet", "HashMap", "BTreeMap", "LinkedList",
+    ];
+
+    let identifiers: Vec<&str> = field
+        .ty
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|part| !part.is_empty())
+        .collect();
+    let multiplicity = if identifiers.iter().any(|id| MANY.contains(id)) || field.ty.starts_with('[') {
+        "*"
+    } else if identifiers.first() == Some(&"Option") {
+        "0..1"
+    } else {
+        "1"
+    };
+
+    let mut targets: Vec<&str> = identifiers
+        .into_iter()
+        .filter(|id| names.contains(id))
+        .collect();
+    targets.dedup();
+
+    targets
+        .into_iter()
+        .map(|target| {
+            format!(
+                "{} {} \"{}\" {} : {}",
+                owner, arrow, multiplicity, target, field.name
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+        /// A user account
+        #[derive(Debug, Clone)]
+        pub struct User {
+            pub id: u64,
+            name: String,
+            pub(crate) role: Role,
+            pub teams: Vec<Team>,
+            manager: Option<Box<User>>,
+        }
+
+        pub struct Team(pub String, Vec<User>);
+
+        pub struct Marker;
+
+        pub enum Role {
+            Admin,
+            Member { team: Team },
+            Guest(String),
+        }
+
+        pub struct Page<T: Clone> where T: Default {
+            items: Vec<T>,
+        }
+    "#;
+
+    fn assert_parsed(types: &[TypeDef]) {
+        assert_eq!(types.len(), 5);
+        assert_eq!(types[0].name, "User");
+        match &types[0].kind {
+            TypeDefKind::Struct { fields } => {
+                assert_eq!(fields.len(), 5);
+                assert_eq!(fields[0].visibility, Visibility::Public);
+                assert_eq!(fields[1].visibility, Visibility::Private);
+                assert_eq!(fields[2].visibility, Visibility::Crate);
+                assert_eq!(fields[3].ty, "Vec<Team>");
+                assert_eq!(fields[4].ty, "Option<Box<User>>");
+            }
+            _ => panic!("Expected struct"),
+        }
+        match &types[1].kind {
+            TypeDefKind::Struct { fields } => assert_eq!(fields[1].name, "1"),
+            _ => panic!("Expected struct"),
+        }
+        match &types[3].kind {
+            TypeDefKind::Enum { variants } => {
+                assert_eq!(variants.len(), 3);
+                assert_eq!(variants[1].fields[0].name, "team");
+            }
+            _ => panic!("Expected enum"),
+        }
+        assert_eq!(types[4].generics, vec!["T".to_string()]);
+    }
+
+    #[test]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_parse_with_syn() {
+        assert_parsed(&parse_rust_types(SOURCE).unwrap());
+    }
+
+    #[test]
+    fn test_parse_simple() {
+        assert_parsed(&parse_rust_types_simple(SOURCE).unwrap());
+    }
+
+    #[test]
+    fn test_render_class_diagram() {
+        let result = convert_rust_to_plantuml(SOURCE).unwrap();
+        assert!(result.contains("class User {"));
+        assert!(result.contains("  +id : u64"));
+        assert!(result.contains("  -name : String"));
+        assert!(result.contains("  ~role : Role"));
+        assert!(result.contains("enum Role {"));
+        assert!(result.contains("  Member { team: Team }"));
+        assert!(result.contains("class Page<T> {"));
+        assert!(result.contains("User *-- \"1\" Role : role"));
+        assert!(result.contains("User *-- \"*\" Team : teams"));
+        assert!(result.contains("User *-- \"0..1\" User : manager"));
+        assert!(result.contains("Role --> \"1\" Team : team"));
+    }
+
+    #[test]
+    fn test_no_definitions() {
+        let result = convert_rust_to_plantuml("fn main() {}");
+        assert!(matches!(result, Err(ConvertError::InvalidInput { .. })));
+    }
+}
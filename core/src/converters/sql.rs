@@ -0,0 +1,561 @@
+// SQL DDL (CREATE TABLE) to PlantUML ER diagram converter
+//
+// Supports a practical subset of DDL:
+// - CREATE TABLE [IF NOT EXISTS] name (...)
+// - Column constraints: PRIMARY KEY, NOT NULL, REFERENCES table(column)
+// - Table constraints: PRIMARY KEY (...), FOREIGN KEY (...) REFERENCES table(...),
+//   optionally prefixed by CONSTRAINT name
+// Other statements (CREATE INDEX, INSERT, ...) are ignored.
+
+use super::{check_input, check_output, ConvertError};
+
+/// Parsed table column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub data_type: String,
+    pub primary_key: bool,
+    pub not_null: bool,
+    pub foreign_key: bool,
+}
+
+/// Foreign key relation between two tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub columns: Vec<String>,
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+}
+
+/// Parsed CREATE TABLE statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// Convert CREATE TABLE statements into a PlantUML ER diagram
+pub fn convert_sql_to_plantuml(input: &str) -> Result<String, ConvertError> {
+    check_input(input)?;
+
+    let tables = parse_create_tables(input)?;
+    if tables.is_empty() {
+        return Err(ConvertError::InvalidInput {
+            format: "SQL DDL".to_string(),
+            reason: "CREATE TABLE文が見つかりませんでした".to_string(),
+        });
+    }
+
+    check_output(render_er_diagram(&tables))
+}
+
+/// Parse all CREATE TABLE statements in the input
+pub fn parse_create_tables(input: &str) -> Result<Vec<Table>, ConvertError> {
+    let sql = strip_comments(input);
+    let mut tables = Vec::new();
+
+    for statement in split_top_level(&sql, ';') {
+        let statement = statement.trim();
+        if !is_create_table(statement) {
+            continue;
+        }
+        tables.push(parse_create_table(statement)?);
+    }
+
+    Ok(tables)
+}
+
+/// Whether `statement` is `CREATE [GLOBAL|LOCAL] [TEMP|TEMPORARY|UNLOGGED] TABLE ...`
+/// (indexes and views are skipped even when their names contain "table")
+fn is_create_table(statement: &str) -> bool {
+    let mut words = statement
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_uppercase)
+        .peekable();
+    if words.next().as_deref() != Some("CREATE") {
+        return false;
+    }
+    words.next_if(|word| matches!(word.as_str(), "GLOBAL" | "LOCAL"));
+    words.next_if(|word| matches!(word.as_str(), "TEMP" | "TEMPORARY" | "UNLOGGED"));
+    words.next().as_deref() == Some("TABLE")
+}
+
+fn invalid(reason: String) -> ConvertError {
+    ConvertError::InvalidInput {
+        format: "SQL DDL".to_string(),
+        reason,
+    }
+}
+
+fn parse_create_table(statement: &str) -> Result<Table, ConvertError> {
+    let open = statement
+        .find('(')
+        .ok_or_else(|| invalid("テーブル定義の括弧が見つかりません".to_string()))?;
+    let close = statement
+        .rfind(')')
+        .filter(|close| *close > open)
+        .ok_or_else(|| invalid("テーブル定義の括弧が閉じられていません".to_string()))?;
+
+    // CREATE [TEMPORARY] TABLE [IF NOT EXISTS] <name>
+    let header = tokenize(&statement[..open]);
+    let name = header
+        .last()
+        .filter(|token| !token.eq_ignore_ascii_case("TABLE"))
+        .map(|token| unquote(token))
+        .ok_or_else(|| invalid("テーブル名が見つかりません".to_string()))?;
+
+    let mut table = Table {
+        name,
+        columns: Vec::new(),
+        foreign_keys: Vec::new(),
+    };
+
+    for item in split_top_level(&statement[open + 1..close], ',') {
+        let tokens = tokenize(&item);
+        if tokens.is_empty() {
+            continue;
+        }
+        parse_table_item(&mut table, &tokens)?;
+    }
+
+    // Mark columns participating in foreign keys
+    let fk_columns: Vec<String> = table
+        .foreign_keys
+        .iter()
+        .flat_map(|fk| fk.columns.clone())
+        .collect();
+    for column in &mut table.columns {
+        if fk_columns.iter().any(|c| c.eq_ignore_ascii_case(&column.name)) {
+            column.foreign_key = true;
+        }
+    }
+
+    Ok(table)
+}
+
+fn parse_table_item(table: &mut Table, tokens: &[String]) -> Result<(), ConvertError> {
+    let keyword = tokens[0].to_ascii_uppercase();
+    match keyword.as_str() {
+        // CONSTRAINT <name> <constraint>
+        "CONSTRAINT" => {
+            if tokens.len() > 2 {
+                parse_table_item(table, &tokens[2..])?;
+            }
+        }
+        "PRIMARY" => {
+            let columns = tokens.get(2).map(|t| parse_name_list(t)).unwrap_or_default();
+            for column in &mut table.columns {
+                if columns.iter().any(|c| c.eq_ignore_ascii_case(&column.name)) {
+                    column.primary_key = true;
+                    column.not_null = true;
+                }
+            }
+        }
+        "FOREIGN" => {
+            let columns = tokens.get(2).map(|t| parse_name_list(t)).unwrap_or_default();
+            if let Some(fk) = parse_references(tokens.get(3..).unwrap_or_default(), columns) {
+                table.foreign_keys.push(fk);
+            }
+        }
+        "UNIQUE" | "KEY" | "INDEX" | "CHECK" | "FULLTEXT" | "SPATIAL" => {}
+        _ => parse_column(table, tokens),
+    }
+    Ok(())
+}
+
+fn parse_column(table: &mut Table, tokens: &[String]) {
+    const CONSTRAINT_KEYWORDS: &[&str] = &[
+        "NOT", "NULL", "PRIMARY", "REFERENCES", "UNIQUE", "DEFAULT", "CHECK", "CONSTRAINT",
+        "AUTO_INCREMENT", "AUTOINCREMENT", "GENERATED", "COLLATE", "COMMENT", "IDENTITY",
+    ];
+
+    let name = unquote(&tokens[0]);
+    let mut data_type = String::new();
+    let mut index = 1;
+    while let Some(token) = tokens.get(index) {
+        if CONSTRAINT_KEYWORDS.contains(&token.to_ascii_uppercase().as_str()) {
+            break;
+        }
+        if !data_type.is_empty() && !token.starts_with('(') {
+            data_type.push(' ');
+        }
+        data_type.push_str(token);
+        index += 1;
+    }
+
+    let mut column = Column {
+        name: name.clone(),
+        data_type: data_type.to_ascii_uppercase(),
+        primary_key: false,
+        not_null: false,
+        foreign_key: false,
+    };
+
+    let rest = &tokens[index..];
+    for (i, token) in rest.iter().enumerate() {
+        let next = rest.get(i + 1).map(|t| t.to_ascii_uppercase());
+        match token.to_ascii_uppercase().as_str() {
+            "PRIMARY" if next.as_deref() == Some("KEY") => {
+                column.primary_key = true;
+                column.not_null = true;
+            }
+            "NOT" if next.as_deref() == Some("NULL") => column.not_null = true,
+            "REFERENCES" => {
+                if let Some(fk) = parse_references(&rest[i..], vec![name.clone()]) {
+                    column.foreign_key = true;
+                    table.foreign_keys.push(fk);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    table.columns.push(column);
+}
+
+/// Parse `REFERENCES table (columns)` starting at the REFERENCES keyword
+fn parse_references(tokens: &[String], columns: Vec<String>) -> Option<ForeignKey> {
+    let position = tokens
+        .iter()
+        .position(|t| t.eq_ignore_ascii_case("REFERENCES"))?;
+    let referenced_table = unquote(tokens.get(position + 1)?);
+    let referenced_columns = tokens
+        .get(position + 2)
+        .filter(|t| t.starts_with('('))
+        .map(|t| parse_name_list(t))
+        .unwrap_or_default();
+
+    Some(ForeignKey {
+        columns,
+        referenced_table,
+        referenced_columns,
+    })
+}
+
+/// Render parsed tables as a PlantUML ER diagram
+pub fn render_er_diagram(tables: &[Table]) -> String {
+    let mut lines = vec![
+        "@startuml".to_string(),
+        "hide circle".to_string(),
+        "skinparam linetype ortho".to_string(),
+    ];
+
+    for table in tables {
+        lines.push(String::new());
+        lines.push(format!("entity \"{}\" as {} {{", table.name, alias(&table.name)));
+
+        let (keys, others): (Vec<&Column>, Vec<&Column>) =
+            table.columns.iter().partition(|c| c.primary_key);
+        for column in &keys {
+            lines.push(format!("  {}", render_column(column)));
+        }
+        if !keys.is_empty() && !others.is_empty() {
+            lines.push("  --".to_string());
+        }
+        for column in &others {
+            lines.push(format!("  {}", render_column(column)));
+        }
+        lines.push("}".to_string());
+    }
+
+    let relations: Vec<String> = tables
+        .iter()
+        .flat_map(|table| {
+            table.foreign_keys.iter().map(move |fk| {
+                let required = fk.columns.iter().all(|name| {
+                    table
+                        .columns
+                        .iter()
+                        .any(|c| c.name.eq_ignore_ascii_case(name) && c.not_null)
+                });
+                let parent_end = if required { "||" } else { "o|" };
+                format!(
+                    "{} }}o--{} {} : {}",
+                    alias(&table.name),
+                    parent_end,
+                    alias(&fk.referenced_table),
+                    fk.columns.join(", ")
+                )
+            })
+        })
+        .collect();
+    if !relations.is_empty() {
+        lines.push(String::new());
+        lines.extend(relations);
+    }
+
+    lines.push("@enduml".to_string());
+    lines.join("\n")
+}
+
+fn render_column(column: &Column) -> String {
+    let mut line = String::new();
+    if column.not_null {
+        line.push_str("* ");
+    }
+    line.push_str(&column.name);
+    if !column.data_type.is_empty() {
+        line.push_str(" : ");
+        line.push_str(&column.data_type);
+    }
+    if column.primary_key {
+        line.push_str(" <<PK>>");
+    }
+    if column.foreign_key {
+        line.push_str(" <<FK>>");
+    }
+    line
+}
+
+/// PlantUML alias for a table name (schema.table -> schema_table)
+fn alias(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Remove `-- line` and `/* block */` comments
+fn strip_comments(sql: &str) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            result.push(c);
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' | '`' => {
+                quote = Some(c);
+                result.push(c);
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        result.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                result.push(' ');
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Split on a separator that is not nested in parentheses or quotes
+fn split_top_level(text: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+
+    for c in text.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ if c == separator && depth == 0 => {
+                    parts.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            },
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Split an item into words; a parenthesized group is kept as one token
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+
+    let flush = |current: &mut String, tokens: &mut Vec<String>| {
+        if !current.is_empty() {
+            tokens.push(std::mem::take(current));
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => {
+                flush(&mut current, &mut tokens);
+                let mut group = String::from("(");
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    group.push(c);
+                    match c {
+                        '(' => depth += 1,
+                        ')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                tokens.push(group);
+            }
+            '\'' | '"' | '`' | '[' => {
+                let closing = if c == '[' { ']' } else { c };
+                current.push(c);
+                for c in chars.by_ref() {
+                    current.push(c);
+                    if c == closing {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => flush(&mut current, &mut tokens),
+            _ => current.push(c),
+        }
+    }
+    flush(&mut current, &mut tokens);
+
+    tokens
+}
+
+/// Parse `(a, b)` into names
+fn parse_name_list(group: &str) -> Vec<String> {
+    group
+        .trim_start_matches('(')
+        .trim_end_matches(')')
+        .split(',')
+        .map(|name| unquote(name.trim()))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Remove identifier quoting ("name", `name`, [name])
+fn unquote(identifier: &str) -> String {
+    identifier
+        .split('.')
+        .map(|part| part.trim_matches(|c| matches!(c, '"' | '`' | '[' | ']')))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        -- チーム
+        CREATE TABLE teams (
+            id INTEGER PRIMARY KEY,
+            name VARCHAR(100) NOT NULL
+        );
+
+        /* ユーザー */
+        CREATE TABLE IF NOT EXISTS `users` (
+            `id` BIGINT NOT NULL AUTO_INCREMENT,
+            `team_id` INTEGER NOT NULL,
+            `manager_id` BIGINT REFERENCES users(id),
+            `score` DECIMAL(10, 2) DEFAULT 0,
+            PRIMARY KEY (`id`),
+            CONSTRAINT fk_team FOREIGN KEY (`team_id`) REFERENCES teams (id)
+        );
+
+        CREATE INDEX idx_users_team ON users (team_id);
+    "#;
+
+    #[test]
+    fn test_parse_tables_and_columns() {
+        let tables = parse_create_tables(SCHEMA).unwrap();
+        assert_eq!(tables.len(), 2);
+
+        let users = &tables[1];
+        assert_eq!(users.name, "users");
+        assert_eq!(users.columns.len(), 4);
+        assert!(users.columns[0].primary_key);
+        assert_eq!(users.columns[3].data_type, "DECIMAL(10, 2)");
+        assert!(users.columns[1].foreign_key);
+        assert!(users.columns[2].foreign_key);
+        assert_eq!(users.foreign_keys.len(), 2);
+        assert_eq!(users.foreign_keys[1].referenced_table, "teams");
+    }
+
+    #[test]
+    fn test_render_er_diagram() {
+        let result = convert_sql_to_plantuml(SCHEMA).unwrap();
+        assert!(result.starts_with("@startuml"));
+        assert!(result.ends_with("@enduml"));
+        assert!(result.contains("entity \"teams\" as teams {"));
+        assert!(result.contains("  * id : INTEGER <<PK>>"));
+        assert!(result.contains("  * team_id : INTEGER <<FK>>"));
+        assert!(result.contains("users }o--|| teams : team_id"));
+        assert!(result.contains("users }o--o| users : manager_id"));
+    }
+
+    #[test]
+    fn test_no_create_table() {
+        let result = convert_sql_to_plantuml("SELECT * FROM users;");
+        assert!(matches!(result, Err(ConvertError::InvalidInput { .. })));
+    }
+
+    #[test]
+    fn test_schema_qualified_name() {
+        let tables = parse_create_tables("CREATE TABLE app.\"orders\" (id INT PRIMARY KEY)").unwrap();
+        assert_eq!(tables[0].name, "app.orders");
+        assert!(render_er_diagram(&tables).contains("as app_orders"));
+    }
+
+    #[test]
+    fn test_skips_other_create_statements() {
+        let input = "
+            CREATE TABLE orders (id INT PRIMARY KEY, customer_id INT);
+            CREATE INDEX idx_orders_table ON orders (customer_id);
+            CREATE VIEW table_view AS SELECT id FROM orders;
+            CREATE GLOBAL TEMPORARY TABLE staging (id INT);
+            CREATE UNLOGGED TABLE log_table (message TEXT);
+        ";
+        let tables = parse_create_tables(input).unwrap();
+        let names: Vec<_> = tables.iter().map(|table| table.name.as_str()).collect();
+        assert_eq!(names, ["orders", "staging", "log_table"]);
+        assert_eq!(tables[0].columns.len(), 2);
+    }
+
+    #[test]
+    fn test_incomplete_foreign_key() {
+        for input in [
+            "CREATE TABLE t (id int, FOREIGN KEY);",
+            "CREATE TABLE t (id int, CONSTRAINT fk FOREIGN KEY);",
+        ] {
+            let tables = parse_create_tables(input).unwrap();
+            assert_eq!(tables[0].columns.len(), 1);
+            assert!(tables[0].foreign_keys.is_empty());
+        }
+    }
+}
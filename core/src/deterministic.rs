@@ -0,0 +1,183 @@
+// Deterministic export output
+//
+// PlantUML embeds data that can change between two renders of the same
+// source: the encoded source and server version in SVG comments and
+// processing instructions, randomly generated IDs for gradients, filters and
+// clip paths, and text and time chunks in PNG files. Committed images then
+// show diffs although the diagram did not change. `normalize` removes that
+// data and renumbers the generated IDs in document order, so unchanged
+// sources give byte-identical files.
+
+use std::collections::HashSet;
+
+use crate::models::ImageFormat;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// PNG chunks dropped from deterministic output (text and modification time)
+const PNG_METADATA_CHUNKS: &[&[u8; 4]] = &[b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+/// Prefix of the renumbered IDs (`pe0`, `pe1`, ...)
+const ID_PREFIX: &str = "pe";
+
+/// Image without run-dependent metadata
+///
+/// Data that is not a well-formed PNG or UTF-8 SVG is returned unchanged.
+pub fn normalize(format: ImageFormat, data: &[u8]) -> Vec<u8> {
+    match format {
+        ImageFormat::Svg => match std::str::from_utf8(data) {
+            Ok(svg) => normalize_svg(svg).into_bytes(),
+            Err(_) => data.to_vec(),
+        },
+        ImageFormat::Png => strip_png_metadata(data).unwrap_or_else(|| data.to_vec()),
+        // Text output carries no metadata
+        ImageFormat::Txt => data.to_vec(),
+        // Removing the PDF creation date would move the objects its
+        // cross-reference table points to
+        ImageFormat::Pdf => data.to_vec(),
+    }
+}
+
+/// SVG without comments and non-XML processing instructions, with the IDs
+/// referenced through `url(#...)` renumbered in order of definition
+pub fn normalize_svg(svg: &str) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find("<!--").into_iter().chain(rest.find("<?")).min() {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let (close, keep) = if tail.starts_with("<!--") {
+            ("-->", false)
+        } else {
+            // Only the XML declaration, not `<?plantuml ...?>` or `<?xml-...?>`
+            let declaration = tail
+                .strip_prefix("<?xml")
+                .is_some_and(|after| after.starts_with(|c: char| c.is_whitespace() || c == '?'));
+            ("?>", declaration)
+        };
+        let end = tail.find(close).map_or(tail.len(), |index| index + close.len());
+        if keep {
+            out.push_str(&tail[..end]);
+        }
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    renumber_ids(&out)
+}
+
+/// `svg` with every ID used as `url(#id)` renamed to `pe<n>`
+fn renumber_ids(svg: &str) -> String {
+    let referenced: HashSet<&str> = svg
+        .match_indices("url(#")
+        .filter_map(|(index, open)| {
+            let id = &svg[index + open.len()..];
+            id.find(')').map(|end| &id[..end])
+        })
+        .collect();
+    let mut definitions: Vec<(usize, &str)> = Vec::new();
+    for quote in ['"', '\''] {
+        let open = format!("id={}", quote);
+        for (index, _) in svg.match_indices(&open) {
+            // `id=` only, not `data-id=` or `xml:id=`
+            if !svg[..index].ends_with(char::is_whitespace) {
+                continue;
+            }
+            let value = &svg[index + open.len()..];
+            if let Some(id) = value.find(quote).map(|end| &value[..end]) {
+                if referenced.contains(id) {
+                    definitions.push((index, id));
+                }
+            }
+        }
+    }
+    definitions.sort_unstable();
+    let mut ids: Vec<&str> = Vec::new();
+    for (_, id) in definitions {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    // Placeholders first: a new name may equal an ID renamed later. NUL never
+    // appears in XML, so a placeholder cannot match another ID's patterns.
+    let mut out = svg.to_string();
+    for (number, id) in ids.iter().enumerate() {
+        let placeholder = format!("\0{}\0", number);
+        for (open, close) in [("id=\"", "\""), ("id='", "'"), ("url(#", ")"), ("\"#", "\""), ("'#", "'")] {
+            out = out.replace(
+                &format!("{}{}{}", open, id, close),
+                &format!("{}{}{}", open, placeholder, close),
+            );
+        }
+    }
+    for number in 0..ids.len() {
+        out = out.replace(&format!("\0{}\0", number), &format!("{}{}", ID_PREFIX, number));
+    }
+    out
+}
+
+/// PNG without text and time chunks, or None when `data` is not a PNG
+fn strip_png_metadata(data: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks = data.strip_prefix(PNG_SIGNATURE)?;
+    let mut out = PNG_SIGNATURE.to_vec();
+    while !chunks.is_empty() {
+        let length = u32::from_be_bytes(chunks.get(..4)?.try_into().ok()?) as usize;
+        // Length, type, data and CRC
+        let chunk = chunks.get(..length.checked_add(12)?)?;
+        if !PNG_METADATA_CHUNKS.iter().any(|kind| &chunk[4..8] == *kind) {
+            out.extend_from_slice(chunk);
+        }
+        chunks = &chunks[chunk.len()..];
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // The CRC is copied as is, so any value does for the test
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        chunk
+    }
+
+    #[test]
+    fn test_normalize_svg() {
+        let render = |seed: &str| {
+            format!(
+                "<?xml version=\"1.0\" encoding=\"us-ascii\" standalone=\"no\"?><svg xmlns=\"http://www.w3.org/2000/svg\"><?plantuml 1.2024.{seed}?><defs><filter id=\"f{seed}x\"/><linearGradient id='g{seed}y'/></defs><!--SRC=[{seed}]--><g id=\"elem_Alice\"><rect filter=\"url(#f{seed}x)\" fill=\"url(#g{seed}y)\"/><use xlink:href=\"#f{seed}x\"/></g></svg>"
+            )
+        };
+        let normalized = normalize_svg(&render("3"));
+        assert_eq!(
+            normalized,
+            "<?xml version=\"1.0\" encoding=\"us-ascii\" standalone=\"no\"?><svg xmlns=\"http://www.w3.org/2000/svg\"><defs><filter id=\"pe0\"/><linearGradient id='pe1'/></defs><g id=\"elem_Alice\"><rect filter=\"url(#pe0)\" fill=\"url(#pe1)\"/><use xlink:href=\"#pe0\"/></g></svg>"
+        );
+        assert_eq!(normalize_svg(&render("7")), normalized);
+        assert_eq!(normalize_svg(&normalized), normalized);
+    }
+
+    #[test]
+    fn test_strip_png_metadata() {
+        let image = [png_chunk(b"IHDR", &[1; 13]), png_chunk(b"IDAT", &[2; 8]), png_chunk(b"IEND", &[])];
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend(png_chunk(b"IHDR", &[1; 13]));
+        png.extend(png_chunk(b"tEXt", b"plantuml\0@startuml"));
+        png.extend(png_chunk(b"tIME", &[7; 7]));
+        png.extend(png_chunk(b"IDAT", &[2; 8]));
+        png.extend(png_chunk(b"iTXt", b"source"));
+        png.extend(png_chunk(b"IEND", &[]));
+
+        let mut expected = PNG_SIGNATURE.to_vec();
+        expected.extend(image.concat());
+        assert_eq!(normalize(ImageFormat::Png, &png), expected);
+
+        // Truncated data is left alone
+        assert_eq!(normalize(ImageFormat::Png, &png[..20]), png[..20].to_vec());
+        assert_eq!(normalize(ImageFormat::Png, b"not a png"), b"not a png".to_vec());
+    }
+}
@@ -0,0 +1,525 @@
+// Diagnostics for external editors
+//
+// Static checks that need no PlantUML server: the validation rules, matching
+// @start/@end lines, and (inside @startuml blocks) braces and sequence
+// diagram groups left open. Like the stats scan this is line-based, not a
+// parser, so brace and group problems are only warnings.
+//
+// Text that PlantUML is known to lay out badly is flagged too: labels mixing
+// right-to-left scripts with other text (PlantUML does not reorder the runs),
+// invisible bidi controls, and characters it has no glyphs or layout for
+// (control characters, private use, vertical presentation forms).
+
+use crate::models::{
+    Diagnostic, DiagnosticPosition, DiagnosticRange, DiagnosticSeverity, ErrorCode,
+};
+use crate::validation::{validate_plantuml_content, ValidationError};
+
+/// `source` of every diagnostic
+pub const DIAGNOSTIC_SOURCE: &str = "plantuml-editor";
+
+/// Sequence diagram groups closed by `end`
+const GROUP_KEYWORDS: &[&str] = &["alt", "opt", "loop", "par", "critical", "group"];
+
+/// Diagnostic covering the text of a line (without surrounding whitespace)
+pub fn line_diagnostic(
+    content: &str,
+    line: usize,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    let text = content.lines().nth(line).unwrap_or_default();
+    let indent = text.len() - text.trim_start().len();
+    let start = utf16_len(&text[..indent]);
+    let end = utf16_len(text.trim_end());
+    at(line, start, end.max(start), severity, code, message)
+}
+
+/// Diagnostic for a syntax error reported by the PlantUML server at a
+/// one-based line (the first line when the server gives none)
+pub fn syntax_error(content: &str, line: Option<usize>, message: &str) -> Diagnostic {
+    let last = content.lines().count().saturating_sub(1);
+    let line = line.map_or(0, |line| line.saturating_sub(1).min(last));
+    line_diagnostic(
+        content,
+        line,
+        DiagnosticSeverity::Error,
+        "syntax-error",
+        format!("構文エラー: {}", message),
+    )
+}
+
+/// Problems that can be found without rendering, in source order
+pub fn lint(content: &str) -> Vec<Diagnostic> {
+    if let Err(e) = validate_plantuml_content(content) {
+        let severity = match e {
+            ValidationError::EmptyContent => DiagnosticSeverity::Warning,
+            ValidationError::ContentTooLarge(..) => DiagnosticSeverity::Error,
+        };
+        let code = match e {
+            ValidationError::EmptyContent => "empty-content",
+            ValidationError::ContentTooLarge(..) => "content-too-large",
+        };
+        return vec![line_diagnostic(content, 0, severity, code, e.to_error_code().to_message())];
+    }
+
+    let mut diagnostics = Vec::new();
+    // Open @start line and its diagram type
+    let mut block: Option<(usize, String)> = None;
+    let mut seen_start = false;
+    let mut scan = BlockScan::default();
+
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        check_text(index, raw, &mut diagnostics);
+        if let Some(kind) = marker(line, "@start") {
+            if let Some((start, _)) = block.take() {
+                diagnostics.push(line_diagnostic(
+                    content,
+                    start,
+                    DiagnosticSeverity::Error,
+                    "missing-end",
+                    "@end がないまま次の @start が始まっています".to_string(),
+                ));
+            }
+            scan.finish(content, &mut diagnostics);
+            block = Some((index, kind));
+            seen_start = true;
+        } else if let Some(kind) = marker(line, "@end") {
+            match block.take() {
+                None => diagnostics.push(line_diagnostic(
+                    content,
+                    index,
+                    DiagnosticSeverity::Error,
+                    "unexpected-end",
+                    format!("@end{} に対応する @start{} がありません", kind, kind),
+                )),
+                Some((_, start_kind)) if start_kind != kind => diagnostics.push(line_diagnostic(
+                    content,
+                    index,
+                    DiagnosticSeverity::Error,
+                    "mismatched-end",
+                    format!("@start{} を @end{} で閉じています", start_kind, kind),
+                )),
+                Some(_) => {}
+            }
+            scan.finish(content, &mut diagnostics);
+        } else if matches!(&block, Some((_, kind)) if kind == "uml") {
+            scan.line(index, raw, &mut diagnostics);
+        }
+    }
+
+    if let Some((start, kind)) = block {
+        scan.finish(content, &mut diagnostics);
+        diagnostics.push(line_diagnostic(
+            content,
+            start,
+            DiagnosticSeverity::Error,
+            "missing-end",
+            format!("@start{} に対応する @end{} がありません", kind, kind),
+        ));
+    }
+    if !seen_start {
+        diagnostics.push(line_diagnostic(
+            content,
+            0,
+            DiagnosticSeverity::Warning,
+            "missing-start",
+            "@startuml がありません。図として認識されない場合があります".to_string(),
+        ));
+    }
+
+    diagnostics.sort_by_key(|diagnostic| {
+        let start = diagnostic.range.start;
+        (start.line, start.character)
+    });
+    diagnostics
+}
+
+/// Braces and groups inside one @startuml block
+#[derive(Default)]
+struct BlockScan {
+    /// Open braces: (line, UTF-16 offset)
+    braces: Vec<(usize, usize)>,
+    /// Open groups: (line, keyword)
+    groups: Vec<(usize, &'static str)>,
+    in_comment: bool,
+    in_note: bool,
+}
+
+impl BlockScan {
+    fn line(&mut self, index: usize, raw: &str, diagnostics: &mut Vec<Diagnostic>) {
+        let line = raw.trim();
+
+        if self.in_comment {
+            self.in_comment = !line.ends_with("'/");
+            return;
+        }
+        if line.starts_with("/'") {
+            self.in_comment = !line.ends_with("'/") || line.len() < 4;
+            return;
+        }
+        if self.in_note {
+            let words: String = line.split_whitespace().collect::<String>().to_lowercase();
+            self.in_note = !(words.starts_with("end") && words.ends_with("note"));
+            return;
+        }
+        if line.is_empty() || line.starts_with('\'') {
+            return;
+        }
+
+        let lower = line.to_lowercase();
+        let first = lower.split_whitespace().next().unwrap_or_default();
+        if matches!(first, "note" | "rnote" | "hnote") {
+            // Without ':' the note text continues until "end note"
+            self.in_note = !line.contains(':');
+            return;
+        }
+        if let Some(keyword) = GROUP_KEYWORDS.iter().find(|keyword| **keyword == first) {
+            self.groups.push((index, keyword));
+        } else if lower == "end" || lower == "end group" {
+            // A bare `end` outside groups ends an activity diagram
+            self.groups.pop();
+        }
+
+        let indent = utf16_len(&raw[..raw.len() - raw.trim_start().len()]);
+        let mut offset = indent;
+        let mut quoted = false;
+        for c in line.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                '{' if !quoted => self.braces.push((index, offset)),
+                '}' if !quoted && self.braces.pop().is_none() => {
+                    diagnostics.push(at(
+                        index,
+                        offset,
+                        offset + 1,
+                        DiagnosticSeverity::Warning,
+                        "unexpected-brace",
+                        "対応する { がない } です".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+            offset += c.len_utf16();
+        }
+    }
+
+    /// Report what is still open at the end of the block and reset
+    fn finish(&mut self, content: &str, diagnostics: &mut Vec<Diagnostic>) {
+        for (line, offset) in self.braces.drain(..) {
+            diagnostics.push(at(
+                line,
+                offset,
+                offset + 1,
+                DiagnosticSeverity::Warning,
+                "unclosed-brace",
+                "{ が } で閉じられていない可能性があります".to_string(),
+            ));
+        }
+        for (line, keyword) in self.groups.drain(..) {
+            diagnostics.push(line_diagnostic(
+                content,
+                line,
+                DiagnosticSeverity::Warning,
+                "unclosed-group",
+                format!("{} が end で閉じられていない可能性があります", keyword),
+            ));
+        }
+        self.in_comment = false;
+        self.in_note = false;
+    }
+}
+
+/// Characters and mixed scripts on a line that break the rendered layout,
+/// reported once per kind of problem
+fn check_text(index: usize, raw: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if raw.trim_start().starts_with('\'') {
+        return;
+    }
+    let mut seen_bidi = false;
+    let mut seen_unsupported = false;
+    let mut offset = 0;
+    for c in raw.chars() {
+        let width = c.len_utf16();
+        if !seen_bidi && is_bidi_control(c) {
+            seen_bidi = true;
+            diagnostics.push(at(
+                index,
+                offset,
+                offset + width,
+                DiagnosticSeverity::Warning,
+                "bidi-control",
+                format!(
+                    "文字の向きを制御する見えない文字 (U+{:04X}) があります。PlantUMLでは無視されるか、表示が崩れる場合があります",
+                    c as u32
+                ),
+            ));
+        } else if let Some(kind) = unsupported_kind(c).filter(|_| !seen_unsupported) {
+            seen_unsupported = true;
+            diagnostics.push(at(
+                index,
+                offset,
+                offset + width,
+                DiagnosticSeverity::Warning,
+                "unsupported-char",
+                format!("{} (U+{:04X}) はPlantUMLで正しく描画されない場合があります", kind, c as u32),
+            ));
+        }
+        offset += width;
+    }
+    if labels(raw).into_iter().any(mixes_rtl) {
+        let text = raw.trim_end();
+        let indent = utf16_len(&text[..text.len() - text.trim_start().len()]);
+        diagnostics.push(at(
+            index,
+            indent,
+            utf16_len(text),
+            DiagnosticSeverity::Warning,
+            "mixed-rtl",
+            "右から左に書く文字 (アラビア語・ヘブライ語など) とほかの文字が同じラベルに混在しています。PlantUMLでは語順が崩れて表示される場合があります".to_string(),
+        ));
+    }
+}
+
+/// Quoted strings of a line and the label after its first `:`
+fn labels(line: &str) -> Vec<&str> {
+    let mut labels = Vec::new();
+    let mut quote_start = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote_start) {
+            ('"', None) => quote_start = Some(i + 1),
+            ('"', Some(start)) => {
+                labels.push(&line[start..i]);
+                quote_start = None;
+            }
+            (':', None) => {
+                labels.push(&line[i + 1..]);
+                break;
+            }
+            _ => {}
+        }
+    }
+    labels
+}
+
+/// Right-to-left letters next to left-to-right letters
+fn mixes_rtl(text: &str) -> bool {
+    text.chars().any(is_rtl) && text.chars().any(|c| c.is_alphabetic() && !is_rtl(c))
+}
+
+/// Hebrew, Arabic, Syriac, Thaana and the other right-to-left scripts
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFC}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Kind of a character PlantUML has no glyph or layout for
+fn unsupported_kind(c: char) -> Option<&'static str> {
+    match c {
+        '\t' => None,
+        c if c.is_control() => Some("制御文字"),
+        '\u{200B}' | '\u{2060}' | '\u{FEFF}' => Some("幅のない文字"),
+        '\u{E000}'..='\u{F8FF}' | '\u{F0000}'..='\u{10FFFF}' => Some("私用領域の文字"),
+        '\u{FE10}'..='\u{FE1F}' | '\u{FE30}'..='\u{FE4F}' => Some("縦書き用の文字"),
+        _ => None,
+    }
+}
+
+/// Diagram type of a `@start...`/`@end...` line (e.g. "uml")
+fn marker(line: &str, prefix: &str) -> Option<String> {
+    let head = line.get(..prefix.len())?;
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    let kind: String = line[prefix.len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    (!kind.is_empty()).then(|| kind.to_ascii_lowercase())
+}
+
+fn at(
+    line: usize,
+    start: usize,
+    end: usize,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range: DiagnosticRange {
+            start: DiagnosticPosition {
+                line,
+                character: start,
+            },
+            end: DiagnosticPosition {
+                line,
+                character: end,
+            },
+        },
+        severity,
+        code: code.to_string(),
+        source: DIAGNOSTIC_SOURCE.to_string(),
+        message,
+    }
+}
+
+fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Byte offsets in `content` of a range, clamped to the text
+pub fn byte_range(content: &str, range: &DiagnosticRange) -> (usize, usize) {
+    let start = byte_offset(content, range.start);
+    (start, byte_offset(content, range.end).max(start))
+}
+
+fn byte_offset(content: &str, position: DiagnosticPosition) -> usize {
+    let Some(line_start) = content
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some(start)
+        })
+        .nth(position.line)
+    else {
+        return content.len();
+    };
+    let line = content[line_start..].lines().next().unwrap_or_default();
+    let mut units = 0;
+    for (index, c) in line.char_indices() {
+        if units >= position.character {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// Result code summarizing the diagnostics
+pub fn summary(diagnostics: &[Diagnostic]) -> ErrorCode {
+    let count = |severity| {
+        diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    };
+    ErrorCode::DiagnosticsReported {
+        errors: count(DiagnosticSeverity::Error),
+        warnings: count(DiagnosticSeverity::Warning),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(content: &str) -> Vec<(usize, String)> {
+        lint(content)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_source_has_no_diagnostics() {
+        let source = "@startuml\nclass A {\n  +name : String\n}\nalt ok\n  A -> B\nelse\n  B -> A\nend\nnote left\n  alt {\nend note\n@enduml";
+        assert!(lint(source).is_empty());
+    }
+
+    #[test]
+    fn test_start_and_end_markers() {
+        assert_eq!(codes("@startuml\nA -> B"), vec![(0, "missing-end".to_string())]);
+        assert_eq!(codes("A -> B\n@enduml"), vec![
+            (0, "missing-start".to_string()),
+            (1, "unexpected-end".to_string()),
+        ]);
+        assert_eq!(codes("@startjson\n{}\n@enduml"), vec![(2, "mismatched-end".to_string())]);
+        assert_eq!(codes("   "), vec![(0, "empty-content".to_string())]);
+    }
+
+    #[test]
+    fn test_braces_and_groups() {
+        let source = "@startuml\nclass A {\n  x\nloop each\n  A -> B : \"{\"\n}\n}\n@enduml";
+        let diagnostics = lint(source);
+        let found: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.code.as_str()))
+            .collect();
+        assert_eq!(found, vec![(3, "unclosed-group"), (6, "unexpected-brace")]);
+        assert!(diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn test_text_layout_checks() {
+        let source = "@startuml\nA -> B : שלום world\nparticipant \"مرحبا\" as C\nB -> A : \u{202E}abc\n' note \u{202E}\nA -> C : 😀︱\u{E001}\u{E002}\n@enduml";
+        assert_eq!(
+            codes(source),
+            vec![
+                (1, "mixed-rtl".to_string()),
+                (3, "bidi-control".to_string()),
+                (5, "unsupported-char".to_string()),
+            ]
+        );
+        let diagnostics = lint(source);
+        // Only the first unsupported character, after the surrogate pair
+        assert_eq!(diagnostics[2].range.start.character, 11);
+        assert_eq!(diagnostics[2].range.end.character, 12);
+        assert!(diagnostics[2].message.contains("U+FE31"));
+    }
+
+    #[test]
+    fn test_ranges_use_utf16_offsets() {
+        let diagnostic = line_diagnostic("  😀 ok ", 0, DiagnosticSeverity::Warning, "x", String::new());
+        assert_eq!(diagnostic.range.start.character, 2);
+        assert_eq!(diagnostic.range.end.character, 7);
+
+        let error = syntax_error("@startuml\nA -> \n@enduml", Some(2), "Syntax Error?");
+        assert_eq!(error.range.start.line, 1);
+        assert_eq!(error.severity, DiagnosticSeverity::Error);
+        assert_eq!(syntax_error("A", Some(9), "x").range.start.line, 0);
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let content = "@startuml\nA -> B : 😀 hi\n@enduml";
+        let diagnostic = at(1, 9, 14, DiagnosticSeverity::Warning, "x", String::new());
+        let (start, end) = byte_range(content, &diagnostic.range);
+        assert_eq!(&content[start..end], "😀 hi");
+        // Positions past a line or the text are clamped
+        let diagnostic = at(5, 0, 3, DiagnosticSeverity::Warning, "x", String::new());
+        assert_eq!(byte_range(content, &diagnostic.range), (content.len(), content.len()));
+        let diagnostic = at(0, 4, 40, DiagnosticSeverity::Warning, "x", String::new());
+        assert_eq!(byte_range(content, &diagnostic.range), (4, 9));
+    }
+
+    #[test]
+    fn test_summary() {
+        let diagnostics = lint("@startuml\nloop\nA -> B");
+        assert_eq!(
+            summary(&diagnostics),
+            ErrorCode::DiagnosticsReported {
+                errors: 1,
+                warnings: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,341 @@
+// Line diffs and three-way merge of document versions
+//
+// Used when a server document was changed by someone else while it was being
+// edited: the diff shows what differs between the two versions, and the merge
+// combines both sets of changes against the version the edit started from.
+// Regions changed differently on both sides are kept with conflict markers.
+//
+// `LinePatch` describes an edit compactly enough to send instead of the whole
+// text, for a receiver that still has the previous version: the editor saves
+// server documents with PATCH /api/v1/documents/{id}.
+
+use serde::{Deserialize, Serialize};
+
+/// One line of a diff from an old text to a new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Result of a three-way merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    pub text: String,
+    /// Regions left with conflict markers
+    pub conflicts: usize,
+}
+
+/// Marker lines around a conflicting region
+pub const CONFLICT_MINE: &str = "<<<<<<< 自分の変更";
+pub const CONFLICT_SEPARATOR: &str = "=======";
+pub const CONFLICT_THEIRS: &str = ">>>>>>> サーバーの変更";
+
+/// Line-by-line diff (longest common subsequence)
+pub fn line_diff<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Common prefix and suffix need no table
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j]: length of the common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff: Vec<DiffLine> = old[..prefix].iter().map(|line| DiffLine::Same(line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            diff.push(DiffLine::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(DiffLine::Removed(a[i]));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(b[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old[old.len() - suffix..].iter().map(|line| DiffLine::Same(line)));
+    diff
+}
+
+/// Replacement of base lines [start, end) by new lines
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+fn hunks<'a>(base: &'a str, changed: &'a str) -> Vec<Hunk<'a>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut position = 0;
+    let mut open = false;
+    for line in line_diff(base, changed) {
+        match line {
+            DiffLine::Same(_) => {
+                position += 1;
+                open = false;
+            }
+            DiffLine::Removed(_) | DiffLine::Added(_) => {
+                if !open {
+                    hunks.push(Hunk {
+                        start: position,
+                        end: position,
+                        lines: Vec::new(),
+                    });
+                    open = true;
+                }
+                let hunk = hunks.last_mut().unwrap();
+                if let DiffLine::Added(text) = line {
+                    hunk.lines.push(text);
+                } else {
+                    hunk.end += 1;
+                    position += 1;
+                }
+            }
+        }
+    }
+    hunks
+}
+
+/// Base lines [start, end) with one side's hunks in that range applied
+fn apply<'a>(base: &[&'a str], start: usize, end: usize, hunks: &[&Hunk<'a>]) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut position = start;
+    for hunk in hunks {
+        lines.extend_from_slice(&base[position..hunk.start]);
+        lines.extend_from_slice(&hunk.lines);
+        position = hunk.end;
+    }
+    lines.extend_from_slice(&base[position..end]);
+    lines
+}
+
+/// Combine my changes and theirs, both made to `base`
+///
+/// Changes to different lines are both kept. Where both sides changed the
+/// same lines differently, my version and theirs are kept between conflict
+/// markers for the user to resolve.
+pub fn merge(base: &str, mine: &str, theirs: &str) -> Merge {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let my_hunks = hunks(base, mine);
+    let their_hunks = hunks(base, theirs);
+
+    let mut lines: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let mut position = 0;
+    let (mut m, mut t) = (0, 0);
+    while m < my_hunks.len() || t < their_hunks.len() {
+        // Start a region at the earliest remaining hunk
+        let first_mine = match (my_hunks.get(m), their_hunks.get(t)) {
+            (Some(a), Some(b)) => a.start <= b.start,
+            (a, _) => a.is_some(),
+        };
+        let first = if first_mine { &my_hunks[m] } else { &their_hunks[t] };
+        let (start, mut end) = (first.start, first.end);
+        let (mut mine_in, mut theirs_in): (Vec<&Hunk>, Vec<&Hunk>) = (Vec::new(), Vec::new());
+
+        // Grow it while a hunk of either side overlaps
+        loop {
+            let overlaps = |hunk: &Hunk| hunk.start < end || hunk.start == start;
+            if let Some(hunk) = my_hunks.get(m).filter(|hunk| overlaps(hunk)) {
+                end = end.max(hunk.end);
+                mine_in.push(hunk);
+                m += 1;
+            } else if let Some(hunk) = their_hunks.get(t).filter(|hunk| overlaps(hunk)) {
+                end = end.max(hunk.end);
+                theirs_in.push(hunk);
+                t += 1;
+            } else {
+                break;
+            }
+        }
+
+        lines.extend_from_slice(&base_lines[position..start]);
+        let my_region = apply(&base_lines, start, end, &mine_in);
+        let their_region = apply(&base_lines, start, end, &theirs_in);
+        if theirs_in.is_empty() || my_region == their_region {
+            lines.extend(my_region);
+        } else if mine_in.is_empty() {
+            lines.extend(their_region);
+        } else {
+            conflicts += 1;
+            lines.push(CONFLICT_MINE);
+            lines.extend(my_region);
+            lines.push(CONFLICT_SEPARATOR);
+            lines.extend(their_region);
+            lines.push(CONFLICT_THEIRS);
+        }
+        position = end;
+    }
+    lines.extend_from_slice(&base_lines[position..]);
+
+    let mut text = lines.join("\n");
+    if mine.ends_with('\n') {
+        text.push('\n');
+    }
+    Merge { text, conflicts }
+}
+
+/// Replacement of `removed` lines at line `start` of the old text by `lines`
+///
+/// Lines are split on `\n` only, so applying the patch restores the new text
+/// exactly, including `\r` and a trailing newline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinePatch {
+    pub start: usize,
+    pub removed: usize,
+    pub lines: Vec<String>,
+}
+
+/// Patch turning `old` into `new`: the lines between their common first and
+/// last lines
+///
+/// An edit while typing touches one region, so a single replacement is as
+/// small as a full diff and takes linear time.
+pub fn line_patch(old: &str, new: &str) -> LinePatch {
+    let old: Vec<&str> = old.split('\n').collect();
+    let new: Vec<&str> = new.split('\n').collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    LinePatch {
+        start: prefix,
+        removed: old.len() - prefix - suffix,
+        lines: new[prefix..new.len() - suffix]
+            .iter()
+            .map(|line| line.to_string())
+            .collect(),
+    }
+}
+
+/// `old` with `patch` applied, or None when the patch does not fit it
+pub fn apply_patch(old: &str, patch: &LinePatch) -> Option<String> {
+    let old: Vec<&str> = old.split('\n').collect();
+    let end = patch.start.checked_add(patch.removed)?;
+    if end > old.len() {
+        return None;
+    }
+    let mut lines: Vec<&str> = old[..patch.start].to_vec();
+    lines.extend(patch.lines.iter().map(String::as_str));
+    lines.extend_from_slice(&old[end..]);
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff("@startuml\nA -> B\n@enduml", "@startuml\nA -> C\nC -> B\n@enduml");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("@startuml"),
+                DiffLine::Removed("A -> B"),
+                DiffLine::Added("A -> C"),
+                DiffLine::Added("C -> B"),
+                DiffLine::Same("@enduml"),
+            ]
+        );
+        assert!(line_diff("a\nb", "a\nb")
+            .iter()
+            .all(|line| matches!(line, DiffLine::Same(_))));
+    }
+
+    #[test]
+    fn test_merge_keeps_changes_to_different_lines() {
+        let base = "@startuml\nA -> B\nB -> C\nC -> D\n@enduml\n";
+        let mine = "@startuml\nA -> B : hello\nB -> C\nC -> D\n@enduml\n";
+        let theirs = "@startuml\nA -> B\nB -> C\nC -> D : bye\nD -> E\n@enduml\n";
+
+        let merged = merge(base, mine, theirs);
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(
+            merged.text,
+            "@startuml\nA -> B : hello\nB -> C\nC -> D : bye\nD -> E\n@enduml\n"
+        );
+    }
+
+    #[test]
+    fn test_merge_marks_conflicting_lines() {
+        let base = "@startuml\nA -> B\n@enduml";
+        let merged = merge(base, "@startuml\nA -> X\n@enduml", "@startuml\nA -> Y\n@enduml");
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            format!(
+                "@startuml\n{}\nA -> X\n{}\nA -> Y\n{}\n@enduml",
+                CONFLICT_MINE, CONFLICT_SEPARATOR, CONFLICT_THEIRS
+            )
+        );
+
+        // The same edit on both sides is not a conflict
+        let same = merge(base, "@startuml\nA -> X\n@enduml", "@startuml\nA -> X\n@enduml");
+        assert_eq!(same.conflicts, 0);
+        assert_eq!(same.text, "@startuml\nA -> X\n@enduml");
+    }
+
+    #[test]
+    fn test_merge_insertions_at_same_place_conflict() {
+        let merged = merge("a\nb", "a\nmine\nb", "a\ntheirs\nb");
+        assert_eq!(merged.conflicts, 1);
+        assert!(merged.text.contains("mine\n=======\ntheirs"));
+    }
+
+    #[test]
+    fn test_line_patch_roundtrip() {
+        let old = "@startuml\r\nA -> B : hello\r\nB -> C\n@enduml\n";
+        let new = "@startuml\r\nA -> B : hello world\r\nnote left : hi\nB -> C\n@enduml\n";
+        let patch = line_patch(old, new);
+        assert_eq!(
+            patch,
+            LinePatch {
+                start: 1,
+                removed: 1,
+                lines: vec!["A -> B : hello world\r".to_string(), "note left : hi".to_string()],
+            }
+        );
+        assert_eq!(apply_patch(old, &patch).as_deref(), Some(new));
+
+        // Unchanged, emptied and appended texts
+        for (old, new) in [(old, old), (old, ""), ("", "A -> B"), ("A", "A\n")] {
+            assert_eq!(apply_patch(old, &line_patch(old, new)).as_deref(), Some(new));
+        }
+    }
+
+    #[test]
+    fn test_apply_patch_out_of_range() {
+        let patch = LinePatch {
+            start: 2,
+            removed: 2,
+            lines: Vec::new(),
+        };
+        assert_eq!(apply_patch("a\nb\nc", &patch), None);
+    }
+}
@@ -0,0 +1,195 @@
+// PlantUML source embedded in exported images
+//
+// An exported image can carry the source it was rendered from, so a diagram
+// shared as a PNG or SVG can be opened again for editing. PNG files get an
+// iTXt chunk (the UTF-8 form of tEXt; tEXt itself is Latin-1 and cannot hold
+// Japanese labels), SVG files a `<desc>` element right after the root start
+// tag. `extract_source` reads both back, and also a Latin-1 tEXt chunk under
+// the same keyword.
+
+use crate::models::ImageFormat;
+use crate::png::{png_chunks, write_png_chunk, PNG_SIGNATURE};
+use crate::svg::root_tag;
+
+/// Keyword of the PNG text chunk holding the source
+pub const PNG_KEYWORD: &str = "plantuml-source";
+
+/// `id` of the SVG `<desc>` element holding the source
+pub const SVG_DESC_ID: &str = "plantuml-source";
+
+/// Image with `source` embedded
+///
+/// Text and PDF output and data that is not a well-formed PNG or UTF-8 SVG are
+/// returned unchanged.
+pub fn embed_source(format: ImageFormat, data: &[u8], source: &str) -> Vec<u8> {
+    let embedded = match format {
+        ImageFormat::Png => embed_png(data, source),
+        ImageFormat::Svg => std::str::from_utf8(data)
+            .ok()
+            .and_then(|svg| embed_svg(svg, source))
+            .map(String::into_bytes),
+        ImageFormat::Txt | ImageFormat::Pdf => None,
+    };
+    embedded.unwrap_or_else(|| data.to_vec())
+}
+
+/// Source embedded in a PNG or SVG image, None when there is none
+pub fn extract_source(data: &[u8]) -> Option<String> {
+    if data.starts_with(PNG_SIGNATURE) {
+        return extract_png(data);
+    }
+    extract_svg(std::str::from_utf8(data).ok()?)
+}
+
+/// PNG with an iTXt chunk holding `source` inserted before IEND
+fn embed_png(data: &[u8], source: &str) -> Option<Vec<u8>> {
+    let chunks = png_chunks(data)?;
+    let end = chunks.iter().position(|(kind, _)| *kind == b"IEND")?;
+    // Keyword, no compression, no language tag or translated keyword
+    let mut text = PNG_KEYWORD.as_bytes().to_vec();
+    text.extend_from_slice(&[0, 0, 0, 0, 0]);
+    text.extend_from_slice(source.as_bytes());
+
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (index, (kind, chunk_data)) in chunks.iter().enumerate() {
+        if index == end {
+            write_png_chunk(&mut out, b"iTXt", &text);
+        }
+        write_png_chunk(&mut out, kind, chunk_data);
+    }
+    Some(out)
+}
+
+fn extract_png(data: &[u8]) -> Option<String> {
+    png_chunks(data)?.into_iter().find_map(|(kind, chunk_data)| {
+        let (keyword, text) = split_at_nul(chunk_data)?;
+        if keyword != PNG_KEYWORD.as_bytes() {
+            return None;
+        }
+        match kind {
+            // Latin-1: every byte is the code point of the same value
+            b"tEXt" => Some(text.iter().map(|byte| char::from(*byte)).collect()),
+            // Compressed text is not written by `embed_source`
+            b"iTXt" if text.first() == Some(&0) => {
+                let (_language, rest) = split_at_nul(text.get(2..)?)?;
+                let (_translated, text) = split_at_nul(rest)?;
+                String::from_utf8(text.to_vec()).ok()
+            }
+            _ => None,
+        }
+    })
+}
+
+fn split_at_nul(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let index = data.iter().position(|byte| *byte == 0)?;
+    Some((&data[..index], &data[index + 1..]))
+}
+
+/// SVG with a `<desc>` holding `source` as the first child of the root
+fn embed_svg(svg: &str, source: &str) -> Option<String> {
+    let (_, end) = root_tag(svg)?;
+    if svg[..end].ends_with("/>") {
+        return None;
+    }
+    let escaped = source
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    Some(format!(
+        "{}<desc id=\"{}\">{}</desc>{}",
+        &svg[..end],
+        SVG_DESC_ID,
+        escaped,
+        &svg[end..]
+    ))
+}
+
+fn extract_svg(svg: &str) -> Option<String> {
+    let open = [
+        format!("<desc id=\"{}\">", SVG_DESC_ID),
+        format!("<desc id='{}'>", SVG_DESC_ID),
+    ]
+    .into_iter()
+    .find_map(|open| svg.find(&open).map(|index| index + open.len()))?;
+    let text = &svg[open..];
+    let close = text.find("</desc>")?;
+    unescape(&text[..close])
+}
+
+/// Text of an XML element with its entity and character references resolved
+fn unescape(text: &str) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let reference = &rest[start + 1..];
+        let end = reference.find(';')?;
+        let c = match &reference[..end] {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            number => {
+                let number = number.strip_prefix('#')?;
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code)?
+            }
+        };
+        out.push(c);
+        rest = &reference[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "@startuml\nAlice -> Bob : <<依頼>> & 返信\n@enduml\n";
+
+    fn png() -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut png, b"IHDR", &[1; 13]);
+        write_png_chunk(&mut png, b"IDAT", &[2; 8]);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let embedded = embed_source(ImageFormat::Png, &png(), SOURCE);
+        let kinds: Vec<&[u8]> = png_chunks(&embedded).unwrap().into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"iTXt", b"IEND"]);
+        assert_eq!(extract_source(&embedded).as_deref(), Some(SOURCE));
+        assert_eq!(extract_source(&png()), None);
+
+        // Latin-1 tEXt under the same keyword
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut png, b"tEXt", b"plantuml-source\0caf\xe9");
+        assert_eq!(extract_source(&png).as_deref(), Some("café"));
+
+        // Not a PNG: unchanged
+        assert_eq!(embed_source(ImageFormat::Png, b"not a png", SOURCE), b"not a png");
+    }
+
+    #[test]
+    fn test_svg_round_trip() {
+        let svg = "<?xml version=\"1.0\"?><svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\"><g/></svg>";
+        let embedded = String::from_utf8(embed_source(ImageFormat::Svg, svg.as_bytes(), SOURCE)).unwrap();
+        assert_eq!(
+            embedded,
+            "<?xml version=\"1.0\"?><svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\"><desc id=\"plantuml-source\">@startuml\nAlice -&gt; Bob : &lt;&lt;依頼&gt;&gt; &amp; 返信\n@enduml\n</desc><g/></svg>"
+        );
+        assert_eq!(extract_source(embedded.as_bytes()).as_deref(), Some(SOURCE));
+        assert_eq!(extract_source(svg.as_bytes()), None);
+
+        // Character references written by other tools
+        let svg = "<svg><desc id='plantuml-source'>A &#x2d;&#62; B</desc></svg>";
+        assert_eq!(extract_source(svg.as_bytes()).as_deref(), Some("A -> B"));
+    }
+}
@@ -0,0 +1,114 @@
+// Bounded decoding of PlantUML's URL encoding
+//
+// Diagram URLs and share links carry the source deflate-compressed and
+// written with PlantUML's base64 alphabet. Deflate can expand a small input a
+// thousandfold, so the text is inflated with a cap just above the size of the
+// largest valid document instead of all at once, and a link cannot exhaust
+// the memory of the server or the browser tab decoding it.
+
+use std::io::Read;
+
+use crate::validation::MAX_CONTENT_CHARS;
+
+/// Largest decoded source in bytes (every character 4 bytes in UTF-8)
+pub const MAX_SOURCE_BYTES: usize = MAX_CONTENT_CHARS * 4;
+
+/// Longest encoded source accepted; compressed text is never much larger
+/// than the text itself, and base64 adds a third
+pub const MAX_ENCODED_CHARS: usize = MAX_SOURCE_BYTES * 2;
+
+/// PlantUML's base64 alphabet
+const ALPHABET: &[u8; 64] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+
+/// Why an encoded source could not be decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("エンコードされたソースが正しくありません")]
+    Invalid,
+    #[error("デコードしたソースが大きすぎます")]
+    TooLarge,
+}
+
+/// Source encoded with `plantuml_encoding::encode_plantuml_deflate`, refused
+/// once it grows past MAX_SOURCE_BYTES
+pub fn decode_deflate(encoded: &str) -> Result<String, DecodeError> {
+    if encoded.len() > MAX_ENCODED_CHARS {
+        return Err(DecodeError::TooLarge);
+    }
+    let compressed = decode_base64(encoded).ok_or(DecodeError::Invalid)?;
+    let mut inflated = Vec::new();
+    flate2::read::DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_SOURCE_BYTES as u64 + 1)
+        .read_to_end(&mut inflated)
+        .map_err(|_| DecodeError::Invalid)?;
+    if inflated.len() > MAX_SOURCE_BYTES {
+        return Err(DecodeError::TooLarge);
+    }
+    String::from_utf8(inflated).map_err(|_| DecodeError::Invalid)
+}
+
+/// Bytes of `encoded`: every 4 characters are 3 bytes, a shorter last group
+/// one byte less than its characters
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    let values = encoded
+        .bytes()
+        .map(|c| ALPHABET.iter().position(|&a| a == c).map(|v| v as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut bytes = Vec::with_capacity(values.len() * 3 / 4);
+    for group in values.chunks(4) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, value)| n | (value << (18 - 6 * i)));
+        let group_bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        bytes.extend_from_slice(&group_bytes[..group.len().saturating_sub(1)]);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Reference encoder: raw deflate written with ALPHABET, three bytes at a
+    /// time padded with zeros, as PlantUML does
+    fn encode(data: &[u8]) -> String {
+        let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut out = String::new();
+        for chunk in compressed.chunks(3) {
+            let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+            let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+            for shift in [18, 12, 6, 0] {
+                out.push(ALPHABET[((n >> shift) & 63) as usize] as char);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode_deflate() {
+        let source = "@startuml\nAlice -> Bob : こんにちは\n@enduml";
+        assert_eq!(decode_deflate(&encode(source.as_bytes())).as_deref(), Ok(source));
+        // The encoding PlantUML's own examples use
+        assert_eq!(
+            decode_deflate("SyfFKj2rKt3CoKnELR1Io4ZDoSa70000").as_deref(),
+            Ok("Bob -> Alice : hello")
+        );
+        assert_eq!(decode_deflate("not encoded!"), Err(DecodeError::Invalid));
+        assert_eq!(decode_deflate(&encode(&[0xff, 0xfe])), Err(DecodeError::Invalid));
+    }
+
+    #[test]
+    fn test_deflate_bomb_is_refused() {
+        // A few kilobytes that inflate to 10 MB
+        let bomb = encode(&vec![b'A'; 10 * 1024 * 1024]);
+        assert!(bomb.len() < MAX_ENCODED_CHARS);
+        assert_eq!(decode_deflate(&bomb), Err(DecodeError::TooLarge));
+
+        assert_eq!(decode_deflate(&encode(&vec![b'A'; MAX_SOURCE_BYTES])).map(|s| s.len()), Ok(MAX_SOURCE_BYTES));
+        assert_eq!(decode_deflate(&"0".repeat(MAX_ENCODED_CHARS + 1)), Err(DecodeError::TooLarge));
+    }
+}
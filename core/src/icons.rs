@@ -0,0 +1,239 @@
+// Icons for the icon picker
+//
+// A catalogue of the icons PlantUML can show in any text: OpenIconic
+// (`<&name>`, built in), Devicons sprites from the standard library (`<$name>`
+// after `!include <tupadr3/devicons/name>`) and emoji (`<:name:>`). Not every
+// icon of each set is listed, only the ones most useful in diagrams.
+
+/// Where an icon comes from, which decides its syntax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IconSet {
+    OpenIconic,
+    Devicons,
+    Emoji,
+}
+
+impl IconSet {
+    pub const ALL: [IconSet; 3] = [IconSet::OpenIconic, IconSet::Devicons, IconSet::Emoji];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            IconSet::OpenIconic => "OpenIconic",
+            IconSet::Devicons => "Devicons",
+            IconSet::Emoji => "絵文字",
+        }
+    }
+
+    fn names(self) -> &'static [&'static str] {
+        match self {
+            IconSet::OpenIconic => OPEN_ICONIC,
+            IconSet::Devicons => DEVICONS,
+            IconSet::Emoji => EMOJI,
+        }
+    }
+}
+
+const OPEN_ICONIC: &[&str] = &[
+    "account-login", "account-logout", "action-redo", "action-undo", "arrow-bottom",
+    "arrow-left", "arrow-right", "arrow-top", "badge", "ban", "bell", "bolt", "book",
+    "bookmark", "box", "briefcase", "browser", "bug", "calendar", "cart", "chat", "check",
+    "clipboard", "clock", "cloud", "cloud-download", "cloud-upload", "code", "cog",
+    "command", "comment-square", "compass", "credit-card", "dashboard",
+    "data-transfer-download", "data-transfer-upload", "delete", "document",
+    "envelope-closed", "envelope-open", "eye", "file", "fire", "flag", "folder", "globe",
+    "graph", "heart", "home", "image", "inbox", "info", "key", "laptop", "layers",
+    "lightbulb", "link-intact", "list", "location", "lock-locked", "lock-unlocked",
+    "magnifying-glass", "map", "monitor", "moon", "people", "person", "phone", "pie-chart",
+    "power-standby", "print", "question-mark", "reload", "rss", "shield", "star", "sun",
+    "tag", "target", "terminal", "thumb-up", "timer", "trash", "warning", "wifi", "wrench",
+    "x", "zoom-in",
+];
+
+const DEVICONS: &[&str] = &[
+    "android", "angular", "apple", "aws", "bootstrap", "chrome", "css3", "database", "debian",
+    "docker", "dotnet", "firefox", "git", "github", "go", "html5", "java", "javascript",
+    "jenkins", "linux", "mongodb", "mysql", "nginx", "nodejs", "npm", "php", "postgresql",
+    "python", "rails", "react", "redis", "ruby", "sass", "ubuntu", "windows",
+];
+
+const EMOJI: &[&str] = &[
+    "smile", "grinning", "thumbsup", "thumbsdown", "heart", "star", "fire", "rocket", "bulb",
+    "warning", "lock", "key", "bug", "hourglass", "white_check_mark", "x", "question",
+    "exclamation", "sunny", "cloud", "zap", "gear", "memo", "email",
+];
+
+/// One icon of the catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Icon {
+    pub set: IconSet,
+    pub name: &'static str,
+}
+
+impl Icon {
+    /// Creole text showing the icon (e.g. "<&star>")
+    pub fn syntax(&self) -> String {
+        match self.set {
+            IconSet::OpenIconic => format!("<&{}>", self.name),
+            IconSet::Devicons => format!("<${}>", self.name),
+            IconSet::Emoji => format!("<:{}:>", self.name),
+        }
+    }
+
+    /// Line the diagram needs before the icon can be used
+    pub fn include(&self) -> Option<String> {
+        match self.set {
+            IconSet::Devicons => Some(format!("!include <tupadr3/devicons/{}>", self.name)),
+            IconSet::OpenIconic | IconSet::Emoji => None,
+        }
+    }
+
+    /// Small diagram showing the icon next to its name
+    pub fn preview_source(&self) -> String {
+        let include = self.include().map(|line| line + "\n").unwrap_or_default();
+        format!(
+            "@startmindmap\n{}* {} {}\n@endmindmap",
+            include,
+            self.syntax(),
+            self.name
+        )
+    }
+}
+
+/// Icons whose name contains `query` (case-insensitive), in catalogue order
+pub fn search(query: &str) -> Vec<Icon> {
+    let query = query.trim().to_lowercase();
+    IconSet::ALL
+        .into_iter()
+        .flat_map(|set| set.names().iter().map(move |&name| Icon { set, name }))
+        .filter(|icon| icon.name.contains(&query))
+        .collect()
+}
+
+/// Insert `icon` at `cursor` (UTF-16 offset, as reported by the editor)
+///
+/// Without a cursor the icon goes on its own line before the closing `@end`. The
+/// `!include` the icon needs is added after the `@start` line unless the
+/// source already has it.
+pub fn insert_icon(text: &str, cursor: Option<usize>, icon: &Icon) -> String {
+    let at = match cursor {
+        Some(cursor) => byte_offset(text, cursor),
+        None => end_line(text).unwrap_or(text.len()),
+    };
+    let snippet = match cursor {
+        Some(_) => icon.syntax(),
+        None => icon.syntax() + "\n",
+    };
+    let mut result = format!("{}{}{}", &text[..at], snippet, &text[at..]);
+
+    if let Some(include) = icon.include() {
+        if !result.lines().any(|line| line.trim() == include) {
+            let at = start_line_end(&result).unwrap_or(0);
+            let separator = if at > 0 && !result[..at].ends_with('\n') { "\n" } else { "" };
+            result.insert_str(at, &format!("{}{}\n", separator, include));
+        }
+    }
+    result
+}
+
+/// Byte index of a UTF-16 offset, clamped to the text
+pub(crate) fn byte_offset(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= utf16 {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Start of the last `@end...` line
+fn end_line(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    let mut found = None;
+    for line in text.split_inclusive('\n') {
+        if line.trim_start().to_lowercase().starts_with("@end") {
+            found = Some(offset);
+        }
+        offset += line.len();
+    }
+    found
+}
+
+/// End of the first `@start...` line, after its line break
+fn start_line_end(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        offset += line.len();
+        if line.trim_start().to_lowercase().starts_with("@start") {
+            return Some(offset);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAR: Icon = Icon {
+        set: IconSet::OpenIconic,
+        name: "star",
+    };
+    const GIT: Icon = Icon {
+        set: IconSet::Devicons,
+        name: "git",
+    };
+
+    #[test]
+    fn test_syntax() {
+        assert_eq!(STAR.syntax(), "<&star>");
+        assert_eq!(STAR.include(), None);
+        assert_eq!(GIT.syntax(), "<$git>");
+        assert_eq!(GIT.include().as_deref(), Some("!include <tupadr3/devicons/git>"));
+        let emoji = Icon {
+            set: IconSet::Emoji,
+            name: "rocket",
+        };
+        assert_eq!(emoji.syntax(), "<:rocket:>");
+        assert!(GIT.preview_source().contains("!include <tupadr3/devicons/git>\n* <$git> git"));
+    }
+
+    #[test]
+    fn test_search() {
+        let found = search("LOCK");
+        assert!(found.contains(&Icon {
+            set: IconSet::OpenIconic,
+            name: "lock-locked"
+        }));
+        assert!(found.iter().any(|icon| icon.set == IconSet::Emoji));
+        assert_eq!(search("").len(), OPEN_ICONIC.len() + DEVICONS.len() + EMOJI.len());
+    }
+
+    #[test]
+    fn test_insert_at_cursor() {
+        // Cursor after "ア -> B : " counted in UTF-16 units
+        let text = "@startuml\nア -> B : 完了\n@enduml";
+        let cursor = "@startuml\nア -> B : ".encode_utf16().count();
+        assert_eq!(
+            insert_icon(text, Some(cursor), &STAR),
+            "@startuml\nア -> B : <&star>完了\n@enduml"
+        );
+        assert_eq!(
+            insert_icon(text, None, &STAR),
+            "@startuml\nア -> B : 完了\n<&star>\n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_insert_adds_include_once() {
+        let text = "@startuml\nA -> B : \n@enduml";
+        let once = insert_icon(text, Some(19), &GIT);
+        assert_eq!(
+            once,
+            "@startuml\n!include <tupadr3/devicons/git>\nA -> B : <$git>\n@enduml"
+        );
+        let twice = insert_icon(&once, Some(0), &GIT);
+        assert_eq!(twice.matches("!include").count(), 1);
+    }
+}
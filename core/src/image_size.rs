@@ -0,0 +1,158 @@
+// Rendered image dimensions and size limits
+//
+// Images are read just far enough to find their size: the IHDR chunk of a
+// PNG, the root element of an SVG. The API server rejects rendered images
+// over its limits with ErrorCode::SizeLimit instead of sending them on.
+
+use crate::models::{ErrorCode, ImageFormat};
+use crate::svg::root_attribute;
+
+/// Largest width or height of a rendered image (pixels)
+pub const MAX_IMAGE_DIMENSION: u32 = 8192;
+
+/// Largest rendered image (bytes)
+pub const MAX_IMAGE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Limits applied to rendered images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    /// Largest width or height (pixels)
+    pub max_dimension: u32,
+    pub max_bytes: usize,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self {
+            max_dimension: MAX_IMAGE_DIMENSION,
+            max_bytes: MAX_IMAGE_BYTES,
+        }
+    }
+}
+
+impl ImageLimits {
+    /// SizeLimit with the actual numbers when `data` is over a limit
+    ///
+    /// Images whose dimensions cannot be read are only checked by size.
+    pub fn check(&self, format: ImageFormat, data: &[u8]) -> Result<(), ErrorCode> {
+        let dimensions = image_dimensions(format, data)
+            .filter(|(width, height)| *width.max(height) > self.max_dimension);
+        if data.len() > self.max_bytes || dimensions.is_some() {
+            return Err(ErrorCode::SizeLimit {
+                actual_bytes: data.len(),
+                max_bytes: self.max_bytes,
+                width: dimensions.map(|(width, _)| width),
+                height: dimensions.map(|(_, height)| height),
+                max_dimension: dimensions.map(|_| self.max_dimension),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Width and height of a PNG or SVG image in pixels (None for text and PDF)
+pub fn image_dimensions(format: ImageFormat, data: &[u8]) -> Option<(u32, u32)> {
+    match format {
+        ImageFormat::Png => png_dimensions(data),
+        ImageFormat::Svg => svg_dimensions(&String::from_utf8_lossy(data)),
+        ImageFormat::Txt | ImageFormat::Pdf => None,
+    }
+}
+
+fn png_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    const SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    if !data.starts_with(SIGNATURE) || data.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let number = |at: usize| Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?));
+    Some((number(16)?, number(20)?))
+}
+
+/// From `width` and `height` on the root element, else from its `viewBox`
+fn svg_dimensions(svg: &str) -> Option<(u32, u32)> {
+    let length = |name| root_attribute(svg, name).and_then(pixels);
+    if let (Some(width), Some(height)) = (length("width"), length("height")) {
+        return Some((width, height));
+    }
+    let view_box: Vec<u32> = root_attribute(svg, "viewBox")?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .filter_map(pixels)
+        .collect();
+    match view_box[..] {
+        [_, _, width, height] => Some((width, height)),
+        _ => None,
+    }
+}
+
+/// Pixels in a length such as "120", "120.5" or "120px"; None for relative units
+fn pixels(length: &str) -> Option<u32> {
+    let number = length.trim().trim_end_matches("px");
+    let value: f64 = number.parse().ok()?;
+    (value.is_finite() && value >= 0.0).then(|| value.ceil() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 13];
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[8, 6, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn test_png_dimensions() {
+        assert_eq!(image_dimensions(ImageFormat::Png, &png(640, 480)), Some((640, 480)));
+        assert_eq!(image_dimensions(ImageFormat::Png, b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn test_svg_dimensions() {
+        let plantuml = r#"<?xml version="1.0" encoding="us-ascii" standalone="no"?><svg xmlns="http://www.w3.org/2000/svg" contentStyleType="text/css" height="152.5px" preserveAspectRatio="none" style="width:113px;height:152px;" version="1.1" viewBox="0 0 113 152" width="113px"><g/></svg>"#;
+        assert_eq!(
+            image_dimensions(ImageFormat::Svg, plantuml.as_bytes()),
+            Some((113, 153))
+        );
+        // Relative sizes fall back to the viewBox
+        let relative = r#"<svg width="100%" viewBox="0,0,300,200"><svgx width="1"/></svg>"#;
+        assert_eq!(
+            image_dimensions(ImageFormat::Svg, relative.as_bytes()),
+            Some((300, 200))
+        );
+        assert_eq!(image_dimensions(ImageFormat::Svg, b"<g/>"), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ImageLimits {
+            max_dimension: 1000,
+            max_bytes: 100,
+        };
+        assert_eq!(limits.check(ImageFormat::Png, &png(1000, 20)), Ok(()));
+        assert_eq!(
+            limits.check(ImageFormat::Png, &png(1200, 20)),
+            Err(ErrorCode::SizeLimit {
+                actual_bytes: 29,
+                max_bytes: 100,
+                width: Some(1200),
+                height: Some(20),
+                max_dimension: Some(1000),
+            })
+        );
+        assert_eq!(
+            limits.check(ImageFormat::Svg, &[b' '; 101]),
+            Err(ErrorCode::SizeLimit {
+                actual_bytes: 101,
+                max_bytes: 100,
+                width: None,
+                height: None,
+                max_dimension: None,
+            })
+        );
+    }
+}
@@ -0,0 +1,194 @@
+// Block indent and outdent for the editor's Tab and Shift+Tab
+//
+// Offsets are UTF-16 units, as the browser reports the selection. A selection
+// indents or outdents every line it touches; a caret alone gets one indent
+// inserted at its position (Tab) or its line outdented (Shift+Tab).
+
+use crate::icons::byte_offset;
+use crate::text::{utf16_len, TextEdit};
+
+/// One level of indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentUnit {
+    Spaces(usize),
+    Tab,
+}
+
+impl Default for IndentUnit {
+    fn default() -> Self {
+        Self::Spaces(2)
+    }
+}
+
+impl IndentUnit {
+    /// Widths offered by the editor settings
+    pub const CHOICES: [IndentUnit; 3] = [Self::Spaces(2), Self::Spaces(4), Self::Tab];
+
+    pub fn text(&self) -> String {
+        match self {
+            Self::Spaces(width) => " ".repeat(*width),
+            Self::Tab => "\t".to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Spaces(width) => format!("スペース{}", width),
+            Self::Tab => "タブ".to_string(),
+        }
+    }
+}
+
+/// Indent the lines touched by `start..end`, or insert one indent at a caret
+pub fn indent(text: &str, start: usize, end: usize, unit: IndentUnit) -> TextEdit {
+    let (start, end) = (start.min(end), start.max(end));
+    let indent = unit.text();
+    if start == end {
+        let at = byte_offset(text, start);
+        let caret = start + utf16_len(&indent);
+        return TextEdit {
+            text: format!("{}{}{}", &text[..at], indent, &text[at..]),
+            start: caret,
+            end: caret,
+        };
+    }
+    edit_lines(text, start, end, |line| {
+        if line.trim().is_empty() {
+            line.to_string()
+        } else {
+            format!("{}{}", indent, line)
+        }
+    })
+}
+
+/// Remove one indent from the lines touched by `start..end`
+///
+/// A line starting with a tab loses the tab; otherwise up to one indent's
+/// width of spaces is removed.
+pub fn outdent(text: &str, start: usize, end: usize, unit: IndentUnit) -> TextEdit {
+    let (start, end) = (start.min(end), start.max(end));
+    let width = match unit {
+        IndentUnit::Spaces(width) => width,
+        IndentUnit::Tab => 4,
+    };
+    let strip = |line: &str| match line.strip_prefix('\t') {
+        Some(rest) => rest.to_string(),
+        None => {
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            line[spaces.min(width)..].to_string()
+        }
+    };
+
+    if start != end {
+        return edit_lines(text, start, end, strip);
+    }
+    // Keep the caret where it was in the line's text
+    let at = byte_offset(text, start);
+    let line_start = text[..at].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[at..].find('\n').map_or(text.len(), |index| at + index);
+    let line = &text[line_start..line_end];
+    let stripped = strip(line);
+    let removed = utf16_len(&line[..line.len() - stripped.len()]);
+    let column = utf16_len(&text[line_start..at]);
+    let caret = start - column.min(removed);
+    TextEdit {
+        text: format!("{}{}{}", &text[..line_start], stripped, &text[line_end..]),
+        start: caret,
+        end: caret,
+    }
+}
+
+/// Apply `edit` to each line touched by the selection and select those lines
+fn edit_lines(text: &str, start: usize, end: usize, edit: impl Fn(&str) -> String) -> TextEdit {
+    let (start, mut end) = (byte_offset(text, start), byte_offset(text, end));
+    // A selection ending at the start of a line does not include that line
+    if end > start && text[..end].ends_with('\n') {
+        end -= 1;
+    }
+    let first = text[..start].rfind('\n').map_or(0, |index| index + 1);
+    let last = text[end..]
+        .find('\n')
+        .map_or(text.len(), |index| end + index);
+
+    let lines: Vec<String> = text[first..last].split('\n').map(edit).collect();
+    let block = lines.join("\n");
+    let selection_start = utf16_len(&text[..first]);
+    TextEdit {
+        text: format!("{}{}{}", &text[..first], block, &text[last..]),
+        start: selection_start,
+        end: selection_start + utf16_len(&block),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "@startuml\ngroup login\nA -> B\n\nB --> A\nend\n@enduml";
+
+    fn select(text: &str, from: &str, to: &str) -> (usize, usize) {
+        let start = text.find(from).unwrap();
+        (start, text.find(to).unwrap() + to.len())
+    }
+
+    #[test]
+    fn test_indent_selected_lines() {
+        // Partial selection from the middle of one line to another
+        let (start, end) = select(SOURCE, "-> B", "B --");
+        let edit = indent(SOURCE, start, end, IndentUnit::Spaces(4));
+        assert_eq!(
+            edit.text,
+            "@startuml\ngroup login\n    A -> B\n\n    B --> A\nend\n@enduml"
+        );
+        assert_eq!(
+            &edit.text[edit.start..edit.end],
+            "    A -> B\n\n    B --> A"
+        );
+
+        let back = outdent(&edit.text, edit.start, edit.end, IndentUnit::Spaces(4));
+        assert_eq!(back.text, SOURCE);
+    }
+
+    #[test]
+    fn test_selection_ending_at_line_start() {
+        let start = SOURCE.find("A -> B").unwrap();
+        let end = SOURCE.find("\nB -->").unwrap() + 1;
+        let edit = indent(SOURCE, start, end, IndentUnit::Tab);
+        assert!(edit.text.contains("\n\tA -> B\n\nB --> A"));
+    }
+
+    #[test]
+    fn test_caret() {
+        let caret = SOURCE.find("A -> B").unwrap();
+        let edit = indent(SOURCE, caret, caret, IndentUnit::Spaces(2));
+        assert!(edit.text.contains("\n  A -> B\n"));
+        assert_eq!((edit.start, edit.end), (caret + 2, caret + 2));
+
+        // Shift+Tab on the caret line outdents it and keeps the caret on the text
+        let in_text = edit.start + 2;
+        let back = outdent(&edit.text, in_text, in_text, IndentUnit::Spaces(2));
+        assert_eq!(back.text, SOURCE);
+        assert_eq!(back.start, caret + 2);
+        let no_indent = outdent(SOURCE, caret, caret, IndentUnit::Spaces(2));
+        assert_eq!(no_indent.text, SOURCE);
+    }
+
+    #[test]
+    fn test_outdent_partial_and_tabs() {
+        let text = "\tA -> B\n B -> C\nC -> D";
+        let edit = outdent(text, 0, text.len(), IndentUnit::Spaces(4));
+        assert_eq!(edit.text, "A -> B\nB -> C\nC -> D");
+    }
+
+    #[test]
+    fn test_utf16_offsets() {
+        let text = "日本 -> 語\nB -> C";
+        let end = text.encode_utf16().count();
+        let edit = indent(text, 1, end, IndentUnit::Spaces(2));
+        assert_eq!(edit.text, "  日本 -> 語\n  B -> C");
+        assert_eq!(
+            (edit.start, edit.end),
+            (0, edit.text.encode_utf16().count())
+        );
+    }
+}
@@ -1,6 +1,32 @@
 // PlantUML Editor - Core Library
 
+pub mod clock;
+pub mod continuation;
+pub mod converters;
+pub mod deterministic;
+pub mod diagnostics;
+pub mod diff;
+pub mod embed;
+pub mod encoded_source;
+pub mod icons;
+pub mod image_size;
+pub mod indent;
+pub mod metadata;
 pub mod models;
+pub mod normalize;
+pub mod optimize;
+pub mod outline;
+pub mod pages;
+mod png;
+pub mod preprocess;
+pub mod refactor;
+pub mod retry;
+pub mod sarif;
+pub mod stats;
+pub mod svg;
+pub mod text;
+pub mod text_import;
+pub mod tokens;
 pub mod validation;
 
 pub use models::*;
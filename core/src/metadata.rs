@@ -0,0 +1,131 @@
+// Document metadata read from PlantUML directives
+
+/// Title of a diagram taken from its `title` directive, or `caption` if it has none
+///
+/// Supports the one-line form (`title Login flow`) and the block form
+/// (`title` ... `end title`), whose lines are joined with spaces. Returns None
+/// when neither directive is present or the text is blank.
+pub fn extract_title(content: &str) -> Option<String> {
+    let mut title = None;
+    let mut caption = None;
+    let mut block: Option<Vec<&str>> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+
+        if let Some(lines) = block.as_mut() {
+            if is_end_title(line) {
+                title = title.or_else(|| clean(&lines.join(" ")));
+                block = None;
+            } else if !line.is_empty() {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if let Some(rest) = directive_argument(line, "title") {
+            if rest.is_empty() {
+                block = Some(Vec::new());
+            } else {
+                title = title.or_else(|| clean(rest));
+            }
+        } else if let Some(rest) = directive_argument(line, "caption") {
+            caption = caption.or_else(|| clean(rest));
+        }
+    }
+
+    title.or(caption)
+}
+
+/// ASCII file name stem for a title (e.g. "Login flow (v2)" -> "Login_flow_v2")
+///
+/// Returns None when nothing usable is left, such as for Japanese-only titles.
+pub fn file_stem_from_title(title: &str) -> Option<String> {
+    let stem = title
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    if stem.chars().any(|c| c.is_ascii_alphanumeric()) {
+        Some(stem)
+    } else {
+        None
+    }
+}
+
+/// Text after `keyword` when `line` starts with it as a whole word
+pub(crate) fn directive_argument<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let head = line.get(..keyword.len())?;
+    if !head.eq_ignore_ascii_case(keyword) {
+        return None;
+    }
+    let rest = &line[keyword.len()..];
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+fn is_end_title(line: &str) -> bool {
+    let words: String = line.split_whitespace().collect();
+    words.eq_ignore_ascii_case("endtitle")
+}
+
+/// Drop surrounding quotes and PlantUML line breaks (`\n`)
+fn clean(text: &str) -> Option<String> {
+    let text = text.trim().trim_matches('"').replace("\\n", " ");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_one_line_title() {
+        let content = "@startuml\ntitle ログイン処理\nAlice -> Bob\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("ログイン処理"));
+
+        let content = "@startuml\n  Title \"Login\\nflow\"\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("Login flow"));
+    }
+
+    #[test]
+    fn test_extract_block_title() {
+        let content = "@startuml\ntitle\n  Order\n  processing\nend title\nA -> B\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("Order processing"));
+
+        let content = "@startuml\ntitle\nPayment\nendtitle\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("Payment"));
+    }
+
+    #[test]
+    fn test_caption_is_fallback() {
+        let content = "@startuml\ncaption Figure 1\nA -> B\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("Figure 1"));
+
+        let content = "@startuml\ncaption Figure 1\ntitle Main\n@enduml";
+        assert_eq!(extract_title(content).as_deref(), Some("Main"));
+    }
+
+    #[test]
+    fn test_no_title() {
+        assert_eq!(extract_title("@startuml\nA -> B : title\n@enduml"), None);
+        assert_eq!(extract_title("@startuml\ntitlebar -> B\n@enduml"), None);
+        assert_eq!(extract_title("@startuml\ntitle   \n@enduml"), None);
+    }
+
+    #[test]
+    fn test_file_stem_from_title() {
+        assert_eq!(file_stem_from_title("Login flow (v2)").as_deref(), Some("Login_flow_v2"));
+        assert_eq!(file_stem_from_title("API設計").as_deref(), Some("API"));
+        assert_eq!(file_stem_from_title("ログイン処理"), None);
+    }
+}
@@ -2,7 +2,13 @@
 
 use serde::{Deserialize, Serialize};
 
-/// Document ID (UUID v4)
+use crate::clock::{Clock, SystemClock};
+use crate::diff::LinePatch;
+
+/// Namespace of content-derived document IDs
+const CONTENT_ID_NAMESPACE: uuid::Uuid = uuid::Uuid::from_u128(0x6f1c_2a8e_93d4_4b57_a0e2_5c7d_18f3_b946);
+
+/// Document ID (UUID v4, or v5 derived from the content; see `IdStrategy`)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct DocumentId(pub uuid::Uuid);
 
@@ -11,6 +17,11 @@ impl DocumentId {
     pub fn new() -> Self {
         Self(uuid::Uuid::new_v4())
     }
+
+    /// ID that is the same for the same PlantUML text (UUID v5)
+    pub fn from_content(content: &str) -> Self {
+        Self(uuid::Uuid::new_v5(&CONTENT_ID_NAMESPACE, content.as_bytes()))
+    }
 }
 
 impl Default for DocumentId {
@@ -19,8 +30,29 @@ impl Default for DocumentId {
     }
 }
 
+/// How new document IDs are chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// Random UUID v4 for every document
+    #[default]
+    Random,
+    /// UUID v5 of the content, so identical sources get identical IDs
+    /// (render caches, dedup, snapshot tests)
+    Content,
+}
+
+impl IdStrategy {
+    pub fn id_for(&self, content: &str) -> DocumentId {
+        match self {
+            IdStrategy::Random => DocumentId::new(),
+            IdStrategy::Content => DocumentId::from_content(content),
+        }
+    }
+}
+
 /// PlantUML document with metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlantUMLDocument {
     /// Unique document identifier
     pub id: DocumentId,
@@ -34,20 +66,38 @@ pub struct PlantUMLDocument {
     /// Last update timestamp (Unix timestamp)
     pub updated_at: i64,
     
-    /// Optional title (user input)
+    /// Optional title (user input, or the source's title directive)
     pub title: Option<String>,
+    
+    /// Folder holding the document (server documents only; None for the top level)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<FolderId>,
+    
+    /// Revision number, incremented on every server-side update
+    #[serde(default = "first_version")]
+    pub version: u64,
+}
+
+fn first_version() -> u64 {
+    1
 }
 
 impl PlantUMLDocument {
     /// Create a new document with given content
     pub fn new(content: String) -> Self {
-        let now = chrono::Utc::now().timestamp();
+        Self::new_at(content, SystemClock.now())
+    }
+
+    /// Create a new document created and updated at `now` (see `clock`)
+    pub fn new_at(content: String, now: i64) -> Self {
         Self {
             id: DocumentId::new(),
             content,
             created_at: now,
             updated_at: now,
             title: None,
+            folder: None,
+            version: first_version(),
         }
     }
     
@@ -55,14 +105,162 @@ impl PlantUMLDocument {
     pub fn validate(&self) -> Result<(), crate::validation::ValidationError> {
         crate::validation::validate_plantuml_content(&self.content)
     }
+
+    /// Take the title from the `title`/`caption` directive unless the user gave one
+    pub fn fill_title_from_source(&mut self) {
+        if self.title.is_none() {
+            self.title = crate::metadata::extract_title(&self.content);
+        }
+    }
+
+    /// Copy of this document under a new ID, titled "<title> のコピー"
+    pub fn duplicate(&self) -> Self {
+        self.duplicate_at(SystemClock.now())
+    }
+
+    /// `duplicate` created at `now`
+    pub fn duplicate_at(&self, now: i64) -> Self {
+        let title = self
+            .title
+            .clone()
+            .or_else(|| crate::metadata::extract_title(&self.content))
+            .unwrap_or_else(|| "無題".to_string());
+
+        let mut copy = Self::new_at(self.content.clone(), now);
+        copy.title = Some(format!("{} のコピー", title));
+        copy.folder = self.folder;
+        copy
+    }
+}
+
+/// Folder ID (UUID v4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FolderId(pub uuid::Uuid);
+
+impl FolderId {
+    /// Generate a new random folder ID
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+}
+
+impl Default for FolderId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Folder organizing server documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Folder {
+    /// Unique folder identifier
+    pub id: FolderId,
+    
+    /// Display name
+    pub name: String,
+    
+    /// Enclosing folder (None for top-level folders)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<FolderId>,
+    
+    /// Creation timestamp (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// Include file stored on the server (`!include <name>`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncludeFile {
+    /// File name, e.g. "company-style.iuml"
+    pub name: String,
+    
+    /// Uploading user for private files (None for files shared by the deployment)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    
+    /// Content size in bytes
+    pub size: usize,
+    
+    /// Last upload timestamp (Unix timestamp)
+    pub updated_at: i64,
 }
 
 /// Image format for diagram output
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ImageFormat {
     Png,
     Svg,
+    /// ASCII art, for the text preview (sequence diagrams and a few others)
+    Txt,
+    /// Vector document for printing (export only, not previewed)
+    Pdf,
+}
+
+/// Access level to a server document, from least to most privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentRole {
+    /// Read and render
+    Viewer,
+    /// Also change the content
+    Editor,
+    /// Also delete, share and manage roles
+    Owner,
+}
+
+impl DocumentRole {
+    /// Name shown to users
+    pub fn label(self) -> &'static str {
+        match self {
+            DocumentRole::Viewer => "閲覧者",
+            DocumentRole::Editor => "編集者",
+            DocumentRole::Owner => "オーナー",
+        }
+    }
+
+    /// Whether the role allows changing the document content
+    pub fn can_edit(self) -> bool {
+        self >= DocumentRole::Editor
+    }
+}
+
+/// Resolution preset for exported images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ExportScale {
+    #[default]
+    #[serde(rename = "1x")]
+    X1,
+    #[serde(rename = "2x")]
+    X2,
+    #[serde(rename = "4x")]
+    X4,
+}
+
+impl ExportScale {
+    /// All presets in menu order
+    pub const ALL: [ExportScale; 3] = [ExportScale::X1, ExportScale::X2, ExportScale::X4];
+
+    /// Value of the PlantUML `scale` directive
+    pub fn factor(self) -> u32 {
+        match self {
+            ExportScale::X1 => 1,
+            ExportScale::X2 => 2,
+            ExportScale::X4 => 4,
+        }
+    }
+
+    /// Wire value ("1x", "2x", "4x"), also used as the label
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportScale::X1 => "1x",
+            ExportScale::X2 => "2x",
+            ExportScale::X4 => "4x",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == ExportScale::X1
+    }
 }
 
 /// Status level for messages
@@ -84,6 +282,11 @@ pub enum ErrorCode {
     // 正常完了 (INFO)
     ConversionOk,
     ExportOk,
+    DiagnosticsReported { 
+        errors: usize, 
+        warnings: usize 
+    },
+    DiagramAnalyzed,
     
     // データ付き成功メッセージ (INFO)
     SaveSuccess { 
@@ -95,6 +298,21 @@ pub enum ErrorCode {
     DeleteSuccess { 
         slot_number: u8 
     },
+    DuplicateSuccess { 
+        from_slot: u8, 
+        to_slot: u8 
+    },
+    RestoreSuccess { 
+        slot_number: u8 
+    },
+    MoveSuccess { 
+        from_slot: u8, 
+        to_slot: u8 
+    },
+    BulkDeleteSuccess { 
+        count: usize 
+    },
+    TrashEmptied,
     
     // バリデーションエラー (WARNING)
     ValidationEmpty,
@@ -102,6 +320,108 @@ pub enum ErrorCode {
         actual: usize, 
         max: usize 
     },
+    /// Over the soft limit: still accepted, but close to ValidationTextLimit
+    ValidationTextNearLimit { 
+        actual: usize, 
+        max: usize 
+    },
+    RequestTooLarge { 
+        max_bytes: usize 
+    },
+    
+    // サーバーのPNG生成に失敗し、プレビューのSVGから変換した (WARNING)
+    RasterizedExport,
+    
+    // ゴミ箱に保存できず、元に戻せない形で削除した (WARNING)
+    DeletedWithoutTrash { 
+        slot_number: u8 
+    },
+    
+    // 大きなSVGのプレビュー (WARNING、画像付き)
+    LargeSvg { 
+        svg_bytes: usize, 
+        threshold_bytes: usize 
+    },
+    SvgDowngraded { 
+        svg_bytes: usize 
+    },
+    
+    // 社内スタイル規約 (WARNING、StylePolicyWarning は画像付き)
+    StylePolicyViolation { 
+        missing: Vec<String> 
+    },
+    StylePolicyWarning { 
+        missing: Vec<String> 
+    },
+    
+    // フォント指定 (WARNING)
+    FontNotAllowed { 
+        font: String 
+    },
+    
+    // エクスポートの後処理 (WARNING、PostProcessorSkipped は画像付き)
+    PostProcessorUnknown { 
+        name: String 
+    },
+    PostProcessorSkipped { 
+        name: String, 
+        format: String 
+    },
+    
+    // インポート (INFO/WARNING)
+    ImportOk,
+    ImportError { 
+        reason: String 
+    },
+    
+    // 名前の変更・抜き出し (WARNING)
+    RenameError { 
+        reason: String 
+    },
+    ExtractError { 
+        reason: String 
+    },
+    
+    // サーバー保存ドキュメント (INFO/WARNING)
+    DocumentSaved,
+    DocumentLoaded,
+    DocumentDeleted,
+    DocumentNotFound { 
+        id: String 
+    },
+    ShareLinkCreated,
+    ShareLinkInvalid,
+    ShareLinkExpired { 
+        expired_at: i64 
+    },
+    DocumentAccessDenied { 
+        required: DocumentRole 
+    },
+    DocumentOwnerRequired,
+    FolderSaved,
+    FolderNotFound { 
+        id: String 
+    },
+    FolderNameEmpty,
+    FolderCycle,
+    DocumentConflict { 
+        current_version: u64 
+    },
+    IncludeSaved,
+    IncludeDeleted,
+    IncludeNotFound { 
+        name: String 
+    },
+    IncludeNameInvalid { 
+        name: String 
+    },
+    IncludeFileLimit { 
+        max_files: usize 
+    },
+    IncludeStorageFull { 
+        max_bytes: usize 
+    },
+    SettingsSaved,
     
     // ストレージエラー (WARNING/ERROR)
     StorageInputLimit { 
@@ -124,7 +444,14 @@ pub enum ErrorCode {
     // 処理エラー (ERROR)
     SizeLimit { 
         actual_bytes: usize, 
-        max_bytes: usize 
+        max_bytes: usize,
+        /// Rendered image size, when over the dimension limit
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        height: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_dimension: Option<u32>,
     },
     EncodingError { 
         encoding: String 
@@ -135,6 +462,47 @@ pub enum ErrorCode {
     ExportError { 
         format: String 
     },
+    ArchiveFileLimit { 
+        max_files: usize 
+    },
+    
+    // バックグラウンドジョブ (INFO/WARNING/ERROR)
+    JobAccepted { 
+        total: usize 
+    },
+    JobRunning { 
+        finished: usize, 
+        total: usize 
+    },
+    JobCompleted { 
+        succeeded: usize, 
+        failed: usize 
+    },
+    JobFailed { 
+        reason: String 
+    },
+    JobNotFound { 
+        id: String 
+    },
+    JobQueueFull { 
+        capacity: usize 
+    },
+    
+    // PlantUMLサーバーの混雑 (WARNING)
+    RenderQueueFull { 
+        max_concurrency: usize 
+    },
+    
+    // 利用量の上限 (WARNING)
+    QuotaExceeded { 
+        limit: u64, 
+        reset_at: i64 
+    },
+    
+    // 運用操作 (WARNING)
+    Maintenance,
+    AdminAccessDenied,
+    AuthenticationRequired,
     
     // サーバー・ネットワークエラー (ERROR)
     ServerError { 
@@ -155,6 +523,10 @@ impl ErrorCode {
             // 成功系 (INFO)
             Self::ConversionOk => "図が正常に生成されました".to_string(),
             Self::ExportOk => "図が正常にエクスポートされました".to_string(),
+            Self::DiagnosticsReported { errors, warnings } => {
+                format!("診断が完了しました（エラー {}件、警告 {}件）", errors, warnings)
+            }
+            Self::DiagramAnalyzed => "図の統計を取得しました".to_string(),
             
             Self::SaveSuccess { slot_number } => {
                 format!("PlantUMLソースをスロット{}に保存しました", slot_number)
@@ -165,6 +537,19 @@ impl ErrorCode {
             Self::DeleteSuccess { slot_number } => {
                 format!("スロット{}のデータを削除しました", slot_number)
             }
+            Self::DuplicateSuccess { from_slot, to_slot } => {
+                format!("スロット{}をスロット{}に複製しました", from_slot, to_slot)
+            }
+            Self::RestoreSuccess { slot_number } => {
+                format!("削除したデータをスロット{}に元に戻しました", slot_number)
+            }
+            Self::TrashEmptied => "ゴミ箱を空にしました".to_string(),
+            Self::MoveSuccess { from_slot, to_slot } => {
+                format!("スロット{}をスロット{}に移動しました", from_slot, to_slot)
+            }
+            Self::BulkDeleteSuccess { count } => {
+                format!("{}件のスロットを削除しました（ゴミ箱から元に戻せます）", count)
+            }
             
             // バリデーションエラー (WARNING)
             Self::ValidationEmpty => "PlantUMLソースを入力してください".to_string(),
@@ -174,6 +559,111 @@ impl ErrorCode {
                     max, actual
                 )
             }
+            Self::ValidationTextNearLimit { actual, max } => {
+                format!(
+                    "PlantUMLソースが上限に近づいています（現在: {}文字 / 上限: {}文字）。図の分割を検討してください",
+                    actual, max
+                )
+            }
+            Self::RequestTooLarge { max_bytes } => {
+                format!("送信データが大きすぎます（上限: {} bytes）。内容を減らすか分割して送信してください", max_bytes)
+            }
+            Self::RasterizedExport => {
+                "サーバーでPNGを生成できなかったため、プレビューのSVGからPNGを作成しました".to_string()
+            }
+            Self::DeletedWithoutTrash { slot_number } => {
+                format!("スロット{}のデータを削除しました（ゴミ箱に空きがないため、元に戻せません）", slot_number)
+            }
+            
+            // 大きなSVGのプレビュー (WARNING、画像付き)
+            Self::LargeSvg { svg_bytes, threshold_bytes } => {
+                format!(
+                    "SVGが大きいため（{} bytes、目安: {} bytes）プレビューが重くなる場合があります。「PNGで表示」に切り替えると軽くなります",
+                    svg_bytes, threshold_bytes
+                )
+            }
+            Self::SvgDowngraded { svg_bytes } => {
+                format!("SVGが大きいため（{} bytes）PNGで表示しています", svg_bytes)
+            }
+            
+            // 社内スタイル規約 (WARNING、StylePolicyWarning は画像付き)
+            Self::StylePolicyViolation { missing } => {
+                format!("社内のスタイル規約を満たしていないためエクスポートできません。次の指定を追加してください: {}", missing.join("、"))
+            }
+            Self::StylePolicyWarning { missing } => {
+                format!("社内のスタイル規約を満たしていません（不足: {}）。図を共有する前に指定を追加してください", missing.join("、"))
+            }
+            
+            // フォント指定 (WARNING)
+            Self::FontNotAllowed { font } => {
+                format!("フォント「{}」はこのサーバーでは使用できません。設定のフォント一覧から選択してください", font)
+            }
+            
+            // エクスポートの後処理 (WARNING、PostProcessorSkipped は画像付き)
+            Self::PostProcessorUnknown { name } => {
+                format!("エクスポートの後処理「{}」はこのサーバーでは使用できません", name)
+            }
+            Self::PostProcessorSkipped { name, format } => {
+                format!("後処理「{}」は{}形式に対応していないため、適用せずにエクスポートしました", name, format)
+            }
+            
+            // インポート (INFO/WARNING)
+            Self::ImportOk => "PlantUMLソースを生成しました".to_string(),
+            Self::ImportError { reason } => {
+                format!("インポートに失敗しました。{}", reason)
+            }
+            
+            // 名前の変更・抜き出し (WARNING)
+            Self::RenameError { reason } => {
+                format!("名前を変更できませんでした。{}", reason)
+            }
+            Self::ExtractError { reason } => {
+                format!("選択した行を抜き出せませんでした。{}", reason)
+            }
+            
+            // サーバー保存ドキュメント (INFO/WARNING)
+            Self::DocumentSaved => "ドキュメントをサーバーに保存しました".to_string(),
+            Self::DocumentLoaded => "サーバーからドキュメントを読み込みました".to_string(),
+            Self::DocumentDeleted => "サーバーのドキュメントを削除しました".to_string(),
+            Self::DocumentNotFound { id } => {
+                format!("ドキュメントが見つかりません（ID: {}）", id)
+            }
+            Self::ShareLinkCreated => "共有リンクを作成しました".to_string(),
+            Self::ShareLinkInvalid => "共有リンクが無効です。リンクを発行した人に新しいリンクを依頼してください".to_string(),
+            Self::ShareLinkExpired { expired_at } => {
+                let expired_at = chrono::DateTime::from_timestamp(*expired_at, 0)
+                    .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_default();
+                format!("共有リンクの有効期限が切れています（{}）。リンクを発行した人に新しいリンクを依頼してください", expired_at)
+            }
+            Self::DocumentAccessDenied { required } => {
+                format!("この操作には{}以上の権限が必要です。ドキュメントのオーナーに権限を依頼してください", required.label())
+            }
+            Self::DocumentOwnerRequired => "ドキュメントには少なくとも1人のオーナーが必要です".to_string(),
+            Self::FolderSaved => "フォルダーを保存しました".to_string(),
+            Self::FolderNotFound { id } => {
+                format!("フォルダーが見つかりません（ID: {}）", id)
+            }
+            Self::FolderNameEmpty => "フォルダー名を入力してください".to_string(),
+            Self::FolderCycle => "フォルダーをそれ自身やその中のフォルダーへ移動することはできません".to_string(),
+            Self::DocumentConflict { current_version } => {
+                format!("読み込んだ後に他のユーザーがドキュメントを更新しました（現在のバージョン: {}）。差分を確認してから保存してください", current_version)
+            }
+            Self::IncludeSaved => "インクルードファイルを保存しました".to_string(),
+            Self::SettingsSaved => "設定を保存しました".to_string(),
+            Self::IncludeDeleted => "インクルードファイルを削除しました".to_string(),
+            Self::IncludeNotFound { name } => {
+                format!("インクルードファイルが見つかりません（{}）", name)
+            }
+            Self::IncludeNameInvalid { name } => {
+                format!("インクルードファイル名「{}」は使用できません。英数字・「-」・「_」・「.」で、拡張子 .iuml または .puml の名前を指定してください", name)
+            }
+            Self::IncludeFileLimit { max_files } => {
+                format!("インクルードファイルは{}個までです。不要なファイルを削除してください", max_files)
+            }
+            Self::IncludeStorageFull { max_bytes } => {
+                format!("インクルードファイルの保存領域（{}バイト）に空きがありません。管理者に連絡してください", max_bytes)
+            }
             
             // ストレージエラー (WARNING/ERROR)
             Self::StorageInputLimit { actual, max } => {
@@ -199,7 +689,13 @@ impl ErrorCode {
             }
             
             // 処理エラー (ERROR)
-            Self::SizeLimit { actual_bytes, max_bytes } => {
+            Self::SizeLimit { width: Some(width), height: Some(height), max_dimension: Some(max_dimension), .. } => {
+                format!(
+                    "画像の寸法が上限を超えています（現在: {}×{} px、上限: {} px）。'scale'でサイズを縮小するか、図を分割してください",
+                    width, height, max_dimension
+                )
+            }
+            Self::SizeLimit { actual_bytes, max_bytes, .. } => {
                 format!(
                     "画像サイズが上限を超えています（現在: {} bytes、上限: {} bytes）。'scale'でサイズを縮小するか、図を分割してください",
                     actual_bytes, max_bytes
@@ -222,6 +718,49 @@ impl ErrorCode {
                 format!("ファイルのエクスポートに失敗しました（形式: {}）。再度お試しください", format)
             }
             
+            // バックグラウンドジョブ (INFO/WARNING/ERROR)
+            Self::JobAccepted { total } => {
+                format!("{}件のエクスポートジョブを受け付けました", total)
+            }
+            Self::JobRunning { finished, total } => {
+                format!("エクスポートジョブを処理中です（{}/{}件）", finished, total)
+            }
+            Self::JobCompleted { succeeded, failed } => {
+                format!("エクスポートジョブが完了しました（成功: {}件、失敗: {}件）", succeeded, failed)
+            }
+            Self::JobFailed { reason } => {
+                format!("エクスポートジョブが失敗しました。{}", reason)
+            }
+            Self::JobNotFound { id } => {
+                format!("ジョブが見つかりません（ID: {}）。保持期間を過ぎた可能性があります", id)
+            }
+            Self::JobQueueFull { capacity } => {
+                format!("エクスポートジョブが混み合っています（上限: {}件）。しばらく待ってから再度お試しください", capacity)
+            }
+            Self::ArchiveFileLimit { max_files } => {
+                format!("一括エクスポートできるファイル数の上限（{}個）を超えています。ファイルを分けて送信してください", max_files)
+            }
+            Self::RenderQueueFull { max_concurrency } => {
+                format!("変換処理が混み合っています（同時実行上限: {}件）。しばらく待ってから再度お試しください", max_concurrency)
+            }
+            Self::QuotaExceeded { limit, reset_at } => {
+                let reset_at = chrono::DateTime::from_timestamp(*reset_at, 0)
+                    .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_default();
+                format!("本日の利用上限（{}回）に達しました。{}以降に再度お試しください", limit, reset_at)
+            }
+            
+            // 運用操作 (WARNING)
+            Self::Maintenance => {
+                "メンテナンス中のため、図の変換を停止しています。しばらく待ってから再度お試しください".to_string()
+            }
+            Self::AdminAccessDenied => {
+                "管理用トークンが正しくありません".to_string()
+            }
+            Self::AuthenticationRequired => {
+                "サーバー保存を使うにはログインが必要です。ログインの有効期限が切れた場合は再度ログインしてください".to_string()
+            }
+            
             // サーバー・ネットワークエラー (ERROR)
             Self::ServerError { message } => {
                 format!("サーバーエラー: {}。時間をおいて再度接続を試すか管理者に問い合わせてください", message)
@@ -238,22 +777,85 @@ impl ErrorCode {
         }
     }
     
+    /// Failures that may succeed when the same request is sent again later:
+    /// the server is busy, slow or paused (see `retry`)
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::RenderQueueFull { .. } | Self::TimeoutError { .. } | Self::Maintenance
+        )
+    }
+
     /// Get status level for this error code
     pub fn status_level(&self) -> StatusLevel {
         match self {
             // INFO
             Self::ConversionOk 
             | Self::ExportOk 
+            | Self::DiagnosticsReported { .. } 
+            | Self::DiagramAnalyzed 
+            | Self::ImportOk 
+            | Self::DocumentSaved 
+            | Self::DocumentLoaded 
+            | Self::DocumentDeleted 
+            | Self::ShareLinkCreated 
+            | Self::FolderSaved 
+            | Self::IncludeSaved 
+            | Self::IncludeDeleted 
+            | Self::SettingsSaved 
+            | Self::JobAccepted { .. } 
+            | Self::JobRunning { .. } 
+            | Self::JobCompleted { .. } 
             | Self::SaveSuccess { .. } 
             | Self::LoadSuccess { .. } 
-            | Self::DeleteSuccess { .. } => StatusLevel::Info,
+            | Self::DeleteSuccess { .. } 
+            | Self::DuplicateSuccess { .. } 
+            | Self::RestoreSuccess { .. } 
+            | Self::TrashEmptied 
+            | Self::MoveSuccess { .. } 
+            | Self::BulkDeleteSuccess { .. } => StatusLevel::Info,
             
             // WARNING
             Self::ValidationEmpty 
             | Self::ValidationTextLimit { .. } 
+            | Self::ValidationTextNearLimit { .. } 
+            | Self::RequestTooLarge { .. } 
+            | Self::RasterizedExport 
+            | Self::DeletedWithoutTrash { .. } 
+            | Self::LargeSvg { .. } 
+            | Self::SvgDowngraded { .. } 
+            | Self::StylePolicyViolation { .. } 
+            | Self::StylePolicyWarning { .. } 
+            | Self::FontNotAllowed { .. } 
+            | Self::PostProcessorUnknown { .. } 
+            | Self::PostProcessorSkipped { .. } 
+            | Self::ImportError { .. } 
+            | Self::RenameError { .. } 
+            | Self::ExtractError { .. } 
+            | Self::DocumentNotFound { .. } 
+            | Self::ShareLinkInvalid 
+            | Self::ShareLinkExpired { .. } 
+            | Self::DocumentAccessDenied { .. } 
+            | Self::DocumentOwnerRequired 
+            | Self::FolderNotFound { .. } 
+            | Self::FolderNameEmpty 
+            | Self::FolderCycle 
+            | Self::DocumentConflict { .. } 
+            | Self::IncludeNotFound { .. } 
+            | Self::IncludeNameInvalid { .. } 
+            | Self::IncludeFileLimit { .. } 
+            | Self::IncludeStorageFull { .. } 
             | Self::StorageInputLimit { .. } 
             | Self::StorageSlotLimit { .. } 
-            | Self::SizeLimit { .. } => StatusLevel::Warning,
+            | Self::SizeLimit { .. } 
+            | Self::ArchiveFileLimit { .. } 
+            | Self::JobNotFound { .. } 
+            | Self::JobQueueFull { .. } 
+            | Self::RenderQueueFull { .. } 
+            | Self::QuotaExceeded { .. } 
+            | Self::Maintenance 
+            | Self::AdminAccessDenied 
+            | Self::AuthenticationRequired => StatusLevel::Warning,
             
             // ERROR
             _ => StatusLevel::Error,
@@ -262,7 +864,7 @@ impl ErrorCode {
 }
 
 /// Processing result information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProcessResult {
     /// Status level (INFO/WARNING/ERROR)
     pub level: StatusLevel,
@@ -322,7 +924,7 @@ impl DiagramImage {
         }
         
         // Check max dimensions (8192 x 8192)
-        const MAX_DIMENSION: u32 = 8192;
+        const MAX_DIMENSION: u32 = crate::image_size::MAX_IMAGE_DIMENSION;
         if self.dimensions.0 > MAX_DIMENSION || self.dimensions.1 > MAX_DIMENSION {
             return Err(ImageError::DimensionsTooLarge(self.dimensions));
         }
@@ -336,6 +938,8 @@ impl DiagramImage {
         let mime_type = match self.format {
             ImageFormat::Png => "image/png",
             ImageFormat::Svg => "image/svg+xml",
+            ImageFormat::Txt => "text/plain;charset=utf-8",
+            ImageFormat::Pdf => "application/pdf",
         };
         ///バイナリデータ（Vec<u8>）をBase64文字列に変換
         use base64::Engine;
@@ -371,11 +975,37 @@ pub struct StorageSlot {
     
     /// Save timestamp (Unix timestamp)
     pub saved_at: i64,
+    
+    /// Pinned to the top of the slot list
+    #[serde(default)]
+    pub pinned: bool,
+    
+    /// Storage schema version the slot was written with (missing before version 2)
+    #[serde(default = "StorageSlot::legacy_schema_version")]
+    pub schema_version: u32,
 }
 
 impl StorageSlot {
     pub const MAX_SLOTS: u8 = 10;
     
+    /// Current storage schema version
+    pub const SCHEMA_VERSION: u32 = 2;
+    
+    /// Create an unpinned slot in the current schema
+    pub fn new(slot_number: u8, document: PlantUMLDocument, saved_at: i64) -> Self {
+        Self {
+            slot_number,
+            document,
+            saved_at,
+            pinned: false,
+            schema_version: Self::SCHEMA_VERSION,
+        }
+    }
+    
+    fn legacy_schema_version() -> u32 {
+        1
+    }
+    
     /// Validate slot number
     pub fn validate_slot_number(slot_number: u8) -> Result<(), StorageError> {
         if !(1..=Self::MAX_SLOTS).contains(&slot_number) {
@@ -404,6 +1034,24 @@ pub enum StorageError {
     
     #[error("スロット{0}は空です")]
     SlotEmpty(u8),
+    
+    #[error("スロット{0}は使用中です")]
+    SlotOccupied(u8),
+    
+    #[error("ゴミ箱に見つかりません（保存期間が過ぎた可能性があります）")]
+    NotInTrash,
+    
+    /// A save would not fit in the space left (checked before writing)
+    #[error(
+        "空き容量が足りません（必要: 約{}KB、空き: 約{}KB）。不要なスロットを削除してゴミ箱を空にするか、コメントや空行を減らして内容を小さくしてください",
+        .needed.div_ceil(1024),
+        .available / 1024
+    )]
+    InsufficientSpace { needed: usize, available: usize },
+    
+    /// The slot holds data that cannot be read as a saved document
+    #[error("スロット{slot}のデータが壊れています: {reason}")]
+    SlotCorrupted { slot: u8, reason: String },
 }
 
 /// API Request: POST /api/v1/convert
@@ -414,6 +1062,30 @@ pub struct ConvertRequest {
     
     /// Output image format
     pub format: ImageFormat,
+
+    /// Resolution preset (applied by the export endpoints only)
+    #[serde(default, skip_serializing_if = "ExportScale::is_default")]
+    pub scale: ExportScale,
+
+    /// Default font family (`skinparam defaultFontName`), one of GET /api/v1/fonts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+
+    /// Strip run-dependent metadata so unchanged sources export to identical
+    /// files (export endpoints only, see `deterministic`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deterministic: bool,
+
+    /// Embed the source in the exported PNG or SVG so it can be opened again
+    /// (export endpoints only, see `embed`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub embed_source: bool,
+
+    /// Post-processors to run on the exported image, in order, instead of
+    /// the server's default pipeline (export endpoints only, e.g.
+    /// `["optimize", "embed-source"]`; GET /api/v1/export/pipeline lists them)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<Vec<String>>,
 }
 
 impl ConvertRequest {
@@ -424,6 +1096,39 @@ impl ConvertRequest {
     }
 }
 
+/// API Response: GET /api/v1/fonts
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FontListResponse {
+    /// Font families the server allows for the `font` render option
+    pub fonts: Vec<String>,
+}
+
+/// Example diagram bundled with the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    /// Stable identifier (e.g. "sequence-login")
+    pub id: String,
+    pub title: String,
+    /// Category shown as a filter in the example browser
+    pub category: String,
+    pub description: String,
+    pub plantuml_text: String,
+    /// Path of the rendered SVG thumbnail, relative to the server
+    pub thumbnail_url: String,
+}
+
+/// API Response: GET /api/v1/examples
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExampleListResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+
+    /// Categories in display order
+    pub categories: Vec<String>,
+
+    pub examples: Vec<Example>,
+}
+
 /// API Response: POST /api/v1/convert
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConvertResponse {
@@ -433,6 +1138,10 @@ pub struct ConvertResponse {
     /// Binary image data (optional, only present on success)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_data: Option<Vec<u8>>,
+    
+    /// File name chosen by the export pipeline's `rename` step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
 }
 
 impl ConvertResponse {
@@ -441,6 +1150,7 @@ impl ConvertResponse {
         Self {
             result: ProcessResult::new(code),
             image_data: Some(image_data),
+            file_name: None,
         }
     }
     
@@ -449,7 +1159,744 @@ impl ConvertResponse {
         Self {
             result: ProcessResult::new(code),
             image_data: None,
+            file_name: None,
         }
     }
 }
 
+
+/// API Request: POST /api/v1/import/openapi
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenApiImportRequest {
+    /// OpenAPI document (JSON or YAML)
+    pub spec: String,
+    
+    /// Operations to include (operationId or "METHOD /path"); empty means all
+    #[serde(default)]
+    pub operations: Vec<String>,
+    
+    /// Only list the operations, without generating a diagram
+    #[serde(default)]
+    pub list_only: bool,
+}
+
+/// Operation listed in an OpenAPI document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenApiOperation {
+    /// operationId, or "METHOD /path" when not defined
+    pub id: String,
+    
+    /// HTTP method (uppercase)
+    pub method: String,
+    
+    /// Path template
+    pub path: String,
+    
+    /// Operation summary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// API Response: POST /api/v1/import/openapi
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OpenApiImportResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// All operations found in the document
+    #[serde(default)]
+    pub operations: Vec<OpenApiOperation>,
+    
+    /// Generated sequence diagram (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plantuml_text: Option<String>,
+}
+
+/// API Request: POST /api/v1/import/url
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiagramUrlImportRequest {
+    /// Image URL from a PlantUML server, or only its encoded part
+    pub url: String,
+}
+
+/// API Response: POST /api/v1/import/url
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiagramUrlImportResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Source decoded from the URL (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plantuml_text: Option<String>,
+}
+
+/// Severity of a diagnostic, numbered as in the Language Server Protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+}
+
+impl From<DiagnosticSeverity> for u8 {
+    fn from(severity: DiagnosticSeverity) -> Self {
+        severity as u8
+    }
+}
+
+impl TryFrom<u8> for DiagnosticSeverity {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, String> {
+        match value {
+            1 => Ok(DiagnosticSeverity::Error),
+            2 => Ok(DiagnosticSeverity::Warning),
+            3 => Ok(DiagnosticSeverity::Information),
+            _ => Err(format!("unknown diagnostic severity: {}", value)),
+        }
+    }
+}
+
+/// Position in a source, zero-based; `character` counts UTF-16 code units as in LSP
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// Range in a source, end exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticRange {
+    pub start: DiagnosticPosition,
+    pub end: DiagnosticPosition,
+}
+
+/// Problem found in a PlantUML source, shaped like an LSP Diagnostic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub range: DiagnosticRange,
+    pub severity: DiagnosticSeverity,
+    
+    /// Stable identifier of the check (e.g. "missing-end")
+    pub code: String,
+    
+    /// Always "plantuml-editor"
+    pub source: String,
+    
+    pub message: String,
+}
+
+/// API Request: POST /api/v1/diagnostics
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiagnosticsRequest {
+    /// PlantUML source to check
+    pub plantuml_text: String,
+    
+    /// Also ask the PlantUML server for syntax errors (default true)
+    #[serde(default = "default_check_syntax")]
+    pub check_syntax: bool,
+}
+
+fn default_check_syntax() -> bool {
+    true
+}
+
+/// API Response: POST /api/v1/diagnostics
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiagnosticsResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Problems found, in source order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Rough size class of a diagram, from its complexity score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComplexityLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl ComplexityLevel {
+    /// Label for the stats panel
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Low => "小",
+            Self::Medium => "中",
+            Self::High => "大",
+        }
+    }
+}
+
+/// Structure statistics of a PlantUML source (see `stats::analyze`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagramAnalysis {
+    /// "sequence", "class", "activity", "uml" (other), or the `@start` type
+    /// such as "mindmap" or "json"
+    pub diagram_type: String,
+    pub pages: usize,
+    pub lines: usize,
+    pub participants: usize,
+    pub messages: usize,
+    pub classes: usize,
+    pub notes: usize,
+    /// Elements, arrows and notes together; CI scripts can compare it to a limit
+    pub complexity: usize,
+    pub complexity_level: ComplexityLevel,
+}
+
+/// API Request: POST /api/v1/analyze
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalyzeRequest {
+    /// PlantUML source to analyze
+    pub plantuml_text: String,
+}
+
+/// API Response: POST /api/v1/analyze
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalyzeResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Statistics (absent when the source failed validation)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<DiagramAnalysis>,
+}
+
+/// Manifest written to manifest.json in the archive returned by POST /api/v1/export/archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Output image format
+    pub format: ImageFormat,
+    
+    /// One entry per uploaded file, in upload order
+    pub files: Vec<ArchiveManifestEntry>,
+}
+
+/// Per-file result in an archive manifest
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveManifestEntry {
+    /// Uploaded file name
+    pub source: String,
+    
+    /// Image file name inside the archive (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    
+    /// Processing result information
+    pub result: ProcessResult,
+}
+
+/// API Request: POST /api/v1/documents, PUT /api/v1/documents/{id}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentRequest {
+    /// PlantUML text content
+    pub content: String,
+    
+    /// Optional title
+    #[serde(default)]
+    pub title: Option<String>,
+    
+    /// Version the edit started from (PUT only); a newer stored version
+    /// rejects the update instead of overwriting someone else's changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_version: Option<u64>,
+}
+
+/// API Request: PATCH /api/v1/documents/{id}
+///
+/// An edit sent as the lines that changed (`diff::line_patch`) instead of the
+/// whole text, applied to the stored text of `base_version`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentPatchRequest {
+    pub patch: LinePatch,
+    
+    /// Optional title
+    #[serde(default)]
+    pub title: Option<String>,
+    
+    /// Version the patch was made from
+    pub base_version: u64,
+}
+
+/// API Response: single server document
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Stored document (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document: Option<PlantUMLDocument>,
+    
+    /// Caller's role on the document (GET /api/v1/documents/{id} only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<DocumentRole>,
+}
+
+/// Earlier saved version of a server document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DocumentRevision {
+    pub version: u64,
+    
+    /// PlantUML text content of this version
+    pub content: String,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    
+    /// When this version was saved (Unix timestamp)
+    pub updated_at: i64,
+}
+
+/// Written to document.json in the archive returned by GET /api/v1/documents/{id}/archive
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentArchive {
+    /// Layout version of the archive
+    pub archive_version: u32,
+    
+    /// Export timestamp (Unix timestamp)
+    pub exported_at: i64,
+    
+    /// Current version of the document
+    pub document: PlantUMLDocument,
+    
+    /// Earlier versions, oldest first
+    #[serde(default)]
+    pub revisions: Vec<DocumentRevision>,
+    
+    /// Rendered images of the current version inside the archive
+    #[serde(default)]
+    pub images: Vec<String>,
+}
+
+/// API Request: POST /api/v1/folders, PUT /api/v1/folders/{id}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderRequest {
+    /// Folder name
+    pub name: String,
+    
+    /// Enclosing folder (None for the top level)
+    #[serde(default)]
+    pub parent: Option<FolderId>,
+}
+
+/// API Response: single folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Stored folder (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<Folder>,
+}
+
+/// API Response: GET /api/v1/folders
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderListResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// All folders, sorted by name
+    pub folders: Vec<Folder>,
+}
+
+/// API Request: PUT /api/v1/includes/{name}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncludeRequest {
+    /// File content
+    pub content: String,
+    
+    /// Share the file with every user instead of keeping it private to the caller
+    ///
+    /// Shared files are written through /api/v1/admin/includes/{name}; the
+    /// user endpoint rejects `shared` with AdminAccessDenied.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+/// API Response: PUT /api/v1/includes/{name}, DELETE /api/v1/includes/{name}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncludeResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Stored file (only present after an upload)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<IncludeFile>,
+}
+
+/// API Response: GET /api/v1/includes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncludeListResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Shared files and the caller's own files, sorted by name
+    pub includes: Vec<IncludeFile>,
+}
+
+/// Pause after typing before the preview is rendered when the user has not
+/// chosen one (ms)
+pub const DEFAULT_RENDER_PAUSE_MS: u32 = 300;
+
+/// Editor preferences that follow the user between browsers
+///
+/// Fields missing from stored or received settings take their defaults, so
+/// settings saved by an older version still load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// Preview format (PNG keeps very large diagrams responsive)
+    pub preview_format: ImageFormat,
+    
+    /// Font family for previews and exports (None for the server default)
+    pub font: Option<String>,
+    
+    /// Keep the last good image dimmed when a conversion fails
+    pub keep_stale_preview: bool,
+    
+    /// Spellcheck notes and labels in the editor
+    pub spellcheck: bool,
+    
+    /// Spaces per indentation level, 0 for a tab
+    pub indent_width: usize,
+    
+    /// Enter after a message starts the next one with the same participants
+    pub prefill_messages: bool,
+    
+    /// Pause after typing before the preview is rendered (ms)
+    pub render_pause_ms: u32,
+    
+    /// Normalize the editor text when saving (see `normalize`)
+    pub normalize_on_save: bool,
+    
+    /// Normalizing on save also removes trailing whitespace
+    pub trim_trailing_whitespace: bool,
+    
+    /// Embed the source in exported images (see `embed`)
+    pub embed_source: bool,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            preview_format: ImageFormat::Svg,
+            font: None,
+            keep_stale_preview: true,
+            spellcheck: false,
+            indent_width: 2,
+            prefill_messages: false,
+            render_pause_ms: DEFAULT_RENDER_PAUSE_MS,
+            normalize_on_save: false,
+            trim_trailing_whitespace: true,
+            embed_source: false,
+        }
+    }
+}
+
+/// Settings and when they were changed, for last-write-wins sync
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsSnapshot {
+    pub settings: UserSettings,
+    
+    /// When the settings were changed (Unix timestamp in milliseconds, as
+    /// measured by the browser that changed them; 0 for never)
+    pub updated_at: i64,
+}
+
+impl SettingsSnapshot {
+    /// The more recently changed of two snapshots (`self` on a tie)
+    pub fn newer(self, other: SettingsSnapshot) -> SettingsSnapshot {
+        if other.updated_at > self.updated_at {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// API Response: GET /api/v1/settings, PUT /api/v1/settings (the request is
+/// a SettingsSnapshot)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Settings in effect for the caller (None before they saved any)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<SettingsSnapshot>,
+}
+
+/// API Request: PUT /api/v1/documents/{id}/folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveDocumentRequest {
+    /// Destination folder (None for the top level)
+    #[serde(default)]
+    pub folder: Option<FolderId>,
+}
+
+/// Document matching a search query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Matching document
+    pub id: DocumentId,
+    
+    /// Document title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    
+    /// Folder holding the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<FolderId>,
+    
+    /// Relevance (higher is better; only comparable within one response)
+    pub score: f32,
+    
+    /// Line of the source containing a match
+    pub snippet: String,
+    
+    /// Matched parts of the snippet as [start, end) character offsets
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// API Response: GET /api/v1/documents/search
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Matching documents the caller can view, best match first
+    pub hits: Vec<SearchHit>,
+}
+
+/// Kind of change recorded in the activity feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ActivityKind {
+    /// Verb shown in the feed
+    pub fn label(self) -> &'static str {
+        match self {
+            ActivityKind::Created => "作成",
+            ActivityKind::Updated => "更新",
+            ActivityKind::Deleted => "削除",
+        }
+    }
+}
+
+/// Change to a server document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub kind: ActivityKind,
+    
+    /// Changed document
+    pub document_id: DocumentId,
+    
+    /// Document title at the time of the change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    
+    /// User who made the change (None for anonymous requests)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    
+    /// Time of the change (Unix timestamp)
+    pub at: i64,
+}
+
+/// API Response: GET /api/v1/activity
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Recent changes the caller can see, newest first
+    pub entries: Vec<ActivityEntry>,
+}
+
+/// API Request: PUT /api/v1/documents/{id}/roles
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleRequest {
+    /// User name as sent by the authenticating proxy
+    pub user: String,
+    
+    /// New role (None removes the user from the document)
+    #[serde(default)]
+    pub role: Option<DocumentRole>,
+}
+
+/// API Response: GET/PUT /api/v1/documents/{id}/roles
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RolesResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Members of the document (empty for documents open to everyone)
+    pub roles: std::collections::BTreeMap<String, DocumentRole>,
+}
+
+/// API Response: GET /api/v1/documents
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DocumentListResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Stored documents, most recently updated first
+    pub documents: Vec<PlantUMLDocument>,
+}
+
+/// API Request: POST /api/v1/documents/{id}/share
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ShareRequest {
+    /// Link lifetime in seconds (the link never expires when omitted)
+    #[serde(default)]
+    pub expires_in_secs: Option<u64>,
+}
+
+/// Public read-only link to a server document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareLink {
+    /// Signed token identifying the document and expiry
+    pub token: String,
+    
+    /// Link to the rendered document (`/share/{token}`)
+    pub url: String,
+    
+    /// Expiry as a Unix timestamp (None for links that never expire)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+/// API Response: POST /api/v1/documents/{id}/share
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Created link (only present on success)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<ShareLink>,
+}
+
+/// Source file in a batch export request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchExportItem {
+    /// File name used inside the resulting archive
+    pub name: String,
+    
+    /// PlantUML text content
+    pub plantuml_text: String,
+}
+
+/// API Request: POST /api/v1/jobs/export
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchExportRequest {
+    /// Output image format
+    pub format: ImageFormat,
+    
+    /// Sources to render, in archive order
+    pub items: Vec<BatchExportItem>,
+}
+
+/// Background job state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Background job status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobInfo {
+    /// Job ID
+    pub id: String,
+    
+    pub state: JobState,
+    
+    /// Number of items in the job
+    pub total: usize,
+    
+    /// Items rendered successfully so far
+    pub succeeded: usize,
+    
+    /// Items that could not be rendered so far
+    pub failed: usize,
+    
+    /// Creation timestamp (Unix timestamp)
+    pub created_at: i64,
+    
+    /// Completion timestamp (Unix timestamp)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<i64>,
+    
+    /// Reason the job failed as a whole (only present in the failed state)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JobInfo {
+    /// ErrorCode describing the current state
+    pub fn status_code(&self) -> ErrorCode {
+        match self.state {
+            JobState::Queued | JobState::Running => ErrorCode::JobRunning {
+                finished: self.succeeded + self.failed,
+                total: self.total,
+            },
+            JobState::Completed => ErrorCode::JobCompleted {
+                succeeded: self.succeeded,
+                failed: self.failed,
+            },
+            JobState::Failed => ErrorCode::JobFailed {
+                reason: self.error.clone().unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// API Response: POST /api/v1/jobs/export, GET /api/v1/jobs/{id}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobResponse {
+    /// Processing result information
+    pub result: ProcessResult,
+    
+    /// Job status (only present when the job exists)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<JobInfo>,
+}
+
+/// Progress event streamed by GET /api/v1/jobs/{id}/events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    /// Item accepted and waiting to be rendered
+    Queued { index: usize, name: String },
+    /// Item is being rendered
+    Rendering { index: usize, name: String },
+    /// Item rendered successfully
+    Done { index: usize, name: String },
+    /// Item could not be rendered
+    Failed { index: usize, name: String, result: ProcessResult },
+    /// All items processed; the archive is ready for download
+    Completed { succeeded: usize, failed: usize },
+}
+
+impl ProgressEvent {
+    /// Whether this is the last event of the stream
+    pub fn is_final(&self) -> bool {
+        matches!(self, ProgressEvent::Completed { .. })
+    }
+}
@@ -0,0 +1,85 @@
+// Normalization of invisible differences in PlantUML source
+//
+// Sources pasted from other tools often start with a byte order mark, use
+// Windows line endings or carry trailing spaces. PlantUML ignores all of
+// them, but they change the encoded source sent to the PlantUML server (so an
+// unchanged diagram looks new) and show up as changes in diffs and conflicts.
+// Validation and rendering always work on the normalized source; the editor
+// can also normalize the text itself when saving (see the web UI settings).
+
+use std::borrow::Cow;
+
+const BOM: char = '\u{feff}';
+
+/// What `normalize` changes besides the BOM and the line endings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    /// Remove spaces and tabs at the end of each line
+    pub trim_trailing_whitespace: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        Self {
+            trim_trailing_whitespace: true,
+        }
+    }
+}
+
+/// `text` without a leading BOM, with `\r\n` and lone `\r` line endings
+/// turned into `\n` and, if enabled, without trailing whitespace
+///
+/// Line numbers are unchanged (a lone `\r` already ends a line for PlantUML).
+/// Borrows `text` when there is nothing to change.
+pub fn normalize(text: &str, options: NormalizeOptions) -> Cow<'_, str> {
+    let text = text.strip_prefix(BOM).unwrap_or(text);
+    let has_trailing_whitespace = |text: &str| {
+        text.split(['\n', '\r'])
+            .any(|line| line.len() != line.trim_end().len())
+    };
+    let changes = text.contains('\r')
+        || (options.trim_trailing_whitespace && has_trailing_whitespace(text));
+    if !changes {
+        return Cow::Borrowed(text);
+    }
+
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    if !options.trim_trailing_whitespace {
+        return Cow::Owned(text);
+    }
+    Cow::Owned(text.split('\n').map(str::trim_end).collect::<Vec<_>>().join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let text = "\u{feff}@startuml  \r\nAlice -> Bob : hi\t\rBob -> Alice\r\n@enduml\r\n";
+        assert_eq!(
+            normalize(text, NormalizeOptions::default()),
+            "@startuml\nAlice -> Bob : hi\nBob -> Alice\n@enduml\n"
+        );
+
+        let keep_spaces = NormalizeOptions {
+            trim_trailing_whitespace: false,
+        };
+        assert_eq!(
+            normalize("@startuml  \r\n@enduml", keep_spaces),
+            "@startuml  \n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_normalize_borrows_clean_text() {
+        let text = "@startuml\n  Alice -> Bob\n@enduml\n";
+        assert!(matches!(normalize(text, NormalizeOptions::default()), Cow::Borrowed(_)));
+        // Only the BOM to remove: still a slice of the input
+        let text = "\u{feff}@startuml\n@enduml";
+        assert!(matches!(
+            normalize(text, NormalizeOptions::default()),
+            Cow::Borrowed("@startuml\n@enduml")
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+// Size optimization of exported images
+//
+// PlantUML output carries data no viewer needs: comments with the encoded
+// source in SVG files, indentation between tags, and text and time chunks in
+// PNG files. `optimize` removes it without changing how the image looks.
+// Chunks that affect colors or the physical size of a PNG are kept.
+
+use crate::models::ImageFormat;
+use crate::png::{png_chunks, write_png_chunk, PNG_SIGNATURE};
+
+/// Ancillary PNG chunks that change how the image is displayed
+const PNG_DISPLAY_CHUNKS: &[&[u8; 4]] = &[b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"iCCP", b"sBIT", b"pHYs"];
+
+/// Smaller image that displays the same
+///
+/// Data that is not a well-formed PNG or UTF-8 SVG is returned unchanged.
+pub fn optimize(format: ImageFormat, data: &[u8]) -> Vec<u8> {
+    match format {
+        ImageFormat::Svg => match std::str::from_utf8(data) {
+            Ok(svg) => optimize_svg(svg).into_bytes(),
+            Err(_) => data.to_vec(),
+        },
+        ImageFormat::Png => optimize_png(data).unwrap_or_else(|| data.to_vec()),
+        ImageFormat::Txt | ImageFormat::Pdf => data.to_vec(),
+    }
+}
+
+/// SVG without comments and without whitespace between tags that spans lines
+/// (indentation; spaces within a line may be text)
+fn optimize_svg(svg: &str) -> String {
+    let is_indentation = |text: &str| text.contains('\n') && text.trim().is_empty();
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        let text = &rest[..start];
+        if !is_indentation(text) {
+            out.push_str(text);
+        }
+        let tail = &rest[start..];
+        if tail.starts_with("<!--") {
+            rest = tail.find("-->").map_or("", |end| &tail[end + 3..]);
+            continue;
+        }
+        let end = tail.find('>').map_or(tail.len(), |end| end + 1);
+        out.push_str(&tail[..end]);
+        rest = &tail[end..];
+    }
+    if !is_indentation(rest) {
+        out.push_str(rest);
+    }
+    out
+}
+
+/// PNG with only critical chunks and those in `PNG_DISPLAY_CHUNKS`
+fn optimize_png(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (kind, chunk_data) in png_chunks(data)? {
+        // Critical chunks have an uppercase first letter
+        let critical = kind[0].is_ascii_uppercase();
+        if critical || PNG_DISPLAY_CHUNKS.iter().any(|display| kind == *display) {
+            write_png_chunk(&mut out, kind, chunk_data);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_optimize_svg() {
+        let svg = "<?xml version=\"1.0\"?>\n<svg>\n  <!--SRC=[abc]-->\n  <g>\n    <text> A </text> <text>B</text>\n  </g>\n</svg>\n";
+        assert_eq!(
+            optimize(ImageFormat::Svg, svg.as_bytes()),
+            b"<?xml version=\"1.0\"?><svg><g><text> A </text> <text>B</text></g></svg>".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_optimize_png() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut png, b"IHDR", &[1; 13]);
+        write_png_chunk(&mut png, b"tEXt", b"plantuml\0@startuml");
+        write_png_chunk(&mut png, b"pHYs", &[3; 9]);
+        write_png_chunk(&mut png, b"IDAT", &[2; 8]);
+        write_png_chunk(&mut png, b"tIME", &[7; 7]);
+        write_png_chunk(&mut png, b"IEND", &[]);
+
+        let mut expected = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut expected, b"IHDR", &[1; 13]);
+        write_png_chunk(&mut expected, b"pHYs", &[3; 9]);
+        write_png_chunk(&mut expected, b"IDAT", &[2; 8]);
+        write_png_chunk(&mut expected, b"IEND", &[]);
+        assert_eq!(optimize(ImageFormat::Png, &png), expected);
+        assert_eq!(optimize(ImageFormat::Png, b"not a png"), b"not a png".to_vec());
+    }
+}
@@ -0,0 +1,387 @@
+// Outline of the participants in a sequence diagram, for structured editing
+//
+// Each declared participant keeps the span of its declaration line, so the
+// editor can rewrite that line from form fields (keyword, alias, stereotype,
+// color) without touching the rest of the source. Participants only used in
+// messages get a declaration line when first edited. Like the stats scan this
+// is line-based; parts of a declaration it does not understand (e.g.
+// `order 10`) are kept as they are.
+
+use crate::metadata::directive_argument;
+use crate::stats::{is_arrow, PARTICIPANT_KEYWORDS};
+
+/// Editable parts of a participant declaration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParticipantStyle {
+    /// `participant`, `actor`, `database`, ...
+    pub keyword: String,
+    pub alias: Option<String>,
+    /// Without the `<<` `>>` markers
+    pub stereotype: Option<String>,
+    /// With the leading `#` (e.g. "#LightBlue")
+    pub color: Option<String>,
+}
+
+/// Declaration line of a participant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Declaration {
+    /// 0-based line number
+    pub line: usize,
+    /// Byte range of the line, without its line break
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Participant {
+    /// Name shown in the diagram
+    pub name: String,
+    pub style: ParticipantStyle,
+    /// None for participants only used in messages
+    pub declaration: Option<Declaration>,
+    /// Unrecognized end of the declaration, kept on rewrite
+    rest: String,
+}
+
+impl Participant {
+    /// How messages refer to the participant
+    pub fn id(&self) -> String {
+        self.style
+            .alias
+            .clone()
+            .unwrap_or_else(|| quote_if_needed(&self.name))
+    }
+
+    fn declaration_line(&self, style: &ParticipantStyle) -> String {
+        let mut line = format!("{} {}", style.keyword, quote_if_needed(&self.name));
+        if let Some(alias) = &style.alias {
+            line.push_str(&format!(" as {}", alias));
+        }
+        if let Some(stereotype) = &style.stereotype {
+            line.push_str(&format!(" <<{}>>", stereotype));
+        }
+        if let Some(color) = &style.color {
+            line.push_str(&format!(" {}", color));
+        }
+        if !self.rest.is_empty() {
+            line.push(' ');
+            line.push_str(&self.rest);
+        }
+        line
+    }
+}
+
+/// Participants in order of declaration, then those only used in messages
+pub fn participants(content: &str) -> Vec<Participant> {
+    let mut declared = Vec::new();
+    let mut used = Vec::new();
+    let mut offset = 0;
+    for (index, raw) in content.split_inclusive('\n').enumerate() {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        if let Some(mut participant) = parse_declaration(line.trim()) {
+            participant.declaration = Some(Declaration {
+                line: index,
+                start: offset,
+                end: offset + line.len(),
+            });
+            declared.push(participant);
+        } else if let Some(endpoints) = message_endpoints(line) {
+            used.extend(endpoints);
+        }
+        offset += raw.len();
+    }
+
+    for id in used {
+        let known = declared
+            .iter()
+            .any(|participant: &Participant| participant.id() == id);
+        if !known {
+            declared.push(Participant {
+                name: id.trim_matches('"').to_string(),
+                style: ParticipantStyle {
+                    keyword: "participant".to_string(),
+                    ..ParticipantStyle::default()
+                },
+                declaration: None,
+                rest: String::new(),
+            });
+        }
+    }
+    declared
+}
+
+/// Source with `participant` declared as `style`
+///
+/// When the alias changes, messages, notes and activations referring to the
+/// participant are updated to the new name. A participant without a
+/// declaration gets one after the last declaration (or the `@start` line).
+pub fn update_participant(content: &str, participant: &Participant, style: &ParticipantStyle) -> String {
+    let style = ParticipantStyle {
+        keyword: style.keyword.trim().to_string(),
+        alias: non_empty(&style.alias),
+        stereotype: non_empty(&style.stereotype).map(|stereotype| {
+            stereotype.trim_start_matches("<<").trim_end_matches(">>").trim().to_string()
+        }),
+        color: non_empty(&style.color).map(|color| {
+            if color.starts_with('#') {
+                color
+            } else {
+                format!("#{}", color)
+            }
+        }),
+    };
+    let declaration = participant.declaration_line(&style);
+    let old_id = participant.id();
+    let new_id = Participant {
+        style: style.clone(),
+        ..participant.clone()
+    }
+    .id();
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    for (index, line) in lines.iter_mut().enumerate() {
+        if participant.declaration.is_some_and(|declared| declared.line == index) {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            *line = format!("{}{}", indent, declaration);
+        } else if old_id != new_id {
+            *line = rename_reference(line, &old_id, &new_id);
+        }
+    }
+
+    if participant.declaration.is_none() {
+        let after = participants(content)
+            .iter()
+            .filter_map(|participant| participant.declaration)
+            .map(|declared| declared.line + 1)
+            .next_back()
+            .or_else(|| {
+                lines
+                    .iter()
+                    .position(|line| line.trim_start().to_lowercase().starts_with("@start"))
+                    .map(|line| line + 1)
+            })
+            .unwrap_or(0);
+        lines.insert(after, declaration);
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+fn parse_declaration(line: &str) -> Option<Participant> {
+    let (keyword, rest) = PARTICIPANT_KEYWORDS
+        .iter()
+        .find_map(|keyword| Some((*keyword, directive_argument(line, keyword)?)))?;
+    let (mut name, mut rest) = take_name(rest)?;
+
+    let mut style = ParticipantStyle {
+        keyword: line[..keyword.len()].to_string(),
+        ..ParticipantStyle::default()
+    };
+    let mut unknown = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = directive_argument(rest, "as") {
+            let (alias, after) = take_name(after)?;
+            // `participant Alice as "Long name"` declares the same as
+            // `participant "Long name" as Alice`
+            if alias.starts_with('"') && !name.starts_with('"') {
+                style.alias = Some(name);
+                name = alias;
+            } else {
+                style.alias = Some(alias);
+            }
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("<<") {
+            let end = after.find(">>")?;
+            style.stereotype = Some(after[..end].trim().to_string());
+            rest = &after[end + 2..];
+        } else if rest.starts_with('#') {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            style.color = Some(rest[..end].to_string());
+            rest = &rest[end..];
+        } else {
+            unknown.push(rest.to_string());
+            break;
+        }
+    }
+
+    Some(Participant {
+        name: name.trim_matches('"').to_string(),
+        style,
+        declaration: None,
+        rest: unknown.join(" "),
+    })
+}
+
+/// Leading quoted or bare name, and the text after it
+fn take_name(text: &str) -> Option<(String, &str)> {
+    let text = text.trim_start();
+    if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        return Some((format!("\"{}\"", &quoted[..end]), &quoted[end + 1..]));
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '<' || c == '#')
+        .unwrap_or(text.len());
+    (end > 0).then(|| (text[..end].to_string(), &text[end..]))
+}
+
+/// Participants on both sides of an arrow, as written
+fn message_endpoints(line: &str) -> Option<Vec<String>> {
+    let (head, _) = split_label(line);
+    let tokens: Vec<&str> = head.split_whitespace().collect();
+    let arrow = tokens.iter().position(|token| is_arrow(token))?;
+    let endpoint = |index: Option<usize>| {
+        let token = tokens.get(index?)?.trim_matches(|c| c == '[' || c == ']');
+        (!token.is_empty()).then(|| token.to_string())
+    };
+    Some(
+        [endpoint(arrow.checked_sub(1)), endpoint(Some(arrow + 1))]
+            .into_iter()
+            .flatten()
+            .collect(),
+    )
+}
+
+/// Line before and after the first `:` outside quotes
+pub(crate) fn split_label(line: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return (&line[..index], &line[index..]),
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+/// Replace `old` by `new` as a whole word in the part of `line` that names
+/// participants (before the label)
+fn rename_reference(line: &str, old: &str, new: &str) -> String {
+    let trimmed = line.trim_start().to_lowercase();
+    let first = trimmed.split_whitespace().next().unwrap_or_default();
+    let refers = matches!(
+        first,
+        "activate" | "deactivate" | "destroy" | "create" | "note" | "hnote" | "rnote" | "ref"
+    ) || message_endpoints(line).is_some();
+    if !refers {
+        return line.to_string();
+    }
+
+    let (head, label) = split_label(line);
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let mut result = String::new();
+    let mut rest = head;
+    while let Some(found) = rest.find(old) {
+        let before = rest[..found].chars().next_back();
+        let after = rest[found + old.len()..].chars().next();
+        let whole = !before.is_some_and(is_word) && !after.is_some_and(is_word);
+        result.push_str(&rest[..found]);
+        result.push_str(if whole { new } else { old });
+        rest = &rest[found + old.len()..];
+    }
+    result.push_str(rest);
+    result.push_str(label);
+    result
+}
+
+fn quote_if_needed(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "@startuml\n\
+        actor User\n\
+        participant \"Web Server\" as Web <<Spring>> #LightBlue order 10\n\
+        User -> Web : login\n\
+        Web --> DB : query\n\
+        note right of Web : WebApp\n\
+        @enduml\n";
+
+    #[test]
+    fn test_participants() {
+        let found = participants(SOURCE);
+        assert_eq!(found.len(), 3);
+
+        assert_eq!(found[0].name, "User");
+        assert_eq!(found[0].style.keyword, "actor");
+        assert_eq!(found[0].declaration.unwrap().line, 1);
+
+        let web = &found[1];
+        assert_eq!(web.name, "Web Server");
+        assert_eq!(web.id(), "Web");
+        assert_eq!(web.style.stereotype.as_deref(), Some("Spring"));
+        assert_eq!(web.style.color.as_deref(), Some("#LightBlue"));
+        let declared = web.declaration.unwrap();
+        assert!(SOURCE[declared.start..declared.end].ends_with("order 10"));
+
+        // Only used in a message
+        assert_eq!(found[2].name, "DB");
+        assert_eq!(found[2].declaration, None);
+    }
+
+    #[test]
+    fn test_update_declaration_and_references() {
+        let web = &participants(SOURCE)[1];
+        let style = ParticipantStyle {
+            keyword: "boundary".to_string(),
+            alias: Some("Srv".to_string()),
+            stereotype: None,
+            color: Some("FF8800".to_string()),
+        };
+        let updated = update_participant(SOURCE, web, &style);
+        assert_eq!(
+            updated,
+            "@startuml\n\
+            actor User\n\
+            boundary \"Web Server\" as Srv #FF8800 order 10\n\
+            User -> Srv : login\n\
+            Srv --> DB : query\n\
+            note right of Srv : WebApp\n\
+            @enduml\n"
+        );
+    }
+
+    #[test]
+    fn test_declare_participant_used_in_messages() {
+        let db = &participants(SOURCE)[2];
+        let style = ParticipantStyle {
+            keyword: "database".to_string(),
+            stereotype: Some("<<PostgreSQL>>".to_string()),
+            ..ParticipantStyle::default()
+        };
+        let updated = update_participant(SOURCE, db, &style);
+        assert!(updated.contains("order 10\ndatabase DB <<PostgreSQL>>\nUser -> Web"));
+    }
+
+    #[test]
+    fn test_alias_form_with_quoted_name() {
+        let found = participants("participant Alice as \"Alice Cooper\" #pink");
+        assert_eq!(found[0].name, "Alice Cooper");
+        assert_eq!(found[0].id(), "Alice");
+        assert_eq!(found[0].style.color.as_deref(), Some("#pink"));
+    }
+}
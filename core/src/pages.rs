@@ -0,0 +1,222 @@
+// Multi-page documents
+//
+// PlantUML starts a new page at each `newpage` line. Splitting the source into
+// one standalone document per page lets every page be rendered on its own,
+// with the directives at the top of the document (theme, skinparams, editor
+// pragmas) repeated on each page.
+
+use crate::metadata::{directive_argument, extract_title, file_stem_from_title};
+use crate::models::BatchExportItem;
+
+/// Number of pages in a document (1 for documents without `newpage`)
+pub fn page_count(content: &str) -> usize {
+    1 + content
+        .lines()
+        .filter(|line| directive_argument(line.trim(), "newpage").is_some())
+        .count()
+}
+
+/// Standalone source for every page, in order
+///
+/// `newpage Some title` becomes `title Some title` on the new page. Documents
+/// without `newpage` are returned unchanged as a single page.
+pub fn split_pages(content: &str) -> Vec<String> {
+    if page_count(content) == 1 {
+        return vec![content.to_string()];
+    }
+    page_lines(content)
+        .into_iter()
+        .map(|page| {
+            page.into_iter()
+                .map(|(line, _)| line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Line of the document (0-based) that `line` of page `page` comes from
+///
+/// Lines repeated on every page map to the document's own, so errors the
+/// server reports for a page can be shown in the editor.
+pub fn document_line(content: &str, page: usize, line: usize) -> usize {
+    if page_count(content) == 1 {
+        return line;
+    }
+    let pages = page_lines(content);
+    let Some(lines) = pages.get(page).or(pages.last()) else {
+        return line;
+    };
+    lines
+        .get(line)
+        .or(lines.last())
+        .map_or(line, |&(_, origin)| origin)
+}
+
+/// Lines of every page with the document line each comes from
+fn page_lines(content: &str) -> Vec<Vec<(String, usize)>> {
+    // Lines between @startuml and @enduml (all lines when they are missing)
+    let lines: Vec<&str> = content.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("@start"))
+        .map_or(0, |index| index + 1);
+    let end = lines[start..]
+        .iter()
+        .position(|line| line.trim_start().starts_with("@end"))
+        .map_or(lines.len(), |index| start + index);
+    let body = &lines[start..end];
+
+    // Leading directives apply to every page
+    let preamble_len = body
+        .iter()
+        .take_while(|line| is_preamble(line.trim()))
+        .count();
+
+    let mut pages = vec![Vec::new()];
+    for (index, line) in body.iter().enumerate().skip(preamble_len) {
+        let origin = start + index;
+        match directive_argument(line.trim(), "newpage") {
+            Some(title) => {
+                let mut page = Vec::new();
+                if !title.is_empty() {
+                    page.push((format!("title {}", title), origin));
+                }
+                pages.push(page);
+            }
+            None => pages
+                .last_mut()
+                .expect("at least one page")
+                .push((line.to_string(), origin)),
+        }
+    }
+
+    let first = start.saturating_sub(1);
+    let last = end.min(lines.len().saturating_sub(1));
+    pages
+        .into_iter()
+        .map(|page| {
+            let mut lines = vec![("@startuml".to_string(), first)];
+            lines.extend((0..preamble_len).map(|index| (body[index].to_string(), start + index)));
+            lines.extend(page);
+            lines.push(("@enduml".to_string(), last));
+            lines
+        })
+        .collect()
+}
+
+/// First page whose source differs between two versions of a document
+///
+/// Returns None when every page is unchanged. An edit to the shared
+/// directives at the top changes every page, so it reports page 0.
+pub fn changed_page(old: &str, new: &str) -> Option<usize> {
+    let old_pages = split_pages(old);
+    let new_pages = split_pages(new);
+    (0..old_pages.len().max(new_pages.len()))
+        .find(|&index| old_pages.get(index) != new_pages.get(index))
+}
+
+/// One numbered batch export item per page, for slide decks
+///
+/// Names are `<title>_01.puml`, `<title>_02.puml`, ... (`slide_01.puml`
+/// without an ASCII title), so the rendered files sort in page order.
+pub fn slide_items(content: &str) -> Vec<BatchExportItem> {
+    let stem = extract_title(content)
+        .and_then(|title| file_stem_from_title(&title))
+        .unwrap_or_else(|| "slide".to_string());
+
+    split_pages(content)
+        .into_iter()
+        .enumerate()
+        .map(|(index, plantuml_text)| BatchExportItem {
+            name: format!("{}_{:02}.puml", stem, index + 1),
+            plantuml_text,
+        })
+        .collect()
+}
+
+/// Lines that configure the whole document rather than draw something
+fn is_preamble(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with('!')
+        || line.starts_with('\'')
+        || directive_argument(line, "skinparam").is_some()
+        || directive_argument(line, "scale").is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_page_is_unchanged() {
+        let content = "@startuml\nA -> B\n@enduml";
+        assert_eq!(page_count(content), 1);
+        assert_eq!(split_pages(content), vec![content.to_string()]);
+    }
+
+    #[test]
+    fn test_split_pages_repeats_preamble() {
+        let content = "@startuml\n!theme toy\nskinparam monochrome true\nA -> B\nnewpage\nB -> C\nnewpage Summary\nC -> A\n@enduml";
+        assert_eq!(page_count(content), 3);
+
+        let pages = split_pages(content);
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0], "@startuml\n!theme toy\nskinparam monochrome true\nA -> B\n@enduml");
+        assert_eq!(pages[1], "@startuml\n!theme toy\nskinparam monochrome true\nB -> C\n@enduml");
+        assert_eq!(
+            pages[2],
+            "@startuml\n!theme toy\nskinparam monochrome true\ntitle Summary\nC -> A\n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_document_line() {
+        let content = "@startuml\n!theme toy\nA -> B\nnewpage Summary\nB -> C\n@enduml";
+        // Page 1: @startuml, !theme toy, title Summary, B -> C, @enduml
+        assert_eq!(document_line(content, 1, 1), 1);
+        assert_eq!(document_line(content, 1, 2), 3);
+        assert_eq!(document_line(content, 1, 3), 4);
+        assert_eq!(document_line(content, 0, 2), 2);
+        assert_eq!(document_line(content, 0, 3), 5);
+        // Past the end of the page: its last line
+        assert_eq!(document_line(content, 1, 99), 5);
+        assert_eq!(document_line("@startuml\nA -> B\n@enduml", 0, 1), 1);
+    }
+
+    #[test]
+    fn test_split_pages_without_startuml() {
+        let pages = split_pages("A -> B\nnewpage\nB -> C");
+        assert_eq!(pages, vec!["@startuml\nA -> B\n@enduml", "@startuml\nB -> C\n@enduml"]);
+    }
+
+    #[test]
+    fn test_changed_page() {
+        let old = "@startuml\nA -> B\nnewpage\nB -> C\nnewpage\nC -> D\n@enduml";
+        let edited = old.replace("B -> C", "B -> C : call");
+        assert_eq!(changed_page(old, &edited), Some(1));
+        assert_eq!(changed_page(old, old), None);
+
+        let added = old.replace("C -> D", "C -> D\nnewpage\nD -> E");
+        assert_eq!(changed_page(old, &added), Some(3));
+
+        let themed = old.replace("@startuml", "@startuml\n!theme toy");
+        assert_eq!(changed_page(old, &themed), Some(0));
+    }
+
+    #[test]
+    fn test_slide_items_are_numbered() {
+        let items = slide_items("@startuml\ntitle Deck\nA -> B\nnewpage\nB -> C\n@enduml");
+        let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        assert_eq!(names, vec!["Deck_01.puml", "Deck_02.puml"]);
+
+        let items = slide_items("@startuml\nA -> B\n@enduml");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "slide_01.puml");
+    }
+
+    #[test]
+    fn test_newpage_must_be_whole_word() {
+        assert_eq!(page_count("@startuml\nnewpages -> A\n@enduml"), 1);
+    }
+}
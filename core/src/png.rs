@@ -0,0 +1,47 @@
+// PNG chunk reading and writing shared by the image post-processing modules
+
+pub(crate) const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Chunks of a PNG as (type, data), or None when `data` is not a PNG
+pub(crate) fn png_chunks(data: &[u8]) -> Option<Vec<(&[u8], &[u8])>> {
+    let mut rest = data.strip_prefix(PNG_SIGNATURE)?;
+    let mut chunks = Vec::new();
+    while !rest.is_empty() {
+        let length = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+        // Length, type, data and CRC
+        let chunk = rest.get(..length.checked_add(12)?)?;
+        chunks.push((&chunk[4..8], &chunk[8..8 + length]));
+        rest = &rest[chunk.len()..];
+    }
+    Some(chunks)
+}
+
+pub(crate) fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&[kind, data]).to_be_bytes());
+}
+
+/// CRC-32 (ISO 3309) of the concatenated `parts`, as stored in PNG chunks
+fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = !0u32;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        // CRC of an empty IEND chunk, as found at the end of every PNG
+        assert_eq!(crc32(&[b"IEND", b""]), 0xae42_6082);
+    }
+}
@@ -0,0 +1,265 @@
+// Directive preprocessing applied to PlantUML source
+//
+// Themes are written into the source as a `!theme` line so the saved document
+// keeps them. Editor options such as the color-blind-safe palette are stored
+// as a comment pragma and expanded by prepare_for_render just before the
+// source is sent to PlantUML. Directives are inserted after `@startuml` and
+// any `!theme`, so they override the theme.
+
+use std::borrow::Cow;
+
+use crate::models::ExportScale;
+use crate::normalize::{normalize, NormalizeOptions};
+
+/// Built-in PlantUML themes offered by the theme picker
+pub const THEMES: &[&str] = &[
+    "amiga",
+    "aws-orange",
+    "black-knight",
+    "bluegray",
+    "blueprint",
+    "cerulean",
+    "crt-green",
+    "cyborg",
+    "hacker",
+    "lightgray",
+    "materia",
+    "metal",
+    "mono",
+    "plain",
+    "sandstone",
+    "sketchy",
+    "spacelab",
+    "superhero",
+    "toy",
+    "vibrant",
+];
+
+/// Comment that turns on the color-blind-safe palette for a document
+///
+/// A comment keeps the source valid for plain PlantUML.
+pub const COLORBLIND_PRAGMA: &str = "' plantuml-editor: colorblind-safe";
+
+/// Okabe-Ito palette, distinguishable with protanopia, deuteranopia and tritanopia
+const COLORBLIND_PALETTE: &[(&str, &str)] = &[
+    ("ArrowColor", "#0072B2"),
+    ("ActorBackgroundColor", "#E69F00"),
+    ("ActorBorderColor", "#000000"),
+    ("ParticipantBackgroundColor", "#56B4E9"),
+    ("ParticipantBorderColor", "#0072B2"),
+    ("SequenceLifeLineBorderColor", "#0072B2"),
+    ("ClassBackgroundColor", "#56B4E9"),
+    ("ClassBorderColor", "#0072B2"),
+    ("ActivityBackgroundColor", "#56B4E9"),
+    ("ActivityBorderColor", "#0072B2"),
+    ("ActivityDiamondBackgroundColor", "#E69F00"),
+    ("UsecaseBackgroundColor", "#56B4E9"),
+    ("UsecaseBorderColor", "#0072B2"),
+    ("ComponentBackgroundColor", "#009E73"),
+    ("ComponentBorderColor", "#000000"),
+    ("StateBackgroundColor", "#56B4E9"),
+    ("StateBorderColor", "#0072B2"),
+    ("NoteBackgroundColor", "#F0E442"),
+    ("NoteBorderColor", "#E69F00"),
+];
+
+/// Theme named by the first `!theme` directive
+pub fn current_theme(content: &str) -> Option<&str> {
+    content.lines().find_map(theme_argument)
+}
+
+/// Replace the `!theme` directive, or remove it with None
+///
+/// Other lines are kept as they are. A new directive goes right after
+/// `@startuml`.
+pub fn set_theme(content: &str, theme: Option<&str>) -> String {
+    let without: Vec<&str> = content
+        .lines()
+        .filter(|line| theme_argument(line).is_none())
+        .collect();
+    let without = join_lines(&without, content);
+
+    match theme {
+        Some(theme) => inject_directives(&without, &[format!("!theme {}", theme)]),
+        None => without,
+    }
+}
+
+/// Whether the document turns on the color-blind-safe palette
+pub fn colorblind_safe(content: &str) -> bool {
+    content.lines().any(|line| line.trim() == COLORBLIND_PRAGMA)
+}
+
+/// Add or remove the color-blind-safe pragma
+pub fn set_colorblind_safe(content: &str, enabled: bool) -> String {
+    let without: Vec<&str> = content
+        .lines()
+        .filter(|line| line.trim() != COLORBLIND_PRAGMA)
+        .collect();
+    let without = join_lines(&without, content);
+
+    if enabled {
+        inject_directives(&without, &[COLORBLIND_PRAGMA.to_string()])
+    } else {
+        without
+    }
+}
+
+/// Source as sent to PlantUML: normalized (see `normalize`), with editor
+/// pragmas expanded into directives
+pub fn prepare_for_render(content: &str) -> Cow<'_, str> {
+    let content = normalize(content, NormalizeOptions::default());
+    if !colorblind_safe(&content) {
+        return content;
+    }
+
+    let palette: Vec<String> = COLORBLIND_PALETTE
+        .iter()
+        .map(|(name, color)| format!("skinparam {} {}", name, color))
+        .collect();
+    Cow::Owned(inject_directives(&content, &palette))
+}
+
+/// Source with a `scale` directive for the export resolution preset
+pub fn apply_scale(content: &str, scale: ExportScale) -> Cow<'_, str> {
+    if scale.is_default() {
+        return Cow::Borrowed(content);
+    }
+    Cow::Owned(inject_directives(content, &[format!("scale {}", scale.factor())]))
+}
+
+/// Source with `skinparam defaultFontName` for the font render option
+///
+/// Inserted at the top, so a font set in the source itself still wins.
+pub fn apply_font<'a>(content: &'a str, font: Option<&str>) -> Cow<'a, str> {
+    match font {
+        Some(font) => Cow::Owned(inject_directives(content, &[format!("skinparam defaultFontName {}", font)])),
+        None => Cow::Borrowed(content),
+    }
+}
+
+/// Insert directive lines after `@startuml` and any `!theme` that follows it
+/// (or at the top without `@startuml`)
+pub fn inject_directives(content: &str, directives: &[String]) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let start = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("@start"))
+        .map_or(0, |index| index + 1);
+    let at = lines
+        .iter()
+        .rposition(|line| theme_argument(line).is_some())
+        .map_or(start, |index| start.max(index + 1));
+    lines.splice(at..at, directives.iter().map(String::as_str));
+    join_lines(&lines, content)
+}
+
+/// Small diagram used for theme thumbnails
+pub fn theme_sample(theme: &str) -> String {
+    format!(
+        "@startuml\n!theme {}\nactor User\nparticipant Server\nUser -> Server : request\nServer --> User : response\nnote right of Server : {}\n@enduml",
+        theme, theme
+    )
+}
+
+/// Join lines, keeping the trailing newline of the original text
+fn join_lines(lines: &[&str], original: &str) -> String {
+    let mut joined = lines.join("\n");
+    if original.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+fn theme_argument(line: &str) -> Option<&str> {
+    let rest = line.trim().strip_prefix("!theme")?;
+    let name = rest.split_whitespace().next()?;
+    rest.starts_with(char::is_whitespace).then_some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_theme() {
+        assert_eq!(current_theme("@startuml\n!theme cerulean\nA -> B\n@enduml"), Some("cerulean"));
+        assert_eq!(current_theme("@startuml\n  !theme mars from ./themes\n@enduml"), Some("mars"));
+        assert_eq!(current_theme("@startuml\n!themes x\n@enduml"), None);
+        assert_eq!(current_theme("@startuml\nA -> B\n@enduml"), None);
+    }
+
+    #[test]
+    fn test_set_theme_inserts_after_startuml() {
+        let content = "' header\n@startuml\nA -> B\n@enduml";
+        assert_eq!(
+            set_theme(content, Some("sketchy")),
+            "' header\n@startuml\n!theme sketchy\nA -> B\n@enduml"
+        );
+        assert_eq!(set_theme("A -> B\n", Some("toy")), "!theme toy\nA -> B\n");
+    }
+
+    #[test]
+    fn test_set_theme_replaces_and_removes() {
+        let content = "@startuml\n!theme toy\nA -> B\n@enduml";
+        assert_eq!(
+            set_theme(content, Some("metal")),
+            "@startuml\n!theme metal\nA -> B\n@enduml"
+        );
+        assert_eq!(set_theme(content, None), "@startuml\nA -> B\n@enduml");
+    }
+
+    #[test]
+    fn test_toggle_colorblind_safe() {
+        let content = "@startuml\n!theme toy\nA -> B\n@enduml\n";
+        let enabled = set_colorblind_safe(content, true);
+        assert!(colorblind_safe(&enabled));
+        assert_eq!(
+            enabled,
+            format!("@startuml\n!theme toy\n{}\nA -> B\n@enduml\n", COLORBLIND_PRAGMA)
+        );
+        assert_eq!(set_colorblind_safe(&enabled, true), enabled);
+        assert_eq!(set_colorblind_safe(&enabled, false), content);
+    }
+
+    #[test]
+    fn test_prepare_for_render_injects_palette_after_theme() {
+        let content = "@startuml\nA -> B\n@enduml";
+        assert!(matches!(prepare_for_render(content), Cow::Borrowed(_)));
+        assert_eq!(prepare_for_render("\u{feff}@startuml\r\nA -> B  \r\n@enduml"), content);
+
+        let content = set_colorblind_safe("@startuml\n!theme toy\nA -> B\n@enduml", true);
+        let rendered = prepare_for_render(&content);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[1], "!theme toy");
+        assert_eq!(lines[2], "skinparam ArrowColor #0072B2");
+        assert_eq!(lines.len(), content.lines().count() + COLORBLIND_PALETTE.len());
+    }
+
+    #[test]
+    fn test_apply_scale() {
+        let content = "@startuml\nA -> B\n@enduml";
+        assert!(matches!(apply_scale(content, ExportScale::X1), Cow::Borrowed(_)));
+        assert_eq!(
+            apply_scale(content, ExportScale::X4),
+            "@startuml\nscale 4\nA -> B\n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_apply_font() {
+        let content = "@startuml\n!theme toy\nA -> B : 日本語\n@enduml";
+        assert!(matches!(apply_font(content, None), Cow::Borrowed(_)));
+        assert_eq!(
+            apply_font(content, Some("Noto Sans CJK JP")),
+            "@startuml\n!theme toy\nskinparam defaultFontName Noto Sans CJK JP\nA -> B : 日本語\n@enduml"
+        );
+    }
+
+    #[test]
+    fn test_theme_sample_uses_theme() {
+        for theme in THEMES {
+            assert_eq!(current_theme(&theme_sample(theme)), Some(*theme));
+        }
+    }
+}
@@ -0,0 +1,503 @@
+// Refactorings on PlantUML source
+//
+// Rename changes every reference to an element in one go: declarations,
+// arrows, notes, activations and class bodies. Unlike find and replace it
+// only touches the code spans found by the prose scan, so labels, notes,
+// titles and comments that mention the name keep their wording, and it never
+// matches part of a longer name.
+//
+// Extract moves selected lines into a named fragment near the top of the
+// diagram and leaves a call in their place. The fragment is a preprocessor
+// procedure inside a `!startsub` block, so other documents can reuse it with
+// `!includesub <file>!<name>` followed by `$<name>()`.
+
+use crate::icons::byte_offset;
+use crate::models::ErrorCode;
+use crate::outline::participants;
+use crate::stats::{declared_name, CLASS_KEYWORDS};
+use crate::tokens::{prose_spans, SpanKind};
+
+/// Why a rename was refused
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RenameError {
+    #[error("名前が不正です: {0}")]
+    InvalidName(String),
+
+    #[error("「{0}」が見つかりません")]
+    NotFound(String),
+
+    #[error("「{0}」は既に使われています")]
+    AlreadyUsed(String),
+
+    #[error("カーソル位置に参加者やクラスがありません")]
+    NoElementAtCursor,
+}
+
+impl RenameError {
+    pub fn to_error_code(&self) -> ErrorCode {
+        ErrorCode::RenameError {
+            reason: self.to_string(),
+        }
+    }
+}
+
+/// Why lines could not be extracted
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExtractError {
+    #[error("名前は英数字と _ で指定してください: {0}")]
+    InvalidName(String),
+
+    #[error("抜き出す行が選択されていません")]
+    EmptySelection,
+
+    #[error("@startuml と @enduml の行は抜き出せません")]
+    DiagramBoundary,
+
+    #[error("「{0}」は既に定義されています")]
+    AlreadyDefined(String),
+}
+
+impl ExtractError {
+    pub fn to_error_code(&self) -> ErrorCode {
+        ErrorCode::ExtractError {
+            reason: self.to_string(),
+        }
+    }
+}
+
+/// Names that can be renamed: participant and class identifiers, without
+/// quotes, in order of first appearance
+pub fn element_names(content: &str) -> Vec<String> {
+    let mut names: Vec<String> = participants(content)
+        .iter()
+        .map(|participant| participant.id().trim_matches('"').to_string())
+        .collect();
+    for line in content.lines() {
+        if let Some(name) = declared_name(line.trim(), CLASS_KEYWORDS) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// Name of the element at `cursor` (UTF-16 offset, as reported by the editor)
+pub fn name_at(content: &str, cursor: usize) -> Option<String> {
+    let at = byte_offset(content, cursor);
+    let name = references(content)
+        .into_iter()
+        .find(|reference| reference.start <= at && at <= reference.end)?
+        .name;
+    element_names(content).contains(&name).then_some(name)
+}
+
+/// Source with every reference to `old` renamed to `new`
+///
+/// Bare identifiers must be renamed to bare identifiers; a quoted name
+/// (`"Web Server"`) may be renamed to anything without quotes or line breaks
+/// and is only quoted when it needs to be.
+pub fn rename_element(content: &str, old: &str, new: &str) -> Result<String, RenameError> {
+    let new = new.trim();
+    if new.is_empty()
+        || new.contains(['"', '\n', '\r'])
+        || (is_identifier(old) && !is_identifier(new))
+    {
+        return Err(RenameError::InvalidName(new.to_string()));
+    }
+
+    // Keywords are references too, so only known elements may be renamed
+    let names = element_names(content);
+    if !names.iter().any(|name| name == old) {
+        return Err(RenameError::NotFound(old.to_string()));
+    }
+    if new != old && names.iter().any(|name| name == new) {
+        return Err(RenameError::AlreadyUsed(new.to_string()));
+    }
+    let found = references(content);
+    let targets = found.iter().filter(|reference| reference.name == old);
+
+    let replacement = if is_identifier(new) {
+        new.to_string()
+    } else {
+        format!("\"{}\"", new)
+    };
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for reference in targets {
+        result.push_str(&content[position..reference.start]);
+        result.push_str(&replacement);
+        position = reference.end;
+    }
+    result.push_str(&content[position..]);
+    Ok(result)
+}
+
+/// Source with the lines touched by the selection `start..end` (UTF-16
+/// offsets, as reported by the editor) moved into a fragment named `name`
+///
+/// The fragment goes after the `@start` line, its directives and earlier
+/// fragments. The selection is replaced by a `$name()` call at the
+/// indentation of its first line.
+pub fn extract_fragment(
+    content: &str,
+    start: usize,
+    end: usize,
+    name: &str,
+) -> Result<String, ExtractError> {
+    let name = name.trim();
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(ExtractError::InvalidName(name.to_string()));
+    }
+    let defined = content.lines().any(|line| {
+        let line = line.trim();
+        line.strip_prefix("!startsub")
+            .is_some_and(|rest| rest.trim() == name)
+            || line.contains(&format!("${}(", name))
+                && (line.starts_with("!procedure") || line.starts_with("!function"))
+    });
+    if defined {
+        return Err(ExtractError::AlreadyDefined(name.to_string()));
+    }
+
+    let (start, end) = (
+        byte_offset(content, start.min(end)),
+        byte_offset(content, start.max(end)),
+    );
+    let lines: Vec<&str> = content.lines().collect();
+    let line_of = |at: usize| content[..at].matches('\n').count();
+    let first = line_of(start);
+    // A selection ending at the start of a line does not include that line
+    let last = if end > start && content[..end].ends_with('\n') {
+        line_of(end) - 1
+    } else {
+        line_of(end)
+    }
+    .min(lines.len().saturating_sub(1));
+
+    let selected = lines.get(first..=last).unwrap_or_default();
+    if selected.iter().all(|line| line.trim().is_empty()) {
+        return Err(ExtractError::EmptySelection);
+    }
+    if selected
+        .iter()
+        .any(|line| line.trim_start().starts_with('@'))
+    {
+        return Err(ExtractError::DiagramBoundary);
+    }
+
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+    let dedent = selected
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indent_of(line))
+        .min()
+        .unwrap_or(0);
+    let mut fragment = vec![
+        format!("!startsub {}", name),
+        format!("!procedure ${}()", name),
+    ];
+    fragment.extend(selected.iter().map(|line| match line.get(dedent..) {
+        Some(body) if !line.trim().is_empty() => format!("  {}", body),
+        _ => String::new(),
+    }));
+    fragment.push("!endprocedure".to_string());
+    fragment.push("!endsub".to_string());
+
+    let first_line = selected
+        .iter()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or(&"");
+    let call = format!("{}${}()", &first_line[..indent_of(first_line)], name);
+
+    let mut result: Vec<String> = Vec::with_capacity(lines.len() + fragment.len());
+    result.extend(lines[..first].iter().map(|line| line.to_string()));
+    result.push(call);
+    result.extend(lines[last + 1..].iter().map(|line| line.to_string()));
+    let at = fragment_position(&lines[..first]);
+    result.splice(at..at, fragment);
+
+    let mut text = result.join("\n");
+    if content.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Line after the `@start` line, the directives right after it and the
+/// fragments extracted before
+fn fragment_position(lines: &[&str]) -> usize {
+    let Some(start) = lines
+        .iter()
+        .position(|line| line.trim_start().to_lowercase().starts_with("@start"))
+    else {
+        return 0;
+    };
+    let mut in_sub = false;
+    let mut position = start + 1;
+    for line in &lines[start + 1..] {
+        let line = line.trim();
+        if line.starts_with("!startsub") {
+            in_sub = true;
+        } else if line.starts_with("!endsub") {
+            in_sub = false;
+        } else if !in_sub && !line.starts_with('!') {
+            break;
+        }
+        position += 1;
+    }
+    position
+}
+
+/// A name written in a code span, with quotes included in the range
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reference {
+    /// Without quotes
+    name: String,
+    start: usize,
+    end: usize,
+}
+
+/// Every identifier and quoted name outside prose, directives and skinparams
+fn references(content: &str) -> Vec<Reference> {
+    let spans = prose_spans(content);
+    let is_prose = |at: usize| {
+        spans
+            .iter()
+            .any(|span| span.kind == SpanKind::Prose && span.start <= at && at < span.end)
+    };
+
+    let mut found = Vec::new();
+    let mut offset = 0;
+    for raw in content.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim_start();
+        let skipped = trimmed.is_empty()
+            || trimmed.starts_with(['@', '!', '\''])
+            || trimmed.starts_with("/'")
+            || trimmed.to_lowercase().starts_with("skinparam")
+            || is_prose(offset + line.len() - trimmed.len());
+        if !skipped {
+            scan_line(line, offset, &is_prose, &mut found);
+        }
+        offset += raw.len();
+    }
+    found
+}
+
+fn scan_line(
+    line: &str,
+    offset: usize,
+    is_prose: &dyn Fn(usize) -> bool,
+    found: &mut Vec<Reference>,
+) {
+    let mut index = 0;
+    while index < line.len() {
+        let rest = &line[index..];
+        let c = rest.chars().next().unwrap_or_default();
+        if is_prose(offset + index) {
+            index += c.len_utf8();
+        } else if c == '"' {
+            // A quoted name; its text is prose, the quotes are not
+            let Some(close) = rest[1..].find('"') else {
+                break;
+            };
+            found.push(Reference {
+                name: rest[1..close + 1].to_string(),
+                start: offset + index,
+                end: offset + index + close + 2,
+            });
+            index += close + 2;
+        } else if let Some(stereotype) = rest.strip_prefix("<<") {
+            index += 2 + stereotype
+                .find(">>")
+                .map_or(stereotype.len(), |end| end + 2);
+        } else if c == '#' {
+            // Colors such as #LightBlue
+            index += rest.find(char::is_whitespace).unwrap_or(rest.len());
+        } else if is_word_char(c) {
+            let mut end = index;
+            while end < line.len() {
+                let next = line[end..].chars().next().unwrap_or_default();
+                let dotted = next == '.' && line[end + 1..].starts_with(|c: char| is_word_char(c));
+                if !is_word_char(next) && !dotted {
+                    break;
+                }
+                end += next.len_utf8();
+            }
+            // Skip the `::` member part of `Class::member`
+            let member = line[..index].ends_with("::");
+            if !member {
+                found.push(Reference {
+                    name: line[index..end].to_string(),
+                    start: offset + index,
+                    end: offset + end,
+                });
+            }
+            index = end;
+        } else {
+            index += c.len_utf8();
+        }
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(is_word_char))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQUENCE: &str = "@startuml\n\
+        skinparam ParticipantBackgroundColor Web\n\
+        participant \"Web Server\" as Web <<Web>> #Web\n\
+        actor User\n\
+        User -> Web : open Web page\n\
+        activate Web\n\
+        note right of Web\n  Web is slow\nend note\n\
+        Website -> Web\n\
+        ' Web comment\n\
+        @enduml\n";
+
+    #[test]
+    fn test_rename_participant_alias() {
+        let renamed = rename_element(SEQUENCE, "Web", "Srv").unwrap();
+        assert_eq!(
+            renamed,
+            "@startuml\n\
+            skinparam ParticipantBackgroundColor Web\n\
+            participant \"Web Server\" as Srv <<Web>> #Web\n\
+            actor User\n\
+            User -> Srv : open Web page\n\
+            activate Srv\n\
+            note right of Srv\n  Web is slow\nend note\n\
+            Website -> Srv\n\
+            ' Web comment\n\
+            @enduml\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_class() {
+        let source = "class Order {\n  +items : List<Item>\n}\nclass Item\nOrder \"1\" *-- \"many\" Item : contains Item\nItem::price --> Order\n";
+        let renamed = rename_element(source, "Item", "LineItem").unwrap();
+        assert_eq!(
+            renamed,
+            "class Order {\n  +items : List<LineItem>\n}\nclass LineItem\nOrder \"1\" *-- \"many\" LineItem : contains Item\nLineItem::price --> Order\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_quoted_name() {
+        let source = "\"Web Server\" -> DB\nparticipant \"Web Server\"\n";
+        let renamed = rename_element(source, "Web Server", "Gateway").unwrap();
+        assert_eq!(renamed, "Gateway -> DB\nparticipant Gateway\n");
+        let renamed = rename_element(source, "Web Server", "API gateway").unwrap();
+        assert!(renamed.starts_with("\"API gateway\" -> DB"));
+    }
+
+    #[test]
+    fn test_rename_errors() {
+        assert_eq!(
+            rename_element(SEQUENCE, "Web", "User"),
+            Err(RenameError::AlreadyUsed("User".to_string()))
+        );
+        assert_eq!(
+            rename_element(SEQUENCE, "Db", "Store"),
+            Err(RenameError::NotFound("Db".to_string()))
+        );
+        assert_eq!(
+            rename_element(SEQUENCE, "Web", "Web Server 2"),
+            Err(RenameError::InvalidName("Web Server 2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_element_names_and_name_at() {
+        assert_eq!(element_names(SEQUENCE), vec!["Web", "User", "Website"]);
+        assert_eq!(
+            element_names("abstract class Shape\nclass Circle"),
+            vec!["Shape", "Circle"]
+        );
+
+        let cursor =
+            "@startuml\nskinparam ParticipantBackgroundColor Web\nparticipant \"Web Server\" as W"
+                .encode_utf16()
+                .count();
+        assert_eq!(name_at(SEQUENCE, cursor).as_deref(), Some("Web"));
+        let label = SEQUENCE.find("open").unwrap();
+        assert_eq!(name_at(SEQUENCE, label), None);
+    }
+
+    const FLOW: &str = "@startuml\n\
+        !theme plain\n\
+        User -> Web : login\n\
+        group auth\n  \
+        Web -> Auth : check\n  \
+        Auth --> Web : ok\n\
+        end\n\
+        @enduml\n";
+
+    #[test]
+    fn test_extract_fragment() {
+        let start = FLOW.find("  Web -> Auth").unwrap();
+        // Ends at the start of the `end` line, which is not included
+        let end = FLOW.find("end\n").unwrap();
+        let extracted = extract_fragment(FLOW, start, end, "check_auth").unwrap();
+        assert_eq!(
+            extracted,
+            "@startuml\n\
+            !theme plain\n\
+            !startsub check_auth\n\
+            !procedure $check_auth()\n  \
+            Web -> Auth : check\n  \
+            Auth --> Web : ok\n\
+            !endprocedure\n\
+            !endsub\n\
+            User -> Web : login\n\
+            group auth\n  \
+            $check_auth()\n\
+            end\n\
+            @enduml\n"
+        );
+
+        // A second fragment goes after the first one
+        let caret = extracted.find("User").unwrap();
+        let again = extract_fragment(&extracted, caret, caret, "login").unwrap();
+        assert!(again
+            .contains("!endsub\n!startsub login\n!procedure $login()\n  User -> Web : login\n"));
+        assert!(again.contains("!endsub\n$login()\ngroup auth"));
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        let caret = FLOW.find("User").unwrap();
+        assert_eq!(
+            extract_fragment(FLOW, caret, caret, "2fa"),
+            Err(ExtractError::InvalidName("2fa".to_string()))
+        );
+        assert_eq!(
+            extract_fragment(FLOW, 0, caret, "intro"),
+            Err(ExtractError::DiagramBoundary)
+        );
+        let extracted = extract_fragment(FLOW, caret, caret, "login").unwrap();
+        assert_eq!(
+            extract_fragment(&extracted, 0, 0, "login"),
+            Err(ExtractError::AlreadyDefined("login".to_string()))
+        );
+        assert_eq!(
+            extract_fragment("A -> B\n\nB -> C", 7, 7, "blank"),
+            Err(ExtractError::EmptySelection)
+        );
+    }
+}
@@ -0,0 +1,63 @@
+// Timeout and retry policy for renders
+//
+// The API server gives the PlantUML server `RENDER_TIMEOUT` per image, and
+// answers with a transient error (see `ErrorCode::is_transient`) when it is
+// busy, slow or paused; the editor keeps the last preview in that case and
+// the next edit tries again. Batch clients such as the CLI use the same
+// timeout and retry those errors with `RetryPolicy`.
+
+use std::time::Duration;
+
+/// Time allowed for one render by the PlantUML server
+pub const RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often and how long to wait before retrying a transient failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first one
+    pub max_attempts: u32,
+    /// Wait before the second attempt, doubled for each later one
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after failed attempt `attempt` (1-based), or None when it was the last
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(self.initial_delay.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_backs_off_up_to_the_limit() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(3),
+        };
+        assert_eq!(policy.delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(3), Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay(4), Some(Duration::from_secs(3)));
+        assert_eq!(policy.delay(5), None);
+        assert_eq!(RetryPolicy::default().delay(3), None);
+    }
+}
@@ -0,0 +1,127 @@
+// SARIF 2.1.0 output for diagnostics
+//
+// Code scanning UIs (GitHub, Azure DevOps, IDE viewers) take findings as a
+// SARIF log. The CLI `check --format sarif` and POST /api/v1/diagnostics
+// with `?format=sarif` both build it here, so the two agree on rule IDs,
+// levels and positions.
+
+use serde_json::{json, Value};
+
+use crate::diagnostics::DIAGNOSTIC_SOURCE;
+use crate::models::{Diagnostic, DiagnosticSeverity};
+
+/// Media type of a SARIF log
+pub const SARIF_CONTENT_TYPE: &str = "application/sarif+json";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Diagnostics of one source, reported under `uri`
+#[derive(Debug, Clone, Copy)]
+pub struct SarifArtifact<'a> {
+    /// Location of the source, relative to the repository root when known
+    pub uri: &'a str,
+    pub diagnostics: &'a [Diagnostic],
+}
+
+/// SARIF 2.1.0 log with one result per diagnostic and one rule per code
+///
+/// Columns are UTF-16 code units, SARIF's default, like the diagnostics.
+pub fn sarif_log(artifacts: &[SarifArtifact]) -> Value {
+    let mut rules: Vec<&str> = artifacts
+        .iter()
+        .flat_map(|artifact| artifact.diagnostics.iter().map(|diagnostic| diagnostic.code.as_str()))
+        .collect();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let results: Vec<Value> = artifacts
+        .iter()
+        .flat_map(|artifact| {
+            artifact
+                .diagnostics
+                .iter()
+                .map(move |diagnostic| sarif_result(artifact.uri, diagnostic))
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": DIAGNOSTIC_SOURCE,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+fn sarif_result(uri: &str, diagnostic: &Diagnostic) -> Value {
+    let range = diagnostic.range;
+    json!({
+        "ruleId": diagnostic.code,
+        "level": sarif_level(diagnostic.severity),
+        "message": { "text": diagnostic.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": {
+                    "startLine": range.start.line + 1,
+                    "startColumn": range.start.character + 1,
+                    "endLine": range.end.line + 1,
+                    "endColumn": range.end.character + 1,
+                },
+            },
+        }],
+    })
+}
+
+fn sarif_level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "error",
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Information => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::lint;
+
+    #[test]
+    fn test_sarif_log() {
+        let missing_end = lint("@startuml\nA -> B");
+        let open_brace = lint("@startuml\nclass A {\n@enduml");
+        let sarif = sarif_log(&[
+            SarifArtifact { uri: "a.puml", diagnostics: &missing_end },
+            SarifArtifact { uri: "docs/b.puml", diagnostics: &open_brace },
+        ]);
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "plantuml-editor");
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{ "id": "missing-end" }, { "id": "unclosed-brace" }])
+        );
+        let result = &run["results"][1];
+        assert_eq!(result["ruleId"], "unclosed-brace");
+        assert_eq!(result["level"], "warning");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "docs/b.puml");
+        assert_eq!(location["region"]["startLine"], 2);
+        assert_eq!(location["region"]["startColumn"], 9);
+        assert_eq!(location["region"]["endColumn"], 10);
+    }
+
+    #[test]
+    fn test_empty_log_has_a_run() {
+        let sarif = sarif_log(&[SarifArtifact { uri: "a.puml", diagnostics: &[] }]);
+        assert_eq!(sarif["runs"][0]["results"], json!([]));
+        assert_eq!(sarif["runs"][0]["tool"]["driver"]["rules"], json!([]));
+    }
+}
@@ -0,0 +1,283 @@
+// Diagram statistics read from PlantUML source
+//
+// A line-based scan, not a full PlantUML parser: it is meant to show roughly
+// how large a diagram is, not to validate it.
+
+use std::collections::BTreeSet;
+
+use crate::metadata::directive_argument;
+use crate::models::{ComplexityLevel, DiagramAnalysis};
+use crate::pages::page_count;
+
+/// Highest complexity still counted as low (a diagram that reads at a glance)
+pub const LOW_COMPLEXITY_MAX: usize = 30;
+
+/// Highest complexity still counted as medium; above it a split is worth considering
+pub const MEDIUM_COMPLEXITY_MAX: usize = 80;
+
+/// Keywords that declare a sequence diagram participant
+pub const PARTICIPANT_KEYWORDS: &[&str] = &[
+    "participant",
+    "actor",
+    "boundary",
+    "control",
+    "entity",
+    "database",
+    "collections",
+    "queue",
+];
+
+/// Keywords that declare a class-like element
+pub(crate) const CLASS_KEYWORDS: &[&str] = &["class", "abstract", "interface", "enum", "annotation"];
+
+/// Element counts for the stats panel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiagramStats {
+    /// Declared participants plus those only appearing in messages
+    pub participants: usize,
+    /// Arrows between elements (messages in sequence diagrams)
+    pub messages: usize,
+    /// Classes, interfaces, enums and annotations
+    pub classes: usize,
+    pub notes: usize,
+}
+
+impl DiagramStats {
+    pub fn from_source(content: &str) -> Self {
+        let mut stats = Self::default();
+        let mut participants = BTreeSet::new();
+        let mut endpoints = BTreeSet::new();
+        let mut in_note = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            if in_note {
+                let words: String = line.split_whitespace().collect();
+                in_note = !words.eq_ignore_ascii_case("endnote");
+                continue;
+            }
+
+            if line.is_empty() || line.starts_with('\'') || line.starts_with('@') {
+                continue;
+            }
+
+            if let Some(rest) = note_argument(line) {
+                stats.notes += 1;
+                // Without ':' or a quoted text the note continues until "end note"
+                in_note = !rest.contains(':') && !rest.contains('"');
+            } else if let Some(name) = declared_name(line, PARTICIPANT_KEYWORDS) {
+                participants.insert(name);
+            } else if declared_name(line, CLASS_KEYWORDS).is_some() {
+                stats.classes += 1;
+            } else if let Some((from, to)) = arrow_endpoints(line) {
+                stats.messages += 1;
+                endpoints.extend([from, to].into_iter().flatten());
+            }
+        }
+
+        // Arrows in class diagrams connect classes, not participants
+        if stats.classes == 0 {
+            participants.extend(endpoints);
+        }
+        stats.participants = participants.len();
+        stats
+    }
+
+    /// Elements, arrows and notes together
+    pub fn complexity(&self) -> usize {
+        self.participants + self.messages + self.classes + self.notes
+    }
+}
+
+/// Structure statistics for POST /api/v1/analyze and the stats panel
+pub fn analyze(content: &str) -> DiagramAnalysis {
+    let stats = DiagramStats::from_source(content);
+    let complexity = stats.complexity();
+    let complexity_level = if complexity <= LOW_COMPLEXITY_MAX {
+        ComplexityLevel::Low
+    } else if complexity <= MEDIUM_COMPLEXITY_MAX {
+        ComplexityLevel::Medium
+    } else {
+        ComplexityLevel::High
+    };
+    DiagramAnalysis {
+        diagram_type: diagram_type(content, &stats),
+        pages: page_count(content),
+        lines: content.lines().count(),
+        participants: stats.participants,
+        messages: stats.messages,
+        classes: stats.classes,
+        notes: stats.notes,
+        complexity,
+        complexity_level,
+    }
+}
+
+/// Type named by the first `@start` line, with `@startuml` narrowed down by
+/// what the source declares
+fn diagram_type(content: &str, stats: &DiagramStats) -> String {
+    let kind = content.lines().find_map(|line| {
+        let line = line.trim();
+        let rest = line.get(..6).filter(|head| head.eq_ignore_ascii_case("@start"))?;
+        let kind: String = line[rest.len()..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        Some(kind.to_ascii_lowercase())
+    });
+    match kind.as_deref() {
+        Some("uml") | Some("") | None => {}
+        Some(kind) => return kind.to_string(),
+    }
+    let activity = content.lines().map(str::trim).any(|line| {
+        line.eq_ignore_ascii_case("start") || (line.starts_with(':') && line.ends_with(';'))
+    });
+    if stats.classes > 0 {
+        "class"
+    } else if stats.participants > 0 || stats.messages > 0 {
+        "sequence"
+    } else if activity {
+        "activity"
+    } else {
+        "uml"
+    }
+    .to_string()
+}
+
+/// Text after `note`, `hnote` or `rnote`
+fn note_argument(line: &str) -> Option<&str> {
+    ["note", "hnote", "rnote"]
+        .iter()
+        .find_map(|keyword| directive_argument(line, keyword))
+}
+
+/// Name declared by `<keyword> "Long name" as alias` or `<keyword> Name`
+pub(crate) fn declared_name(line: &str, keywords: &[&str]) -> Option<String> {
+    let rest = keywords
+        .iter()
+        .find_map(|keyword| directive_argument(line, keyword))?;
+    // "abstract class Foo"
+    let rest = directive_argument(rest, "class").unwrap_or(rest);
+
+    let name = match rest.rsplit_once(" as ") {
+        Some((_, alias)) => alias,
+        None if rest.starts_with('"') => rest[1..].split('"').next().unwrap_or_default(),
+        None => rest.split_whitespace().next().unwrap_or_default(),
+    };
+    let name = name
+        .trim()
+        .trim_end_matches(['{', '<'])
+        .trim_matches('"')
+        .trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Elements on both sides of an arrow such as `A -> B : text`
+///
+/// Returns None for lines without an arrow. Incoming and outgoing messages
+/// (`[->`, `->]`) have no element on the open side.
+fn arrow_endpoints(line: &str) -> Option<(Option<String>, Option<String>)> {
+    let line = line.split(':').next().unwrap_or(line);
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let arrow = tokens.iter().position(|token| is_arrow(token))?;
+
+    let endpoint = |token: Option<&&str>| {
+        let name = token?.trim_matches(|c| c == '"' || c == '[' || c == ']');
+        (!name.is_empty()).then(|| name.to_string())
+    };
+    let from = arrow.checked_sub(1).and_then(|index| tokens.get(index));
+    Some((endpoint(from), endpoint(tokens.get(arrow + 1))))
+}
+
+/// `->`, `-->`, `<-`, `->>`, `-[#red]>`, `[->` and the like
+pub(crate) fn is_arrow(token: &str) -> bool {
+    let token = token.trim_matches(|c| c == '[' || c == ']');
+    let body: String = match (token.find('['), token.find(']')) {
+        // Drop styling such as -[#red]>
+        (Some(open), Some(close)) if open < close => {
+            format!("{}{}", &token[..open], &token[close + 1..])
+        }
+        _ => token.to_string(),
+    };
+    let dashes = body.trim_start_matches(['<', 'x', 'o']).trim_end_matches(['>', 'x', 'o']);
+    let head = body.starts_with('<') || body.ends_with('>');
+    head && !dashes.is_empty() && dashes.chars().all(|c| c == '-' || c == '.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_diagram() {
+        let content = "@startuml\n\
+            actor User\n\
+            participant \"Web Server\" as Web\n\
+            User -> Web : login\n\
+            Web --> DB : query\n\
+            DB -->> Web\n\
+            note right of Web : cached\n\
+            note over User\n  waits\nend note\n\
+            @enduml";
+        let stats = DiagramStats::from_source(content);
+        assert_eq!(stats.participants, 3);
+        assert_eq!(stats.messages, 3);
+        assert_eq!(stats.classes, 0);
+        assert_eq!(stats.notes, 2);
+    }
+
+    #[test]
+    fn test_class_diagram() {
+        let content = "@startuml\n\
+            abstract class Shape {\n  area()\n}\n\
+            class Circle\n\
+            interface Drawable\n\
+            enum Color\n\
+            Circle --> Color\n\
+            Shape <|-- Circle\n\
+            @enduml";
+        let stats = DiagramStats::from_source(content);
+        assert_eq!(stats.classes, 4);
+        assert_eq!(stats.messages, 1);
+        assert_eq!(stats.participants, 0);
+    }
+
+    #[test]
+    fn test_arrow_forms() {
+        assert!(is_arrow("->"));
+        assert!(is_arrow("<--"));
+        assert!(is_arrow("-[#red]>"));
+        assert!(is_arrow("[->"));
+        assert!(!is_arrow("<|--"));
+        assert!(!is_arrow("-"));
+        assert!(!is_arrow(">"));
+
+        let (from, to) = arrow_endpoints("[-> Bob : hello").unwrap();
+        assert_eq!((from, to.as_deref()), (None, Some("Bob")));
+        assert_eq!(arrow_endpoints("A : -> text"), None);
+    }
+
+    #[test]
+    fn test_analyze() {
+        let analysis = analyze("@startuml\nAlice -> Bob : hi\nnewpage\nBob -> Alice\nnote left : ok\n@enduml");
+        assert_eq!(analysis.diagram_type, "sequence");
+        assert_eq!(analysis.pages, 2);
+        assert_eq!(analysis.lines, 6);
+        assert_eq!(analysis.complexity, 5);
+        assert_eq!(analysis.complexity_level, ComplexityLevel::Low);
+
+        assert_eq!(analyze("@startmindmap\n* root\n@endmindmap").diagram_type, "mindmap");
+        assert_eq!(analyze("@startuml\nstart\n:step;\nstop\n@enduml").diagram_type, "activity");
+        assert_eq!(analyze("@startuml\nclass A\n@enduml").diagram_type, "class");
+
+        let large: String = (0..MEDIUM_COMPLEXITY_MAX).map(|i| format!("A -> B{}\n", i)).collect();
+        assert_eq!(analyze(&large).complexity_level, ComplexityLevel::High);
+    }
+
+    #[test]
+    fn test_comments_are_ignored() {
+        let stats = DiagramStats::from_source("' A -> B\n' note left: x");
+        assert_eq!(stats, DiagramStats::default());
+    }
+}
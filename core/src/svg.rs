@@ -0,0 +1,466 @@
+// SVG sanitization for the preview
+//
+// Diagrams can carry links and raw SVG from the source (e.g. `[[url]]` or
+// sprites), and server documents and share links show other people's
+// diagrams, so the SVG returned by the server is cleaned before it is shown.
+// Only the elements and attributes PlantUML draws with are kept (an
+// allowlist); anything else is dropped, an unknown element with its content.
+// Links may only point to http(s), mailto, the document itself or, for
+// `<image>`, a `data:image/` URL, checked after character references and the
+// tabs and newlines browsers ignore in URLs are taken out. The preview sits
+// in the app's own page, so `<style>` elements are dropped and `style`
+// attributes lose declarations that load resources or move the element out
+// of the diagram (`url(`, `@import`, `position`). Tags are written
+// back with their attributes double-quoted, and comments, processing
+// instructions and doctypes are dropped, so the browser's HTML or XML parser
+// sees exactly the elements checked here.
+
+/// Elements kept in the preview (lowercase)
+const ELEMENTS: &[&str] = &[
+    "a", "circle", "clippath", "defs", "desc", "ellipse", "feblend", "fecolormatrix",
+    "fecomponenttransfer", "fecomposite", "fedropshadow", "feflood", "fefunca", "fefuncb",
+    "fefuncg", "fefuncr", "fegaussianblur", "femerge", "femergenode", "femorphology", "feoffset",
+    "filter", "g", "image", "line", "lineargradient", "marker", "mask", "path", "pattern",
+    "polygon", "polyline", "radialgradient", "rect", "stop", "svg", "symbol", "text",
+    "textpath", "title", "tspan", "use",
+];
+
+/// Attributes kept on them (lowercase), besides `data-*` and the links
+const ATTRIBUTES: &[&str] = &[
+    "alignment-baseline", "amplitude", "baseline-shift", "baseprofile", "class", "clip-path",
+    "clip-rule", "clippathunits", "color", "contentscripttype", "contentstyletype", "cursor", "cx",
+    "cy", "d", "direction", "display", "dominant-baseline", "dx", "dy", "exponent", "fill",
+    "fill-opacity", "fill-rule", "filter", "filterunits", "flood-color", "flood-opacity",
+    "font-family", "font-size", "font-stretch", "font-style", "font-variant", "font-weight", "fx",
+    "fy", "gradienttransform", "gradientunits", "height", "id", "in", "in2", "intercept", "k1",
+    "k2", "k3", "k4", "lengthadjust", "letter-spacing", "marker-end", "marker-mid",
+    "marker-start", "markerheight", "markerunits", "markerwidth", "mask", "maskcontentunits",
+    "maskunits", "method", "mode", "offset", "opacity", "operator", "orient", "pathlength",
+    "patterncontentunits", "patterntransform", "patternunits", "points", "preserveaspectratio",
+    "primitiveunits", "r", "radius", "refx", "refy", "result", "rotate", "rx", "ry", "slope",
+    "spacing", "spreadmethod", "startoffset", "stddeviation", "stop-color", "stop-opacity",
+    "stroke", "stroke-dasharray", "stroke-dashoffset", "stroke-linecap", "stroke-linejoin",
+    "stroke-miterlimit", "stroke-opacity", "stroke-width", "style", "tablevalues", "target",
+    "text-anchor", "text-decoration", "textlength", "title", "transform", "type", "values",
+    "version", "viewbox", "visibility", "width", "word-spacing", "writing-mode", "x", "x1", "x2",
+    "xlink:actuate", "xlink:show", "xlink:title", "xlink:type", "xml:lang", "xml:space", "xmlns",
+    "xmlns:xlink", "y", "y1", "y2", "zoomandpan",
+];
+
+/// SVG with only allowlisted elements, attributes and links
+pub fn sanitize(svg: &str) -> String {
+    // ASCII lowercasing keeps the byte offsets of `svg`
+    let lower = svg.to_ascii_lowercase();
+    let mut out = String::with_capacity(svg.len());
+    // Open `<svg>` elements; text outside them is dropped
+    let mut depth = 0usize;
+    let mut rest = 0;
+    while let Some(found) = svg[rest..].find('<') {
+        let start = rest + found;
+        if depth > 0 {
+            out.push_str(&svg[rest..start]);
+        }
+
+        if lower[start..].starts_with("<![cdata[") {
+            let end = lower[start..]
+                .find("]]>")
+                .map_or(svg.len(), |index| start + index + 3);
+            if depth > 0 {
+                out.push_str(&svg[start..end]);
+            }
+            rest = end;
+            continue;
+        }
+        if lower[start..].starts_with("<!--") {
+            rest = lower[start..]
+                .find("-->")
+                .map_or(svg.len(), |index| start + index + 3);
+            continue;
+        }
+        match svg[start + 1..].chars().next() {
+            // Doctype or processing instruction
+            Some('!' | '?') => {
+                rest = tag_end(svg, start);
+                continue;
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '/' => {}
+            // A `<` that does not start a tag
+            _ => {
+                if depth > 0 {
+                    out.push_str("&lt;");
+                }
+                rest = start + 1;
+                continue;
+            }
+        }
+
+        let end = tag_end(svg, start);
+        let tag = &svg[start..end];
+        let name = tag_name(tag);
+        let element = name.to_ascii_lowercase();
+        let allowed = ELEMENTS.contains(&element.as_str()) && (depth > 0 || element == "svg");
+        rest = end;
+        if tag.starts_with("</") {
+            if allowed && depth > 0 {
+                out.push_str(&format!("</{}>", name));
+                if element == "svg" {
+                    depth -= 1;
+                }
+            }
+            continue;
+        }
+        let self_closing = tag.ends_with("/>");
+        if !allowed {
+            // Dropped with its content
+            if !self_closing {
+                rest = closing_tag_end(&lower, end, &element);
+            }
+            continue;
+        }
+        out.push_str(&clean_tag(tag, name, &element, self_closing));
+        if element == "svg" && !self_closing {
+            depth += 1;
+        }
+    }
+    if depth > 0 {
+        out.push_str(&svg[rest..]);
+    }
+    out
+}
+
+/// Start and end of the root `<svg ...>` start tag
+pub(crate) fn root_tag(svg: &str) -> Option<(usize, usize)> {
+    let lower = svg.to_ascii_lowercase();
+    let start = lower.match_indices("<svg").map(|(index, _)| index).find(|index| {
+        lower[index + 4..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_whitespace() || c == '>' || c == '/')
+    })?;
+    Some((start, tag_end(svg, start)))
+}
+
+/// Value of attribute `name` on the root `<svg>` element
+pub(crate) fn root_attribute<'a>(svg: &'a str, name: &str) -> Option<&'a str> {
+    let (start, end) = root_tag(svg)?;
+    let tag = &svg[start..end];
+    let mut at = 4;
+    loop {
+        at += tag[at..].len() - tag[at..].trim_start().len();
+        let (attribute_name, value, end) = attribute(tag, at)?;
+        if attribute_name.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+        at = end;
+    }
+}
+
+/// Index after the `>` closing the tag that starts at `start`
+fn tag_end(svg: &str, start: usize) -> usize {
+    let mut quote = None;
+    for (index, byte) in svg.bytes().enumerate().skip(start + 1) {
+        match (quote, byte) {
+            (Some(open), _) if byte == open => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(byte),
+            (None, b'>') => return index + 1,
+            _ => {}
+        }
+    }
+    svg.len()
+}
+
+/// Index after `</name ...>` following `from`, or the end of the text
+fn closing_tag_end(lower: &str, from: usize, name: &str) -> usize {
+    lower[from..]
+        .find(&format!("</{}", name))
+        .and_then(|index| {
+            let close = from + index;
+            lower[close..].find('>').map(|end| close + end + 1)
+        })
+        .unwrap_or(lower.len())
+}
+
+fn tag_name(tag: &str) -> &str {
+    // Exactly one `<`: `clean_tag` takes the name to start at offset 1
+    let name = tag.strip_prefix('<').unwrap_or(tag).trim_start_matches('/');
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// Start tag `name` with its allowlisted attributes, double-quoted
+fn clean_tag(tag: &str, name: &str, element: &str, self_closing: bool) -> String {
+    let mut out = format!("<{}", name);
+    let mut at = 1 + name.len();
+    loop {
+        at += tag[at..].len() - tag[at..].trim_start().len();
+        let Some((attribute_name, value, end)) = attribute(tag, at) else {
+            break;
+        };
+        let lower_name = attribute_name.to_ascii_lowercase();
+        if lower_name == "style" {
+            let style = clean_style(value);
+            if !style.is_empty() {
+                out.push_str(&format!(
+                    " {}=\"{}\"",
+                    attribute_name,
+                    style.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+                ));
+            }
+        } else if is_allowed(element, &lower_name, value) {
+            out.push_str(&format!(
+                " {}=\"{}\"",
+                attribute_name,
+                value.replace('"', "&quot;").replace('<', "&lt;")
+            ));
+        }
+        at = end;
+    }
+    out.push_str(if self_closing { "/>" } else { ">" });
+    out
+}
+
+/// Name, unquoted value and end of the attribute starting at `at`
+fn attribute(tag: &str, at: usize) -> Option<(&str, &str, usize)> {
+    let rest = &tag[at..];
+    let name_len = rest
+        .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+        .unwrap_or(rest.len());
+    if name_len == 0 {
+        return None;
+    }
+    let name = &rest[..name_len];
+    let after_name = rest[name_len..].trim_start();
+    let Some(value) = after_name.strip_prefix('=') else {
+        return Some((name, "", at + name_len));
+    };
+    let value = value.trim_start();
+    let value_start = tag.len() - value.len();
+    let (value, end) = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let inner = &value[1..];
+            let len = inner.find(quote).unwrap_or(inner.len());
+            (&inner[..len], (value_start + 1 + len + 1).min(tag.len()))
+        }
+        _ => {
+            let len = value
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .unwrap_or(value.len());
+            (&value[..len], value_start + len)
+        }
+    };
+    Some((name, value, end))
+}
+
+/// Declarations of a `style` attribute that stay inside the diagram: no
+/// resources, imports, escapes or positioning
+fn clean_style(value: &str) -> String {
+    decode_references(value)
+        .split(';')
+        .map(str::trim)
+        .filter(|declaration| {
+            let compact: String = declaration
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>()
+                .to_ascii_lowercase();
+            !declaration.is_empty()
+                && !compact.starts_with("position:")
+                && !["url(", "@", "\\", "expression(", "image-set("]
+                    .iter()
+                    .any(|needle| compact.contains(needle))
+        })
+        .map(|declaration| format!("{};", declaration))
+        .collect()
+}
+
+fn is_allowed(element: &str, name: &str, value: &str) -> bool {
+    if name == "href" || name == "xlink:href" {
+        return is_safe_link(element, value);
+    }
+    ATTRIBUTES.contains(&name)
+        || name.strip_prefix("data-").is_some_and(|rest| {
+            rest.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        })
+}
+
+/// Whether a link on `element` is safe to follow or load
+fn is_safe_link(element: &str, value: &str) -> bool {
+    // Browsers drop tabs and newlines anywhere in a URL
+    let url: String = decode_references(value)
+        .chars()
+        .filter(|&c| c > ' ')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if url.starts_with('#') {
+        return true;
+    }
+    // `<use>` may only refer to the document itself
+    if element == "use" {
+        return false;
+    }
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => {
+            let scheme = &url[..index];
+            matches!(scheme, "http" | "https" | "mailto")
+                || (element == "image" && url.starts_with("data:image/"))
+        }
+        // Relative, unless a reference not decoded here could hide a scheme
+        end => !url[..end.unwrap_or(url.len())].contains('&'),
+    }
+}
+
+/// `value` with its numeric character references and the named ones that
+/// can spell out a URL scheme replaced; other references stay as they are
+fn decode_references(value: &str) -> String {
+    const NAMED: &[(&str, char)] = &[
+        ("amp", '&'), ("apos", '\''), ("colon", ':'), ("gt", '>'), ("lpar", '('), ("lt", '<'),
+        ("newline", '\n'), ("num", '#'), ("period", '.'), ("quest", '?'), ("quot", '"'),
+        ("rpar", ')'), ("sol", '/'), ("tab", '\t'),
+    ];
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('&') {
+        out.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        let (decoded, len) = if let Some(number) = rest.strip_prefix('#') {
+            let (digits, radix, prefix) = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16, 2),
+                None => (number, 10, 1),
+            };
+            let end = digits
+                .find(|c: char| !c.is_digit(radix))
+                .unwrap_or(digits.len());
+            let code = u32::from_str_radix(&digits[..end], radix).ok();
+            // Out of range numbers still end the reference
+            let decoded = code.map(|code| char::from_u32(code).unwrap_or('\u{FFFD}'));
+            (decoded, prefix + end)
+        } else {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            let name = rest[..end].to_ascii_lowercase();
+            let decoded = NAMED
+                .iter()
+                .find(|(known, _)| *known == name)
+                .map(|&(_, c)| c);
+            (decoded, end)
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[len..];
+                rest = rest.strip_prefix(';').unwrap_or(rest);
+            }
+            None => out.push('&'),
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removes_scripts() {
+        let svg = "<svg><g><script type=\"text/javascript\">alert('<b>')</script><text>A</text></g><SCRIPT src='x.js'/></svg>";
+        assert_eq!(sanitize(svg), "<svg><g><text>A</text></g></svg>");
+    }
+
+    #[test]
+    fn test_removes_handlers_and_javascript_links() {
+        let svg = r#"<svg onload="alert(1)"><a href=" JavaScript:alert(1)" target="_top"><rect width='10' onclick='x()'/></a><a xlink:href="https://example.com">ok</a></svg>"#;
+        assert_eq!(
+            sanitize(svg),
+            r#"<svg><a target="_top"><rect width="10"/></a><a xlink:href="https://example.com">ok</a></svg>"#
+        );
+    }
+
+    #[test]
+    fn test_keeps_regular_svg() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 10 10" style="width:10px;"><defs><filter id="f1"><feGaussianBlur stdDeviation="2"/></filter></defs><g data-entity="A"><a href="docs/a.html" xlink:href="mailto:team@example.com" target="_top" xlink:show="new"><text x="1" y="2" filter="url(#f1)">日本語 &lt;ok&gt;</text></a><image href="data:image/png;base64,AAAA" width="1" height="1"/><use xlink:href="#f1"/></g></svg>"##;
+        assert_eq!(sanitize(svg), svg);
+        // Only the SVG itself is kept
+        let wrapped = format!("<?xml version=\"1.0\"?><!DOCTYPE svg><!-- a <script> in a comment -->{}<?plantuml 1.2024.0?>after", svg);
+        assert_eq!(sanitize(&wrapped), svg);
+    }
+
+    #[test]
+    fn test_diagram_cannot_restyle_the_page() {
+        let svg = "<svg style=\"width:10px;position:fixed\"><style>body { display: none; }</style><style><![CDATA[.toolbar { display: none; }]]></style><rect/></svg>";
+        assert_eq!(sanitize(svg), r#"<svg style="width:10px;"><rect/></svg>"#);
+        for style in [
+            "background:url(https://example.com/track)",
+            "background: URL ( https://example.com/track )",
+            "background:u&#114;l(https://example.com/track)",
+            "background:u\\72l(https://example.com/track)",
+            "@import 'https://example.com/a.css'",
+            "position :  fixed",
+        ] {
+            let svg = format!("<svg><rect style=\"fill:red;{}\"/></svg>", style);
+            assert_eq!(sanitize(&svg), r#"<svg><rect style="fill:red;"/></svg>"#, "{}", style);
+        }
+    }
+
+    #[test]
+    fn test_removes_animations_and_foreign_content() {
+        let svg = r##"<svg><a href="#x"><set attributeName="href" to="javascript:alert(1)"/><animate attributeName="href" values="javascript:alert(1)"></animate><text>A</text></a><foreignObject width="10"><iframe src="javascript:alert(1)"></iframe><div>x</div></foreignObject><rect/></svg>"##;
+        assert_eq!(sanitize(svg), r##"<svg><a href="#x"><text>A</text></a><rect/></svg>"##);
+    }
+
+    #[test]
+    fn test_removes_disguised_javascript_links() {
+        for link in [
+            "jav&#x61;script:alert(1)",
+            "&#106;avascript:alert(1)",
+            "&#0000106avascript:alert(1)",
+            "javascript&colon;alert(1)",
+            "java\tscript:alert(1)",
+            "java\nscript:alert(1)",
+            "java&#x09;script:alert(1)",
+            "java&Tab;script:alert(1)",
+            "java&NewLine;script:alert(1)",
+            "\u{1}javascript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "jav&unknown;ascript:alert(1)",
+        ] {
+            for attribute in ["href", "xlink:href"] {
+                let svg = format!("<svg><a {}=\"{}\">x</a></svg>", attribute, link);
+                assert_eq!(sanitize(&svg), "<svg><a>x</a></svg>", "{}", link);
+            }
+        }
+        // Images may come from data URLs, `<use>` only from the document
+        assert_eq!(
+            sanitize(r#"<svg><image href="data:text/html,x"/><use href="https://example.com/a.svg#b"/></svg>"#),
+            "<svg><image/><use/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_parser_breakouts() {
+        for svg in [
+            "<svg><!--x--!><img src=x onerror=alert(1)>--><text>A</text></svg>",
+            "<svg><style><img src=x onerror=alert(1)></style><text>A</text></svg>",
+            "<svg><text>A</text></svg><img src=x onerror=alert(1)>",
+            "<svg></svg><![CDATA[><img src=x onerror=alert(1)>]]>",
+            "<svg><?x \"?><img src=x onerror=alert(1)>\"?><text>A</text></svg>",
+            "<svg><rect x=\"1\"\" onclick=\"alert(1)\"/></svg>",
+        ] {
+            let sanitized = sanitize(svg);
+            assert!(!sanitized.contains("<img"), "{}", sanitized);
+            assert!(!sanitized.contains("on"), "{}", sanitized);
+        }
+    }
+
+    #[test]
+    fn test_repeated_angle_brackets() {
+        assert_eq!(sanitize("<<é"), "");
+        assert_eq!(sanitize("rnote<<rnoteé"), "");
+        assert_eq!(sanitize("<svg><<é</svg>"), "<svg>&lt;&lt;é</svg>");
+    }
+}
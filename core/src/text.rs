@@ -0,0 +1,94 @@
+// Text utilities that count and cut by user-perceived characters
+//
+// Counters and previews are defined in "characters" as the user sees them,
+// so they work on grapheme clusters instead of bytes. Slicing by byte index
+// would panic on multi-byte text such as Japanese. Hard limits count Unicode
+// scalar values instead (`scalar_count`): a grapheme cluster can hold any
+// number of combining marks, so counting clusters would not bound the size.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Text and selection (UTF-16 offsets, as the browser reports them) after an
+/// editor command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Number of user-perceived characters (grapheme clusters) in `text`
+///
+/// "が" written with a combining mark or a flag emoji counts as one character.
+pub fn char_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// Number of Unicode scalar values (`char`s) in `text`, as the size limits
+/// count them
+///
+/// Same as `char_count` for most text, but "か" with a combining dakuten is
+/// two, and a letter followed by a million combining marks is a million and one.
+pub fn scalar_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Length of `text` in UTF-16 units
+pub fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Longest prefix of `text` with at most `max_chars` characters
+///
+/// Always cuts on a grapheme boundary, so the result is valid UTF-8 and never
+/// splits a combined character.
+pub fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.grapheme_indices(true).nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// First `max_lines` lines of `text`, cut to `max_chars` characters
+///
+/// Appends "..." when the lines had to be cut.
+pub fn preview(text: &str, max_lines: usize, max_chars: usize) -> String {
+    let preview = text.lines().take(max_lines).collect::<Vec<_>>().join("\n");
+    let truncated = truncate_chars(&preview, max_chars);
+
+    if truncated.len() < preview.len() {
+        format!("{}...", truncated)
+    } else {
+        preview
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_count() {
+        assert_eq!(char_count(""), 0);
+        assert_eq!(char_count("Alice -> Bob"), 12);
+        assert_eq!(char_count("こんにちは"), 5);
+        // "か" + combining dakuten, and a flag made of two regional indicators
+        assert_eq!(char_count("か\u{3099}🇯🇵"), 2);
+        assert_eq!(scalar_count("か\u{3099}🇯🇵"), 4);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("こんにちは", 3), "こんに");
+        assert_eq!(truncate_chars("こんにちは", 5), "こんにちは");
+        assert_eq!(truncate_chars("こんにちは", 10), "こんにちは");
+        assert_eq!(truncate_chars("か\u{3099}き", 1), "か\u{3099}");
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("a\nb\nc\nd", 3, 100), "a\nb\nc");
+        assert_eq!(preview(&"あ".repeat(120), 3, 100), format!("{}...", "あ".repeat(100)));
+        assert_eq!(preview(&"あ".repeat(100), 3, 100), "あ".repeat(100));
+    }
+}
@@ -0,0 +1,122 @@
+// Decoding of imported text files
+//
+// `.puml` files come from many editors: Windows tools often save UTF-16 or
+// Shift_JIS, and most of them write CRLF line endings. `decode_text_file`
+// detects the encoding (byte order mark, UTF-16 without one, UTF-8, then
+// Shift_JIS as the usual legacy encoding of Japanese files) and returns the
+// text as UTF-8 with `\n` line endings, so the editor never shows mojibake.
+// Anything else is reported as EncodingError instead of being imported.
+
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8};
+
+use crate::models::ErrorCode;
+use crate::normalize::{normalize, NormalizeOptions};
+
+/// Encoding of an imported file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    ShiftJis,
+}
+
+impl TextEncoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::ShiftJis => "Shift_JIS",
+        }
+    }
+
+    fn encoding(&self) -> &'static Encoding {
+        match self {
+            Self::Utf8 => UTF_8,
+            Self::Utf16Le => UTF_16LE,
+            Self::Utf16Be => UTF_16BE,
+            Self::ShiftJis => SHIFT_JIS,
+        }
+    }
+}
+
+/// Text of an imported file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedText {
+    /// Content with `\n` line endings and no byte order mark
+    pub text: String,
+    /// Encoding the file was in
+    pub encoding: TextEncoding,
+    /// The file had CRLF (or CR) line endings
+    pub converted_line_endings: bool,
+}
+
+/// Decode an imported file, see the module comment
+pub fn decode_text_file(bytes: &[u8]) -> Result<ImportedText, ErrorCode> {
+    let (encoding, body) = match Encoding::for_bom(bytes) {
+        Some((encoding, bom_length)) => (bom_encoding(encoding), &bytes[bom_length..]),
+        None => (detect_without_bom(bytes), bytes),
+    };
+    let encoding = encoding.ok_or_else(unknown_encoding)?;
+    let text = encoding
+        .encoding()
+        .decode_without_bom_handling_and_without_replacement(body)
+        .ok_or_else(|| ErrorCode::EncodingError {
+            encoding: encoding.name().to_string(),
+        })?;
+    // NUL and other control characters mean a binary file, not text
+    if text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')) {
+        return Err(unknown_encoding());
+    }
+
+    let converted_line_endings = text.contains('\r');
+    let options = NormalizeOptions {
+        trim_trailing_whitespace: false,
+    };
+    Ok(ImportedText {
+        text: normalize(&text, options).into_owned(),
+        encoding,
+        converted_line_endings,
+    })
+}
+
+fn bom_encoding(encoding: &'static Encoding) -> Option<TextEncoding> {
+    if encoding == UTF_8 {
+        Some(TextEncoding::Utf8)
+    } else if encoding == UTF_16LE {
+        Some(TextEncoding::Utf16Le)
+    } else if encoding == UTF_16BE {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// UTF-16 without a BOM shows up as NUL bytes in every other position
+/// (PlantUML keywords and most symbols are ASCII); otherwise UTF-8 if valid,
+/// then Shift_JIS
+fn detect_without_bom(bytes: &[u8]) -> Option<TextEncoding> {
+    if bytes.len() >= 2 && bytes.len().is_multiple_of(2) {
+        let units = bytes.len() / 2;
+        let zeros_at = |offset: usize| bytes.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 2 > units && even < odd {
+            return Some(TextEncoding::Utf16Le);
+        }
+        if even * 2 > units && odd < even {
+            return Some(TextEncoding::Utf16Be);
+        }
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Some(TextEncoding::Utf8);
+    }
+    let (_, had_errors) = SHIFT_JIS.decode_without_bom_handling(bytes);
+    (!had_errors).then_some(TextEncoding::ShiftJis)
+}
+
+fn unknown_encoding() -> ErrorCode {
+    ErrorCode::EncodingError {
+        encoding: "UTF-8・UTF-16・Shift_JIS 以外".to_string(),
+    }
+}
@@ -0,0 +1,246 @@
+// Prose regions of PlantUML source
+//
+// Splits the source into natural-language text (notes, labels, titles,
+// legends, comments and quoted names) and everything else, so the editor can
+// spellcheck the former without flagging identifiers and keywords. Like the
+// stats scan this is line-based, not a parser; anything it does not recognize
+// counts as code.
+
+/// What a span of source contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// Keywords, identifiers, arrows and directives
+    Code,
+    /// Text written for people to read
+    Prose,
+}
+
+/// Span of the source (byte offsets)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSpan {
+    pub kind: SpanKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Keywords whose text may continue on the following lines until
+/// `end <keyword>` (or `end<keyword>`)
+const BLOCK_KEYWORDS: &[&str] = &["note", "rnote", "hnote", "legend", "title", "header", "footer"];
+
+/// Keywords followed by prose on the same line
+const TEXT_KEYWORDS: &[&str] = &["title", "header", "footer", "caption"];
+
+/// Spans covering the whole source in order, alternating between code and prose
+pub fn prose_spans(content: &str) -> Vec<TextSpan> {
+    let mut prose: Vec<(usize, usize)> = Vec::new();
+    let mut scan = Scan::default();
+    let mut offset = 0;
+    for raw in content.split_inclusive('\n') {
+        let line = raw.trim_end_matches(['\n', '\r']);
+        scan.line(line, offset, &mut prose);
+        offset += raw.len();
+    }
+    fill(content.len(), prose)
+}
+
+#[derive(Default)]
+struct Scan {
+    in_comment: bool,
+    /// Keyword of the open multi-line block
+    block: Option<&'static str>,
+    /// Brace depth; labels after ':' inside braces are member types
+    depth: usize,
+}
+
+impl Scan {
+    fn line(&mut self, line: &str, offset: usize, prose: &mut Vec<(usize, usize)>) {
+        let trimmed = line.trim();
+        let start = offset + (line.len() - line.trim_start().len());
+        let end = start + trimmed.len();
+
+        if self.in_comment {
+            self.in_comment = !trimmed.ends_with("'/");
+            prose.push((start, end));
+            return;
+        }
+        if let Some(comment) = trimmed.strip_prefix("/'") {
+            self.in_comment = !comment.ends_with("'/");
+            prose.push((start + 2, end));
+            return;
+        }
+        if let Some(keyword) = self.block {
+            let words: String = trimmed.split_whitespace().collect::<String>().to_lowercase();
+            if words.strip_prefix("end") == Some(keyword) {
+                self.block = None;
+            } else {
+                prose.push((start, end));
+            }
+            return;
+        }
+        if trimmed.starts_with('\'') {
+            prose.push((start + 1, end));
+            return;
+        }
+        if trimmed.is_empty() || trimmed.starts_with(['@', '!']) {
+            return;
+        }
+
+        let lower = trimmed.to_lowercase();
+        let first = lower.split_whitespace().next().unwrap_or_default();
+        if first == "skinparam" {
+            return;
+        }
+        if let Some(text) = trimmed.strip_prefix(':') {
+            // Activity: `:text;`
+            let text_end = text.rfind(';').unwrap_or(text.len());
+            prose.push((start + 1, start + 1 + text_end));
+            return;
+        }
+
+        let block = BLOCK_KEYWORDS.iter().find(|keyword| **keyword == first);
+        let is_note = matches!(first, "note" | "rnote" | "hnote");
+        if TEXT_KEYWORDS.contains(&first) && !is_note {
+            let rest = trimmed[first.len()..].trim_start();
+            if rest.is_empty() {
+                self.block = block.copied();
+            } else {
+                prose.push((end - rest.len(), end));
+            }
+            return;
+        }
+        if first == "legend" {
+            self.block = block.copied();
+            return;
+        }
+
+        let label = self.quoted_and_label(trimmed, start, prose);
+        if is_note && label.is_none() && !trimmed.contains('"') {
+            // Without ':' or a quoted text the note continues until "end note"
+            self.block = block.copied();
+        }
+        if let Some(label) = label {
+            prose.push((label, end));
+        }
+    }
+
+    /// Record quoted strings and return where a `: label` starts, if any
+    fn quoted_and_label(
+        &mut self,
+        line: &str,
+        start: usize,
+        prose: &mut Vec<(usize, usize)>,
+    ) -> Option<usize> {
+        let mut quote_start = None;
+        let mut chars = line.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            match (c, quote_start) {
+                ('"', None) => quote_start = Some(index + 1),
+                ('"', Some(open)) => {
+                    prose.push((start + open, start + index));
+                    quote_start = None;
+                }
+                (_, Some(_)) => {}
+                ('{', None) => self.depth += 1,
+                ('}', None) => self.depth = self.depth.saturating_sub(1),
+                (':', None) if self.depth == 0 => {
+                    // `::` separates namespaces
+                    if chars.peek().is_some_and(|(_, next)| *next == ':') {
+                        chars.next();
+                        continue;
+                    }
+                    let label = &line[index + 1..];
+                    let skipped = label.len() - label.trim_start().len();
+                    return Some(start + index + 1 + skipped);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Merge the prose ranges and fill the gaps with code
+fn fill(len: usize, mut prose: Vec<(usize, usize)>) -> Vec<TextSpan> {
+    prose.retain(|(start, end)| start < end);
+    prose.sort_unstable();
+
+    let mut spans: Vec<TextSpan> = Vec::new();
+    let mut push = |kind, start: usize, end: usize| {
+        if start >= end {
+            return;
+        }
+        match spans.last_mut() {
+            Some(last) if last.kind == kind && last.end >= start => last.end = last.end.max(end),
+            _ => spans.push(TextSpan { kind, start, end }),
+        }
+    };
+    let mut position = 0;
+    for (start, end) in prose {
+        let start = start.max(position);
+        push(SpanKind::Code, position, start);
+        push(SpanKind::Prose, start, end);
+        position = position.max(end);
+    }
+    push(SpanKind::Code, position, len);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prose(content: &str) -> Vec<&str> {
+        prose_spans(content)
+            .into_iter()
+            .filter(|span| span.kind == SpanKind::Prose)
+            .map(|span| &content[span.start..span.end])
+            .collect()
+    }
+
+    #[test]
+    fn test_spans_cover_the_source() {
+        let source = "@startuml\nAlice -> Bob : Hello there\n@enduml";
+        let spans = prose_spans(source);
+        assert_eq!(spans.first().unwrap().start, 0);
+        assert_eq!(spans.last().unwrap().end, source.len());
+        assert!(spans.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert!(spans.windows(2).all(|pair| pair[0].kind != pair[1].kind));
+    }
+
+    #[test]
+    fn test_labels_notes_and_titles() {
+        let source = "@startuml\n\
+            title Odrer flow\n\
+            participant \"Web Shop\" as Shop\n\
+            ' recieve the order\n\
+            Shop -> Shop : valdate cart\n\
+            note left of Shop\n  chekc stock\nend note\n\
+            note right : paymnet\n\
+            :ship parcel;\n\
+            @enduml";
+        assert_eq!(
+            prose(source),
+            vec![
+                "Odrer flow",
+                "Web Shop",
+                " recieve the order",
+                "valdate cart",
+                "chekc stock",
+                "paymnet",
+                "ship parcel",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identifiers_stay_code() {
+        let source = "@startuml\n!theme plain\nskinparam shadowing false\nclass net::Order {\n  +total : Decimal\n}\nOrder --> Item\n@enduml";
+        assert!(prose(source).is_empty());
+    }
+
+    #[test]
+    fn test_legend_and_block_comment() {
+        let source = "legend\nSee the wiki\nendlegend\n/' multi\nline '/\nA -> B";
+        assert_eq!(prose(source), vec!["See the wiki", " multi", "line '/"]);
+    }
+}
@@ -1,6 +1,14 @@
 // Validation logic for PlantUML content
 
 use crate::models::{ErrorCode, StatusLevel};
+use crate::normalize::{normalize, NormalizeOptions};
+use crate::text::scalar_count;
+
+/// Maximum document length in characters (300 lines × 80 chars/line)
+pub const MAX_CONTENT_CHARS: usize = 24_000;
+
+/// Length from which users are warned that the limit is near (250 lines)
+pub const SOFT_LIMIT_CHARS: usize = 20_000;
 
 /// Validation errors
 #[derive(Debug, thiserror::Error)]
@@ -34,25 +42,45 @@ impl ValidationError {
 ///
 /// # Rules
 /// - Content must not be empty
-/// - Content must be within 24,000 character limit (300 lines × 80 chars/line)
+/// - Content must be within 24,000 character limit (300 lines × 80 chars/line),
+///   counted as Unicode scalar values rather than bytes (see `scalar_count`)
+///
+/// Both rules apply to the normalized content (see `normalize`), so a BOM,
+/// `\r\n` line endings and trailing whitespace do not count.
 ///
 /// Note: @startuml/@enduml tags are NOT validated here.
 /// PlantUML.jar will generate an error image if tags are missing.
 pub fn validate_plantuml_content(content: &str) -> Result<(), ValidationError> {
+    let content = normalize(content, NormalizeOptions::default());
+
     // Empty check
     if content.trim().is_empty() {
         return Err(ValidationError::EmptyContent);
     }
 
     // Character limit check (300 lines × 80 chars/line = 24,000 chars)
-    const MAX_CHARS: usize = 24_000;
-    if content.len() > MAX_CHARS {
-        return Err(ValidationError::ContentTooLarge(content.len(), MAX_CHARS));
+    let chars = scalar_count(&content);
+    if chars > MAX_CONTENT_CHARS {
+        return Err(ValidationError::ContentTooLarge(chars, MAX_CONTENT_CHARS));
     }
 
     Ok(())
 }
 
+/// Warning for content over the soft limit but still within the hard one
+///
+/// Conversions still succeed with it (as a Warning-level result), so users
+/// can split a diagram before edits start failing with ValidationTextLimit.
+pub fn content_size_warning(content: &str) -> Option<ErrorCode> {
+    let chars = scalar_count(&normalize(content, NormalizeOptions::default()));
+    (chars > SOFT_LIMIT_CHARS && chars <= MAX_CONTENT_CHARS).then_some(
+        ErrorCode::ValidationTextNearLimit {
+            actual: chars,
+            max: MAX_CONTENT_CHARS,
+        },
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +127,55 @@ mod tests {
             Err(ValidationError::ContentTooLarge(_, _))
         ));
     }
+
+    #[test]
+    fn test_invisible_characters_do_not_count() {
+        assert!(matches!(
+            validate_plantuml_content("\u{feff}  \r\n"),
+            Err(ValidationError::EmptyContent)
+        ));
+        // Trailing spaces pasted from another tool do not reach the limit
+        let content = format!("{}\r\n", "x".repeat(MAX_CONTENT_CHARS - 1)) + &" ".repeat(100);
+        assert!(validate_plantuml_content(&content).is_ok());
+    }
+
+    #[test]
+    fn test_content_size_warning() {
+        assert_eq!(content_size_warning(&"x".repeat(SOFT_LIMIT_CHARS)), None);
+        let warning = content_size_warning(&"あ".repeat(SOFT_LIMIT_CHARS + 1)).unwrap();
+        assert_eq!(
+            warning,
+            ErrorCode::ValidationTextNearLimit {
+                actual: SOFT_LIMIT_CHARS + 1,
+                max: MAX_CONTENT_CHARS
+            }
+        );
+        assert_eq!(warning.status_level(), StatusLevel::Warning);
+        assert!(content_size_warning(&"x".repeat(MAX_CONTENT_CHARS)).is_some());
+        // Over the hard limit it is an error from validation instead
+        assert_eq!(content_size_warning(&"x".repeat(MAX_CONTENT_CHARS + 1)), None);
+    }
+
+    #[test]
+    fn test_limit_counts_characters_not_bytes() {
+        // 24,000 Japanese characters are 72,000 bytes but still within the limit
+        let content = "あ".repeat(MAX_CONTENT_CHARS);
+        assert!(validate_plantuml_content(&content).is_ok());
+
+        let content = "あ".repeat(MAX_CONTENT_CHARS + 1);
+        assert!(matches!(
+            validate_plantuml_content(&content),
+            Err(ValidationError::ContentTooLarge(24_001, MAX_CONTENT_CHARS))
+        ));
+    }
+
+    #[test]
+    fn test_limit_counts_combining_marks() {
+        // One grapheme cluster, but a million characters of text
+        let content = format!("a{}", "\u{301}".repeat(1_000_000));
+        assert!(matches!(
+            validate_plantuml_content(&content),
+            Err(ValidationError::ContentTooLarge(1_000_001, MAX_CONTENT_CHARS))
+        ));
+    }
 }
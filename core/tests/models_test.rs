@@ -20,6 +20,19 @@ fn test_document_id_generation() {
     assert_ne!(doc1.id, doc2.id);
 }
 
+#[test]
+fn test_id_strategy() {
+    let source = "@startuml\nA -> B\n@enduml";
+    let id = IdStrategy::Content.id_for(source);
+    assert_eq!(id, DocumentId::from_content(source));
+    assert_eq!(id.0.get_version_num(), 5);
+    assert_ne!(id, IdStrategy::Content.id_for("@startuml\nB -> A\n@enduml"));
+
+    assert_eq!(IdStrategy::default(), IdStrategy::Random);
+    assert_ne!(IdStrategy::Random.id_for(source), IdStrategy::Random.id_for(source));
+    assert_eq!(serde_json::to_string(&IdStrategy::Content).unwrap(), "\"content\"");
+}
+
 // ==================== PlantUMLDocument Tests ====================
 
 #[test]
@@ -33,6 +46,24 @@ fn test_document_creation() {
     assert_eq!(doc.created_at, doc.updated_at);
 }
 
+#[test]
+fn test_document_duplicate() {
+    let mut doc = PlantUMLDocument::new("@startuml\ntitle Login\n@enduml".to_string());
+    doc.created_at = 1;
+
+    let copy = doc.duplicate();
+    assert_ne!(copy.id, doc.id);
+    assert_eq!(copy.content, doc.content);
+    assert_eq!(copy.title.as_deref(), Some("Login のコピー"));
+    assert!(copy.created_at > doc.created_at);
+
+    doc.title = Some("設計案".to_string());
+    assert_eq!(doc.duplicate().title.as_deref(), Some("設計案 のコピー"));
+
+    let untitled = PlantUMLDocument::new("@startuml\n@enduml".to_string());
+    assert_eq!(untitled.duplicate().title.as_deref(), Some("無題 のコピー"));
+}
+
 #[test]
 fn test_document_validation_valid() {
     let content = "@startuml\nAlice -> Bob: Hello\n@enduml".to_string();
@@ -154,6 +185,20 @@ fn test_diagram_image_to_data_url_svg() {
     assert!(data_url.starts_with("data:image/svg+xml;base64,"));
 }
 
+#[test]
+fn test_diagram_image_to_data_url_pdf() {
+    let image = DiagramImage {
+        document_id: DocumentId::new(),
+        format: ImageFormat::Pdf,
+        data: b"%PDF-1.4".to_vec(),
+        dimensions: (0, 0),
+        generated_at: chrono::Utc::now().timestamp(),
+    };
+
+    assert!(image.to_data_url().starts_with("data:application/pdf;base64,"));
+    assert_eq!(serde_json::to_string(&ImageFormat::Pdf).unwrap(), "\"pdf\"");
+}
+
 /// ==================== StorageSlot Tests ====================
 
 #[test]
@@ -176,12 +221,34 @@ fn test_storage_slot_key() {
     assert_eq!(StorageSlot::storage_key(10), "plantuml_slot_10");
 }
 
+#[test]
+fn test_storage_slot_schema_version() {
+    let doc = PlantUMLDocument::new("@startuml\n@enduml".to_string());
+    let slot = StorageSlot::new(2, doc, 0);
+    assert_eq!(slot.schema_version, StorageSlot::SCHEMA_VERSION);
+    assert!(!slot.pinned);
+
+    // Slots saved before versioning read as version 1
+    let mut json = serde_json::to_value(&slot).unwrap();
+    let object = json.as_object_mut().unwrap();
+    object.remove("schema_version");
+    object.remove("pinned");
+    let legacy: StorageSlot = serde_json::from_value(json).unwrap();
+    assert_eq!(legacy.schema_version, 1);
+    assert!(!legacy.pinned);
+}
+
 #[test]
 fn test_convert_request_validation() {
     // Valid request with tags
     let valid_request = ConvertRequest {
         plantuml_text: "@startuml\nAlice -> Bob\n@enduml".to_string(),
         format: ImageFormat::Png,
+        scale: ExportScale::X1,
+        font: None,
+        deterministic: false,
+        embed_source: false,
+        pipeline: None,
     };
     assert!(valid_request.validate().is_ok());
     
@@ -189,6 +256,11 @@ fn test_convert_request_validation() {
     let valid_without_tags = ConvertRequest {
         plantuml_text: "Alice -> Bob".to_string(),
         format: ImageFormat::Png,
+        scale: ExportScale::X1,
+        font: None,
+        deterministic: false,
+        embed_source: false,
+        pipeline: None,
     };
     assert!(valid_without_tags.validate().is_ok());
     
@@ -196,6 +268,11 @@ fn test_convert_request_validation() {
     let invalid_empty = ConvertRequest {
         plantuml_text: "   ".to_string(),
         format: ImageFormat::Png,
+        scale: ExportScale::X1,
+        font: None,
+        deterministic: false,
+        embed_source: false,
+        pipeline: None,
     };
     assert!(invalid_empty.validate().is_err());
     
@@ -203,10 +280,41 @@ fn test_convert_request_validation() {
     let invalid_too_large = ConvertRequest {
         plantuml_text: "x".repeat(25000),
         format: ImageFormat::Png,
+        scale: ExportScale::X1,
+        font: None,
+        deterministic: false,
+        embed_source: false,
+        pipeline: None,
     };
     assert!(invalid_too_large.validate().is_err());
 }
 
+#[test]
+fn test_convert_request_scale_defaults_to_1x() {
+    let request: ConvertRequest =
+        serde_json::from_str(r#"{"plantuml_text":"A -> B","format":"png"}"#).unwrap();
+    assert_eq!(request.scale, ExportScale::X1);
+
+    let request: ConvertRequest =
+        serde_json::from_str(r#"{"plantuml_text":"A -> B","format":"png","scale":"4x"}"#).unwrap();
+    assert_eq!(request.scale.factor(), 4);
+
+    // The default is left out so older servers keep accepting requests
+    let json = serde_json::to_string(&ConvertRequest {
+        plantuml_text: "A -> B".to_string(),
+        format: ImageFormat::Png,
+        scale: ExportScale::X1,
+        font: None,
+        deterministic: false,
+        embed_source: false,
+        pipeline: None,
+    })
+    .unwrap();
+    for field in ["scale", "font", "deterministic", "embed_source", "pipeline"] {
+        assert!(!json.contains(field), "{}", field);
+    }
+}
+
 // ==================== ErrorCode Tests ====================
 
 #[test]
@@ -225,6 +333,23 @@ fn test_error_code_to_message_success() {
         ErrorCode::DeleteSuccess { slot_number: 7 }.to_message(),
         "スロット7のデータを削除しました"
     );
+    assert_eq!(
+        ErrorCode::DuplicateSuccess { from_slot: 2, to_slot: 4 }.to_message(),
+        "スロット2をスロット4に複製しました"
+    );
+    assert_eq!(
+        ErrorCode::RestoreSuccess { slot_number: 3 }.to_message(),
+        "削除したデータをスロット3に元に戻しました"
+    );
+    assert_eq!(ErrorCode::TrashEmptied.to_message(), "ゴミ箱を空にしました");
+    assert_eq!(
+        ErrorCode::MoveSuccess { from_slot: 5, to_slot: 1 }.to_message(),
+        "スロット5をスロット1に移動しました"
+    );
+    assert_eq!(
+        ErrorCode::BulkDeleteSuccess { count: 3 }.to_message(),
+        "3件のスロットを削除しました（ゴミ箱から元に戻せます）"
+    );
 }
 
 #[test]
@@ -236,6 +361,9 @@ fn test_error_code_to_message_validation() {
     let msg = ErrorCode::ValidationTextLimit { actual: 25000, max: 24000 }.to_message();
     assert!(msg.contains("24000"));
     assert!(msg.contains("25000"));
+    let msg = ErrorCode::ValidationTextNearLimit { actual: 21000, max: 24000 }.to_message();
+    assert!(msg.contains("21000"));
+    assert!(msg.contains("分割"));
 }
 
 #[test]
@@ -256,12 +384,160 @@ fn test_error_code_to_message_storage() {
     assert!(msg.contains("test"));
 }
 
+#[test]
+fn test_error_code_server_documents() {
+    assert_eq!(ErrorCode::DocumentSaved.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::DocumentLoaded.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::DocumentDeleted.status_level(), StatusLevel::Info);
+    
+    let code = ErrorCode::DocumentNotFound { id: "abc".to_string() };
+    assert!(code.to_message().contains("abc"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_share_links() {
+    assert_eq!(ErrorCode::ShareLinkCreated.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::ShareLinkInvalid.status_level(), StatusLevel::Warning);
+
+    let code = ErrorCode::ShareLinkExpired { expired_at: 1700000000 };
+    assert!(code.to_message().contains("2023-11-14 22:13 UTC"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_document_roles() {
+    assert!(DocumentRole::Owner > DocumentRole::Editor);
+    assert!(DocumentRole::Editor.can_edit());
+    assert!(!DocumentRole::Viewer.can_edit());
+    assert_eq!(serde_json::to_string(&DocumentRole::Viewer).unwrap(), "\"viewer\"");
+
+    let code = ErrorCode::DocumentAccessDenied { required: DocumentRole::Editor };
+    assert!(code.to_message().contains("編集者"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::DocumentOwnerRequired.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_folders() {
+    assert_eq!(ErrorCode::FolderSaved.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::FolderNameEmpty.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::FolderCycle.status_level(), StatusLevel::Warning);
+
+    let code = ErrorCode::FolderNotFound { id: "abc".to_string() };
+    assert!(code.to_message().contains("abc"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_activity_entry_wire_format() {
+    let entry = ActivityEntry {
+        kind: ActivityKind::Updated,
+        document_id: DocumentId::new(),
+        title: None,
+        user: Some("alice".to_string()),
+        at: 1700000000,
+    };
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["kind"], "updated");
+    assert!(json.get("title").is_none());
+    assert_eq!(ActivityKind::Deleted.label(), "削除");
+}
+
+#[test]
+fn test_duplicate_keeps_folder() {
+    let mut doc = PlantUMLDocument::new("@startuml\nA -> B\n@enduml".to_string());
+    doc.folder = Some(FolderId::new());
+    assert_eq!(doc.duplicate().folder, doc.folder);
+
+    // Local documents carry no folder on the wire
+    let json = serde_json::to_value(PlantUMLDocument::new("A".to_string())).unwrap();
+    assert!(json.get("folder").is_none());
+}
+
+#[test]
+fn test_document_timestamps_from_clock() {
+    use plantuml_editor_core::clock::{Clock, MockClock};
+
+    let clock = MockClock::new(1_700_000_000);
+    let doc = PlantUMLDocument::new_at("@startuml\nA -> B\n@enduml".to_string(), clock.now());
+    assert_eq!((doc.created_at, doc.updated_at), (1_700_000_000, 1_700_000_000));
+
+    clock.advance(60);
+    let copy = doc.duplicate_at(clock.now());
+    assert_eq!((copy.created_at, copy.updated_at), (1_700_000_060, 1_700_000_060));
+}
+
+#[test]
+fn test_error_code_request_too_large() {
+    let code = ErrorCode::RequestTooLarge { max_bytes: 1048576 };
+    assert!(code.to_message().contains("1048576"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_rasterized_export() {
+    let code = ErrorCode::RasterizedExport;
+    assert!(code.to_message().contains("SVG"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_deleted_without_trash() {
+    let code = ErrorCode::DeletedWithoutTrash { slot_number: 4 };
+    assert!(code.to_message().contains("スロット4"));
+    assert!(code.to_message().contains("元に戻せません"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_large_svg() {
+    let code = ErrorCode::LargeSvg { svg_bytes: 3000000, threshold_bytes: 2097152 };
+    assert!(code.to_message().contains("3000000"));
+    assert!(code.to_message().contains("PNGで表示"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+
+    let code = ErrorCode::SvgDowngraded { svg_bytes: 3000000 };
+    assert!(code.to_message().contains("PNG"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_maintenance() {
+    assert!(ErrorCode::Maintenance.to_message().contains("メンテナンス"));
+    assert_eq!(ErrorCode::Maintenance.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::AdminAccessDenied.status_level(), StatusLevel::Warning);
+    assert!(ErrorCode::AuthenticationRequired.to_message().contains("ログイン"));
+    assert_eq!(ErrorCode::AuthenticationRequired.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_render_queue_full() {
+    let code = ErrorCode::RenderQueueFull { max_concurrency: 4 };
+    assert!(code.to_message().contains("4"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_error_code_quota_exceeded() {
+    // 2023-11-15 00:00 UTC
+    let code = ErrorCode::QuotaExceeded { limit: 1000, reset_at: 1700006400 };
+    let message = code.to_message();
+    assert!(message.contains("1000"));
+    assert!(message.contains("2023-11-15 00:00 UTC"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
 #[test]
 fn test_error_code_to_message_processing() {
-    let msg = ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000 }.to_message();
+    let msg = ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000, width: None, height: None, max_dimension: None }.to_message();
     assert!(msg.contains("5000"));
     assert!(msg.contains("4000"));
     
+    let msg = ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000, width: Some(9000), height: Some(300), max_dimension: Some(8192) }.to_message();
+    assert!(msg.contains("9000×300"));
+    assert!(msg.contains("8192"));
+    
     let msg = ErrorCode::EncodingError { encoding: "UTF-8".to_string() }.to_message();
     assert!(msg.contains("UTF-8"));
     
@@ -294,15 +570,23 @@ fn test_error_code_status_level_info() {
     assert_eq!(ErrorCode::SaveSuccess { slot_number: 1 }.status_level(), StatusLevel::Info);
     assert_eq!(ErrorCode::LoadSuccess { slot_number: 1 }.status_level(), StatusLevel::Info);
     assert_eq!(ErrorCode::DeleteSuccess { slot_number: 1 }.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::DuplicateSuccess { from_slot: 1, to_slot: 2 }.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::RestoreSuccess { slot_number: 1 }.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::TrashEmptied.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::MoveSuccess { from_slot: 2, to_slot: 1 }.status_level(), StatusLevel::Info);
+    assert_eq!(ErrorCode::BulkDeleteSuccess { count: 3 }.status_level(), StatusLevel::Info);
 }
 
 #[test]
 fn test_error_code_status_level_warning() {
     assert_eq!(ErrorCode::ValidationEmpty.status_level(), StatusLevel::Warning);
     assert_eq!(ErrorCode::ValidationTextLimit { actual: 25000, max: 24000 }.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::ValidationTextNearLimit { actual: 21000, max: 24000 }.status_level(), StatusLevel::Warning);
     assert_eq!(ErrorCode::StorageInputLimit { actual: 25000, max: 24000 }.status_level(), StatusLevel::Warning);
     assert_eq!(ErrorCode::StorageSlotLimit { max_slots: 10 }.status_level(), StatusLevel::Warning);
-    assert_eq!(ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000 }.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::SizeLimit { actual_bytes: 5000, max_bytes: 4000, width: None, height: None, max_dimension: None }.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::PostProcessorUnknown { name: "sharpen".to_string() }.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::PostProcessorSkipped { name: "watermark".to_string(), format: "PNG".to_string() }.status_level(), StatusLevel::Warning);
 }
 
 #[test]
@@ -318,6 +602,15 @@ fn test_error_code_status_level_error() {
     assert_eq!(ErrorCode::NetworkError { endpoint: "/api".to_string() }.status_level(), StatusLevel::Error);
 }
 
+#[test]
+fn test_error_code_is_transient() {
+    assert!(ErrorCode::RenderQueueFull { max_concurrency: 4 }.is_transient());
+    assert!(ErrorCode::TimeoutError { duration_ms: 30000 }.is_transient());
+    assert!(ErrorCode::Maintenance.is_transient());
+    assert!(!ErrorCode::ExportError { format: "SVG".to_string() }.is_transient());
+    assert!(!ErrorCode::ValidationEmpty.is_transient());
+}
+
 // ==================== ProcessResult Tests ====================
 
 #[test]
@@ -428,4 +721,99 @@ fn test_storage_error_slot_empty() {
     assert!(error_str.contains("空です"));
 }
 
+#[test]
+fn test_storage_error_slot_occupied() {
+    let error = StorageError::SlotOccupied(4);
+    assert_eq!(error.to_string(), "スロット4は使用中です");
+}
+
+
+
+#[test]
+fn test_document_version() {
+    let doc = PlantUMLDocument::new("@startuml\nA -> B\n@enduml".to_string());
+    assert_eq!(doc.version, 1);
+
+    // Documents saved before versions existed start at 1
+    let mut json = serde_json::to_value(&doc).unwrap();
+    json.as_object_mut().unwrap().remove("version");
+    let legacy: PlantUMLDocument = serde_json::from_value(json).unwrap();
+    assert_eq!(legacy.version, 1);
+
+    let code = ErrorCode::DocumentConflict { current_version: 3 };
+    assert!(code.to_message().contains("バージョン: 3"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+}
+
+#[test]
+fn test_include_codes() {
+    let code = ErrorCode::IncludeNameInvalid { name: "../style".to_string() };
+    assert!(code.to_message().contains("「../style」"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+    assert_eq!(ErrorCode::IncludeSaved.status_level(), StatusLevel::Info);
+    let code = ErrorCode::IncludeFileLimit { max_files: 100 };
+    assert!(code.to_message().contains("100個"));
+    assert_eq!(code.status_level(), StatusLevel::Warning);
+
+    // Uploads are private to the caller unless shared
+    let request: IncludeRequest = serde_json::from_str(r#"{"content":"skinparam shadowing false"}"#).unwrap();
+    assert!(!request.shared);
+}
+
+#[test]
+fn test_user_settings_sync() {
+    // Settings saved before a field existed load with its default
+    let snapshot: SettingsSnapshot =
+        serde_json::from_str(r#"{"settings":{"spellcheck":true},"updated_at":5}"#).unwrap();
+    assert!(snapshot.settings.spellcheck);
+    assert!(snapshot.settings.keep_stale_preview);
+    assert_eq!(snapshot.settings.render_pause_ms, DEFAULT_RENDER_PAUSE_MS);
+
+    // The last write wins, and the current snapshot is kept on a tie
+    let other = SettingsSnapshot {
+        settings: UserSettings { indent_width: 0, ..UserSettings::default() },
+        updated_at: 9,
+    };
+    assert_eq!(snapshot.clone().newer(other.clone()), other);
+    assert_eq!(other.clone().newer(snapshot.clone()), other);
+    let tie = SettingsSnapshot { updated_at: 9, ..snapshot };
+    assert_eq!(other.clone().newer(tie), other);
+
+    assert_eq!(ErrorCode::SettingsSaved.status_level(), StatusLevel::Info);
+}
+
+#[test]
+fn test_diagnostics_request_and_severity() {
+    // Syntax checking is on unless the client opts out
+    let request: DiagnosticsRequest =
+        serde_json::from_str(r#"{"plantuml_text":"@startuml"}"#).unwrap();
+    assert!(request.check_syntax);
 
+    // Severities use the LSP numbers
+    assert_eq!(serde_json::to_value(DiagnosticSeverity::Warning).unwrap(), 2);
+    let severity: DiagnosticSeverity = serde_json::from_str("1").unwrap();
+    assert_eq!(severity, DiagnosticSeverity::Error);
+    assert!(serde_json::from_str::<DiagnosticSeverity>("4").is_err());
+
+    let code = ErrorCode::DiagnosticsReported { errors: 2, warnings: 1 };
+    assert_eq!(code.status_level(), StatusLevel::Info);
+}
+
+#[test]
+fn test_analyze_response() {
+    let response = AnalyzeResponse {
+        result: ProcessResult::new(ErrorCode::DiagramAnalyzed),
+        analysis: Some(plantuml_editor_core::stats::analyze("@startuml\nA -> B\n@enduml")),
+    };
+    let json = serde_json::to_value(&response).unwrap();
+    assert_eq!(json["result"]["level"], "INFO");
+    assert_eq!(json["analysis"]["diagram_type"], "sequence");
+    assert_eq!(json["analysis"]["complexity"], 3);
+    assert_eq!(json["analysis"]["complexity_level"], "low");
+
+    let error = AnalyzeResponse {
+        result: ProcessResult::new(ErrorCode::ValidationEmpty),
+        analysis: None,
+    };
+    assert!(!serde_json::to_string(&error).unwrap().contains("analysis"));
+}
@@ -0,0 +1,52 @@
+// Tests for decoding imported files, with fixtures saved in several encodings
+
+use plantuml_editor_core::text_import::{decode_text_file, TextEncoding};
+use plantuml_editor_core::ErrorCode;
+
+const EXPECTED: &str = "@startuml\nactor 利用者\n利用者 -> サーバー : ログイン\nサーバー --> 利用者 : 完了\n@enduml\n";
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_bytes!(concat!("fixtures/text_import/", $name)).as_slice()
+    };
+}
+
+#[test]
+fn test_decodes_each_encoding_to_utf8() {
+    let cases = [
+        (fixture!("sequence_utf8.puml"), TextEncoding::Utf8, false),
+        (fixture!("sequence_utf8_bom.puml"), TextEncoding::Utf8, true),
+        (fixture!("sequence_utf16le_bom.puml"), TextEncoding::Utf16Le, true),
+        (fixture!("sequence_utf16be_bom.puml"), TextEncoding::Utf16Be, false),
+        (fixture!("sequence_utf16le.puml"), TextEncoding::Utf16Le, false),
+        (fixture!("sequence_shift_jis.puml"), TextEncoding::ShiftJis, true),
+    ];
+    for (bytes, encoding, crlf) in cases {
+        let imported = decode_text_file(bytes).unwrap();
+        assert_eq!(imported.text, EXPECTED);
+        assert_eq!(imported.encoding, encoding);
+        assert_eq!(imported.converted_line_endings, crlf, "{}", encoding.name());
+    }
+}
+
+#[test]
+fn test_rejects_unsupported_and_binary_files() {
+    for bytes in [fixture!("sequence_euc_jp.puml"), fixture!("image.png")] {
+        assert!(matches!(
+            decode_text_file(bytes),
+            Err(ErrorCode::EncodingError { .. })
+        ));
+    }
+    // A UTF-8 BOM followed by invalid UTF-8 names the encoding it claimed
+    assert_eq!(
+        decode_text_file(b"\xef\xbb\xbf@startuml\xff"),
+        Err(ErrorCode::EncodingError { encoding: "UTF-8".to_string() })
+    );
+}
+
+#[test]
+fn test_empty_file_is_empty_text() {
+    let imported = decode_text_file(b"").unwrap();
+    assert_eq!(imported.text, "");
+    assert_eq!(imported.encoding, TextEncoding::Utf8);
+}
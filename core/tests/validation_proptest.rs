@@ -0,0 +1,55 @@
+// Property-based tests for validation
+
+use plantuml_editor_core::{validate_plantuml_content, ValidationError, MAX_CONTENT_CHARS};
+use proptest::prelude::*;
+
+/// PlantUML-ish lines: participants, arrows, notes and directives
+fn plantuml_line() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[A-Za-z][A-Za-z0-9_]{0,12} -> [A-Za-z][A-Za-z0-9_]{0,12}( : [\\PC&&[^\n]]{0,30})?",
+        "(participant|actor|class|entity) [A-Za-z][A-Za-z0-9_]{0,12}",
+        "note (left|right|over) [A-Za-z]{1,8} : [\\PC&&[^\n]]{0,40}",
+        "(skinparam|!theme|title) [\\PC&&[^\n]]{0,30}",
+        "[ \t]{0,4}",
+    ]
+}
+
+fn plantuml_document() -> impl Strategy<Value = String> {
+    prop::collection::vec(plantuml_line(), 0..40)
+        .prop_map(|lines| format!("@startuml\n{}\n@enduml", lines.join("\n")))
+}
+
+proptest! {
+    #[test]
+    fn prop_never_panics(content in "\\PC{0,500}") {
+        let _ = validate_plantuml_content(&content);
+    }
+
+    #[test]
+    fn prop_small_documents_are_valid(content in plantuml_document()) {
+        prop_assert!(validate_plantuml_content(&content).is_ok());
+    }
+
+    #[test]
+    fn prop_whitespace_only_is_empty(content in "[ \t\r\n]{0,50}") {
+        prop_assert!(matches!(
+            validate_plantuml_content(&content),
+            Err(ValidationError::EmptyContent)
+        ));
+    }
+
+    #[test]
+    fn prop_ascii_limit_is_exact(extra in 0usize..200, under in any::<bool>()) {
+        let len = if under { MAX_CONTENT_CHARS - extra } else { MAX_CONTENT_CHARS + 1 + extra };
+        let content = "x".repeat(len);
+        match validate_plantuml_content(&content) {
+            Ok(()) => prop_assert!(under),
+            Err(ValidationError::ContentTooLarge(actual, max)) => {
+                prop_assert!(!under);
+                prop_assert_eq!(actual, len);
+                prop_assert_eq!(max, MAX_CONTENT_CHARS);
+            }
+            Err(e) => prop_assert!(false, "unexpected error: {}", e),
+        }
+    }
+}
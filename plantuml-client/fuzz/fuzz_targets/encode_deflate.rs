@@ -0,0 +1,14 @@
+// Fuzz the deflate encoder used for PlantUML server URLs
+//
+// Run with: cargo +nightly fuzz run encode_deflate (from plantuml-client/)
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plantuml_encoding::{decode_plantuml_deflate, encode_plantuml_deflate};
+
+fuzz_target!(|text: &str| {
+    let encoded = encode_plantuml_deflate(text).expect("encoding valid UTF-8 never fails");
+    let decoded = decode_plantuml_deflate(&encoded).expect("encoded text always decodes");
+    assert_eq!(decoded, text);
+});
@@ -1,10 +1,23 @@
 // PlantUML HTTP client
 
 use crate::errors::ClientError;
-use plantuml_editor_core::{DiagramImage, DocumentId, GenerationResult, ImageFormat};
-use std::time::Duration;
+use plantuml_editor_core::{DiagramImage, DocumentId, ImageFormat};
+use plantuml_editor_core::image_size::image_dimensions;
+use plantuml_editor_core::retry::RENDER_TIMEOUT;
 use plantuml_encoding::encode_plantuml_deflate;
 
+/// Response headers PlantUML sets on diagrams with a syntax error
+const ERROR_HEADER: &str = "X-PlantUML-Diagram-Error";
+const ERROR_LINE_HEADER: &str = "X-PlantUML-Diagram-Error-Line";
+
+/// Syntax error reported by the PlantUML server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// One-based line of the error, when the server reports one
+    pub line: Option<usize>,
+    pub message: String,
+}
+
 /// PlantUML client for converting text to diagrams
 pub struct PlantUmlClient {
     client: reqwest::Client,
@@ -18,7 +31,7 @@ impl PlantUmlClient {
     /// * `base_url` - PlantUML Picoweb server URL (e.g., "http://localhost:8081")
     pub fn new(base_url: String) -> Result<Self, ClientError> {
         let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
+            .timeout(RENDER_TIMEOUT)
             .no_proxy() // Disable proxy for localhost connections
             .build()?;
         
@@ -57,16 +70,59 @@ impl PlantUmlClient {
         self.convert(document_id, plantuml_text, ImageFormat::Svg).await
     }
     
-    /// Internal conversion method
-    async fn convert(
+    /// Convert PlantUML text to ASCII art
+    ///
+    /// Only some diagram types (sequence diagrams in particular) have a text
+    /// rendering; for the others PlantUML answers with an error message.
+    pub async fn convert_to_txt(
         &self,
         document_id: DocumentId,
         plantuml_text: &str,
-        format: ImageFormat,
     ) -> Result<DiagramImage, ClientError> {
+        self.convert(document_id, plantuml_text, ImageFormat::Txt).await
+    }
+    
+    /// Convert PlantUML text to a PDF document
+    ///
+    /// The server needs Apache Batik and FOP for PDF output; without them
+    /// PlantUML answers with an error message.
+    pub async fn convert_to_pdf(
+        &self,
+        document_id: DocumentId,
+        plantuml_text: &str,
+    ) -> Result<DiagramImage, ClientError> {
+        self.convert(document_id, plantuml_text, ImageFormat::Pdf).await
+    }
+    
+    /// Ask the server whether the source has a syntax error
+    ///
+    /// Renders the source as SVG and reads the error headers PlantUML adds to
+    /// its error image; the image itself is discarded.
+    pub async fn check_syntax(&self, plantuml_text: &str) -> Result<Option<SyntaxError>, ClientError> {
+        let url = self.url(plantuml_text, ImageFormat::Svg)?;
+        let response = self.client.get(&url).send().await?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let Some(message) = header(ERROR_HEADER) else {
+            return Ok(None);
+        };
+        let line = header(ERROR_LINE_HEADER).and_then(|line| line.trim().parse().ok());
+        Ok(Some(SyntaxError { line, message }))
+    }
+
+    /// Picoweb URL rendering the source in the given format
+    fn url(&self, plantuml_text: &str, format: ImageFormat) -> Result<String, ClientError> {
         let endpoint = match format {
             ImageFormat::Png => "png",
             ImageFormat::Svg => "svg",
+            ImageFormat::Txt => "txt",
+            ImageFormat::Pdf => "pdf",
         };
         
         // Encode PlantUML text using deflate compression
@@ -74,7 +130,17 @@ impl PlantUmlClient {
             .map_err(|e| ClientError::EncodingError(format!("{:?}", e)))?;
         
         // Build URL with encoded text as path parameter
-        let url = format!("{}/{}/{}", self.base_url, endpoint, encoded);
+        Ok(format!("{}/{}/{}", self.base_url, endpoint, encoded))
+    }
+    
+    /// Internal conversion method
+    async fn convert(
+        &self,
+        document_id: DocumentId,
+        plantuml_text: &str,
+        format: ImageFormat,
+    ) -> Result<DiagramImage, ClientError> {
+        let url = self.url(plantuml_text, format)?;
         
         // Send GET request (PlantUML Picoweb uses GET with encoded path)
         let response = self.client
@@ -88,13 +154,8 @@ impl PlantUmlClient {
         // We accept all responses and let the client decide how to handle them.
         let data = response.bytes().await?.to_vec();
         
-        // TODO: Extract actual dimensions from image data
-        // For now, use placeholder values
-        let dimensions = (800, 600);
-        
-        // TODO: Detect syntax error images
-        // PlantUML returns PNG with error message for syntax errors
-        let result = GenerationResult::Success;
+        // (0, 0) when the image does not state its size
+        let dimensions = image_dimensions(format, &data).unwrap_or((0, 0));
         
         let generated_at = chrono::Utc::now().timestamp();
         
@@ -104,7 +165,6 @@ impl PlantUmlClient {
             data,
             dimensions,
             generated_at,
-            result,
         })
     }
 }
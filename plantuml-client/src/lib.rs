@@ -5,6 +5,7 @@
 
 mod client;
 mod errors;
+pub mod source_url;
 
-pub use client::PlantUmlClient;
+pub use client::{PlantUmlClient, SyntaxError};
 pub use errors::ClientError;
@@ -0,0 +1,108 @@
+// PlantUML source from a diagram URL
+//
+// Images served by a PlantUML server carry their whole source in the URL
+// (`https://www.plantuml.com/plantuml/png/SoWkIImgAStDuNBAJrBGjLDmpCbCJbMmKiX8pSd9vt98pKi1IW80`),
+// so a diagram linked from a wiki can be opened for editing without the
+// original file. `source_from_url` takes such a URL, or only the encoded
+// part, and decodes it back with the same encoding used to render (deflate,
+// or hex with the `~h` prefix). Deflate is inflated with a cap (see
+// `encoded_source`) and the result has to be a valid document.
+
+use plantuml_editor_core::encoded_source::{decode_deflate, DecodeError};
+use plantuml_editor_core::{validate_plantuml_content, ErrorCode};
+use plantuml_encoding::decode_plantuml_hex;
+
+/// Path segments a PlantUML server puts before the encoded source
+const FORMAT_SEGMENTS: &[&str] = &[
+    "png", "svg", "txt", "uml", "img", "eps", "epstext", "latex", "pdf", "map", "check",
+];
+
+/// Source encoded in a PlantUML server image URL (or in the encoded part on
+/// its own)
+pub fn source_from_url(url: &str) -> Result<String, ErrorCode> {
+    let encoded = encoded_part(url.trim()).ok_or_else(|| ErrorCode::ImportError {
+        reason: "PlantUMLサーバーの画像URLではありません".to_string(),
+    })?;
+    let decoded = if encoded.starts_with("~h") {
+        decode_plantuml_hex(encoded).map_err(|_| DecodeError::Invalid)
+    } else {
+        decode_deflate(encoded)
+    };
+    let text = decoded.map_err(|e| ErrorCode::ImportError {
+        reason: match e {
+            DecodeError::Invalid => "URLからPlantUMLのソースを復元できませんでした".to_string(),
+            DecodeError::TooLarge => e.to_string(),
+        },
+    })?;
+    validate_plantuml_content(&text).map_err(|e| ErrorCode::ImportError { reason: e.to_string() })?;
+    Ok(text)
+}
+
+/// Encoded source: the segment after the format in a URL, or the whole text
+/// when it is not a URL
+fn encoded_part(url: &str) -> Option<&str> {
+    let Some((_, rest)) = url.split_once("://") else {
+        return is_encoded(url).then_some(url);
+    };
+    let path = rest.split(['?', '#']).next().unwrap_or(rest);
+    let segments: Vec<&str> = path.split('/').collect();
+    let (encoded, before) = segments.split_last()?;
+    let format = before.last()?;
+    (FORMAT_SEGMENTS.contains(format) && is_encoded(encoded)).then_some(*encoded)
+}
+
+/// Whether `text` only uses the characters of PlantUML's URL encodings
+fn is_encoded(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '~'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_encoding::{encode_plantuml_deflate, encode_plantuml_hex};
+
+    const SOURCE: &str = "@startuml\nAlice -> Bob : こんにちは\n@enduml";
+
+    #[test]
+    fn test_source_from_url() {
+        let encoded = encode_plantuml_deflate(SOURCE).unwrap();
+        for url in [
+            format!("https://www.plantuml.com/plantuml/png/{}", encoded),
+            format!("http://localhost:8081/svg/{}?download", encoded),
+            format!("  {}  ", encoded),
+        ] {
+            assert_eq!(source_from_url(&url).as_deref(), Ok(SOURCE), "{}", url);
+        }
+
+        let hex = encode_plantuml_hex(SOURCE).unwrap();
+        let url = format!("https://www.plantuml.com/plantuml/uml/{}", hex);
+        assert_eq!(source_from_url(&url).as_deref(), Ok(SOURCE));
+    }
+
+    #[test]
+    fn test_rejects_bombs_and_invalid_sources() {
+        // Far over the limit once inflated, tiny encoded
+        let bomb = encode_plantuml_deflate(&"A".repeat(10 * 1024 * 1024)).unwrap();
+        let url = format!("https://www.plantuml.com/plantuml/png/{}", bomb);
+        assert!(matches!(source_from_url(&url), Err(ErrorCode::ImportError { .. })));
+
+        let too_long = encode_plantuml_deflate(&"A".repeat(plantuml_editor_core::MAX_CONTENT_CHARS + 1)).unwrap();
+        assert!(matches!(source_from_url(&too_long), Err(ErrorCode::ImportError { .. })));
+        let blank = encode_plantuml_deflate("  \n").unwrap();
+        assert!(matches!(source_from_url(&blank), Err(ErrorCode::ImportError { .. })));
+    }
+
+    #[test]
+    fn test_rejects_other_urls() {
+        for url in ["https://example.com/images/diagram.png", "https://example.com/png/", "", "not encoded!"] {
+            assert!(
+                matches!(source_from_url(url), Err(ErrorCode::ImportError { .. })),
+                "{}",
+                url
+            );
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// Property-based tests for the PlantUML text encoders
+
+use plantuml_encoding::{
+    decode_plantuml_deflate, decode_plantuml_hex, encode_plantuml_deflate, encode_plantuml_hex,
+};
+use proptest::prelude::*;
+
+/// Characters allowed in PlantUML's URL-safe deflate encoding
+const DEFLATE_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz-_";
+
+proptest! {
+    #[test]
+    fn prop_deflate_round_trip(text in "\\PC{0,2000}") {
+        let encoded = encode_plantuml_deflate(&text).unwrap();
+        prop_assert_eq!(decode_plantuml_deflate(&encoded).unwrap(), text);
+    }
+
+    #[test]
+    fn prop_deflate_is_url_safe(text in "(\\PC|\n){0,500}") {
+        let encoded = encode_plantuml_deflate(&text).unwrap();
+        prop_assert!(encoded.chars().all(|c| DEFLATE_ALPHABET.contains(c)));
+    }
+
+    #[test]
+    fn prop_hex_round_trip(text in "(\\PC|\n){0,500}") {
+        let encoded = encode_plantuml_hex(&text).unwrap();
+        prop_assert!(encoded.starts_with("~h"));
+        prop_assert_eq!(decode_plantuml_hex(&encoded).unwrap(), text);
+    }
+}
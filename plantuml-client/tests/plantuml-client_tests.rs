@@ -145,4 +145,47 @@ async fn test_convert_encoding_error() {
     // エンコードエラーのテストは実際には難しい？？
     // （どんな文字列でもエンコード可能なため）
     // ここでは省略
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_check_syntax_reads_error_headers() {
+    let mut server = Server::new_async().await;
+    
+    // PlantUML はエラー画像をヘッダー付きで返す
+    let _mock = server
+        .mock("GET", Matcher::Regex(r"^/svg/.*".to_string()))
+        .with_status(400)
+        .with_header("content-type", "image/svg+xml")
+        .with_header("X-PlantUML-Diagram-Error", "Syntax Error?")
+        .with_header("X-PlantUML-Diagram-Error-Line", "2")
+        .with_body("<svg/>")
+        .create_async()
+        .await;
+    
+    let client = PlantUmlClient::new(server.url()).unwrap();
+    let error = client
+        .check_syntax("@startuml\nAlice -> \n@enduml")
+        .await
+        .unwrap()
+        .unwrap();
+    
+    assert_eq!(error.line, Some(2));
+    assert_eq!(error.message, "Syntax Error?");
+}
+
+#[tokio::test]
+async fn test_check_syntax_valid_source() {
+    let mut server = Server::new_async().await;
+    
+    let _mock = server
+        .mock("GET", Matcher::Regex(r"^/svg/.*".to_string()))
+        .with_status(200)
+        .with_header("content-type", "image/svg+xml")
+        .with_body("<svg/>")
+        .create_async()
+        .await;
+    
+    let client = PlantUmlClient::new(server.url()).unwrap();
+    let result = client.check_syntax("@startuml\nAlice -> Bob\n@enduml").await.unwrap();
+    
+    assert_eq!(result, None);
+}
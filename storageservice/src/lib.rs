@@ -2,13 +2,20 @@
 //
 // This crate provides storage abstraction with pluggable backends
 
-use plantuml_editor_core::{StorageError, ProcessResult, ErrorCode};
+use plantuml_editor_core::{DocumentId, StorageError, StorageSlot, ProcessResult, ErrorCode};
 use serde::{Deserialize, Serialize};
 
 // Re-export local storage backend
 pub mod local;
 pub use local::LocalStorageBackend;
 
+pub mod migration;
+
+pub mod quota;
+
+pub mod trash;
+pub use trash::{TrashInfo, TRASH_RETENTION_DAYS};
+
 /// Slot information for display
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlotInfo {
@@ -16,6 +23,9 @@ pub struct SlotInfo {
     pub title: String,
     pub saved_at: i64,
     pub preview: String,
+    /// Pinned slots are listed first
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// Storage backend trait
@@ -23,9 +33,36 @@ pub struct SlotInfo {
 /// Clone is required to enable dependency injection in Yew components
 pub trait StorageBackend: Clone {
     fn save_to_slot(&self, slot_number: usize, text: &str) -> Result<(), StorageError>;
+    /// Content of a slot; None when it is empty, SlotCorrupted when its data
+    /// cannot be read
     fn load_from_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
+    /// Text stored for a slot as it is, so a corrupted slot can be inspected
+    fn load_raw_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
     fn list_slots(&self) -> Vec<SlotInfo>;
-    fn delete_slot(&self, slot_number: usize) -> Result<(), StorageError>;
+    /// Pin or unpin a slot so it is listed first
+    fn set_pinned(&self, slot_number: usize, pinned: bool) -> Result<(), StorageError>;
+    /// Move a slot to the trash, where it stays restorable for TRASH_RETENTION_DAYS
+    ///
+    /// Returns whether a copy was kept: when the trash cannot be written even
+    /// after dropping its oldest entries, the slot is deleted without one
+    /// rather than not at all.
+    fn delete_slot(&self, slot_number: usize) -> Result<bool, StorageError>;
+    /// Duplicate the document in `from` into the empty slot `to`
+    fn copy_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+    /// Move the document in `from` to `to`, swapping with the document already there
+    ///
+    /// Either both slots are updated or neither is.
+    fn move_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+    /// Deleted slots that can still be restored, most recently deleted first
+    fn list_trash(&self) -> Vec<TrashInfo>;
+    /// Put a deleted document back into its old slot (or the first empty one)
+    /// and return the slot number
+    fn restore_from_trash(&self, document_id: DocumentId) -> Result<usize, StorageError>;
+    /// Remove one document from the trash for good, or all of them with None
+    fn purge_trash(&self, document_id: Option<DocumentId>) -> Result<(), StorageError>;
+    /// Free space in bytes, probed up to `wanted` (see `quota::probe_free_space`);
+    /// None when the backend cannot tell
+    fn free_space(&self, wanted: usize) -> Option<usize>;
 }
 
 /// Storage service with pluggable backend
@@ -47,13 +84,75 @@ impl<B: StorageBackend> StorageService<B> {
         self.backend.load_from_slot(slot_number)
     }
     
+    pub fn load_raw_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError> {
+        self.backend.load_raw_slot(slot_number)
+    }
+    
     pub fn list_slots(&self) -> Vec<SlotInfo> {
         self.backend.list_slots()
     }
     
-    pub fn delete_slot(&self, slot_number: usize) -> Result<(), StorageError> {
+    pub fn delete_slot(&self, slot_number: usize) -> Result<bool, StorageError> {
         self.backend.delete_slot(slot_number)
     }
+    
+    pub fn set_pinned(&self, slot_number: usize, pinned: bool) -> Result<(), StorageError> {
+        self.backend.set_pinned(slot_number, pinned)
+    }
+    
+    pub fn list_trash(&self) -> Vec<TrashInfo> {
+        self.backend.list_trash()
+    }
+    
+    pub fn restore_from_trash(&self, document_id: DocumentId) -> Result<usize, StorageError> {
+        self.backend.restore_from_trash(document_id)
+    }
+    
+    pub fn purge_trash(&self, document_id: Option<DocumentId>) -> Result<(), StorageError> {
+        self.backend.purge_trash(document_id)
+    }
+    
+    /// Check that saving `text` into `slot_number` fits in the space left
+    ///
+    /// Fails with InsufficientSpace before anything is written, where the
+    /// save itself would fail with QuotaExceeded. Data already in the slot is
+    /// replaced, so its size counts as free.
+    pub fn check_capacity(&self, slot_number: usize, text: &str) -> Result<(), StorageError> {
+        let key = StorageSlot::storage_key(slot_number as u8);
+        let current = self
+            .backend
+            .load_raw_slot(slot_number)?
+            .map_or(0, |raw| quota::stored_bytes(&key, &raw));
+        let needed = quota::estimated_slot_bytes(slot_number as u8, text).saturating_sub(current);
+        match self.backend.free_space(needed) {
+            Some(available) if available < needed => {
+                Err(StorageError::InsufficientSpace { needed, available })
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Copy slot `from` into the empty slot `to` (title gets a "のコピー" suffix)
+    pub fn copy_slot(&self, from: usize, to: usize) -> Result<(), StorageError> {
+        self.backend.copy_slot(from, to)
+    }
+    
+    /// Move slot `from` to `to`, swapping when `to` is occupied
+    pub fn move_slot(&self, from: usize, to: usize) -> Result<(), StorageError> {
+        self.backend.move_slot(from, to)
+    }
+    
+    /// Copy slot `from` into the first empty slot and return its number
+    pub fn duplicate_slot(&self, from: usize) -> Result<usize, StorageError> {
+        let max_slots = StorageSlot::MAX_SLOTS as usize;
+        for to in 1..=max_slots {
+            if let Ok(None) = self.backend.load_from_slot(to) {
+                self.backend.copy_slot(from, to)?;
+                return Ok(to);
+            }
+        }
+        Err(StorageError::SlotsFull)
+    }
 }
 
 /// Convert StorageError to ProcessResult
@@ -69,6 +168,21 @@ pub fn storage_error_to_result(error: &StorageError, _slot_number: Option<u8>) -
                 max_slots: 10,
             }
         }
+        StorageError::SlotOccupied(slot) => {
+            ErrorCode::StorageWriteError {
+                reason: format!("スロット{}は使用中です", slot),
+            }
+        }
+        StorageError::InsufficientSpace { .. } => {
+            ErrorCode::StorageWriteError {
+                reason: error.to_string(),
+            }
+        }
+        StorageError::NotInTrash | StorageError::SlotCorrupted { .. } => {
+            ErrorCode::StorageReadError {
+                reason: error.to_string(),
+            }
+        }
         StorageError::QuotaExceeded => {
             ErrorCode::StorageInputLimit {
                 actual: 0, // Not available in this context
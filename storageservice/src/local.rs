@@ -1,18 +1,31 @@
 // LocalStorage backend implementation
 
-use super::{StorageBackend, SlotInfo};
-use plantuml_editor_core::StorageError;
+use super::{StorageBackend, SlotInfo, TrashInfo};
+use plantuml_editor_core::clock::{Clock, SystemClock};
+use plantuml_editor_core::{text, DocumentId, StorageError};
 
 #[cfg(target_arch = "wasm32")]
-use plantuml_editor_core::{PlantUMLDocument, DocumentId, StorageSlot};
+use plantuml_editor_core::{PlantUMLDocument, StorageSlot};
 
 /// LocalStorage backend for browser-based storage
+///
+/// Save times and trash expiry are read from `C` (the system clock unless
+/// built with `with_clock`).
 #[derive(Default, Clone, PartialEq)]
-pub struct LocalStorageBackend;
+pub struct LocalStorageBackend<C: Clock = SystemClock> {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    clock: C,
+}
 
 impl LocalStorageBackend {
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+}
+
+impl<C: Clock> LocalStorageBackend<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Self { clock }
     }
 }
 
@@ -20,27 +33,72 @@ impl LocalStorageBackend {
 #[cfg(target_arch = "wasm32")]
 mod wasm_impl {
     use super::*;
+    use crate::migration;
+    use crate::quota;
+    use crate::trash::{self, TrashEntry};
     use gloo_storage::{LocalStorage, Storage};
 
-    impl StorageBackend for LocalStorageBackend {
+    /// LocalStorage key of the trash (list of TrashEntry)
+    const TRASH_KEY: &str = "plantuml_trash";
+
+    /// LocalStorage key written and removed again by `free_space`
+    const PROBE_KEY: &str = "plantuml_quota_probe";
+
+    /// Entries not yet expired at `now`, newest first
+    fn load_trash(now: i64) -> Vec<TrashEntry> {
+        let entries = LocalStorage::get::<Vec<TrashEntry>>(TRASH_KEY).unwrap_or_default();
+        trash::prune(entries, now)
+    }
+
+    fn store_trash(entries: &[TrashEntry]) -> Result<(), StorageError> {
+        LocalStorage::set(TRASH_KEY, entries).map_err(|_| StorageError::QuotaExceeded)
+    }
+
+    /// Text stored under a slot's key, None when nothing is stored
+    fn read_raw(slot_number: u8) -> Option<String> {
+        let key = StorageSlot::storage_key(slot_number);
+        LocalStorage::raw().get_item(&key).ok().flatten()
+    }
+
+    /// Read a slot, upgrading it to the current schema on first access
+    ///
+    /// Data that does not parse is reported as SlotCorrupted rather than
+    /// treated as an empty slot, so it is not silently overwritten.
+    fn read_slot(slot_number: u8) -> Result<Option<StorageSlot>, StorageError> {
+        let Some(raw) = read_raw(slot_number) else {
+            return Ok(None);
+        };
+        let (slot, migrated) = migration::parse_slot(&raw).map_err(|e| StorageError::SlotCorrupted {
+            slot: slot_number,
+            reason: e.to_string(),
+        })?;
+        if migrated {
+            // Best effort: an unwritten upgrade is simply repeated next time
+            let _ = LocalStorage::set(StorageSlot::storage_key(slot_number), &slot);
+        }
+        Ok(Some(slot))
+    }
+
+    impl<C: Clock + Clone> StorageBackend for LocalStorageBackend<C> {
         fn save_to_slot(&self, slot_number: usize, text: &str) -> Result<(), StorageError> {
             let slot_number = slot_number as u8;
             StorageSlot::validate_slot_number(slot_number)?;
             
-            let now = chrono::Utc::now().timestamp();
-            let document = PlantUMLDocument {
+            let now = self.clock.now();
+            let mut document = PlantUMLDocument {
                 id: DocumentId::new(),
                 content: text.to_string(),
                 created_at: now,
                 updated_at: now,
                 title: None,
+                folder: None,
+                version: 1,
             };
+            document.fill_title_from_source();
             
-            let slot = StorageSlot {
-                slot_number,
-                document,
-                saved_at: chrono::Utc::now().timestamp(),
-            };
+            // Saving over a slot keeps its pin (saving over corrupted data replaces it)
+            let mut slot = StorageSlot::new(slot_number, document, now);
+            slot.pinned = matches!(read_slot(slot_number), Ok(Some(old)) if old.pinned);
             
             let key = StorageSlot::storage_key(slot_number);
             LocalStorage::set(&key, &slot)
@@ -53,24 +111,27 @@ mod wasm_impl {
             let slot_number = slot_number as u8;
             StorageSlot::validate_slot_number(slot_number)?;
             
-            let key = StorageSlot::storage_key(slot_number);
-            match LocalStorage::get::<StorageSlot>(&key) {
-                Ok(slot) => Ok(Some(slot.document.content)),
-                Err(_) => Ok(None),
-            }
+            Ok(read_slot(slot_number)?.map(|slot| slot.document.content))
+        }
+        
+        fn load_raw_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError> {
+            let slot_number = slot_number as u8;
+            StorageSlot::validate_slot_number(slot_number)?;
+            
+            Ok(read_raw(slot_number))
         }
         
         fn list_slots(&self) -> Vec<SlotInfo> {
             let mut slots = Vec::new();
             
             for slot_number in 1..=StorageSlot::MAX_SLOTS {
-                let key = StorageSlot::storage_key(slot_number);
-                if let Ok(slot) = LocalStorage::get::<StorageSlot>(&key) {
+                if let Ok(Some(slot)) = read_slot(slot_number) {
                     slots.push(SlotInfo {
                         slot_number,
                         title: slot.document.title.clone().unwrap_or_else(|| "無題".to_string()),
                         saved_at: slot.saved_at,
                         preview: get_preview(&slot.document.content),
+                        pinned: slot.pinned,
                     });
                 }
             }
@@ -78,33 +139,150 @@ mod wasm_impl {
             slots
         }
         
-        fn delete_slot(&self, slot_number: usize) -> Result<(), StorageError> {
+        fn delete_slot(&self, slot_number: usize) -> Result<bool, StorageError> {
             let slot_number = slot_number as u8;
             StorageSlot::validate_slot_number(slot_number)?;
             
             let key = StorageSlot::storage_key(slot_number);
+            // Corrupted data cannot be restored, so it skips the trash
+            let trashed = match read_slot(slot_number) {
+                Ok(Some(slot)) => {
+                    let now = self.clock.now();
+                    let mut entries = load_trash(now);
+                    entries.insert(0, TrashEntry::new(slot, now));
+                    trash::store_dropping_oldest(entries, |entries| store_trash(entries).is_ok())
+                }
+                _ => false,
+            };
             LocalStorage::delete(&key);
             
+            Ok(trashed)
+        }
+        
+        fn set_pinned(&self, slot_number: usize, pinned: bool) -> Result<(), StorageError> {
+            let slot_number = slot_number as u8;
+            StorageSlot::validate_slot_number(slot_number)?;
+            
+            let mut slot = read_slot(slot_number)?.ok_or(StorageError::SlotEmpty(slot_number))?;
+            slot.pinned = pinned;
+            LocalStorage::set(StorageSlot::storage_key(slot_number), &slot)
+                .map_err(|_| StorageError::QuotaExceeded)
+        }
+        
+        fn copy_slot(&self, from: usize, to: usize) -> Result<(), StorageError> {
+            let from = from as u8;
+            let to = to as u8;
+            StorageSlot::validate_slot_number(from)?;
+            StorageSlot::validate_slot_number(to)?;
+            
+            let source = read_slot(from)?.ok_or(StorageError::SlotEmpty(from))?;
+            if read_raw(to).is_some() {
+                return Err(StorageError::SlotOccupied(to));
+            }
+            
+            let now = self.clock.now();
+            let slot = StorageSlot::new(to, source.document.duplicate_at(now), now);
+            LocalStorage::set(StorageSlot::storage_key(to), &slot)
+                .map_err(|_| StorageError::QuotaExceeded)?;
+            
+            Ok(())
+        }
+        
+        fn move_slot(&self, from: usize, to: usize) -> Result<(), StorageError> {
+            let from = from as u8;
+            let to = to as u8;
+            StorageSlot::validate_slot_number(from)?;
+            StorageSlot::validate_slot_number(to)?;
+            if from == to {
+                return Ok(());
+            }
+            
+            let from_key = StorageSlot::storage_key(from);
+            let to_key = StorageSlot::storage_key(to);
+            let moving = read_slot(from)?.ok_or(StorageError::SlotEmpty(from))?;
+            let displaced = read_slot(to)?;
+            
+            let moved = StorageSlot { slot_number: to, ..moving };
+            LocalStorage::set(&to_key, &moved).map_err(|_| StorageError::QuotaExceeded)?;
+            
+            match displaced {
+                Some(displaced) => {
+                    let swapped = StorageSlot { slot_number: from, ..displaced.clone() };
+                    if LocalStorage::set(&from_key, &swapped).is_err() {
+                        // Put the target back so no document is lost
+                        let _ = LocalStorage::set(&to_key, &displaced);
+                        return Err(StorageError::QuotaExceeded);
+                    }
+                }
+                None => LocalStorage::delete(&from_key),
+            }
+            
             Ok(())
         }
-    }
-
-    pub(super) fn get_preview(content: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
-        let preview_lines = lines.iter().take(3).copied().collect::<Vec<_>>();
-        let preview = preview_lines.join("\n");
         
-        if preview.len() > 100 {
-            format!("{}...", &preview[..100])
-        } else {
-            preview
+        fn list_trash(&self) -> Vec<TrashInfo> {
+            load_trash(self.clock.now()).iter().map(TrashEntry::info).collect()
+        }
+        
+        fn restore_from_trash(&self, document_id: DocumentId) -> Result<usize, StorageError> {
+            let mut entries = load_trash(self.clock.now());
+            let index = entries
+                .iter()
+                .position(|entry| entry.slot.document.id == document_id)
+                .ok_or(StorageError::NotInTrash)?;
+            
+            // Prefer the slot it was deleted from, then the first empty one
+            let is_empty = |slot_number: u8| read_raw(slot_number).is_none();
+            let original = entries[index].slot.slot_number;
+            let target = std::iter::once(original)
+                .chain(1..=StorageSlot::MAX_SLOTS)
+                .find(|&slot_number| is_empty(slot_number))
+                .ok_or(StorageError::SlotsFull)?;
+            
+            let mut slot = entries.remove(index).slot;
+            slot.slot_number = target;
+            LocalStorage::set(StorageSlot::storage_key(target), &slot)
+                .map_err(|_| StorageError::QuotaExceeded)?;
+            store_trash(&entries)?;
+            
+            Ok(target as usize)
+        }
+        
+        fn purge_trash(&self, document_id: Option<DocumentId>) -> Result<(), StorageError> {
+            match document_id {
+                Some(id) => {
+                    let mut entries = load_trash(self.clock.now());
+                    entries.retain(|entry| entry.slot.document.id != id);
+                    store_trash(&entries)
+                }
+                None => {
+                    LocalStorage::delete(TRASH_KEY);
+                    Ok(())
+                }
+            }
+        }
+        
+        fn free_space(&self, wanted: usize) -> Option<usize> {
+            let storage = LocalStorage::raw();
+            let free = quota::probe_free_space(wanted, |bytes| {
+                let fits = storage.set_item(PROBE_KEY, &"0".repeat(bytes / 2)).is_ok();
+                let _ = storage.remove_item(PROBE_KEY);
+                fits
+            });
+            Some(free)
         }
     }
 }
 
+/// First three lines of a document, cut to 100 characters
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) fn get_preview(content: &str) -> String {
+    text::preview(content, 3, 100)
+}
+
 // Stub implementation for non-WASM targets (for compilation purposes)
 #[cfg(not(target_arch = "wasm32"))]
-impl StorageBackend for LocalStorageBackend {
+impl<C: Clock + Clone> StorageBackend for LocalStorageBackend<C> {
     fn save_to_slot(&self, _slot_number: usize, _text: &str) -> Result<(), StorageError> {
         panic!("LocalStorageBackend is only available on WASM targets")
     }
@@ -113,11 +291,73 @@ impl StorageBackend for LocalStorageBackend {
         panic!("LocalStorageBackend is only available on WASM targets")
     }
     
+    fn load_raw_slot(&self, _slot_number: usize) -> Result<Option<String>, StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
     fn list_slots(&self) -> Vec<SlotInfo> {
         panic!("LocalStorageBackend is only available on WASM targets")
     }
     
-    fn delete_slot(&self, _slot_number: usize) -> Result<(), StorageError> {
+    fn delete_slot(&self, _slot_number: usize) -> Result<bool, StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn set_pinned(&self, _slot_number: usize, _pinned: bool) -> Result<(), StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn copy_slot(&self, _from: usize, _to: usize) -> Result<(), StorageError> {
         panic!("LocalStorageBackend is only available on WASM targets")
     }
+    
+    fn move_slot(&self, _from: usize, _to: usize) -> Result<(), StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn list_trash(&self) -> Vec<TrashInfo> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn restore_from_trash(&self, _document_id: DocumentId) -> Result<usize, StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn purge_trash(&self, _document_id: Option<DocumentId>) -> Result<(), StorageError> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+    
+    fn free_space(&self, _wanted: usize) -> Option<usize> {
+        panic!("LocalStorageBackend is only available on WASM targets")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_preview_counts_japanese_characters() {
+        let content = "あ".repeat(120);
+        let preview = get_preview(&content);
+        assert_eq!(preview, format!("{}...", "あ".repeat(100)));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_preview_never_panics(content in "\\PC{0,300}(\n\\PC{0,80}){0,5}") {
+            let preview = get_preview(&content);
+            prop_assert!(text::char_count(&preview) <= 103);
+            prop_assert!(preview.lines().count() <= 3);
+        }
+
+        #[test]
+        fn prop_preview_is_prefix_of_first_lines(content in "[a-zあ-ん@ \n]{0,200}") {
+            let preview = get_preview(&content);
+            let first_lines = content.lines().take(3).collect::<Vec<_>>().join("\n");
+            let kept = preview.strip_suffix("...").unwrap_or(&preview);
+            prop_assert!(first_lines.starts_with(kept));
+        }
+    }
 }
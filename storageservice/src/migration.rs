@@ -0,0 +1,123 @@
+// Storage schema migrations
+//
+// Slots are stored as JSON in LocalStorage. Each step upgrades the raw JSON by
+// one schema version, so saves made by older releases keep loading.
+//
+// The oldest layout on record is the first release's (specs data-model.md,
+// "StorageSlot"): same `plantuml_slot_{n}` keys, no schema version, pin or
+// document version, and documents without a title field. It is version 1.
+
+use plantuml_editor_core::StorageSlot;
+use serde_json::Value;
+
+/// Upgrade a stored slot to StorageSlot::SCHEMA_VERSION
+///
+/// Returns the slot and whether it changed (and should be written back).
+pub fn migrate_slot(mut value: Value) -> Result<(StorageSlot, bool), serde_json::Error> {
+    let stored_version = schema_version(&value);
+    let mut version = stored_version;
+
+    // Anything but an object fails to deserialize below
+    if let Some(object) = value.as_object_mut() {
+        while version < StorageSlot::SCHEMA_VERSION {
+            // v2: slots can be pinned
+            if version == 1 {
+                object.entry("pinned").or_insert(Value::Bool(false));
+            }
+            version += 1;
+            object.insert("schema_version".to_string(), Value::from(version));
+        }
+    }
+
+    let slot = serde_json::from_value(value)?;
+    Ok((slot, version != stored_version))
+}
+
+/// Parse the text stored for a slot and upgrade it (see `migrate_slot`)
+pub fn parse_slot(raw: &str) -> Result<(StorageSlot, bool), serde_json::Error> {
+    migrate_slot(serde_json::from_str(raw)?)
+}
+
+/// Version recorded in the slot (saves before versioning are version 1)
+fn schema_version(value: &Value) -> u32 {
+    value
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn legacy_slot() -> Value {
+        json!({
+            "slot_number": 3,
+            "document": {
+                "id": "550e8400-e29b-41d4-a716-446655440000",
+                "content": "@startuml\nA -> B\n@enduml",
+                "created_at": 1700000000,
+                "updated_at": 1700000000,
+                "title": null
+            },
+            "saved_at": 1700000000
+        })
+    }
+
+    #[test]
+    fn test_migrates_unversioned_slot() {
+        let (slot, migrated) = migrate_slot(legacy_slot()).unwrap();
+        assert!(migrated);
+        assert_eq!(slot.slot_number, 3);
+        assert!(!slot.pinned);
+        assert_eq!(slot.schema_version, StorageSlot::SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_current_slot_is_unchanged() {
+        let mut value = legacy_slot();
+        value["pinned"] = json!(true);
+        value["schema_version"] = json!(StorageSlot::SCHEMA_VERSION);
+
+        let (slot, migrated) = migrate_slot(value).unwrap();
+        assert!(!migrated);
+        assert!(slot.pinned);
+    }
+
+    #[test]
+    fn test_rejects_broken_slot() {
+        assert!(migrate_slot(json!({ "slot_number": 1 })).is_err());
+        assert!(migrate_slot(json!("plantuml")).is_err());
+    }
+
+    /// Slot as written by the first release, captured as stored
+    const FIRST_RELEASE_SLOT: &str = r#"{"slot_number":2,"document":{"id":"7d444840-9dc0-11d1-b245-5ffdce74fad2","content":"@startuml\nAlice -> Bob: こんにちは\n@enduml","created_at":1730000000,"updated_at":1730000100},"saved_at":1730000100}"#;
+
+    #[test]
+    fn test_parses_first_release_slot() {
+        let (slot, migrated) = parse_slot(FIRST_RELEASE_SLOT).unwrap();
+        assert!(migrated);
+        assert_eq!(slot.slot_number, 2);
+        assert_eq!(slot.saved_at, 1730000100);
+        assert_eq!(slot.schema_version, StorageSlot::SCHEMA_VERSION);
+        assert_eq!(slot.document.content, "@startuml\nAlice -> Bob: こんにちは\n@enduml");
+        assert_eq!(slot.document.title, None);
+        assert_eq!(slot.document.version, 1);
+
+        // Written back in the current layout, it reads again unchanged
+        let (again, migrated) = parse_slot(&serde_json::to_string(&slot).unwrap()).unwrap();
+        assert!(!migrated);
+        assert_eq!(again.document.content, slot.document.content);
+    }
+
+    #[test]
+    fn test_parse_slot() {
+        let (slot, migrated) = parse_slot(&legacy_slot().to_string()).unwrap();
+        assert!(migrated);
+        assert_eq!(slot.slot_number, 3);
+        // Truncated writes and text that is not JSON are errors, not empty slots
+        assert!(parse_slot("{\"slot_number\": 3, \"docum").is_err());
+        assert!(parse_slot("@startuml").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+// Save preflight against the LocalStorage quota
+//
+// Browsers reject writes once LocalStorage is full (about 5MB, counted in
+// UTF-16 code units of keys and values). Before saving, the editor estimates
+// the size of the slot it is about to write and probes how much space is
+// left, so a full storage is reported up front with a suggestion instead of a
+// failed save.
+
+use plantuml_editor_core::{PlantUMLDocument, StorageSlot};
+
+/// Bytes a key and value take in LocalStorage (2 per UTF-16 code unit)
+pub fn stored_bytes(key: &str, value: &str) -> usize {
+    (key.encode_utf16().count() + value.encode_utf16().count()) * 2
+}
+
+/// Bytes saving `text` into `slot_number` will take
+///
+/// Timestamps are at their widest, so the estimate errs on the high side.
+pub fn estimated_slot_bytes(slot_number: u8, text: &str) -> usize {
+    let mut document = PlantUMLDocument::new_at(text.to_string(), i64::MIN);
+    document.fill_title_from_source();
+    let slot = StorageSlot::new(slot_number, document, i64::MIN);
+    let value = serde_json::to_string(&slot).unwrap_or_default();
+    stored_bytes(&StorageSlot::storage_key(slot_number), &value)
+}
+
+/// Free space found by trial writes, up to `wanted` bytes
+///
+/// `fits(n)` tries to store `n` bytes. Returns `wanted` when that much fits,
+/// and otherwise searches for the largest size that does.
+pub fn probe_free_space(wanted: usize, mut fits: impl FnMut(usize) -> bool) -> usize {
+    if fits(wanted) {
+        return wanted;
+    }
+    // Invariant: `low` fits, `high` does not
+    let (mut low, mut high) = (0, wanted);
+    while high - low > 2 {
+        let mid = low + (high - low) / 2;
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_covers_content_and_key() {
+        let text = "@startuml\ntitle 注文フロー\nA -> B\n@enduml";
+        let bytes = estimated_slot_bytes(3, text);
+        assert!(bytes > stored_bytes(&StorageSlot::storage_key(3), text));
+        assert!(estimated_slot_bytes(3, &text.repeat(10)) > bytes);
+    }
+
+    #[test]
+    fn test_probe_free_space() {
+        let free = 1_000;
+        let fits = |bytes: usize| bytes <= free;
+        // Enough space is confirmed with a single write
+        let mut writes = 0;
+        let wanted = probe_free_space(400, |bytes| {
+            writes += 1;
+            fits(bytes)
+        });
+        assert_eq!((wanted, writes), (400, 1));
+
+        let found = probe_free_space(5_000, fits);
+        assert!(found <= free && free - found <= 2);
+        assert_eq!(probe_free_space(5_000, |_| false), 0);
+    }
+}
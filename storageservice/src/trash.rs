@@ -0,0 +1,146 @@
+// Trash for deleted slots
+//
+// Deleting a slot moves it here instead of dropping it, so the UI can offer
+// "元に戻す" and a trash panel. Entries expire after TRASH_RETENTION_DAYS.
+
+use plantuml_editor_core::{DocumentId, StorageSlot};
+use serde::{Deserialize, Serialize};
+
+/// Days a deleted slot stays restorable
+pub const TRASH_RETENTION_DAYS: i64 = 7;
+
+/// Maximum number of entries kept (oldest are dropped first)
+pub const MAX_TRASH_ENTRIES: usize = 20;
+
+/// Deleted slot as stored in the trash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub slot: StorageSlot,
+
+    /// Deletion timestamp (Unix timestamp)
+    pub deleted_at: i64,
+}
+
+/// Trash entry information for display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashInfo {
+    pub document_id: DocumentId,
+    /// Slot the document was deleted from
+    pub slot_number: u8,
+    pub title: String,
+    pub deleted_at: i64,
+}
+
+impl TrashEntry {
+    pub fn new(slot: StorageSlot, deleted_at: i64) -> Self {
+        Self { slot, deleted_at }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now - self.deleted_at > TRASH_RETENTION_DAYS * 24 * 60 * 60
+    }
+
+    pub fn info(&self) -> TrashInfo {
+        TrashInfo {
+            document_id: self.slot.document.id,
+            slot_number: self.slot.slot_number,
+            title: self
+                .slot
+                .document
+                .title
+                .clone()
+                .unwrap_or_else(|| "無題".to_string()),
+            deleted_at: self.deleted_at,
+        }
+    }
+}
+
+/// Drop expired entries, newest first
+pub fn prune(mut entries: Vec<TrashEntry>, now: i64) -> Vec<TrashEntry> {
+    entries.retain(|entry| !entry.is_expired(now));
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    entries.truncate(MAX_TRASH_ENTRIES);
+    entries
+}
+
+/// Write `entries` (newest first) with `store`, dropping the oldest ones
+/// while the write fails, e.g. because LocalStorage is full
+///
+/// False when not even the newest entry could be written; the trash is then
+/// left as it was.
+pub fn store_dropping_oldest(
+    mut entries: Vec<TrashEntry>,
+    mut store: impl FnMut(&[TrashEntry]) -> bool,
+) -> bool {
+    while !entries.is_empty() {
+        if store(&entries) {
+            return true;
+        }
+        entries.pop();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::PlantUMLDocument;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn entry(slot_number: u8, deleted_at: i64) -> TrashEntry {
+        let document = PlantUMLDocument::new("@startuml\n@enduml".to_string());
+        let slot = StorageSlot::new(slot_number, document, 0);
+        TrashEntry::new(slot, deleted_at)
+    }
+
+    #[test]
+    fn test_prune_drops_expired_entries() {
+        let now = 100 * DAY;
+        let entries = vec![
+            entry(1, now - 8 * DAY),
+            entry(2, now - DAY),
+            entry(3, now - 7 * DAY),
+        ];
+
+        let slots: Vec<u8> = prune(entries, now)
+            .iter()
+            .map(|entry| entry.slot.slot_number)
+            .collect();
+        assert_eq!(slots, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_prune_keeps_newest_entries() {
+        let entries = (0..25).map(|i| entry(1, i)).collect();
+        let pruned = prune(entries, 30);
+        assert_eq!(pruned.len(), MAX_TRASH_ENTRIES);
+        assert_eq!(pruned[0].deleted_at, 24);
+        assert_eq!(pruned.last().unwrap().deleted_at, 5);
+    }
+
+    #[test]
+    fn test_store_drops_oldest_until_it_fits() {
+        let entries = vec![entry(1, 30), entry(2, 20), entry(3, 10)];
+        let mut stored = Vec::new();
+        assert!(store_dropping_oldest(entries.clone(), |entries| {
+            stored = entries.iter().map(|entry| entry.slot.slot_number).collect();
+            entries.len() <= 2
+        }));
+        assert_eq!(stored, vec![1, 2]);
+
+        let mut attempts = 0;
+        assert!(!store_dropping_oldest(entries, |_| {
+            attempts += 1;
+            false
+        }));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_info_defaults_title() {
+        let info = entry(4, 10).info();
+        assert_eq!(info.slot_number, 4);
+        assert_eq!(info.title, "無題");
+    }
+}
@@ -0,0 +1,530 @@
+// OIDC login for server storage
+//
+// When the page configures `oidc` (see `host`), the editor signs in with the
+// authorization code flow and PKCE. `login` sends the browser to the
+// provider, which returns to the page with a code; on the next start
+// `complete_login` exchanges it for tokens. Tokens are kept in sessionStorage,
+// so they end with the tab, and the access token is refreshed with the
+// refresh token shortly before it expires. `host::api_client` attaches the
+// access token to every request; the API server checks it (OIDC_ISSUER).
+//
+// Login is for the standalone page only: in a VS Code webview the extension
+// talks to the server.
+
+use std::cell::RefCell;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use gloo_timers::callback::Timeout;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+use crate::host;
+
+/// sessionStorage keys of the tokens and of a login in progress
+const TOKENS_KEY: &str = "plantuml_oidc_tokens";
+const PENDING_KEY: &str = "plantuml_oidc_pending";
+
+/// Scopes requested when the configuration names none
+const DEFAULT_SCOPE: &str = "openid profile offline_access";
+
+/// Refresh the access token this long before it expires (seconds)
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Lifetime assumed when the token response has no `expires_in` (seconds)
+const DEFAULT_EXPIRES_IN: i64 = 300;
+
+/// `oidc` in the page configuration
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcSettings {
+    /// Provider URL; its discovery document lists the endpoints
+    pub issuer: String,
+    /// Client registered for the editor (a public client, without secret)
+    pub client_id: String,
+    /// Where the provider returns to (the current page by default)
+    #[serde(default)]
+    pub redirect_uri: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// Endpoints from the provider's discovery document
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Endpoints {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    #[serde(default)]
+    pub end_session_endpoint: Option<String>,
+}
+
+/// Login started by `login`, checked when the provider returns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PendingLogin {
+    state: String,
+    verifier: String,
+    redirect_uri: String,
+}
+
+/// Response of the token endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub id_token: Option<String>,
+}
+
+/// Tokens of the signed-in user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenSet {
+    pub access_token: String,
+    /// Unix timestamp (seconds)
+    pub expires_at: i64,
+    pub refresh_token: Option<String>,
+    pub id_token: Option<String>,
+}
+
+impl TokenSet {
+    /// Tokens from a token response received at `now`
+    ///
+    /// A refresh response may omit the refresh and ID tokens; the previous
+    /// ones are kept then.
+    pub fn from_response(response: TokenResponse, now: i64, previous: Option<&TokenSet>) -> Self {
+        Self {
+            access_token: response.access_token,
+            expires_at: now + response.expires_in.unwrap_or(DEFAULT_EXPIRES_IN),
+            refresh_token: response
+                .refresh_token
+                .or_else(|| previous.and_then(|tokens| tokens.refresh_token.clone())),
+            id_token: response
+                .id_token
+                .or_else(|| previous.and_then(|tokens| tokens.id_token.clone())),
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Seconds until the access token should be refreshed
+    pub fn refresh_in(&self, now: i64) -> i64 {
+        (self.expires_at - REFRESH_MARGIN_SECS - now).max(0)
+    }
+
+    /// Name to show for the user, from the ID token
+    ///
+    /// The ID token is only read here, not verified: the API server verifies
+    /// the access token of every request.
+    pub fn user_name(&self) -> Option<String> {
+        let payload = self.id_token.as_deref()?.split('.').nth(1)?;
+        let claims: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+        ["preferred_username", "name", "email", "sub"]
+            .into_iter()
+            .find_map(|claim| claims.get(claim)?.as_str().map(str::to_string))
+    }
+}
+
+/// PKCE code challenge (S256) of `verifier`
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// URL sending the user to the provider's login page
+pub fn authorize_url(
+    endpoints: &Endpoints,
+    settings: &OidcSettings,
+    redirect_uri: &str,
+    state: &str,
+    challenge: &str,
+) -> String {
+    let separator = if endpoints.authorization_endpoint.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&code_challenge={}&code_challenge_method=S256",
+        endpoints.authorization_endpoint,
+        separator,
+        urlencoding::encode(&settings.client_id),
+        urlencoding::encode(redirect_uri),
+        urlencoding::encode(settings.scope.as_deref().unwrap_or(DEFAULT_SCOPE)),
+        urlencoding::encode(state),
+        urlencoding::encode(challenge),
+    )
+}
+
+/// What the provider sent back in the query of the redirect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginCallback {
+    Code { code: String, state: String },
+    /// The provider refused, e.g. "access_denied"
+    Error(String),
+}
+
+/// The login callback in a location search string, if it is one
+pub fn login_callback(search: &str) -> Option<LoginCallback> {
+    let param = |name: &str| {
+        search
+            .trim_start_matches('?')
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| {
+                urlencoding::decode(&value.replace('+', " "))
+                    .ok()
+                    .map(|value| value.into_owned())
+            })
+    };
+    if let Some(error) = param("error") {
+        return Some(LoginCallback::Error(error));
+    }
+    Some(LoginCallback::Code {
+        code: param("code")?,
+        state: param("state")?,
+    })
+}
+
+/// `application/x-www-form-urlencoded` body of `fields`
+fn form(fields: &[(&str, &str)]) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+thread_local! {
+    /// Timer of the next refresh; replacing it cancels the previous one
+    static REFRESH_TIMER: RefCell<Option<Timeout>> = const { RefCell::new(None) };
+}
+
+fn now() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
+}
+
+fn session_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.session_storage().ok()?
+}
+
+fn read<T: serde::de::DeserializeOwned>(key: &str) -> Option<T> {
+    let json = session_storage()?.get_item(key).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn write<T: Serialize>(key: &str, value: &T) {
+    if let (Some(storage), Ok(json)) = (session_storage(), serde_json::to_string(value)) {
+        let _ = storage.set_item(key, &json);
+    }
+}
+
+fn remove(key: &str) {
+    if let Some(storage) = session_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
+
+fn settings() -> Option<OidcSettings> {
+    host::oidc_settings().filter(|_| !host::is_webview())
+}
+
+/// Whether the page asks for login to server storage
+pub fn is_enabled() -> bool {
+    settings().is_some()
+}
+
+fn tokens() -> Option<TokenSet> {
+    read(TOKENS_KEY)
+}
+
+/// Access token for API requests, unless missing or expired
+pub fn access_token() -> Option<String> {
+    settings()?;
+    tokens()
+        .filter(|tokens| !tokens.is_expired(now()))
+        .map(|tokens| tokens.access_token)
+}
+
+/// Signed-in user, while the tokens are usable
+pub fn user_name() -> Option<String> {
+    let tokens = tokens().filter(|tokens| !tokens.is_expired(now()) || tokens.refresh_token.is_some())?;
+    tokens.user_name().or_else(|| Some("ログイン中".to_string()))
+}
+
+/// Page the provider returns to
+fn redirect_uri(settings: &OidcSettings) -> Option<String> {
+    if let Some(uri) = &settings.redirect_uri {
+        return Some(uri.clone());
+    }
+    let location = web_sys::window()?.location();
+    Some(format!("{}{}", location.origin().ok()?, location.pathname().ok()?))
+}
+
+fn random_string() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Body of a response, failing on network errors and HTTP errors
+async fn fetch_text(url: &str, form_body: Option<String>) -> Result<String, String> {
+    let window = web_sys::window().ok_or("no window")?;
+    let init = web_sys::RequestInit::new();
+    if let Some(body) = form_body {
+        init.set_method("POST");
+        init.set_body(&body.into());
+        let headers = web_sys::Headers::new().map_err(|_| "headers")?;
+        let _ = headers.set("Content-Type", "application/x-www-form-urlencoded");
+        init.set_headers(&headers);
+    }
+    let response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+        .await
+        .map_err(|_| format!("{} に接続できません", url))?;
+    let response: web_sys::Response = response.dyn_into().map_err(|_| "invalid response")?;
+    if !response.ok() {
+        return Err(format!("{} が {} を返しました", url, response.status()));
+    }
+    let text = JsFuture::from(response.text().map_err(|_| "invalid response")?)
+        .await
+        .map_err(|_| "invalid response")?;
+    text.as_string().ok_or_else(|| "invalid response".to_string())
+}
+
+async fn discover(settings: &OidcSettings) -> Result<Endpoints, String> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        settings.issuer.trim_end_matches('/')
+    );
+    let json = fetch_text(&url, None).await?;
+    serde_json::from_str(&json).map_err(|e| e.to_string())
+}
+
+/// Go to the provider's login page
+pub async fn login() -> Result<(), String> {
+    let settings = settings().ok_or("OIDC is not configured")?;
+    let endpoints = discover(&settings).await?;
+    let pending = PendingLogin {
+        state: random_string()?,
+        verifier: random_string()?,
+        redirect_uri: redirect_uri(&settings).ok_or("no redirect URI")?,
+    };
+    let url = authorize_url(
+        &endpoints,
+        &settings,
+        &pending.redirect_uri,
+        &pending.state,
+        &pkce_challenge(&pending.verifier),
+    );
+    write(PENDING_KEY, &pending);
+    web_sys::window()
+        .ok_or("no window")?
+        .location()
+        .assign(&url)
+        .map_err(|_| "cannot leave the page".to_string())
+}
+
+/// Remove the login callback from the address bar
+fn clear_callback_query() {
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let location = window.location();
+    if let (Ok(path), Ok(history)) = (location.pathname(), window.history()) {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&path));
+    }
+}
+
+/// Finish a login when the provider just returned to the page
+///
+/// None when the page was not opened by the provider.
+pub async fn complete_login() -> Option<Result<(), String>> {
+    let settings = settings()?;
+    let search = web_sys::window()?.location().search().ok()?;
+    let callback = login_callback(&search)?;
+    let pending: Option<PendingLogin> = read(PENDING_KEY);
+    remove(PENDING_KEY);
+    clear_callback_query();
+
+    let result = async {
+        let (code, state) = match callback {
+            LoginCallback::Code { code, state } => (code, state),
+            LoginCallback::Error(error) => return Err(error),
+        };
+        let pending = pending
+            .filter(|pending| pending.state == state)
+            .ok_or("login state does not match")?;
+        let endpoints = discover(&settings).await?;
+        let body = form(&[
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &pending.redirect_uri),
+            ("client_id", &settings.client_id),
+            ("code_verifier", &pending.verifier),
+        ]);
+        let json = fetch_text(&endpoints.token_endpoint, Some(body)).await?;
+        let response: TokenResponse = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        write(TOKENS_KEY, &TokenSet::from_response(response, now(), None));
+        schedule_refresh();
+        Ok(())
+    };
+    Some(result.await)
+}
+
+async fn refresh() -> Result<(), String> {
+    let settings = settings().ok_or("OIDC is not configured")?;
+    let previous = tokens().ok_or("not logged in")?;
+    let refresh_token = previous.refresh_token.clone().ok_or("no refresh token")?;
+    let endpoints = discover(&settings).await?;
+    let body = form(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", &refresh_token),
+        ("client_id", &settings.client_id),
+    ]);
+    let json = fetch_text(&endpoints.token_endpoint, Some(body)).await?;
+    let response: TokenResponse = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    write(TOKENS_KEY, &TokenSet::from_response(response, now(), Some(&previous)));
+    Ok(())
+}
+
+/// Refresh the access token before it expires, and again after each refresh
+///
+/// Without a refresh token the user logs in again once it has expired.
+pub fn schedule_refresh() {
+    let Some(tokens) = tokens().filter(|tokens| tokens.refresh_token.is_some()) else {
+        return;
+    };
+    let delay_ms = (tokens.refresh_in(now()) * 1000).min(u32::MAX as i64) as u32;
+    let timer = Timeout::new(delay_ms, || {
+        spawn_local(async {
+            match refresh().await {
+                Ok(()) => schedule_refresh(),
+                // Refresh token expired or revoked: log in again
+                Err(_) => remove(TOKENS_KEY),
+            }
+        });
+    });
+    REFRESH_TIMER.with(|current| *current.borrow_mut() = Some(timer));
+}
+
+/// Forget the tokens, and end the provider's session when it supports that
+pub async fn logout() {
+    let id_token = tokens().and_then(|tokens| tokens.id_token);
+    remove(TOKENS_KEY);
+    REFRESH_TIMER.with(|current| current.borrow_mut().take());
+
+    let Some(settings) = settings() else {
+        return;
+    };
+    let Ok(Endpoints {
+        end_session_endpoint: Some(end_session),
+        ..
+    }) = discover(&settings).await
+    else {
+        return;
+    };
+    let mut url = format!(
+        "{}{}client_id={}",
+        end_session,
+        if end_session.contains('?') { '&' } else { '?' },
+        urlencoding::encode(&settings.client_id)
+    );
+    if let Some(redirect_uri) = redirect_uri(&settings) {
+        url.push_str(&format!("&post_logout_redirect_uri={}", urlencoding::encode(&redirect_uri)));
+    }
+    if let Some(id_token) = id_token {
+        url.push_str(&format!("&id_token_hint={}", urlencoding::encode(&id_token)));
+    }
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().assign(&url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> OidcSettings {
+        OidcSettings {
+            issuer: "https://login.example.com".to_string(),
+            client_id: "plantuml-editor".to_string(),
+            redirect_uri: None,
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // RFC 7636, Appendix B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn test_authorize_url() {
+        let endpoints = Endpoints {
+            authorization_endpoint: "https://login.example.com/auth".to_string(),
+            token_endpoint: "https://login.example.com/token".to_string(),
+            end_session_endpoint: None,
+        };
+        let url = authorize_url(&endpoints, &settings(), "https://editor.example/", "s1", "c1");
+        assert!(url.starts_with("https://login.example.com/auth?response_type=code&client_id=plantuml-editor"));
+        assert!(url.contains("&redirect_uri=https%3A%2F%2Feditor.example%2F"));
+        assert!(url.contains("&scope=openid%20profile%20offline_access"));
+        assert!(url.ends_with("&state=s1&code_challenge=c1&code_challenge_method=S256"));
+    }
+
+    #[test]
+    fn test_login_callback() {
+        assert_eq!(
+            login_callback("?code=abc%2F1&state=xyz&session_state=q"),
+            Some(LoginCallback::Code {
+                code: "abc/1".to_string(),
+                state: "xyz".to_string(),
+            })
+        );
+        assert_eq!(
+            login_callback("?error=access_denied&state=xyz"),
+            Some(LoginCallback::Error("access_denied".to_string()))
+        );
+        assert_eq!(login_callback("?doc=123"), None);
+        assert_eq!(login_callback(""), None);
+    }
+
+    #[test]
+    fn test_token_set() {
+        let claims = URL_SAFE_NO_PAD.encode(r#"{"sub":"u-1","preferred_username":"alice"}"#);
+        let response = TokenResponse {
+            access_token: "access-1".to_string(),
+            expires_in: Some(300),
+            refresh_token: Some("refresh-1".to_string()),
+            id_token: Some(format!("e30.{}.sig", claims)),
+        };
+        let tokens = TokenSet::from_response(response, 1000, None);
+        assert_eq!(tokens.expires_at, 1300);
+        assert_eq!(tokens.refresh_in(1000), 240);
+        assert_eq!(tokens.refresh_in(1290), 0);
+        assert!(tokens.is_expired(1300));
+        assert_eq!(tokens.user_name().as_deref(), Some("alice"));
+
+        // A refresh response without refresh or ID token keeps the old ones
+        let refreshed = TokenSet::from_response(
+            TokenResponse {
+                access_token: "access-2".to_string(),
+                expires_in: None,
+                refresh_token: None,
+                id_token: None,
+            },
+            1200,
+            Some(&tokens),
+        );
+        assert_eq!(refreshed.access_token, "access-2");
+        assert_eq!(refreshed.expires_at, 1200 + DEFAULT_EXPIRES_IN);
+        assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+        assert_eq!(refreshed.user_name().as_deref(), Some("alice"));
+    }
+}
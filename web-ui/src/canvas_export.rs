@@ -0,0 +1,80 @@
+// In-browser SVG to PNG rasterization
+//
+// Fallback for when the server cannot produce a PNG but the preview already
+// holds an SVG: the SVG is drawn onto an OffscreenCanvas and encoded there.
+
+use plantuml_editor_core::metadata::{extract_title, file_stem_from_title};
+use plantuml_editor_core::ImageFormat;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+/// Image file name for a diagram, from its title when it has an ASCII one
+pub fn image_file_name(plantuml_text: &str, format: ImageFormat) -> String {
+    let stem = extract_title(plantuml_text)
+        .and_then(|title| file_stem_from_title(&title))
+        .unwrap_or_else(|| "diagram".to_string());
+    let extension = match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Svg => "svg",
+        ImageFormat::Txt => "txt",
+        ImageFormat::Pdf => "pdf",
+    };
+    format!("{}.{}", stem, extension)
+}
+
+/// Canvas size for an image scaled by `scale`, at least 1x1
+fn canvas_size(width: u32, height: u32, scale: u32) -> (u32, u32) {
+    ((width * scale).max(1), (height * scale).max(1))
+}
+
+/// Rasterize an SVG image URL into a PNG blob, `scale` times its natural size
+pub async fn rasterize_svg(svg_url: &str, scale: u32) -> Result<web_sys::Blob, JsValue> {
+    let image = web_sys::HtmlImageElement::new()?;
+    let loaded = js_sys::Promise::new(&mut |resolve, reject| {
+        image.set_onload(Some(&resolve));
+        image.set_onerror(Some(&reject));
+    });
+    image.set_src(svg_url);
+    JsFuture::from(loaded).await?;
+
+    let (width, height) = canvas_size(image.natural_width(), image.natural_height(), scale);
+    let canvas = web_sys::OffscreenCanvas::new(width, height)?;
+    let context = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("2d context is not available"))?
+        .dyn_into::<web_sys::OffscreenCanvasRenderingContext2d>()?;
+
+    // PlantUML SVGs may have a transparent background
+    context.set_fill_style_str("white");
+    context.fill_rect(0.0, 0.0, width as f64, height as f64);
+    context.draw_image_with_html_image_element_and_dw_and_dh(
+        &image,
+        0.0,
+        0.0,
+        width as f64,
+        height as f64,
+    )?;
+
+    let blob = JsFuture::from(canvas.convert_to_blob()?).await?;
+    blob.dyn_into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_file_name() {
+        let png = |text| image_file_name(text, ImageFormat::Png);
+        assert_eq!(png("@startuml\ntitle Order flow\n@enduml"), "Order_flow.png");
+        assert_eq!(png("@startuml\ntitle 注文処理\n@enduml"), "diagram.png");
+        assert_eq!(png(""), "diagram.png");
+        assert_eq!(image_file_name("", ImageFormat::Svg), "diagram.svg");
+    }
+
+    #[test]
+    fn test_canvas_size() {
+        assert_eq!(canvas_size(200, 100, 2), (400, 200));
+        assert_eq!(canvas_size(0, 0, 4), (1, 1));
+    }
+}
@@ -0,0 +1,176 @@
+// CodeMirror editing surface (feature "codemirror")
+//
+// Bindings to CodeMirror 5, which the page loads as the global `CodeMirror`
+// (codemirror.min.js and codemirror.min.css, e.g. from a CDN, before the
+// bundle). CodeMirror indexes text in UTF-16 code units like the textarea, and
+// its {line, ch} positions match the diagnostics' {line, character}.
+//
+// CodeMirror 5 has no destroy call: dropping the backend removes its element
+// and the listeners with it.
+
+use std::cell::RefCell;
+
+use js_sys::{Object, Reflect};
+use plantuml_editor_core::{Diagnostic, DiagnosticPosition};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::editor_backend::{marker_class, EditorBackend, EditorEvents};
+
+#[wasm_bindgen]
+extern "C" {
+    /// A CodeMirror 5 editor instance
+    type CodeMirror;
+
+    #[wasm_bindgen(catch, js_name = CodeMirror)]
+    fn create(parent: &web_sys::Element, options: &JsValue) -> Result<CodeMirror, JsValue>;
+
+    #[wasm_bindgen(method, js_name = getValue)]
+    fn get_value(this: &CodeMirror) -> String;
+
+    #[wasm_bindgen(method, js_name = setValue)]
+    fn set_value(this: &CodeMirror, value: &str);
+
+    #[wasm_bindgen(method, js_name = getCursor)]
+    fn get_cursor(this: &CodeMirror, which: &str) -> JsValue;
+
+    #[wasm_bindgen(method, js_name = setSelection)]
+    fn set_selection(this: &CodeMirror, anchor: &JsValue, head: &JsValue);
+
+    #[wasm_bindgen(method, js_name = indexFromPos)]
+    fn index_from_pos(this: &CodeMirror, pos: &JsValue) -> u32;
+
+    #[wasm_bindgen(method, js_name = posFromIndex)]
+    fn pos_from_index(this: &CodeMirror, index: u32) -> JsValue;
+
+    #[wasm_bindgen(method, js_name = markText)]
+    fn mark_text(this: &CodeMirror, from: &JsValue, to: &JsValue, options: &JsValue) -> TextMarker;
+
+    #[wasm_bindgen(method, js_name = setOption)]
+    fn set_option(this: &CodeMirror, name: &str, value: &JsValue);
+
+    #[wasm_bindgen(method)]
+    fn on(this: &CodeMirror, event: &str, handler: &js_sys::Function);
+
+    #[wasm_bindgen(method, js_name = getWrapperElement)]
+    fn get_wrapper_element(this: &CodeMirror) -> web_sys::Element;
+
+    /// Marked range returned by `markText`
+    type TextMarker;
+
+    #[wasm_bindgen(method)]
+    fn clear(this: &TextMarker);
+}
+
+/// Whether the page loaded CodeMirror
+pub fn is_available() -> bool {
+    web_sys::window()
+        .and_then(|window| Reflect::get(&window, &JsValue::from_str("CodeMirror")).ok())
+        .is_some_and(|code_mirror| code_mirror.is_function())
+}
+
+/// CodeMirror mounted in an element
+pub struct CodeMirrorBackend {
+    editor: CodeMirror,
+    markers: RefCell<Vec<TextMarker>>,
+    /// Kept alive for as long as the editor calls them
+    _listeners: Vec<Closure<dyn FnMut(JsValue)>>,
+}
+
+impl CodeMirrorBackend {
+    /// Mount an editor holding `text` in `parent`
+    pub fn mount(
+        parent: &web_sys::Element,
+        text: &str,
+        read_only: bool,
+        events: EditorEvents,
+    ) -> Result<Self, JsValue> {
+        let options = Object::new();
+        Reflect::set(&options, &"value".into(), &text.into())?;
+        Reflect::set(&options, &"readOnly".into(), &read_only.into())?;
+        Reflect::set(&options, &"lineNumbers".into(), &true.into())?;
+        Reflect::set(&options, &"lineWrapping".into(), &true.into())?;
+        let editor = create(parent, &options)?;
+
+        // Handlers get the instance as their first argument
+        let on_change = events.on_change;
+        let change = Closure::<dyn FnMut(JsValue)>::new(move |instance: JsValue| {
+            on_change.emit(instance.unchecked_into::<CodeMirror>().get_value());
+        });
+        let on_selection = events.on_selection;
+        let cursor = Closure::<dyn FnMut(JsValue)>::new(move |instance: JsValue| {
+            let editor = instance.unchecked_into::<CodeMirror>();
+            on_selection.emit(selection_of(&editor));
+        });
+        editor.on("change", change.as_ref().unchecked_ref());
+        editor.on("cursorActivity", cursor.as_ref().unchecked_ref());
+
+        Ok(Self {
+            editor,
+            markers: RefCell::default(),
+            _listeners: vec![change, cursor],
+        })
+    }
+}
+
+fn selection_of(editor: &CodeMirror) -> (usize, usize) {
+    let start = editor.index_from_pos(&editor.get_cursor("from"));
+    let end = editor.index_from_pos(&editor.get_cursor("to"));
+    (start as usize, end as usize)
+}
+
+/// CodeMirror position of a diagnostic position
+fn pos(position: DiagnosticPosition) -> JsValue {
+    let pos = Object::new();
+    let _ = Reflect::set(&pos, &"line".into(), &(position.line as u32).into());
+    let _ = Reflect::set(&pos, &"ch".into(), &(position.character as u32).into());
+    pos.into()
+}
+
+impl EditorBackend for CodeMirrorBackend {
+    fn content(&self) -> String {
+        self.editor.get_value()
+    }
+
+    fn set_content(&self, text: &str) {
+        self.editor.set_value(text);
+    }
+
+    fn selection(&self) -> Option<(usize, usize)> {
+        Some(selection_of(&self.editor))
+    }
+
+    fn set_selection(&self, start: usize, end: usize) {
+        let anchor = self.editor.pos_from_index(start as u32);
+        let head = self.editor.pos_from_index(end as u32);
+        self.editor.set_selection(&anchor, &head);
+    }
+
+    fn set_markers(&self, markers: &[Diagnostic]) {
+        let mut marked = self.markers.borrow_mut();
+        for marker in marked.drain(..) {
+            marker.clear();
+        }
+        for marker in markers {
+            let options = Object::new();
+            let class_name = marker_class(marker.severity);
+            let _ = Reflect::set(&options, &"className".into(), &class_name.into());
+            let _ = Reflect::set(&options, &"title".into(), &marker.message.as_str().into());
+            marked.push(self.editor.mark_text(
+                &pos(marker.range.start),
+                &pos(marker.range.end),
+                &options,
+            ));
+        }
+    }
+
+    fn set_read_only(&self, read_only: bool) {
+        self.editor.set_option("readOnly", &read_only.into());
+    }
+}
+
+impl Drop for CodeMirrorBackend {
+    fn drop(&mut self) {
+        self.editor.get_wrapper_element().remove();
+    }
+}
@@ -0,0 +1,36 @@
+// Login state for server storage, shown above the server tab when the page
+// configures OIDC (see `auth`)
+
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct AccountBarProps {
+    /// Signed-in user, None before login
+    pub user: Option<String>,
+    pub on_login: Callback<()>,
+    pub on_logout: Callback<()>,
+}
+
+#[function_component(AccountBar)]
+pub fn account_bar(props: &AccountBarProps) -> Html {
+    let on_login = {
+        let on_login = props.on_login.clone();
+        Callback::from(move |_| on_login.emit(()))
+    };
+    let on_logout = {
+        let on_logout = props.on_logout.clone();
+        Callback::from(move |_| on_logout.emit(()))
+    };
+
+    html! {
+        <div class="account-bar">
+            if let Some(user) = &props.user {
+                <span class="account-user" title={user.clone()}>{ user }</span>
+                <button class="account-button" onclick={on_logout}>{ "ログアウト" }</button>
+            } else {
+                <span class="account-note">{ "サーバー保存を使うにはログインしてください" }</span>
+                <button class="account-button" onclick={on_login}>{ "ログイン" }</button>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,110 @@
+// "最近の更新" feed of changes to server-side documents
+
+use plantuml_editor_core::{ActivityEntry, ActivityKind, ErrorCode};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+
+/// Entries requested from the server
+const FEED_LIMIT: usize = 50;
+
+#[derive(Properties, PartialEq)]
+pub struct ActivityFeedProps {
+    /// Open a document by ID
+    pub on_open: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+}
+
+/// Time since a change (e.g., "たった今", "5分前", "3時間前", "2日前")
+fn format_relative(at: i64, now: i64) -> String {
+    let elapsed = (now - at).max(0);
+    match elapsed {
+        0..=59 => "たった今".to_string(),
+        60..=3599 => format!("{}分前", elapsed / 60),
+        3600..=86399 => format!("{}時間前", elapsed / 3600),
+        _ => format!("{}日前", elapsed / 86400),
+    }
+}
+
+#[function_component(ActivityFeed)]
+pub fn activity_feed(props: &ActivityFeedProps) -> Html {
+    let entries = use_state(|| None::<Vec<ActivityEntry>>);
+    // Bumped by the refresh button
+    let reload = use_state(|| 0usize);
+
+    {
+        let entries = entries.clone();
+        let on_error = props.on_error.clone();
+        use_effect_with(*reload, move |_| {
+            spawn_local(async move {
+                match host::api_client().activity(FEED_LIMIT).await {
+                    Ok(list) => entries.set(Some(list)),
+                    Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/activity")),
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_refresh = {
+        let reload = reload.clone();
+        Callback::from(move |_| reload.set(*reload + 1))
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let render_entry = |entry: &ActivityEntry| {
+        let deleted = entry.kind == ActivityKind::Deleted;
+        let on_click = {
+            let on_open = props.on_open.clone();
+            let id = entry.document_id.0.to_string();
+            Callback::from(move |_| {
+                // Deleted documents cannot be opened
+                if !deleted {
+                    on_open.emit(id.clone());
+                }
+            })
+        };
+        let user = entry.user.as_deref().unwrap_or("匿名ユーザー");
+        let title = entry.title.as_deref().unwrap_or("無題");
+
+        html! {
+            <div class={classes!("activity-entry", deleted.then_some("deleted"))} onclick={on_click}>
+                <div class="activity-summary">
+                    { format!("{}が「{}」を{}", user, title, entry.kind.label()) }
+                </div>
+                <div class="activity-time">{ format_relative(entry.at, now) }</div>
+            </div>
+        }
+    };
+
+    html! {
+        <div class="activity-feed">
+            <button class="activity-refresh" onclick={on_refresh}>{ "↻ 更新" }</button>
+            { match &*entries {
+                None => html! { <div class="tree-empty">{ "読み込み中..." }</div> },
+                Some(list) if list.is_empty() => html! {
+                    <div class="tree-empty">{ "最近の更新はありません" }</div>
+                },
+                Some(list) => html! { { for list.iter().map(render_entry) } },
+            } }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_relative() {
+        let now = 1_700_000_000;
+        assert_eq!(format_relative(now - 5, now), "たった今");
+        assert_eq!(format_relative(now - 300, now), "5分前");
+        assert_eq!(format_relative(now - 3 * 3600, now), "3時間前");
+        assert_eq!(format_relative(now - 2 * 86400, now), "2日前");
+        // Clock skew between server and browser
+        assert_eq!(format_relative(now + 30, now), "たった今");
+    }
+}
@@ -0,0 +1,215 @@
+// Batch export dialog showing per-item progress of a server-side export job
+
+use plantuml_editor_core::{BatchExportItem, ErrorCode, ImageFormat, ProgressEvent};
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{EventSource, MessageEvent};
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+
+const ENDPOINT: &str = "/api/v1/jobs/export";
+
+/// Display status of one item
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemStatus {
+    Queued,
+    Rendering,
+    Done,
+    Failed(String),
+}
+
+impl ItemStatus {
+    fn label(&self) -> &str {
+        match self {
+            ItemStatus::Queued => "待機中",
+            ItemStatus::Rendering => "変換中",
+            ItemStatus::Done => "完了",
+            ItemStatus::Failed(_) => "失敗",
+        }
+    }
+
+    fn class(&self) -> &'static str {
+        match self {
+            ItemStatus::Queued => "batch-item queued",
+            ItemStatus::Rendering => "batch-item rendering",
+            ItemStatus::Done => "batch-item done",
+            ItemStatus::Failed(_) => "batch-item failed",
+        }
+    }
+}
+
+/// Progress of a running batch
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BatchProgress {
+    pub job_id: Option<String>,
+    pub statuses: Vec<ItemStatus>,
+    /// (succeeded, failed) once the batch has finished
+    pub completed: Option<(usize, usize)>,
+}
+
+pub enum BatchAction {
+    Started(String),
+    Progress(ProgressEvent),
+}
+
+impl Reducible for BatchProgress {
+    type Action = BatchAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut next = (*self).clone();
+        match action {
+            BatchAction::Started(job_id) => next.job_id = Some(job_id),
+            BatchAction::Progress(event) => {
+                let (index, status) = match event {
+                    ProgressEvent::Queued { index, .. } => (index, ItemStatus::Queued),
+                    ProgressEvent::Rendering { index, .. } => (index, ItemStatus::Rendering),
+                    ProgressEvent::Done { index, .. } => (index, ItemStatus::Done),
+                    ProgressEvent::Failed { index, result, .. } => {
+                        (index, ItemStatus::Failed(result.message()))
+                    }
+                    ProgressEvent::Completed { succeeded, failed } => {
+                        next.completed = Some((succeeded, failed));
+                        return Rc::new(next);
+                    }
+                };
+                if let Some(slot) = next.statuses.get_mut(index) {
+                    *slot = status;
+                }
+            }
+        }
+        Rc::new(next)
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct BatchExportDialogProps {
+    /// Sources to export
+    pub items: Vec<BatchExportItem>,
+    pub format: ImageFormat,
+    pub on_error: Callback<ErrorCode>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(BatchExportDialog)]
+pub fn batch_export_dialog(props: &BatchExportDialogProps) -> Html {
+    let item_count = props.items.len();
+    let progress = use_reducer(move || BatchProgress {
+        statuses: vec![ItemStatus::Queued; item_count],
+        ..Default::default()
+    });
+    let event_source = use_mut_ref(|| None::<(EventSource, Closure<dyn FnMut(MessageEvent)>)>);
+
+    {
+        let dispatcher = progress.dispatcher();
+        let items = props.items.clone();
+        let format = props.format;
+        let on_error = props.on_error.clone();
+        let on_close = props.on_close.clone();
+        let event_source = event_source.clone();
+
+        use_effect_with((), move |_| {
+            let cleanup_source = event_source.clone();
+
+            spawn_local(async move {
+                let job_id = match host::api_client().start_export_job(items, format).await {
+                    Ok((job, _)) => job.id,
+                    Err(e) => {
+                        on_error.emit(api_error_to_code(e, ENDPOINT));
+                        on_close.emit(());
+                        return;
+                    }
+                };
+                dispatcher.dispatch(BatchAction::Started(job_id.clone()));
+
+                let Ok(source) = EventSource::new(&host::api_client().job_events_url(&job_id)) else {
+                    on_error.emit(ErrorCode::NetworkError {
+                        endpoint: ENDPOINT.to_string(),
+                    });
+                    return;
+                };
+                let onmessage = {
+                    let source = source.clone();
+                    Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                        let Some(data) = e.data().as_string() else {
+                            return;
+                        };
+                        if let Ok(event) = serde_json::from_str::<ProgressEvent>(&data) {
+                            if event.is_final() {
+                                source.close();
+                            }
+                            dispatcher.dispatch(BatchAction::Progress(event));
+                        }
+                    })
+                };
+                source.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+                *event_source.borrow_mut() = Some((source, onmessage));
+            });
+
+            move || {
+                if let Some((source, _)) = cleanup_source.borrow_mut().take() {
+                    source.close();
+                }
+            }
+        });
+    }
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    let rows = props.items.iter().zip(progress.statuses.iter()).map(|(item, status)| {
+        let detail = match status {
+            ItemStatus::Failed(message) => message.clone(),
+            _ => String::new(),
+        };
+        html! {
+            <li class={status.class()} title={detail}>
+                <span class="batch-item-name">{ &item.name }</span>
+                <span class="batch-item-status">{ status.label() }</span>
+            </li>
+        }
+    });
+
+    let finished = progress
+        .statuses
+        .iter()
+        .filter(|s| matches!(s, ItemStatus::Done | ItemStatus::Failed(_)))
+        .count();
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog">
+                <div class="import-dialog-header">{"一括エクスポート"}</div>
+                <div class="batch-summary">
+                    {
+                        match progress.completed {
+                            Some((succeeded, failed)) => {
+                                format!("完了: 成功 {}件 / 失敗 {}件", succeeded, failed)
+                            }
+                            None => format!("処理中... {}/{}", finished, item_count),
+                        }
+                    }
+                </div>
+                <ul class="batch-items">
+                    { for rows }
+                </ul>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_close}>{"閉じる"}</button>
+                    if let (Some(job_id), Some(_)) = (&progress.job_id, progress.completed) {
+                        <a
+                            class="import-submit-btn"
+                            href={host::api_client().job_result_url(job_id)}
+                            download="diagrams.zip"
+                        >
+                            {"ZIPをダウンロード"}
+                        </a>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
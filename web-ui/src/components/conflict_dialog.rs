@@ -0,0 +1,88 @@
+// Dialog shown when saving a server document that someone else changed
+// since it was loaded: shows the differences and offers to overwrite, take
+// the server version, or merge both
+
+use plantuml_editor_core::diff::{line_diff, merge, DiffLine};
+use plantuml_editor_core::PlantUMLDocument;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct ConflictDialogProps {
+    /// Version the edit started from
+    pub base: String,
+    /// Source in the editor
+    pub mine: String,
+    /// Version now on the server
+    pub theirs: PlantUMLDocument,
+    /// Save the editor source over the server version
+    pub on_overwrite: Callback<()>,
+    /// Discard the edit and load the server version
+    pub on_take_theirs: Callback<()>,
+    /// Load the merged source into the editor
+    pub on_merge: Callback<String>,
+    pub on_cancel: Callback<()>,
+}
+
+#[function_component(ConflictDialog)]
+pub fn conflict_dialog(props: &ConflictDialogProps) -> Html {
+    let merged = merge(&props.base, &props.mine, &props.theirs.content);
+
+    let diff_lines = line_diff(&props.theirs.content, &props.mine)
+        .into_iter()
+        .map(|line| {
+            let (class, prefix, text) = match line {
+                DiffLine::Same(text) => ("diff-same", " ", text),
+                DiffLine::Removed(text) => ("diff-removed", "-", text),
+                DiffLine::Added(text) => ("diff-added", "+", text),
+            };
+            html! { <div class={class}>{ format!("{} {}", prefix, text) }</div> }
+        });
+
+    let on_overwrite = {
+        let on_overwrite = props.on_overwrite.clone();
+        Callback::from(move |_| on_overwrite.emit(()))
+    };
+    let on_take_theirs = {
+        let on_take_theirs = props.on_take_theirs.clone();
+        Callback::from(move |_| on_take_theirs.emit(()))
+    };
+    let merge_label = match merged.conflicts {
+        0 => "マージ".to_string(),
+        conflicts => format!("マージ（競合{}箇所）", conflicts),
+    };
+    let on_merge = {
+        let on_merge = props.on_merge.clone();
+        let text = merged.text.clone();
+        Callback::from(move |_| on_merge.emit(text.clone()))
+    };
+    let on_cancel = {
+        let on_cancel = props.on_cancel.clone();
+        Callback::from(move |_| on_cancel.emit(()))
+    };
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog conflict-dialog">
+                <div class="import-dialog-header">{"他のユーザーが先に保存しました"}</div>
+                <div class="conflict-legend">
+                    <span class="diff-removed">{ "- サーバーの内容" }</span>
+                    <span class="diff-added">{ "+ 自分の変更" }</span>
+                </div>
+                <div class="conflict-diff">
+                    { for diff_lines }
+                </div>
+                if merged.conflicts > 0 {
+                    <div class="conflict-note">
+                        { "マージすると、両方が変更した箇所は競合マーカー付きでエディタに読み込まれます" }
+                    </div>
+                }
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{"キャンセル"}</button>
+                    <button class="import-cancel-btn" onclick={on_take_theirs}>{"サーバーの内容を読み込む"}</button>
+                    <button class="import-cancel-btn" onclick={on_overwrite}>{"自分の変更で上書き"}</button>
+                    <button class="import-submit-btn" onclick={on_merge}>{ merge_label }</button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,151 @@
+// Full-text search box over server-side documents, with ranked results and
+// the matching line highlighted
+
+use gloo_timers::callback::Timeout;
+use plantuml_editor_core::{ErrorCode, SearchHit};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+
+/// Delay after the last keystroke before searching (ms)
+const SEARCH_DEBOUNCE_MS: u32 = 300;
+
+#[derive(Properties, PartialEq)]
+pub struct DocumentSearchProps {
+    /// Open a document by ID
+    pub on_open: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+}
+
+/// Snippet split into (text, highlighted) parts
+///
+/// Highlights are [start, end) character offsets; ranges outside the
+/// snippet are clamped.
+fn highlight_segments(snippet: &str, highlights: &[(usize, usize)]) -> Vec<(String, bool)> {
+    let chars: Vec<char> = snippet.chars().collect();
+    let mut segments = Vec::new();
+    let mut position = 0;
+    for &(start, end) in highlights {
+        let start = start.clamp(position, chars.len());
+        let end = end.clamp(start, chars.len());
+        if start > position {
+            segments.push((chars[position..start].iter().collect(), false));
+        }
+        if end > start {
+            segments.push((chars[start..end].iter().collect(), true));
+        }
+        position = end;
+    }
+    if position < chars.len() {
+        segments.push((chars[position..].iter().collect(), false));
+    }
+    segments
+}
+
+#[function_component(DocumentSearch)]
+pub fn document_search(props: &DocumentSearchProps) -> Html {
+    let query = use_state(String::new);
+    let hits = use_state(|| None::<Vec<SearchHit>>);
+    let timeout_handle = use_state(|| None::<Timeout>);
+
+    let on_input = {
+        let query = query.clone();
+        let hits = hits.clone();
+        let timeout_handle = timeout_handle.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let value = input.value();
+            query.set(value.clone());
+
+            // Cancel previous timeout by dropping the old handle
+            timeout_handle.set(None);
+            if value.trim().is_empty() {
+                hits.set(None);
+                return;
+            }
+
+            let hits = hits.clone();
+            let on_error = on_error.clone();
+            let handle = Timeout::new(SEARCH_DEBOUNCE_MS, move || {
+                spawn_local(async move {
+                    match host::api_client().search_documents(&value).await {
+                        Ok(found) => hits.set(Some(found)),
+                        Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/documents/search")),
+                    }
+                });
+            });
+            timeout_handle.set(Some(handle));
+        })
+    };
+
+    let render_hit = |hit: &SearchHit| {
+        let on_click = {
+            let on_open = props.on_open.clone();
+            let id = hit.id.0.to_string();
+            Callback::from(move |_| on_open.emit(id.clone()))
+        };
+
+        html! {
+            <div class="search-hit" key={hit.id.0.to_string()} onclick={on_click}>
+                <div class="search-hit-title">{ hit.title.as_deref().unwrap_or("無題") }</div>
+                <div class="search-hit-snippet">
+                    { for highlight_segments(&hit.snippet, &hit.highlights).into_iter().map(|(text, marked)| {
+                        if marked {
+                            html! { <mark>{ text }</mark> }
+                        } else {
+                            html! { { text } }
+                        }
+                    }) }
+                </div>
+            </div>
+        }
+    };
+
+    html! {
+        <div class="document-search">
+            <input
+                type="search"
+                class="search-input"
+                placeholder="タイトル・本文を検索"
+                value={(*query).clone()}
+                oninput={on_input}
+            />
+            if let Some(hits) = &*hits {
+                <div class="search-results">
+                    if hits.is_empty() {
+                        <div class="tree-empty">{ "一致するドキュメントがありません" }</div>
+                    }
+                    { for hits.iter().map(render_hit) }
+                </div>
+            }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_segments() {
+        let segments = highlight_segments("User -> Login : login()", &[(8, 13), (16, 21)]);
+        let marked: Vec<&str> = segments
+            .iter()
+            .filter(|(_, marked)| *marked)
+            .map(|(text, _)| text.as_str())
+            .collect();
+        assert_eq!(marked, vec!["Login", "login"]);
+        let joined: String = segments.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(joined, "User -> Login : login()");
+
+        // Multi-byte snippets use character offsets
+        let segments = highlight_segments("ログイン要求", &[(0, 4), (10, 12)]);
+        assert_eq!(
+            segments,
+            vec![("ログイン".to_string(), true), ("要求".to_string(), false)]
+        );
+    }
+}
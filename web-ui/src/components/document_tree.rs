@@ -0,0 +1,398 @@
+// Folder tree of server-side documents, shown in the sidebar instead of the
+// slot list while server storage is active
+
+use std::collections::HashSet;
+
+use plantuml_editor_core::{ErrorCode, Folder, FolderId, PlantUMLDocument};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+
+#[derive(Properties, PartialEq)]
+pub struct DocumentTreeProps {
+    /// Open a document by ID
+    pub on_open: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+}
+
+/// One line of the tree, indented by depth
+#[derive(Debug, Clone, Copy)]
+enum TreeRow<'a> {
+    Folder {
+        folder: &'a Folder,
+        depth: usize,
+        expanded: bool,
+    },
+    Document {
+        document: &'a PlantUMLDocument,
+        depth: usize,
+    },
+}
+
+/// dataTransfer format carrying the dragged document ID
+const DRAG_FORMAT: &str = "text/plain";
+
+/// Document ID from dragged data, ignoring drags that did not start in the tree
+fn dragged_document(data: &str) -> Option<&str> {
+    data.strip_prefix("plantuml-document:")
+}
+
+fn document_title(document: &PlantUMLDocument) -> &str {
+    document.title.as_deref().unwrap_or("無題")
+}
+
+/// Visible rows: folders before documents at each level, children of
+/// expanded folders right below them
+///
+/// Documents in folders that no longer exist are shown at the top level.
+fn tree_rows<'a>(
+    folders: &'a [Folder],
+    documents: &'a [PlantUMLDocument],
+    expanded: &HashSet<FolderId>,
+) -> Vec<TreeRow<'a>> {
+    let known: HashSet<FolderId> = folders.iter().map(|folder| folder.id).collect();
+    let mut documents: Vec<&PlantUMLDocument> = documents.iter().collect();
+    documents.sort_by(|a, b| document_title(a).cmp(document_title(b)));
+
+    fn push_level<'a>(
+        rows: &mut Vec<TreeRow<'a>>,
+        parent: Option<FolderId>,
+        depth: usize,
+        folders: &'a [Folder],
+        documents: &[&'a PlantUMLDocument],
+        known: &HashSet<FolderId>,
+        expanded: &HashSet<FolderId>,
+    ) {
+        for folder in folders.iter().filter(|folder| folder.parent == parent) {
+            let is_expanded = expanded.contains(&folder.id);
+            rows.push(TreeRow::Folder {
+                folder,
+                depth,
+                expanded: is_expanded,
+            });
+            if is_expanded {
+                let child = Some(folder.id);
+                push_level(rows, child, depth + 1, folders, documents, known, expanded);
+            }
+        }
+        for &document in documents {
+            let folder = document.folder.filter(|folder| known.contains(folder));
+            if folder == parent {
+                rows.push(TreeRow::Document { document, depth });
+            }
+        }
+    }
+
+    let mut rows = Vec::new();
+    push_level(&mut rows, None, 0, folders, &documents, &known, expanded);
+    rows
+}
+
+#[function_component(DocumentTree)]
+pub fn document_tree(props: &DocumentTreeProps) -> Html {
+    let folders = use_state(Vec::<Folder>::new);
+    let documents = use_state(Vec::<PlantUMLDocument>::new);
+    let expanded = use_state(HashSet::<FolderId>::new);
+    // Folder that new folders are created in (None for the top level)
+    let selected = use_state(|| None::<FolderId>);
+    let new_folder_name = use_state(String::new);
+    // Bumped to reload the tree after a change
+    let reload = use_state(|| 0usize);
+
+    {
+        let folders = folders.clone();
+        let documents = documents.clone();
+        let on_error = props.on_error.clone();
+        use_effect_with(*reload, move |_| {
+            spawn_local(async move {
+                let client = host::api_client();
+                match client.list_folders().await {
+                    Ok(list) => folders.set(list),
+                    Err(e) => return on_error.emit(api_error_to_code(e, "/api/v1/folders")),
+                }
+                match client.list_documents().await {
+                    Ok(list) => documents.set(list),
+                    Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/documents")),
+                }
+            });
+            || ()
+        });
+    }
+
+    let move_document = {
+        let reload = reload.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |(id, folder): (String, Option<FolderId>)| {
+            let reload = reload.clone();
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                match host::api_client().move_document(&id, folder).await {
+                    Ok(_) => reload.set(*reload + 1),
+                    Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/documents")),
+                }
+            });
+        })
+    };
+
+    // Drop handler moving the dragged document into `folder`
+    let drop_into = |folder: Option<FolderId>| {
+        let move_document = move_document.clone();
+        Callback::from(move |e: DragEvent| {
+            e.prevent_default();
+            e.stop_propagation();
+            let id = e
+                .data_transfer()
+                .and_then(|transfer| transfer.get_data(DRAG_FORMAT).ok());
+            if let Some(id) = id.as_deref().and_then(dragged_document) {
+                move_document.emit((id.to_string(), folder));
+            }
+        })
+    };
+    let allow_drop = Callback::from(|e: DragEvent| e.prevent_default());
+
+    let on_name_input = {
+        let new_folder_name = new_folder_name.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            new_folder_name.set(input.value());
+        })
+    };
+
+    let on_create_folder = {
+        let new_folder_name = new_folder_name.clone();
+        let selected = selected.clone();
+        let expanded = expanded.clone();
+        let reload = reload.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |_: MouseEvent| {
+            let name = (*new_folder_name).clone();
+            let parent = *selected;
+            let new_folder_name = new_folder_name.clone();
+            let expanded = expanded.clone();
+            let reload = reload.clone();
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                match host::api_client().create_folder(name, parent).await {
+                    Ok(_) => {
+                        new_folder_name.set(String::new());
+                        // Show the new folder inside its parent
+                        if let Some(parent) = parent {
+                            let mut open = (*expanded).clone();
+                            open.insert(parent);
+                            expanded.set(open);
+                        }
+                        reload.set(*reload + 1);
+                    }
+                    Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/folders")),
+                }
+            });
+        })
+    };
+
+    // Import a document archive exported from this or another server
+    let on_import_archive = {
+        let reload = reload.clone();
+        let on_open = props.on_open.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            // Allow choosing the same file again
+            input.set_value("");
+
+            let reload = reload.clone();
+            let on_open = on_open.clone();
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                let Ok(buffer) = JsFuture::from(file.array_buffer()).await else {
+                    return on_error.emit(ErrorCode::ImportError {
+                        reason: "ファイルを読み込めません".to_string(),
+                    });
+                };
+                let archive = js_sys::Uint8Array::new(&buffer).to_vec();
+                match host::api_client().import_document_archive(archive).await {
+                    Ok((document, result)) => {
+                        reload.set(*reload + 1);
+                        on_open.emit(document.id.0.to_string());
+                        on_error.emit(result.code);
+                    }
+                    Err(e) => on_error.emit(api_error_to_code(e, "/api/v1/documents/import")),
+                }
+            });
+        })
+    };
+
+    let render_row = |row: TreeRow| {
+        match row {
+            TreeRow::Folder {
+                folder,
+                depth,
+                expanded: is_expanded,
+            } => {
+                let id = folder.id;
+                let on_click = {
+                    let expanded = expanded.clone();
+                    let selected = selected.clone();
+                    Callback::from(move |_| {
+                        let mut open = (*expanded).clone();
+                        if !open.remove(&id) {
+                            open.insert(id);
+                        }
+                        expanded.set(open);
+                        selected.set(Some(id));
+                    })
+                };
+                let is_selected = *selected == Some(id);
+
+                html! {
+                    <div
+                        class={classes!("tree-row", "tree-folder", is_selected.then_some("selected"))}
+                        key={id.0.to_string()}
+                        style={format!("padding-left: {}rem", 0.5 + depth as f32)}
+                        onclick={on_click}
+                        ondragover={allow_drop.clone()}
+                        ondrop={drop_into(Some(id))}
+                    >
+                        { if is_expanded { "▾ 📁 " } else { "▸ 📁 " } }
+                        { &folder.name }
+                    </div>
+                }
+            }
+            TreeRow::Document { document, depth } => {
+                let id = document.id.0.to_string();
+                let on_drag_start = {
+                    let id = id.clone();
+                    Callback::from(move |e: DragEvent| {
+                        if let Some(transfer) = e.data_transfer() {
+                            let _ = transfer.set_data(DRAG_FORMAT, &format!("plantuml-document:{}", id));
+                            transfer.set_effect_allowed("move");
+                        }
+                    })
+                };
+                let on_open = {
+                    let on_open = props.on_open.clone();
+                    let id = id.clone();
+                    Callback::from(move |_| on_open.emit(id.clone()))
+                };
+
+                html! {
+                    <div
+                        class="tree-row tree-document"
+                        key={id}
+                        style={format!("padding-left: {}rem", 0.5 + depth as f32)}
+                        draggable="true"
+                        ondragstart={on_drag_start}
+                        onclick={on_open}
+                    >
+                        { format!("📄 {}", document_title(document)) }
+                    </div>
+                }
+            }
+        }
+    };
+
+    let select_root = {
+        let selected = selected.clone();
+        Callback::from(move |_| selected.set(None))
+    };
+
+    let rows = tree_rows(&folders, &documents, &expanded);
+    let target = selected
+        .and_then(|id| folders.iter().find(|folder| folder.id == id))
+        .map_or("トップ", |folder| folder.name.as_str());
+
+    html! {
+        <div class="document-tree">
+            <div class="tree-new-folder">
+                <input
+                    type="text"
+                    placeholder={format!("新しいフォルダー（{}内）", target)}
+                    value={(*new_folder_name).clone()}
+                    oninput={on_name_input}
+                />
+                <button class="tree-new-folder-btn" onclick={on_create_folder}>{ "作成" }</button>
+            </div>
+            <label class="tree-import-archive">
+                { "📦 アーカイブから取り込む" }
+                <input type="file" accept=".zip,application/zip" onchange={on_import_archive} />
+            </label>
+            <div
+                class={classes!("tree-row", "tree-root", selected.is_none().then_some("selected"))}
+                onclick={select_root}
+                ondragover={allow_drop.clone()}
+                ondrop={drop_into(None)}
+            >
+                { "トップ" }
+            </div>
+            if rows.is_empty() {
+                <div class="tree-empty">{ "サーバーにドキュメントがありません" }</div>
+            }
+            { for rows.into_iter().map(render_row) }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(name: &str, parent: Option<FolderId>) -> Folder {
+        Folder {
+            id: FolderId::new(),
+            name: name.to_string(),
+            parent,
+            created_at: 0,
+        }
+    }
+
+    fn document(title: &str, folder: Option<FolderId>) -> PlantUMLDocument {
+        let mut document = PlantUMLDocument::new("@startuml\n@enduml".to_string());
+        document.title = Some(title.to_string());
+        document.folder = folder;
+        document
+    }
+
+    fn labels(rows: &[TreeRow]) -> Vec<String> {
+        rows.iter()
+            .map(|row| match row {
+                TreeRow::Folder { folder, depth, .. } => format!("{}{}/", " ".repeat(*depth), folder.name),
+                TreeRow::Document { document, depth } => {
+                    format!("{}{}", " ".repeat(*depth), document_title(document))
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_rows_nest_expanded_folders() {
+        let specs = folder("specs", None);
+        let api = folder("api", Some(specs.id));
+        let documents = vec![
+            document("login", Some(api.id)),
+            document("overview", Some(specs.id)),
+            document("readme", None),
+            document("orphan", Some(FolderId::new())),
+        ];
+        let folders = vec![api.clone(), specs.clone()];
+
+        let collapsed = tree_rows(&folders, &documents, &HashSet::new());
+        assert_eq!(labels(&collapsed), vec!["specs/", "orphan", "readme"]);
+
+        let expanded = HashSet::from([specs.id, api.id]);
+        let rows = tree_rows(&folders, &documents, &expanded);
+        assert_eq!(
+            labels(&rows),
+            vec!["specs/", " api/", "  login", " overview", "orphan", "readme"]
+        );
+    }
+
+    #[test]
+    fn test_dragged_document() {
+        assert_eq!(dragged_document("plantuml-document:abc"), Some("abc"));
+        assert_eq!(dragged_document("plantuml-slot:3"), None);
+    }
+}
@@ -1,52 +1,469 @@
-// Editor component with debounce
+// Editor component with render-on-idle scheduling
+//
+// Normally a plain textarea. With `prose_spellcheck` the source is shown in a
+// contenteditable surface instead, split into spans by the core tokenizer so
+// the browser spellchecks notes and labels but not identifiers and keywords.
+// The spans are rebuilt whenever the scheduled update runs, keeping the
+// caret. Updates wait for a typing pause and idle time (see crate::idle).
+//
+// Tab and Shift+Tab indent and outdent the selected lines. Pressing Escape
+// first lets the next Tab move the focus out of the editor as usual. Enter
+// at the end of a line continues notes, groups and optionally messages (see
+// core::continuation); Shift+Enter inserts a plain line break.
+//
+// Without prose spellcheck the text is edited through an EditorBackend (see
+// crate::editor_backend): the textarea, or CodeMirror with the `codemirror`
+// feature. CodeMirror brings its own key handling, so the commands above are
+// the textarea's and the prose surface's.
 
-use gloo_timers::callback::Timeout;
+use crate::editor_backend::{EditorBackend, EditorKind, TextAreaBackend};
+
+use crate::idle::IdleTask;
+use plantuml_editor_core::continuation::{continue_line, ContinuationOptions};
+use plantuml_editor_core::indent::{indent, outdent, IndentUnit};
+use plantuml_editor_core::text::{char_count, scalar_count};
+use plantuml_editor_core::tokens::{prose_spans, SpanKind};
+use plantuml_editor_core::{content_size_warning, Diagnostic, MAX_CONTENT_CHARS};
 use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
 pub struct EditorProps {
     pub value: String,
     pub on_change: Callback<String>,
+    /// Show the source without allowing edits (server documents opened by a viewer)
+    #[prop_or_default]
+    pub read_only: bool,
+    /// Spellcheck only notes, labels and comments
+    #[prop_or_default]
+    pub prose_spellcheck: bool,
+    /// Caret position (UTF-16 offset) after each input, click or key press
+    #[prop_or_default]
+    pub on_cursor: Callback<usize>,
+    /// Selected range (UTF-16 offsets), reported together with the caret
+    #[prop_or_default]
+    pub on_selection: Callback<(usize, usize)>,
+    /// Indentation added by Tab and removed by Shift+Tab
+    #[prop_or_default]
+    pub indent_unit: IndentUnit,
+    /// Start the line after a message with the same participants on Enter
+    #[prop_or_default]
+    pub prefill_messages: bool,
+    /// Surface to edit in (the prose surface takes precedence)
+    #[prop_or_default]
+    pub kind: EditorKind,
+    /// Diagnostics to mark in the text
+    #[prop_or_default]
+    pub markers: Vec<Diagnostic>,
 }
 
 #[function_component(Editor)]
 pub fn editor(props: &EditorProps) -> Html {
     let content = use_state(|| props.value.clone());
-    let timeout_handle = use_state(|| None::<Timeout>);
+    // Not state: replacing the pending update must not re-render while typing
+    let pending = use_mut_ref(|| None::<IdleTask>);
+    let surface = use_node_ref();
+    let textarea = use_node_ref();
+    // Counts the updates passed on, so markers are redrawn for the new text
+    let updates = use_mut_ref(|| 0u32);
+    let revision = use_state(|| 0u32);
+    // Element CodeMirror is mounted in
+    let host = use_node_ref();
+    #[cfg(feature = "codemirror")]
+    let code_mirror = use_mut_ref(|| None::<crate::codemirror::CodeMirrorBackend>);
 
-    let on_input = {
+    // Fill the prose surface when it appears; afterwards the DOM is its own
+    {
+        let surface = surface.clone();
         let content = content.clone();
-        let timeout_handle = timeout_handle.clone();
+        use_effect_with(props.prose_spellcheck, move |enabled| {
+            if let (true, Some(element)) = (*enabled, surface.cast::<web_sys::HtmlElement>()) {
+                highlight(&element, &content, false);
+            }
+        });
+    }
+
+    let schedule = {
+        let pending = pending.clone();
         let on_change = props.on_change.clone();
+        let surface = surface.clone();
+        let updates = updates.clone();
+        let revision = revision.clone();
+
+        Callback::from(move |value: String| {
+            let on_change = on_change.clone();
+            let surface = surface.clone();
+            let updates = updates.clone();
+            let revision = revision.clone();
+            // Replacing the task cancels the previous one, coalescing bursts
+            *pending.borrow_mut() = Some(IdleTask::schedule(move || {
+                if let Some(element) = surface.cast::<web_sys::HtmlElement>() {
+                    highlight(&element, &value, true);
+                }
+                on_change.emit(value);
+                let update = {
+                    let mut updates = updates.borrow_mut();
+                    *updates += 1;
+                    *updates
+                };
+                revision.set(update);
+            }));
+        })
+    };
+
+    #[cfg(feature = "codemirror")]
+    {
+        let host = host.clone();
+        let code_mirror = code_mirror.clone();
+        let content = content.clone();
+        let schedule = schedule.clone();
+        let on_cursor = props.on_cursor.clone();
+        let on_selection = props.on_selection.clone();
+        let read_only = props.read_only;
+        use_effect_with((props.kind, props.prose_spellcheck), move |(kind, prose)| {
+            if let (true, false, Some(parent)) =
+                (kind.is_code_mirror(), *prose, host.cast::<web_sys::Element>())
+            {
+                let events = crate::editor_backend::EditorEvents {
+                    on_change: {
+                        let content = content.clone();
+                        Callback::from(move |text: String| {
+                            content.set(text.clone());
+                            schedule.emit(text);
+                        })
+                    },
+                    on_selection: Callback::from(move |(start, end): (usize, usize)| {
+                        on_cursor.emit(end);
+                        on_selection.emit((start, end));
+                    }),
+                };
+                *code_mirror.borrow_mut() =
+                    crate::codemirror::CodeMirrorBackend::mount(&parent, &content, read_only, events)
+                        .ok();
+            }
+            move || {
+                code_mirror.borrow_mut().take();
+            }
+        });
+    }
+    #[cfg(feature = "codemirror")]
+    {
+        let code_mirror = code_mirror.clone();
+        use_effect_with(props.read_only, move |read_only| {
+            if let Some(backend) = code_mirror.borrow().as_ref() {
+                backend.set_read_only(*read_only);
+            }
+        });
+    }
+
+    // After mounting, so a new surface gets the current markers. Typing
+    // clears the textarea's; they come back with the update of the text.
+    {
+        let textarea = textarea.clone();
+        #[cfg(feature = "codemirror")]
+        let code_mirror = code_mirror.clone();
+        use_effect_with(
+            (props.markers.clone(), props.kind, props.prose_spellcheck, *revision),
+            move |(markers, _, _, _)| {
+                if let Some(element) = textarea.cast::<web_sys::HtmlTextAreaElement>() {
+                    TextAreaBackend::new(element).set_markers(markers);
+                }
+                #[cfg(feature = "codemirror")]
+                if let Some(backend) = code_mirror.borrow().as_ref() {
+                    backend.set_markers(markers);
+                }
+            },
+        );
+    }
+
+    let report_cursor = {
+        let on_cursor = props.on_cursor.clone();
+        let on_selection = props.on_selection.clone();
+        let surface = surface.clone();
+
+        Callback::from(move |e: Event| {
+            let selection = match surface.cast::<web_sys::HtmlElement>() {
+                Some(element) => selection_offsets(&element).map(|(start, end)| (end, start, end)),
+                None => TextAreaBackend::new(e.target_unchecked_into())
+                    .selection()
+                    .map(|(start, end)| (start as u32, start as u32, end as u32)),
+            };
+            if let Some((cursor, start, end)) = selection {
+                on_cursor.emit(cursor as usize);
+                on_selection.emit((start as usize, end as usize));
+            }
+        })
+    };
+    let on_click = report_cursor.reform(|e: MouseEvent| e.into());
+    let on_keyup = report_cursor.reform(|e: KeyboardEvent| e.into());
+
+    // Set by Escape so that the next Tab leaves the editor
+    let tab_leaves = use_mut_ref(|| false);
+    let on_keydown = {
+        let content = content.clone();
+        let schedule = schedule.clone();
+        let surface = surface.clone();
+        let read_only = props.read_only;
+        let unit = props.indent_unit;
+        let prefill_messages = props.prefill_messages;
+
+        Callback::from(move |e: KeyboardEvent| {
+            let key = e.key();
+            let leaves = std::mem::replace(&mut *tab_leaves.borrow_mut(), key == "Escape");
+            let handled = match key.as_str() {
+                "Tab" => !leaves,
+                "Enter" => !e.shift_key(),
+                _ => false,
+            };
+            // Enter also confirms IME conversions
+            if !handled
+                || read_only
+                || e.is_composing()
+                || e.ctrl_key()
+                || e.alt_key()
+                || e.meta_key()
+            {
+                return;
+            }
+            let surface = surface.cast::<web_sys::HtmlElement>();
+            let textarea = TextAreaBackend::new(e.target_unchecked_into());
+            let selection = match &surface {
+                Some(element) => selection_offsets(element),
+                None => textarea
+                    .selection()
+                    .map(|(start, end)| (start as u32, end as u32)),
+            };
+            let Some((start, end)) = selection else {
+                return;
+            };
+
+            let (start, end) = (start as usize, end as usize);
+            let edit = if key == "Enter" {
+                let options = ContinuationOptions {
+                    unit,
+                    prefill_messages,
+                };
+                // Anything but a caret at the end of a line gets a plain line break
+                let edit = (start == end)
+                    .then(|| continue_line(&content, start, options))
+                    .flatten();
+                let Some(edit) = edit else {
+                    return;
+                };
+                edit
+            } else if e.shift_key() {
+                outdent(&content, start, end, unit)
+            } else {
+                indent(&content, start, end, unit)
+            };
+            e.prevent_default();
+            let (start, end) = (edit.start as u32, edit.end as u32);
+            match &surface {
+                Some(element) => {
+                    highlight(element, &edit.text, false);
+                    set_selection(element, start, end);
+                }
+                None => {
+                    textarea.set_content(&edit.text);
+                    textarea.set_selection(start as usize, end as usize);
+                    textarea.set_markers(&[]);
+                }
+            }
+            content.set(edit.text.clone());
+            schedule.emit(edit.text);
+        })
+    };
+
+    let on_input = {
+        let content = content.clone();
+        let schedule = schedule.clone();
+        let report_cursor = report_cursor.clone();
 
         Callback::from(move |e: InputEvent| {
             let input: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
             let value = input.value();
+            // The underlines no longer match the text
+            TextAreaBackend::new(input).set_markers(&[]);
             content.set(value.clone());
+            schedule.emit(value);
+            report_cursor.emit(e.into());
+        })
+    };
 
-            // Cancel previous timeout by dropping the old handle
-            timeout_handle.set(None);
+    let on_surface_input = {
+        let content = content.clone();
 
-            // Set new timeout for debounce (500ms)
-            let on_change = on_change.clone();
-            let new_handle = Timeout::new(500, move || {
-                on_change.emit(value);
-            });
-            timeout_handle.set(Some(new_handle));
+        Callback::from(move |e: InputEvent| {
+            let element: web_sys::HtmlElement = e.target_unchecked_into();
+            let value = element.inner_text();
+            content.set(value.clone());
+            schedule.emit(value);
+            report_cursor.emit(e.into());
         })
     };
 
+    let on_scroll = Callback::from(|e: Event| {
+        TextAreaBackend::new(e.target_unchecked_into()).sync_scroll();
+    });
+    let on_mouse_move = Callback::from(|e: MouseEvent| {
+        let x = e.client_x() as f64;
+        let y = e.client_y() as f64;
+        TextAreaBackend::new(e.target_unchecked_into()).hover(x, y);
+    });
+
+    let chars = char_count(&content);
+    // The limit itself counts scalar values (see validate_plantuml_content)
+    let over_limit = scalar_count(&content) > MAX_CONTENT_CHARS;
+    // Early notice before edits start failing with ValidationTextLimit
+    let size_warning = content_size_warning(&content);
+    let editable = if props.read_only {
+        "false"
+    } else {
+        "plaintext-only"
+    };
+
     html! {
-        <textarea
-            class="editor-textarea"
-            placeholder="PlantUMLソースを入力してください...
+        <>
+            if props.read_only {
+                <div class="read-only-badge">{ "閲覧のみ（編集権限がありません）" }</div>
+            }
+            if props.prose_spellcheck {
+                <div
+                    ref={surface}
+                    class="editor-textarea editor-prose"
+                    contenteditable={editable}
+                    spellcheck="false"
+                    oninput={on_surface_input}
+                    onclick={on_click.clone()}
+                    onkeyup={on_keyup.clone()}
+                    onkeydown={on_keydown.clone()}
+                />
+            } else if props.kind.is_code_mirror() {
+                <div ref={host} class="editor-textarea editor-codemirror" />
+            } else {
+                <div class="editor-surface">
+                    <div class="editor-backdrop" aria-hidden="true" />
+                    <textarea
+                        ref={textarea}
+                        class="editor-textarea"
+                        placeholder="PlantUMLソースを入力してください...
 例:
 @startuml
 Alice -> Bob: Hello
 Bob --> Alice: Hi!
 @enduml"
-            oninput={on_input}
-            value={(*content).clone()}
-        />
+                        oninput={on_input}
+                        onclick={on_click}
+                        onkeyup={on_keyup}
+                        onkeydown={on_keydown}
+                        onscroll={on_scroll}
+                        onmousemove={on_mouse_move}
+                        readonly={props.read_only}
+                        value={(*content).clone()}
+                    />
+                </div>
+            }
+            if let Some(warning) = &size_warning {
+                <div class="size-warning" role="status">{ warning.to_message() }</div>
+            }
+            <div class={classes!(
+                "char-counter",
+                over_limit.then_some("over-limit"),
+                size_warning.is_some().then_some("near-limit"),
+            )}>
+                { format!("{} / {} 文字", chars, MAX_CONTENT_CHARS) }
+            </div>
+        </>
+    }
+}
+
+/// Replace the surface contents with one span per code or prose region
+fn highlight(element: &web_sys::HtmlElement, text: &str, keep_caret: bool) {
+    let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+        return;
+    };
+    let caret = keep_caret.then(|| caret_offset(element)).flatten();
+
+    element.set_text_content(None);
+    for span in prose_spans(text) {
+        let Ok(node) = document.create_element("span") else {
+            return;
+        };
+        let prose = span.kind == SpanKind::Prose;
+        node.set_class_name(if prose { "prose" } else { "code" });
+        let _ = node.set_attribute("spellcheck", if prose { "true" } else { "false" });
+        node.set_text_content(Some(&text[span.start..span.end]));
+        let _ = element.append_child(&node);
+    }
+
+    if let Some(caret) = caret {
+        set_selection(element, caret, caret);
+    }
+}
+
+/// Caret position in UTF-16 units from the start of `element`
+fn caret_offset(element: &web_sys::HtmlElement) -> Option<u32> {
+    selection_offsets(element).map(|(_, end)| end)
+}
+
+/// Start and end of the selection in UTF-16 units from the start of `element`
+fn selection_offsets(element: &web_sys::HtmlElement) -> Option<(u32, u32)> {
+    let selection = web_sys::window()?.get_selection().ok()??;
+    if selection.range_count() == 0 {
+        return None;
+    }
+    let range = selection.get_range_at(0).ok()?;
+    if !element.contains(Some(&range.end_container().ok()?)) {
+        return None;
+    }
+    let offset = |container: web_sys::Node, offset: u32| {
+        let before = range.clone_range();
+        before.select_node_contents(element).ok()?;
+        before.set_end(&container, offset).ok()?;
+        Some(String::from(before.to_string()).encode_utf16().count() as u32)
+    };
+    let start = offset(range.start_container().ok()?, range.start_offset().ok()?)?;
+    let end = offset(range.end_container().ok()?, range.end_offset().ok()?)?;
+    Some((start, end))
+}
+
+/// Select `start..end` (UTF-16 units) in the spans built by `highlight`
+fn set_selection(element: &web_sys::HtmlElement, start: u32, end: u32) {
+    let Some(selection) =
+        web_sys::window().and_then(|window| window.get_selection().ok().flatten())
+    else {
+        return;
+    };
+    let Ok(range) = web_sys::Range::new() else {
+        return;
+    };
+    let (Some((start_node, start)), Some((end_node, end))) =
+        (text_position(element, start), text_position(element, end))
+    else {
+        return;
+    };
+    if range.set_start(&start_node, start).is_err() || range.set_end(&end_node, end).is_err() {
+        return;
+    }
+    let _ = selection.remove_all_ranges();
+    let _ = selection.add_range(&range);
+}
+
+/// Text node and offset in it `offset` UTF-16 units into the spans
+fn text_position(element: &web_sys::HtmlElement, mut offset: u32) -> Option<(web_sys::Node, u32)> {
+    let spans = element.child_nodes();
+    for index in 0..spans.length() {
+        let Some(text) = spans.item(index).and_then(|span| span.first_child()) else {
+            continue;
+        };
+        let len = text
+            .text_content()
+            .unwrap_or_default()
+            .encode_utf16()
+            .count() as u32;
+        if offset <= len || index + 1 == spans.length() {
+            return Some((text, offset.min(len)));
+        }
+        offset -= len;
     }
+    None
 }
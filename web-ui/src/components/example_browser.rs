@@ -0,0 +1,185 @@
+// Dialog for browsing the example diagrams bundled with the API server
+//
+// Examples are listed by category with their rendered thumbnails; picking one
+// loads its source into the editor like the other imports.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use plantuml_editor_core::{ErrorCode, Example};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+use crate::object_url::ObjectUrl;
+
+const ENDPOINT: &str = "/api/v1/examples";
+
+/// Examples of `category`, or all of them
+pub fn examples_in<'a>(examples: &'a [Example], category: Option<&str>) -> Vec<&'a Example> {
+    examples
+        .iter()
+        .filter(|example| category.is_none_or(|category| example.category == category))
+        .collect()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ExampleBrowserProps {
+    /// Called with the source of the chosen example
+    pub on_import: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(ExampleBrowser)]
+pub fn example_browser(props: &ExampleBrowserProps) -> Html {
+    let categories = use_state(Vec::<String>::new);
+    let examples = use_state(Vec::<Example>::new);
+    let category = use_state(|| None::<String>);
+    let thumbnails = use_mut_ref(HashMap::<String, Rc<ObjectUrl>>::new);
+    let loading = use_state(|| true);
+    let refresh = use_force_update();
+
+    // Load the list, then the thumbnails one by one
+    {
+        let categories = categories.clone();
+        let examples = examples.clone();
+        let thumbnails = thumbnails.clone();
+        let loading = loading.clone();
+        let on_error = props.on_error.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let client = host::api_client();
+                let list = match client.examples().await {
+                    Ok((list_categories, list)) => {
+                        categories.set(list_categories);
+                        examples.set(list.clone());
+                        list
+                    }
+                    Err(e) => {
+                        on_error.emit(api_error_to_code(e, ENDPOINT));
+                        Vec::new()
+                    }
+                };
+                loading.set(false);
+
+                for example in list {
+                    // A missing thumbnail only leaves its card without a picture
+                    let Ok(svg) = client.example_thumbnail(&example.id).await else {
+                        continue;
+                    };
+                    let svg = String::from_utf8_lossy(&svg);
+                    if let Ok(url) = ObjectUrl::from_svg(&svg) {
+                        thumbnails.borrow_mut().insert(example.id, Rc::new(url));
+                        refresh.force_update();
+                    }
+                }
+            });
+        });
+    }
+
+    let on_cancel = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    let tab = |label: &str, value: Option<String>| {
+        let active = *category == value;
+        let onclick = {
+            let category = category.clone();
+            Callback::from(move |_| category.set(value.clone()))
+        };
+        html! {
+            <button class={classes!("example-tab", active.then_some("active"))} {onclick}>
+                { label }
+            </button>
+        }
+    };
+    let tabs = categories
+        .iter()
+        .map(|name| tab(name, Some(name.clone())));
+
+    let thumbnails = thumbnails.borrow();
+    let cards = examples_in(&examples, category.as_deref())
+        .into_iter()
+        .map(|example| {
+            let onclick = {
+                let text = example.plantuml_text.clone();
+                let on_import = props.on_import.clone();
+                let on_close = props.on_close.clone();
+                Callback::from(move |_| {
+                    on_import.emit(text.clone());
+                    on_close.emit(());
+                })
+            };
+            html! {
+                <button class="example-card" key={example.id.clone()} {onclick} title={example.description.clone()}>
+                    <div class="example-thumbnail">
+                        if let Some(url) = thumbnails.get(&example.id) {
+                            <img src={url.as_str().to_string()} alt={example.title.clone()} />
+                        } else {
+                            <span class="loading">{"..."}</span>
+                        }
+                    </div>
+                    <div class="example-title">{ &example.title }</div>
+                    <div class="example-category">{ &example.category }</div>
+                </button>
+            }
+        });
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog example-browser">
+                <div class="import-dialog-header">{"サンプル図から選ぶ"}</div>
+                <div class="example-tabs">
+                    { tab("すべて", None) }
+                    { for tabs }
+                </div>
+                if *loading {
+                    <div class="loading">{"読み込み中..."}</div>
+                }
+                <div class="example-grid">
+                    { for cards }
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{"閉じる"}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example(id: &str, category: &str) -> Example {
+        Example {
+            id: id.to_string(),
+            title: id.to_string(),
+            category: category.to_string(),
+            description: String::new(),
+            plantuml_text: "@startuml\n@enduml".to_string(),
+            thumbnail_url: format!("/api/v1/examples/{}/thumbnail", id),
+        }
+    }
+
+    #[test]
+    fn test_examples_in() {
+        let examples = vec![
+            example("sequence-login", "シーケンス図"),
+            example("class-shop", "クラス図"),
+            example("sequence-order", "シーケンス図"),
+        ];
+        let ids = |category| {
+            examples_in(&examples, category)
+                .iter()
+                .map(|example| example.id.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(None).len(), 3);
+        assert_eq!(ids(Some("シーケンス図")), vec!["sequence-login", "sequence-order"]);
+        assert!(ids(Some("ガントチャート")).is_empty());
+    }
+}
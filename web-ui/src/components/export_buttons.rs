@@ -1,16 +1,29 @@
 // Export buttons component for downloading diagrams
 
-use plantuml_editor_core::ImageFormat;
+use plantuml_editor_core::{ExportScale, ImageFormat};
 use yew::prelude::*;
 
 #[derive(Properties, PartialEq)]
 pub struct ExportButtonsProps {
-    pub on_export: Callback<ImageFormat>,
+    /// Format and resolution preset (the preset only changes PNG output)
+    pub on_export: Callback<(ImageFormat, ExportScale)>,
+    /// Pages in the current document; slide export is offered above one
+    #[prop_or(1)]
+    pub page_count: usize,
+    /// Export every page as a numbered file in a ZIP
+    #[prop_or_default]
+    pub on_export_slides: Callback<()>,
+    /// Exported PNG and SVG files carry their source
+    #[prop_or_default]
+    pub embed_source: bool,
+    #[prop_or_default]
+    pub on_toggle_embed_source: Callback<()>,
 }
 
 #[function_component(ExportButtons)]
 pub fn export_buttons(props: &ExportButtonsProps) -> Html {
     let dropdown_open = use_state(|| false);
+    let scale = use_state(ExportScale::default);
 
     let toggle_dropdown = {
         let dropdown_open = dropdown_open.clone();
@@ -22,8 +35,9 @@ pub fn export_buttons(props: &ExportButtonsProps) -> Html {
     let on_export_png = {
         let on_export = props.on_export.clone();
         let dropdown_open = dropdown_open.clone();
+        let scale = scale.clone();
         Callback::from(move |_| {
-            on_export.emit(ImageFormat::Png);
+            on_export.emit((ImageFormat::Png, *scale));
             dropdown_open.set(false);
         })
     };
@@ -32,11 +46,51 @@ pub fn export_buttons(props: &ExportButtonsProps) -> Html {
         let on_export = props.on_export.clone();
         let dropdown_open = dropdown_open.clone();
         Callback::from(move |_| {
-            on_export.emit(ImageFormat::Svg);
+            // SVG is vector data; scaling it only changes its nominal size
+            on_export.emit((ImageFormat::Svg, ExportScale::X1));
             dropdown_open.set(false);
         })
     };
 
+    let on_export_pdf = {
+        let on_export = props.on_export.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            // Vector output as well, for printing
+            on_export.emit((ImageFormat::Pdf, ExportScale::X1));
+            dropdown_open.set(false);
+        })
+    };
+
+    let on_export_slides = {
+        let on_export_slides = props.on_export_slides.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            on_export_slides.emit(());
+            dropdown_open.set(false);
+        })
+    };
+
+    let on_toggle_embed_source = {
+        let on_toggle_embed_source = props.on_toggle_embed_source.clone();
+        Callback::from(move |_| on_toggle_embed_source.emit(()))
+    };
+
+    let render_preset = |preset: ExportScale| {
+        let on_click = {
+            let scale = scale.clone();
+            Callback::from(move |_| scale.set(preset))
+        };
+        html! {
+            <button
+                class={classes!("scale-preset", (*scale == preset).then_some("active"))}
+                onclick={on_click}
+            >
+                { preset.as_str() }
+            </button>
+        }
+    };
+
     html! {
         <div class={classes!("export-dropdown", dropdown_open.then(|| "open"))}>
             <button class="export-btn" onclick={toggle_dropdown}>
@@ -44,12 +98,32 @@ pub fn export_buttons(props: &ExportButtonsProps) -> Html {
                 <span>{"▼"}</span>
             </button>
             <div class="export-options">
+                <div class="scale-presets" title="PNGの解像度（印刷や4Kスライドには2x/4x）">
+                    <span class="scale-label">{"PNG解像度"}</span>
+                    { for ExportScale::ALL.into_iter().map(render_preset) }
+                </div>
+                <label class="embed-source-toggle" title="画像を「画像からソースを復元」で開くと、編集できるソースに戻せます">
+                    <input
+                        type="checkbox"
+                        checked={props.embed_source}
+                        onchange={on_toggle_embed_source}
+                    />
+                    {"ソースを埋め込む"}
+                </label>
                 <button class="export-option" onclick={on_export_png}>
-                    {"PNG形式で保存"}
+                    {format!("PNG形式で保存 ({})", scale.as_str())}
                 </button>
                 <button class="export-option" onclick={on_export_svg}>
                     {"SVG形式で保存"}
                 </button>
+                <button class="export-option" onclick={on_export_pdf}>
+                    {"PDF形式で保存"}
+                </button>
+                if props.page_count > 1 {
+                    <button class="export-option" onclick={on_export_slides}>
+                        {format!("スライド用に全{}ページを保存 (ZIP)", props.page_count)}
+                    </button>
+                }
             </div>
         </div>
     }
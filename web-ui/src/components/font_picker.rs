@@ -0,0 +1,57 @@
+// Font family for rendering, from the fonts the server allows (ALLOWED_FONTS)
+
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::host;
+
+#[derive(Properties, PartialEq)]
+pub struct FontPickerProps {
+    /// Chosen font (None for the server default)
+    pub font: Option<String>,
+    pub on_change: Callback<Option<String>>,
+}
+
+#[function_component(FontPicker)]
+pub fn font_picker(props: &FontPickerProps) -> Html {
+    let fonts = use_state(Vec::<String>::new);
+
+    {
+        let fonts = fonts.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                // Without the list the option is simply not offered
+                if let Ok(list) = host::api_client().fonts().await {
+                    fonts.set(list);
+                }
+            });
+            || ()
+        });
+    }
+
+    if fonts.is_empty() {
+        return html! {};
+    }
+
+    let on_select = props.on_change.reform(|e: Event| {
+        let value = e
+            .target_unchecked_into::<web_sys::HtmlSelectElement>()
+            .value();
+        (!value.is_empty()).then_some(value)
+    });
+
+    html! {
+        <select
+            class="theme-select"
+            title="プレビューとエクスポートに使うフォント（日本語が文字化けする場合に変更します）"
+            onchange={on_select}
+        >
+            <option value="" selected={props.font.is_none()}>{ "フォント: 既定" }</option>
+            { for fonts.iter().map(|name| html! {
+                <option value={name.clone()} selected={props.font.as_deref() == Some(name.as_str())}>
+                    { format!("フォント: {}", name) }
+                </option>
+            }) }
+        </select>
+    }
+}
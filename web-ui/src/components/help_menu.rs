@@ -0,0 +1,90 @@
+// Help menu: the "?" button in the corner of the page
+
+use yew::prelude::*;
+
+use crate::host;
+use crate::telemetry::{self, TelemetryMode};
+
+#[derive(Properties, PartialEq)]
+pub struct HelpMenuProps {
+    /// Start the guided tour (see components::tour)
+    pub on_start_tour: Callback<()>,
+    /// Open the bug report dialog (see components::report_dialog)
+    pub on_report: Callback<()>,
+}
+
+#[function_component(HelpMenu)]
+pub fn help_menu(props: &HelpMenuProps) -> Html {
+    let open = use_state(|| false);
+    let telemetry_mode = use_state(telemetry::mode);
+
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let on_start_tour = {
+        let on_start_tour = props.on_start_tour.clone();
+        let open = open.clone();
+        Callback::from(move |_| {
+            open.set(false);
+            on_start_tour.emit(());
+        })
+    };
+    let on_report = {
+        let on_report = props.on_report.clone();
+        let open = open.clone();
+        Callback::from(move |_| {
+            open.set(false);
+            on_report.emit(());
+        })
+    };
+
+    let on_telemetry_mode = {
+        let telemetry_mode = telemetry_mode.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Some(mode) = TelemetryMode::parse(&select.value()) {
+                telemetry::set_mode(mode);
+                telemetry_mode.set(mode);
+            }
+        })
+    };
+
+    // Send is offered only when the page configures an endpoint
+    let telemetry_modes: Vec<TelemetryMode> = TelemetryMode::ALL
+        .into_iter()
+        .filter(|mode| *mode != TelemetryMode::Send || host::telemetry_url().is_some())
+        .collect();
+
+    html! {
+        <div class={classes!("help-menu", open.then_some("open"))}>
+            if *open {
+                <div class="help-options">
+                    <button class="help-option" onclick={on_start_tour}>{ "使い方ツアー" }</button>
+                    <button class="help-option" onclick={on_report}>{ "問題を報告" }</button>
+                    if telemetry::ENABLED {
+                        <label class="help-telemetry">
+                            { "利用状況の記録" }
+                            <select onchange={on_telemetry_mode}>
+                                { for telemetry_modes.into_iter().map(|mode| html! {
+                                    <option value={mode.as_str()} selected={mode == *telemetry_mode}>
+                                        { mode.label() }
+                                    </option>
+                                }) }
+                            </select>
+                            <span class="help-telemetry-note">
+                                { "機能ごとの使用回数とエラーの種類だけを記録し、図の内容は含みません" }
+                            </span>
+                            if *telemetry_mode != TelemetryMode::Off {
+                                <span class="help-telemetry-note">
+                                    { format!("保存中の記録 {}回", telemetry::counts().values().sum::<u64>()) }
+                                </span>
+                            }
+                        </label>
+                    }
+                </div>
+            }
+            <button class="help-button" onclick={toggle} title="ヘルプ">{ "?" }</button>
+        </div>
+    }
+}
@@ -0,0 +1,168 @@
+// Icon picker: searchable OpenIconic, Devicons and emoji icons with
+// previews rendered by the server as they come into view
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use plantuml_editor_core::icons::{search, Icon};
+use plantuml_editor_core::ImageFormat;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::{host, svg_data_url, PREVIEW_TIMEOUT};
+
+/// Icons shown at once; narrowing the search shows the rest
+pub const MAX_VISIBLE_ICONS: usize = 48;
+
+thread_local! {
+    /// Rendered previews (icon -> SVG data URL), kept for the whole session
+    static PREVIEWS: RefCell<HashMap<Icon, String>> = RefCell::new(HashMap::new());
+}
+
+fn cached_preview(icon: &Icon) -> Option<String> {
+    PREVIEWS.with(|cache| cache.borrow().get(icon).cloned())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct IconPickerProps {
+    /// Chosen icon, to be inserted at the editor cursor
+    pub on_insert: Callback<Icon>,
+}
+
+#[function_component(IconPicker)]
+pub fn icon_picker(props: &IconPickerProps) -> Html {
+    let open = use_state(|| false);
+
+    let on_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(true))
+    };
+    let on_close = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(false))
+    };
+    let on_select = {
+        let open = open.clone();
+        let on_insert = props.on_insert.clone();
+        Callback::from(move |icon: Icon| {
+            open.set(false);
+            on_insert.emit(icon);
+        })
+    };
+
+    html! {
+        <>
+            <button class="theme-gallery-btn" onclick={on_open} title="アイコンを検索してカーソル位置に挿入">
+                { "アイコン" }
+            </button>
+            if *open {
+                <IconDialog on_select={on_select} on_close={on_close} />
+            }
+        </>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct IconDialogProps {
+    on_select: Callback<Icon>,
+    on_close: Callback<MouseEvent>,
+}
+
+#[function_component(IconDialog)]
+fn icon_dialog(props: &IconDialogProps) -> Html {
+    let query = use_state(String::new);
+    // Bumped whenever a preview arrives, to re-render from the cache
+    let loaded = use_state(|| 0usize);
+    // Lets a newer search stop the previews of an older one
+    let generation = use_mut_ref(|| 0u32);
+
+    let visible: Vec<Icon> = search(&query).into_iter().take(MAX_VISIBLE_ICONS).collect();
+
+    {
+        let loaded = loaded.clone();
+        let generation = generation.clone();
+        let visible = visible.clone();
+        use_effect_with((*query).clone(), move |_| {
+            *generation.borrow_mut() += 1;
+            let current = *generation.borrow();
+            // One at a time, so the picker does not crowd out the live preview
+            spawn_local(async move {
+                let client = host::api_client().with_timeout(PREVIEW_TIMEOUT);
+                let mut count = 0;
+                for icon in visible {
+                    if *generation.borrow() != current {
+                        return;
+                    }
+                    if cached_preview(&icon).is_some() {
+                        continue;
+                    }
+                    let Ok((bytes, _)) = client.convert(icon.preview_source(), ImageFormat::Svg).await
+                    else {
+                        continue;
+                    };
+                    if let Ok(svg) = String::from_utf8(bytes) {
+                        PREVIEWS.with(|cache| cache.borrow_mut().insert(icon, svg_data_url(&svg)));
+                        count += 1;
+                        loaded.set(count);
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_search = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+        })
+    };
+
+    let render_icon = |icon: &Icon| {
+        let icon = *icon;
+        let on_click = {
+            let on_select = props.on_select.clone();
+            Callback::from(move |_| on_select.emit(icon))
+        };
+        let syntax = icon.syntax();
+
+        html! {
+            <button class="icon-card" key={syntax.clone()} onclick={on_click} title={syntax.clone()}>
+                if let Some(data_url) = cached_preview(&icon) {
+                    <img class="icon-preview" src={data_url} alt={icon.name} />
+                } else {
+                    <div class="icon-preview loading">{ "..." }</div>
+                }
+                <span class="icon-name">{ icon.name }</span>
+                <span class="icon-set">{ icon.set.label() }</span>
+            </button>
+        }
+    };
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog icon-picker">
+                <div class="import-dialog-header">{ "アイコンを挿入" }</div>
+                <input
+                    class="icon-search"
+                    type="search"
+                    placeholder="名前で検索（例: cloud, docker, rocket）"
+                    value={(*query).clone()}
+                    oninput={on_search}
+                />
+                <div class="icon-grid">
+                    { for visible.iter().map(render_icon) }
+                </div>
+                if visible.is_empty() {
+                    <div class="icon-empty">{ "該当するアイコンがありません" }</div>
+                }
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={props.on_close.clone()}>
+                        { "閉じる" }
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,314 @@
+// Import menu component for converting pasted content into PlantUML
+
+use plantuml_editor_core::converters::{
+    convert_data_to_plantuml, convert_rust_to_plantuml, convert_sql_to_plantuml,
+    detect_data_format, ConvertError,
+};
+use plantuml_editor_core::ErrorCode;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use super::example_browser::ExampleBrowser;
+use super::openapi_import::OpenApiImportDialog;
+use super::url_import::UrlImportDialog;
+use crate::file_import;
+
+/// Kind of content accepted by the import dialog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    /// JSON or YAML (format is detected automatically)
+    Data,
+    /// CREATE TABLE statements (rendered as an ER diagram)
+    Sql,
+    /// Rust struct/enum definitions (rendered as a class diagram)
+    Rust,
+}
+
+impl ImportKind {
+    /// All kinds in menu order
+    pub const ALL: [ImportKind; 3] = [ImportKind::Data, ImportKind::Sql, ImportKind::Rust];
+
+    fn menu_label(&self) -> &'static str {
+        match self {
+            ImportKind::Data => "JSON/YAMLを貼り付け",
+            ImportKind::Sql => "SQL DDLからER図を作成",
+            ImportKind::Rust => "Rust型定義からクラス図を作成",
+        }
+    }
+
+    fn placeholder(&self) -> &'static str {
+        match self {
+            ImportKind::Data => "JSONまたはYAMLを貼り付けてください...",
+            ImportKind::Sql => "CREATE TABLE文を貼り付けてください...\n例:\nCREATE TABLE users (\n    id INTEGER PRIMARY KEY,\n    name VARCHAR(100) NOT NULL\n);",
+            ImportKind::Rust => "struct/enum定義を貼り付けてください...\n例:\npub struct User {\n    pub id: u64,\n    pub role: Role,\n}",
+        }
+    }
+
+    /// Convert pasted text into a PlantUML document
+    pub fn convert(&self, input: &str) -> Result<String, ConvertError> {
+        match self {
+            ImportKind::Data => {
+                let format = detect_data_format(input).ok_or_else(|| {
+                    if input.trim().is_empty() {
+                        ConvertError::EmptyInput
+                    } else {
+                        ConvertError::InvalidInput {
+                            format: "JSON/YAML".to_string(),
+                            reason: "形式を判別できませんでした".to_string(),
+                        }
+                    }
+                })?;
+                convert_data_to_plantuml(input, format)
+            }
+            ImportKind::Sql => convert_sql_to_plantuml(input),
+            ImportKind::Rust => convert_rust_to_plantuml(input),
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ImportMenuProps {
+    /// Called with the generated PlantUML source
+    pub on_import: Callback<String>,
+    /// Called with a source opened from a file, an image or a URL, which is
+    /// already saved there
+    pub on_open: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+}
+
+#[function_component(ImportMenu)]
+pub fn import_menu(props: &ImportMenuProps) -> Html {
+    let dropdown_open = use_state(|| false);
+    let active_kind = use_state(|| None::<ImportKind>);
+    let input = use_state(String::new);
+    let openapi_open = use_state(|| false);
+    let examples_open = use_state(|| false);
+    let url_open = use_state(|| false);
+
+    let toggle_dropdown = {
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            dropdown_open.set(!*dropdown_open);
+        })
+    };
+
+    let on_input = {
+        let input = input.clone();
+        Callback::from(move |e: InputEvent| {
+            let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+            input.set(textarea.value());
+        })
+    };
+
+    let on_cancel = {
+        let active_kind = active_kind.clone();
+        let input = input.clone();
+        Callback::from(move |_| {
+            active_kind.set(None);
+            input.set(String::new());
+        })
+    };
+
+    let on_submit = {
+        let active_kind = active_kind.clone();
+        let input = input.clone();
+        let on_import = props.on_import.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |_| {
+            let Some(kind) = *active_kind else {
+                return;
+            };
+            match kind.convert(&input) {
+                Ok(plantuml_text) => {
+                    on_import.emit(plantuml_text);
+                    active_kind.set(None);
+                    input.set(String::new());
+                }
+                Err(e) => on_error.emit(e.to_error_code()),
+            }
+        })
+    };
+
+    let menu_items = ImportKind::ALL.iter().map(|kind| {
+        let kind = *kind;
+        let active_kind = active_kind.clone();
+        let dropdown_open = dropdown_open.clone();
+        let onclick = Callback::from(move |_| {
+            active_kind.set(Some(kind));
+            dropdown_open.set(false);
+        });
+        html! {
+            <button class="import-option" {onclick}>{ kind.menu_label() }</button>
+        }
+    });
+
+    // Open a .puml file in any supported encoding (see file_import)
+    let on_open_file = {
+        let dropdown_open = dropdown_open.clone();
+        let on_open = props.on_open.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            // Allow choosing the same file again
+            input.set_value("");
+            dropdown_open.set(false);
+
+            let on_open = on_open.clone();
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                match file_import::read_text_file(&file).await {
+                    Ok(text) => on_open.emit(text),
+                    Err(code) => on_error.emit(code),
+                }
+            });
+        })
+    };
+
+    // Recover the source from an image exported with it embedded
+    let on_open_image = {
+        let dropdown_open = dropdown_open.clone();
+        let on_open = props.on_open.clone();
+        let on_error = props.on_error.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            input.set_value("");
+            dropdown_open.set(false);
+
+            let on_open = on_open.clone();
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                match file_import::read_embedded_source(&file).await {
+                    Ok(text) => on_open.emit(text),
+                    Err(code) => on_error.emit(code),
+                }
+            });
+        })
+    };
+
+    let open_openapi = {
+        let openapi_open = openapi_open.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            openapi_open.set(true);
+            dropdown_open.set(false);
+        })
+    };
+
+    let close_openapi = {
+        let openapi_open = openapi_open.clone();
+        Callback::from(move |_| openapi_open.set(false))
+    };
+
+    let open_url = {
+        let url_open = url_open.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            url_open.set(true);
+            dropdown_open.set(false);
+        })
+    };
+
+    let close_url = {
+        let url_open = url_open.clone();
+        Callback::from(move |_| url_open.set(false))
+    };
+
+    let open_examples = {
+        let examples_open = examples_open.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_| {
+            examples_open.set(true);
+            dropdown_open.set(false);
+        })
+    };
+
+    let close_examples = {
+        let examples_open = examples_open.clone();
+        Callback::from(move |_| examples_open.set(false))
+    };
+
+    html! {
+        <>
+            <div class={classes!("import-dropdown", dropdown_open.then(|| "open"))}>
+                <button class="import-btn" onclick={toggle_dropdown}>
+                    {"インポート"}
+                    <span>{"▼"}</span>
+                </button>
+                <div class="import-options">
+                    <label class="import-option">
+                        {"ファイルを開く"}
+                        <input
+                            type="file"
+                            class="import-file-input"
+                            accept={file_import::ACCEPT}
+                            onchange={on_open_file}
+                        />
+                    </label>
+                    <label class="import-option" title="ソースを埋め込んでエクスポートしたPNG・SVGから図を開きます">
+                        {"画像からソースを復元"}
+                        <input
+                            type="file"
+                            class="import-file-input"
+                            accept={file_import::ACCEPT_IMAGES}
+                            onchange={on_open_image}
+                        />
+                    </label>
+                    <button class="import-option" onclick={open_url}>
+                        {"画像URLから開く"}
+                    </button>
+                    { for menu_items }
+                    <button class="import-option" onclick={open_openapi}>
+                        {"OpenAPIからシーケンス図を作成"}
+                    </button>
+                    <button class="import-option" onclick={open_examples}>
+                        {"サンプル図から選ぶ"}
+                    </button>
+                </div>
+            </div>
+            if *openapi_open {
+                <OpenApiImportDialog
+                    on_import={props.on_import.clone()}
+                    on_error={props.on_error.clone()}
+                    on_close={close_openapi}
+                />
+            }
+            if *url_open {
+                <UrlImportDialog
+                    on_import={props.on_open.clone()}
+                    on_error={props.on_error.clone()}
+                    on_close={close_url}
+                />
+            }
+            if *examples_open {
+                <ExampleBrowser
+                    on_import={props.on_import.clone()}
+                    on_error={props.on_error.clone()}
+                    on_close={close_examples}
+                />
+            }
+            if let Some(kind) = *active_kind {
+                <div class="import-dialog-overlay">
+                    <div class="import-dialog">
+                        <div class="import-dialog-header">{ kind.menu_label() }</div>
+                        <textarea
+                            class="import-textarea"
+                            placeholder={kind.placeholder()}
+                            value={(*input).clone()}
+                            oninput={on_input}
+                        />
+                        <div class="import-dialog-actions">
+                            <button class="import-cancel-btn" onclick={on_cancel}>{"キャンセル"}</button>
+                            <button class="import-submit-btn" onclick={on_submit}>{"取り込む"}</button>
+                        </div>
+                    </div>
+                </div>
+            }
+        </>
+    }
+}
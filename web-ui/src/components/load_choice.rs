@@ -0,0 +1,49 @@
+// Dialog shown when loading a slot over unsaved changes: replace the editor
+// content, open the slot in a new browser tab instead, or cancel
+
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct LoadChoiceDialogProps {
+    /// Slot about to be loaded
+    pub slot: usize,
+    /// Load the slot into this editor, discarding the changes
+    pub on_replace: Callback<()>,
+    /// Open the slot in a new tab, keeping this editor as it is
+    pub on_new_tab: Callback<()>,
+    pub on_cancel: Callback<()>,
+}
+
+#[function_component(LoadChoiceDialog)]
+pub fn load_choice_dialog(props: &LoadChoiceDialogProps) -> Html {
+    let on_replace = {
+        let on_replace = props.on_replace.clone();
+        Callback::from(move |_| on_replace.emit(()))
+    };
+    let on_new_tab = {
+        let on_new_tab = props.on_new_tab.clone();
+        Callback::from(move |_| on_new_tab.emit(()))
+    };
+    let on_cancel = {
+        let on_cancel = props.on_cancel.clone();
+        Callback::from(move |_| on_cancel.emit(()))
+    };
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog">
+                <div class="import-dialog-header">
+                    { format!("スロット{}を読み込む", props.slot) }
+                </div>
+                <div class="bulk-confirm-note">
+                    { "エディタに保存されていない変更があります。置き換えると変更は失われます" }
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{ "キャンセル" }</button>
+                    <button class="import-cancel-btn" onclick={on_new_tab}>{ "新しいタブで開く" }</button>
+                    <button class="import-submit-btn" onclick={on_replace}>{ "置き換える" }</button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1,13 +1,55 @@
 // Components module
 
+pub mod account_bar;
+pub mod activity_feed;
+pub mod batch_export;
+pub mod conflict_dialog;
+pub mod document_search;
+pub mod document_tree;
 pub mod editor;
+pub mod example_browser;
 pub mod export_buttons;
+pub mod font_picker;
+pub mod help_menu;
+pub mod icon_picker;
+pub mod import_menu;
+pub mod load_choice;
+pub mod openapi_import;
+pub mod participant_panel;
 pub mod preview;
+pub mod report_dialog;
 pub mod save_button;
 pub mod slot_list;
+pub mod stats_panel;
+pub mod status_banner;
+pub mod theme_picker;
+pub mod tour;
+pub mod trash_panel;
+pub mod url_import;
 
+pub use account_bar::AccountBar;
+pub use activity_feed::ActivityFeed;
+pub use batch_export::BatchExportDialog;
+pub use conflict_dialog::ConflictDialog;
+pub use document_search::DocumentSearch;
+pub use document_tree::DocumentTree;
 pub use editor::Editor;
+pub use example_browser::ExampleBrowser;
 pub use export_buttons::ExportButtons;
+pub use font_picker::FontPicker;
+pub use help_menu::HelpMenu;
+pub use icon_picker::IconPicker;
+pub use import_menu::{ImportKind, ImportMenu};
+pub use load_choice::LoadChoiceDialog;
+pub use openapi_import::OpenApiImportDialog;
+pub use participant_panel::ParticipantPanel;
 pub use preview::Preview;
+pub use report_dialog::ReportDialog;
 pub use save_button::{SaveButton, SaveValidationError};
 pub use slot_list::SlotList;
+pub use stats_panel::{RenderStats, StatsPanel};
+pub use status_banner::StatusBanner;
+pub use theme_picker::ThemePicker;
+pub use tour::Tour;
+pub use trash_panel::TrashPanel;
+pub use url_import::UrlImportDialog;
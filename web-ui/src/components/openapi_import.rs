@@ -0,0 +1,170 @@
+// OpenAPI upload dialog for generating sequence diagrams on the server
+
+use plantuml_editor_core::{ErrorCode, OpenApiOperation};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::{file_import, host};
+
+const ENDPOINT: &str = "/api/v1/import/openapi";
+
+#[derive(Properties, PartialEq)]
+pub struct OpenApiImportDialogProps {
+    /// Called with the generated PlantUML source
+    pub on_import: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(OpenApiImportDialog)]
+pub fn openapi_import_dialog(props: &OpenApiImportDialogProps) -> Html {
+    let spec = use_state(|| None::<String>);
+    let operations = use_state(Vec::<OpenApiOperation>::new);
+    let selected = use_state(Vec::<String>::new);
+    let loading = use_state(|| false);
+
+    let on_file_change = {
+        let spec = spec.clone();
+        let operations = operations.clone();
+        let selected = selected.clone();
+        let loading = loading.clone();
+        let on_error = props.on_error.clone();
+
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+
+            let spec = spec.clone();
+            let operations = operations.clone();
+            let selected = selected.clone();
+            let loading = loading.clone();
+            let on_error = on_error.clone();
+            loading.set(true);
+
+            spawn_local(async move {
+                let text = match file_import::read_text_file(&file).await {
+                    Ok(text) => text,
+                    Err(code) => {
+                        on_error.emit(code);
+                        loading.set(false);
+                        return;
+                    }
+                };
+
+                // Fetch the operation list (all operations selected by default);
+                // a diagram too large for all of them can still be made for a subset
+                match host::api_client().list_openapi_operations(text.clone()).await {
+                    Ok(ops) => {
+                        selected.set(ops.iter().map(|op| op.id.clone()).collect());
+                        operations.set(ops);
+                        spec.set(Some(text));
+                    }
+                    Err(e) => {
+                        operations.set(Vec::new());
+                        spec.set(None);
+                        on_error.emit(api_error_to_code(e, ENDPOINT));
+                    }
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    let on_submit = {
+        let spec = spec.clone();
+        let selected = selected.clone();
+        let loading = loading.clone();
+        let on_import = props.on_import.clone();
+        let on_error = props.on_error.clone();
+        let on_close = props.on_close.clone();
+
+        Callback::from(move |_| {
+            let Some(text) = (*spec).clone() else {
+                return;
+            };
+            let operations = (*selected).clone();
+            let loading = loading.clone();
+            let on_import = on_import.clone();
+            let on_error = on_error.clone();
+            let on_close = on_close.clone();
+            loading.set(true);
+
+            spawn_local(async move {
+                match host::api_client().import_openapi(text, operations).await {
+                    Ok((plantuml_text, _, _)) => {
+                        on_import.emit(plantuml_text);
+                        on_close.emit(());
+                    }
+                    Err(e) => on_error.emit(api_error_to_code(e, ENDPOINT)),
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    let on_cancel = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    let operation_items = operations.iter().map(|op| {
+        let id = op.id.clone();
+        let checked = selected.contains(&id);
+        let onchange = {
+            let selected = selected.clone();
+            Callback::from(move |_| {
+                let mut next = (*selected).clone();
+                if let Some(position) = next.iter().position(|s| *s == id) {
+                    next.remove(position);
+                } else {
+                    next.push(id.clone());
+                }
+                selected.set(next);
+            })
+        };
+        let label = match &op.summary {
+            Some(summary) => format!("{} {} — {}", op.method, op.path, summary),
+            None => format!("{} {}", op.method, op.path),
+        };
+        html! {
+            <label class="openapi-operation" key={op.id.clone()}>
+                <input type="checkbox" {checked} {onchange} />
+                { label }
+            </label>
+        }
+    });
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog">
+                <div class="import-dialog-header">{"OpenAPIからシーケンス図を作成"}</div>
+                <div class="openapi-body">
+                    <input
+                        type="file"
+                        accept=".json,.yaml,.yml"
+                        onchange={on_file_change}
+                    />
+                    if *loading {
+                        <div class="loading">{"処理中..."}</div>
+                    }
+                    <div class="openapi-operations">
+                        { for operation_items }
+                    </div>
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{"キャンセル"}</button>
+                    <button
+                        class="import-submit-btn"
+                        onclick={on_submit}
+                        disabled={spec.is_none() || selected.is_empty() || *loading}
+                    >
+                        {"取り込む"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,203 @@
+// Participant panel for editing a participant's keyword, alias, stereotype
+// and color through form fields instead of the source text
+
+use plantuml_editor_core::outline::{participants, update_participant, ParticipantStyle};
+use plantuml_editor_core::refactor::{rename_element, RenameError};
+use plantuml_editor_core::stats::PARTICIPANT_KEYWORDS;
+use plantuml_editor_core::ErrorCode;
+use yew::prelude::*;
+
+/// Ask for a new name for `old` and rename every reference to it
+///
+/// Returns None when the prompt is cancelled or the name is unchanged.
+pub fn prompt_rename(text: &str, old: &str) -> Option<Result<String, RenameError>> {
+    let new = web_sys::window()?
+        .prompt_with_message_and_default(&format!("「{}」の新しい名前", old), old)
+        .ok()
+        .flatten()?;
+    (new.trim() != old).then(|| rename_element(text, old, &new))
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ParticipantPanelProps {
+    pub plantuml_text: String,
+    /// Receives the rewritten source
+    pub on_apply: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+}
+
+#[function_component(ParticipantPanel)]
+pub fn participant_panel(props: &ParticipantPanelProps) -> Html {
+    let open = use_state(|| false);
+    let selected = use_state(|| None::<usize>);
+    let form = use_state(ParticipantStyle::default);
+
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+
+    if !*open {
+        return html! {
+            <div class="participant-panel">
+                <button class="stats-toggle" onclick={toggle}>{ "参加者 ▼" }</button>
+            </div>
+        };
+    }
+
+    let found = participants(&props.plantuml_text);
+    // The source may have changed since the participant was selected
+    let current = selected.and_then(|index| found.get(index)).cloned();
+
+    let on_select = {
+        let selected = selected.clone();
+        let form = form.clone();
+        let found = found.clone();
+        Callback::from(move |e: Event| {
+            let value = e
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .value();
+            let index = value.parse::<usize>().ok();
+            form.set(
+                index
+                    .and_then(|index| found.get(index))
+                    .map(|participant| participant.style.clone())
+                    .unwrap_or_default(),
+            );
+            selected.set(index);
+        })
+    };
+
+    let field = |update: fn(&mut ParticipantStyle, String)| {
+        let form = form.clone();
+        Callback::from(move |e: Event| {
+            let value = e
+                .target_unchecked_into::<web_sys::HtmlInputElement>()
+                .value();
+            let mut style = (*form).clone();
+            update(&mut style, value);
+            form.set(style);
+        })
+    };
+
+    let on_keyword = {
+        let form = form.clone();
+        Callback::from(move |e: Event| {
+            let mut style = (*form).clone();
+            style.keyword = e
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .value();
+            form.set(style);
+        })
+    };
+
+    let on_submit = {
+        let plantuml_text = props.plantuml_text.clone();
+        let on_apply = props.on_apply.clone();
+        let form = form.clone();
+        let current = current.clone();
+        Callback::from(move |_| {
+            if let Some(participant) = &current {
+                on_apply.emit(update_participant(&plantuml_text, participant, &form));
+            }
+        })
+    };
+
+    let on_rename = {
+        let plantuml_text = props.plantuml_text.clone();
+        let on_apply = props.on_apply.clone();
+        let on_error = props.on_error.clone();
+        let current = current.clone();
+        Callback::from(move |_| {
+            let Some(participant) = &current else { return };
+            let id = participant.id();
+            match prompt_rename(&plantuml_text, id.trim_matches('"')) {
+                Some(Ok(text)) => on_apply.emit(text),
+                Some(Err(e)) => on_error.emit(e.to_error_code()),
+                None => {}
+            }
+        })
+    };
+
+    let editor = match &current {
+        Some(_) => {
+            let style = &*form;
+            let keyword_known = PARTICIPANT_KEYWORDS
+                .iter()
+                .any(|keyword| style.keyword.eq_ignore_ascii_case(keyword));
+            html! {
+                <div class="participant-form">
+                    <label>
+                        { "種類" }
+                        <select onchange={on_keyword}>
+                            if !keyword_known {
+                                <option value={style.keyword.clone()} selected=true>{ style.keyword.clone() }</option>
+                            }
+                            { for PARTICIPANT_KEYWORDS.iter().map(|keyword| html! {
+                                <option
+                                    value={*keyword}
+                                    selected={style.keyword.eq_ignore_ascii_case(keyword)}
+                                >{ *keyword }</option>
+                            }) }
+                        </select>
+                    </label>
+                    <label>
+                        { "エイリアス" }
+                        <input
+                            type="text"
+                            value={style.alias.clone().unwrap_or_default()}
+                            onchange={field(|style, value| style.alias = Some(value))}
+                        />
+                    </label>
+                    <label>
+                        { "ステレオタイプ" }
+                        <input
+                            type="text"
+                            placeholder="Service"
+                            value={style.stereotype.clone().unwrap_or_default()}
+                            onchange={field(|style, value| style.stereotype = Some(value))}
+                        />
+                    </label>
+                    <label>
+                        { "色" }
+                        <input
+                            type="text"
+                            placeholder="#LightBlue"
+                            value={style.color.clone().unwrap_or_default()}
+                            onchange={field(|style, value| style.color = Some(value))}
+                        />
+                    </label>
+                    <button class="participant-apply" onclick={on_submit}>{ "適用" }</button>
+                    <button
+                        class="participant-rename"
+                        title="宣言・メッセージ・ノートなどの参照をまとめて変更します"
+                        onclick={on_rename}
+                    >{ "名前の変更…" }</button>
+                </div>
+            }
+        }
+        None => html! {},
+    };
+
+    html! {
+        <div class="participant-panel">
+            <button class="stats-toggle" onclick={toggle}>{ "参加者 ▲" }</button>
+            if found.is_empty() {
+                <div class="participant-empty">{ "参加者がありません" }</div>
+            } else {
+                <select class="participant-select" onchange={on_select}>
+                    <option value="" selected={current.is_none()}>{ "参加者を選択" }</option>
+                    { for found.iter().enumerate().map(|(index, participant)| html! {
+                        <option
+                            value={index.to_string()}
+                            selected={*selected == Some(index)}
+                        >
+                            { format!("{} ({})", participant.name, participant.style.keyword) }
+                        </option>
+                    }) }
+                </select>
+                { editor }
+            }
+        </div>
+    }
+}
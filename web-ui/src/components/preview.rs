@@ -1,26 +1,249 @@
 // Preview component for displaying diagram
+//
+// The zoom level and scroll position belong to the component, not to the
+// image, so they survive re-renders after an edit and page switches. While a
+// new image is converted the previous one stays in place, which keeps the
+// viewport from collapsing and losing its scroll position. How the diagram is
+// drawn is picked with tabs (see `preview_renderer`).
 
 use yew::prelude::*;
 
+use crate::object_url::PreviewImage;
+use crate::preview_renderer::{PreviewRenderer, RenderedText};
+
+/// Factor applied by one zoom step
+const ZOOM_STEP: f64 = 1.25;
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 8.0;
+
 #[derive(Properties, PartialEq)]
 pub struct PreviewProps {
-    pub image_data: Option<String>,
+    pub image_data: Option<PreviewImage>,
+    /// Error of the last conversion; `image_data` is then the last good render
+    #[prop_or_default]
+    pub error: Option<String>,
     pub loading: bool,
+    /// Page shown (0-based) of a multi-page document
+    #[prop_or_default]
+    pub page: usize,
+    #[prop_or(1)]
+    pub page_count: usize,
+    #[prop_or_default]
+    pub on_page_change: Callback<usize>,
+    #[prop_or_default]
+    pub renderer: PreviewRenderer,
+    #[prop_or_default]
+    pub on_renderer_change: Callback<PreviewRenderer>,
 }
 
 #[function_component(Preview)]
 pub fn preview(props: &PreviewProps) -> Html {
+    // None fits the image to the width of the preview
+    let zoom = use_state(|| None::<f64>);
+    let natural_width = use_state(|| 0u32);
+    // Last scroll position the user chose, restored when a new image loads
+    let scroll = use_mut_ref(|| (0, 0));
+    let restore_scroll = use_mut_ref(|| false);
+    let viewport = use_node_ref();
+    let image = use_node_ref();
+
+    {
+        let viewport = viewport.clone();
+        let scroll = scroll.clone();
+        let restore_scroll = restore_scroll.clone();
+        // After every render, so the new width is applied before scrolling
+        use_effect(move || {
+            if std::mem::take(&mut *restore_scroll.borrow_mut()) {
+                if let Some(viewport) = viewport.cast::<web_sys::Element>() {
+                    let (left, top) = *scroll.borrow();
+                    viewport.set_scroll_left(left);
+                    viewport.set_scroll_top(top);
+                }
+            }
+        });
+    }
+
+    let on_scroll = {
+        let viewport = viewport.clone();
+        let scroll = scroll.clone();
+        let loading = props.loading;
+        Callback::from(move |_: Event| {
+            // Layout changes while loading are not the user's scrolling
+            if loading {
+                return;
+            }
+            if let Some(viewport) = viewport.cast::<web_sys::Element>() {
+                *scroll.borrow_mut() = (viewport.scroll_left(), viewport.scroll_top());
+            }
+        })
+    };
+
+    let on_load = {
+        let natural_width = natural_width.clone();
+        let restore_scroll = restore_scroll.clone();
+        Callback::from(move |e: Event| {
+            let image: web_sys::HtmlImageElement = e.target_unchecked_into();
+            *restore_scroll.borrow_mut() = true;
+            // Re-renders even when the width is unchanged, running the restore
+            natural_width.set(image.natural_width());
+        })
+    };
+
+    // Scale shown now, also when fitted to the width
+    let current_scale = {
+        let zoom = zoom.clone();
+        let image = image.clone();
+        let natural_width = *natural_width;
+        move || {
+            zoom.unwrap_or_else(|| match image.cast::<web_sys::HtmlElement>() {
+                Some(image) if natural_width > 0 => {
+                    image.client_width() as f64 / natural_width as f64
+                }
+                _ => 1.0,
+            })
+        }
+    };
+    let zoom_by = |factor: f64| {
+        let zoom = zoom.clone();
+        let current_scale = current_scale.clone();
+        Callback::from(move |_: MouseEvent| {
+            zoom.set(Some((current_scale() * factor).clamp(MIN_ZOOM, MAX_ZOOM)));
+        })
+    };
+    let on_fit = {
+        let zoom = zoom.clone();
+        Callback::from(move |_: MouseEvent| zoom.set(None))
+    };
+    let on_actual_size = {
+        let zoom = zoom.clone();
+        Callback::from(move |_: MouseEvent| zoom.set(Some(1.0)))
+    };
+    // Ctrl+wheel (and pinch on touchpads) zooms instead of scrolling the page
+    let on_wheel = {
+        let zoom = zoom.clone();
+        let current_scale = current_scale.clone();
+        Callback::from(move |e: WheelEvent| {
+            if !e.ctrl_key() {
+                return;
+            }
+            e.prevent_default();
+            let factor = if e.delta_y() < 0.0 {
+                ZOOM_STEP
+            } else {
+                1.0 / ZOOM_STEP
+            };
+            zoom.set(Some((current_scale() * factor).clamp(MIN_ZOOM, MAX_ZOOM)));
+        })
+    };
+
+    let go_to = |page: usize| {
+        let on_page_change = props.on_page_change.clone();
+        Callback::from(move |_| on_page_change.emit(page))
+    };
+    let last_page = props.page_count.saturating_sub(1);
+
+    let image_style = match *zoom {
+        Some(zoom) if *natural_width > 0 => format!(
+            "width: {:.0}px; max-width: none;",
+            *natural_width as f64 * zoom
+        ),
+        _ => String::new(),
+    };
+    let stale = props.error.is_some();
+    let diagram = |data: &PreviewImage| match props.renderer {
+        PreviewRenderer::Image => html! {
+            <img
+                ref={image.clone()}
+                class={classes!("diagram-image", stale.then_some("stale"))}
+                style={image_style.clone()}
+                src={data.as_str().to_string()}
+                alt="PlantUML Diagram"
+                onload={on_load.clone()}
+            />
+        },
+        renderer => html! {
+            <RenderedText
+                data={data.clone()}
+                {renderer}
+                class={classes!(stale.then_some("stale"))}
+            />
+        },
+    };
+    let select_renderer = |renderer: PreviewRenderer| {
+        let on_renderer_change = props.on_renderer_change.clone();
+        Callback::from(move |_: MouseEvent| on_renderer_change.emit(renderer))
+    };
+
     html! {
         <div class="diagram-display">
-            {
-                if props.loading {
-                    html! { <div class="loading">{"変換中..."}</div> }
-                } else if let Some(data) = &props.image_data {
-                    html! { <img class="diagram-image" src={data.clone()} alt="PlantUML Diagram" /> }
-                } else {
-                    html! { <div class="placeholder">{"ここに生成された図が表示されます"}</div> }
-                }
+            <div class="renderer-tabs">
+                { for PreviewRenderer::ALL.into_iter().map(|renderer| html! {
+                    <button
+                        class={classes!("renderer-tab", (renderer == props.renderer).then_some("active"))}
+                        onclick={select_renderer(renderer)}
+                    >
+                        { renderer.label() }
+                    </button>
+                }) }
+            </div>
+            if props.page_count > 1 {
+                <div class="page-nav">
+                    <button
+                        class="page-button"
+                        disabled={props.page == 0}
+                        onclick={go_to(props.page.saturating_sub(1))}
+                        title="前のページ"
+                    >
+                        {"◀"}
+                    </button>
+                    <span class="page-indicator">
+                        {format!("{} / {}", props.page + 1, props.page_count)}
+                    </span>
+                    <button
+                        class="page-button"
+                        disabled={props.page >= last_page}
+                        onclick={go_to((props.page + 1).min(last_page))}
+                        title="次のページ"
+                    >
+                        {"▶"}
+                    </button>
+                </div>
             }
+            if props.image_data.is_some() && props.renderer.zoomable() {
+                <div class="zoom-controls">
+                    <button class="page-button" onclick={zoom_by(1.0 / ZOOM_STEP)} title="縮小 (Ctrl+ホイール)">{"−"}</button>
+                    <span class="zoom-level">
+                        { zoom.map_or("全体".to_string(), |zoom| format!("{:.0}%", zoom * 100.0)) }
+                    </span>
+                    <button class="page-button" onclick={zoom_by(ZOOM_STEP)} title="拡大 (Ctrl+ホイール)">{"+"}</button>
+                    <button class="page-button" onclick={on_fit} title="幅に合わせる">{"全体"}</button>
+                    <button class="page-button" onclick={on_actual_size} title="実寸">{"100%"}</button>
+                </div>
+            }
+            <div class="preview-viewport" ref={viewport} onscroll={on_scroll} onwheel={on_wheel}>
+                {
+                    match (&props.image_data, &props.error) {
+                        (Some(data), Some(error)) => html! {
+                            <div class="stale-preview">
+                                { diagram(data) }
+                                <div class="preview-error-badge" title={error.clone()}>
+                                    { format!("⚠ {}", error) }
+                                </div>
+                            </div>
+                        },
+                        (Some(data), None) => html! {
+                            <div class="stale-preview">
+                                { diagram(data) }
+                                if props.loading {
+                                    <div class="preview-loading-badge">{"変換中..."}</div>
+                                }
+                            </div>
+                        },
+                        (None, _) if props.loading => html! { <div class="loading">{"変換中..."}</div> },
+                        (None, _) => html! { <div class="placeholder">{"ここに生成された図が表示されます"}</div> },
+                    }
+                }
+            </div>
         </div>
     }
 }
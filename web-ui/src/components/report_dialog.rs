@@ -0,0 +1,161 @@
+// "問題を報告" dialog: builds a diagnostic bundle for a bug report
+//
+// The bundle (see `diagnostic_bundle`) is downloaded as JSON, or posted to the
+// feedback endpoint when the page configures one (see `host`).
+
+use plantuml_editor_core::ErrorCode;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::diagnostic_bundle::{DiagnosticBundle, ResultEntry};
+use crate::{download, host};
+
+/// Outcome of posting the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SendState {
+    Idle,
+    Sending,
+    Sent,
+    Failed,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ReportDialogProps {
+    /// Source in the editor, included only when the user opts in
+    pub source: String,
+    /// Messages shown most recently
+    pub history: Vec<ResultEntry>,
+    pub on_error: Callback<ErrorCode>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(ReportDialog)]
+pub fn report_dialog(props: &ReportDialogProps) -> Html {
+    let description = use_state(String::new);
+    let include_source = use_state(|| false);
+    let send_state = use_state(|| SendState::Idle);
+    let feedback_url = host::feedback_url();
+
+    let bundle = {
+        let description = description.clone();
+        let include_source = include_source.clone();
+        let source = props.source.clone();
+        let history = props.history.clone();
+        move || DiagnosticBundle {
+            app_version: env!("CARGO_PKG_VERSION"),
+            host: if host::is_webview() { "vscode" } else { "browser" },
+            user_agent: web_sys::window().and_then(|window| window.navigator().user_agent().ok()),
+            created_at: chrono::Local::now().to_rfc3339(),
+            description: (*description).clone(),
+            results: history.clone(),
+            requests: host::recent_requests(),
+            source: include_source.then(|| source.clone()),
+        }
+    };
+
+    let on_description = {
+        let description = description.clone();
+        Callback::from(move |e: InputEvent| {
+            let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+            description.set(textarea.value());
+        })
+    };
+
+    let on_include_source = {
+        let include_source = include_source.clone();
+        Callback::from(move |_| include_source.set(!*include_source))
+    };
+
+    let on_download = {
+        let bundle = bundle.clone();
+        let on_error = props.on_error.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| {
+            let bundle = bundle();
+            let json = bundle.to_json();
+            match download::download_bytes(json.as_bytes(), "application/json", &bundle.file_name()) {
+                Ok(()) => on_close.emit(()),
+                Err(_) => on_error.emit(ErrorCode::ExportError {
+                    format: "JSON".to_string(),
+                }),
+            }
+        })
+    };
+
+    let on_send = feedback_url.clone().map(|url| {
+        let send_state = send_state.clone();
+        Callback::from(move |_| {
+            let json = bundle().to_json();
+            let url = url.clone();
+            let send_state = send_state.clone();
+            send_state.set(SendState::Sending);
+            spawn_local(async move {
+                let sent = host::post_json(&url, &json).await;
+                send_state.set(if sent { SendState::Sent } else { SendState::Failed });
+            });
+        })
+    });
+
+    let on_cancel = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    let request_ids = host::recent_requests()
+        .iter()
+        .filter(|request| request.request_id.is_some())
+        .count();
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog report-dialog">
+                <div class="import-dialog-header">{"問題を報告"}</div>
+                <div class="report-body">
+                    <textarea
+                        class="import-textarea"
+                        placeholder="起きたこと、期待していた動作、再現手順など"
+                        value={(*description).clone()}
+                        oninput={on_description}
+                    />
+                    <label class="report-option">
+                        <input type="checkbox" checked={*include_source} onchange={on_include_source} />
+                        {"図のソースを含める"}
+                    </label>
+                    <ul class="report-contents">
+                        <li>{ format!("アプリのバージョン ({})", env!("CARGO_PKG_VERSION")) }</li>
+                        <li>{ format!("直近のメッセージ {}件", props.history.len()) }</li>
+                        <li>{ format!("直近のリクエストID {}件", request_ids) }</li>
+                        if *include_source {
+                            <li>{ format!("図のソース ({}文字)", props.source.chars().count()) }</li>
+                        }
+                    </ul>
+                    {
+                        match *send_state {
+                            SendState::Idle => html! {},
+                            SendState::Sending => html! { <div class="loading">{"送信中..."}</div> },
+                            SendState::Sent => html! { <div class="report-status">{"送信しました。ご協力ありがとうございます。"}</div> },
+                            SendState::Failed => html! {
+                                <div class="report-status report-failed">
+                                    {"送信に失敗しました。「ダウンロード」で保存したファイルを添付して報告してください。"}
+                                </div>
+                            },
+                        }
+                    }
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{"閉じる"}</button>
+                    <button class="import-submit-btn" onclick={on_download}>{"ダウンロード"}</button>
+                    if let Some(on_send) = on_send {
+                        <button
+                            class="import-submit-btn"
+                            onclick={on_send}
+                            disabled={matches!(*send_state, SendState::Sending | SendState::Sent)}
+                        >
+                            {"送信"}
+                        </button>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
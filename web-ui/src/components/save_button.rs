@@ -1,6 +1,7 @@
 // Save button component
 
-use plantuml_editor_core::StorageError;
+use plantuml_editor_core::text::scalar_count;
+use plantuml_editor_core::{StorageError, MAX_CONTENT_CHARS};
 use yew::prelude::*;
 
 /// Validation result for save operation
@@ -35,9 +36,9 @@ pub fn save_button(props: &SaveButtonProps) -> Html {
             }
 
             // Rule 2: Max 24,000 characters
-            const MAX_CHARS: usize = 24_000;
-            if plantuml_text.len() > MAX_CHARS {
-                on_error.emit(SaveValidationError::ContentTooLarge(plantuml_text.len()));
+            let chars = scalar_count(&plantuml_text);
+            if chars > MAX_CONTENT_CHARS {
+                on_error.emit(SaveValidationError::ContentTooLarge(chars));
                 return;
             }
 
@@ -1,12 +1,113 @@
 // Slot list component for loading saved documents
 
-use plantuml_editor_storageservice::{LocalStorageBackend, StorageService};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use gloo_timers::callback::Timeout;
+use plantuml_editor_core::{ImageFormat, StorageError, StorageSlot};
+use plantuml_editor_storageservice::{LocalStorageBackend, SlotInfo, StorageService};
+use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+use crate::host;
+use crate::PREVIEW_TIMEOUT;
+
+/// Lines of saved content shown when hovering a slot
+const PREVIEW_LINES: usize = 15;
+
+/// Hover time before the preview opens, so moving the pointer across the list
+/// does not read and render every slot
+const PREVIEW_DELAY_MS: u32 = 400;
+
+thread_local! {
+    /// Rendered thumbnails (saved content -> SVG data URL), kept for the whole
+    /// session
+    static THUMBNAILS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+fn cached_thumbnail(content: &str) -> Option<String> {
+    THUMBNAILS.with(|cache| cache.borrow().get(content).cloned())
+}
+
 #[derive(Properties, PartialEq)]
 pub struct SlotListProps {
     pub on_load: Callback<usize>,
     pub on_delete: Callback<usize>,
+    pub on_duplicate: Callback<usize>,
+    /// Drag-and-drop reorder: (from, to)
+    pub on_move: Callback<(usize, usize)>,
+    /// Star toggle: (slot, pinned)
+    pub on_pin: Callback<(usize, bool)>,
+    /// Bulk actions on the checked slots, emitted after confirmation
+    pub on_bulk_delete: Callback<Vec<usize>>,
+    pub on_bulk_export: Callback<Vec<usize>>,
+    /// Saved content of a slot for the hover preview, read through the
+    /// injected storage service (None when empty or unreadable)
+    pub load_preview: Callback<usize, Option<String>>,
+}
+
+/// Hover preview of a slot, placed next to it
+#[derive(Clone, PartialEq)]
+struct SlotPreview {
+    content: String,
+    left: f64,
+    top: f64,
+}
+
+/// Bulk action waiting for confirmation
+#[derive(Clone, Copy, PartialEq)]
+enum BulkAction {
+    Delete,
+    Export,
+}
+
+impl BulkAction {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Delete => "削除",
+            Self::Export => "エクスポート (ZIP)",
+        }
+    }
+}
+
+/// dataTransfer format carrying the dragged slot number
+const DRAG_FORMAT: &str = "text/plain";
+
+/// First `max` lines of `content`, and whether lines were left out
+fn preview_lines(content: &str, max: usize) -> (String, bool) {
+    let lines: Vec<&str> = content.lines().collect();
+    (lines.iter().take(max).copied().collect::<Vec<_>>().join("\n"), lines.len() > max)
+}
+
+/// Slot number from dragged data, ignoring drags that did not start in the list
+fn dragged_slot(data: &str) -> Option<usize> {
+    data.strip_prefix("plantuml-slot:")?.parse().ok()
+}
+
+/// Check or uncheck a slot, keeping the selection sorted
+fn toggle_selection(selected: &[usize], slot_num: usize) -> Vec<usize> {
+    let mut selected = selected.to_vec();
+    match selected.binary_search(&slot_num) {
+        Ok(index) => {
+            selected.remove(index);
+        }
+        Err(index) => selected.insert(index, slot_num),
+    }
+    selected
+}
+
+/// Slot numbers in display order: pinned slots first, then the rest by number
+fn display_order(slots: &[SlotInfo]) -> Vec<usize> {
+    let is_pinned = |slot_num: usize| {
+        slots
+            .iter()
+            .any(|slot| slot.slot_number as usize == slot_num && slot.pinned)
+    };
+    let all = 1..=StorageSlot::MAX_SLOTS as usize;
+    all.clone()
+        .filter(|&slot_num| is_pinned(slot_num))
+        .chain(all.filter(|&slot_num| !is_pinned(slot_num)))
+        .collect()
 }
 
 #[function_component(SlotList)]
@@ -22,12 +123,156 @@ pub fn slot_list(props: &SlotListProps) -> Html {
         })
     };
 
+    // Slot currently under the dragged item, for highlighting
+    let drag_over = use_state(|| None::<usize>);
+
+    // Checked slots (sorted) and the bulk action awaiting confirmation
+    let selected = use_state(Vec::<usize>::new);
+    let pending = use_state(|| None::<BulkAction>);
+
+    // Corrupted slot whose stored text is shown: (slot, raw text)
+    let raw_view = use_state(|| None::<(usize, String)>);
+
+    // Hovered slot's preview and the timer that opens it
+    let preview = use_state(|| None::<SlotPreview>);
+    let preview_timer = use_state(|| None::<Timeout>);
+    // Bumped whenever a thumbnail arrives, to re-render from the cache
+    let thumbnail_loaded = use_state(|| 0usize);
+
+    // Read on every render so pins and moves show up immediately
+    let slot_infos = service.list_slots();
+
+    // Drop checks on slots that were emptied elsewhere
+    let selected_slots: Vec<usize> = selected
+        .iter()
+        .copied()
+        .filter(|&slot_num| {
+            slot_infos
+                .iter()
+                .any(|slot| slot.slot_number as usize == slot_num)
+        })
+        .collect();
+
     let render_slot = |slot_num: usize| {
         let service = StorageService::new(LocalStorageBackend::new());
-        let slot_data = service.load_from_slot(slot_num).ok().flatten();
+        let loaded = service.load_from_slot(slot_num);
+        let corrupted = match &loaded {
+            Err(StorageError::SlotCorrupted { reason, .. }) => Some(reason.clone()),
+            _ => None,
+        };
+        let slot_data = loaded.ok().flatten();
+        let pinned = slot_infos
+            .iter()
+            .any(|slot| slot.slot_number as usize == slot_num && slot.pinned);
+
+        let on_drag_start = Callback::from(move |e: DragEvent| {
+            if let Some(transfer) = e.data_transfer() {
+                let _ = transfer.set_data(DRAG_FORMAT, &format!("plantuml-slot:{}", slot_num));
+                transfer.set_effect_allowed("move");
+            }
+        });
+
+        let on_drag_over = {
+            let drag_over = drag_over.clone();
+            Callback::from(move |e: DragEvent| {
+                // Allow dropping here
+                e.prevent_default();
+                if *drag_over != Some(slot_num) {
+                    drag_over.set(Some(slot_num));
+                }
+            })
+        };
+
+        let on_drag_leave = {
+            let drag_over = drag_over.clone();
+            Callback::from(move |_: DragEvent| drag_over.set(None))
+        };
+
+        let on_drop = {
+            let drag_over = drag_over.clone();
+            let on_move = props.on_move.clone();
+            let refresh = refresh_slots.clone();
+            Callback::from(move |e: DragEvent| {
+                e.prevent_default();
+                drag_over.set(None);
+                let from = e
+                    .data_transfer()
+                    .and_then(|transfer| transfer.get_data(DRAG_FORMAT).ok())
+                    .and_then(|data| dragged_slot(&data));
+                if let Some(from) = from.filter(|&from| from != slot_num) {
+                    on_move.emit((from, slot_num));
+                    refresh.emit(());
+                }
+            })
+        };
+
+        let drop_class = (*drag_over == Some(slot_num)).then_some("drag-over");
+        let checked = selected_slots.contains(&slot_num);
+
+        let on_check = {
+            let selected = selected.clone();
+            let selected_slots = selected_slots.clone();
+            Callback::from(move |_: Event| {
+                selected.set(toggle_selection(&selected_slots, slot_num));
+            })
+        };
+
+        let on_mouse_enter = {
+            let preview = preview.clone();
+            let preview_timer = preview_timer.clone();
+            let thumbnail_loaded = thumbnail_loaded.clone();
+            let load_preview = props.load_preview.clone();
+            Callback::from(move |e: MouseEvent| {
+                let rect = e.target_unchecked_into::<web_sys::Element>().get_bounding_client_rect();
+                let preview = preview.clone();
+                let thumbnail_loaded = thumbnail_loaded.clone();
+                let load_preview = load_preview.clone();
+                let timer = Timeout::new(PREVIEW_DELAY_MS, move || {
+                    // Read only now, so slots that are never hovered are never loaded
+                    let Some(content) = load_preview.emit(slot_num) else {
+                        return;
+                    };
+                    preview.set(Some(SlotPreview {
+                        content: content.clone(),
+                        left: rect.right() + 8.0,
+                        top: rect.top(),
+                    }));
+                    if cached_thumbnail(&content).is_some() {
+                        return;
+                    }
+                    // Without a server the preview shows the text only
+                    spawn_local(async move {
+                        let client = host::api_client().with_timeout(PREVIEW_TIMEOUT);
+                        let Ok((bytes, _)) = client.convert(content.clone(), ImageFormat::Svg).await else {
+                            return;
+                        };
+                        if let Ok(svg) = String::from_utf8(bytes) {
+                            let data_url = format!(
+                                "data:image/svg+xml;charset=utf-8,{}",
+                                urlencoding::encode(&svg)
+                            );
+                            THUMBNAILS.with(|cache| cache.borrow_mut().insert(content, data_url));
+                            thumbnail_loaded.set(*thumbnail_loaded + 1);
+                        }
+                    });
+                });
+                preview_timer.set(Some(timer));
+            })
+        };
+
+        let on_mouse_leave = {
+            let preview = preview.clone();
+            let preview_timer = preview_timer.clone();
+            Callback::from(move |_: MouseEvent| {
+                // Dropping the timer cancels a preview that has not opened yet
+                preview_timer.set(None);
+                preview.set(None);
+            })
+        };
 
         let on_load = props.on_load.clone();
         let on_delete = props.on_delete.clone();
+        let on_duplicate = props.on_duplicate.clone();
         let refresh = refresh_slots.clone();
 
         let on_load_click = {
@@ -36,6 +281,32 @@ pub fn slot_list(props: &SlotListProps) -> Html {
             })
         };
 
+        let on_duplicate_click = {
+            let refresh = refresh.clone();
+            Callback::from(move |_| {
+                on_duplicate.emit(slot_num);
+                refresh.emit(());
+            })
+        };
+
+        let on_pin_click = {
+            let on_pin = props.on_pin.clone();
+            let refresh = refresh.clone();
+            Callback::from(move |_| {
+                on_pin.emit((slot_num, !pinned));
+                refresh.emit(());
+            })
+        };
+
+        let on_raw_click = {
+            let raw_view = raw_view.clone();
+            Callback::from(move |_| {
+                let service = StorageService::new(LocalStorageBackend::new());
+                let raw = service.load_raw_slot(slot_num).ok().flatten().unwrap_or_default();
+                raw_view.set(Some((slot_num, raw)));
+            })
+        };
+
         let on_delete_click = {
             Callback::from(move |_| {
                 on_delete.emit(slot_num);
@@ -52,12 +323,58 @@ pub fn slot_list(props: &SlotListProps) -> Html {
             };
 
             html! {
-                <div class="save-slot" key={slot_num}>
+                <div
+                    class={classes!(
+                        "save-slot",
+                        pinned.then_some("pinned"),
+                        checked.then_some("selected"),
+                        drop_class
+                    )}
+                    key={slot_num}
+                    draggable="true"
+                    onmouseenter={on_mouse_enter}
+                    onmouseleave={on_mouse_leave}
+                    ondragstart={on_drag_start}
+                    ondragover={on_drag_over}
+                    ondragleave={on_drag_leave}
+                    ondrop={on_drop}
+                >
+                    <input
+                        type="checkbox"
+                        class="slot-checkbox"
+                        checked={checked}
+                        onchange={on_check}
+                        title="選択"
+                    />
+                    <button
+                        class={classes!("slot-button", "pin-button", pinned.then_some("active"))}
+                        onclick={on_pin_click}
+                        title={if pinned { "ピン留めを解除" } else { "ピン留め" }}
+                    >
+                        {if pinned { "★" } else { "☆" }}
+                    </button>
                     <span class="slot-text">{format!("スロット{}: {}", slot_num, title)}</span>
                     <div class="slot-actions">
                         <button class="slot-button reload-button" onclick={on_load_click} title="再読み込み">
                             {"↻"}
                         </button>
+                        <button class="slot-button duplicate-button" onclick={on_duplicate_click} title="複製">
+                            {"⧉"}
+                        </button>
+                        <button class="slot-button delete-button" onclick={on_delete_click} title="削除">
+                            {"×"}
+                        </button>
+                    </div>
+                </div>
+            }
+        } else if let Some(reason) = corrupted {
+            html! {
+                <div class={classes!("save-slot", "corrupted")} key={slot_num} title={reason}>
+                    <span class="slot-text">{format!("スロット{}: データが壊れています", slot_num)}</span>
+                    <div class="slot-actions">
+                        <button class="slot-button raw-button" onclick={on_raw_click} title="保存されている内容を表示">
+                            {"⚠"}
+                        </button>
                         <button class="slot-button delete-button" onclick={on_delete_click} title="削除">
                             {"×"}
                         </button>
@@ -66,16 +383,201 @@ pub fn slot_list(props: &SlotListProps) -> Html {
             }
         } else {
             html! {
-                <div class="save-slot empty" key={slot_num}>
+                <div
+                    class={classes!("save-slot", "empty", drop_class)}
+                    key={slot_num}
+                    ondragover={on_drag_over}
+                    ondragleave={on_drag_leave}
+                    ondrop={on_drop}
+                >
                     <span class="slot-text">{format!("スロット{}: (空)", slot_num)}</span>
                 </div>
             }
         }
     };
 
+    let request = |action: BulkAction| {
+        let pending = pending.clone();
+        Callback::from(move |_: MouseEvent| pending.set(Some(action)))
+    };
+
+    let clear_selection = {
+        let selected = selected.clone();
+        Callback::from(move |_: MouseEvent| selected.set(Vec::new()))
+    };
+
+    let cancel = {
+        let pending = pending.clone();
+        Callback::from(move |_: MouseEvent| pending.set(None))
+    };
+
+    let confirm = {
+        let pending = pending.clone();
+        let selected = selected.clone();
+        let selected_slots = selected_slots.clone();
+        let on_bulk_delete = props.on_bulk_delete.clone();
+        let on_bulk_export = props.on_bulk_export.clone();
+        let refresh = refresh_slots.clone();
+        Callback::from(move |_: MouseEvent| {
+            match *pending {
+                Some(BulkAction::Delete) => {
+                    on_bulk_delete.emit(selected_slots.clone());
+                    refresh.emit(());
+                }
+                Some(BulkAction::Export) => on_bulk_export.emit(selected_slots.clone()),
+                None => {}
+            }
+            pending.set(None);
+            selected.set(Vec::new());
+        })
+    };
+
+    let close_raw = {
+        let raw_view = raw_view.clone();
+        Callback::from(move |_: MouseEvent| raw_view.set(None))
+    };
+
+    let delete_raw = {
+        let raw_view = raw_view.clone();
+        let on_delete = props.on_delete.clone();
+        let refresh = refresh_slots.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some((slot_num, _)) = &*raw_view {
+                on_delete.emit(*slot_num);
+                refresh.emit(());
+            }
+            raw_view.set(None);
+        })
+    };
+
+    let slot_title = |slot_num: usize| {
+        slot_infos
+            .iter()
+            .find(|slot| slot.slot_number as usize == slot_num)
+            .map(|slot| slot.title.clone())
+            .unwrap_or_default()
+    };
+
     html! {
         <div class="slot-list">
-            { for (1..=10).map(render_slot) }
+            if !selected_slots.is_empty() {
+                <div class="bulk-toolbar">
+                    <span class="bulk-count">{ format!("{}件選択中", selected_slots.len()) }</span>
+                    <button class="bulk-button" onclick={request(BulkAction::Export)}>
+                        { "エクスポート" }
+                    </button>
+                    <button class="bulk-button danger" onclick={request(BulkAction::Delete)}>
+                        { "削除" }
+                    </button>
+                    <button class="bulk-button" onclick={clear_selection}>{ "選択解除" }</button>
+                </div>
+            }
+            { for display_order(&slot_infos).into_iter().map(render_slot) }
+            if let Some(shown) = &*preview {
+                <div
+                    class="slot-preview-popover"
+                    style={format!("left: {}px; top: {}px;", shown.left, shown.top)}
+                >
+                    if let Some(thumbnail) = cached_thumbnail(&shown.content) {
+                        <img class="slot-preview-thumbnail" src={thumbnail} alt="サムネイル" />
+                    }
+                    <pre class="slot-preview-text">
+                        {{
+                            let (lines, truncated) = preview_lines(&shown.content, PREVIEW_LINES);
+                            if truncated { format!("{}\n…", lines) } else { lines }
+                        }}
+                    </pre>
+                </div>
+            }
+            if let Some(action) = *pending {
+                <div class="import-dialog-overlay">
+                    <div class="import-dialog">
+                        <div class="import-dialog-header">
+                            { format!("{}件のスロットを{}", selected_slots.len(), action.label()) }
+                        </div>
+                        <ul class="bulk-confirm-items">
+                            { for selected_slots.iter().map(|&slot_num| html! {
+                                <li key={slot_num}>
+                                    { format!("スロット{}: {}", slot_num, slot_title(slot_num)) }
+                                </li>
+                            }) }
+                        </ul>
+                        if action == BulkAction::Delete {
+                            <div class="bulk-confirm-note">
+                                { "削除したスロットはゴミ箱から元に戻せます" }
+                            </div>
+                        }
+                        <div class="import-dialog-actions">
+                            <button class="import-cancel-btn" onclick={cancel}>{ "キャンセル" }</button>
+                            <button class="import-submit-btn" onclick={confirm}>
+                                { action.label() }
+                            </button>
+                        </div>
+                    </div>
+                </div>
+            }
+            if let Some((slot_num, raw)) = (*raw_view).clone() {
+                <div class="import-dialog-overlay">
+                    <div class="import-dialog">
+                        <div class="import-dialog-header">
+                            { format!("スロット{}の保存データ", slot_num) }
+                        </div>
+                        <div class="bulk-confirm-note">
+                            { "このデータは読み込めません。必要な部分をコピーしてから削除してください（削除したデータはゴミ箱に入りません）" }
+                        </div>
+                        <textarea class="raw-slot-text" readonly=true value={raw} />
+                        <div class="import-dialog-actions">
+                            <button class="import-cancel-btn" onclick={close_raw}>{ "閉じる" }</button>
+                            <button class="import-submit-btn" onclick={delete_raw}>{ "削除" }</button>
+                        </div>
+                    </div>
+                </div>
+            }
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dragged_slot() {
+        assert_eq!(dragged_slot("plantuml-slot:3"), Some(3));
+        assert_eq!(dragged_slot("plantuml-slot:x"), None);
+        assert_eq!(dragged_slot("3"), None);
+    }
+
+    #[test]
+    fn test_preview_lines() {
+        let content = (1..=20).map(|n| format!("line{}", n)).collect::<Vec<_>>().join("\n");
+        let (lines, truncated) = preview_lines(&content, 15);
+        assert_eq!(lines.lines().count(), 15);
+        assert!(lines.ends_with("line15"));
+        assert!(truncated);
+        assert_eq!(preview_lines("@startuml\nA -> B\n@enduml", 15), ("@startuml\nA -> B\n@enduml".to_string(), false));
+    }
+
+    #[test]
+    fn test_toggle_selection_keeps_slots_sorted() {
+        let selected = toggle_selection(&[], 5);
+        let selected = toggle_selection(&selected, 2);
+        let selected = toggle_selection(&selected, 9);
+        assert_eq!(selected, vec![2, 5, 9]);
+        assert_eq!(toggle_selection(&selected, 5), vec![2, 9]);
+    }
+
+    #[test]
+    fn test_display_order_lists_pinned_slots_first() {
+        let slot = |slot_number: u8, pinned: bool| SlotInfo {
+            slot_number,
+            title: "無題".to_string(),
+            saved_at: 0,
+            preview: String::new(),
+            pinned,
+        };
+        let slots = vec![slot(2, false), slot(7, true), slot(4, true)];
+        assert_eq!(display_order(&slots), vec![4, 7, 1, 2, 3, 5, 6, 8, 9, 10]);
+        assert_eq!(display_order(&[]), (1..=10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,100 @@
+// Stats panel summarizing the diagram size, for spotting oversized diagrams
+
+use plantuml_editor_core::stats::analyze;
+use yew::prelude::*;
+
+/// Measurements of the last successful conversion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    /// Round trip to the API server in milliseconds
+    pub render_ms: f64,
+    /// Size of the returned image in bytes
+    pub image_bytes: usize,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct StatsPanelProps {
+    pub plantuml_text: String,
+    pub render: Option<RenderStats>,
+}
+
+/// Human-readable byte size (e.g., "512 B", "12.3 KB", "1.5 MB")
+fn format_bytes(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size < KB {
+        format!("{} B", bytes)
+    } else if size < KB * KB {
+        format!("{:.1} KB", size / KB)
+    } else {
+        format!("{:.1} MB", size / (KB * KB))
+    }
+}
+
+#[function_component(StatsPanel)]
+pub fn stats_panel(props: &StatsPanelProps) -> Html {
+    let open = use_state(|| false);
+
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+
+    let row = |label: &str, value: String| {
+        html! {
+            <div class="stats-row">
+                <span class="stats-label">{ label.to_string() }</span>
+                <span class="stats-value">{ value }</span>
+            </div>
+        }
+    };
+
+    let content = if *open {
+        // Same numbers as POST /api/v1/analyze, computed locally
+        let stats = analyze(&props.plantuml_text);
+        let (render_time, image_size) = match props.render {
+            Some(render) => (
+                format!("{:.0} ms", render.render_ms),
+                format_bytes(render.image_bytes),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+
+        html! {
+            <div class="stats-list">
+                { row("図の種類", stats.diagram_type.clone()) }
+                { row("ページ", stats.pages.to_string()) }
+                { row("参加者", stats.participants.to_string()) }
+                { row("メッセージ", stats.messages.to_string()) }
+                { row("クラス", stats.classes.to_string()) }
+                { row("ノート", stats.notes.to_string()) }
+                { row("複雑さ", format!("{} ({})", stats.complexity, stats.complexity_level.label())) }
+                { row("変換時間", render_time) }
+                { row("画像サイズ (SVG)", image_size) }
+            </div>
+        }
+    } else {
+        html! {}
+    };
+
+    html! {
+        <div class="stats-panel">
+            <button class="stats-toggle" onclick={toggle}>
+                { if *open { "統計 ▲" } else { "統計 ▼" } }
+            </button>
+            { content }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(12_595), "12.3 KB");
+        assert_eq!(format_bytes(1_572_864), "1.5 MB");
+    }
+}
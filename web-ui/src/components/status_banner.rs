@@ -0,0 +1,69 @@
+// Banner for the operator's announcement and maintenance mode
+
+use gloo_timers::callback::Interval;
+use plantuml_editor_api_client::ServiceStatus;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::host;
+
+/// Interval between GET /api/v1/status requests (5 minutes)
+const STATUS_POLL_MS: u32 = 5 * 60 * 1000;
+
+/// Shown when maintenance mode is on without an announcement
+const DEFAULT_MAINTENANCE_NOTICE: &str =
+    "メンテナンス中のため、図の変換を停止しています。しばらくしてから再度お試しください。";
+
+/// Text of the banner for `status`, if any
+fn notice(status: &ServiceStatus) -> Option<String> {
+    status.announcement.clone().or_else(|| {
+        status
+            .maintenance
+            .then(|| DEFAULT_MAINTENANCE_NOTICE.to_string())
+    })
+}
+
+#[function_component(StatusBanner)]
+pub fn status_banner() -> Html {
+    let status = use_state(ServiceStatus::default);
+    // Text the user closed; a different announcement shows the banner again
+    let dismissed = use_state(|| None::<String>);
+
+    {
+        let status = status.clone();
+        use_effect_with((), move |_| {
+            let fetch = move || {
+                let status = status.clone();
+                spawn_local(async move {
+                    // Polling runs in the background, so failures are not reported
+                    if let Ok(current) = host::api_client().status().await {
+                        status.set(current);
+                    }
+                });
+            };
+            fetch();
+            let interval = Interval::new(STATUS_POLL_MS, fetch);
+            move || drop(interval)
+        });
+    }
+
+    let Some(text) = notice(&status) else {
+        return html! {};
+    };
+    if dismissed.as_deref() == Some(text.as_str()) {
+        return html! {};
+    }
+
+    let on_dismiss = {
+        let dismissed = dismissed.clone();
+        let text = text.clone();
+        Callback::from(move |_| dismissed.set(Some(text.clone())))
+    };
+
+    html! {
+        <div class={classes!("status-banner", status.maintenance.then_some("maintenance"))} role="status">
+            <span class="status-banner-text">{ text }</span>
+            <button class="status-banner-close" title="閉じる" onclick={on_dismiss}>{ "×" }</button>
+        </div>
+    }
+}
@@ -0,0 +1,176 @@
+// Theme picker with a gallery of rendered theme thumbnails and the
+// color-blind-safe palette toggle
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use plantuml_editor_core::preprocess::{
+    colorblind_safe, current_theme, set_colorblind_safe, set_theme, theme_sample, THEMES,
+};
+use plantuml_editor_core::ImageFormat;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::host;
+use crate::PREVIEW_TIMEOUT;
+
+thread_local! {
+    /// Rendered thumbnails (theme -> SVG data URL), kept for the whole session
+    static THUMBNAILS: RefCell<HashMap<&'static str, String>> = RefCell::new(HashMap::new());
+}
+
+fn cached_thumbnail(theme: &str) -> Option<String> {
+    THUMBNAILS.with(|cache| cache.borrow().get(theme).cloned())
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ThemePickerProps {
+    pub plantuml_text: String,
+    /// Source with the chosen `!theme` directive or palette option applied
+    pub on_apply: Callback<String>,
+}
+
+#[function_component(ThemePicker)]
+pub fn theme_picker(props: &ThemePickerProps) -> Html {
+    let gallery_open = use_state(|| false);
+    let theme = current_theme(&props.plantuml_text).map(str::to_string);
+
+    let apply = {
+        let plantuml_text = props.plantuml_text.clone();
+        let on_apply = props.on_apply.clone();
+        let gallery_open = gallery_open.clone();
+        Callback::from(move |theme: Option<String>| {
+            gallery_open.set(false);
+            on_apply.emit(set_theme(&plantuml_text, theme.as_deref()));
+        })
+    };
+
+    let on_select = apply.reform(|e: Event| {
+        let value = e
+            .target_unchecked_into::<web_sys::HtmlSelectElement>()
+            .value();
+        (!value.is_empty()).then_some(value)
+    });
+
+    let palette_enabled = colorblind_safe(&props.plantuml_text);
+    let on_toggle_palette = {
+        let plantuml_text = props.plantuml_text.clone();
+        let on_apply = props.on_apply.clone();
+        Callback::from(move |_: Event| {
+            on_apply.emit(set_colorblind_safe(&plantuml_text, !palette_enabled));
+        })
+    };
+
+    let open_gallery = {
+        let gallery_open = gallery_open.clone();
+        Callback::from(move |_| gallery_open.set(true))
+    };
+
+    let close_gallery = {
+        let gallery_open = gallery_open.clone();
+        Callback::from(move |_| gallery_open.set(false))
+    };
+
+    html! {
+        <div class="theme-picker">
+            <select class="theme-select" onchange={on_select}>
+                <option value="" selected={theme.is_none()}>{ "テーマなし" }</option>
+                { for THEMES.iter().map(|&name| html! {
+                    <option value={name} selected={theme.as_deref() == Some(name)}>{ name }</option>
+                }) }
+            </select>
+            <button class="theme-gallery-btn" onclick={open_gallery} title="テーマ一覧をプレビュー">
+                { "ギャラリー" }
+            </button>
+            <label class="palette-toggle" title="色覚の多様性に配慮した配色で描画します">
+                <input type="checkbox" checked={palette_enabled} onchange={on_toggle_palette} />
+                { "色覚配慮" }
+            </label>
+            if *gallery_open {
+                <ThemeGallery current={theme} on_select={apply} on_close={close_gallery} />
+            }
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+struct ThemeGalleryProps {
+    current: Option<String>,
+    on_select: Callback<Option<String>>,
+    on_close: Callback<MouseEvent>,
+}
+
+#[function_component(ThemeGallery)]
+fn theme_gallery(props: &ThemeGalleryProps) -> Html {
+    // Bumped whenever a thumbnail arrives, to re-render from the cache
+    let loaded = use_state(|| 0usize);
+
+    {
+        let loaded = loaded.clone();
+        use_effect_with((), move |_| {
+            // One at a time, so the gallery does not crowd out the live preview
+            spawn_local(async move {
+                let client = host::api_client().with_timeout(PREVIEW_TIMEOUT);
+                let mut count = 0;
+                for &theme in THEMES {
+                    if cached_thumbnail(theme).is_some() {
+                        continue;
+                    }
+                    let Ok((bytes, _)) = client.convert(theme_sample(theme), ImageFormat::Svg).await
+                    else {
+                        continue;
+                    };
+                    if let Ok(svg) = String::from_utf8(bytes) {
+                        let data_url = format!(
+                            "data:image/svg+xml;charset=utf-8,{}",
+                            urlencoding::encode(&svg)
+                        );
+                        THUMBNAILS.with(|cache| cache.borrow_mut().insert(theme, data_url));
+                        count += 1;
+                        loaded.set(count);
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let render_theme = |&theme: &&'static str| {
+        let on_click = {
+            let on_select = props.on_select.clone();
+            Callback::from(move |_| on_select.emit(Some(theme.to_string())))
+        };
+        let current = props.current.as_deref() == Some(theme);
+
+        html! {
+            <button
+                class={classes!("theme-card", current.then_some("current"))}
+                key={theme}
+                onclick={on_click}
+            >
+                if let Some(data_url) = cached_thumbnail(theme) {
+                    <img class="theme-thumbnail" src={data_url} alt={theme} />
+                } else {
+                    <div class="theme-thumbnail loading">{ "読み込み中..." }</div>
+                }
+                <span class="theme-name">{ theme }</span>
+            </button>
+        }
+    };
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog theme-gallery">
+                <div class="import-dialog-header">{ "テーマギャラリー" }</div>
+                <div class="theme-grid">
+                    { for THEMES.iter().map(render_theme) }
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={props.on_close.clone()}>
+                        { "閉じる" }
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,274 @@
+// Guided tour for first-time users
+//
+// Walks through editing, previewing, saving to a slot and exporting. Each step
+// points at an element of the page by CSS selector; the overlay darkens the
+// rest of the page around it and shows the explanation next to it. Steps whose
+// element is not on the page (e.g. saving in a VS Code webview) are left out.
+//
+// The tour starts by itself until it has been finished or skipped once, which
+// is remembered in LocalStorage, and can be started again from the help menu.
+
+use wasm_bindgen::prelude::*;
+use yew::prelude::*;
+
+/// LocalStorage key set once the tour was finished or skipped
+const TOUR_SEEN_KEY: &str = "plantuml_tour_seen";
+
+/// Space between the highlighted element and its outline (px)
+const SPOTLIGHT_PADDING: f64 = 6.0;
+/// Width of the explanation card and its margin to the window edge (px)
+const CARD_WIDTH: f64 = 320.0;
+const CARD_MARGIN: f64 = 12.0;
+/// Height the card is expected to need, to decide whether it fits below
+const CARD_HEIGHT: f64 = 170.0;
+
+/// One step of the tour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TourStep {
+    /// CSS selector of the element the step is about
+    pub target: &'static str,
+    pub title: &'static str,
+    pub body: &'static str,
+}
+
+/// Steps in order
+pub const TOUR_STEPS: &[TourStep] = &[
+    TourStep {
+        target: ".editor-area",
+        title: "ソースを編集",
+        body: "ここにPlantUMLのソースを入力します。入力が止まると自動で図に変換されます。",
+    },
+    TourStep {
+        target: ".preview-area",
+        title: "プレビュー",
+        body: "変換された図がここに表示されます。拡大・縮小や、リンク付きSVG・テキストでの表示に切り替えられます。",
+    },
+    TourStep {
+        target: ".save-btn",
+        title: "スロットに保存",
+        body: "ブラウザ内のスロットに保存します。保存した図は左の「保存一覧」からいつでも開けます。",
+    },
+    TourStep {
+        target: ".export-btn",
+        title: "エクスポート",
+        body: "図をPNGまたはSVGでダウンロードします。PNGは解像度も選べます。",
+    },
+];
+
+/// Area of the page, in viewport coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub top: f64,
+    pub left: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Top-left corner of the card for a highlighted `target`
+///
+/// Below the target when there is room, otherwise above it (or at the top of
+/// the window), and kept inside the window horizontally.
+pub fn card_position(target: Rect, viewport_width: f64, viewport_height: f64) -> (f64, f64) {
+    let below = target.top + target.height + CARD_MARGIN;
+    let top = if below + CARD_HEIGHT <= viewport_height {
+        below
+    } else {
+        (target.top - CARD_HEIGHT - CARD_MARGIN).max(CARD_MARGIN)
+    };
+    let max_left = (viewport_width - CARD_WIDTH - CARD_MARGIN).max(CARD_MARGIN);
+    (top, target.left.clamp(CARD_MARGIN, max_left))
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// Whether the tour was finished or skipped before
+pub fn tour_seen() -> bool {
+    local_storage()
+        .and_then(|storage| storage.get_item(TOUR_SEEN_KEY).ok().flatten())
+        .is_some()
+}
+
+/// Remember that the tour was seen, so it does not start by itself again
+pub fn mark_tour_seen() {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(TOUR_SEEN_KEY, "1");
+    }
+}
+
+fn find(selector: &str) -> Option<web_sys::Element> {
+    web_sys::window()?
+        .document()?
+        .query_selector(selector)
+        .ok()
+        .flatten()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct TourProps {
+    /// Finished or skipped
+    pub on_close: Callback<()>,
+}
+
+#[function_component(Tour)]
+pub fn tour(props: &TourProps) -> Html {
+    let step = use_state(|| 0usize);
+    let refresh = use_force_update();
+
+    // Follow the highlighted element when the window is resized
+    {
+        let refresh = refresh.clone();
+        use_effect_with((), move |_| {
+            let listener = Closure::<dyn FnMut()>::new(move || refresh.force_update());
+            let window = web_sys::window();
+            if let Some(window) = &window {
+                let _ = window
+                    .add_event_listener_with_callback("resize", listener.as_ref().unchecked_ref());
+            }
+            move || {
+                if let Some(window) = window {
+                    let _ = window.remove_event_listener_with_callback(
+                        "resize",
+                        listener.as_ref().unchecked_ref(),
+                    );
+                }
+            }
+        });
+    }
+
+    let steps: Vec<(&TourStep, web_sys::Element)> = TOUR_STEPS
+        .iter()
+        .filter_map(|step| find(step.target).map(|element| (step, element)))
+        .collect();
+    let index = (*step).min(steps.len().saturating_sub(1));
+
+    // Bring the element into view when a step starts
+    {
+        let target = steps.get(index).map(|(_, element)| element.clone());
+        let refresh = refresh.clone();
+        use_effect_with(index, move |_| {
+            let Some(target) = target else {
+                return;
+            };
+            let rect = target.get_bounding_client_rect();
+            let height = web_sys::window()
+                .and_then(|window| window.inner_height().ok())
+                .and_then(|height| height.as_f64())
+                .unwrap_or_default();
+            if rect.top() < 0.0 || rect.bottom() > height {
+                target.scroll_into_view();
+                refresh.force_update();
+            }
+        });
+    }
+
+    // Nothing on the page to show
+    {
+        let on_close = props.on_close.clone();
+        use_effect_with(steps.is_empty(), move |empty| {
+            if *empty {
+                on_close.emit(());
+            }
+        });
+    }
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+    let Some((current, target)) = steps.get(index) else {
+        return html! {};
+    };
+
+    let bounds = target.get_bounding_client_rect();
+    let spotlight = Rect {
+        top: bounds.top() - SPOTLIGHT_PADDING,
+        left: bounds.left() - SPOTLIGHT_PADDING,
+        width: bounds.width() + SPOTLIGHT_PADDING * 2.0,
+        height: bounds.height() + SPOTLIGHT_PADDING * 2.0,
+    };
+    let (viewport_width, viewport_height) = web_sys::window()
+        .map(|window| {
+            let size = |value: Result<JsValue, JsValue>| {
+                value.ok().and_then(|value| value.as_f64()).unwrap_or_default()
+            };
+            (size(window.inner_width()), size(window.inner_height()))
+        })
+        .unwrap_or_default();
+    let (card_top, card_left) = card_position(spotlight, viewport_width, viewport_height);
+
+    let go_to = |index: usize| {
+        let step = step.clone();
+        Callback::from(move |_: MouseEvent| step.set(index))
+    };
+    let last = index + 1 == steps.len();
+
+    html! {
+        <div class="tour">
+            <div class="tour-blocker" />
+            <div
+                class="tour-spotlight"
+                style={format!(
+                    "top: {:.0}px; left: {:.0}px; width: {:.0}px; height: {:.0}px;",
+                    spotlight.top, spotlight.left, spotlight.width, spotlight.height
+                )}
+            />
+            <div
+                class="tour-card"
+                role="dialog"
+                aria-label={current.title}
+                style={format!("top: {:.0}px; left: {:.0}px; width: {:.0}px;", card_top, card_left, CARD_WIDTH)}
+            >
+                <div class="tour-card-header">
+                    <strong>{ current.title }</strong>
+                    <span class="tour-progress">{ format!("{} / {}", index + 1, steps.len()) }</span>
+                </div>
+                <p>{ current.body }</p>
+                <div class="tour-actions">
+                    <button class="tour-skip" onclick={on_close.clone()}>{ "スキップ" }</button>
+                    if index > 0 {
+                        <button onclick={go_to(index - 1)}>{ "戻る" }</button>
+                    }
+                    if last {
+                        <button class="tour-next" onclick={on_close}>{ "完了" }</button>
+                    } else {
+                        <button class="tour-next" onclick={go_to(index + 1)}>{ "次へ" }</button>
+                    }
+                </div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_cover_the_basics() {
+        let targets: Vec<_> = TOUR_STEPS.iter().map(|step| step.target).collect();
+        assert_eq!(targets, vec![".editor-area", ".preview-area", ".save-btn", ".export-btn"]);
+        assert!(TOUR_STEPS.iter().all(|step| !step.title.is_empty() && !step.body.is_empty()));
+    }
+
+    #[test]
+    fn test_card_position() {
+        let target = Rect {
+            top: 100.0,
+            left: 50.0,
+            width: 200.0,
+            height: 40.0,
+        };
+        // Below the target
+        assert_eq!(card_position(target, 1280.0, 800.0), (152.0, 50.0));
+        // Above it near the bottom of the window
+        let low = Rect { top: 700.0, ..target };
+        assert_eq!(card_position(low, 1280.0, 800.0), (518.0, 50.0));
+        // Kept inside the window on the right and at the top
+        let right = Rect { left: 1200.0, ..target };
+        assert_eq!(card_position(right, 1280.0, 800.0).1, 1280.0 - CARD_WIDTH - CARD_MARGIN);
+        let tall = Rect { top: 0.0, height: 790.0, ..target };
+        assert_eq!(card_position(tall, 1280.0, 800.0).0, CARD_MARGIN);
+    }
+}
@@ -0,0 +1,96 @@
+// Trash panel listing deleted slots that can still be restored
+
+use plantuml_editor_core::DocumentId;
+use plantuml_editor_storageservice::{
+    LocalStorageBackend, StorageService, TrashInfo, TRASH_RETENTION_DAYS,
+};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct TrashPanelProps {
+    pub on_restore: Callback<DocumentId>,
+    /// Purge one document, or the whole trash with None
+    pub on_purge: Callback<Option<DocumentId>>,
+}
+
+/// Deletion time shown in the panel (e.g., "05/12 14:03")
+fn format_deleted_at(deleted_at: i64) -> String {
+    chrono::DateTime::from_timestamp(deleted_at, 0)
+        .map(|time| time.with_timezone(&chrono::Local).format("%m/%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+#[function_component(TrashPanel)]
+pub fn trash_panel(props: &TrashPanelProps) -> Html {
+    let open = use_state(|| false);
+
+    // Read on every render so deletes and restores elsewhere show up immediately
+    let items = if *open {
+        StorageService::new(LocalStorageBackend::new()).list_trash()
+    } else {
+        Vec::new()
+    };
+
+    let toggle = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+
+    let render_item = |item: &TrashInfo| {
+        let on_restore = {
+            let on_restore = props.on_restore.clone();
+            let id = item.document_id;
+            Callback::from(move |_| on_restore.emit(id))
+        };
+        let on_purge = {
+            let on_purge = props.on_purge.clone();
+            let id = item.document_id;
+            Callback::from(move |_| on_purge.emit(Some(id)))
+        };
+
+        html! {
+            <div class="trash-item" key={item.document_id.0.to_string()}>
+                <span class="slot-text">
+                    { format!("スロット{}: {}", item.slot_number, item.title) }
+                </span>
+                <span class="trash-deleted-at">{ format_deleted_at(item.deleted_at) }</span>
+                <div class="slot-actions">
+                    <button class="slot-button restore-button" onclick={on_restore} title="元に戻す">
+                        {"↺"}
+                    </button>
+                    <button class="slot-button delete-button" onclick={on_purge} title="完全に削除">
+                        {"×"}
+                    </button>
+                </div>
+            </div>
+        }
+    };
+
+    let on_purge_all = {
+        let on_purge = props.on_purge.clone();
+        Callback::from(move |_| on_purge.emit(None))
+    };
+
+    html! {
+        <div class="trash-panel">
+            <button class="trash-toggle" onclick={toggle}>
+                { if *open { "ゴミ箱 ▲" } else { "ゴミ箱 ▼" } }
+            </button>
+            if *open {
+                <div class="trash-list">
+                    if items.is_empty() {
+                        <div class="trash-empty">{ "ゴミ箱は空です" }</div>
+                    } else {
+                        { for items.iter().map(render_item) }
+                        <button class="trash-purge-all" onclick={on_purge_all}>
+                            { "ゴミ箱を空にする" }
+                        </button>
+                    }
+                    <div class="trash-note">
+                        { format!("削除したスロットは{}日間保持されます", TRASH_RETENTION_DAYS) }
+                    </div>
+                </div>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,98 @@
+// Dialog for opening a diagram from its PlantUML server image URL
+
+use plantuml_editor_core::ErrorCode;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::errors::api_error_to_code;
+use crate::host;
+
+const ENDPOINT: &str = "/api/v1/import/url";
+
+#[derive(Properties, PartialEq)]
+pub struct UrlImportDialogProps {
+    /// Called with the source decoded from the URL
+    pub on_import: Callback<String>,
+    pub on_error: Callback<ErrorCode>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(UrlImportDialog)]
+pub fn url_import_dialog(props: &UrlImportDialogProps) -> Html {
+    let url = use_state(String::new);
+    let loading = use_state(|| false);
+
+    let on_input = {
+        let url = url.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            url.set(input.value());
+        })
+    };
+
+    let on_submit = {
+        let url = url.clone();
+        let loading = loading.clone();
+        let on_import = props.on_import.clone();
+        let on_error = props.on_error.clone();
+        let on_close = props.on_close.clone();
+
+        Callback::from(move |_| {
+            let url = (*url).clone();
+            let loading = loading.clone();
+            let on_import = on_import.clone();
+            let on_error = on_error.clone();
+            let on_close = on_close.clone();
+            loading.set(true);
+
+            spawn_local(async move {
+                match host::api_client().import_diagram_url(url).await {
+                    Ok((plantuml_text, _)) => {
+                        on_import.emit(plantuml_text);
+                        on_close.emit(());
+                    }
+                    Err(e) => on_error.emit(api_error_to_code(e, ENDPOINT)),
+                }
+                loading.set(false);
+            });
+        })
+    };
+
+    let on_cancel = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    html! {
+        <div class="import-dialog-overlay">
+            <div class="import-dialog">
+                <div class="import-dialog-header">{"画像URLから開く"}</div>
+                <div class="url-import-body">
+                    <input
+                        type="url"
+                        class="url-import-input"
+                        placeholder="https://www.plantuml.com/plantuml/png/SyfFKj2rKt3CoKnELR1Io4ZDoSa70000"
+                        value={(*url).clone()}
+                        oninput={on_input}
+                    />
+                    <div class="url-import-hint">
+                        {"Wikiなどに貼られたPlantUMLサーバーの画像URLから、編集できるソースを復元します"}
+                    </div>
+                    if *loading {
+                        <div class="loading">{"処理中..."}</div>
+                    }
+                </div>
+                <div class="import-dialog-actions">
+                    <button class="import-cancel-btn" onclick={on_cancel}>{"キャンセル"}</button>
+                    <button
+                        class="import-submit-btn"
+                        onclick={on_submit}
+                        disabled={url.trim().is_empty() || *loading}
+                    >
+                        {"取り込む"}
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
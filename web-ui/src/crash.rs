@@ -0,0 +1,163 @@
+// Crash handling
+//
+// A panic leaves the wasm app unusable: Yew cannot render again afterwards.
+// The panic hook from `install` logs the panic to the console (as
+// console_error_panic_hook does), keeps the text of the editor in recovery
+// storage and replaces the part of the page inside `ErrorBoundary` with a
+// crash screen built without Yew. Its reload link starts the editor again,
+// which then restores the text (see `take_recovered`).
+
+use std::any::Any;
+use std::cell::RefCell;
+
+use yew::prelude::*;
+
+/// LocalStorage key holding the editor text saved by the panic hook
+const RECOVERY_KEY: &str = "plantuml_crash_recovery";
+
+thread_local! {
+    /// Text in the editor, kept up to date by the App
+    static CONTENT: RefCell<String> = const { RefCell::new(String::new()) };
+    /// Element the crash screen replaces (see ErrorBoundary)
+    static BOUNDARY: RefCell<Option<web_sys::Element>> = const { RefCell::new(None) };
+}
+
+/// Remember the editor text, to save it if the app panics
+pub fn track_content(text: &str) {
+    CONTENT.with(|content| {
+        if let Ok(mut content) = content.try_borrow_mut() {
+            content.clear();
+            content.push_str(text);
+        }
+    });
+}
+
+/// Message of a panic payload
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "不明なエラー".to_string())
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// Text saved by the panic hook before the last reload, removed from storage
+pub fn take_recovered() -> Option<String> {
+    let storage = local_storage()?;
+    let text = storage.get_item(RECOVERY_KEY).ok().flatten()?;
+    let _ = storage.remove_item(RECOVERY_KEY);
+    Some(text).filter(|text| !text.trim().is_empty())
+}
+
+fn save_recovery() {
+    let text = CONTENT
+        .try_with(|content| content.try_borrow().map(|text| text.clone()).ok())
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if text.trim().is_empty() {
+        return;
+    }
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(RECOVERY_KEY, &text);
+    }
+}
+
+/// Replace the boundary (or the whole page) with the crash screen
+///
+/// Built from plain DOM calls, since Yew no longer runs. Reloading is a link
+/// to the current page, so it needs no script.
+fn show_crash_screen(details: &str) -> Option<()> {
+    let document = web_sys::window()?.document()?;
+    let target = BOUNDARY
+        .try_with(|boundary| boundary.try_borrow().ok().and_then(|element| element.clone()))
+        .ok()
+        .flatten()
+        .or_else(|| document.body().map(Into::into))?;
+    let href = document.location()?.href().ok()?;
+
+    // Appended to `parent`, or None when the DOM refuses
+    let element = |parent: &web_sys::Element, tag: &str, class: &str, text: Option<&str>| {
+        let element = document.create_element(tag).ok()?;
+        element.set_class_name(class);
+        element.set_text_content(text);
+        parent.append_child(&element).ok()?;
+        Some(element)
+    };
+    let screen = document.create_element("div").ok()?;
+    screen.set_class_name("crash-screen");
+    screen.set_attribute("role", "alert").ok()?;
+    element(&screen, "h2", "crash-title", Some("予期しないエラーが発生しました"))?;
+    element(
+        &screen,
+        "p",
+        "crash-body",
+        Some("編集中の内容は保存しました。再読み込みするとエディタに復元されます。"),
+    )?;
+    let reload = element(&screen, "a", "crash-reload", Some("再読み込み"))?;
+    reload.set_attribute("href", &href).ok()?;
+    let more = element(&screen, "details", "crash-details", None)?;
+    element(&more, "summary", "", Some("詳細"))?;
+    element(&more, "pre", "", Some(details))?;
+
+    target.set_text_content(None);
+    target.append_child(&screen).ok()?;
+    Some(())
+}
+
+/// Install the panic hook; call once before rendering the App
+pub fn install() {
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+        save_recovery();
+        let details = match info.location() {
+            Some(location) => format!("{}\n({})", panic_message(info.payload()), location),
+            None => panic_message(info.payload()),
+        };
+        let _ = show_crash_screen(&details);
+    }));
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ErrorBoundaryProps {
+    pub children: Html,
+}
+
+/// Part of the page the crash screen replaces when the app panics
+#[function_component(ErrorBoundary)]
+pub fn error_boundary(props: &ErrorBoundaryProps) -> Html {
+    let node = use_node_ref();
+
+    {
+        let node = node.clone();
+        use_effect_with((), move |_| {
+            BOUNDARY.with(|boundary| *boundary.borrow_mut() = node.cast::<web_sys::Element>());
+            || BOUNDARY.with(|boundary| *boundary.borrow_mut() = None)
+        });
+    }
+
+    html! {
+        <div ref={node} class="error-boundary">
+            { props.children.clone() }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("描画に失敗")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "描画に失敗");
+        let payload = std::panic::catch_unwind(|| panic!("{} 行目", 3)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "3 行目");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "不明なエラー");
+    }
+}
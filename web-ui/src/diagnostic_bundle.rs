@@ -0,0 +1,127 @@
+// Diagnostic bundle attached to bug reports
+//
+// Collects what is needed to reproduce a problem: the app version and host,
+// the messages the editor showed most recently, and the API requests behind
+// them with the IDs the server logged them under. The diagram source is only
+// included when the user opts in.
+
+use std::collections::VecDeque;
+
+use plantuml_editor_api_client::RequestRecord;
+use serde::Serialize;
+
+use crate::MessageLevel;
+
+/// Messages kept for the bundle; older ones are dropped
+pub const RESULT_HISTORY_LIMIT: usize = 30;
+
+/// A message the editor showed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResultEntry {
+    /// When it was shown (RFC 3339)
+    pub at: String,
+    pub level: MessageLevel,
+    pub message: String,
+}
+
+/// Most recent messages, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ResultHistory {
+    entries: VecDeque<ResultEntry>,
+}
+
+impl ResultHistory {
+    pub fn push(&mut self, entry: ResultEntry) {
+        if self.entries.len() == RESULT_HISTORY_LIMIT {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> Vec<ResultEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+/// Everything a bug report carries, downloaded or posted as JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticBundle {
+    pub app_version: &'static str,
+    /// "browser" or "vscode"
+    pub host: &'static str,
+    pub user_agent: Option<String>,
+    /// When the bundle was made (RFC 3339)
+    pub created_at: String,
+    /// What the user describes
+    pub description: String,
+    pub results: Vec<ResultEntry>,
+    pub requests: Vec<RequestRecord>,
+    /// Diagram source, when the user chose to include it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl DiagnosticBundle {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Download name, e.g. "plantuml-report-20250401-093000.json"
+    pub fn file_name(&self) -> String {
+        let stamp = chrono::DateTime::parse_from_rfc3339(&self.created_at)
+            .map(|time| time.format("%Y%m%d-%H%M%S").to_string())
+            .unwrap_or_else(|_| "report".to_string());
+        format!("plantuml-report-{}.json", stamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str) -> ResultEntry {
+        ResultEntry {
+            at: "2025-04-01T09:30:00+00:00".to_string(),
+            level: MessageLevel::Error,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_result_history_keeps_the_latest() {
+        let mut history = ResultHistory::default();
+        for index in 0..RESULT_HISTORY_LIMIT + 3 {
+            history.push(entry(&index.to_string()));
+        }
+        let entries = history.entries();
+        assert_eq!(entries.len(), RESULT_HISTORY_LIMIT);
+        assert_eq!(entries[0].message, "3");
+    }
+
+    #[test]
+    fn test_bundle_includes_source_only_when_chosen() {
+        let mut bundle = DiagnosticBundle {
+            app_version: "0.1.0",
+            host: "browser",
+            user_agent: None,
+            created_at: "2025-04-01T09:30:00+00:00".to_string(),
+            description: "変換が終わらない".to_string(),
+            results: vec![entry("構文エラー")],
+            requests: vec![RequestRecord {
+                path: "/api/v1/convert".to_string(),
+                status: 200,
+                request_id: Some("req-1".to_string()),
+            }],
+            source: None,
+        };
+        let json: serde_json::Value = serde_json::from_str(&bundle.to_json()).unwrap();
+        assert!(json.get("source").is_none());
+        assert_eq!(json["results"][0]["level"], "error");
+        assert_eq!(json["requests"][0]["request_id"], "req-1");
+        assert_eq!(bundle.file_name(), "plantuml-report-20250401-093000.json");
+
+        bundle.source = Some("@startuml\n@enduml".to_string());
+        let json: serde_json::Value = serde_json::from_str(&bundle.to_json()).unwrap();
+        assert_eq!(json["source"], "@startuml\n@enduml");
+    }
+}
@@ -0,0 +1,96 @@
+// Browser-native file downloads
+//
+// In a VS Code webview the extension saves files instead (see `webview`).
+
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::{host, webview};
+
+/// Name of the hidden frame that receives download responses
+const DOWNLOAD_FRAME: &str = "plantuml-download-frame";
+
+/// Submit a hidden POST form so the browser downloads the response itself
+///
+/// The form targets a hidden frame, so the page stays put even when the server
+/// answers with something other than an attachment.
+pub fn submit_download_form(action: &str, fields: &[(&str, &str)]) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("document is not available"))?;
+    let body = document
+        .body()
+        .ok_or_else(|| JsValue::from_str("document body is not available"))?;
+
+    if document.get_element_by_id(DOWNLOAD_FRAME).is_none() {
+        let frame = document.create_element("iframe")?;
+        frame.set_id(DOWNLOAD_FRAME);
+        frame.set_attribute("name", DOWNLOAD_FRAME)?;
+        frame.set_attribute("hidden", "")?;
+        body.append_child(&frame)?;
+    }
+
+    let form = document
+        .create_element("form")?
+        .dyn_into::<web_sys::HtmlFormElement>()?;
+    form.set_method("post");
+    form.set_action(action);
+    form.set_target(DOWNLOAD_FRAME);
+    form.set_hidden(true);
+
+    for (name, value) in fields {
+        let input = document
+            .create_element("input")?
+            .dyn_into::<web_sys::HtmlInputElement>()?;
+        input.set_type("hidden");
+        input.set_name(name);
+        input.set_value(value);
+        form.append_child(&input)?;
+    }
+
+    body.append_child(&form)?;
+    form.submit()?;
+    form.remove();
+    Ok(())
+}
+
+/// Download in-memory data (e.g. a rendered PNG) as a file
+pub fn download_blob(blob: &web_sys::Blob, file_name: &str) -> Result<(), JsValue> {
+    if host::is_webview() {
+        let blob = blob.clone();
+        let file_name = file_name.to_string();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Ok(buffer) = wasm_bindgen_futures::JsFuture::from(blob.array_buffer()).await {
+                let data = js_sys::Uint8Array::new(&buffer).to_vec();
+                webview::download(&data, &blob.type_(), &file_name);
+            }
+        });
+        return Ok(());
+    }
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("document is not available"))?;
+
+    let url = web_sys::Url::create_object_url_with_blob(blob)?;
+    let link = document
+        .create_element("a")?
+        .dyn_into::<web_sys::HtmlAnchorElement>()?;
+    link.set_href(&url);
+    link.set_download(file_name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// Download raw bytes with the given MIME type
+pub fn download_bytes(bytes: &[u8], mime_type: &str, file_name: &str) -> Result<(), JsValue> {
+    if host::is_webview() {
+        webview::download(bytes, mime_type, file_name);
+        return Ok(());
+    }
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    download_blob(&blob, file_name)
+}
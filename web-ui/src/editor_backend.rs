@@ -0,0 +1,273 @@
+// Editing surfaces behind the Editor component
+//
+// The Editor component keeps the document state, the idle-scheduled updates
+// and the editing commands (indent, line continuation); the surface the user
+// types into is an `EditorBackend`. The default is the plain textarea. With
+// the `codemirror` feature the editor can use CodeMirror instead (see
+// `codemirror`), which draws diagnostics in the text and gives the
+// foundation for richer editing.
+//
+// Offsets are UTF-16 code units, as everywhere in the editor. Markers are the
+// diagnostics of `plantuml_editor_core::diagnostics`: the static checks and
+// syntax errors the server reports, underlined in the text with their message
+// shown on hover.
+//
+// A textarea cannot style its text, so its markers are drawn in a backdrop
+// behind it: an element with the same text and layout, transparent except for
+// the underlines, scrolled along with the textarea.
+
+use plantuml_editor_core::diagnostics::byte_range;
+use plantuml_editor_core::{Diagnostic, DiagnosticSeverity};
+use yew::Callback;
+
+/// Surface the Editor component edits through
+pub trait EditorBackend {
+    /// Current text
+    fn content(&self) -> String;
+    /// Replace the text (without reporting a change)
+    fn set_content(&self, text: &str);
+    /// Selected range; start equals end for a caret
+    fn selection(&self) -> Option<(usize, usize)>;
+    fn set_selection(&self, start: usize, end: usize);
+    /// Show `markers` in place of the previous ones
+    fn set_markers(&self, markers: &[Diagnostic]);
+    fn set_read_only(&self, read_only: bool);
+}
+
+/// Events a backend reports on its own (the textarea's come through Yew)
+#[derive(Clone, Default)]
+pub struct EditorEvents {
+    /// Text after each edit
+    pub on_change: Callback<String>,
+    /// Selected range after each edit, click or cursor move
+    pub on_selection: Callback<(usize, usize)>,
+}
+
+/// Which surface the Editor component shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EditorKind {
+    #[default]
+    TextArea,
+    #[cfg(feature = "codemirror")]
+    CodeMirror,
+}
+
+impl EditorKind {
+    /// CodeMirror when the feature is built in and its script is on the page
+    pub fn preferred() -> Self {
+        #[cfg(feature = "codemirror")]
+        if crate::codemirror::is_available() {
+            return Self::CodeMirror;
+        }
+        Self::TextArea
+    }
+
+    pub fn is_code_mirror(self) -> bool {
+        !matches!(self, Self::TextArea)
+    }
+}
+
+/// Class of the underline for a marker
+pub fn marker_class(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "marker-error",
+        DiagnosticSeverity::Warning => "marker-warning",
+        DiagnosticSeverity::Information => "marker-info",
+    }
+}
+
+/// `content` cut into byte ranges, each with the marker covering it
+///
+/// Overlapping markers are cut at the start of the later one. Empty ranges
+/// cover the character after them, so markers at a line end stay visible.
+pub fn marked_segments(content: &str, markers: &[Diagnostic]) -> Vec<(usize, usize, Option<usize>)> {
+    let mut ranges: Vec<(usize, usize, usize)> = markers
+        .iter()
+        .enumerate()
+        .map(|(index, marker)| {
+            let (start, end) = byte_range(content, &marker.range);
+            let end = if end > start {
+                end
+            } else {
+                content[start..]
+                    .chars()
+                    .next()
+                    .map_or(start, |c| start + c.len_utf8())
+            };
+            (start, end, index)
+        })
+        .filter(|(start, end, _)| end > start)
+        .collect();
+    ranges.sort();
+
+    let mut segments = Vec::new();
+    let mut at = 0;
+    for (index, &(start, end, marker)) in ranges.iter().enumerate() {
+        let start = start.max(at);
+        let end = ranges
+            .get(index + 1)
+            .map_or(end, |&(next, _, _)| end.min(next.max(start)));
+        if end <= start {
+            continue;
+        }
+        if start > at {
+            segments.push((at, start, None));
+        }
+        segments.push((start, end, Some(marker)));
+        at = end;
+    }
+    if at < content.len() {
+        segments.push((at, content.len(), None));
+    }
+    segments
+}
+
+/// The default backend: a `<textarea>`, with its markers in the
+/// `.editor-backdrop` element before it
+pub struct TextAreaBackend {
+    element: web_sys::HtmlTextAreaElement,
+}
+
+impl TextAreaBackend {
+    pub fn new(element: web_sys::HtmlTextAreaElement) -> Self {
+        Self { element }
+    }
+
+    fn backdrop(&self) -> Option<web_sys::Element> {
+        self.element.previous_element_sibling().filter(|element| {
+            element
+                .class_name()
+                .split_whitespace()
+                .any(|class| class == "editor-backdrop")
+        })
+    }
+
+    /// Keep the underlines under the text after scrolling
+    pub fn sync_scroll(&self) {
+        if let Some(backdrop) = self.backdrop() {
+            backdrop.set_scroll_top(self.element.scroll_top());
+            backdrop.set_scroll_left(self.element.scroll_left());
+        }
+    }
+
+    /// Show the message of the marker under the pointer as the tooltip
+    pub fn hover(&self, x: f64, y: f64) {
+        let message = self.backdrop().and_then(|backdrop| {
+            let marks = backdrop.get_elements_by_tag_name("mark");
+            (0..marks.length())
+                .filter_map(|index| marks.item(index))
+                .find(|mark| {
+                    let rects = mark.get_client_rects();
+                    (0..rects.length())
+                        .filter_map(|index| rects.item(index))
+                        .any(|rect| {
+                            (rect.left()..=rect.right()).contains(&x)
+                                && (rect.top()..=rect.bottom()).contains(&y)
+                        })
+                })
+                .and_then(|mark| mark.get_attribute("title"))
+        });
+        let _ = match message {
+            Some(message) => self.element.set_attribute("title", &message),
+            None => self.element.remove_attribute("title"),
+        };
+    }
+}
+
+impl EditorBackend for TextAreaBackend {
+    fn content(&self) -> String {
+        self.element.value()
+    }
+
+    fn set_content(&self, text: &str) {
+        self.element.set_value(text);
+    }
+
+    fn selection(&self) -> Option<(usize, usize)> {
+        let start = self.element.selection_start().ok().flatten()?;
+        let end = self.element.selection_end().ok().flatten()?;
+        Some((start as usize, end as usize))
+    }
+
+    fn set_selection(&self, start: usize, end: usize) {
+        let _ = self
+            .element
+            .set_selection_range(start as u32, end as u32);
+    }
+
+    fn set_markers(&self, markers: &[Diagnostic]) {
+        let element = &self.element;
+        let _ = element.remove_attribute("title");
+        let _ = if markers.is_empty() {
+            element.remove_attribute("aria-invalid")
+        } else {
+            element.set_attribute("aria-invalid", "true")
+        };
+
+        let Some(backdrop) = self.backdrop() else {
+            return;
+        };
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        backdrop.set_text_content(None);
+        if markers.is_empty() {
+            return;
+        }
+        let text = element.value();
+        for (start, end, marker) in marked_segments(&text, markers) {
+            let piece = &text[start..end];
+            let Some(marker) = marker.map(|index| &markers[index]) else {
+                let _ = backdrop.append_with_str_1(piece);
+                continue;
+            };
+            let Ok(mark) = document.create_element("mark") else {
+                return;
+            };
+            mark.set_class_name(marker_class(marker.severity));
+            let _ = mark.set_attribute("title", &marker.message);
+            mark.set_text_content(Some(piece));
+            let _ = backdrop.append_child(&mark);
+        }
+        // A trailing line break only takes up height with text after it
+        let _ = backdrop.append_with_str_1(" ");
+        self.sync_scroll();
+    }
+
+    fn set_read_only(&self, read_only: bool) {
+        self.element.set_read_only(read_only);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use plantuml_editor_core::diagnostics::line_diagnostic;
+
+    #[test]
+    fn test_marked_segments() {
+        let content = "@startuml\nloop\nA -> B\n@enduml";
+        let loop_line = line_diagnostic(content, 1, DiagnosticSeverity::Warning, "x", String::new());
+        let segments = marked_segments(content, std::slice::from_ref(&loop_line));
+        let pieces: Vec<_> = segments
+            .iter()
+            .map(|&(start, end, marker)| (&content[start..end], marker))
+            .collect();
+        assert_eq!(
+            pieces,
+            vec![("@startuml\n", None), ("loop", Some(0)), ("\nA -> B\n@enduml", None)]
+        );
+
+        // The second marker starts inside the first, which is cut there
+        let arrow = line_diagnostic(content, 2, DiagnosticSeverity::Error, "y", String::new());
+        let mut wide = loop_line;
+        wide.range.end = arrow.range.end;
+        let segments = marked_segments(content, &[wide, arrow]);
+        let marked: Vec<_> = segments
+            .iter()
+            .filter_map(|&(start, end, marker)| marker.map(|marker| (&content[start..end], marker)))
+            .collect();
+        assert_eq!(marked, vec![("loop\n", 0), ("A -> B", 1)]);
+        assert_eq!(marked_segments(content, &[]), vec![(0, content.len(), None)]);
+    }
+}
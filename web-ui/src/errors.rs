@@ -19,3 +19,20 @@ impl std::fmt::Display for UiError {
 }
 
 impl std::error::Error for UiError {}
+
+/// Map API client errors to ErrorCode for display
+pub fn api_error_to_code(
+    error: plantuml_editor_api_client::ApiError,
+    endpoint: &str,
+) -> plantuml_editor_core::ErrorCode {
+    use plantuml_editor_api_client::ApiError;
+    use plantuml_editor_core::ErrorCode;
+
+    match error {
+        ApiError::ProcessError(code) => code,
+        ApiError::NetworkError(_) => ErrorCode::NetworkError {
+            endpoint: endpoint.to_string(),
+        },
+        ApiError::ServerError(message) => ErrorCode::ServerError { message },
+    }
+}
@@ -0,0 +1,62 @@
+// Opening files from disk (file picker and drag and drop)
+//
+// Files are read as bytes and decoded by plantuml_editor_core::text_import,
+// so UTF-16 and Shift_JIS files and CRLF line endings import as clean UTF-8;
+// other encodings and binary files are reported as EncodingError. Images
+// exported with their source embedded can be opened for editing as well.
+
+use plantuml_editor_core::embed::extract_source;
+use plantuml_editor_core::text_import::decode_text_file;
+use plantuml_editor_core::ErrorCode;
+use wasm_bindgen_futures::JsFuture;
+
+/// File types offered by the picker
+pub const ACCEPT: &str = ".puml,.plantuml,.pu,.iuml,.wsd,.txt";
+
+/// Images the source can be recovered from (see `read_embedded_source`)
+pub const ACCEPT_IMAGES: &str = ".png,.svg";
+
+async fn read_bytes(file: &web_sys::File) -> Result<Vec<u8>, ErrorCode> {
+    let buffer = JsFuture::from(file.array_buffer())
+        .await
+        .map_err(|_| ErrorCode::ImportError {
+            reason: "ファイルを読み込めません".to_string(),
+        })?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Text of `file`, decoded to UTF-8 with `\n` line endings
+pub async fn read_text_file(file: &web_sys::File) -> Result<String, ErrorCode> {
+    let bytes = read_bytes(file).await?;
+    decode_text_file(&bytes).map(|imported| imported.text)
+}
+
+/// Source embedded in an exported PNG or SVG (see plantuml_editor_core::embed)
+pub async fn read_embedded_source(file: &web_sys::File) -> Result<String, ErrorCode> {
+    let bytes = read_bytes(file).await?;
+    extract_source(&bytes).ok_or_else(|| ErrorCode::ImportError {
+        reason: "画像に PlantUML のソースが埋め込まれていません".to_string(),
+    })
+}
+
+/// Source in a dropped file: the embedded source of a PNG or SVG image,
+/// otherwise the decoded text
+pub async fn read_dropped_file(file: &web_sys::File) -> Result<String, ErrorCode> {
+    match file.type_().as_str() {
+        "image/png" | "image/svg+xml" => read_embedded_source(file).await,
+        _ => read_text_file(file).await,
+    }
+}
+
+/// Whether a drag carries files (rather than text or a slot)
+pub fn drags_files(event: &web_sys::DragEvent) -> bool {
+    event
+        .data_transfer()
+        .map(|transfer| transfer.types().includes(&"Files".into(), 0))
+        .unwrap_or(false)
+}
+
+/// First file dropped by `event`
+pub fn dropped_file(event: &web_sys::DragEvent) -> Option<web_sys::File> {
+    event.data_transfer()?.files()?.get(0)
+}
@@ -0,0 +1,205 @@
+// Where the editor runs and how it reaches the API server
+//
+// A page embedding the bundle may set `window.PLANTUML_EDITOR_CONFIG` before
+// loading it, e.g. `{ "host": "vscode", "apiBaseUrl": "http://localhost:8080" }`.
+// Without it the editor is a normal browser app using the default API URL.
+// `feedbackUrl` is where the "問題を報告" dialog posts its bundle; without it
+// the bundle can only be downloaded. `telemetryUrl` receives usage counts when
+// the user allows sending them (see `telemetry`). `oidc` (`{ issuer, clientId }`)
+// makes server storage require login (see `auth`).
+
+use plantuml_editor_api_client::{ApiClient, RequestLog, RequestRecord};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::auth::{self, OidcSettings};
+use crate::webview;
+
+/// Global the embedding page sets to configure the editor
+const CONFIG_GLOBAL: &str = "PLANTUML_EDITOR_CONFIG";
+
+/// Application embedding the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    /// Standalone page: fetches the API directly and keeps slots in LocalStorage
+    #[default]
+    Browser,
+    /// VS Code webview: everything goes through the extension (see `webview`)
+    Vscode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostConfig {
+    #[serde(default)]
+    pub host: HostKind,
+    /// API server URL (the API client default when missing)
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    /// Endpoint accepting bug reports as JSON (see `diagnostic_bundle`)
+    #[serde(default)]
+    pub feedback_url: Option<String>,
+    /// Endpoint accepting usage telemetry batches (see `telemetry`)
+    #[serde(default)]
+    pub telemetry_url: Option<String>,
+    /// Login for server storage (see `auth`)
+    #[serde(default)]
+    pub oidc: Option<OidcSettings>,
+}
+
+impl HostConfig {
+    /// Parse an injected configuration; anything unreadable means the defaults
+    pub fn from_json(json: &str) -> Self {
+        serde_json::from_str(json).unwrap_or_default()
+    }
+
+    fn injected() -> Self {
+        let Some(window) = web_sys::window() else {
+            return Self::default();
+        };
+        js_sys::Reflect::get(&window, &CONFIG_GLOBAL.into())
+            .ok()
+            .filter(|value| !value.is_undefined())
+            .and_then(|value| js_sys::JSON::stringify(&value).ok())
+            .and_then(|json| json.as_string())
+            .map(|json| Self::from_json(&json))
+            .unwrap_or_default()
+    }
+}
+
+thread_local! {
+    static CONFIG: RefCell<HostConfig> = const {
+        RefCell::new(HostConfig {
+            host: HostKind::Browser,
+            api_base_url: None,
+            feedback_url: None,
+            telemetry_url: None,
+            oidc: None,
+        })
+    };
+    /// Requests of every client `api_client` builds, for bug reports
+    static REQUEST_LOG: RequestLog = RequestLog::new();
+}
+
+/// Read the injected configuration and connect to the extension in a webview
+pub fn init() -> HostConfig {
+    let mut config = HostConfig::injected();
+    if config.host == HostKind::Vscode && !webview::connect() {
+        // Not actually inside VS Code (e.g. the webview page opened directly)
+        config.host = HostKind::Browser;
+    }
+    CONFIG.with(|current| *current.borrow_mut() = config.clone());
+    config
+}
+
+/// Whether the editor runs in a VS Code webview
+pub fn is_webview() -> bool {
+    CONFIG.with(|config| config.borrow().host == HostKind::Vscode)
+}
+
+/// Where bug reports are posted, if anywhere
+pub fn feedback_url() -> Option<String> {
+    CONFIG.with(|config| config.borrow().feedback_url.clone())
+}
+
+/// Where usage telemetry is sent, if anywhere
+pub fn telemetry_url() -> Option<String> {
+    CONFIG.with(|config| config.borrow().telemetry_url.clone())
+}
+
+/// OIDC provider to log in with for server storage, if any
+pub fn oidc_settings() -> Option<OidcSettings> {
+    CONFIG.with(|config| config.borrow().oidc.clone())
+}
+
+/// Most recent API requests, oldest first
+pub fn recent_requests() -> Vec<RequestRecord> {
+    REQUEST_LOG.with(RequestLog::records)
+}
+
+/// Post `json` to an endpoint outside the API server (bug reports,
+/// telemetry), true when it accepted it
+pub async fn post_json(url: &str, json: &str) -> bool {
+    use wasm_bindgen::JsCast;
+
+    let Some(window) = web_sys::window() else {
+        return false;
+    };
+    let init = web_sys::RequestInit::new();
+    init.set_method("POST");
+    init.set_body(&json.into());
+    if let Ok(headers) = web_sys::Headers::new() {
+        let _ = headers.set("Content-Type", "application/json");
+        init.set_headers(&headers);
+    }
+    let request = window.fetch_with_str_and_init(url, &init);
+    let Ok(response) = wasm_bindgen_futures::JsFuture::from(request).await else {
+        return false;
+    };
+    response
+        .dyn_into::<web_sys::Response>()
+        .is_ok_and(|response| response.ok())
+}
+
+/// Client for the API server, relayed through the extension in a webview
+pub fn api_client() -> ApiClient {
+    let config = CONFIG.with(|config| config.borrow().clone());
+    let base_url = config
+        .api_base_url
+        .unwrap_or_else(|| ApiClient::default().base_url().to_string());
+
+    let mut builder = ApiClient::builder()
+        .base_url(base_url)
+        .request_log(REQUEST_LOG.with(RequestLog::clone));
+    if config.host == HostKind::Vscode {
+        builder = builder.relay(Arc::new(webview::VsCodeRelay));
+    } else if let Some(token) = auth::access_token() {
+        builder = builder.bearer_token(&token);
+    }
+    builder.build().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_config_from_json() {
+        assert_eq!(
+            HostConfig::from_json(r#"{"host":"vscode","apiBaseUrl":"http://127.0.0.1:9000"}"#),
+            HostConfig {
+                host: HostKind::Vscode,
+                api_base_url: Some("http://127.0.0.1:9000".to_string()),
+                feedback_url: None,
+                telemetry_url: None,
+                oidc: None,
+            }
+        );
+        assert_eq!(
+            HostConfig::from_json(r#"{"apiBaseUrl":"/api"}"#).host,
+            HostKind::Browser
+        );
+        assert_eq!(
+            HostConfig::from_json(r#"{"feedbackUrl":"https://support.example/reports"}"#).feedback_url,
+            Some("https://support.example/reports".to_string())
+        );
+        assert_eq!(
+            HostConfig::from_json(r#"{"oidc":{"issuer":"https://login.example.com","clientId":"editor"}}"#)
+                .oidc
+                .map(|oidc| oidc.client_id),
+            Some("editor".to_string())
+        );
+        assert_eq!(HostConfig::from_json("not json"), HostConfig::default());
+    }
+
+    #[test]
+    fn test_api_client_uses_injected_base_url() {
+        CONFIG.with(|config| {
+            *config.borrow_mut() = HostConfig::from_json(r#"{"apiBaseUrl":"http://example.test/"}"#)
+        });
+        assert_eq!(api_client().base_url(), "http://example.test");
+        assert!(!is_webview());
+    }
+}
@@ -0,0 +1,98 @@
+// Render-on-idle scheduling
+//
+// Work triggered by typing (re-rendering the preview, rebuilding the prose
+// spans) waits until the user pauses and then until the browser has idle
+// time, so it does not compete with keystrokes on slow machines. Scheduling
+// again replaces the pending task, which coalesces a burst of edits into a
+// single run. Browsers without `requestIdleCallback` (Safari) run the task
+// right after the pause, like the former fixed debounce. The pause is one of
+// the user's settings (see `settings`).
+
+use gloo_timers::callback::Timeout;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+/// Milliseconds without input before a task may run, unless the user chose
+/// another pause
+pub const PAUSE_MS: u32 = plantuml_editor_core::DEFAULT_RENDER_PAUSE_MS;
+
+thread_local! {
+    static PAUSE: Cell<u32> = const { Cell::new(PAUSE_MS) };
+}
+
+/// Use `pause_ms` for tasks scheduled from now on
+pub fn set_pause_ms(pause_ms: u32) {
+    PAUSE.set(pause_ms);
+}
+
+/// Longest wait for idle time after the pause; the task then runs anyway
+pub const MAX_IDLE_WAIT_MS: u32 = 1000;
+
+/// Idle callback kept alive until it runs or is cancelled
+type IdleCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+/// A pending task; dropping it cancels the task
+pub struct IdleTask {
+    pause: Option<Timeout>,
+    /// Handle of the requested idle callback, once the pause is over
+    idle: Rc<RefCell<Option<u32>>>,
+    callback: IdleCallback,
+}
+
+impl IdleTask {
+    /// Run `task` once the user paused (`PAUSE_MS` by default) and the browser
+    /// is idle
+    pub fn schedule(task: impl FnOnce() + 'static) -> Self {
+        let idle: Rc<RefCell<Option<u32>>> = Rc::default();
+        let callback: IdleCallback = Rc::default();
+
+        let pause = {
+            let idle = idle.clone();
+            let callback = callback.clone();
+            Timeout::new(PAUSE.get(), move || {
+                let Some(window) = web_sys::window().filter(has_idle_callback) else {
+                    return task();
+                };
+                let mut task = Some(task);
+                let done = callback.clone();
+                let closure = Closure::<dyn FnMut()>::new(move || {
+                    if let Some(task) = task.take() {
+                        task();
+                    }
+                    // Freed once it has run; it is not called again
+                    done.borrow_mut().take();
+                });
+                let options = web_sys::IdleRequestOptions::new();
+                options.set_timeout(MAX_IDLE_WAIT_MS);
+                let handle = window
+                    .request_idle_callback_with_options(closure.as_ref().unchecked_ref(), &options)
+                    .ok();
+                *idle.borrow_mut() = handle;
+                *callback.borrow_mut() = Some(closure);
+            })
+        };
+
+        Self {
+            pause: Some(pause),
+            idle,
+            callback,
+        }
+    }
+}
+
+impl Drop for IdleTask {
+    fn drop(&mut self) {
+        // Dropping the Timeout cancels a pause that is still running
+        self.pause.take();
+        if let (Some(handle), Some(window)) = (self.idle.borrow_mut().take(), web_sys::window()) {
+            window.cancel_idle_callback(handle);
+        }
+        self.callback.borrow_mut().take();
+    }
+}
+
+fn has_idle_callback(window: &web_sys::Window) -> bool {
+    js_sys::Reflect::has(window, &"requestIdleCallback".into()).unwrap_or(false)
+}
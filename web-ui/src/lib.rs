@@ -3,19 +3,41 @@
 // This crate provides reusable Yew components and UI models
 // for the PlantUML editor frontend application.
 
-use wasm_bindgen::JsCast;
 use yew::prelude::*;
 use std::rc::Rc;
 use plantuml_editor_storageservice::{StorageBackend, StorageService};
-
+use preview_cache::CacheKey;
+use preview_renderer::PreviewRenderer;
+use telemetry::TelemetryEvent;
+
+pub mod auth;
+pub mod canvas_export;
+#[cfg(feature = "codemirror")]
+pub mod codemirror;
 pub mod components;
+pub mod crash;
+pub mod diagnostic_bundle;
+pub mod download;
+pub mod editor_backend;
 pub mod errors;
+pub mod file_import;
+pub mod host;
+pub mod idle;
+pub mod object_url;
+pub mod preview_cache;
+pub mod preview_renderer;
+pub mod settings;
+pub mod sharing;
+pub mod svg_worker;
+pub mod telemetry;
+pub mod webview;
 
 // Re-export components
 pub use components::*;
 
 /// Message level for UI display
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum MessageLevel {
     Info,
     Warning,
@@ -33,6 +55,17 @@ impl From<plantuml_editor_core::StatusLevel> for MessageLevel {
     }
 }
 
+/// What the sidebar lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SidebarTab {
+    /// Slots in browser storage
+    Local,
+    /// Folder tree of server documents
+    Server,
+    /// Recent changes to server documents
+    Activity,
+}
+
 /// Get CSS class for message level
 fn get_message_class(level: MessageLevel) -> &'static str {
     match level {
@@ -42,6 +75,50 @@ fn get_message_class(level: MessageLevel) -> &'static str {
     }
 }
 
+/// How long the "元に戻す" action stays available after deleting a slot (ms)
+const UNDO_DELETE_MS: u32 = 10_000;
+
+/// How long the live preview waits for the API server before giving up
+pub const PREVIEW_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// Non-empty value of the query parameter `name` in `search` ("?a=1&b=2")
+fn query_value<'a>(search: &'a str, name: &str) -> Option<&'a str> {
+    search.trim_start_matches('?').split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name && !value.is_empty()).then_some(value)
+    })
+}
+
+/// Server document named by the `doc` query parameter (e.g., "?doc=<id>")
+fn document_id_from_query(search: &str) -> Option<String> {
+    query_value(search, "doc").map(str::to_string)
+}
+
+/// Local slot named by the `slot` query parameter (e.g., "?slot=3"), used to
+/// open a slot in a new tab
+fn slot_from_query(search: &str) -> Option<usize> {
+    query_value(search, "slot")?
+        .parse()
+        .ok()
+        .filter(|slot| (1..=plantuml_editor_core::StorageSlot::MAX_SLOTS as usize).contains(slot))
+}
+
+/// Whether the editor has changes not in the slot it was saved to or loaded
+/// from (an empty editor has nothing to lose)
+fn has_unsaved_changes(text: &str, saved_text: &str) -> bool {
+    !text.trim().is_empty() && text != saved_text
+}
+
+/// Data URL showing a small SVG image (icon and theme previews), without scripts
+///
+/// The diagram preview uses object URLs instead (see `object_url`).
+pub(crate) fn svg_data_url(svg_text: &str) -> String {
+    format!(
+        "data:image/svg+xml;charset=utf-8,{}",
+        urlencoding::encode(&plantuml_editor_core::svg::sanitize(svg_text))
+    )
+}
+
 /// Application properties for dependency injection
 #[derive(Properties, PartialEq, Clone)]
 pub struct AppProps<B: StorageBackend + PartialEq + 'static> {
@@ -66,75 +143,257 @@ impl<B: StorageBackend + PartialEq + 'static> Default for AppProps<B> {
 /// - Tests can inject MockStorageBackend
 #[function_component(App)]
 pub fn app<B: StorageBackend + PartialEq + 'static>(props: &AppProps<B>) -> Html {
-    use plantuml_editor_api_client::{convert_plantuml, export_plantuml};
-    use plantuml_editor_core::{ImageFormat, ProcessResult};
+    use plantuml_editor_core::{validate_plantuml_content, ErrorCode, ImageFormat, ProcessResult};
     use wasm_bindgen_futures::spawn_local;
 
     // Dependency Injection: Get StorageService from props
+    // (none in a VS Code webview, where documents are the extension's files)
     let storage_service = props.storage_service.clone();
+    let has_local_storage = storage_service.is_some();
 
     let plantuml_text = use_state(String::new);
     let editor_key = use_state(|| 0);
-    let image_data = use_state(|| None::<String>);
+    let image_data = use_state(|| None::<object_url::PreviewImage>);
+    // Error of the last conversion, shown over the previous image
+    let preview_error = use_state(|| None::<String>);
+    // Keep the last good image dimmed when a conversion fails
+    let keep_stale_preview = use_state(|| true);
+    let render_stats = use_state(|| None::<RenderStats>);
+    // Format requested for the preview; PNG keeps very large diagrams responsive
+    let preview_format = use_state(|| ImageFormat::Svg);
+    // How the preview shows the diagram (image, inline SVG or text)
+    let preview_renderer = use_state(PreviewRenderer::default);
+    // Font family for previews and exports (None for the server default)
+    let render_font = use_state(|| None::<String>);
+    // Set when the server warns that the SVG preview is large (LargeSvg)
+    let offer_png_preview = use_state(|| false);
+    // Page shown in the preview (0-based) and rendered pages by source and page
+    let current_page = use_state(|| 0usize);
+    let preview_cache = use_mut_ref(preview_cache::ConversionCache::default);
+    let preview_tracker = use_mut_ref(preview_cache::PreviewTracker::default);
     let loading = use_state(|| false);
     let sidebar_collapsed = use_state(|| false);
     let message = use_state(|| "".to_string());
     let message_level = use_state(|| MessageLevel::Info);
+    let batch_items = use_state(|| None::<Vec<plantuml_editor_core::BatchExportItem>>);
+    // Document that the "元に戻す" action restores, and the timer that hides it
+    let undo_target = use_state(|| None::<plantuml_editor_core::DocumentId>);
+    let undo_timeout = use_state(|| None::<gloo_timers::callback::Timeout>);
+    // Set when a server document is opened without permission to edit it
+    let read_only = use_state(|| false);
+    // Spellcheck only notes and labels (see components::editor)
+    let prose_spellcheck = use_state(|| false);
+    // Indentation for Tab and Shift+Tab in the editor
+    let indent_unit = use_state(plantuml_editor_core::indent::IndentUnit::default);
+    // Enter after a message starts the next one with the same participants
+    let prefill_messages = use_state(|| false);
+    // Tidy the text when saving (BOM, line endings and, unless switched off,
+    // trailing whitespace; see plantuml_editor_core::normalize)
+    let normalize_on_save = use_state(|| false);
+    let trim_trailing_whitespace = use_state(|| true);
+    // Exported images carry their source (see plantuml_editor_core::embed)
+    let embed_source = use_state(|| false);
+    // Pause after typing before the preview is rendered (see idle)
+    let render_pause_ms = use_state(|| plantuml_editor_core::DEFAULT_RENDER_PAUSE_MS);
+    // Settings as last saved or synced (see settings)
+    let synced_settings = use_mut_ref(plantuml_editor_core::SettingsSnapshot::default);
+    // CodeMirror when built in and loaded, otherwise the textarea
+    let editor_kind = use_state(editor_backend::EditorKind::preferred);
+    // Syntax error the server reported for the text, marked in the editor
+    let syntax_marker = use_state(|| None::<plantuml_editor_core::Diagnostic>);
+    // Static diagnostics marked in the editor
+    let editor_markers = use_memo((*plantuml_text).clone(), |text| {
+        if text.is_empty() {
+            Vec::new()
+        } else {
+            plantuml_editor_core::diagnostics::lint(text)
+        }
+    });
+    // Last caret position reported by the editor (UTF-16 offset)
+    let editor_cursor = use_mut_ref(|| None::<usize>);
+    let editor_selection = use_mut_ref(|| None::<(usize, usize)>);
+    // Server document in the editor, as loaded or last saved
+    let server_document = use_state(|| None::<plantuml_editor_core::PlantUMLDocument>);
+    // Editor text as last saved to or loaded from a slot
+    let saved_text = use_state(String::new);
+    // Slot waiting for the replace / new tab choice over unsaved changes
+    let pending_load = use_state(|| None::<usize>);
+    // Newer server version found when saving over someone else's changes
+    let conflict = use_state(|| None::<plantuml_editor_core::PlantUMLDocument>);
+    // Guided tour, started by itself on the first visit (browser only: a
+    // webview has no storage to remember it in)
+    let tour_open = use_state(|| has_local_storage && !components::tour::tour_seen());
+    // Messages shown recently and the bug report dialog (see diagnostic_bundle)
+    let result_history = use_mut_ref(diagnostic_bundle::ResultHistory::default);
+    let report_open = use_state(|| false);
+    // User logged in to server storage (see auth)
+    let auth_user = use_state(auth::user_name);
+    let sidebar_tab = use_state(|| {
+        if has_local_storage {
+            SidebarTab::Local
+        } else {
+            SidebarTab::Server
+        }
+    });
 
-    let on_text_change = {
-        let plantuml_text = plantuml_text.clone();
+    // Shows one page of the document, from the cache when it was rendered before
+    let render_preview = {
+        let preview_cache = preview_cache.clone();
+        let preview_tracker = preview_tracker.clone();
         let image_data = image_data.clone();
+        let preview_error = preview_error.clone();
+        let keep_stale = *keep_stale_preview;
+        let format = preview_renderer.format(*preview_format);
+        let font = (*render_font).clone();
+        let offer_png_preview = offer_png_preview.clone();
+        let render_stats = render_stats.clone();
         let loading = loading.clone();
         let message = message.clone();
         let message_level = message_level.clone();
-
-        Callback::from(move |text: String| {
-            plantuml_text.set(text.clone());
+        let syntax_marker = syntax_marker.clone();
+
+        Callback::from(move |(text, page): (String, usize)| {
+            // Keyed by the page's own source, so edits elsewhere keep it cached
+            let source = plantuml_editor_core::pages::split_pages(&text)
+                .into_iter()
+                .nth(page)
+                .unwrap_or_else(|| text.clone());
+            let key = CacheKey::new(&source, page, format).with_font(font.as_deref());
+            // Already on screen: keep the preview and its message as they are
+            if !preview_tracker.borrow_mut().request(key) {
+                loading.set(false);
+                return;
+            }
+            syntax_marker.set(None);
+            if let Some(image) = preview_cache.borrow_mut().get(&key) {
+                preview_tracker.borrow_mut().rendered(key);
+                image_data.set(Some(image));
+                preview_error.set(None);
+                loading.set(false);
+                return;
+            }
+            let preview_cache = preview_cache.clone();
+            let preview_tracker = preview_tracker.clone();
             let image_data = image_data.clone();
+            let preview_error = preview_error.clone();
+            let offer_png_preview = offer_png_preview.clone();
+            let render_stats = render_stats.clone();
             let loading = loading.clone();
             let message = message.clone();
             let message_level = message_level.clone();
+            let font = font.clone();
+            let syntax_marker = syntax_marker.clone();
 
             loading.set(true);
 
             spawn_local(async move {
-                match convert_plantuml(text, ImageFormat::Svg).await {
+                let client = host::api_client()
+                    .with_timeout(PREVIEW_TIMEOUT)
+                    .with_font(font);
+                let started = js_sys::Date::now();
+                let response = client.convert(source, format).await;
+                let render_ms = js_sys::Date::now() - started;
+                // Sanitize before checking for newer text, which may change meanwhile
+                let response = match response {
                     Ok((bytes, result)) => {
-                        // SVG is text-based, convert to string and create data URL
-                        match String::from_utf8(bytes) {
-                            Ok(svg_text) => {
-                                let data_url = format!(
-                                    "data:image/svg+xml;charset=utf-8,{}",
-                                    urlencoding::encode(&svg_text)
-                                );
-                                image_data.set(Some(data_url));
+                        let image_bytes = bytes.len();
+                        // The server may answer a large SVG with a PNG (SvgDowngraded)
+                        let is_png = format == ImageFormat::Png
+                            || matches!(result.code, ErrorCode::SvgDowngraded { .. });
+                        let image = if is_png {
+                            object_url::ObjectUrl::from_png(&bytes).ok().map(Rc::new)
+                        } else if format == ImageFormat::Txt {
+                            String::from_utf8(bytes)
+                                .ok()
+                                .and_then(|text| object_url::ObjectUrl::from_text(&text).ok())
+                                .map(Rc::new)
+                        } else {
+                            match String::from_utf8(bytes) {
+                                Ok(svg_text) => {
+                                    let svg_text = svg_worker::sanitize(svg_text).await;
+                                    object_url::ObjectUrl::from_svg(&svg_text).ok().map(Rc::new)
+                                }
+                                Err(_) => None,
+                            }
+                        };
+                        Ok((image_bytes, image, result))
+                    }
+                    Err(e) => Err(e),
+                };
+                // Dim the last image under the error, or clear it
+                let show_error = |text: String| {
+                    if keep_stale {
+                        preview_error.set(Some(text));
+                    } else {
+                        image_data.set(None);
+                    }
+                };
+                if !preview_tracker.borrow().is_current(key) {
+                    // The text changed while waiting; keep the image for later
+                    if let Ok((_, Some(image), _)) = response {
+                        preview_cache.borrow_mut().insert(key, image.clone(), image.size());
+                    }
+                    return;
+                }
+                match response {
+                    Ok((image_bytes, image, result)) => {
+                        render_stats.set(Some(RenderStats {
+                            render_ms,
+                            image_bytes,
+                        }));
+                        match image {
+                            Some(image) => {
+                                telemetry::record(TelemetryEvent::Conversion);
+                                preview_cache.borrow_mut().insert(key, image.clone(), image.size());
+                                preview_tracker.borrow_mut().rendered(key);
+                                image_data.set(Some(image));
+                                preview_error.set(None);
+                                offer_png_preview
+                                    .set(matches!(result.code, ErrorCode::LargeSvg { .. }));
 
                                 // Set success message
                                 message.set(result.message());
                                 message_level.set(result.level.into());
                             }
-                            Err(_) => {
+                            None => {
+                                preview_tracker.borrow_mut().failed();
                                 message.set("SVG変換エラー".to_string());
                                 message_level.set(MessageLevel::Error);
-                                image_data.set(None);
+                                show_error("SVG変換エラー".to_string());
                             }
                         }
                     }
                     Err(e) => {
                         use plantuml_editor_api_client::ApiError;
 
+                        preview_tracker.borrow_mut().failed();
                         match e {
                             ApiError::ProcessError(code) => {
+                                telemetry::record(TelemetryEvent::Error(&code));
+                                if let ErrorCode::ParseError { line } = code {
+                                    use plantuml_editor_core::{diagnostics, pages};
+                                    // Lines are the page's, one-based
+                                    let line = line.map(|line| {
+                                        pages::document_line(&text, page, line.saturating_sub(1)) + 1
+                                    });
+                                    let message = "PlantUMLがこの行を解釈できませんでした";
+                                    syntax_marker.set(Some(diagnostics::syntax_error(&text, line, message)));
+                                }
+                                // Keep the last preview while the server is busy, slow or paused
+                                let keep_preview = code.is_transient();
                                 let result = ProcessResult::new(code);
                                 message.set(result.message());
                                 message_level.set(result.level.into());
+                                if !keep_preview {
+                                    show_error(result.message());
+                                }
                             }
                             _ => {
                                 message.set(e.to_string());
                                 message_level.set(MessageLevel::Error);
+                                show_error(e.to_string());
                             }
                         }
-                        image_data.set(None);
                     }
                 }
                 loading.set(false);
@@ -142,85 +401,492 @@ pub fn app<B: StorageBackend + PartialEq + 'static>(props: &AppProps<B>) -> Html
         })
     };
 
+    let on_text_change = {
+        let plantuml_text = plantuml_text.clone();
+        let current_page = current_page.clone();
+        let render_preview = render_preview.clone();
+
+        Callback::from(move |text: String| {
+            use plantuml_editor_core::pages::{changed_page, page_count};
+
+            // Follow the edit to its page; only that page is re-rendered
+            let page = changed_page(&plantuml_text, &text)
+                .unwrap_or(*current_page)
+                .min(page_count(&text) - 1);
+            plantuml_text.set(text.clone());
+            current_page.set(page);
+            if host::is_webview() {
+                webview::notify_changed(&text);
+            }
+            render_preview.emit((text, page));
+        })
+    };
+
+    let on_toggle_spellcheck = {
+        let prose_spellcheck = prose_spellcheck.clone();
+        Callback::from(move |_| prose_spellcheck.set(!*prose_spellcheck))
+    };
+
+    let on_toggle_stale_preview = {
+        let keep_stale_preview = keep_stale_preview.clone();
+        let image_data = image_data.clone();
+        let preview_error = preview_error.clone();
+        Callback::from(move |_| {
+            // Without the mode a failed conversion leaves no image
+            if *keep_stale_preview && preview_error.is_some() {
+                image_data.set(None);
+                preview_error.set(None);
+            }
+            keep_stale_preview.set(!*keep_stale_preview);
+        })
+    };
+
+    // Switch the preview between SVG and PNG, its renderer, or the font; the
+    // effect below re-renders it
+    let on_preview_format = {
+        let preview_format = preview_format.clone();
+        let offer_png_preview = offer_png_preview.clone();
+        move |format: ImageFormat| {
+            let preview_format = preview_format.clone();
+            let offer_png_preview = offer_png_preview.clone();
+            Callback::from(move |_| {
+                offer_png_preview.set(false);
+                preview_format.set(format);
+            })
+        }
+    };
+    let on_preview_renderer = {
+        let preview_renderer = preview_renderer.clone();
+        Callback::from(move |renderer: PreviewRenderer| preview_renderer.set(renderer))
+    };
+    {
+        let plantuml_text = plantuml_text.clone();
+        let current_page = current_page.clone();
+        let render_preview = render_preview.clone();
+        use_effect_with((*preview_format, *preview_renderer, (*render_font).clone()), move |_| {
+            // Nothing to show before the first edit
+            if !plantuml_text.is_empty() {
+                render_preview.emit(((*plantuml_text).clone(), *current_page));
+            }
+            || ()
+        });
+    }
+
+    let on_font_change = {
+        let render_font = render_font.clone();
+        Callback::from(move |font: Option<String>| render_font.set(font))
+    };
+
+    let on_toggle_prefill = {
+        let prefill_messages = prefill_messages.clone();
+        Callback::from(move |_| prefill_messages.set(!*prefill_messages))
+    };
+
+    let on_indent_change = {
+        let indent_unit = indent_unit.clone();
+        Callback::from(move |e: Event| {
+            use plantuml_editor_core::indent::IndentUnit;
+
+            let value = e
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .value();
+            if let Some(unit) = IndentUnit::CHOICES.into_iter().find(|unit| unit.label() == value) {
+                indent_unit.set(unit);
+            }
+        })
+    };
+
+    let on_toggle_normalize = {
+        let normalize_on_save = normalize_on_save.clone();
+        Callback::from(move |_| normalize_on_save.set(!*normalize_on_save))
+    };
+
+    let on_toggle_trim = {
+        let trim_trailing_whitespace = trim_trailing_whitespace.clone();
+        Callback::from(move |_| trim_trailing_whitespace.set(!*trim_trailing_whitespace))
+    };
+
+    let on_toggle_embed_source = {
+        let embed_source = embed_source.clone();
+        Callback::from(move |_| embed_source.set(!*embed_source))
+    };
+
+    let on_render_pause_change = {
+        let render_pause_ms = render_pause_ms.clone();
+        Callback::from(move |e: Event| {
+            let value = e
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .value();
+            if let Ok(pause_ms) = value.parse() {
+                render_pause_ms.set(pause_ms);
+            }
+        })
+    };
+
+    // Settings that follow the user between browsers (see settings)
+    let user_settings = plantuml_editor_core::UserSettings {
+        preview_format: *preview_format,
+        font: (*render_font).clone(),
+        keep_stale_preview: *keep_stale_preview,
+        spellcheck: *prose_spellcheck,
+        indent_width: settings::indent_width(*indent_unit),
+        prefill_messages: *prefill_messages,
+        render_pause_ms: *render_pause_ms,
+        normalize_on_save: *normalize_on_save,
+        trim_trailing_whitespace: *trim_trailing_whitespace,
+        embed_source: *embed_source,
+    };
+    let apply_settings = {
+        let synced_settings = synced_settings.clone();
+        let preview_format = preview_format.clone();
+        let render_font = render_font.clone();
+        let keep_stale_preview = keep_stale_preview.clone();
+        let prose_spellcheck = prose_spellcheck.clone();
+        let indent_unit = indent_unit.clone();
+        let prefill_messages = prefill_messages.clone();
+        let render_pause_ms = render_pause_ms.clone();
+        let normalize_on_save = normalize_on_save.clone();
+        let trim_trailing_whitespace = trim_trailing_whitespace.clone();
+        let embed_source = embed_source.clone();
+        Callback::from(move |snapshot: plantuml_editor_core::SettingsSnapshot| {
+            let settings = &snapshot.settings;
+            preview_format.set(settings.preview_format);
+            render_font.set(settings.font.clone());
+            keep_stale_preview.set(settings.keep_stale_preview);
+            prose_spellcheck.set(settings.spellcheck);
+            indent_unit.set(settings::indent_unit(settings));
+            prefill_messages.set(settings.prefill_messages);
+            render_pause_ms.set(settings.render_pause_ms);
+            normalize_on_save.set(settings.normalize_on_save);
+            trim_trailing_whitespace.set(settings.trim_trailing_whitespace);
+            embed_source.set(settings.embed_source);
+            *synced_settings.borrow_mut() = snapshot;
+        })
+    };
+    // Save changes made here, stamped with the time of the change; settings
+    // applied from a sync are already saved
+    {
+        let synced_settings = synced_settings.clone();
+        let apply_settings = apply_settings.clone();
+        use_effect_with(user_settings.clone(), move |user_settings| {
+            idle::set_pause_ms(user_settings.render_pause_ms);
+            if *user_settings != synced_settings.borrow().settings {
+                let snapshot = plantuml_editor_core::SettingsSnapshot {
+                    settings: user_settings.clone(),
+                    updated_at: chrono::Utc::now().timestamp_millis(),
+                };
+                *synced_settings.borrow_mut() = snapshot.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let current = settings::save(&settings::LocalSettings, settings::server(), snapshot.clone()).await;
+                    if current != snapshot {
+                        apply_settings.emit(current);
+                    }
+                });
+            }
+            || ()
+        });
+    }
+    // Load the settings on start and pick up the server's after logging in
+    {
+        let synced_settings = synced_settings.clone();
+        let apply_settings = apply_settings.clone();
+        use_effect_with((*auth_user).clone(), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(current) = settings::sync(&settings::LocalSettings, settings::server()).await {
+                    if current != *synced_settings.borrow() {
+                        apply_settings.emit(current);
+                    }
+                }
+            });
+            || ()
+        });
+    }
+
+    let on_page_change = {
+        let plantuml_text = plantuml_text.clone();
+        let current_page = current_page.clone();
+        let render_preview = render_preview.clone();
+
+        Callback::from(move |page: usize| {
+            current_page.set(page);
+            render_preview.emit(((*plantuml_text).clone(), page));
+        })
+    };
+
     let on_export = {
         let plantuml_text = plantuml_text.clone();
+        let font = (*render_font).clone();
+        let embed_source = *embed_source;
+        let image_data = image_data.clone();
         let message = message.clone();
         let message_level = message_level.clone();
 
-        Callback::from(move |format: ImageFormat| {
+        Callback::from(move |(format, scale): (ImageFormat, plantuml_editor_core::ExportScale)| {
+            telemetry::record(TelemetryEvent::Feature("export"));
             let text = (*plantuml_text).clone();
-            let msg = message.clone();
-            let msg_level = message_level.clone();
 
-            spawn_local(async move {
-                match export_plantuml(text, format).await {
-                    Ok((bytes, result)) => {
-                        // Update message based on export result
-                        msg.set(result.message());
-                        msg_level.set(result.level.into());
-
-                        // Download the file
-                        let blob_parts = js_sys::Array::new();
-                        let uint8_array = js_sys::Uint8Array::from(&bytes[..]);
-                        blob_parts.push(&uint8_array);
-
-                        let options = web_sys::BlobPropertyBag::new();
-                        let mime_type = match format {
-                            ImageFormat::Png => "image/png",
-                            ImageFormat::Svg => "image/svg+xml",
-                        };
-                        options.set_type(mime_type);
-
-                        if let Ok(blob) = web_sys::Blob::new_with_u8_array_sequence_and_options(
-                            &blob_parts,
-                            &options,
-                        ) {
-                            let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
-
-                            let window = web_sys::window().unwrap();
-                            let document = window.document().unwrap();
-                            let anchor = document.create_element("a").unwrap();
-                            let anchor = anchor.dyn_into::<web_sys::HtmlAnchorElement>().unwrap();
-
-                            let extension = match format {
-                                ImageFormat::Png => "png",
-                                ImageFormat::Svg => "svg",
-                            };
-                            let filename = format!("diagram.{}", extension);
-
-                            anchor.set_href(&url);
-                            anchor.set_download(&filename);
-                            anchor.click();
-
-                            web_sys::Url::revoke_object_url(&url).unwrap();
-                        }
+            // Check locally first
+            if let Err(e) = validate_plantuml_content(&text) {
+                let result = ProcessResult::new(e.to_error_code());
+                message.set(result.message());
+                message_level.set(result.level.into());
+                return;
+            }
+
+            let form_format = match format {
+                ImageFormat::Svg => Some("svg"),
+                ImageFormat::Pdf => Some("pdf"),
+                ImageFormat::Png | ImageFormat::Txt => None,
+            };
+            if let Some(form_format) = form_format.filter(|_| !host::is_webview()) {
+                // The download itself is left to the browser
+                let mut fields = vec![
+                    ("plantuml_text", text.as_str()),
+                    ("format", form_format),
+                    ("scale", scale.as_str()),
+                ];
+                if let Some(font) = &font {
+                    fields.push(("font", font.as_str()));
+                }
+                if embed_source {
+                    fields.push(("embed_source", "true"));
+                }
+                let action = host::api_client().export_download_url();
+                match download::submit_download_form(&action, &fields) {
+                    Ok(()) => {
+                        let result = ProcessResult::new(ErrorCode::ExportOk);
+                        message.set(result.message());
+                        message_level.set(result.level.into());
                     }
-                    Err(e) => {
-                        // Display error message from ProcessResult if available
-                        use plantuml_editor_api_client::ApiError;
-                        match e {
-                            ApiError::ProcessError(code) => {
-                                let result = ProcessResult::new(code);
-                                msg.set(result.message());
-                                msg_level.set(result.level.into());
-                            }
-                            _ => {
-                                // For network/server errors, display as-is
-                                msg.set(format!("エクスポートエラー: {}", e));
-                                msg_level.set(MessageLevel::Error);
-                            }
-                        }
+                    Err(_) => {
+                        message.set("エクスポートエラー: ダウンロードを開始できませんでした".to_string());
+                        message_level.set(MessageLevel::Error);
                     }
                 }
+                return;
+            }
+
+            // PNG is fetched so a server failure can fall back to the SVG preview
+            // (and so are SVG and PDF in a webview, where the extension saves the file)
+            // The text preview cannot be rasterized
+            let svg_preview = (*image_data)
+                .clone()
+                .filter(|image| format == ImageFormat::Png && image.mime() != "text/plain");
+            let message = message.clone();
+            let message_level = message_level.clone();
+            let font = font.clone();
+            spawn_local(async move {
+                let file_name = canvas_export::image_file_name(&text, format);
+                let (mime_type, format_label) = match format {
+                    ImageFormat::Png => ("image/png", "PNG"),
+                    ImageFormat::Svg => ("image/svg+xml", "SVG"),
+                    ImageFormat::Txt => ("text/plain", "TXT"),
+                    ImageFormat::Pdf => ("application/pdf", "PDF"),
+                };
+                let exported = host::api_client()
+                    .with_font(font)
+                    .with_embed_source(embed_source)
+                    .export_scaled(text, format, scale)
+                    .await;
+
+                let result = match exported {
+                    Ok((bytes, result)) => download::download_bytes(&bytes, mime_type, &file_name)
+                        .map(|()| result)
+                        .map_err(|_| ErrorCode::ExportError { format: format_label.to_string() }),
+                    Err(e) => match svg_preview {
+                        Some(svg) => {
+                            let rasterized = canvas_export::rasterize_svg(svg.as_str(), scale.factor()).await;
+                            rasterized
+                                .and_then(|blob| download::download_blob(&blob, &file_name))
+                                .map(|()| ProcessResult::new(ErrorCode::RasterizedExport))
+                                .map_err(|_| ErrorCode::ExportError { format: "PNG".to_string() })
+                        }
+                        None => Err(match e {
+                            plantuml_editor_api_client::ApiError::ProcessError(code) => code,
+                            _ => ErrorCode::ExportError { format: format_label.to_string() },
+                        }),
+                    },
+                };
+
+                let result = result.unwrap_or_else(ProcessResult::new);
+                message.set(result.message());
+                message_level.set(result.level.into());
             });
         })
     };
 
+    // Text to save, normalized in the editor first when the user asked for it
+    let text_to_save = {
+        let plantuml_text = plantuml_text.clone();
+        let editor_key = editor_key.clone();
+        let editor_cursor = editor_cursor.clone();
+        let editor_selection = editor_selection.clone();
+        let normalize_on_save = *normalize_on_save;
+        let options = plantuml_editor_core::normalize::NormalizeOptions {
+            trim_trailing_whitespace: *trim_trailing_whitespace,
+        };
+        move || {
+            let text = (*plantuml_text).clone();
+            if !normalize_on_save {
+                return text;
+            }
+            let normalized = plantuml_editor_core::normalize::normalize(&text, options);
+            if *normalized == *text {
+                return text;
+            }
+            let normalized = normalized.into_owned();
+            plantuml_text.set(normalized.clone());
+            // The editor is recreated with the new text, as on import
+            editor_key.set(*editor_key + 1);
+            *editor_cursor.borrow_mut() = None;
+            *editor_selection.borrow_mut() = None;
+            normalized
+        }
+    };
+
     let on_save = {
         let storage_service = storage_service.clone();
+        let text_to_save = text_to_save.clone();
+        let saved_text = saved_text.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |slot: usize| {
+            use plantuml_editor_core::ErrorCode;
+            use plantuml_editor_storageservice::{
+                storage_error_to_result, storage_success_result,
+            };
+
+            // Use injected storage service
+            if let Some(service) = &storage_service {
+                let plantuml_text = text_to_save();
+                // Report a full storage before writing anything
+                let saved = service
+                    .check_capacity(slot, &plantuml_text)
+                    .and_then(|()| service.save_to_slot(slot, &plantuml_text));
+                let result = match saved {
+                    Ok(_) => {
+                        saved_text.set(plantuml_text);
+                        storage_success_result(ErrorCode::SaveSuccess { slot_number: slot as u8 }, slot as u8)
+                    }
+                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    let on_save_error = {
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |error: SaveValidationError| {
+            use plantuml_editor_core::{ErrorCode, ProcessResult};
+            use plantuml_editor_storageservice::storage_error_to_result;
+
+            let result = match error {
+                SaveValidationError::EmptyContent => {
+                    ProcessResult::new(ErrorCode::ValidationEmpty)
+                }
+                SaveValidationError::ContentTooLarge(actual_length) => {
+                    ProcessResult::new(ErrorCode::StorageInputLimit {
+                        actual: actual_length,
+                        max: plantuml_editor_core::MAX_CONTENT_CHARS,
+                    })
+                }
+                SaveValidationError::StorageError(storage_error) => {
+                    storage_error_to_result(&storage_error, None)
+                }
+            };
+
+            message.set(result.message());
+            message_level.set(result.level.into());
+        })
+    };
+
+    let load_slot = {
+        let storage_service = storage_service.clone();
+        let plantuml_text = plantuml_text.clone();
+        let saved_text = saved_text.clone();
+        let editor_key = editor_key.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |slot: usize| {
+            use plantuml_editor_core::ErrorCode;
+            use plantuml_editor_storageservice::{
+                storage_error_to_result, storage_success_result,
+            };
+
+            // Use injected storage service
+            if let Some(service) = &storage_service {
+                let result = match service.load_from_slot(slot) {
+                    Ok(Some(text)) => {
+                        saved_text.set(text.clone());
+                        plantuml_text.set(text);
+                        editor_key.set(*editor_key + 1);
+                        storage_success_result(ErrorCode::LoadSuccess { slot_number: slot as u8 }, slot as u8)
+                    }
+                    Ok(None) => {
+                        ProcessResult::new(ErrorCode::StorageReadError {
+                            reason: "スロットにデータがありません".to_string(),
+                        })
+                    }
+                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    // Loading over unsaved changes asks first (see LoadChoiceDialog)
+    let on_load = {
+        let load_slot = load_slot.clone();
         let plantuml_text = plantuml_text.clone();
+        let saved_text = saved_text.clone();
+        let pending_load = pending_load.clone();
+        Callback::from(move |slot: usize| {
+            if has_unsaved_changes(&plantuml_text, &saved_text) {
+                pending_load.set(Some(slot));
+            } else {
+                load_slot.emit(slot);
+            }
+        })
+    };
+    let on_load_replace = {
+        let load_slot = load_slot.clone();
+        let pending_load = pending_load.clone();
+        Callback::from(move |_| {
+            if let Some(slot) = *pending_load {
+                load_slot.emit(slot);
+            }
+            pending_load.set(None);
+        })
+    };
+    let on_load_new_tab = {
+        let pending_load = pending_load.clone();
+        Callback::from(move |_| {
+            if let (Some(slot), Some(window)) = (*pending_load, web_sys::window()) {
+                let _ = window.open_with_url_and_target(&format!("?slot={}", slot), "_blank");
+            }
+            pending_load.set(None);
+        })
+    };
+    let on_load_cancel = {
+        let pending_load = pending_load.clone();
+        Callback::from(move |_| pending_load.set(None))
+    };
+
+    let on_delete = {
+        let storage_service = storage_service.clone();
         let message = message.clone();
         let message_level = message_level.clone();
+        let undo_target = undo_target.clone();
+        let undo_timeout = undo_timeout.clone();
 
         Callback::from(move |slot: usize| {
             use plantuml_editor_core::ErrorCode;
@@ -228,107 +894,713 @@ pub fn app<B: StorageBackend + PartialEq + 'static>(props: &AppProps<B>) -> Html
                 storage_error_to_result, storage_success_result,
             };
 
-            // Use injected storage service
-            if let Some(service) = &storage_service {
-                let result = match service.save_to_slot(slot, &plantuml_text) {
-                    Ok(_) => storage_success_result(ErrorCode::SaveSuccess { slot_number: slot as u8 }, slot as u8),
-                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
-                };
+            // Use injected storage service
+            if let Some(service) = &storage_service {
+                let result = match service.delete_slot(slot) {
+                    Ok(false) => {
+                        // The trash was full; nothing to undo
+                        undo_target.set(None);
+                        ProcessResult::new(ErrorCode::DeletedWithoutTrash { slot_number: slot as u8 })
+                    }
+                    Ok(true) => {
+                        // The slot was moved to the trash; offer to undo for a while
+                        let deleted = service
+                            .list_trash()
+                            .into_iter()
+                            .find(|item| item.slot_number == slot as u8);
+                        undo_target.set(deleted.map(|item| item.document_id));
+                        let undo_target = undo_target.clone();
+                        undo_timeout.set(Some(gloo_timers::callback::Timeout::new(
+                            UNDO_DELETE_MS,
+                            move || undo_target.set(None),
+                        )));
+                        storage_success_result(ErrorCode::DeleteSuccess { slot_number: slot as u8 }, slot as u8)
+                    }
+                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+                // Note: SlotList will automatically refresh via its internal state
+            }
+        })
+    };
+
+    // Hover preview in the slot list, read without loading into the editor
+    let load_slot_preview = {
+        let storage_service = storage_service.clone();
+        Callback::from(move |slot: usize| {
+            storage_service.as_ref()?.load_from_slot(slot).ok().flatten()
+        })
+    };
+
+    let on_move = {
+        let storage_service = storage_service.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |(from, to): (usize, usize)| {
+            use plantuml_editor_storageservice::{
+                storage_error_to_result, storage_success_result,
+            };
+
+            if let Some(service) = &storage_service {
+                let result = match service.move_slot(from, to) {
+                    Ok(()) => storage_success_result(
+                        ErrorCode::MoveSuccess { from_slot: from as u8, to_slot: to as u8 },
+                        to as u8,
+                    ),
+                    Err(e) => storage_error_to_result(&e, Some(from as u8)),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    let on_pin = {
+        let storage_service = storage_service.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |(slot, pinned): (usize, bool)| {
+            use plantuml_editor_storageservice::storage_error_to_result;
+
+            if let Some(service) = &storage_service {
+                if let Err(e) = service.set_pinned(slot, pinned) {
+                    let result = storage_error_to_result(&e, Some(slot as u8));
+                    message.set(result.message());
+                    message_level.set(result.level.into());
+                }
+            }
+        })
+    };
+
+    let on_restore = {
+        let storage_service = storage_service.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+        let undo_target = undo_target.clone();
+
+        Callback::from(move |document_id: plantuml_editor_core::DocumentId| {
+            use plantuml_editor_storageservice::{
+                storage_error_to_result, storage_success_result,
+            };
+
+            if let Some(service) = &storage_service {
+                let result = match service.restore_from_trash(document_id) {
+                    Ok(slot) => storage_success_result(
+                        ErrorCode::RestoreSuccess { slot_number: slot as u8 },
+                        slot as u8,
+                    ),
+                    Err(e) => storage_error_to_result(&e, None),
+                };
+
+                undo_target.set(None);
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    let on_undo_delete = {
+        let undo_target = undo_target.clone();
+        let on_restore = on_restore.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(document_id) = *undo_target {
+                on_restore.emit(document_id);
+            }
+        })
+    };
+
+    let on_purge = {
+        let storage_service = storage_service.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |document_id: Option<plantuml_editor_core::DocumentId>| {
+            use plantuml_editor_storageservice::storage_error_to_result;
+
+            if let Some(service) = &storage_service {
+                let result = match service.purge_trash(document_id) {
+                    Ok(()) if document_id.is_none() => ProcessResult::new(ErrorCode::TrashEmptied),
+                    Ok(()) => return,
+                    Err(e) => storage_error_to_result(&e, None),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    let on_duplicate = {
+        let storage_service = storage_service.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |slot: usize| {
+            use plantuml_editor_core::ErrorCode;
+            use plantuml_editor_storageservice::{
+                storage_error_to_result, storage_success_result,
+            };
+
+            if let Some(service) = &storage_service {
+                let result = match service.duplicate_slot(slot) {
+                    Ok(to) => storage_success_result(
+                        ErrorCode::DuplicateSuccess { from_slot: slot as u8, to_slot: to as u8 },
+                        to as u8,
+                    ),
+                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
+                };
+
+                message.set(result.message());
+                message_level.set(result.level.into());
+            }
+        })
+    };
+
+    let on_import = {
+        let plantuml_text = plantuml_text.clone();
+        let editor_key = editor_key.clone();
+        let editor_cursor = editor_cursor.clone();
+        let editor_selection = editor_selection.clone();
+        let on_text_change = on_text_change.clone();
+
+        Callback::from(move |text: String| {
+            plantuml_text.set(text.clone());
+            editor_key.set(*editor_key + 1);
+            // The editor is recreated, so its caret and selection start over
+            *editor_cursor.borrow_mut() = None;
+            *editor_selection.borrow_mut() = None;
+            // Render the imported diagram immediately
+            on_text_change.emit(text);
+        })
+    };
+
+    // Open a source saved elsewhere (a server document, a file, a URL), so
+    // the editor starts without unsaved changes
+    let open_saved = {
+        let saved_text = saved_text.clone();
+        let on_import = on_import.clone();
+        Callback::from(move |text: String| {
+            saved_text.set(text.clone());
+            on_import.emit(text);
+        })
+    };
+
+    let on_cursor = {
+        let editor_cursor = editor_cursor.clone();
+        Callback::from(move |cursor: usize| *editor_cursor.borrow_mut() = Some(cursor))
+    };
+
+    let on_selection = {
+        let editor_selection = editor_selection.clone();
+        Callback::from(move |selection: (usize, usize)| *editor_selection.borrow_mut() = Some(selection))
+    };
+
+    // Insert an icon where the caret was, or before @end when it is unknown
+    let on_insert_icon = {
+        let plantuml_text = plantuml_text.clone();
+        let editor_cursor = editor_cursor.clone();
+        let on_import = on_import.clone();
+
+        Callback::from(move |icon: plantuml_editor_core::icons::Icon| {
+            telemetry::record(TelemetryEvent::Feature("icon"));
+            let cursor = *editor_cursor.borrow();
+            let text = plantuml_editor_core::icons::insert_icon(&plantuml_text, cursor, &icon);
+            on_import.emit(text);
+        })
+    };
+
+    // Show an ErrorCode reported by a child component
+    let on_error_code = {
+        let message = message.clone();
+        let message_level = message_level.clone();
+
+        Callback::from(move |code: plantuml_editor_core::ErrorCode| {
+            telemetry::record(TelemetryEvent::Error(&code));
+            let result = ProcessResult::new(code);
+            message.set(result.message());
+            message_level.set(result.level.into());
+        })
+    };
+
+    // Open a file dropped on the editor (see file_import)
+    let on_file_drag_over = Callback::from(|e: DragEvent| {
+        if file_import::drags_files(&e) {
+            e.prevent_default();
+        }
+    });
+    let on_file_drop = {
+        let open_saved = open_saved.clone();
+        let on_error_code = on_error_code.clone();
+        let read_only = *read_only;
+        Callback::from(move |e: DragEvent| {
+            let Some(file) = file_import::dropped_file(&e) else {
+                return;
+            };
+            e.prevent_default();
+            if read_only {
+                return;
+            }
+            let open_saved = open_saved.clone();
+            let on_error_code = on_error_code.clone();
+            spawn_local(async move {
+                match file_import::read_dropped_file(&file).await {
+                    Ok(text) => open_saved.emit(text),
+                    Err(code) => on_error_code.emit(code),
+                }
+            });
+        })
+    };
+
+    // Rename the participant or class at the caret everywhere it is referenced
+    let on_rename_at_cursor = {
+        let plantuml_text = plantuml_text.clone();
+        let editor_cursor = editor_cursor.clone();
+        let on_import = on_import.clone();
+        let on_error_code = on_error_code.clone();
+
+        Callback::from(move |_| {
+            use plantuml_editor_core::refactor::{name_at, RenameError};
+
+            let name = (*editor_cursor.borrow()).and_then(|cursor| name_at(&plantuml_text, cursor));
+            let Some(name) = name else {
+                return on_error_code.emit(RenameError::NoElementAtCursor.to_error_code());
+            };
+            match components::participant_panel::prompt_rename(&plantuml_text, &name) {
+                Some(Ok(text)) => on_import.emit(text),
+                Some(Err(e)) => on_error_code.emit(e.to_error_code()),
+                None => {}
+            }
+        })
+    };
+
+    // Move the selected lines into a named fragment and call it in their place
+    let on_extract_selection = {
+        let plantuml_text = plantuml_text.clone();
+        let editor_selection = editor_selection.clone();
+        let on_import = on_import.clone();
+        let on_error_code = on_error_code.clone();
+
+        Callback::from(move |_| {
+            use plantuml_editor_core::refactor::{extract_fragment, ExtractError};
+
+            let Some((start, end)) = *editor_selection.borrow() else {
+                return on_error_code.emit(ExtractError::EmptySelection.to_error_code());
+            };
+            let name = web_sys::window().and_then(|window| {
+                window
+                    .prompt_with_message_and_default("抜き出す部分の名前（英数字と _）", "fragment")
+                    .ok()
+                    .flatten()
+            });
+            let Some(name) = name else { return };
+            match extract_fragment(&plantuml_text, start, end, &name) {
+                Ok(text) => on_import.emit(text),
+                Err(e) => on_error_code.emit(e.to_error_code()),
+            }
+        })
+    };
+
+    // Open a server document, read-only for viewers
+    let open_server_document = {
+        let open_saved = open_saved.clone();
+        let on_error_code = on_error_code.clone();
+        let read_only = read_only.clone();
+        let server_document = server_document.clone();
+
+        Callback::from(move |id: String| {
+            let open_saved = open_saved.clone();
+            let on_error_code = on_error_code.clone();
+            let read_only = read_only.clone();
+            let server_document = server_document.clone();
+            spawn_local(async move {
+                match host::api_client().get_document(&id).await {
+                    Ok((document, role)) => {
+                        read_only.set(!role.can_edit());
+                        open_saved.emit(document.content.clone());
+                        server_document.set(Some(document));
+                    }
+                    Err(e) => {
+                        on_error_code.emit(errors::api_error_to_code(e, "/api/v1/documents"));
+                    }
+                }
+            });
+        })
+    };
+
+    // Save the editor source over the server document at the given version;
+    // a newer version saved by someone else opens the conflict dialog
+    let save_to_server = {
+        let server_document = server_document.clone();
+        let saved_text = saved_text.clone();
+        let conflict = conflict.clone();
+        let on_error_code = on_error_code.clone();
+
+        Callback::from(move |(base, content): (plantuml_editor_core::PlantUMLDocument, String)| {
+            let server_document = server_document.clone();
+            let saved_text = saved_text.clone();
+            let conflict = conflict.clone();
+            let on_error_code = on_error_code.clone();
+            spawn_local(async move {
+                use plantuml_editor_api_client::ApiError;
+                use plantuml_editor_core::ErrorCode;
+
+                let client = host::api_client();
+                let id = base.id.0.to_string();
+                // Only the changed lines are sent
+                match client.patch_document(&base, &content, base.title.clone()).await {
+                    Ok((document, result)) => {
+                        saved_text.set(content);
+                        server_document.set(Some(document));
+                        on_error_code.emit(result.code);
+                    }
+                    Err(ApiError::ProcessError(code @ ErrorCode::DocumentConflict { .. })) => {
+                        match client.get_document(&id).await {
+                            Ok((theirs, _)) => conflict.set(Some(theirs)),
+                            Err(e) => on_error_code.emit(errors::api_error_to_code(e, "/api/v1/documents")),
+                        }
+                        on_error_code.emit(code);
+                    }
+                    Err(e) => on_error_code.emit(errors::api_error_to_code(e, "/api/v1/documents")),
+                }
+            });
+        })
+    };
+
+    let on_server_save = {
+        let server_document = server_document.clone();
+        let text_to_save = text_to_save.clone();
+        let save_to_server = save_to_server.clone();
+        Callback::from(move |_: MouseEvent| {
+            if let Some(base) = (*server_document).clone() {
+                save_to_server.emit((base, text_to_save()));
+            }
+        })
+    };
+
+    // Download the open server document with its history as a ZIP
+    let on_export_archive = {
+        let server_document = server_document.clone();
+        let on_error_code = on_error_code.clone();
+        Callback::from(move |_: MouseEvent| {
+            let Some(document) = (*server_document).clone() else {
+                return;
+            };
+            let on_error_code = on_error_code.clone();
+            spawn_local(async move {
+                let id = document.id.0.to_string();
+                match host::api_client().export_document_archive(&id).await {
+                    Ok(archive) => {
+                        let stem = document
+                            .title
+                            .as_deref()
+                            .and_then(plantuml_editor_core::metadata::file_stem_from_title)
+                            .unwrap_or_else(|| "document".to_string());
+                        let file_name = format!("{}.zip", stem);
+                        if download::download_bytes(&archive, "application/zip", &file_name).is_err() {
+                            on_error_code.emit(plantuml_editor_core::ErrorCode::ExportError {
+                                format: "ZIP".to_string(),
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        on_error_code.emit(errors::api_error_to_code(e, "/api/v1/documents"));
+                    }
+                }
+            });
+        })
+    };
+
+    // Conflict dialog actions; each one continues from the newer server version
+    let on_conflict_overwrite = {
+        let conflict = conflict.clone();
+        let plantuml_text = plantuml_text.clone();
+        let save_to_server = save_to_server.clone();
+        Callback::from(move |_| {
+            if let Some(theirs) = (*conflict).clone() {
+                save_to_server.emit((theirs, (*plantuml_text).clone()));
+            }
+            conflict.set(None);
+        })
+    };
+    let on_conflict_take_theirs = {
+        let conflict = conflict.clone();
+        let server_document = server_document.clone();
+        let open_saved = open_saved.clone();
+        Callback::from(move |_| {
+            if let Some(theirs) = (*conflict).clone() {
+                open_saved.emit(theirs.content.clone());
+                server_document.set(Some(theirs));
+            }
+            conflict.set(None);
+        })
+    };
+    let on_conflict_merge = {
+        let conflict = conflict.clone();
+        let server_document = server_document.clone();
+        let on_import = on_import.clone();
+        Callback::from(move |merged: String| {
+            if let Some(theirs) = (*conflict).clone() {
+                server_document.set(Some(theirs));
+            }
+            on_import.emit(merged);
+            conflict.set(None);
+        })
+    };
+    let on_conflict_cancel = {
+        let conflict = conflict.clone();
+        Callback::from(move |_| conflict.set(None))
+    };
+
+    // Open the diagram of a share link ("#uml=..."), else the server document
+    // in "?doc=<id>" or the slot in "?slot=<n>" (a slot opened in a new tab)
+    {
+        let open_server_document = open_server_document.clone();
+        let load_slot = load_slot.clone();
+        let sidebar_tab = sidebar_tab.clone();
+        let plantuml_text = plantuml_text.clone();
+        let saved_text = saved_text.clone();
+        let editor_key = editor_key.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+        use_effect_with((), move |_| {
+            let search = web_sys::window()
+                .and_then(|window| window.location().search().ok())
+                .unwrap_or_default();
+            if let Some(text) = sharing::take_shared_source() {
+                // The link keeps the source, so there is nothing to lose yet
+                saved_text.set(text.clone());
+                plantuml_text.set(text);
+                editor_key.set(*editor_key + 1);
+                message.set("共有リンクの図を開きました".to_string());
+                message_level.set(MessageLevel::Info);
+            } else if let Some(id) = document_id_from_query(&search) {
+                sidebar_tab.set(SidebarTab::Server);
+                open_server_document.emit(id);
+            } else if let Some(slot) = slot_from_query(&search) {
+                load_slot.emit(slot);
+            }
+            || ()
+        });
+    }
+
+    // Finish an OIDC login when the provider returned to the page, or keep
+    // the tokens of an earlier login fresh
+    {
+        let auth_user = auth_user.clone();
+        let sidebar_tab = sidebar_tab.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+        use_effect_with((), move |_| {
+            wasm_bindgen_futures::spawn_local(async move {
+                match auth::complete_login().await {
+                    Some(Ok(())) => {
+                        auth_user.set(auth::user_name());
+                        sidebar_tab.set(SidebarTab::Server);
+                        message.set("ログインしました".to_string());
+                        message_level.set(MessageLevel::Info);
+                    }
+                    Some(Err(e)) => {
+                        message.set(format!("ログインに失敗しました: {}", e));
+                        message_level.set(MessageLevel::Warning);
+                    }
+                    None => auth::schedule_refresh(),
+                }
+            });
+            || ()
+        });
+    }
+    let on_login = {
+        let message = message.clone();
+        let message_level = message_level.clone();
+        Callback::from(move |_| {
+            let message = message.clone();
+            let message_level = message_level.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = auth::login().await {
+                    message.set(format!("ログインを開始できませんでした: {}", e));
+                    message_level.set(MessageLevel::Warning);
+                }
+            });
+        })
+    };
+    let on_logout = {
+        let auth_user = auth_user.clone();
+        Callback::from(move |_| {
+            auth_user.set(None);
+            wasm_bindgen_futures::spawn_local(auth::logout());
+        })
+    };
+
+    // Restore the text saved when the app crashed before the last reload
+    {
+        let on_import = on_import.clone();
+        let message = message.clone();
+        let message_level = message_level.clone();
+        use_effect_with((), move |_| {
+            if !host::is_webview() {
+                if let Some(text) = crash::take_recovered() {
+                    on_import.emit(text);
+                    message.set("エラーで中断する前の編集内容を復元しました".to_string());
+                    message_level.set(MessageLevel::Warning);
+                }
+            }
+            || ()
+        });
+    }
+
+    // Keep the text for the panic hook to save
+    use_effect_with((*plantuml_text).clone(), |text| crash::track_content(text));
 
-                message.set(result.message());
-                message_level.set(result.level.into());
+    // In a webview, the extension loads its document into the editor
+    {
+        let open_saved = open_saved.clone();
+        use_effect_with((), move |_| {
+            if host::is_webview() {
+                webview::set_load_handler(open_saved);
             }
-        })
-    };
+            || ()
+        });
+    }
 
-    let on_save_error = {
+    // Export the given slots as one ZIP through the batch export dialog
+    let on_bulk_export = {
+        let storage_service = storage_service.clone();
+        let batch_items = batch_items.clone();
         let message = message.clone();
         let message_level = message_level.clone();
 
-        Callback::from(move |error: SaveValidationError| {
-            use plantuml_editor_core::{ErrorCode, ProcessResult};
-            use plantuml_editor_storageservice::storage_error_to_result;
+        Callback::from(move |slots: Vec<usize>| {
+            use plantuml_editor_core::{BatchExportItem, ErrorCode};
 
-            let result = match error {
-                SaveValidationError::EmptyContent => {
-                    ProcessResult::new(ErrorCode::ValidationEmpty)
-                }
-                SaveValidationError::ContentTooLarge(actual_length) => {
-                    ProcessResult::new(ErrorCode::StorageInputLimit {
-                        actual: actual_length,
-                        max: 24000,
+            if let Some(service) = &storage_service {
+                let items: Vec<BatchExportItem> = slots
+                    .into_iter()
+                    .filter_map(|slot| {
+                        let text = service.load_from_slot(slot).ok().flatten()?;
+                        Some(BatchExportItem {
+                            name: format!("slot{:02}.puml", slot),
+                            plantuml_text: text,
+                        })
                     })
+                    .collect();
+
+                if items.is_empty() {
+                    let result = ProcessResult::new(ErrorCode::StorageReadError {
+                        reason: "保存済みのスロットがありません".to_string(),
+                    });
+                    message.set(result.message());
+                    message_level.set(result.level.into());
+                } else {
+                    batch_items.set(Some(items));
                 }
-                SaveValidationError::StorageError(storage_error) => {
-                    storage_error_to_result(&storage_error, None)
-                }
-            };
-
-            message.set(result.message());
-            message_level.set(result.level.into());
+            }
         })
     };
 
-    let on_load = {
+    let on_batch_export = on_bulk_export.reform(|_: MouseEvent| (1..=10).collect());
+
+    let on_bulk_delete = {
         let storage_service = storage_service.clone();
-        let plantuml_text = plantuml_text.clone();
-        let editor_key = editor_key.clone();
         let message = message.clone();
         let message_level = message_level.clone();
+        let undo_target = undo_target.clone();
 
-        Callback::from(move |slot: usize| {
-            use plantuml_editor_core::ErrorCode;
-            use plantuml_editor_storageservice::{
-                storage_error_to_result, storage_success_result,
-            };
+        Callback::from(move |slots: Vec<usize>| {
+            use plantuml_editor_storageservice::storage_error_to_result;
 
-            // Use injected storage service
             if let Some(service) = &storage_service {
-                let result = match service.load_from_slot(slot) {
-                    Ok(Some(text)) => {
-                        plantuml_text.set(text);
-                        editor_key.set(*editor_key + 1);
-                        storage_success_result(ErrorCode::LoadSuccess { slot_number: slot as u8 }, slot as u8)
+                // Stop at the first failure; slots deleted so far stay in the trash
+                let mut untrashed = None;
+                let failure = slots.iter().find_map(|&slot| match service.delete_slot(slot) {
+                    Ok(trashed) => {
+                        if !trashed {
+                            untrashed.get_or_insert(slot as u8);
+                        }
+                        None
                     }
-                    Ok(None) => {
-                        ProcessResult::new(ErrorCode::StorageReadError {
-                            reason: "スロットにデータがありません".to_string(),
-                        })
+                    Err(e) => Some(storage_error_to_result(&e, Some(slot as u8))),
+                });
+                let result = failure.unwrap_or_else(|| match untrashed {
+                    // The trash was full, so some of them cannot be restored
+                    Some(slot_number) => {
+                        ProcessResult::new(ErrorCode::DeletedWithoutTrash { slot_number })
                     }
-                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
-                };
+                    None => ProcessResult::new(ErrorCode::BulkDeleteSuccess { count: slots.len() }),
+                });
 
+                // Several slots were deleted; restore them from the trash panel instead
+                undo_target.set(None);
                 message.set(result.message());
                 message_level.set(result.level.into());
             }
         })
     };
 
-    let on_delete = {
-        let storage_service = storage_service.clone();
+    // Slide mode: every page of the document as a numbered PNG in one ZIP
+    let on_export_slides = {
+        let plantuml_text = plantuml_text.clone();
+        let batch_items = batch_items.clone();
+        Callback::from(move |_: ()| {
+            telemetry::record(TelemetryEvent::Feature("slides"));
+            batch_items.set(Some(plantuml_editor_core::pages::slide_items(&plantuml_text)));
+        })
+    };
+
+    // Copy a link that carries the source in its fragment (see `sharing`)
+    let on_share_link = {
+        let plantuml_text = plantuml_text.clone();
         let message = message.clone();
         let message_level = message_level.clone();
+        Callback::from(move |_: MouseEvent| {
+            use plantuml_editor_core::{ErrorCode, ProcessResult};
 
-        Callback::from(move |slot: usize| {
-            use plantuml_editor_core::ErrorCode;
-            use plantuml_editor_storageservice::{
-                storage_error_to_result, storage_success_result,
-            };
-
-            // Use injected storage service
-            if let Some(service) = &storage_service {
-                let result = match service.delete_slot(slot) {
-                    Ok(_) => storage_success_result(ErrorCode::DeleteSuccess { slot_number: slot as u8 }, slot as u8),
-                    Err(e) => storage_error_to_result(&e, Some(slot as u8)),
-                };
-
+            if plantuml_text.trim().is_empty() {
+                let result = ProcessResult::new(ErrorCode::ValidationEmpty);
                 message.set(result.message());
                 message_level.set(result.level.into());
-                // Note: SlotList will automatically refresh via its internal state
+                return;
             }
+            let (Some(url), Some(window)) = (sharing::share_url(&plantuml_text), web_sys::window()) else {
+                message.set("共有リンクを作成できませんでした".to_string());
+                message_level.set(MessageLevel::Warning);
+                return;
+            };
+            telemetry::record(TelemetryEvent::Feature("share-link"));
+            let copy = window.navigator().clipboard().write_text(&url);
+            let message = message.clone();
+            let message_level = message_level.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(copy).await {
+                    Ok(_) if url.chars().count() > sharing::LONG_URL_CHARS => {
+                        message.set(format!(
+                            "共有リンクをコピーしました（{}文字）。長いリンクはチャットやメールで途中までしか送れない場合があります",
+                            url.chars().count()
+                        ));
+                        message_level.set(MessageLevel::Warning);
+                    }
+                    Ok(_) => {
+                        message.set("共有リンクをコピーしました".to_string());
+                        message_level.set(MessageLevel::Info);
+                    }
+                    Err(_) => {
+                        message.set("共有リンクをクリップボードにコピーできませんでした".to_string());
+                        message_level.set(MessageLevel::Warning);
+                    }
+                }
+            });
         })
     };
 
+    let close_batch_export = {
+        let batch_items = batch_items.clone();
+        Callback::from(move |_| batch_items.set(None))
+    };
+
     let toggle_sidebar = {
         let sidebar_collapsed = sidebar_collapsed.clone();
         Callback::from(move |_| {
@@ -336,6 +1608,61 @@ pub fn app<B: StorageBackend + PartialEq + 'static>(props: &AppProps<B>) -> Html
         })
     };
 
+    let show_tab = |tab: SidebarTab| {
+        let sidebar_tab = sidebar_tab.clone();
+        let read_only = read_only.clone();
+        let server_document = server_document.clone();
+        Callback::from(move |_: MouseEvent| {
+            sidebar_tab.set(tab);
+            // Local slots are always editable and not tied to a server document
+            if tab == SidebarTab::Local {
+                read_only.set(false);
+                server_document.set(None);
+            }
+        })
+    };
+    // Remember each message for bug reports
+    {
+        let result_history = result_history.clone();
+        use_effect_with(((*message).clone(), *message_level), move |(message, level)| {
+            if !message.is_empty() {
+                result_history.borrow_mut().push(diagnostic_bundle::ResultEntry {
+                    at: chrono::Local::now().to_rfc3339(),
+                    level: *level,
+                    message: message.clone(),
+                });
+            }
+        });
+    }
+    let on_report = {
+        let report_open = report_open.clone();
+        Callback::from(move |_| {
+            telemetry::record(TelemetryEvent::Feature("report"));
+            report_open.set(true)
+        })
+    };
+    let close_report = {
+        let report_open = report_open.clone();
+        Callback::from(move |_| report_open.set(false))
+    };
+    let on_start_tour = {
+        let tour_open = tour_open.clone();
+        Callback::from(move |_| {
+            telemetry::record(TelemetryEvent::Feature("tour"));
+            tour_open.set(true)
+        })
+    };
+    let on_close_tour = {
+        let tour_open = tour_open.clone();
+        Callback::from(move |_| {
+            components::tour::mark_tour_seen();
+            tour_open.set(false);
+        })
+    };
+    // Server storage lists nothing until the user logs in
+    let needs_login = auth::is_enabled() && auth_user.is_none();
+    let tab_class = |tab: SidebarTab| classes!("storage-tab", (*sidebar_tab == tab).then_some("active"));
+
     html! {
         <div class="app-container">
             // サイドバー（保存一覧表示）
@@ -345,49 +1672,296 @@ pub fn app<B: StorageBackend + PartialEq + 'static>(props: &AppProps<B>) -> Html
                     <span class="sidebar-toggle">{ "◀" }</span>
                 </div>
                 <div class="sidebar-content">
-                    <SlotList on_load={on_load} on_delete={on_delete} />
+                    <div class="storage-tabs">
+                        if has_local_storage {
+                            <button class={tab_class(SidebarTab::Local)} onclick={show_tab(SidebarTab::Local)}>
+                                { "ローカル" }
+                            </button>
+                        }
+                        <button class={tab_class(SidebarTab::Server)} onclick={show_tab(SidebarTab::Server)}>
+                            { "サーバー" }
+                        </button>
+                        <button class={tab_class(SidebarTab::Activity)} onclick={show_tab(SidebarTab::Activity)}>
+                            { "最近の更新" }
+                        </button>
+                    </div>
+                    if *sidebar_tab != SidebarTab::Local && auth::is_enabled() {
+                        <AccountBar user={(*auth_user).clone()} on_login={on_login} on_logout={on_logout} />
+                    }
+                    if *sidebar_tab == SidebarTab::Server {
+                        if !needs_login {
+                            <DocumentSearch
+                                on_open={open_server_document.clone()}
+                                on_error={on_error_code.clone()}
+                            />
+                            <DocumentTree on_open={open_server_document} on_error={on_error_code.clone()} />
+                        }
+                    } else if *sidebar_tab == SidebarTab::Activity {
+                        if !needs_login {
+                            <ActivityFeed on_open={open_server_document} on_error={on_error_code.clone()} />
+                        }
+                    } else {
+                        <SlotList
+                            on_load={on_load}
+                            on_delete={on_delete}
+                            on_duplicate={on_duplicate}
+                            on_move={on_move}
+                            on_pin={on_pin}
+                            on_bulk_delete={on_bulk_delete}
+                            on_bulk_export={on_bulk_export}
+                            load_preview={load_slot_preview}
+                        />
+                        <TrashPanel on_restore={on_restore} on_purge={on_purge} />
+                        <div class="sidebar-actions">
+                            <button class="batch-export-btn" onclick={on_batch_export}>
+                                { "全スロットを一括エクスポート" }
+                            </button>
+                        </div>
+                    }
                 </div>
             </div>
 
             // メインコンテンツ
             <div class="main-content">
+                // 運用者からのお知らせ・メンテナンス中の表示
+                <StatusBanner />
                 // 処理メッセージ
                 <div class="message-area">
                     <div class={get_message_class(*message_level)}>{ &*message }</div>
+                    if undo_target.is_some() {
+                        <button class="undo-button" onclick={on_undo_delete}>{ "元に戻す" }</button>
+                    }
+                    if *preview_format == ImageFormat::Png {
+                        <button class="undo-button" onclick={on_preview_format(ImageFormat::Svg)}>
+                            { "SVGで表示" }
+                        </button>
+                    } else if *offer_png_preview {
+                        <button class="undo-button" onclick={on_preview_format(ImageFormat::Png)}>
+                            { "PNGで表示" }
+                        </button>
+                    }
                 </div>
 
                 // エディタとプレビューコンテナ
                 <div class="editor-preview-container">
-                    // PlantUMLソース編集エディタ
-                    <div class="editor-area">
-                        <div class="editor-header">{ "PlantUMLソース" }</div>
+                    // PlantUMLソース編集エディタ (.puml ファイルのドロップで開く)
+                    <div class="editor-area" ondragover={on_file_drag_over} ondrop={on_file_drop}>
+                        <div class="editor-header">
+                            <span>{ "PlantUMLソース" }</span>
+                            if !*read_only {
+                                <ThemePicker
+                                    plantuml_text={(*plantuml_text).clone()}
+                                    on_apply={on_import.clone()}
+                                />
+                                <ImportMenu on_import={on_import.clone()} on_open={open_saved.clone()} on_error={on_error_code.clone()} />
+                                <IconPicker on_insert={on_insert_icon} />
+                                <button
+                                    class="theme-gallery-btn"
+                                    title="カーソル位置の参加者・クラスの名前を、参照ごとまとめて変更します"
+                                    onclick={on_rename_at_cursor}
+                                >
+                                    { "名前の変更" }
+                                </button>
+                                <button
+                                    class="theme-gallery-btn"
+                                    title="選択した行を !startsub の部品に抜き出し、呼び出しに置き換えます"
+                                    onclick={on_extract_selection}
+                                >
+                                    { "部品に抜き出し" }
+                                </button>
+                            }
+                            <label class="palette-toggle" title="ノートやラベルの文章だけをスペルチェックします">
+                                <input
+                                    type="checkbox"
+                                    checked={*prose_spellcheck}
+                                    onchange={on_toggle_spellcheck}
+                                />
+                                { "文章のみスペルチェック" }
+                            </label>
+                            <label class="palette-toggle" title="メッセージの行で Enter を押すと、同じ参加者と矢印を次の行に入力します">
+                                <input
+                                    type="checkbox"
+                                    checked={*prefill_messages}
+                                    onchange={on_toggle_prefill}
+                                />
+                                { "矢印を引き継ぐ" }
+                            </label>
+                            <label class="palette-toggle" title="保存するときに BOM を除き、改行を LF に統一します">
+                                <input
+                                    type="checkbox"
+                                    checked={*normalize_on_save}
+                                    onchange={on_toggle_normalize}
+                                />
+                                { "保存時に整える" }
+                            </label>
+                            if *normalize_on_save {
+                                <label class="palette-toggle" title="保存時に各行の末尾の空白・タブも削除します">
+                                    <input
+                                        type="checkbox"
+                                        checked={*trim_trailing_whitespace}
+                                        onchange={on_toggle_trim}
+                                    />
+                                    { "行末の空白を削除" }
+                                </label>
+                            }
+                            <select
+                                class="theme-select"
+                                title="Tab・Shift+Tab で増減するインデント"
+                                onchange={on_indent_change}
+                            >
+                                { for plantuml_editor_core::indent::IndentUnit::CHOICES.iter().map(|unit| html! {
+                                    <option value={unit.label()} selected={*unit == *indent_unit}>
+                                        { format!("インデント: {}", unit.label()) }
+                                    </option>
+                                }) }
+                            </select>
+                            <select
+                                class="theme-select"
+                                title="入力が止まってからプレビューを更新するまでの時間"
+                                onchange={on_render_pause_change}
+                            >
+                                { for settings::RENDER_PAUSE_CHOICES.iter().map(|pause_ms| html! {
+                                    <option value={pause_ms.to_string()} selected={*pause_ms == *render_pause_ms}>
+                                        { format!("更新待ち: {}ms", pause_ms) }
+                                    </option>
+                                }) }
+                            </select>
+                            <FontPicker font={(*render_font).clone()} on_change={on_font_change} />
+                        </div>
                         <Editor
                             key={*editor_key}
                             value={(*plantuml_text).clone()}
                             on_change={on_text_change}
+                            read_only={*read_only}
+                            prose_spellcheck={*prose_spellcheck}
+                            on_cursor={on_cursor}
+                            on_selection={on_selection}
+                            indent_unit={*indent_unit}
+                            prefill_messages={*prefill_messages}
+                            kind={*editor_kind}
+                            markers={editor_markers
+                                .iter()
+                                .cloned()
+                                .chain((*syntax_marker).clone())
+                                .collect::<Vec<_>>()}
                         />
                         <div class="editor-actions">
-                            <SaveButton
+                            if has_local_storage {
+                                <SaveButton
+                                    plantuml_text={(*plantuml_text).clone()}
+                                    on_save={on_save}
+                                    on_error={on_save_error}
+                                />
+                            }
+                            if server_document.is_some() && !*read_only {
+                                <button class="server-save-btn" onclick={on_server_save}>
+                                    { "サーバーに保存" }
+                                </button>
+                            }
+                            if server_document.is_some() {
+                                <button class="server-archive-btn" onclick={on_export_archive}>
+                                    { "履歴ごとエクスポート" }
+                                </button>
+                            }
+                        </div>
+                        <StatsPanel
+                            plantuml_text={(*plantuml_text).clone()}
+                            render={*render_stats}
+                        />
+                        if !*read_only {
+                            <ParticipantPanel
                                 plantuml_text={(*plantuml_text).clone()}
-                                on_save={on_save}
-                                on_error={on_save_error}
+                                on_apply={on_import.clone()}
+                                on_error={on_error_code.clone()}
                             />
-                        </div>
+                        }
                     </div>
 
                     // ダイアグラム図プレビュー
                     <div class="preview-area">
                         <div class="preview-header">
                             <span>{ "プレビュー" }</span>
-                            <ExportButtons on_export={on_export} />
+                            <label class="palette-toggle" title="変換に失敗したとき、直前の図を薄く表示したままにします">
+                                <input
+                                    type="checkbox"
+                                    checked={*keep_stale_preview}
+                                    onchange={on_toggle_stale_preview}
+                                />
+                                { "エラー時も前の図を表示" }
+                            </label>
+                            <ExportButtons
+                                on_export={on_export}
+                                page_count={plantuml_editor_core::pages::page_count(&plantuml_text)}
+                                on_export_slides={on_export_slides}
+                                embed_source={*embed_source}
+                                on_toggle_embed_source={on_toggle_embed_source}
+                            />
+                            if !host::is_webview() {
+                                <button
+                                    class="export-btn share-link-btn"
+                                    onclick={on_share_link}
+                                    title="図のソースを含むリンクをコピーします。ソースはサーバーに送信されません"
+                                >
+                                    { "リンクを共有" }
+                                </button>
+                            }
                         </div>
                         <Preview
                             image_data={(*image_data).clone()}
+                            error={(*preview_error).clone()}
                             loading={*loading}
+                            page={*current_page}
+                            page_count={plantuml_editor_core::pages::page_count(&plantuml_text)}
+                            on_page_change={on_page_change}
+                            renderer={*preview_renderer}
+                            on_renderer_change={on_preview_renderer}
                         />
                     </div>
                 </div>
             </div>
+
+            if let (Some(base), Some(theirs)) = ((*server_document).clone(), (*conflict).clone()) {
+                <ConflictDialog
+                    base={base.content}
+                    mine={(*plantuml_text).clone()}
+                    {theirs}
+                    on_overwrite={on_conflict_overwrite}
+                    on_take_theirs={on_conflict_take_theirs}
+                    on_merge={on_conflict_merge}
+                    on_cancel={on_conflict_cancel}
+                />
+            }
+
+            if let Some(slot) = *pending_load {
+                <LoadChoiceDialog
+                    {slot}
+                    on_replace={on_load_replace}
+                    on_new_tab={on_load_new_tab}
+                    on_cancel={on_load_cancel}
+                />
+            }
+
+            if let Some(items) = (*batch_items).clone() {
+                <BatchExportDialog
+                    {items}
+                    format={ImageFormat::Png}
+                    on_error={on_error_code.clone()}
+                    on_close={close_batch_export}
+                />
+            }
+
+            <HelpMenu on_start_tour={on_start_tour} on_report={on_report} />
+            if *report_open {
+                <ReportDialog
+                    source={(*plantuml_text).clone()}
+                    history={result_history.borrow().entries()}
+                    on_error={on_error_code}
+                    on_close={close_report}
+                />
+            }
+            if *tour_open {
+                <Tour on_close={on_close_tour} />
+            }
         </div>
     }
 }
@@ -407,7 +1981,35 @@ pub fn app_with_local_storage() -> Html {
     
     // Call the generic app function with concrete type
     html! {
-        <App<LocalStorageBackend> storage_service={props.storage_service} />
+        <crash::ErrorBoundary>
+            <App<LocalStorageBackend> storage_service={props.storage_service} />
+        </crash::ErrorBoundary>
+    }
+}
+
+/// App for a VS Code webview: no browser storage, so no local slots
+#[function_component(WebviewApp)]
+pub fn webview_app() -> Html {
+    use plantuml_editor_storageservice::LocalStorageBackend;
+
+    html! {
+        <crash::ErrorBoundary>
+            <App<LocalStorageBackend> />
+        </crash::ErrorBoundary>
+    }
+}
+
+/// Start the editor in the page body, as a browser app or in a VS Code
+/// webview depending on the injected configuration (see `host`)
+pub fn mount() {
+    crash::install();
+    match host::init().host {
+        host::HostKind::Browser => {
+            yew::Renderer::<AppWithLocalStorage>::new().render();
+        }
+        host::HostKind::Vscode => {
+            yew::Renderer::<WebviewApp>::new().render();
+        }
     }
 }
 
@@ -421,6 +2023,31 @@ mod tests {
     // StatusLevel から MessageLevel への変換が正しく動作することを検証
     // ========================================
 
+    #[test]
+    fn test_document_id_from_query() {
+        assert_eq!(document_id_from_query("?doc=abc"), Some("abc".to_string()));
+        assert_eq!(document_id_from_query("?page=2&doc=abc"), Some("abc".to_string()));
+        assert_eq!(document_id_from_query("?doc="), None);
+        assert_eq!(document_id_from_query(""), None);
+    }
+
+    #[test]
+    fn test_slot_from_query() {
+        assert_eq!(slot_from_query("?slot=3"), Some(3));
+        assert_eq!(slot_from_query("?doc=abc&slot=10"), Some(10));
+        assert_eq!(slot_from_query("?slot=0"), None);
+        assert_eq!(slot_from_query("?slot=11"), None);
+        assert_eq!(slot_from_query("?slot=x"), None);
+    }
+
+    #[test]
+    fn test_has_unsaved_changes() {
+        assert!(!has_unsaved_changes("", ""));
+        assert!(!has_unsaved_changes("  \n", "@startuml"));
+        assert!(!has_unsaved_changes("@startuml\n@enduml", "@startuml\n@enduml"));
+        assert!(has_unsaved_changes("@startuml\nA -> B\n@enduml", "@startuml\n@enduml"));
+    }
+
     #[test]
     fn test_message_level_from_status_level_info() {
         // StatusLevel::Info が MessageLevel::Info に変換されることを確認
@@ -569,8 +2196,16 @@ mod storage_tests {
         impl StorageBackend for StorageBackend {
             fn save_to_slot(&self, slot_number: usize, text: &str) -> Result<(), StorageError>;
             fn load_from_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
+            fn load_raw_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
             fn list_slots(&self) -> Vec<plantuml_editor_storageservice::SlotInfo>;
-            fn delete_slot(&self, slot_number: usize) -> Result<(), StorageError>;
+            fn delete_slot(&self, slot_number: usize) -> Result<bool, StorageError>;
+            fn set_pinned(&self, slot_number: usize, pinned: bool) -> Result<(), StorageError>;
+            fn copy_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+            fn move_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+            fn list_trash(&self) -> Vec<plantuml_editor_storageservice::TrashInfo>;
+            fn restore_from_trash(&self, document_id: plantuml_editor_core::DocumentId) -> Result<usize, StorageError>;
+            fn purge_trash(&self, document_id: Option<plantuml_editor_core::DocumentId>) -> Result<(), StorageError>;
+            fn free_space(&self, wanted: usize) -> Option<usize>;
         }
     }
 
@@ -764,6 +2399,35 @@ mod storage_tests {
         }
     }
 
+    #[test]
+    fn test_load_corrupted_slot() {
+        // 壊れたスロットは空扱いにせず、原因付きの読み込みエラーになることを確認
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_load_from_slot()
+            .times(1)
+            .returning(|_| Err(StorageError::SlotCorrupted {
+                slot: 4,
+                reason: "EOF while parsing".to_string(),
+            }));
+        mock_backend
+            .expect_load_raw_slot()
+            .times(1)
+            .returning(|_| Ok(Some("{\"slot_number\": 4, \"docum".to_string())));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        let error = service.load_from_slot(4).unwrap_err();
+        let process_result = storage_error_to_result(&error, Some(4));
+        assert_eq!(process_result.level, StatusLevel::Error);
+        assert!(process_result.message().contains("スロット4のデータが壊れています"));
+
+        // 復旧画面では保存されている文字列をそのまま見られる
+        assert_eq!(
+            service.load_raw_slot(4).unwrap().as_deref(),
+            Some("{\"slot_number\": 4, \"docum")
+        );
+    }
+
     #[test]
     fn test_load_updates_editor_key_logic() {
         // 読み込み成功時に editor_key がインクリメントされることを確認
@@ -780,6 +2444,113 @@ mod storage_tests {
         assert_eq!(editor_key, 6);
     }
 
+    // ========================================
+    // on_duplicate 相当のテスト
+    // ========================================
+
+    #[test]
+    fn test_duplicate_uses_first_empty_slot() {
+        // 空きスロット（3）が複製先になることを確認
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_load_from_slot()
+            .returning(|slot| Ok((slot < 3).then(|| "@startuml\n@enduml".to_string())));
+        mock_backend
+            .expect_copy_slot()
+            .with(mockall::predicate::eq(1), mockall::predicate::eq(3))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        assert_eq!(service.duplicate_slot(1).unwrap(), 3);
+
+        let process_result = storage_success_result(
+            ErrorCode::DuplicateSuccess { from_slot: 1, to_slot: 3 },
+            3
+        );
+        assert_eq!(process_result.level, StatusLevel::Info);
+        assert_eq!(process_result.message(), "スロット1をスロット3に複製しました");
+    }
+
+    #[test]
+    fn test_duplicate_when_slots_full() {
+        // 空きスロットがない場合は StorageSlotLimit になることを確認
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_load_from_slot()
+            .returning(|_| Ok(Some("@startuml\n@enduml".to_string())));
+        mock_backend.expect_copy_slot().never();
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        let error = service.duplicate_slot(1).unwrap_err();
+        let process_result = storage_error_to_result(&error, Some(1));
+        assert!(matches!(process_result.code, ErrorCode::StorageSlotLimit { max_slots: 10 }));
+    }
+
+    // ========================================
+    // ゴミ箱（元に戻す）のテスト
+    // ========================================
+
+    #[test]
+    fn test_restore_from_trash_returns_restore_success() {
+        let document_id = plantuml_editor_core::DocumentId::new();
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_restore_from_trash()
+            .with(mockall::predicate::eq(document_id))
+            .times(1)
+            .returning(|_| Ok(4));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        let slot = service.restore_from_trash(document_id).unwrap();
+        let process_result = storage_success_result(
+            ErrorCode::RestoreSuccess { slot_number: slot as u8 },
+            slot as u8
+        );
+        assert_eq!(process_result.level, StatusLevel::Info);
+        assert_eq!(process_result.message(), "削除したデータをスロット4に元に戻しました");
+    }
+
+    #[test]
+    fn test_restore_expired_entry_returns_error() {
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_restore_from_trash()
+            .returning(|_| Err(StorageError::NotInTrash));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        let error = service
+            .restore_from_trash(plantuml_editor_core::DocumentId::new())
+            .unwrap_err();
+        let process_result = storage_error_to_result(&error, None);
+        assert!(matches!(process_result.code, ErrorCode::StorageReadError { .. }));
+        assert!(process_result.message().contains("ゴミ箱に見つかりません"));
+    }
+
+    // ========================================
+    // スロット並べ替えのテスト
+    // ========================================
+
+    #[test]
+    fn test_move_slot_returns_move_success() {
+        let mut mock_backend = MockStorageBackend::new();
+        mock_backend
+            .expect_move_slot()
+            .with(mockall::predicate::eq(5), mockall::predicate::eq(1))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        assert!(service.move_slot(5, 1).is_ok());
+
+        let process_result = storage_success_result(
+            ErrorCode::MoveSuccess { from_slot: 5, to_slot: 1 },
+            1
+        );
+        assert_eq!(process_result.level, StatusLevel::Info);
+        assert_eq!(process_result.message(), "スロット5をスロット1に移動しました");
+    }
+
     // ========================================
     // on_delete 相当のテスト
     // 削除処理のロジックをモックを使用して検証
@@ -793,7 +2564,7 @@ mod storage_tests {
             .expect_delete_slot()
             .with(mockall::predicate::eq(3))
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_| Ok(true));
 
         let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
         let result = service.delete_slot(3);
@@ -870,7 +2641,7 @@ mod storage_tests {
             .expect_delete_slot()
             .with(mockall::predicate::eq(1))
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_| Ok(true));
 
         let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
         
@@ -921,8 +2692,16 @@ mod callback_integration_tests {
         impl StorageBackend for CallbackStorageBackend {
             fn save_to_slot(&self, slot_number: usize, text: &str) -> Result<(), StorageError>;
             fn load_from_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
+            fn load_raw_slot(&self, slot_number: usize) -> Result<Option<String>, StorageError>;
             fn list_slots(&self) -> Vec<plantuml_editor_storageservice::SlotInfo>;
-            fn delete_slot(&self, slot_number: usize) -> Result<(), StorageError>;
+            fn delete_slot(&self, slot_number: usize) -> Result<bool, StorageError>;
+            fn set_pinned(&self, slot_number: usize, pinned: bool) -> Result<(), StorageError>;
+            fn copy_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+            fn move_slot(&self, from: usize, to: usize) -> Result<(), StorageError>;
+            fn list_trash(&self) -> Vec<plantuml_editor_storageservice::TrashInfo>;
+            fn restore_from_trash(&self, document_id: plantuml_editor_core::DocumentId) -> Result<usize, StorageError>;
+            fn purge_trash(&self, document_id: Option<plantuml_editor_core::DocumentId>) -> Result<(), StorageError>;
+            fn free_space(&self, wanted: usize) -> Option<usize>;
         }
     }
 
@@ -1024,6 +2803,55 @@ mod callback_integration_tests {
         assert!(message.contains("上限") || message.contains("文字数"));
     }
 
+    #[test]
+    fn test_on_save_callback_preflight_with_insufficient_space() {
+        // 空き容量が足りないときは書き込む前に警告し、保存は呼ばれないことを確認
+        let mut mock_backend = MockCallbackStorageBackend::new();
+        mock_backend
+            .expect_load_raw_slot()
+            .returning(|_| Ok(None));
+        mock_backend
+            .expect_free_space()
+            .times(1)
+            .returning(|_| Some(100));
+        mock_backend
+            .expect_save_to_slot()
+            .times(0);
+
+        let service = Rc::new(plantuml_editor_storageservice::StorageService::new(mock_backend));
+
+        let slot = 2_usize;
+        let plantuml_text = "@startuml\nA -> B\n@enduml";
+        let saved = service
+            .check_capacity(slot, plantuml_text)
+            .and_then(|()| service.save_to_slot(slot, plantuml_text));
+        let error = saved.unwrap_err();
+        assert!(matches!(error, StorageError::InsufficientSpace { available: 100, .. }));
+
+        let result = storage_error_to_result(&error, Some(slot as u8));
+        assert!(matches!(result.code, ErrorCode::StorageWriteError { .. }));
+        let message = result.message();
+        assert!(message.contains("空き容量が足りません"));
+        assert!(message.contains("ゴミ箱"));
+    }
+
+    #[test]
+    fn test_check_capacity_counts_replaced_slot_as_free() {
+        // 上書きするスロットの既存データ分は空きとして扱う
+        let raw = "x".repeat(2_000);
+        let mut mock_backend = MockCallbackStorageBackend::new();
+        mock_backend
+            .expect_load_raw_slot()
+            .returning(move |_| Ok(Some(raw.clone())));
+        mock_backend
+            .expect_free_space()
+            .withf(|&wanted| wanted == 0)
+            .returning(|_| Some(0));
+
+        let service = plantuml_editor_storageservice::StorageService::new(mock_backend);
+        assert!(service.check_capacity(1, "@startuml\nA -> B\n@enduml").is_ok());
+    }
+
     #[test]
     fn test_on_save_callback_with_slots_full() {
         // SlotsFull エラーのハンドリングをテスト
@@ -1257,7 +3085,7 @@ mod callback_integration_tests {
             .returning(|| {
                 let mut m = MockCallbackStorageBackend::new();
                 m.expect_delete_slot()
-                    .returning(|_| Ok(()));
+                    .returning(|_| Ok(true));
                 m.expect_eq()
                     .returning(|_| true);
                 m
@@ -1271,7 +3099,7 @@ mod callback_integration_tests {
             .expect_delete_slot()
             .with(mockall::predicate::eq(7))
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_| Ok(true));
 
         let service = Rc::new(plantuml_editor_storageservice::StorageService::new(mock_backend));
         
@@ -1427,7 +3255,7 @@ mod callback_integration_tests {
             .returning(|| {
                 let mut m = MockCallbackStorageBackend::new();
                 m.expect_delete_slot()
-                    .returning(|_| Ok(()));
+                    .returning(|_| Ok(true));
                 m.expect_eq()
                     .returning(|_| true);
                 m
@@ -1439,7 +3267,7 @@ mod callback_integration_tests {
             .expect_delete_slot()
             .with(mockall::predicate::eq(4))
             .times(1)
-            .returning(|_| Ok(()));
+            .returning(|_| Ok(true));
         
         let delete_service = Rc::new(plantuml_editor_storageservice::StorageService::new(delete_backend));
         
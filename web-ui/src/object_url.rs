@@ -0,0 +1,128 @@
+// Object URLs for preview images
+//
+// A data URL copies the whole image into the `src` attribute, percent-encoded,
+// which for a multi-megabyte SVG means several copies of it in memory and in
+// the DOM. An object URL points at a Blob instead and stays a short
+// `blob:` string. The URL is revoked when the last `ObjectUrl` handle is
+// dropped: the preview state and the conversion cache share one through an
+// `Rc`, so an image is released once it is neither shown nor cached.
+
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+
+/// A `blob:` URL, revoked when dropped
+#[derive(Debug, PartialEq, Eq)]
+pub struct ObjectUrl {
+    url: String,
+    /// Size of the Blob in bytes
+    size: usize,
+    /// Type of the Blob, without parameters
+    mime: &'static str,
+}
+
+/// Shared handle to a preview image
+pub type PreviewImage = Rc<ObjectUrl>;
+
+impl ObjectUrl {
+    /// URL of a Blob holding `svg`
+    pub fn from_svg(svg: &str) -> Result<Self, JsValue> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(svg));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("image/svg+xml;charset=utf-8");
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        Ok(Self {
+            url,
+            size: svg.len(),
+            mime: "image/svg+xml",
+        })
+    }
+
+    /// URL of a Blob holding `png`, for previews shown as PNG
+    pub fn from_png(png: &[u8]) -> Result<Self, JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(png));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("image/png");
+        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        Ok(Self {
+            url,
+            size: png.len(),
+            mime: "image/png",
+        })
+    }
+
+    /// URL of a Blob holding `text`, for the text preview
+    pub fn from_text(text: &str) -> Result<Self, JsValue> {
+        let parts = js_sys::Array::of1(&JsValue::from_str(text));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/plain;charset=utf-8");
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+        Ok(Self {
+            url,
+            size: text.len(),
+            mime: "text/plain",
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.url
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn mime(&self) -> &'static str {
+        self.mime
+    }
+}
+
+impl Drop for ObjectUrl {
+    fn drop(&mut self) {
+        let _ = web_sys::Url::revoke_object_url(&self.url);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// SVG of about `bytes` bytes, with text that needs percent-encoding
+    fn large_svg(bytes: usize) -> String {
+        let line = "<text x=\"10\" y=\"20\">参加者 A -> B : メッセージ</text>\n";
+        let mut svg = String::from("<svg xmlns=\"http://www.w3.org/2000/svg\">\n");
+        while svg.len() < bytes {
+            svg.push_str(line);
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// Memory regression guard: the `src` attribute no longer grows with the image
+    #[wasm_bindgen_test]
+    fn test_object_url_size_does_not_grow_with_image() {
+        for bytes in [10_000, 1_000_000, 4_000_000] {
+            let svg = large_svg(bytes);
+            let data_url = crate::svg_data_url(&svg);
+            let object_url = ObjectUrl::from_svg(&svg).expect("should create object URL");
+
+            assert!(data_url.len() > svg.len() * 2, "percent-encoding expands the SVG");
+            assert!(object_url.as_str().starts_with("blob:"));
+            assert!(object_url.as_str().len() < 200, "object URLs are short");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_shared_url_lives_until_last_handle() {
+        let image: PreviewImage = Rc::new(ObjectUrl::from_svg("<svg/>").unwrap());
+        let cached = image.clone();
+        drop(image);
+        assert_eq!(Rc::strong_count(&cached), 1);
+        assert!(cached.as_str().starts_with("blob:"));
+    }
+}
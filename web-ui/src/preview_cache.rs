@@ -0,0 +1,271 @@
+// Conversion cache for the live preview
+//
+// Rendered pages are kept by (page source hash, page, format), so flipping
+// back to a page, editing a different page or undoing an edit shows the image
+// without another server round trip. PreviewTracker goes one step further
+// for the page on screen: when the debounced text renders to what is already
+// shown (e.g. a character typed and deleted), nothing is requested at all.
+//
+// The cache is bounded by entry count and by the total size of its images.
+// An evicted image is dropped, which revokes its object URL unless the
+// preview still shows it (see `object_url`).
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use plantuml_editor_core::ImageFormat;
+
+/// Rendered images kept before the oldest is dropped
+pub const CACHE_CAPACITY: usize = 32;
+
+/// Total image bytes kept before the oldest images are dropped
+pub const CACHE_BUDGET_BYTES: usize = 64 * 1024 * 1024;
+
+/// Cache key: hash of one page's source plus its position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub hash: u64,
+    pub page: usize,
+    pub format: ImageFormat,
+}
+
+impl CacheKey {
+    pub fn new(page_source: &str, page: usize, format: ImageFormat) -> Self {
+        let mut hasher = DefaultHasher::new();
+        page_source.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            page,
+            format,
+        }
+    }
+
+    /// Key for the page rendered with `font` (the font render option)
+    pub fn with_font(self, font: Option<&str>) -> Self {
+        let Some(font) = font else {
+            return self;
+        };
+        let mut hasher = DefaultHasher::new();
+        self.hash.hash(&mut hasher);
+        font.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            ..self
+        }
+    }
+}
+
+/// Least-recently-used cache of preview images (see `object_url`)
+#[derive(Debug)]
+pub struct ConversionCache<T> {
+    /// Images with their size in bytes
+    entries: HashMap<CacheKey, (T, usize)>,
+    /// Keys from least to most recently used
+    order: VecDeque<CacheKey>,
+    total_bytes: usize,
+    budget_bytes: usize,
+}
+
+impl<T> Default for ConversionCache<T> {
+    fn default() -> Self {
+        Self::with_budget(CACHE_BUDGET_BYTES)
+    }
+}
+
+impl<T> ConversionCache<T> {
+    pub fn with_budget(budget_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            total_bytes: 0,
+            budget_bytes,
+        }
+    }
+}
+
+impl<T: Clone> ConversionCache<T> {
+    pub fn get(&mut self, key: &CacheKey) -> Option<T> {
+        let image = self.entries.get(key)?.0.clone();
+        self.touch(*key);
+        Some(image)
+    }
+
+    /// Keep `image` of `bytes` bytes; the least recently used entries are
+    /// dropped while the cache is over its capacity or budget
+    ///
+    /// The newest image is kept even when it alone exceeds the budget.
+    pub fn insert(&mut self, key: CacheKey, image: T, bytes: usize) {
+        if let Some((_, replaced)) = self.entries.insert(key, (image, bytes)) {
+            self.total_bytes -= replaced;
+        }
+        self.total_bytes += bytes;
+        self.touch(key);
+        while self.order.len() > CACHE_CAPACITY
+            || (self.total_bytes > self.budget_bytes && self.order.len() > 1)
+        {
+            if let Some(oldest) = self.order.pop_front() {
+                if let Some((_, size)) = self.entries.remove(&oldest) {
+                    self.total_bytes -= size;
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Size of the cached images in bytes
+    pub fn total_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: CacheKey) {
+        self.order.retain(|existing| *existing != key);
+        self.order.push_back(key);
+    }
+}
+
+/// Which preview is on screen and which request it is waiting for
+#[derive(Debug, Default)]
+pub struct PreviewTracker {
+    /// Last successfully rendered page, shown with its message
+    shown: Option<CacheKey>,
+    /// Latest page asked for; older responses are stale
+    requested: Option<CacheKey>,
+}
+
+impl PreviewTracker {
+    /// Ask for `key`; false when it is already shown and nothing needs to change
+    pub fn request(&mut self, key: CacheKey) -> bool {
+        self.requested = Some(key);
+        self.shown != Some(key)
+    }
+
+    /// Whether a response for `key` should still be shown
+    pub fn is_current(&self, key: CacheKey) -> bool {
+        self.requested == Some(key)
+    }
+
+    /// `key` is now on screen
+    pub fn rendered(&mut self, key: CacheKey) {
+        self.shown = Some(key);
+    }
+
+    /// The preview or its message changed without a successful render, so the
+    /// next request is sent even for the same text
+    pub fn failed(&mut self) {
+        self.shown = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_distinguishes_page_and_format() {
+        let source = "@startuml\nA -> B\nnewpage\nB -> C\n@enduml";
+        let key = CacheKey::new(source, 0, ImageFormat::Svg);
+        assert_eq!(key, CacheKey::new(source, 0, ImageFormat::Svg));
+        assert_ne!(key, CacheKey::new(source, 1, ImageFormat::Svg));
+        assert_ne!(key, CacheKey::new(source, 0, ImageFormat::Png));
+        assert_ne!(key, CacheKey::new("@startuml\n@enduml", 0, ImageFormat::Svg));
+    }
+
+    #[test]
+    fn test_key_distinguishes_font() {
+        let key = CacheKey::new("@startuml\nA -> B\n@enduml", 0, ImageFormat::Svg);
+        assert_eq!(key.with_font(None), key);
+        assert_ne!(key.with_font(Some("IPAexGothic")), key);
+        assert_ne!(
+            key.with_font(Some("IPAexGothic")),
+            key.with_font(Some("Noto Sans CJK JP"))
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = ConversionCache::<String>::default();
+        let key = |page| CacheKey::new("source", page, ImageFormat::Svg);
+
+        for page in 0..CACHE_CAPACITY {
+            cache.insert(key(page), format!("page{}", page), 10);
+        }
+        // Reading page 0 keeps it; page 1 becomes the oldest
+        assert_eq!(cache.get(&key(0)).as_deref(), Some("page0"));
+        cache.insert(key(CACHE_CAPACITY), "new".to_string(), 10);
+
+        assert_eq!(cache.len(), CACHE_CAPACITY);
+        assert!(cache.get(&key(0)).is_some());
+        assert!(cache.get(&key(1)).is_none());
+    }
+
+    #[test]
+    fn test_evicts_over_byte_budget() {
+        let mut cache = ConversionCache::with_budget(100);
+        let key = |page| CacheKey::new("source", page, ImageFormat::Svg);
+
+        cache.insert(key(0), "a", 40);
+        cache.insert(key(1), "b", 40);
+        cache.insert(key(0), "a2", 50);
+        assert_eq!(cache.total_bytes(), 90);
+
+        // Page 1 is now the least recently used
+        cache.insert(key(2), "c", 30);
+        assert!(cache.get(&key(1)).is_none());
+        assert_eq!(cache.get(&key(0)), Some("a2"));
+        assert_eq!(cache.total_bytes(), 80);
+
+        // Larger than the budget: only the newest image is kept
+        cache.insert(key(3), "huge", 500);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.total_bytes(), 500);
+    }
+
+    #[test]
+    fn test_eviction_releases_unshared_images() {
+        use std::rc::Rc;
+
+        let mut cache = ConversionCache::default();
+        let key = |page| CacheKey::new("source", page, ImageFormat::Svg);
+        let shown = Rc::new("page0".to_string());
+        cache.insert(key(0), shown.clone(), 10);
+        let evicted = Rc::downgrade(&cache.get(&key(0)).unwrap());
+        for page in 1..=CACHE_CAPACITY {
+            cache.insert(key(page), Rc::new(format!("page{}", page)), 10);
+        }
+
+        // Still on screen, so only the cache's handle is gone
+        assert!(cache.get(&key(0)).is_none());
+        assert_eq!(Rc::strong_count(&shown), 1);
+        drop(shown);
+        assert!(evicted.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_tracker_skips_shown_preview() {
+        let mut tracker = PreviewTracker::default();
+        let a = CacheKey::new("@startuml\nA -> B\n@enduml", 0, ImageFormat::Svg);
+        let b = CacheKey::new("@startuml\nA -> Bx\n@enduml", 0, ImageFormat::Svg);
+
+        assert!(tracker.request(a));
+        tracker.rendered(a);
+        assert!(!tracker.request(a));
+
+        // Typed a character, then deleted it before the response came back
+        assert!(tracker.request(b));
+        assert!(!tracker.request(a));
+        assert!(!tracker.is_current(b));
+
+        // After a failure the same text is rendered again
+        tracker.failed();
+        assert!(tracker.request(a));
+        assert!(tracker.is_current(a));
+    }
+}
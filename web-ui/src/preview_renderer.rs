@@ -0,0 +1,144 @@
+// Preview renderers
+//
+// The preview can show a diagram in several ways, chosen with tabs above it:
+// as an `<img>` (zoomable, the default), as SVG placed inline so its links can
+// be clicked, or as PlantUML's ASCII art. Each renderer names the format it
+// needs from the server and how it is drawn; the Preview component only
+// dispatches on it, so another renderer (a canvas, PDF.js) is a new variant
+// here rather than a rewrite of the component.
+//
+// Inline SVG and text are read back from the preview's object URL. The SVG was
+// cut down to allowlisted elements, attributes and links before its Blob was
+// created (see `svg_worker` and core::svg), so it is safe to place in the
+// document, even when it shows someone else's diagram.
+
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use yew::prelude::*;
+
+use crate::object_url::PreviewImage;
+use plantuml_editor_core::ImageFormat;
+
+/// How the preview shows the diagram
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewRenderer {
+    /// `<img>` of the SVG or PNG preview
+    #[default]
+    Image,
+    /// SVG in the document, with clickable links
+    InteractiveSvg,
+    /// ASCII art from PlantUML's text output
+    Text,
+}
+
+impl PreviewRenderer {
+    /// Renderers in tab order
+    pub const ALL: [PreviewRenderer; 3] = [Self::Image, Self::InteractiveSvg, Self::Text];
+
+    /// Tab label
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Image => "画像",
+            Self::InteractiveSvg => "SVG (リンク可)",
+            Self::Text => "テキスト",
+        }
+    }
+
+    /// Format to request from the server, given the format chosen for images
+    pub fn format(self, image_format: ImageFormat) -> ImageFormat {
+        match self {
+            Self::Image => image_format,
+            Self::InteractiveSvg => ImageFormat::Svg,
+            Self::Text => ImageFormat::Txt,
+        }
+    }
+
+    /// Whether the zoom controls apply
+    pub fn zoomable(self) -> bool {
+        self == Self::Image
+    }
+}
+
+/// Text held by a Blob behind an object URL
+async fn fetch_text(url: &str) -> Option<String> {
+    let window = web_sys::window()?;
+    let response = JsFuture::from(window.fetch_with_str(url)).await.ok()?;
+    let response: web_sys::Response = response.dyn_into().ok()?;
+    let text = JsFuture::from(response.text().ok()?).await.ok()?;
+    text.as_string()
+}
+
+#[derive(Properties, PartialEq)]
+pub struct RenderedTextProps {
+    pub data: PreviewImage,
+    /// InteractiveSvg or Text
+    pub renderer: PreviewRenderer,
+    #[prop_or_default]
+    pub class: Classes,
+}
+
+/// Preview drawn from the text of the image: inline SVG or ASCII art
+#[function_component(RenderedText)]
+pub fn rendered_text(props: &RenderedTextProps) -> Html {
+    // The previous text stays until the new one is read, like the `<img>`
+    let text = use_state(|| None::<Rc<str>>);
+    let requested = use_mut_ref(String::new);
+    {
+        let text = text.clone();
+        let requested = requested.clone();
+        use_effect_with(props.data.clone(), move |data| {
+            // Held until read, so the URL is not revoked meanwhile
+            let data = data.clone();
+            *requested.borrow_mut() = data.as_str().to_string();
+            if data.mime() != "image/png" {
+                spawn_local(async move {
+                    let read = fetch_text(data.as_str()).await;
+                    // A newer image may have been requested while reading
+                    if *requested.borrow() == data.as_str() {
+                        text.set(read.map(Rc::from));
+                    }
+                });
+            }
+            || ()
+        });
+    }
+
+    // The server may answer a large SVG with a PNG (SvgDowngraded)
+    if props.data.mime() == "image/png" {
+        return html! {
+            <img
+                class={classes!("diagram-image", props.class.clone())}
+                src={props.data.as_str().to_string()}
+                alt="PlantUML Diagram"
+            />
+        };
+    }
+    let Some(text) = (*text).clone() else {
+        return html! {};
+    };
+    match props.renderer {
+        PreviewRenderer::Text => html! {
+            <pre class={classes!("text-preview", props.class.clone())}>{ &*text }</pre>
+        },
+        _ => html! {
+            <div class={classes!("inline-svg", props.class.clone())}>
+                { Html::from_html_unchecked(AttrValue::from(text)) }
+            </div>
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renderer_formats() {
+        assert_eq!(PreviewRenderer::Image.format(ImageFormat::Png), ImageFormat::Png);
+        assert_eq!(PreviewRenderer::Image.format(ImageFormat::Svg), ImageFormat::Svg);
+        assert_eq!(PreviewRenderer::InteractiveSvg.format(ImageFormat::Png), ImageFormat::Svg);
+        assert_eq!(PreviewRenderer::Text.format(ImageFormat::Svg), ImageFormat::Txt);
+        assert!(PreviewRenderer::ALL.iter().filter(|r| r.zoomable()).eq([&PreviewRenderer::Image]));
+    }
+}
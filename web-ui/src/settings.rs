@@ -0,0 +1,255 @@
+// Editor settings that follow the user between browsers
+//
+// The preferences in `UserSettings` (preview format, font, spellcheck,
+// indentation, render pause...) are kept in LocalStorage and, while the user
+// is logged in (see `auth`), on the server too, so another browser picks them
+// up after logging in. Every change is stamped with the time it was made and
+// the later copy wins (last write wins); the server applies the same rule to
+// writes from two browsers at once.
+//
+// Without a login, or when the server cannot be reached, only the local copy
+// is used. Settings this version does not know yet take their defaults.
+
+use futures::future::LocalBoxFuture;
+use plantuml_editor_core::indent::IndentUnit;
+use plantuml_editor_core::{ErrorCode, SettingsSnapshot, UserSettings};
+
+use crate::{auth, errors, host};
+
+/// LocalStorage key of the settings
+const SETTINGS_KEY: &str = "plantuml_settings";
+
+/// Render pauses offered in the editor settings (ms)
+pub const RENDER_PAUSE_CHOICES: [u32; 4] = [150, 300, 600, 1000];
+
+/// Where settings are kept
+pub trait SettingsBackend {
+    /// Settings saved so far (None before any were saved)
+    fn load(&self) -> LocalBoxFuture<'_, Result<Option<SettingsSnapshot>, ErrorCode>>;
+
+    /// Save `snapshot`; returns the settings now in effect, which are the
+    /// stored ones when they were changed later
+    fn save(&self, snapshot: SettingsSnapshot) -> LocalBoxFuture<'_, Result<SettingsSnapshot, ErrorCode>>;
+}
+
+/// Settings in this browser's LocalStorage
+pub struct LocalSettings;
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+impl SettingsBackend for LocalSettings {
+    fn load(&self) -> LocalBoxFuture<'_, Result<Option<SettingsSnapshot>, ErrorCode>> {
+        let snapshot = local_storage()
+            .and_then(|storage| storage.get_item(SETTINGS_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok());
+        Box::pin(async move { Ok(snapshot) })
+    }
+
+    fn save(&self, snapshot: SettingsSnapshot) -> LocalBoxFuture<'_, Result<SettingsSnapshot, ErrorCode>> {
+        let saved = match (local_storage(), serde_json::to_string(&snapshot)) {
+            (Some(storage), Ok(json)) => storage
+                .set_item(SETTINGS_KEY, &json)
+                .map(|_| snapshot)
+                .map_err(|e| ErrorCode::StorageWriteError {
+                    reason: e.as_string().unwrap_or_else(|| "setItem".to_string()),
+                }),
+            _ => Err(ErrorCode::StorageWriteError {
+                reason: "LocalStorage を利用できません".to_string(),
+            }),
+        };
+        Box::pin(async move { saved })
+    }
+}
+
+/// Settings of the logged-in user on the server (GET/PUT /api/v1/settings)
+pub struct ServerSettings;
+
+impl SettingsBackend for ServerSettings {
+    fn load(&self) -> LocalBoxFuture<'_, Result<Option<SettingsSnapshot>, ErrorCode>> {
+        Box::pin(async {
+            host::api_client()
+                .settings()
+                .await
+                .map_err(|e| errors::api_error_to_code(e, "/api/v1/settings"))
+        })
+    }
+
+    fn save(&self, snapshot: SettingsSnapshot) -> LocalBoxFuture<'_, Result<SettingsSnapshot, ErrorCode>> {
+        Box::pin(async move {
+            host::api_client()
+                .save_settings(&snapshot)
+                .await
+                .map_err(|e| errors::api_error_to_code(e, "/api/v1/settings"))
+        })
+    }
+}
+
+/// The server backend while the user is logged in, otherwise None
+pub fn server() -> Option<&'static dyn SettingsBackend> {
+    if auth::access_token().is_some() {
+        Some(&ServerSettings)
+    } else {
+        None
+    }
+}
+
+/// Bring the local copy and the server's (when given) up to date with each
+/// other; returns the settings to use (None when nothing was saved anywhere)
+///
+/// The server is skipped when it cannot be reached.
+pub async fn sync(
+    local: &dyn SettingsBackend,
+    server: Option<&dyn SettingsBackend>,
+) -> Option<SettingsSnapshot> {
+    let stored = local.load().await.ok().flatten();
+    let Some(server) = server else {
+        return stored;
+    };
+    let Ok(remote) = server.load().await else {
+        return stored;
+    };
+    let mut latest = match (stored.clone(), remote.clone()) {
+        (Some(stored), Some(remote)) => stored.newer(remote),
+        (stored, remote) => stored.or(remote)?,
+    };
+    if remote.as_ref() != Some(&latest) {
+        latest = server.save(latest.clone()).await.unwrap_or(latest);
+    }
+    if stored.as_ref() != Some(&latest) {
+        let _ = local.save(latest.clone()).await;
+    }
+    Some(latest)
+}
+
+/// Save changed settings locally and on the server (when given); returns the
+/// settings in effect, which are the server's when another browser changed
+/// them later
+pub async fn save(
+    local: &dyn SettingsBackend,
+    server: Option<&dyn SettingsBackend>,
+    snapshot: SettingsSnapshot,
+) -> SettingsSnapshot {
+    let mut current = snapshot;
+    if let Some(server) = server {
+        if let Ok(saved) = server.save(current.clone()).await {
+            current = saved;
+        }
+    }
+    let _ = local.save(current.clone()).await;
+    current
+}
+
+/// Indentation the editor uses for `settings`
+pub fn indent_unit(settings: &UserSettings) -> IndentUnit {
+    match settings.indent_width {
+        0 => IndentUnit::Tab,
+        width => IndentUnit::Spaces(width),
+    }
+}
+
+/// `indent_width` of the settings for `unit`
+pub fn indent_width(unit: IndentUnit) -> usize {
+    match unit {
+        IndentUnit::Spaces(width) => width,
+        IndentUnit::Tab => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::cell::RefCell;
+
+    /// Backend keeping the settings in memory, failing when `offline`
+    #[derive(Default)]
+    struct MemorySettings {
+        stored: RefCell<Option<SettingsSnapshot>>,
+        offline: bool,
+    }
+
+    impl MemorySettings {
+        fn with(snapshot: SettingsSnapshot) -> Self {
+            Self {
+                stored: RefCell::new(Some(snapshot)),
+                offline: false,
+            }
+        }
+    }
+
+    impl SettingsBackend for MemorySettings {
+        fn load(&self) -> LocalBoxFuture<'_, Result<Option<SettingsSnapshot>, ErrorCode>> {
+            let result = if self.offline {
+                Err(ErrorCode::NetworkError { endpoint: "/api/v1/settings".to_string() })
+            } else {
+                Ok(self.stored.borrow().clone())
+            };
+            Box::pin(async move { result })
+        }
+
+        fn save(&self, snapshot: SettingsSnapshot) -> LocalBoxFuture<'_, Result<SettingsSnapshot, ErrorCode>> {
+            let result = if self.offline {
+                Err(ErrorCode::NetworkError { endpoint: "/api/v1/settings".to_string() })
+            } else {
+                let current = match self.stored.borrow_mut().take() {
+                    Some(stored) => stored.newer(snapshot),
+                    None => snapshot,
+                };
+                *self.stored.borrow_mut() = Some(current.clone());
+                Ok(current)
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    fn snapshot(indent_width: usize, updated_at: i64) -> SettingsSnapshot {
+        SettingsSnapshot {
+            settings: UserSettings { indent_width, ..UserSettings::default() },
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn test_sync_keeps_the_latest_copy() {
+        // The server's copy is newer: it replaces the local one
+        let local = MemorySettings::with(snapshot(2, 5));
+        let server = MemorySettings::with(snapshot(4, 9));
+        assert_eq!(block_on(sync(&local, Some(&server))), Some(snapshot(4, 9)));
+        assert_eq!(*local.stored.borrow(), Some(snapshot(4, 9)));
+
+        // Settings changed before logging in are uploaded
+        let local = MemorySettings::with(snapshot(0, 12));
+        assert_eq!(block_on(sync(&local, Some(&server))), Some(snapshot(0, 12)));
+        assert_eq!(*server.stored.borrow(), Some(snapshot(0, 12)));
+
+        let empty = MemorySettings::default();
+        assert_eq!(block_on(sync(&empty, None)), None);
+    }
+
+    #[test]
+    fn test_local_fallback() {
+        let local = MemorySettings::with(snapshot(4, 5));
+        let server = MemorySettings {
+            offline: true,
+            ..MemorySettings::default()
+        };
+        assert_eq!(block_on(sync(&local, Some(&server))), Some(snapshot(4, 5)));
+        assert_eq!(block_on(save(&local, Some(&server), snapshot(0, 8))), snapshot(0, 8));
+        assert_eq!(*local.stored.borrow(), Some(snapshot(0, 8)));
+
+        // A later change from another browser wins over this one
+        let server = MemorySettings::with(snapshot(2, 20));
+        assert_eq!(block_on(save(&local, Some(&server), snapshot(4, 10))), snapshot(2, 20));
+        assert_eq!(*local.stored.borrow(), Some(snapshot(2, 20)));
+    }
+
+    #[test]
+    fn test_indent_width_round_trip() {
+        for unit in IndentUnit::CHOICES {
+            let settings = UserSettings { indent_width: indent_width(unit), ..UserSettings::default() };
+            assert_eq!(indent_unit(&settings), unit);
+        }
+    }
+}
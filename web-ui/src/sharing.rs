@@ -0,0 +1,114 @@
+// Diagram sharing through the page URL
+//
+// The source is deflate-encoded the way PlantUML servers encode it (see
+// `plantuml_encoding`) and put in the URL fragment ("#uml=<encoded>"), so a
+// link opens the same diagram without saving it anywhere. Browsers never send
+// the fragment to the server, so the source stays between the people who
+// share the link.
+
+use plantuml_editor_core::encoded_source::{decode_deflate, MAX_ENCODED_CHARS};
+use plantuml_editor_core::validate_plantuml_content;
+use plantuml_encoding::encode_plantuml_deflate;
+
+/// Fragment parameter holding the encoded source
+const FRAGMENT_KEY: &str = "uml";
+
+/// URL length above which some chat tools and mail clients cut links
+pub const LONG_URL_CHARS: usize = 8000;
+
+/// Fragment ("#uml=...") that opens `source`
+pub fn share_fragment(source: &str) -> Option<String> {
+    let encoded = encode_plantuml_deflate(source).ok()?;
+    Some(format!("#{}={}", FRAGMENT_KEY, encoded))
+}
+
+/// Source in a fragment made by `share_fragment`, if it is a valid diagram
+pub fn source_from_fragment(fragment: &str) -> Option<String> {
+    // Longer than any valid document could encode to
+    if fragment.len() > MAX_ENCODED_CHARS + FRAGMENT_KEY.len() + 2 {
+        return None;
+    }
+    let encoded = fragment.trim_start_matches('#').split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == FRAGMENT_KEY && !value.is_empty()).then_some(value)
+    })?;
+    // Inflated with a cap and held to the same limits as typed text, so a
+    // crafted link cannot overload the editor
+    let source = decode_deflate(encoded).ok()?;
+    validate_plantuml_content(&source).ok()?;
+    Some(source)
+}
+
+/// Link to this page that opens `source`
+pub fn share_url(source: &str) -> Option<String> {
+    let href = web_sys::window()?.location().href().ok()?;
+    link_on_page(&href, source)
+}
+
+/// `page` without its query and fragment, plus the fragment opening `source`
+///
+/// The query is dropped because "?doc=" or "?slot=" would open that document
+/// or slot for the recipient instead of the shared diagram.
+fn link_on_page(page: &str, source: &str) -> Option<String> {
+    let end = page.find(['?', '#']).unwrap_or(page.len());
+    Some(format!("{}{}", &page[..end], share_fragment(source)?))
+}
+
+/// Source of the share link the page was opened with
+///
+/// The fragment and query are removed from the address bar, so reloading
+/// after editing does not bring the shared version back.
+pub fn take_shared_source() -> Option<String> {
+    let window = web_sys::window()?;
+    let location = window.location();
+    let source = source_from_fragment(&location.hash().ok()?)?;
+    if let (Ok(path), Ok(history)) = (location.pathname(), window.history()) {
+        let _ = history.replace_state_with_url(&wasm_bindgen::JsValue::NULL, "", Some(&path));
+    }
+    Some(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fragment_round_trip() {
+        let source = "@startuml\nAlice -> Bob : こんにちは\n@enduml";
+        let fragment = share_fragment(source).unwrap();
+        assert!(fragment.starts_with("#uml="));
+        assert_eq!(source_from_fragment(&fragment).as_deref(), Some(source));
+        // Other parameters in the fragment are ignored
+        let fragment = format!("#page=2&{}", fragment.trim_start_matches('#'));
+        assert_eq!(source_from_fragment(&fragment).as_deref(), Some(source));
+    }
+
+    #[test]
+    fn test_link_drops_query() {
+        let source = "@startuml\nA -> B\n@enduml";
+        let fragment = share_fragment(source).unwrap();
+        for page in [
+            "https://example.com/editor/",
+            "https://example.com/editor/?doc=abc",
+            "https://example.com/editor/?slot=3#uml=old",
+        ] {
+            assert_eq!(
+                link_on_page(page, source),
+                Some(format!("https://example.com/editor/{}", fragment))
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_other_fragments() {
+        assert_eq!(source_from_fragment(""), None);
+        assert_eq!(source_from_fragment("#section-2"), None);
+        assert_eq!(source_from_fragment("#uml="), None);
+        // Decodes to an empty diagram
+        assert_eq!(source_from_fragment(&share_fragment("  ").unwrap()), None);
+        // Tiny links that inflate far past the limit, and over-long ones
+        let bomb = share_fragment(&"A".repeat(10 * 1024 * 1024)).unwrap();
+        assert_eq!(source_from_fragment(&bomb), None);
+        assert_eq!(source_from_fragment(&format!("#uml={}", "0".repeat(MAX_ENCODED_CHARS + 1))), None);
+    }
+}
@@ -0,0 +1,67 @@
+// Preview sanitization in a web worker
+//
+// Sanitizing a multi-megabyte SVG takes long enough to freeze typing, so the
+// browser app does it in a dedicated worker (the `svg_worker` binary of
+// browser-app, loaded from `WORKER_PATH`). The VS Code webview cannot load
+// extra scripts and sanitizes on the main thread, as does the browser app
+// when the worker does not answer. The result becomes an object URL (see
+// `object_url`), which is cheap to create from the sanitized text.
+
+use gloo_timers::future::TimeoutFuture;
+use gloo_worker::oneshot::{oneshot, OneshotBridge};
+use gloo_worker::{Registrable, Spawnable};
+use std::cell::RefCell;
+
+use plantuml_editor_core::svg::sanitize as sanitize_svg;
+
+use crate::host;
+
+/// Script trunk builds for the worker binary
+const WORKER_PATH: &str = "./svg_worker.js";
+
+/// How long to wait for the worker before sanitizing on the main thread
+const WORKER_TIMEOUT_MS: u32 = 10_000;
+
+/// SVG with only allowlisted elements and attributes (see core::svg)
+#[oneshot]
+pub async fn SvgSanitizer(svg: String) -> String {
+    sanitize_svg(&svg)
+}
+
+thread_local! {
+    /// Worker shared by all conversions; None once it is known not to work
+    static WORKER: RefCell<Option<OneshotBridge<SvgSanitizer>>> = RefCell::new(spawn());
+}
+
+fn spawn() -> Option<OneshotBridge<SvgSanitizer>> {
+    let window = web_sys::window()?;
+    let supported = js_sys::Reflect::has(&window, &"Worker".into()).unwrap_or(false);
+    (supported && !host::is_webview()).then(|| SvgSanitizer::spawner().spawn(WORKER_PATH))
+}
+
+/// Entry point of the worker binary
+pub fn register() {
+    SvgSanitizer::registrar().register();
+}
+
+/// Sanitize `svg` for the preview, off the main thread when possible
+pub async fn sanitize(svg: String) -> String {
+    // A fork per call, so conversions running at once get their own answers
+    let Some(mut bridge) = WORKER.with(|worker| worker.borrow().as_ref().map(|bridge| bridge.fork()))
+    else {
+        return sanitize_svg(&svg);
+    };
+    let sanitized = {
+        use futures::future::{select, Either};
+        let run = std::pin::pin!(bridge.run(svg.clone()));
+        match select(run, TimeoutFuture::new(WORKER_TIMEOUT_MS)).await {
+            Either::Left((sanitized, _)) => Some(sanitized),
+            Either::Right(_) => None,
+        }
+    };
+    sanitized.unwrap_or_else(|| {
+        // The script did not load or the worker hangs; stop using it
+        WORKER.with(|worker| worker.borrow_mut().take());
+        sanitize_svg(&svg)
+    })
+}
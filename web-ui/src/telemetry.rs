@@ -0,0 +1,269 @@
+// Opt-in usage telemetry
+//
+// Counts anonymous events so maintainers can see which features matter:
+// conversions, the kind of each error (the ErrorCode type, never its message
+// or the diagram) and uses of features such as export or import. There are
+// no IDs or timestamps, only counters.
+//
+// Nothing is recorded until the user picks a mode in the help menu:
+// - LocalOnly keeps the counters in LocalStorage and never sends them
+// - Send also posts them in batches to `telemetryUrl` from the page
+//   configuration (see `host`), at most once a minute
+// Changing the mode clears the counters, so what was kept locally is never
+// sent later.
+//
+// Recording is compiled in only with the `telemetry` feature; without it
+// `record` does nothing and the help menu shows no setting.
+
+use std::collections::BTreeMap;
+
+use plantuml_editor_core::ErrorCode;
+use serde::Serialize;
+
+/// Whether the build includes telemetry
+pub const ENABLED: bool = cfg!(feature = "telemetry");
+
+/// LocalStorage keys of the chosen mode and the counters not sent yet
+#[cfg(feature = "telemetry")]
+const MODE_KEY: &str = "plantuml_telemetry_mode";
+#[cfg(feature = "telemetry")]
+const COUNTS_KEY: &str = "plantuml_telemetry_counts";
+
+/// Delay between the first event of a batch and sending it (ms)
+#[cfg(feature = "telemetry")]
+const BATCH_DELAY_MS: u32 = 60_000;
+
+/// What the user allowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TelemetryMode {
+    #[default]
+    Off,
+    LocalOnly,
+    Send,
+}
+
+impl TelemetryMode {
+    pub const ALL: [TelemetryMode; 3] = [Self::Off, Self::LocalOnly, Self::Send];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::LocalOnly => "local",
+            Self::Send => "send",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.as_str() == value)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "記録しない",
+            Self::LocalOnly => "この端末にのみ記録",
+            Self::Send => "記録して送信",
+        }
+    }
+}
+
+/// Something worth counting
+#[derive(Debug, Clone, Copy)]
+pub enum TelemetryEvent<'a> {
+    /// A diagram was converted for the preview
+    Conversion,
+    /// An operation failed or warned with this code
+    Error(&'a ErrorCode),
+    /// A feature was used (e.g. "export", "import")
+    Feature(&'static str),
+}
+
+impl TelemetryEvent<'_> {
+    /// Counter the event adds to, e.g. "error:ParseError"
+    pub fn key(&self) -> String {
+        match self {
+            Self::Conversion => "conversion".to_string(),
+            Self::Error(code) => {
+                let kind = serde_json::to_value(code)
+                    .ok()
+                    .and_then(|value| value.get("type")?.as_str().map(str::to_string))
+                    .unwrap_or_else(|| "unknown".to_string());
+                format!("error:{}", kind)
+            }
+            Self::Feature(name) => format!("feature:{}", name),
+        }
+    }
+}
+
+/// Counters by event key
+pub type Counts = BTreeMap<String, u64>;
+
+/// What Send mode posts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TelemetryBatch {
+    pub app_version: &'static str,
+    pub counts: Counts,
+}
+
+impl TelemetryBatch {
+    pub fn new(counts: Counts) -> Self {
+        Self {
+            app_version: env!("CARGO_PKG_VERSION"),
+            counts,
+        }
+    }
+}
+
+pub use imp::{counts, mode, record, set_mode};
+
+/// Without the feature: nothing is recorded and the mode stays Off
+#[cfg(not(feature = "telemetry"))]
+mod imp {
+    use super::*;
+
+    pub fn record(_event: TelemetryEvent) {}
+
+    pub fn mode() -> TelemetryMode {
+        TelemetryMode::Off
+    }
+
+    pub fn set_mode(_mode: TelemetryMode) {}
+
+    pub fn counts() -> Counts {
+        Counts::new()
+    }
+}
+
+#[cfg(feature = "telemetry")]
+mod imp {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::host;
+
+    thread_local! {
+        /// A batch is waiting to be sent
+        static SCHEDULED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    /// Mode the user chose
+    pub fn mode() -> TelemetryMode {
+        local_storage()
+            .and_then(|storage| storage.get_item(MODE_KEY).ok().flatten())
+            .and_then(|value| TelemetryMode::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Change the mode, dropping the counters kept so far
+    pub fn set_mode(mode: TelemetryMode) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.set_item(MODE_KEY, mode.as_str());
+            let _ = storage.remove_item(COUNTS_KEY);
+        }
+    }
+
+    /// Counters kept locally and not sent yet
+    pub fn counts() -> Counts {
+        local_storage()
+            .and_then(|storage| storage.get_item(COUNTS_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn store(counts: &Counts) {
+        if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(counts)) {
+            let _ = storage.set_item(COUNTS_KEY, &json);
+        }
+    }
+
+    /// Count `event` if the user allowed it
+    pub fn record(event: TelemetryEvent) {
+        let mode = mode();
+        if mode == TelemetryMode::Off {
+            return;
+        }
+        let mut counts = counts();
+        *counts.entry(event.key()).or_default() += 1;
+        store(&counts);
+        if mode == TelemetryMode::Send {
+            schedule_send();
+        }
+    }
+
+    fn schedule_send() {
+        let Some(url) = host::telemetry_url() else {
+            return;
+        };
+        if SCHEDULED.replace(true) {
+            return;
+        }
+        // The timer's closure is freed once it has run
+        gloo_timers::callback::Timeout::new(BATCH_DELAY_MS, move || {
+            SCHEDULED.set(false);
+            send(url);
+        })
+        .forget();
+    }
+
+    /// Post the counters, and remove what was sent once it is accepted
+    fn send(url: String) {
+        let sent = counts();
+        if sent.is_empty() || mode() != TelemetryMode::Send {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(&TelemetryBatch::new(sent.clone())) else {
+            return;
+        };
+        wasm_bindgen_futures::spawn_local(async move {
+            if !host::post_json(&url, &json).await {
+                // Kept for the next batch
+                return;
+            }
+            // Events recorded while sending stay for the next batch
+            let mut counts = counts();
+            for (key, count) in sent {
+                if let Some(current) = counts.get_mut(&key) {
+                    *current = current.saturating_sub(count);
+                }
+            }
+            counts.retain(|_, count| *count > 0);
+            store(&counts);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_keys() {
+        assert_eq!(TelemetryEvent::Conversion.key(), "conversion");
+        assert_eq!(
+            TelemetryEvent::Error(&ErrorCode::ParseError { line: Some(3) }).key(),
+            "error:ParseError"
+        );
+        assert_eq!(TelemetryEvent::Feature("export").key(), "feature:export");
+    }
+
+    #[test]
+    fn test_mode_round_trip() {
+        for mode in TelemetryMode::ALL {
+            assert_eq!(TelemetryMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(TelemetryMode::parse("always"), None);
+    }
+
+    #[test]
+    fn test_batch_has_only_counts() {
+        let counts = Counts::from([("conversion".to_string(), 4), ("feature:export".to_string(), 1)]);
+        let json = serde_json::to_value(TelemetryBatch::new(counts)).unwrap();
+        assert_eq!(json["counts"]["conversion"], 4);
+        let mut fields: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        fields.sort();
+        assert_eq!(fields, vec!["app_version", "counts"]);
+    }
+}
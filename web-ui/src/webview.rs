@@ -0,0 +1,240 @@
+// Bridge to a VS Code extension hosting the editor in a webview
+//
+// A webview cannot download files and should not reach the API server
+// itself, so the editor posts messages to its extension instead: API requests
+// (answered with a "response" message of the same ID), downloads, and source
+// changes for the extension to write back to its document. The extension
+// sends "load" to replace the editor source, e.g. when the file is opened.
+
+use futures::channel::oneshot;
+use plantuml_editor_api_client::{ApiError, Relay, RelayFuture, RelayRequest, RelayResponse};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use yew::Callback;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Handle VS Code gives a webview for messaging its extension
+    type VsCodeApi;
+
+    /// Only defined inside VS Code webviews, and only callable once
+    #[wasm_bindgen(catch, js_name = acquireVsCodeApi)]
+    fn acquire_vscode_api() -> Result<VsCodeApi, JsValue>;
+
+    #[wasm_bindgen(method, js_name = postMessage)]
+    fn post_message(this: &VsCodeApi, message: &JsValue);
+}
+
+/// Messages from the editor to the extension
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebviewMessage {
+    /// The editor is listening for messages
+    Ready,
+    /// API request for the extension to send
+    Request { id: u32, request: RelayRequest },
+    /// File to save (e.g., an exported image)
+    Download {
+        file_name: String,
+        mime: String,
+        data: Vec<u8>,
+    },
+    /// The editor source changed (also sent after "load")
+    Changed { text: String },
+}
+
+/// Messages from the extension to the editor
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostMessage {
+    /// Answer to a request, or why the server could not be reached
+    Response {
+        id: u32,
+        #[serde(default)]
+        response: Option<RelayResponse>,
+        #[serde(default)]
+        error: Option<String>,
+    },
+    /// Replace the editor source
+    Load { text: String },
+}
+
+/// Message from the extension, or None for anything else posted to the page
+pub fn parse_host_message(json: &str) -> Option<HostMessage> {
+    serde_json::from_str(json).ok()
+}
+
+type PendingResponse = oneshot::Sender<Result<RelayResponse, ApiError>>;
+
+thread_local! {
+    static VSCODE: RefCell<Option<VsCodeApi>> = const { RefCell::new(None) };
+    static NEXT_ID: Cell<u32> = const { Cell::new(0) };
+    static PENDING: RefCell<BTreeMap<u32, PendingResponse>> = const { RefCell::new(BTreeMap::new()) };
+    static ON_LOAD: RefCell<Option<Callback<String>>> = const { RefCell::new(None) };
+}
+
+/// Start listening to the extension; false outside a VS Code webview
+pub fn connect() -> bool {
+    let Ok(api) = acquire_vscode_api() else {
+        return false;
+    };
+    VSCODE.with(|vscode| *vscode.borrow_mut() = Some(api));
+
+    let listener = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(|event: web_sys::MessageEvent| {
+        let message = js_sys::JSON::stringify(&event.data())
+            .ok()
+            .and_then(|json| json.as_string())
+            .and_then(|json| parse_host_message(&json));
+        if let Some(message) = message {
+            receive(message);
+        }
+    });
+    if let Some(window) = web_sys::window() {
+        let _ = window.add_event_listener_with_callback("message", listener.as_ref().unchecked_ref());
+    }
+    // Listens for the lifetime of the page
+    listener.forget();
+
+    post(&WebviewMessage::Ready);
+    true
+}
+
+fn receive(message: HostMessage) {
+    match message {
+        HostMessage::Response { id, response, error } => {
+            let Some(sender) = PENDING.with(|pending| pending.borrow_mut().remove(&id)) else {
+                return;
+            };
+            let result = response.ok_or_else(|| {
+                ApiError::NetworkError(
+                    error.unwrap_or_else(|| "拡張機能からの応答がありません".to_string()),
+                )
+            });
+            // The request may have timed out already
+            let _ = sender.send(result);
+        }
+        HostMessage::Load { text } => {
+            if let Some(on_load) = ON_LOAD.with(|on_load| on_load.borrow().clone()) {
+                on_load.emit(text);
+            }
+        }
+    }
+}
+
+fn post(message: &WebviewMessage) {
+    let Ok(json) = serde_json::to_string(message) else {
+        return;
+    };
+    let Ok(value) = js_sys::JSON::parse(&json) else {
+        return;
+    };
+    VSCODE.with(|vscode| {
+        if let Some(api) = vscode.borrow().as_ref() {
+            api.post_message(&value);
+        }
+    });
+}
+
+/// Call `on_load` with the source of each "load" message
+pub fn set_load_handler(on_load: Callback<String>) {
+    ON_LOAD.with(|handler| *handler.borrow_mut() = Some(on_load));
+}
+
+/// Tell the extension the editor source changed
+pub fn notify_changed(text: &str) {
+    post(&WebviewMessage::Changed {
+        text: text.to_string(),
+    });
+}
+
+/// Ask the extension to save a file
+pub fn download(data: &[u8], mime: &str, file_name: &str) {
+    post(&WebviewMessage::Download {
+        file_name: file_name.to_string(),
+        mime: mime.to_string(),
+        data: data.to_vec(),
+    });
+}
+
+/// Relay for ApiClient that sends requests through the extension
+pub struct VsCodeRelay;
+
+impl Relay for VsCodeRelay {
+    fn send(&self, request: RelayRequest) -> RelayFuture {
+        let id = NEXT_ID.with(|next| {
+            let id = next.get();
+            next.set(id.wrapping_add(1));
+            id
+        });
+        let (sender, receiver) = oneshot::channel();
+        PENDING.with(|pending| pending.borrow_mut().insert(id, sender));
+        post(&WebviewMessage::Request { id, request });
+
+        Box::pin(async move {
+            receiver.await.unwrap_or_else(|_| {
+                Err(ApiError::NetworkError("拡張機能からの応答がありません".to_string()))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_webview_message_format() {
+        let request = WebviewMessage::Request {
+            id: 7,
+            request: RelayRequest {
+                method: "GET".to_string(),
+                url: "http://localhost:8080/api/v1/health".to_string(),
+                content_type: None,
+                content_encoding: None,
+                body: Vec::new(),
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "type": "request",
+                "id": 7,
+                "request": { "method": "GET", "url": "http://localhost:8080/api/v1/health" }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(WebviewMessage::Ready).unwrap(),
+            serde_json::json!({ "type": "ready" })
+        );
+    }
+
+    #[test]
+    fn test_parse_host_message() {
+        assert_eq!(
+            parse_host_message(r#"{"type":"load","text":"@startuml\n@enduml"}"#),
+            Some(HostMessage::Load {
+                text: "@startuml\n@enduml".to_string()
+            })
+        );
+        assert_eq!(
+            parse_host_message(r#"{"type":"response","id":3,"error":"ECONNREFUSED"}"#),
+            Some(HostMessage::Response {
+                id: 3,
+                response: None,
+                error: Some("ECONNREFUSED".to_string()),
+            })
+        );
+        let Some(HostMessage::Response { response, .. }) = parse_host_message(
+            r#"{"type":"response","id":4,"response":{"status":200,"body":[123,125]}}"#,
+        ) else {
+            panic!("expected a response");
+        };
+        assert_eq!(response.unwrap().body, b"{}");
+
+        // Messages from other sources are ignored
+        assert_eq!(parse_host_message(r#"{"type":"other"}"#), None);
+        assert_eq!(parse_host_message("null"), None);
+    }
+}